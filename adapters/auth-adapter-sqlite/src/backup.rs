@@ -0,0 +1,186 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Tenant backup: dumping a tenant's auth rows and loading them back.
+//!
+//! Rows travel as raw columns, so a dump loads into the same or a later schema.
+//! The tenant gets a fresh `tn_id` on load, and `api_keys` and `events`
+//! fresh surrogate keys — nothing references either.
+
+use serde_json::{Map, Value};
+use sqlx::{Column, Row, SqlitePool, TypeInfo, ValueRef, sqlite::SqliteRow};
+use std::collections::HashSet;
+
+use crate::utils::Db;
+use cloudillo_types::prelude::*;
+use cloudillo_types::types::TableDump;
+use cloudillo_types::utils::normalize_id_tag;
+
+/// Every table the backup carries, as `(table, surrogate key)`. `tenants` must
+/// stay first: it assigns the `tn_id` the rest are loaded under.
+///
+/// The tenant cascade in `tenant.rs`, plus the `tenants` row itself. `user_vfy`
+/// is left out: pending verification codes are not worth moving.
+const TENANT_TABLES: &[(&str, Option<&str>)] = &[
+	("tenants", Some("tn_id")),
+	("keys", None),
+	("certs", None),
+	("events", Some("ev_id")),
+	("webauthn", None),
+	("api_keys", Some("key_id")),
+];
+
+/// Dump every backed-up table of a tenant.
+pub(crate) async fn export(db: &SqlitePool, tn_id: TnId) -> ClResult<Vec<TableDump>> {
+	// One read transaction, so the tables agree with each other.
+	let mut tx = db.begin().await.db()?;
+	let mut tables = Vec::with_capacity(TENANT_TABLES.len());
+	for (table, _) in TENANT_TABLES {
+		let rows =
+			sqlx::query(sqlx::AssertSqlSafe(format!("SELECT * FROM {} WHERE tn_id = ?1", table)))
+				.bind(tn_id.0)
+				.fetch_all(&mut *tx)
+				.await
+				.db()?;
+		if *table == "tenants" && rows.is_empty() {
+			return Err(Error::NotFound);
+		}
+		let rows = rows.iter().map(|row| row_to_json(table, row)).collect::<ClResult<_>>()?;
+		tables.push(TableDump { table: (*table).into(), rows });
+	}
+	tx.commit().await.db()?;
+	Ok(tables)
+}
+
+/// Load a dump as a new tenant, in one transaction, returning its `tn_id`.
+pub(crate) async fn import(db: &SqlitePool, tables: &[TableDump]) -> ClResult<TnId> {
+	for dump in tables {
+		if !TENANT_TABLES.iter().any(|(t, _)| **t == *dump.table) {
+			return Err(Error::ValidationError(format!(
+				"backup table {} is not a tenant table",
+				dump.table
+			)));
+		}
+	}
+	let Some([tenant]) = tables.iter().find(|d| &*d.table == "tenants").map(|d| d.rows.as_slice())
+	else {
+		return Err(Error::ValidationError("backup must hold exactly one tenant".into()));
+	};
+	let id_tag = tenant
+		.get("id_tag")
+		.and_then(Value::as_str)
+		.ok_or_else(|| Error::ValidationError("backup tenant has no id_tag".into()))?;
+
+	let mut tx = db.begin().await.db()?;
+
+	let exists = sqlx::query("SELECT 1 FROM tenants WHERE id_tag = ?1")
+		.bind(normalize_id_tag(id_tag).as_ref())
+		.fetch_optional(&mut *tx)
+		.await
+		.db()?;
+	if exists.is_some() {
+		return Err(Error::Conflict(format!("tenant {} already exists", id_tag)));
+	}
+
+	let mut tn_id = None;
+	for (table, id) in TENANT_TABLES {
+		let Some(dump) = tables.iter().find(|d| *d.table == **table) else {
+			continue;
+		};
+		let columns = table_columns(&mut tx, table).await?;
+
+		for row in &dump.rows {
+			let mut names = Vec::with_capacity(row.len());
+			let mut values = Vec::with_capacity(row.len());
+			for (name, value) in row {
+				if !columns.contains(name.as_str()) {
+					return Err(Error::ValidationError(format!(
+						"backup column {}.{} does not exist",
+						table, name
+					)));
+				}
+				if *id == Some(name.as_str()) {
+					continue;
+				}
+				let value = match (name.as_str(), tn_id) {
+					("tn_id", Some(TnId(tn_id))) => Value::from(tn_id),
+					_ => value.clone(),
+				};
+				names.push(name.as_str());
+				values.push(value);
+			}
+
+			let mut query = sqlx::QueryBuilder::new(format!("INSERT INTO {} (", table));
+			query.push(names.join(", ")).push(") VALUES (");
+			let mut sep = query.separated(", ");
+			for value in &values {
+				match value {
+					Value::Null => sep.push_bind(None::<i64>),
+					Value::Bool(b) => sep.push_bind(i64::from(*b)),
+					Value::Number(n) if n.is_i64() => sep.push_bind(n.as_i64()),
+					Value::Number(n) => sep.push_bind(n.as_f64()),
+					Value::String(s) => sep.push_bind(s.as_str()),
+					Value::Array(_) | Value::Object(_) => {
+						return Err(Error::ValidationError(format!(
+							"backup row in {} has a non-scalar column",
+							table
+						)));
+					}
+				};
+			}
+			query.push(")");
+
+			if *table == "tenants" {
+				query.push(" RETURNING tn_id");
+				let row = query.build().fetch_one(&mut *tx).await.db()?;
+				tn_id = Some(TnId(row.try_get("tn_id").db()?));
+			} else {
+				query.build().execute(&mut *tx).await.db()?;
+			}
+		}
+	}
+
+	tx.commit().await.db()?;
+	tn_id.ok_or(Error::DbError)
+}
+
+async fn table_columns(
+	tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+	table: &str,
+) -> ClResult<HashSet<String>> {
+	let rows = sqlx::query(sqlx::AssertSqlSafe(format!("PRAGMA table_info({})", table)))
+		.fetch_all(&mut **tx)
+		.await
+		.db()?;
+	rows.iter().map(|row| row.try_get::<String, _>("name").db()).collect()
+}
+
+/// A row as `column → value`, by the stored value's own type rather than the
+/// declared one.
+fn row_to_json(table: &str, row: &SqliteRow) -> ClResult<Map<String, Value>> {
+	let mut map = Map::with_capacity(row.len());
+	for (i, column) in row.columns().iter().enumerate() {
+		let raw = row.try_get_raw(i).db()?;
+		let value = if raw.is_null() {
+			Value::Null
+		} else {
+			match raw.type_info().name() {
+				"INTEGER" => Value::from(row.try_get::<i64, _>(i).db()?),
+				"REAL" => Value::from(row.try_get::<f64, _>(i).db()?),
+				"TEXT" => Value::from(row.try_get::<String, _>(i).db()?),
+				other => {
+					return Err(Error::Internal(format!(
+						"cannot back up {} column {}.{}",
+						other,
+						table,
+						column.name()
+					)));
+				}
+			}
+		};
+		map.insert(column.name().to_string(), value);
+	}
+	Ok(map)
+}
+
+// vim: ts=4
@@ -17,11 +17,13 @@ use cloudillo_types::{
 		TenantListItem, UpdateProxySiteData, Webauthn,
 	},
 	prelude::*,
+	types::TableDump,
 	worker::WorkerPool,
 };
 
 mod api_key;
 mod auth;
+mod backup;
 mod cert;
 mod crypto;
 mod profile_key;
@@ -112,6 +114,14 @@ impl AuthAdapter for AuthAdapterSqlite {
 		tenant::delete_tenant(&self.db, id_tag).await
 	}
 
+	async fn export_tenant(&self, tn_id: TnId) -> ClResult<Vec<TableDump>> {
+		backup::export(&self.db, tn_id).await
+	}
+
+	async fn import_tenant(&self, tables: &[TableDump]) -> ClResult<TnId> {
+		backup::import(&self.db, tables).await
+	}
+
 	async fn list_tenants(&self, opts: &ListTenantsOptions<'_>) -> ClResult<Vec<TenantListItem>> {
 		tenant::list_tenants(&self.db, opts).await
 	}
@@ -0,0 +1,74 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Tenant backup tests — `export_tenant` on one node, `import_tenant` on another.
+#![allow(clippy::panic, clippy::expect_used, clippy::unwrap_used)]
+
+#[cfg(test)]
+mod tests {
+	use cloudillo_auth_adapter_sqlite::AuthAdapterSqlite;
+	use cloudillo_types::auth_adapter::{AuthAdapter, CreateTenantData};
+	use cloudillo_types::prelude::*;
+	use cloudillo_types::worker::WorkerPool;
+	use std::sync::Arc;
+	use tempfile::TempDir;
+
+	async fn create_test_adapter() -> ClResult<(AuthAdapterSqlite, TempDir)> {
+		let tmp_dir = TempDir::new().unwrap();
+		let worker = Arc::new(WorkerPool::new(1, 1, 1));
+		let adapter = AuthAdapterSqlite::new(worker, tmp_dir.path()).await?;
+		Ok((adapter, tmp_dir))
+	}
+
+	async fn create_test_tenant(adapter: &AuthAdapterSqlite, id_tag: &str) -> TnId {
+		adapter
+			.create_tenant(
+				id_tag,
+				CreateTenantData {
+					vfy_code: None,
+					email: Some("alice@example.com"),
+					password: Some("correct_password_123"),
+					roles: None,
+				},
+			)
+			.await
+			.expect("Failed to create tenant")
+	}
+
+	#[tokio::test]
+	async fn test_backup_moves_login_and_keys_to_another_node() {
+		let (source, _tmp1) = create_test_adapter().await.expect("Failed to create adapter");
+		let (target, _tmp2) = create_test_adapter().await.expect("Failed to create adapter");
+
+		// Occupy the first tn_id on the target, so the restored tenant gets another one
+		create_test_tenant(&target, "bob.example.com").await;
+		let tn_id = create_test_tenant(&source, "alice.example.com").await;
+		let key = source.create_profile_key(tn_id, None).await.expect("Failed to create key");
+
+		let dump = source.export_tenant(tn_id).await.expect("Failed to export");
+		let restored = target.import_tenant(&dump).await.expect("Failed to import");
+		assert_ne!(restored, tn_id);
+
+		assert_eq!(target.read_tn_id("alice.example.com").await.unwrap(), restored);
+		target
+			.check_tenant_password("alice.example.com", "correct_password_123")
+			.await
+			.expect("password survives the move");
+		let keys = target.list_profile_keys(restored).await.expect("Failed to list keys");
+		assert!(keys.iter().any(|k| k.key_id == key.key_id && k.public_key == key.public_key));
+	}
+
+	#[tokio::test]
+	async fn test_backup_import_refuses_existing_id_tag() {
+		let (adapter, _tmp) = create_test_adapter().await.expect("Failed to create adapter");
+		let tn_id = create_test_tenant(&adapter, "alice.example.com").await;
+
+		let dump = adapter.export_tenant(tn_id).await.expect("Failed to export");
+		let res = adapter.import_tenant(&dump).await;
+		assert!(matches!(res, Err(Error::Conflict(_))), "{res:?}");
+
+		assert!(matches!(adapter.export_tenant(TnId(999)).await, Err(Error::NotFound)));
+	}
+}
+
+// vim: ts=4
@@ -6,9 +6,11 @@
 use sqlx::{Row, SqlitePool};
 
 use crate::utils::{Db, escape_like, inspect, parse_str_list, push_in};
+use cloudillo_types::hashtags::normalize_hashtag;
 use cloudillo_types::meta_adapter::{
-	Action, ActionData, ActionId, ActionView, AttachmentView, AudienceType, FinalizeActionOptions,
-	ListActionOptions, ProfileInfo, ProfileStatus, ProfileType, UpdateActionDataOptions,
+	Action, ActionData, ActionId, ActionRevision, ActionView, AttachmentView, AudienceType,
+	FinalizeActionOptions, InteractionCount, ListActionOptions, ProfileInfo, ProfileStatus,
+	ProfileType, UpdateActionDataOptions,
 };
 use cloudillo_types::prelude::*;
 use cloudillo_types::utils::normalize_id_tag;
@@ -20,6 +22,17 @@ fn db_visibility_to_action(s: Option<String>) -> Option<char> {
 	s.and_then(|s| s.chars().next()).filter(|c| *c != 'D')
 }
 
+/// The `mentions` column of an action select: the indexed id_tags, space-separated.
+fn mentions_column(row: &sqlx::sqlite::SqliteRow) -> Option<Vec<Box<str>>> {
+	let mentions: Option<String> = row.try_get("mentions").ok().flatten();
+	mentions.map(|m| m.split(' ').map(Into::into).collect())
+}
+
+/// True when the action at alias `a` carries a hashtag the tenant follows.
+const HAS_FOLLOWED_HASHTAG: &str = "EXISTS (SELECT 1 FROM action_hashtags h \
+	JOIN followed_hashtags fh ON fh.tn_id=h.tn_id AND fh.tag=h.tag \
+	WHERE h.tn_id=a.tn_id AND h.action_id=a.action_id)";
+
 /// Append the WHERE filters shared by `list`, `count`, and `count_grouped`.
 /// Caller has already emitted `... WHERE a.tn_id=<bind>`; this appends `AND ...`
 /// clauses. Operates on alias `a`, with `pi` (issuer profile) and `pa`
@@ -34,9 +47,9 @@ fn push_action_filters(
 		query = push_in(query, status);
 	} else {
 		// Default: hide deleted ('D'), inbound-verifying ('V', attachments not yet
-		// synced — surfacing would leak half-synced posts), and permanently-failed
-		// ('F', verifier exhausted retries) rows.
-		query.push(" AND coalesce(a.status, 'A') NOT IN ('D', 'V', 'F')");
+		// synced — surfacing would leak half-synced posts), permanently-failed
+		// ('F', verifier exhausted retries) and moderator-hidden ('H') rows.
+		query.push(" AND coalesce(a.status, 'A') NOT IN ('D', 'V', 'F', 'H')");
 	}
 	if let Some(typ) = &opts.typ {
 		query.push(" AND a.type IN ");
@@ -67,9 +80,11 @@ fn push_action_filters(
 		// Drop posts whose effective audience the reader opted out of their home
 		// feed (hidden_in_home communities). Same effective-audience expression
 		// the inclusive `audience` filter uses above.
+		// A public post carrying a followed hashtag still comes through.
 		let codes: Vec<&str> = excluded_aud.iter().map(String::as_str).collect();
-		query.push(" AND coalesce(a.audience, a.issuer_tag) NOT IN ");
+		query.push(" AND (coalesce(a.audience, a.issuer_tag) NOT IN ");
 		query = push_in(query, codes.as_slice());
+		query.push(" OR (a.visibility='P' AND ").push(HAS_FOLLOWED_HASHTAG).push("))");
 	}
 	if let Some(audience_type) = opts.audience_type {
 		// Filter on the effective-audience profile's type. `pa` joins on
@@ -179,10 +194,28 @@ fn push_action_filters(
 		query.push(")");
 	}
 	if let Some(tag) = &opts.tag {
+		// An invalid tag is in no action's index, so it matches nothing.
+		let tag = normalize_hashtag(tag).unwrap_or_default();
 		query
-			.push(" AND a.content LIKE ")
-			.push_bind(format!("%#{}%", escape_like(tag)))
-			.push(" ESCAPE '\\'");
+			.push(
+				" AND EXISTS (SELECT 1 FROM action_hashtags h \
+				 WHERE h.tn_id=a.tn_id AND h.action_id=a.action_id AND h.tag=",
+			)
+			.push_bind(tag)
+			.push(")");
+	}
+	if opts.followed_hashtags == Some(true) {
+		query.push(" AND a.visibility='P' AND ").push(HAS_FOLLOWED_HASHTAG).push(
+			" AND EXISTS (SELECT 1 FROM profiles pf \
+			 WHERE pf.tn_id=a.tn_id AND pf.id_tag=a.issuer_tag AND pf.following)",
+		);
+	}
+	if opts.related_issuers == Some(true) {
+		query.push(
+			" AND EXISTS (SELECT 1 FROM profiles pr \
+			 WHERE pr.tn_id=a.tn_id AND pr.id_tag=a.issuer_tag \
+			 AND (pr.following = 1 OR pr.connected = 1))",
+		);
 	}
 	if let Some(search) = &opts.search {
 		query
@@ -366,6 +399,7 @@ pub(crate) async fn count_grouped(
 	// Fixed column mapping — never interpolate caller input (injection-safe).
 	let col = match group_by {
 		ActionCountGroupBy::SubType => "a.sub_type",
+		ActionCountGroupBy::Content => "a.content",
 	};
 	let mut query = sqlx::QueryBuilder::new(format!(
 		"SELECT {col} AS grp, COUNT(DISTINCT a.a_id) AS cnt FROM actions a"
@@ -434,9 +468,10 @@ pub(crate) async fn list(
 		pi.name as issuer_name, pi.profile_pic as issuer_profile_pic, pi.type as issuer_type,
 		a.audience, pa.name as audience_name, pa.profile_pic as audience_profile_pic, pa.type as audience_type,
 		a.subject, ps.id_tag as subject_id_tag, ps.name as subject_name, ps.profile_pic as subject_profile_pic, ps.type as subject_type,
-		a.content, a.created_at, a.received_at, a.expires_at,
+		a.content, a.created_at, a.received_at, a.edited_at, a.expires_at,
 		own.sub_type as own_reaction,
-		a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.reposts, a.visibility, a.flags, a.sub_level, a.x
+		a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.seen_at, a.reposts, a.votes, a.rsvps, a.visibility, a.flags, a.sub_level, a.x,
+		(SELECT group_concat(m.id_tag, ' ') FROM action_mentions m WHERE m.tn_id=a.tn_id AND m.action_id=a.action_id) AS mentions
 		FROM actions a
 		LEFT JOIN profiles pi ON pi.tn_id=a.tn_id AND pi.id_tag=a.issuer_tag
 		LEFT JOIN profiles pa ON pa.tn_id=a.tn_id AND pa.id_tag=coalesce(a.audience, a.issuer_tag)
@@ -606,8 +641,11 @@ pub(crate) async fn list(
 		let comment_count: i64 = row.try_get("comments").unwrap_or(0);
 		let last_comment_at: Option<i64> = row.try_get("comments_ts").ok().flatten();
 		let comments_read_at: Option<i64> = row.try_get("comments_read_at").ok().flatten();
+		let seen_at: Option<i64> = row.try_get("seen_at").ok().flatten();
 		let own_reaction: Option<String> = row.try_get("own_reaction").ok().flatten();
 		let reposts: i64 = row.try_get("reposts").unwrap_or(0);
+		let votes: Option<String> = row.try_get("votes").ok().flatten();
+		let rsvps: Option<String> = row.try_get("rsvps").ok().flatten();
 		let mut stat_obj = serde_json::json!({});
 		if comment_count > 0 {
 			stat_obj["commentCount"] = serde_json::Value::from(comment_count);
@@ -618,6 +656,9 @@ pub(crate) async fn list(
 		if let Some(ts) = comments_read_at {
 			stat_obj["commentsReadAt"] = serde_json::Value::String(Timestamp(ts).to_iso_string());
 		}
+		if let Some(ts) = seen_at {
+			stat_obj["seenAt"] = serde_json::Value::String(Timestamp(ts).to_iso_string());
+		}
 		if let Some(reactions) = reactions {
 			stat_obj["reactions"] = serde_json::Value::String(reactions);
 		}
@@ -627,6 +668,12 @@ pub(crate) async fn list(
 		if reposts > 0 {
 			stat_obj["reposts"] = serde_json::Value::from(reposts);
 		}
+		if let Some(votes) = votes.and_then(|v| serde_json::from_str(&v).ok()) {
+			stat_obj["votes"] = votes;
+		}
+		if let Some(rsvps) = rsvps.and_then(|v| serde_json::from_str(&v).ok()) {
+			stat_obj["rsvps"] = rsvps;
+		}
 		let stat = Some(stat_obj);
 		let visibility: Option<String> = row.try_get("visibility").ok().flatten();
 		let visibility = db_visibility_to_action(visibility);
@@ -676,6 +723,7 @@ pub(crate) async fn list(
 			},
 			// Hydrated below for REPOST rows.
 			subject_action: None,
+			mentions: mentions_column(&row),
 			content: row
 				.try_get::<Option<String>, _>("content")
 				.db()?
@@ -683,6 +731,7 @@ pub(crate) async fn list(
 			attachments,
 			created_at: row.try_get("created_at").map(Timestamp).db()?,
 			received_at: row.try_get::<Option<i64>, _>("received_at").ok().flatten().map(Timestamp),
+			edited_at: row.try_get::<Option<i64>, _>("edited_at").ok().flatten().map(Timestamp),
 			expires_at: row.try_get("expires_at").map(|ts: Option<i64>| ts.map(Timestamp)).db()?,
 			status: row.try_get("status").db()?,
 			stat,
@@ -693,6 +742,7 @@ pub(crate) async fn list(
 				.try_get::<Option<String>, _>("x")
 				.db()?
 				.and_then(|s| serde_json::from_str(&s).ok()),
+			filtered: None,
 			token: None,
 		});
 	}
@@ -1072,7 +1122,7 @@ pub(crate) async fn get_data(
 	action_id: &str,
 ) -> ClResult<Option<ActionData>> {
 	let res = sqlx::query(
-		"SELECT subject, reactions, comments, comments_ts, stat_at FROM actions WHERE tn_id=? AND action_id=?",
+		"SELECT subject, reactions, comments, comments_ts, stat_at, votes, rsvps FROM actions WHERE tn_id=? AND action_id=?",
 	)
 	.bind(tn_id.0)
 	.bind(action_id)
@@ -1087,6 +1137,8 @@ pub(crate) async fn get_data(
 			comments: row.try_get::<Option<i64>, _>("comments").ok().flatten(),
 			comments_ts: row.try_get::<Option<i64>, _>("comments_ts").ok().flatten().map(Timestamp),
 			stat_at: row.try_get::<Option<i64>, _>("stat_at").ok().flatten().map(Timestamp),
+			votes: row.try_get::<Option<String>, _>("votes").ok().flatten().map(Into::into),
+			rsvps: row.try_get::<Option<String>, _>("rsvps").ok().flatten().map(Into::into),
 		})),
 		None => Ok(None),
 	}
@@ -1207,6 +1259,12 @@ pub(crate) async fn update_data(
 	if !opts.reposts.is_undefined() {
 		set_clauses.push("reposts = ?");
 	}
+	if !opts.votes.is_undefined() {
+		set_clauses.push("votes = ?");
+	}
+	if !opts.rsvps.is_undefined() {
+		set_clauses.push("rsvps = ?");
+	}
 	if !opts.status.is_undefined() {
 		set_clauses.push("status = ?");
 	}
@@ -1299,6 +1357,22 @@ pub(crate) async fn update_data(
 		};
 		query = query.bind(val);
 	}
+	if !opts.votes.is_undefined() {
+		let val: Option<&str> = match &opts.votes {
+			Patch::Null => None,
+			Patch::Value(v) => Some(v.as_str()),
+			Patch::Undefined => unreachable!(),
+		};
+		query = query.bind(val);
+	}
+	if !opts.rsvps.is_undefined() {
+		let val: Option<&str> = match &opts.rsvps {
+			Patch::Null => None,
+			Patch::Value(v) => Some(v.as_str()),
+			Patch::Undefined => unreachable!(),
+		};
+		query = query.bind(val);
+	}
 	if !opts.status.is_undefined() {
 		let val: Option<String> = match &opts.status {
 			Patch::Null => None,
@@ -1422,8 +1496,9 @@ pub(crate) async fn get(
 			pi.name as issuer_name, pi.profile_pic as issuer_profile_pic, pi.type as issuer_type,
 			a.audience, pa.name as audience_name, pa.profile_pic as audience_profile_pic, pa.type as audience_type,
 			a.subject, ps.id_tag as subject_id_tag, ps.name as subject_name, ps.profile_pic as subject_profile_pic, ps.type as subject_type,
-			a.content, a.created_at, a.received_at, a.expires_at,
-			a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.reposts, a.visibility, a.flags, a.sub_level, a.x
+			a.content, a.created_at, a.received_at, a.edited_at, a.expires_at,
+			a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.seen_at, a.reposts, a.votes, a.rsvps, a.visibility, a.flags, a.sub_level, a.x,
+			(SELECT group_concat(m.id_tag, ' ') FROM action_mentions m WHERE m.tn_id=a.tn_id AND m.action_id=a.action_id) AS mentions
 			FROM actions a
 			LEFT JOIN profiles pi ON pi.tn_id=a.tn_id AND pi.id_tag=a.issuer_tag
 			LEFT JOIN profiles pa ON pa.tn_id=a.tn_id AND pa.id_tag=coalesce(a.audience, a.issuer_tag)
@@ -1443,8 +1518,9 @@ pub(crate) async fn get(
 			pi.name as issuer_name, pi.profile_pic as issuer_profile_pic, pi.type as issuer_type,
 			a.audience, pa.name as audience_name, pa.profile_pic as audience_profile_pic, pa.type as audience_type,
 			a.subject, ps.id_tag as subject_id_tag, ps.name as subject_name, ps.profile_pic as subject_profile_pic, ps.type as subject_type,
-			a.content, a.created_at, a.received_at, a.expires_at,
-			a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.reposts, a.visibility, a.flags, a.sub_level, a.x
+			a.content, a.created_at, a.received_at, a.edited_at, a.expires_at,
+			a.attachments, a.status, a.reactions, a.comments, a.comments_ts, a.comments_read_at, a.seen_at, a.reposts, a.votes, a.rsvps, a.visibility, a.flags, a.sub_level, a.x,
+			(SELECT group_concat(m.id_tag, ' ') FROM action_mentions m WHERE m.tn_id=a.tn_id AND m.action_id=a.action_id) AS mentions
 			FROM actions a
 			LEFT JOIN profiles pi ON pi.tn_id=a.tn_id AND pi.id_tag=a.issuer_tag
 			LEFT JOIN profiles pa ON pa.tn_id=a.tn_id AND pa.id_tag=coalesce(a.audience, a.issuer_tag)
@@ -1531,7 +1607,10 @@ pub(crate) async fn get(
 	let comment_count: i64 = row.try_get("comments").unwrap_or(0);
 	let last_comment_at: Option<i64> = row.try_get("comments_ts").ok().flatten();
 	let comments_read_at: Option<i64> = row.try_get("comments_read_at").ok().flatten();
+	let seen_at: Option<i64> = row.try_get("seen_at").ok().flatten();
 	let reposts: i64 = row.try_get("reposts").unwrap_or(0);
+	let votes: Option<String> = row.try_get("votes").ok().flatten();
+	let rsvps: Option<String> = row.try_get("rsvps").ok().flatten();
 	let mut stat_obj = serde_json::json!({});
 	if comment_count > 0 {
 		stat_obj["commentCount"] = serde_json::Value::from(comment_count);
@@ -1542,12 +1621,21 @@ pub(crate) async fn get(
 	if let Some(ts) = comments_read_at {
 		stat_obj["commentsReadAt"] = serde_json::Value::String(Timestamp(ts).to_iso_string());
 	}
+	if let Some(ts) = seen_at {
+		stat_obj["seenAt"] = serde_json::Value::String(Timestamp(ts).to_iso_string());
+	}
 	if let Some(reactions) = reactions {
 		stat_obj["reactions"] = serde_json::Value::String(reactions);
 	}
 	if reposts > 0 {
 		stat_obj["reposts"] = serde_json::Value::from(reposts);
 	}
+	if let Some(votes) = votes.and_then(|v| serde_json::from_str(&v).ok()) {
+		stat_obj["votes"] = votes;
+	}
+	if let Some(rsvps) = rsvps.and_then(|v| serde_json::from_str(&v).ok()) {
+		stat_obj["rsvps"] = rsvps;
+	}
 	// Per-user stat, only when a viewer is supplied. Mirrors the list path's
 	// own_reaction (lines 341-360) and ownRepostIds (lines 468-505) so an
 	// embedded subject_action carries the viewer's reaction/repost state.
@@ -1644,6 +1732,7 @@ pub(crate) async fn get(
 		},
 		// Hydrated below for REPOST rows (see post-construction embed).
 		subject_action: None,
+		mentions: mentions_column(&row),
 		content: row
 			.try_get::<Option<String>, _>("content")
 			.db()?
@@ -1651,6 +1740,7 @@ pub(crate) async fn get(
 		attachments,
 		created_at: row.try_get("created_at").map(Timestamp).db()?,
 		received_at: row.try_get::<Option<i64>, _>("received_at").ok().flatten().map(Timestamp),
+		edited_at: row.try_get::<Option<i64>, _>("edited_at").ok().flatten().map(Timestamp),
 		expires_at: row.try_get("expires_at").map(|ts: Option<i64>| ts.map(Timestamp)).db()?,
 		status: row.try_get("status").db()?,
 		stat,
@@ -1661,6 +1751,7 @@ pub(crate) async fn get(
 			.try_get::<Option<String>, _>("x")
 			.db()?
 			.and_then(|s| serde_json::from_str(&s).ok()),
+		filtered: None,
 		token: None,
 	};
 
@@ -1705,6 +1796,114 @@ pub(crate) async fn delete(db: &SqlitePool, tn_id: TnId, action_id: &str) -> ClR
 	Ok(())
 }
 
+/// Apply an `EDIT` to an action's content. See `MetaAdapter::apply_action_edit`.
+pub(crate) async fn apply_edit(
+	db: &SqlitePool,
+	tn_id: TnId,
+	action_id: &str,
+	edit_id: &str,
+	content: Option<&str>,
+	edited_at: Timestamp,
+) -> ClResult<bool> {
+	let mut tx = db.begin().await.db()?;
+
+	// The first edit records the original next to itself, so the history is complete
+	// without copying every never-edited action into the table.
+	let res = sqlx::query(
+		"INSERT INTO action_revisions (tn_id, action_id, edit_id, content, edited_at)
+		SELECT tn_id, action_id, action_id, content, created_at FROM actions
+		WHERE tn_id=? AND action_id=? AND coalesce(status, 'A') != 'D'
+			AND NOT EXISTS (SELECT 1 FROM action_revisions WHERE tn_id=? AND action_id=?)",
+	)
+	.bind(tn_id.0)
+	.bind(action_id)
+	.bind(tn_id.0)
+	.bind(action_id)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	if res.rows_affected() == 0 {
+		let exists: Option<i64> = sqlx::query_scalar(
+			"SELECT 1 FROM actions
+			WHERE tn_id=? AND action_id=? AND coalesce(status, 'A') != 'D'",
+		)
+		.bind(tn_id.0)
+		.bind(action_id)
+		.fetch_optional(&mut *tx)
+		.await
+		.db()?;
+		if exists.is_none() {
+			return Err(Error::NotFound);
+		}
+	}
+
+	let res = sqlx::query(
+		"INSERT OR IGNORE INTO action_revisions (tn_id, action_id, edit_id, content, edited_at)
+		VALUES (?, ?, ?, ?, ?)",
+	)
+	.bind(tn_id.0)
+	.bind(action_id)
+	.bind(edit_id)
+	.bind(content)
+	.bind(edited_at.0)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	if res.rows_affected() == 0 {
+		// Replay of an edit already applied (or already recorded as stale).
+		tx.commit().await.db()?;
+		return Ok(false);
+	}
+
+	// Newest edit wins, by the issuer's clock; an older one that arrives late stays
+	// in the history only.
+	let res = sqlx::query(
+		"UPDATE actions SET content=?, edited_at=?, updated_at=unixepoch()
+		WHERE tn_id=? AND action_id=? AND (edited_at IS NULL OR edited_at < ?)",
+	)
+	.bind(content)
+	.bind(edited_at.0)
+	.bind(tn_id.0)
+	.bind(action_id)
+	.bind(edited_at.0)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+
+	tx.commit().await.db()?;
+	Ok(res.rows_affected() > 0)
+}
+
+/// List the revisions of an edited action, newest first.
+pub(crate) async fn list_revisions(
+	db: &SqlitePool,
+	tn_id: TnId,
+	action_id: &str,
+) -> ClResult<Vec<ActionRevision>> {
+	let rows = sqlx::query(
+		"SELECT edit_id, content, edited_at FROM action_revisions
+		WHERE tn_id=? AND action_id=? ORDER BY edited_at DESC, edit_id DESC",
+	)
+	.bind(tn_id.0)
+	.bind(action_id)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter()
+		.map(|row| {
+			Ok(ActionRevision {
+				edit_id: row.try_get("edit_id").db()?,
+				content: row
+					.try_get::<Option<String>, _>("content")
+					.db()?
+					.and_then(|s| serde_json::from_str(&s).ok()),
+				edited_at: row.try_get("edited_at").map(Timestamp).db()?,
+			})
+		})
+		.collect()
+}
+
 /// Set a read-watermark forward-only (lower position is a no-op). Dispatches by
 /// `scope` over the two tables that carry reader-relative state. See the
 /// `MetaAdapter::set_read_marker` doc for the scope/column map.
@@ -1728,12 +1927,50 @@ pub async fn set_read_marker(
 			"UPDATE actions SET comments_read_at = max(coalesce(comments_read_at, 0), ?) \
 			 WHERE tn_id = ? AND action_id = ?"
 		}
+		"seen" => {
+			"UPDATE actions SET seen_at = max(coalesce(seen_at, 0), ?) \
+			 WHERE tn_id = ? AND action_id = ?"
+		}
 		_ => return Err(Error::ValidationError(format!("unknown read-marker scope: {scope}"))),
 	};
 	sqlx::query(sql).bind(position).bind(tn_id.0).bind(key).execute(db).await.db()?;
 	Ok(())
 }
 
+/// Count the reader's reactions, comments and reposts since `since`, per issuer of
+/// the action they answered. Own actions are left out.
+pub async fn count_interactions(
+	db: &SqlitePool,
+	tn_id: TnId,
+	reader_tag: &str,
+	since: Timestamp,
+) -> ClResult<Vec<InteractionCount>> {
+	let rows = sqlx::query(
+		"SELECT t.issuer_tag, count(*) AS interactions
+		 FROM actions r
+		 JOIN actions t ON t.tn_id=r.tn_id AND t.action_id=coalesce(r.subject, r.parent_id)
+		 WHERE r.tn_id=? AND r.issuer_tag=? AND r.type IN ('REACT', 'CMNT', 'REPOST')
+		   AND coalesce(r.sub_type, '')!='DEL' AND coalesce(r.status, 'A')='A'
+		   AND r.created_at>=? AND t.issuer_tag!=r.issuer_tag
+		 GROUP BY t.issuer_tag",
+	)
+	.bind(tn_id.0)
+	.bind(reader_tag)
+	.bind(since.0)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter()
+		.map(|row| {
+			Ok(InteractionCount {
+				id_tag: row.try_get("issuer_tag").db()?,
+				interactions: row.try_get("interactions").db()?,
+			})
+		})
+		.collect()
+}
+
 /// Auto-subscribe at Tracking: set `sub_level='T'` only when currently NULL
 /// (never downgrade an existing Watching). No-op if the row is absent.
 pub async fn auto_track(db: &SqlitePool, tn_id: TnId, action_id: &str) -> ClResult<()> {
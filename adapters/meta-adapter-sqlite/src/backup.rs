@@ -0,0 +1,237 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Tenant backup: dumping a tenant's rows and loading them back.
+//!
+//! Rows travel as raw columns, so a dump taken on one schema version loads into
+//! the same or a later one: a column added since takes its default, a column the
+//! loading side does not know is refused.
+//!
+//! Integer surrogate keys are never carried over — the target node's sequence
+//! already hands them to its own tenants. A table with one is inserted without
+//! it, and the rows referencing it are rewritten to the id the insert got back.
+//! That is why [`TENANT_TABLES`] is ordered: a referenced table comes first.
+
+use serde_json::{Map, Value};
+use sqlx::{Column, Row, SqlitePool, TypeInfo, ValueRef, sqlite::SqliteRow};
+use std::collections::{HashMap, HashSet};
+
+use crate::utils::Db;
+use cloudillo_types::prelude::*;
+use cloudillo_types::types::TableDump;
+
+/// A table in a tenant backup.
+struct BackupTable {
+	name: &'static str,
+	/// Integer surrogate key, renumbered on load.
+	id: Option<&'static str>,
+	/// Columns holding another table's surrogate key, as `(column, table)`.
+	refs: &'static [(&'static str, &'static str)],
+}
+
+const fn table(name: &'static str) -> BackupTable {
+	BackupTable { name, id: None, refs: &[] }
+}
+
+/// Every table the backup carries, referenced tables first.
+///
+/// The tenant cascade in `tenant.rs` minus what is derived or transient:
+/// `search_docs` (rebuilt by a reindex sweep), `key_cache` (re-fetched on
+/// demand), `trending_hashtags` (recomputed by the trending task) and `tasks`
+/// (in-flight work of the node that wrote it).
+const TENANT_TABLES: &[BackupTable] = &[
+	table("tenants"),
+	table("tenant_data"),
+	table("settings"),
+	BackupTable { name: "subscriptions", id: Some("subs_id"), refs: &[] },
+	table("profiles"),
+	table("tags"),
+	BackupTable { name: "files", id: Some("f_id"), refs: &[] },
+	BackupTable { name: "file_variants", id: None, refs: &[("f_id", "files")] },
+	BackupTable { name: "file_user_data", id: None, refs: &[("f_id", "files")] },
+	table("refs"),
+	BackupTable { name: "actions", id: Some("a_id"), refs: &[] },
+	table("action_tokens"),
+	table("action_revisions"),
+	BackupTable { name: "moderation_log", id: Some("ml_id"), refs: &[] },
+	BackupTable { name: "content_filters", id: Some("cf_id"), refs: &[] },
+	table("action_hashtags"),
+	table("followed_hashtags"),
+	table("action_mentions"),
+	BackupTable { name: "collections", id: Some("col_id"), refs: &[] },
+	BackupTable { name: "saved_items", id: None, refs: &[("col_id", "collections")] },
+	BackupTable { name: "share_entries", id: Some("id"), refs: &[] },
+	table("installed_apps"),
+	table("doc_formats"),
+	BackupTable { name: "address_books", id: Some("ab_id"), refs: &[] },
+	BackupTable { name: "contacts", id: Some("c_id"), refs: &[("ab_id", "address_books")] },
+	BackupTable { name: "calendars", id: Some("cal_id"), refs: &[] },
+	BackupTable { name: "calendar_objects", id: Some("co_id"), refs: &[("cal_id", "calendars")] },
+	table("sites"),
+	table("site_docs"),
+];
+
+/// Dump every backed-up table of a tenant.
+pub(crate) async fn export(dbr: &SqlitePool, tn_id: TnId) -> ClResult<Vec<TableDump>> {
+	// One read transaction, so the tables agree with each other.
+	let mut tx = dbr.begin().await.db()?;
+	let mut tables = Vec::with_capacity(TENANT_TABLES.len());
+	for table in TENANT_TABLES {
+		let rows =
+			sqlx::query(sqlx::AssertSqlSafe(format!("SELECT * FROM {} WHERE tn_id=?", table.name)))
+				.bind(tn_id.0)
+				.fetch_all(&mut *tx)
+				.await
+				.db()?;
+		let rows = rows.iter().map(|row| row_to_json(table.name, row)).collect::<ClResult<_>>()?;
+		tables.push(TableDump { table: table.name.into(), rows });
+	}
+	tx.commit().await.db()?;
+	Ok(tables)
+}
+
+/// Load a dump as tenant `tn_id`, in one transaction.
+pub(crate) async fn import(db: &SqlitePool, tn_id: TnId, tables: &[TableDump]) -> ClResult<()> {
+	for dump in tables {
+		if !TENANT_TABLES.iter().any(|t| *t.name == *dump.table) {
+			return Err(Error::ValidationError(format!(
+				"backup table {} is not a tenant table",
+				dump.table
+			)));
+		}
+	}
+
+	let mut tx = db.begin().await.db()?;
+
+	let exists = sqlx::query("SELECT 1 FROM tenants WHERE tn_id=?")
+		.bind(tn_id.0)
+		.fetch_optional(&mut *tx)
+		.await
+		.db()?;
+	if exists.is_some() {
+		return Err(Error::Conflict(format!("tenant {} already exists", tn_id)));
+	}
+
+	let mut ids: HashMap<&str, HashMap<i64, i64>> = HashMap::new();
+	for table in TENANT_TABLES {
+		let Some(dump) = tables.iter().find(|d| *d.table == *table.name) else {
+			continue;
+		};
+		let columns = table_columns(&mut tx, table.name).await?;
+		let mut renumbered = HashMap::new();
+
+		for row in &dump.rows {
+			let mut names = Vec::with_capacity(row.len());
+			let mut values = Vec::with_capacity(row.len());
+			let mut old_id = None;
+			for (name, value) in row {
+				if !columns.contains(name.as_str()) {
+					return Err(Error::ValidationError(format!(
+						"backup column {}.{} does not exist",
+						table.name, name
+					)));
+				}
+				if table.id == Some(name.as_str()) {
+					old_id = value.as_i64();
+					continue;
+				}
+				let value = if name == "tn_id" {
+					Value::from(tn_id.0)
+				} else if let Some((_, parent)) = table.refs.iter().find(|(c, _)| *c == name) {
+					let new_id = value
+						.as_i64()
+						.and_then(|old| ids.get(parent).and_then(|m| m.get(&old)))
+						.ok_or_else(|| {
+							Error::ValidationError(format!(
+								"backup row in {} references a missing {} row",
+								table.name, parent
+							))
+						})?;
+					Value::from(*new_id)
+				} else {
+					value.clone()
+				};
+				names.push(name.as_str());
+				values.push(value);
+			}
+
+			let mut query = sqlx::QueryBuilder::new(format!("INSERT INTO {} (", table.name));
+			query.push(names.join(", ")).push(") VALUES (");
+			let mut sep = query.separated(", ");
+			for value in &values {
+				match value {
+					Value::Null => sep.push_bind(None::<i64>),
+					Value::Bool(b) => sep.push_bind(i64::from(*b)),
+					Value::Number(n) if n.is_i64() => sep.push_bind(n.as_i64()),
+					Value::Number(n) => sep.push_bind(n.as_f64()),
+					Value::String(s) => sep.push_bind(s.as_str()),
+					Value::Array(_) | Value::Object(_) => {
+						return Err(Error::ValidationError(format!(
+							"backup row in {} has a non-scalar column",
+							table.name
+						)));
+					}
+				};
+			}
+			query.push(")");
+
+			if let Some(id_col) = table.id {
+				query.push(format!(" RETURNING {}", id_col));
+				let new_id: i64 = query.build().fetch_one(&mut *tx).await.db()?.try_get(0).db()?;
+				if let Some(old_id) = old_id {
+					renumbered.insert(old_id, new_id);
+				}
+			} else {
+				query.build().execute(&mut *tx).await.db()?;
+			}
+		}
+
+		if !renumbered.is_empty() {
+			ids.insert(table.name, renumbered);
+		}
+	}
+
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+async fn table_columns(
+	tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+	table: &str,
+) -> ClResult<HashSet<String>> {
+	let rows = sqlx::query(sqlx::AssertSqlSafe(format!("PRAGMA table_info({})", table)))
+		.fetch_all(&mut **tx)
+		.await
+		.db()?;
+	rows.iter().map(|row| row.try_get::<String, _>("name").db()).collect()
+}
+
+/// A row as `column → value`, by the stored value's own type rather than the
+/// declared one.
+fn row_to_json(table: &str, row: &SqliteRow) -> ClResult<Map<String, Value>> {
+	let mut map = Map::with_capacity(row.len());
+	for (i, column) in row.columns().iter().enumerate() {
+		let raw = row.try_get_raw(i).db()?;
+		let value = if raw.is_null() {
+			Value::Null
+		} else {
+			match raw.type_info().name() {
+				"INTEGER" => Value::from(row.try_get::<i64, _>(i).db()?),
+				"REAL" => Value::from(row.try_get::<f64, _>(i).db()?),
+				"TEXT" => Value::from(row.try_get::<String, _>(i).db()?),
+				other => {
+					return Err(Error::Internal(format!(
+						"cannot back up {} column {}.{}",
+						other,
+						table,
+						column.name()
+					)));
+				}
+			}
+		};
+		map.insert(column.name().to_string(), value);
+	}
+	Ok(map)
+}
+
+// vim: ts=4
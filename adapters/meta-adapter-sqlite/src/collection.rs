@@ -0,0 +1,352 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Collection (saved items) database operations
+
+use crate::utils::{Db, push_patch};
+use cloudillo_types::{
+	meta_adapter::{
+		Collection, CreateCollection, SaveItem, SavedItem, SavedItemType, UpdateCollectionOptions,
+		UpdateSavedItemOptions,
+	},
+	prelude::*,
+};
+use sqlx::{Row, Sqlite, SqlitePool, Transaction, sqlite::SqliteRow};
+
+/// Convert a `SavedItemType` to its CHAR(1) database representation (the search
+/// index's object type).
+fn type_to_db(typ: SavedItemType) -> &'static str {
+	match typ {
+		SavedItemType::Action => "A",
+		SavedItemType::File => "F",
+		SavedItemType::Profile => "P",
+	}
+}
+
+fn row_to_collection(row: &SqliteRow) -> ClResult<Collection> {
+	Ok(Collection {
+		collection_id: u64::try_from(row.try_get::<i64, _>("col_id").db()?).unwrap_or_default(),
+		name: row.try_get("name").db()?,
+		description: row.try_get("description").db()?,
+		item_count: row.try_get("item_count").db()?,
+		created_at: row.try_get("created_at").map(Timestamp).db()?,
+		updated_at: row.try_get("updated_at").map(Timestamp).db()?,
+	})
+}
+
+fn row_to_item(row: &SqliteRow) -> ClResult<SavedItem> {
+	let item_type = match row.try_get::<&str, _>("item_type").db()? {
+		"A" => SavedItemType::Action,
+		"F" => SavedItemType::File,
+		"P" => SavedItemType::Profile,
+		other => return Err(Error::Internal(format!("Invalid saved item type '{}'", other))),
+	};
+	Ok(SavedItem {
+		item_type,
+		item_id: row.try_get("item_id").db()?,
+		note: row.try_get("note").db()?,
+		position: row.try_get("position").db()?,
+		created_at: row.try_get("created_at").map(Timestamp).db()?,
+	})
+}
+
+/// List the owner's collections, oldest first
+pub(crate) async fn list(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+) -> ClResult<Vec<Collection>> {
+	let rows = sqlx::query(
+		"SELECT c.col_id, c.name, c.description, c.created_at, c.updated_at,
+			(SELECT count(*) FROM saved_items i WHERE i.col_id=c.col_id) AS item_count
+		 FROM collections c
+		 WHERE c.tn_id=? AND c.owner_tag=?
+		 ORDER BY c.col_id",
+	)
+	.bind(tn_id.0)
+	.bind(owner_tag)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter().map(row_to_collection).collect()
+}
+
+/// Read one of the owner's collections
+pub(crate) async fn read(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+) -> ClResult<Collection> {
+	let row = sqlx::query(
+		"SELECT c.col_id, c.name, c.description, c.created_at, c.updated_at,
+			(SELECT count(*) FROM saved_items i WHERE i.col_id=c.col_id) AS item_count
+		 FROM collections c
+		 WHERE c.tn_id=? AND c.owner_tag=? AND c.col_id=?",
+	)
+	.bind(tn_id.0)
+	.bind(owner_tag)
+	.bind(collection_id.cast_signed())
+	.fetch_optional(db)
+	.await
+	.db()?;
+
+	row.map(|r| row_to_collection(&r)).ok_or(Error::NotFound)?
+}
+
+/// Create a collection
+pub(crate) async fn create(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection: &CreateCollection,
+) -> ClResult<Collection> {
+	let row = sqlx::query(
+		"INSERT INTO collections (tn_id, owner_tag, name, description) VALUES (?, ?, ?, ?)
+		 RETURNING col_id, name, description, created_at, updated_at, 0 AS item_count",
+	)
+	.bind(tn_id.0)
+	.bind(owner_tag)
+	.bind(&collection.name)
+	.bind(&collection.description)
+	.fetch_one(db)
+	.await
+	.db()?;
+
+	row_to_collection(&row)
+}
+
+/// Update a collection
+pub(crate) async fn update(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+	opts: &UpdateCollectionOptions,
+) -> ClResult<Collection> {
+	let mut query = sqlx::QueryBuilder::new("UPDATE collections SET ");
+	let mut has = false;
+	has = push_patch!(query, has, "name", &opts.name);
+	has = push_patch!(query, has, "description", &opts.description);
+	if has {
+		query.push(", updated_at=unixepoch()");
+		query.push(" WHERE tn_id=").push_bind(tn_id.0);
+		query.push(" AND owner_tag=").push_bind(owner_tag);
+		query.push(" AND col_id=").push_bind(collection_id.cast_signed());
+		let result = query.build().execute(db).await.db()?;
+		if result.rows_affected() == 0 {
+			return Err(Error::NotFound);
+		}
+	}
+	read(db, tn_id, owner_tag, collection_id).await
+}
+
+/// Delete a collection with its items
+pub(crate) async fn delete(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+) -> ClResult<()> {
+	let mut tx = db.begin().await.db()?;
+	let result = sqlx::query("DELETE FROM collections WHERE tn_id=? AND owner_tag=? AND col_id=?")
+		.bind(tn_id.0)
+		.bind(owner_tag)
+		.bind(collection_id.cast_signed())
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	if result.rows_affected() == 0 {
+		return Err(Error::NotFound);
+	}
+	sqlx::query("DELETE FROM saved_items WHERE tn_id=? AND col_id=?")
+		.bind(tn_id.0)
+		.bind(collection_id.cast_signed())
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+/// Mark one of the owner's collections changed. Doubles as the ownership check of
+/// every item write: `Error::NotFound` for a collection of another owner.
+async fn touch(
+	tx: &mut Transaction<'_, Sqlite>,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+) -> ClResult<()> {
+	let result = sqlx::query(
+		"UPDATE collections SET updated_at=unixepoch() WHERE tn_id=? AND owner_tag=? AND col_id=?",
+	)
+	.bind(tn_id.0)
+	.bind(owner_tag)
+	.bind(collection_id.cast_signed())
+	.execute(&mut **tx)
+	.await
+	.db()?;
+	if result.rows_affected() == 0 {
+		return Err(Error::NotFound);
+	}
+	Ok(())
+}
+
+/// Make room at `position` by moving the items at and after it, except the item
+/// being placed there, one down.
+async fn make_room(
+	tx: &mut Transaction<'_, Sqlite>,
+	collection_id: u64,
+	position: i64,
+	item_type: SavedItemType,
+	item_id: &str,
+) -> ClResult<()> {
+	sqlx::query(
+		"UPDATE saved_items SET position=position+1
+		 WHERE col_id=? AND position>=? AND NOT (item_type=? AND item_id=?)",
+	)
+	.bind(collection_id.cast_signed())
+	.bind(position)
+	.bind(type_to_db(item_type))
+	.bind(item_id)
+	.execute(&mut **tx)
+	.await
+	.db()?;
+	Ok(())
+}
+
+/// List a collection's items in order
+pub(crate) async fn list_items(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+) -> ClResult<Vec<SavedItem>> {
+	let owned = sqlx::query("SELECT 1 FROM collections WHERE tn_id=? AND owner_tag=? AND col_id=?")
+		.bind(tn_id.0)
+		.bind(owner_tag)
+		.bind(collection_id.cast_signed())
+		.fetch_optional(db)
+		.await
+		.db()?;
+	if owned.is_none() {
+		return Err(Error::NotFound);
+	}
+
+	let rows = sqlx::query(
+		"SELECT item_type, item_id, note, position, created_at FROM saved_items
+		 WHERE col_id=? ORDER BY position, created_at",
+	)
+	.bind(collection_id.cast_signed())
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter().map(row_to_item).collect()
+}
+
+/// Save an item, or update the note and position of one the collection holds
+pub(crate) async fn save_item(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+	item: &SaveItem,
+) -> ClResult<SavedItem> {
+	let mut tx = db.begin().await.db()?;
+	touch(&mut tx, tn_id, owner_tag, collection_id).await?;
+	if let Some(position) = item.position {
+		make_room(&mut tx, collection_id, position, item.item_type, &item.item_id).await?;
+	}
+	// Without a position a new item goes last and a saved one stays where it is.
+	let row = sqlx::query(
+		"INSERT INTO saved_items (tn_id, col_id, item_type, item_id, note, position)
+		 VALUES (?, ?, ?, ?, ?, coalesce(?,
+			(SELECT coalesce(max(position) + 1, 0) FROM saved_items WHERE col_id=?)))
+		 ON CONFLICT(col_id, item_type, item_id) DO UPDATE SET note=excluded.note,
+			position=CASE WHEN ? IS NULL THEN position ELSE excluded.position END
+		 RETURNING item_type, item_id, note, position, created_at",
+	)
+	.bind(tn_id.0)
+	.bind(collection_id.cast_signed())
+	.bind(type_to_db(item.item_type))
+	.bind(&item.item_id)
+	.bind(&item.note)
+	.bind(item.position)
+	.bind(collection_id.cast_signed())
+	.bind(item.position)
+	.fetch_one(&mut *tx)
+	.await
+	.db()?;
+	tx.commit().await.db()?;
+
+	row_to_item(&row)
+}
+
+/// Update a saved item's note or position
+pub(crate) async fn update_item(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+	item_type: SavedItemType,
+	item_id: &str,
+	opts: &UpdateSavedItemOptions,
+) -> ClResult<SavedItem> {
+	let mut tx = db.begin().await.db()?;
+	touch(&mut tx, tn_id, owner_tag, collection_id).await?;
+	if let Patch::Value(position) = opts.position {
+		make_room(&mut tx, collection_id, position, item_type, item_id).await?;
+	}
+
+	let mut query = sqlx::QueryBuilder::new("UPDATE saved_items SET ");
+	let mut has = false;
+	has = push_patch!(query, has, "note", &opts.note);
+	has = push_patch!(query, has, "position", &opts.position);
+	if !has {
+		// Nothing to change: still answer with the row, and NotFound without one.
+		query = sqlx::QueryBuilder::new(
+			"SELECT item_type, item_id, note, position, created_at FROM saved_items",
+		);
+	}
+	query.push(" WHERE col_id=").push_bind(collection_id.cast_signed());
+	query.push(" AND item_type=").push_bind(type_to_db(item_type));
+	query.push(" AND item_id=").push_bind(item_id);
+	if has {
+		query.push(" RETURNING item_type, item_id, note, position, created_at");
+	}
+
+	let row = query.build().fetch_optional(&mut *tx).await.db()?;
+	let item = row.map(|r| row_to_item(&r)).ok_or(Error::NotFound)??;
+	tx.commit().await.db()?;
+	Ok(item)
+}
+
+/// Remove an item from a collection
+pub(crate) async fn remove_item(
+	db: &SqlitePool,
+	tn_id: TnId,
+	owner_tag: &str,
+	collection_id: u64,
+	item_type: SavedItemType,
+	item_id: &str,
+) -> ClResult<()> {
+	let mut tx = db.begin().await.db()?;
+	touch(&mut tx, tn_id, owner_tag, collection_id).await?;
+	let result =
+		sqlx::query("DELETE FROM saved_items WHERE col_id=? AND item_type=? AND item_id=?")
+			.bind(collection_id.cast_signed())
+			.bind(type_to_db(item_type))
+			.bind(item_id)
+			.execute(&mut *tx)
+			.await
+			.db()?;
+	if result.rows_affected() == 0 {
+		return Err(Error::NotFound);
+	}
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+// vim: ts=4
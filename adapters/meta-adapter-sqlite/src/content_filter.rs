@@ -0,0 +1,142 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Content filter database operations
+
+use crate::utils::{Db, push_patch};
+use cloudillo_types::{
+	meta_adapter::{
+		ContentFilter, ContentFilterKind, ContentFilterMode, CreateContentFilter,
+		UpdateContentFilterOptions,
+	},
+	prelude::*,
+};
+use sqlx::{Row, SqlitePool, sqlite::SqliteRow};
+
+/// Convert a `ContentFilterKind` to its CHAR(1) database representation.
+fn kind_to_db(kind: ContentFilterKind) -> &'static str {
+	match kind {
+		ContentFilterKind::Keyword => "K",
+		ContentFilterKind::Regex => "R",
+		ContentFilterKind::Hashtag => "H",
+		ContentFilterKind::Instance => "I",
+	}
+}
+
+/// Convert a `ContentFilterMode` to its CHAR(1) database representation.
+fn mode_to_db(mode: ContentFilterMode) -> &'static str {
+	match mode {
+		ContentFilterMode::Warn => "W",
+		ContentFilterMode::Hide => "H",
+		ContentFilterMode::Drop => "D",
+	}
+}
+
+fn row_to_filter(row: &SqliteRow) -> ClResult<ContentFilter> {
+	let kind = match row.try_get::<&str, _>("kind").db()? {
+		"K" => ContentFilterKind::Keyword,
+		"R" => ContentFilterKind::Regex,
+		"H" => ContentFilterKind::Hashtag,
+		"I" => ContentFilterKind::Instance,
+		other => return Err(Error::Internal(format!("Invalid content filter kind '{}'", other))),
+	};
+	let mode = match row.try_get::<&str, _>("mode").db()? {
+		"W" => ContentFilterMode::Warn,
+		"H" => ContentFilterMode::Hide,
+		"D" => ContentFilterMode::Drop,
+		other => return Err(Error::Internal(format!("Invalid content filter mode '{}'", other))),
+	};
+	Ok(ContentFilter {
+		filter_id: u64::try_from(row.try_get::<i64, _>("cf_id").db()?).unwrap_or_default(),
+		kind,
+		pattern: row.try_get("pattern").db()?,
+		mode,
+		expires_at: row.try_get::<Option<i64>, _>("expires_at").db()?.map(Timestamp),
+		created_at: row.try_get("created_at").map(Timestamp).db()?,
+	})
+}
+
+/// List the filters that have not expired, oldest first
+pub(crate) async fn list(db: &SqlitePool, tn_id: TnId) -> ClResult<Vec<ContentFilter>> {
+	let rows = sqlx::query(
+		"SELECT cf_id, kind, pattern, mode, expires_at, created_at FROM content_filters
+		 WHERE tn_id=? AND (expires_at IS NULL OR expires_at > unixepoch())
+		 ORDER BY cf_id",
+	)
+	.bind(tn_id.0)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter().map(row_to_filter).collect()
+}
+
+/// Create a filter
+pub(crate) async fn create(
+	db: &SqlitePool,
+	tn_id: TnId,
+	filter: &CreateContentFilter,
+) -> ClResult<ContentFilter> {
+	let row = sqlx::query(
+		"INSERT INTO content_filters (tn_id, kind, pattern, mode, expires_at)
+		 VALUES (?, ?, ?, ?, ?)
+		 RETURNING cf_id, kind, pattern, mode, expires_at, created_at",
+	)
+	.bind(tn_id.0)
+	.bind(kind_to_db(filter.kind))
+	.bind(&filter.pattern)
+	.bind(mode_to_db(filter.mode))
+	.bind(filter.expires_at.map(|t| t.0))
+	.fetch_one(db)
+	.await
+	.db()?;
+
+	row_to_filter(&row)
+}
+
+/// Update a filter
+pub(crate) async fn update(
+	db: &SqlitePool,
+	tn_id: TnId,
+	filter_id: u64,
+	opts: &UpdateContentFilterOptions,
+) -> ClResult<ContentFilter> {
+	let filter_id = filter_id.cast_signed();
+
+	let mut query = sqlx::QueryBuilder::new("UPDATE content_filters SET ");
+	let mut has = false;
+	has = push_patch!(query, has, "pattern", &opts.pattern);
+	has = push_patch!(query, has, "mode", &opts.mode, |v| mode_to_db(*v));
+	has = push_patch!(query, has, "expires_at", &opts.expires_at, |v| v.0);
+	if !has {
+		// Nothing to change: still answer with the row, and NotFound without one.
+		query = sqlx::QueryBuilder::new(
+			"SELECT cf_id, kind, pattern, mode, expires_at, created_at FROM content_filters",
+		);
+	}
+	query.push(" WHERE tn_id=").push_bind(tn_id.0);
+	query.push(" AND cf_id=").push_bind(filter_id);
+	if has {
+		query.push(" RETURNING cf_id, kind, pattern, mode, expires_at, created_at");
+	}
+
+	let row = query.build().fetch_optional(db).await.db()?;
+	row.map(|r| row_to_filter(&r)).ok_or(Error::NotFound)?
+}
+
+/// Delete a filter
+pub(crate) async fn delete(db: &SqlitePool, tn_id: TnId, filter_id: u64) -> ClResult<()> {
+	let result = sqlx::query("DELETE FROM content_filters WHERE tn_id=? AND cf_id=?")
+		.bind(tn_id.0)
+		.bind(filter_id.cast_signed())
+		.execute(db)
+		.await
+		.db()?;
+
+	if result.rows_affected() == 0 {
+		return Err(Error::NotFound);
+	}
+	Ok(())
+}
+
+// vim: ts=4
@@ -20,7 +20,7 @@ use sqlx::{Row, SqlitePool, sqlite::SqliteRow};
 pub async fn read(db: &SqlitePool, tn_id: TnId, content_type: &str) -> ClResult<Option<DocFormat>> {
 	let row = sqlx::query(
 		"SELECT content_type, publisher_tag, app_name, format_version, store_tp, nav_param, \
-		 search, x, updated_at \
+		 search, triggers, x, updated_at \
 		 FROM doc_formats WHERE tn_id=? AND content_type=? AND status='A'",
 	)
 	.bind(tn_id.0)
@@ -36,7 +36,7 @@ pub async fn read(db: &SqlitePool, tn_id: TnId, content_type: &str) -> ClResult<
 pub async fn list(db: &SqlitePool, tn_id: TnId) -> ClResult<Vec<DocFormat>> {
 	let rows = sqlx::query(
 		"SELECT content_type, publisher_tag, app_name, format_version, store_tp, nav_param, \
-		 search, x, updated_at \
+		 search, triggers, x, updated_at \
 		 FROM doc_formats WHERE tn_id=? AND status='A' ORDER BY content_type",
 	)
 	.bind(tn_id.0)
@@ -51,13 +51,14 @@ pub async fn list(db: &SqlitePool, tn_id: TnId) -> ClResult<Vec<DocFormat>> {
 /// claim rule — this is an unconditional upsert.
 pub async fn upsert(db: &SqlitePool, tn_id: TnId, fmt: &UpsertDocFormat<'_>) -> ClResult<()> {
 	let search = to_json(fmt.search)?;
+	let triggers = to_json(fmt.triggers)?;
 	let x = to_json(fmt.x)?;
 
 	sqlx::query(
 		"INSERT INTO doc_formats \
 		 (tn_id, content_type, publisher_tag, app_name, format_version, store_tp, nav_param, \
-		 search, x) \
-		 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) \
+		 search, triggers, x) \
+		 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
 		 ON CONFLICT(tn_id, content_type) DO UPDATE SET \
 			publisher_tag = excluded.publisher_tag, \
 			app_name = excluded.app_name, \
@@ -65,6 +66,7 @@ pub async fn upsert(db: &SqlitePool, tn_id: TnId, fmt: &UpsertDocFormat<'_>) ->
 			store_tp = excluded.store_tp, \
 			nav_param = excluded.nav_param, \
 			search = excluded.search, \
+			triggers = excluded.triggers, \
 			x = excluded.x, \
 			status = 'A'",
 	)
@@ -76,6 +78,7 @@ pub async fn upsert(db: &SqlitePool, tn_id: TnId, fmt: &UpsertDocFormat<'_>) ->
 	.bind(fmt.store_tp)
 	.bind(fmt.nav_param)
 	.bind(search)
+	.bind(triggers)
 	.bind(x)
 	.execute(db)
 	.await
@@ -118,6 +121,7 @@ fn map_row(row: &SqliteRow) -> Result<DocFormat, sqlx::Error> {
 		store_tp: row.try_get::<Option<String>, _>("store_tp")?.map(Into::into),
 		nav_param: row.try_get::<Option<String>, _>("nav_param")?.map(Into::into),
 		search: parse_json(row.try_get::<Option<String>, _>("search")?.as_deref(), "search"),
+		triggers: parse_json(row.try_get::<Option<String>, _>("triggers")?.as_deref(), "triggers"),
 		x: parse_json(row.try_get::<Option<String>, _>("x")?.as_deref(), "x"),
 		updated_at: Timestamp(row.try_get::<Option<i64>, _>("updated_at")?.unwrap_or(0)),
 	})
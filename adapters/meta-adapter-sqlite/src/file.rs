@@ -11,7 +11,7 @@ use crate::utils::{Db, collect_res, map_res, parse_str_list, push_patch};
 use cloudillo_types::meta_adapter::{
 	BrokenReason, CreateFile, DeleteFileResult, FileId, FileStatus, FileUserData, FileVariant,
 	FileView, ListFileOptions, ProfileInfo, ProfileType, ROOT_PARENT_ID, SHARE_FILE_REF_TYPE,
-	UpdateFileOptions,
+	StorageUsage, TRASH_PARENT_ID, UpdateFileOptions, VERSIONS_PARENT_ID,
 };
 use cloudillo_types::prelude::*;
 use cloudillo_types::types::AccessLevel;
@@ -95,7 +95,7 @@ pub(crate) async fn list(
 			|| matches!(opts.sort.as_deref(), Some("recent" | "modified")));
 
 	let mut query = sqlx::QueryBuilder::new(
-		"SELECT f.f_id, f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason,
+		"SELECT f.f_id, f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason, f.version_of,
 		        t.id_tag as tn_id_tag, t.name as tn_name, t.type as tn_type, t.profile_pic as tn_profile_pic,
 		        p.id_tag as owner_id_tag, p.name as owner_name, p.type as owner_type, p.profile_pic as owner_profile_pic,
 		        p2.id_tag as creator_id_tag, p2.name as creator_name, p2.type as creator_type, p2.profile_pic as creator_profile_pic",
@@ -202,16 +202,18 @@ pub(crate) async fn list(
 			query.push(" AND f.parent_id=").push_bind(parent_id.as_str());
 		}
 	} else if opts.file_id.is_none() && !opts.sweep_all {
-		// Default browse listing (no targeted fileId): exclude trashed and managed
-		// files. A by-id lookup with no parentId deliberately skips this so it
-		// returns the file wherever it lives (managed or trash included), and so
-		// does `sweep_all`, which must see a trashed file to take its derived
-		// state back out.
+		// Default browse listing (no targeted fileId): exclude trashed, managed
+		// and retained-revision files. A by-id lookup with no parentId
+		// deliberately skips this so it returns the file wherever it lives
+		// (managed, trash or an old revision included), and so does `sweep_all`,
+		// which must see a trashed file to take its derived state back out.
 		query
 			.push(" AND (f.parent_id IS NULL OR f.parent_id NOT IN (")
 			.push_bind(cloudillo_types::meta_adapter::TRASH_PARENT_ID)
 			.push(", ")
 			.push_bind(cloudillo_types::meta_adapter::MANAGED_PARENT_ID)
+			.push(", ")
+			.push_bind(cloudillo_types::meta_adapter::VERSIONS_PARENT_ID)
 			.push("))");
 	}
 	// else (fileId present, no parentId): no parent-folder predicate — return the
@@ -573,6 +575,7 @@ pub(crate) async fn list(
 			root_id: row.try_get("root_id").ok().flatten(),
 			owner,
 			owner_tag: row.try_get("owner_tag").ok().flatten(),
+			version_of: row.try_get("version_of").ok().flatten(),
 			creator,
 			preset: row.try_get("preset")?,
 			content_type: row.try_get("content_type")?,
@@ -742,6 +745,43 @@ pub(crate) async fn is_variant_referenced(
 	Ok(row.is_some())
 }
 
+/// Sum the blobs a tenant holds in its own store, each distinct `variant_id`
+/// once. A blob counts as an original if any row names it `orig`, and as trash
+/// only if every file referencing it is in the trash. Rows of tombstoned files
+/// are left out, and so are metadata-only stubs (`available = 0`), which have no
+/// blob behind them.
+pub(crate) async fn read_storage_usage(db: &SqlitePool, tn_id: TnId) -> ClResult<StorageUsage> {
+	let row = sqlx::query(
+		"SELECT count(*) AS blobs, coalesce(sum(size), 0) AS total,
+			coalesce(sum(CASE WHEN orig THEN size END), 0) AS originals,
+			coalesce(sum(CASE WHEN NOT live THEN size END), 0) AS trash
+		 FROM (
+			SELECT max(coalesce(fv.size, 0)) AS size, max(fv.variant = 'orig') AS orig,
+				max(coalesce(f.parent_id, '') != ?) AS live
+			FROM file_variants fv
+			JOIN files f ON f.tn_id = fv.tn_id AND f.f_id = fv.f_id
+			WHERE fv.tn_id = ? AND fv.available = 1 AND coalesce(fv.global, 0) = 0
+				AND fv.variant_id IS NOT NULL AND coalesce(f.status, 'A') != 'D'
+			GROUP BY fv.variant_id
+		 )",
+	)
+	.bind(TRASH_PARENT_ID)
+	.bind(tn_id.0)
+	.fetch_one(db)
+	.await
+	.db()?;
+
+	let total = row.try_get::<i64, _>("total").db()?.cast_unsigned();
+	let originals = row.try_get::<i64, _>("originals").db()?.cast_unsigned();
+	Ok(StorageUsage {
+		total,
+		originals,
+		variants: total - originals,
+		trash: row.try_get::<i64, _>("trash").db()?.cast_unsigned(),
+		blobs: row.try_get::<i64, _>("blobs").db()?.cast_unsigned(),
+	})
+}
+
 /// List available (locally present) variant names for a file by f_id
 pub(crate) async fn list_available_variants_by_fid(
 	db: &SqlitePool,
@@ -850,14 +890,25 @@ pub(crate) async fn create(
 	opts: CreateFile,
 ) -> ClResult<FileId<Box<str>>> {
 	// Only check for existing file if we have preset and orig_variant_id (normal file creation)
-	// For shared files (FSHR), these are None so we skip the dedup check
+	// For shared files (FSHR), these are None so we skip the dedup check.
+	// A file dropped through a share link (`x.dropLink`) only dedups against what the
+	// same link dropped, and nothing else against a drop: the dropper must not learn
+	// of the owner's files, and its own content id differs from theirs anyway.
 	if let (Some(preset), Some(orig_variant_id)) = (&opts.preset, &opts.orig_variant_id) {
+		let drop_link = opts
+			.x
+			.as_ref()
+			.and_then(|x| x.get("dropLink"))
+			.and_then(|l| l.as_str())
+			.unwrap_or("");
 		let file_id_exists: Option<Box<str>> = sqlx::query(
 			"SELECT min(f.file_id) FROM file_variants fv
 			JOIN files f ON f.tn_id=fv.tn_id AND f.f_id=fv.f_id AND f.preset=? AND f.file_id IS NOT NULL AND f.status != 'D'
+				AND coalesce(json_extract(f.x, '$.dropLink'), '')=?
 			WHERE fv.tn_id=? AND fv.variant_id=? AND fv.variant='orig'",
 		)
 		.bind(preset)
+		.bind(drop_link)
 		.bind(tn_id.0)
 		.bind(orig_variant_id)
 		.fetch_one(db)
@@ -907,8 +958,8 @@ pub(crate) async fn create(
 	// writer created. NULL file_id rows never conflict (SQLite treats NULLs as
 	// distinct), so pending uploads are unaffected.
 	let inserted: Option<i64> = sqlx::query_scalar(
-		"INSERT INTO files (tn_id, file_id, parent_id, root_id, status, owner_tag, creator_tag, preset, content_type, file_name, file_tp, created_at, tags, x, visibility, hidden) \
-		 VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
+		"INSERT INTO files (tn_id, file_id, parent_id, root_id, status, owner_tag, creator_tag, preset, content_type, file_name, file_tp, created_at, tags, x, visibility, hidden, version_of) \
+		 VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) \
 		 ON CONFLICT(file_id, tn_id) DO NOTHING \
 		 RETURNING f_id",
 	)
@@ -916,7 +967,7 @@ pub(crate) async fn create(
 		.bind(status).bind(owner_tag.as_deref()).bind(creator_tag.as_deref()).bind(opts.preset)
 		.bind(opts.content_type).bind(opts.file_name).bind(file_tp).bind(created_at.0)
 		.bind(opts.tags.map(|tags| tags.join(","))).bind(opts.x).bind(visibility)
-		.bind(i32::from(opts.hidden))
+		.bind(i32::from(opts.hidden)).bind(opts.version_of)
 		.fetch_optional(db).await.db()?;
 
 	if let Some(f_id) = inserted {
@@ -1205,7 +1256,7 @@ pub(crate) async fn read(
 			.parse::<i64>()
 			.map_err(|_| Error::ValidationError("invalid f_id".into()))?;
 		sqlx::query(
-			"SELECT f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason,
+			"SELECT f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason, f.version_of,
 			        t.id_tag as tn_id_tag, t.name as tn_name, t.type as tn_type, t.profile_pic as tn_profile_pic,
 			        p.id_tag as owner_id_tag, p.name as owner_name, p.type as owner_type, p.profile_pic as owner_profile_pic,
 			        p2.id_tag as creator_id_tag, p2.name as creator_name, p2.type as creator_type, p2.profile_pic as creator_profile_pic
@@ -1223,7 +1274,7 @@ pub(crate) async fn read(
 	} else {
 		// Content-addressable ID - query by file_id
 		sqlx::query(
-			"SELECT f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason,
+			"SELECT f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, f.broken_at, f.broken_reason, f.version_of,
 			        t.id_tag as tn_id_tag, t.name as tn_name, t.type as tn_type, t.profile_pic as tn_profile_pic,
 			        p.id_tag as owner_id_tag, p.name as owner_name, p.type as owner_type, p.profile_pic as owner_profile_pic,
 			        p2.id_tag as creator_id_tag, p2.name as creator_name, p2.type as creator_type, p2.profile_pic as creator_profile_pic
@@ -1292,6 +1343,7 @@ fn row_to_file_view(
 		root_id: row.try_get("root_id").ok().flatten(),
 		owner,
 		owner_tag: row.try_get("owner_tag").ok().flatten(),
+		version_of: row.try_get("version_of").ok().flatten(),
 		creator,
 		preset: row.try_get("preset").ok().flatten(),
 		content_type: row.try_get("content_type").ok().flatten(),
@@ -1330,7 +1382,7 @@ pub(crate) async fn read_with_user_data(
 	let base_sql = "SELECT f.f_id, f.file_id, f.parent_id, f.root_id, f.file_name, f.file_tp, \
 		f.created_at, f.accessed_at, f.modified_at, f.status, f.tags, f.owner_tag, \
 		f.creator_tag, f.preset, f.content_type, f.visibility, f.hidden, f.x, \
-		f.broken_at, f.broken_reason, \
+		f.broken_at, f.broken_reason, f.version_of, \
 		t.id_tag as tn_id_tag, t.name as tn_name, t.type as tn_type, t.profile_pic as tn_profile_pic, \
 		p.id_tag as owner_id_tag, p.name as owner_name, p.type as owner_type, p.profile_pic as owner_profile_pic, \
 		p2.id_tag as creator_id_tag, p2.name as creator_name, p2.type as creator_type, p2.profile_pic as creator_profile_pic, \
@@ -1710,14 +1762,54 @@ pub(crate) async fn delete(
 	.db()?
 	.rows_affected();
 
+	// The current revision of a version chain takes the retained ones with it: they are
+	// reachable only through the chain, and would otherwise wait in `VERSIONS_PARENT_ID` for
+	// good. Deleting a retained revision itself leaves the rest of its chain alone.
+	let chain =
+		sqlx::query("SELECT version_of, parent_id FROM files WHERE tn_id = ? AND file_id = ?")
+			.bind(tn_id.0)
+			.bind(file_id.as_ref())
+			.fetch_optional(&mut *tx)
+			.await
+			.db()?;
+	let version_of: Option<Box<str>> =
+		chain.as_ref().and_then(|row| row.try_get("version_of").ok().flatten());
+	let parent_id: Option<Box<str>> =
+		chain.as_ref().and_then(|row| row.try_get("parent_id").ok().flatten());
+	let mut revisions: Vec<(i64, Option<Box<str>>)> = Vec::new();
+	if let Some(version_of) = version_of
+		&& parent_id.as_deref() != Some(VERSIONS_PARENT_ID)
+	{
+		revisions = sqlx::query_as(
+			"SELECT f_id, file_id FROM files WHERE tn_id = ? AND version_of = ? AND parent_id = ?",
+		)
+		.bind(tn_id.0)
+		.bind(version_of.as_ref())
+		.bind(VERSIONS_PARENT_ID)
+		.fetch_all(&mut *tx)
+		.await
+		.db()?;
+		files_deleted += sqlx::query(
+			"UPDATE files SET status = 'D' WHERE tn_id = ? AND version_of = ? AND parent_id = ?",
+		)
+		.bind(tn_id.0)
+		.bind(version_of.as_ref())
+		.bind(VERSIONS_PARENT_ID)
+		.execute(&mut *tx)
+		.await
+		.db()?
+		.rows_affected();
+	}
+
 	// Root first, so the caller's cache eviction order matches the delete order. Only resolvable
 	// content ids: the caller keys its folder cache on them, and nothing can reference a NULL
 	// `file_id` anyway. A self-rooted root would appear twice, so drop the duplicate.
-	let mut file_ids: Vec<Box<str>> = Vec::with_capacity(children.len() + 1);
+	let mut file_ids: Vec<Box<str>> = Vec::with_capacity(children.len() + revisions.len() + 1);
 	file_ids.push(file_id.clone());
 	file_ids.extend(
 		children
 			.into_iter()
+			.chain(revisions)
 			.filter_map(|(_f_id, id)| id)
 			.filter(|id| id.as_ref() != file_id.as_ref()),
 	);
@@ -1798,6 +1890,7 @@ mod tests {
 			visibility: None,
 			hidden: false,
 			status: Some(FileStatus::Active),
+			version_of: None,
 		}
 	}
 
@@ -0,0 +1,246 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Version chains of BLOB files.
+//!
+//! A BLOB's `file_id` is the hash of its content, so a new revision is always a new
+//! row. What makes the rows one logical file is `files.version_of`: every revision
+//! names the chain's first one. Exactly one revision is current and sits in a real
+//! folder; the retained ones sit in [`VERSIONS_PARENT_ID`], and a pruned one in
+//! [`MANAGED_PARENT_ID`] until the file GC takes it.
+//!
+//! Which revision to promote, and whether the caller may, is the handler's call.
+
+use crate::utils::Db;
+use cloudillo_types::meta_adapter::{
+	FileVersion, MANAGED_PARENT_ID, SHARE_FILE_REF_TYPE, VERSIONS_PARENT_ID,
+};
+use cloudillo_types::prelude::*;
+use sqlx::{Row, SqlitePool};
+
+/// Make `file_id` the current revision of its chain. See
+/// [`cloudillo_types::meta_adapter::MetaAdapter::promote_file_version`].
+pub(crate) async fn promote(
+	db: &SqlitePool,
+	tn_id: TnId,
+	file_id: &str,
+) -> ClResult<Option<Box<str>>> {
+	let mut tx = db.begin().await.db()?;
+
+	let target = sqlx::query(
+		"SELECT f_id, version_of, parent_id FROM files \
+		 WHERE tn_id = ? AND file_id = ? AND status = 'A'",
+	)
+	.bind(tn_id.0)
+	.bind(file_id)
+	.fetch_optional(&mut *tx)
+	.await
+	.db()?
+	.ok_or(Error::NotFound)?;
+	let f_id: i64 = target.try_get("f_id").db()?;
+	let version_of: Option<Box<str>> = target.try_get("version_of").db()?;
+	let parent_id: Option<Box<str>> = target.try_get("parent_id").db()?;
+	let Some(version_of) = version_of else {
+		return Err(Error::ValidationError("file is not part of a version chain".into()));
+	};
+	if !matches!(parent_id.as_deref(), Some(VERSIONS_PARENT_ID | MANAGED_PARENT_ID)) {
+		return Ok(None);
+	}
+
+	// Until its first promotion the chain's first revision is a plain file.
+	sqlx::query(
+		"UPDATE files SET version_of = ?1 WHERE tn_id = ?2 AND file_id = ?1 AND version_of IS NULL",
+	)
+	.bind(version_of.as_ref())
+	.bind(tn_id.0)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+
+	// The current revision is the one in a real folder — the trash counts, so a
+	// trashed file restored later still comes back with its newest content.
+	let current = sqlx::query(
+		"SELECT f_id, file_id, parent_id, visibility, tags FROM files \
+		 WHERE tn_id = ? AND version_of = ? AND f_id <> ? AND status != 'D' \
+		   AND (parent_id IS NULL OR parent_id NOT IN (?, ?)) \
+		 ORDER BY f_id DESC LIMIT 1",
+	)
+	.bind(tn_id.0)
+	.bind(version_of.as_ref())
+	.bind(f_id)
+	.bind(VERSIONS_PARENT_ID)
+	.bind(MANAGED_PARENT_ID)
+	.fetch_optional(&mut *tx)
+	.await
+	.db()?;
+
+	let Some(current) = current else {
+		// Nothing to take the place of: the revision lands at the root as it is.
+		sqlx::query("UPDATE files SET parent_id = NULL WHERE tn_id = ? AND f_id = ?")
+			.bind(tn_id.0)
+			.bind(f_id)
+			.execute(&mut *tx)
+			.await
+			.db()?;
+		tx.commit().await.db()?;
+		return Ok(None);
+	};
+	let current_f_id: i64 = current.try_get("f_id").db()?;
+	let current_id: Option<Box<str>> = current.try_get("file_id").db()?;
+	let current_parent: Option<Box<str>> = current.try_get("parent_id").db()?;
+	let visibility: Option<Box<str>> = current.try_get("visibility").db()?;
+	let tags: Option<Box<str>> = current.try_get("tags").db()?;
+
+	sqlx::query(
+		"UPDATE files SET parent_id = ?, visibility = ?, tags = ? WHERE tn_id = ? AND f_id = ?",
+	)
+	.bind(current_parent.as_deref())
+	.bind(visibility.as_deref())
+	.bind(tags.as_deref())
+	.bind(tn_id.0)
+	.bind(f_id)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	sqlx::query("UPDATE files SET parent_id = ? WHERE tn_id = ? AND f_id = ?")
+		.bind(VERSIONS_PARENT_ID)
+		.bind(tn_id.0)
+		.bind(current_f_id)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+
+	if let Some(current_id) = &current_id {
+		repoint_shares(&mut tx, tn_id, current_id, file_id).await?;
+	}
+
+	tx.commit().await.db()?;
+	Ok(current_id)
+}
+
+/// Move every share naming `from` over to `to`: the share links, and `share_entries`
+/// on both sides. A grant `to` already holds wins over the one being moved, so the
+/// UNIQUE index never trips; the leftovers are dropped.
+async fn repoint_shares(
+	tx: &mut sqlx::SqliteConnection,
+	tn_id: TnId,
+	from: &str,
+	to: &str,
+) -> ClResult<()> {
+	sqlx::query("UPDATE refs SET resource_id = ? WHERE tn_id = ? AND resource_id = ? AND type = ?")
+		.bind(to)
+		.bind(tn_id.0)
+		.bind(from)
+		.bind(SHARE_FILE_REF_TYPE)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+
+	sqlx::query(
+		"UPDATE OR IGNORE share_entries SET resource_id = ? \
+		 WHERE tn_id = ? AND resource_type = 'F' AND resource_id = ?",
+	)
+	.bind(to)
+	.bind(tn_id.0)
+	.bind(from)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	sqlx::query(
+		"UPDATE OR IGNORE share_entries SET subject_id = ? \
+		 WHERE tn_id = ? AND subject_type = 'F' AND subject_id = ?",
+	)
+	.bind(to)
+	.bind(tn_id.0)
+	.bind(from)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	sqlx::query(
+		"DELETE FROM share_entries WHERE tn_id = ? \
+		 AND ((resource_type = 'F' AND resource_id = ?) OR (subject_type = 'F' AND subject_id = ?))",
+	)
+	.bind(tn_id.0)
+	.bind(from)
+	.bind(from)
+	.execute(&mut *tx)
+	.await
+	.db()?;
+	Ok(())
+}
+
+/// The finalized, unpruned revisions of a chain, newest first.
+pub(crate) async fn list(
+	db: &SqlitePool,
+	tn_id: TnId,
+	version_of: &str,
+) -> ClResult<Vec<FileVersion>> {
+	let rows = sqlx::query(
+		"SELECT f.file_id, f.file_name, f.content_type, f.creator_tag, f.created_at, f.parent_id, \
+		        (SELECT max(v.size) FROM file_variants v WHERE v.tn_id = f.tn_id AND v.f_id = f.f_id) AS size \
+		 FROM files f \
+		 WHERE f.tn_id = ?1 AND (f.version_of = ?2 OR f.file_id = ?2) AND f.status = 'A' \
+		   AND (f.parent_id IS NULL OR f.parent_id <> ?3) \
+		 ORDER BY f.f_id DESC",
+	)
+	.bind(tn_id.0)
+	.bind(version_of)
+	.bind(MANAGED_PARENT_ID)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter()
+		.map(|row| {
+			let parent_id: Option<Box<str>> = row.try_get("parent_id").db()?;
+			let size: Option<i64> = row.try_get("size").db()?;
+			Ok(FileVersion {
+				file_id: row.try_get("file_id").db()?,
+				file_name: row.try_get("file_name").db()?,
+				content_type: row.try_get("content_type").db()?,
+				creator_tag: row.try_get("creator_tag").db()?,
+				size: size.and_then(|s| u64::try_from(s).ok()),
+				created_at: Timestamp(row.try_get("created_at").db()?),
+				current: parent_id.as_deref() != Some(VERSIONS_PARENT_ID),
+			})
+		})
+		.collect()
+}
+
+/// Move all but the newest `keep` retained revisions into the managed folder.
+pub(crate) async fn prune(
+	db: &SqlitePool,
+	tn_id: TnId,
+	version_of: &str,
+	keep: u32,
+) -> ClResult<Vec<Box<str>>> {
+	let mut tx = db.begin().await.db()?;
+	let pruned: Vec<(i64, Box<str>)> = sqlx::query_as(
+		"SELECT f_id, file_id FROM files \
+		 WHERE tn_id = ? AND version_of = ? AND parent_id = ? AND status = 'A' \
+		   AND file_id IS NOT NULL \
+		 ORDER BY f_id DESC LIMIT -1 OFFSET ?",
+	)
+	.bind(tn_id.0)
+	.bind(version_of)
+	.bind(VERSIONS_PARENT_ID)
+	.bind(i64::from(keep))
+	.fetch_all(&mut *tx)
+	.await
+	.db()?;
+
+	for (f_id, _) in &pruned {
+		sqlx::query("UPDATE files SET parent_id = ? WHERE tn_id = ? AND f_id = ?")
+			.bind(MANAGED_PARENT_ID)
+			.bind(tn_id.0)
+			.bind(f_id)
+			.execute(&mut *tx)
+			.await
+			.db()?;
+	}
+	tx.commit().await.db()?;
+
+	Ok(pruned.into_iter().map(|(_, file_id)| file_id).collect())
+}
+
+// vim: ts=4
@@ -0,0 +1,186 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Hashtag index, followed hashtags and trending lists
+
+use crate::utils::Db;
+use cloudillo_types::{
+	meta_adapter::{FollowedHashtag, HashtagCount, TrendingHashtag},
+	prelude::*,
+};
+use sqlx::{Row, SqlitePool};
+
+/// Replace the hashtags indexed for an action
+pub(crate) async fn update_action(
+	db: &SqlitePool,
+	tn_id: TnId,
+	action_id: &str,
+	tags: &[String],
+) -> ClResult<()> {
+	let mut tx = db.begin().await.db()?;
+	sqlx::query("DELETE FROM action_hashtags WHERE tn_id=? AND action_id=?")
+		.bind(tn_id.0)
+		.bind(action_id)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	for tag in tags {
+		sqlx::query(
+			"INSERT OR IGNORE INTO action_hashtags (tn_id, action_id, tag) VALUES (?, ?, ?)",
+		)
+		.bind(tn_id.0)
+		.bind(action_id)
+		.bind(tag)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	}
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+/// Count hashtag use by public, active actions created in `[since, until)`
+pub(crate) async fn count(
+	db: &SqlitePool,
+	tn_id: TnId,
+	since: Timestamp,
+	until: Timestamp,
+	limit: u32,
+) -> ClResult<Vec<HashtagCount>> {
+	let rows = sqlx::query(
+		"SELECT h.tag, count(*) AS uses, count(DISTINCT a.issuer_tag) AS accounts
+		 FROM action_hashtags h
+		 JOIN actions a ON a.tn_id=h.tn_id AND a.action_id=h.action_id
+		 WHERE h.tn_id=? AND a.visibility='P' AND coalesce(a.status, 'A')='A'
+		   AND a.created_at>=? AND a.created_at<?
+		 GROUP BY h.tag
+		 ORDER BY accounts DESC, uses DESC, h.tag
+		 LIMIT ?",
+	)
+	.bind(tn_id.0)
+	.bind(since.0)
+	.bind(until.0)
+	.bind(limit)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter()
+		.map(|row| {
+			Ok(HashtagCount {
+				tag: row.try_get("tag").db()?,
+				uses: row.try_get("uses").db()?,
+				accounts: row.try_get("accounts").db()?,
+			})
+		})
+		.collect()
+}
+
+/// Replace the stored trending list of a window
+pub(crate) async fn replace_trending(
+	db: &SqlitePool,
+	tn_id: TnId,
+	window: &str,
+	trending: &[TrendingHashtag],
+) -> ClResult<()> {
+	let mut tx = db.begin().await.db()?;
+	sqlx::query("DELETE FROM trending_hashtags WHERE tn_id=? AND period=?")
+		.bind(tn_id.0)
+		.bind(window)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	for entry in trending {
+		sqlx::query(
+			"INSERT INTO trending_hashtags (tn_id, period, tag, uses, accounts, score)
+			 VALUES (?, ?, ?, ?, ?, ?)",
+		)
+		.bind(tn_id.0)
+		.bind(window)
+		.bind(&entry.tag)
+		.bind(entry.uses)
+		.bind(entry.accounts)
+		.bind(entry.score)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	}
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+/// List the stored trending list of a window, highest score first
+pub(crate) async fn list_trending(
+	db: &SqlitePool,
+	tn_id: TnId,
+	window: &str,
+) -> ClResult<Vec<TrendingHashtag>> {
+	let rows = sqlx::query(
+		"SELECT tag, uses, accounts, score FROM trending_hashtags
+		 WHERE tn_id=? AND period=?
+		 ORDER BY score DESC, tag",
+	)
+	.bind(tn_id.0)
+	.bind(window)
+	.fetch_all(db)
+	.await
+	.db()?;
+
+	rows.iter()
+		.map(|row| {
+			Ok(TrendingHashtag {
+				tag: row.try_get("tag").db()?,
+				uses: row.try_get("uses").db()?,
+				accounts: row.try_get("accounts").db()?,
+				score: row.try_get("score").db()?,
+			})
+		})
+		.collect()
+}
+
+/// List the followed hashtags, alphabetically
+pub(crate) async fn list_followed(db: &SqlitePool, tn_id: TnId) -> ClResult<Vec<FollowedHashtag>> {
+	let rows =
+		sqlx::query("SELECT tag, created_at FROM followed_hashtags WHERE tn_id=? ORDER BY tag")
+			.bind(tn_id.0)
+			.fetch_all(db)
+			.await
+			.db()?;
+
+	rows.iter()
+		.map(|row| {
+			Ok(FollowedHashtag {
+				tag: row.try_get("tag").db()?,
+				created_at: row.try_get("created_at").map(Timestamp).db()?,
+			})
+		})
+		.collect()
+}
+
+/// Follow a hashtag
+pub(crate) async fn follow(db: &SqlitePool, tn_id: TnId, tag: &str) -> ClResult<()> {
+	sqlx::query("INSERT OR IGNORE INTO followed_hashtags (tn_id, tag) VALUES (?, ?)")
+		.bind(tn_id.0)
+		.bind(tag)
+		.execute(db)
+		.await
+		.db()?;
+	Ok(())
+}
+
+/// Unfollow a hashtag
+pub(crate) async fn unfollow(db: &SqlitePool, tn_id: TnId, tag: &str) -> ClResult<()> {
+	let result = sqlx::query("DELETE FROM followed_hashtags WHERE tn_id=? AND tag=?")
+		.bind(tn_id.0)
+		.bind(tag)
+		.execute(db)
+		.await
+		.db()?;
+
+	if result.rows_affected() == 0 {
+		return Err(Error::NotFound);
+	}
+	Ok(())
+}
+
+// vim: ts=4
@@ -4,13 +4,20 @@
 use std::{path::Path, sync::Arc};
 
 mod action;
+mod backup;
 mod calendar;
+mod collection;
 mod contact;
+mod content_filter;
 mod doc_format;
 mod file;
 mod file_user_data;
+mod file_version;
+mod hashtag;
 mod installed_app;
 mod maintenance;
+mod mention;
+mod moderation;
 mod profile;
 mod push;
 mod reference;
@@ -32,21 +39,26 @@ use tokio::fs;
 
 use cloudillo_types::{
 	meta_adapter::{
-		Action, ActionData, ActionId, ActionView, AddressBook, Calendar, CalendarObject,
-		CalendarObjectExtracted, CalendarObjectSyncEntry, CalendarObjectView, CalendarObjectWrite,
-		Contact, ContactExtracted, ContactSyncEntry, ContactView, CreateCalendarData, CreateFile,
-		CreateRefOptions, CreateShareEntry, DeleteFileResult, DocFormat, FileId, FileUserData,
-		FileVariant, FileView, FinalizeActionOptions, InstallApp, InstalledApp, ListActionOptions,
-		ListCalendarObjectOptions, ListContactOptions, ListFileOptions, ListProfileOptions,
-		ListRefsOptions, ListTaskOptions, ListTenantsMetaOptions, MetaAdapter, Profile,
-		ProfileData, PublicProfileRow, PublishSiteDoc, PushSubscription, PushSubscriptionData,
-		RefData, SearchObject, SearchOptions, SearchPart, SearchRow, ShareEntry, Site, SiteDoc,
-		SpaceReport, Task, TaskPatch, Tenant, TenantListMeta, UpdateActionDataOptions,
-		UpdateAddressBookData, UpdateCalendarData, UpdateFileOptions, UpdateRefOptions,
-		UpdateShareEntryOptions, UpdateTenantData, UpsertDocFormat, UpsertProfileFields,
-		UpsertResult, UpsertSite,
+		Action, ActionData, ActionId, ActionRevision, ActionView, AddressBook, Calendar,
+		CalendarObject, CalendarObjectExtracted, CalendarObjectSyncEntry, CalendarObjectView,
+		CalendarObjectWrite, Collection, Contact, ContactExtracted, ContactSyncEntry, ContactView,
+		ContentFilter, CreateCalendarData, CreateCollection, CreateContentFilter, CreateFile,
+		CreateModerationEntry, CreateRefOptions, CreateShareEntry, DeleteFileResult, DocFormat,
+		FileId, FileUserData, FileVariant, FileVersion, FileView, FinalizeActionOptions,
+		FollowedHashtag, HashtagCount, InstallApp, InstalledApp, InteractionCount,
+		ListActionOptions, ListCalendarObjectOptions, ListContactOptions, ListFileOptions,
+		ListModerationLogOptions, ListProfileOptions, ListRefsOptions, ListTaskOptions,
+		ListTenantsMetaOptions, MetaAdapter, ModerationEntry, Profile, ProfileData,
+		PublicProfileRow, PublishSiteDoc, PushSubscription, PushSubscriptionData, RefData,
+		SaveItem, SavedItem, SavedItemType, SearchObject, SearchOptions, SearchPart, SearchRow,
+		ShareEntry, Site, SiteDoc, SpaceReport, StorageUsage, Task, TaskPatch, Tenant,
+		TenantListMeta, TrendingHashtag, UpdateActionDataOptions, UpdateAddressBookData,
+		UpdateCalendarData, UpdateCollectionOptions, UpdateContentFilterOptions, UpdateFileOptions,
+		UpdateRefOptions, UpdateSavedItemOptions, UpdateShareEntryOptions, UpdateTenantData,
+		UpsertDocFormat, UpsertProfileFields, UpsertResult, UpsertSite,
 	},
 	prelude::*,
+	types::TableDump,
 	worker::WorkerPool,
 };
 
@@ -54,7 +66,6 @@ use cloudillo_types::{
 pub struct MetaAdapterSqlite {
 	db: SqlitePool,
 	dbr: SqlitePool,
-	#[allow(dead_code)]
 	worker: Arc<WorkerPool>,
 }
 
@@ -128,6 +139,14 @@ impl MetaAdapter for MetaAdapterSqlite {
 		tenant::delete(&self.db, tn_id).await
 	}
 
+	async fn export_tenant(&self, tn_id: TnId) -> ClResult<Vec<TableDump>> {
+		backup::export(&self.dbr, tn_id).await
+	}
+
+	async fn import_tenant(&self, tn_id: TnId, tables: &[TableDump]) -> ClResult<()> {
+		backup::import(&self.db, tn_id, tables).await
+	}
+
 	async fn list_tenants(&self, opts: &ListTenantsMetaOptions) -> ClResult<Vec<TenantListMeta>> {
 		tenant::list(&self.dbr, opts).await
 	}
@@ -245,6 +264,15 @@ impl MetaAdapter for MetaAdapterSqlite {
 		action::set_read_marker(&self.db, tn_id, scope, key, position).await
 	}
 
+	async fn count_interactions(
+		&self,
+		tn_id: TnId,
+		reader_tag: &str,
+		since: Timestamp,
+	) -> ClResult<Vec<InteractionCount>> {
+		action::count_interactions(&self.dbr, tn_id, reader_tag, since).await
+	}
+
 	async fn auto_track_action(&self, tn_id: TnId, action_id: &str) -> ClResult<()> {
 		action::auto_track(&self.db, tn_id, action_id).await
 	}
@@ -311,6 +339,25 @@ impl MetaAdapter for MetaAdapterSqlite {
 		action::update_data(&self.db, tn_id, action_id, opts).await
 	}
 
+	async fn apply_action_edit(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		edit_id: &str,
+		content: Option<&str>,
+		edited_at: Timestamp,
+	) -> ClResult<bool> {
+		action::apply_edit(&self.db, tn_id, action_id, edit_id, content, edited_at).await
+	}
+
+	async fn list_action_revisions(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+	) -> ClResult<Vec<ActionRevision>> {
+		action::list_revisions(&self.dbr, tn_id, action_id).await
+	}
+
 	async fn get_related_action_tokens(
 		&self,
 		tn_id: TnId,
@@ -349,6 +396,10 @@ impl MetaAdapter for MetaAdapterSqlite {
 		file::is_variant_referenced(&self.dbr, tn_id, variant_id).await
 	}
 
+	async fn read_storage_usage(&self, tn_id: TnId) -> ClResult<StorageUsage> {
+		file::read_storage_usage(&self.dbr, tn_id).await
+	}
+
 	async fn read_file_variant(
 		&self,
 		tn_id: TnId,
@@ -402,6 +453,27 @@ impl MetaAdapter for MetaAdapterSqlite {
 		file::hard_delete_file(&self.db, tn_id, f_id).await
 	}
 
+	async fn promote_file_version(&self, tn_id: TnId, file_id: &str) -> ClResult<Option<Box<str>>> {
+		file_version::promote(&self.db, tn_id, file_id).await
+	}
+
+	async fn list_file_versions(
+		&self,
+		tn_id: TnId,
+		version_of: &str,
+	) -> ClResult<Vec<FileVersion>> {
+		file_version::list(&self.dbr, tn_id, version_of).await
+	}
+
+	async fn prune_file_versions(
+		&self,
+		tn_id: TnId,
+		version_of: &str,
+		keep: u32,
+	) -> ClResult<Vec<Box<str>>> {
+		file_version::prune(&self.db, tn_id, version_of, keep).await
+	}
+
 	// Task scheduler
 	//****************
 	async fn list_tasks(&self, opts: ListTaskOptions) -> ClResult<Vec<Task>> {
@@ -515,7 +587,7 @@ impl MetaAdapter for MetaAdapterSqlite {
 		ref_id: &str,
 		opts: &CreateRefOptions,
 	) -> ClResult<RefData> {
-		reference::create(&self.db, tn_id, ref_id, opts).await
+		reference::create(&self.db, &self.worker, tn_id, ref_id, opts).await
 	}
 
 	async fn delete_ref(&self, tn_id: TnId, ref_id: &str) -> ClResult<()> {
@@ -528,7 +600,7 @@ impl MetaAdapter for MetaAdapterSqlite {
 		ref_id: &str,
 		opts: &UpdateRefOptions,
 	) -> ClResult<RefData> {
-		reference::update(&self.db, tn_id, ref_id, opts).await
+		reference::update(&self.db, &self.worker, tn_id, ref_id, opts).await
 	}
 
 	async fn use_ref(
@@ -547,6 +619,10 @@ impl MetaAdapter for MetaAdapterSqlite {
 		reference::validate_ref(&self.dbr, ref_id, expected_types).await
 	}
 
+	async fn check_ref_password(&self, ref_id: &str, password: &str) -> ClResult<()> {
+		reference::check_password(&self.dbr, &self.worker, ref_id, password).await
+	}
+
 	// Tag Management
 	//***************
 
@@ -641,6 +717,193 @@ impl MetaAdapter for MetaAdapterSqlite {
 		push::delete(&self.db, tn_id, subscription_id).await
 	}
 
+	// Moderation audit trail
+	async fn create_moderation_entry(
+		&self,
+		tn_id: TnId,
+		entry: &CreateModerationEntry<'_>,
+	) -> ClResult<u64> {
+		moderation::create(&self.db, tn_id, entry).await
+	}
+
+	async fn list_moderation_log(
+		&self,
+		tn_id: TnId,
+		opts: &ListModerationLogOptions,
+	) -> ClResult<Vec<ModerationEntry>> {
+		moderation::list(&self.dbr, tn_id, opts).await
+	}
+
+	// Content filters
+	async fn list_content_filters(&self, tn_id: TnId) -> ClResult<Vec<ContentFilter>> {
+		content_filter::list(&self.dbr, tn_id).await
+	}
+
+	async fn create_content_filter(
+		&self,
+		tn_id: TnId,
+		filter: &CreateContentFilter,
+	) -> ClResult<ContentFilter> {
+		content_filter::create(&self.db, tn_id, filter).await
+	}
+
+	async fn update_content_filter(
+		&self,
+		tn_id: TnId,
+		filter_id: u64,
+		opts: &UpdateContentFilterOptions,
+	) -> ClResult<ContentFilter> {
+		content_filter::update(&self.db, tn_id, filter_id, opts).await
+	}
+
+	async fn delete_content_filter(&self, tn_id: TnId, filter_id: u64) -> ClResult<()> {
+		content_filter::delete(&self.db, tn_id, filter_id).await
+	}
+
+	// Hashtags
+	async fn update_action_hashtags(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		tags: &[String],
+	) -> ClResult<()> {
+		hashtag::update_action(&self.db, tn_id, action_id, tags).await
+	}
+
+	async fn count_hashtags(
+		&self,
+		tn_id: TnId,
+		since: Timestamp,
+		until: Timestamp,
+		limit: u32,
+	) -> ClResult<Vec<HashtagCount>> {
+		hashtag::count(&self.dbr, tn_id, since, until, limit).await
+	}
+
+	async fn replace_trending_hashtags(
+		&self,
+		tn_id: TnId,
+		window: &str,
+		trending: &[TrendingHashtag],
+	) -> ClResult<()> {
+		hashtag::replace_trending(&self.db, tn_id, window, trending).await
+	}
+
+	async fn list_trending_hashtags(
+		&self,
+		tn_id: TnId,
+		window: &str,
+	) -> ClResult<Vec<TrendingHashtag>> {
+		hashtag::list_trending(&self.dbr, tn_id, window).await
+	}
+
+	async fn list_followed_hashtags(&self, tn_id: TnId) -> ClResult<Vec<FollowedHashtag>> {
+		hashtag::list_followed(&self.dbr, tn_id).await
+	}
+
+	async fn follow_hashtag(&self, tn_id: TnId, tag: &str) -> ClResult<()> {
+		hashtag::follow(&self.db, tn_id, tag).await
+	}
+
+	async fn unfollow_hashtag(&self, tn_id: TnId, tag: &str) -> ClResult<()> {
+		hashtag::unfollow(&self.db, tn_id, tag).await
+	}
+
+	// Mentions
+	async fn update_action_mentions(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		mentions: &[String],
+	) -> ClResult<()> {
+		mention::update_action(&self.db, tn_id, action_id, mentions).await
+	}
+
+	// Collections
+	async fn list_collections(&self, tn_id: TnId, owner_tag: &str) -> ClResult<Vec<Collection>> {
+		collection::list(&self.dbr, tn_id, owner_tag).await
+	}
+
+	async fn read_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<Collection> {
+		collection::read(&self.dbr, tn_id, owner_tag, collection_id).await
+	}
+
+	async fn create_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection: &CreateCollection,
+	) -> ClResult<Collection> {
+		collection::create(&self.db, tn_id, owner_tag, collection).await
+	}
+
+	async fn update_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		opts: &UpdateCollectionOptions,
+	) -> ClResult<Collection> {
+		collection::update(&self.db, tn_id, owner_tag, collection_id, opts).await
+	}
+
+	async fn delete_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<()> {
+		collection::delete(&self.db, tn_id, owner_tag, collection_id).await
+	}
+
+	async fn list_saved_items(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<Vec<SavedItem>> {
+		collection::list_items(&self.dbr, tn_id, owner_tag, collection_id).await
+	}
+
+	async fn save_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item: &SaveItem,
+	) -> ClResult<SavedItem> {
+		collection::save_item(&self.db, tn_id, owner_tag, collection_id, item).await
+	}
+
+	async fn update_saved_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item_type: SavedItemType,
+		item_id: &str,
+		opts: &UpdateSavedItemOptions,
+	) -> ClResult<SavedItem> {
+		collection::update_item(&self.db, tn_id, owner_tag, collection_id, item_type, item_id, opts)
+			.await
+	}
+
+	async fn remove_saved_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item_type: SavedItemType,
+		item_id: &str,
+	) -> ClResult<()> {
+		collection::remove_item(&self.db, tn_id, owner_tag, collection_id, item_type, item_id).await
+	}
+
 	// Share Entry Management
 	//***********************
 
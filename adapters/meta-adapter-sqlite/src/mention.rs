@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Mention index
+
+use crate::utils::Db;
+use cloudillo_types::prelude::*;
+use sqlx::SqlitePool;
+
+/// Replace the mentions indexed for an action
+pub(crate) async fn update_action(
+	db: &SqlitePool,
+	tn_id: TnId,
+	action_id: &str,
+	mentions: &[String],
+) -> ClResult<()> {
+	let mut tx = db.begin().await.db()?;
+	sqlx::query("DELETE FROM action_mentions WHERE tn_id=? AND action_id=?")
+		.bind(tn_id.0)
+		.bind(action_id)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	for id_tag in mentions {
+		sqlx::query(
+			"INSERT OR IGNORE INTO action_mentions (tn_id, action_id, id_tag) VALUES (?, ?, ?)",
+		)
+		.bind(tn_id.0)
+		.bind(action_id)
+		.bind(id_tag)
+		.execute(&mut *tx)
+		.await
+		.db()?;
+	}
+	tx.commit().await.db()?;
+	Ok(())
+}
+
+// vim: ts=4
@@ -0,0 +1,86 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Moderation audit trail database operations
+
+use crate::utils::Db;
+use cloudillo_types::{
+	meta_adapter::{CreateModerationEntry, ListModerationLogOptions, ModerationEntry},
+	prelude::*,
+};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+
+/// Page size when the caller names none, and the most a caller may ask for.
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// Append a decision to the audit trail
+pub(crate) async fn create(
+	db: &SqlitePool,
+	tn_id: TnId,
+	entry: &CreateModerationEntry<'_>,
+) -> ClResult<u64> {
+	let reports = serde_json::to_string(entry.reports)
+		.map_err(|e| Error::Internal(format!("Failed to serialize report ids: {}", e)))?;
+
+	let result = sqlx::query(
+		"INSERT INTO moderation_log (tn_id, decision, subject, target_tag, moderator_tag, reports, note)
+		 VALUES (?, ?, ?, ?, ?, ?, ?)",
+	)
+	.bind(tn_id.0)
+	.bind(entry.decision)
+	.bind(entry.subject)
+	.bind(entry.target_id_tag)
+	.bind(entry.moderator_id_tag)
+	.bind(reports)
+	.bind(entry.note)
+	.execute(db)
+	.await
+	.db()?;
+
+	Ok(u64::try_from(result.last_insert_rowid()).unwrap_or_default())
+}
+
+/// List the audit trail, newest first
+pub(crate) async fn list(
+	db: &SqlitePool,
+	tn_id: TnId,
+	opts: &ListModerationLogOptions,
+) -> ClResult<Vec<ModerationEntry>> {
+	let mut query: QueryBuilder<Sqlite> = QueryBuilder::new(
+		"SELECT ml_id, decision, subject, target_tag, moderator_tag, reports, note, created_at
+		 FROM moderation_log WHERE tn_id=",
+	);
+	query.push_bind(tn_id.0);
+	if let Some(subject) = &opts.subject {
+		query.push(" AND subject=").push_bind(subject.as_str());
+	}
+	if let Some(target) = &opts.target_id_tag {
+		query.push(" AND target_tag=").push_bind(target.as_str());
+	}
+	if let Some(before) = opts.before {
+		query.push(" AND ml_id<").push_bind(before.cast_signed());
+	}
+	let limit = opts.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+	query.push(" ORDER BY ml_id DESC LIMIT ").push_bind(i64::from(limit));
+
+	let rows = query.build().fetch_all(db).await.db()?;
+
+	rows.iter()
+		.map(|row| {
+			let reports: Option<String> = row.try_get("reports").db()?;
+			Ok(ModerationEntry {
+				entry_id: u64::try_from(row.try_get::<i64, _>("ml_id").db()?).unwrap_or_default(),
+				decision: row.try_get("decision").db()?,
+				subject: row.try_get("subject").db()?,
+				target_id_tag: row.try_get("target_tag").db()?,
+				moderator_id_tag: row.try_get("moderator_tag").db()?,
+				reports: reports.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+				note: row.try_get("note").db()?,
+				created_at: row.try_get("created_at").map(Timestamp).db()?,
+			})
+		})
+		.collect()
+}
+
+// vim: ts=4
@@ -4,19 +4,43 @@
 //! Reference/bookmark management
 //!
 //! Handles named references or bookmarks that can be used to mark important resources.
+//!
+//! Share link passwords are hashed here with bcrypt, on the worker pool like the auth
+//! adapter's tenant passwords; the hash is never returned, only whether one is set.
 
 use sqlx::{Row, SqlitePool};
 
 use cloudillo_types::meta_adapter::{CreateRefOptions, ListRefsOptions, RefData, UpdateRefOptions};
 use cloudillo_types::prelude::*;
+use cloudillo_types::worker::WorkerPool;
 
 use crate::utils::{Db, push_patch};
 
+const BCRYPT_COST: u32 = 10;
+
+/// Columns every ref read returns, in `row_to_ref_data`'s vocabulary.
+const REF_COLUMNS: &str = "ref_id, type, description, created_at, expires_at, count, \
+	resource_id, access_level, params, link_mode, password_hash IS NOT NULL AS password_protected";
+
+async fn hash_password(worker: &WorkerPool, password: &str) -> ClResult<Box<str>> {
+	let password = password.to_string();
+	worker
+		.try_run_immed(move || {
+			bcrypt::hash(password, BCRYPT_COST)
+				.map(Into::into)
+				.map_err(|_| Error::Internal("password hashing failed".into()))
+		})
+		.await
+}
+
+fn first_char(s: Option<String>) -> Option<char> {
+	s.and_then(|s| s.chars().next())
+}
+
 fn row_to_ref_data(row: &sqlx::sqlite::SqliteRow) -> RefData {
 	let created_at: i64 = row.get("created_at");
 	let expires_at: Option<i64> = row.get("expires_at");
 	let count: Option<i32> = row.get("count");
-	let access_level: Option<String> = row.get("access_level");
 
 	RefData {
 		ref_id: row.get("ref_id"),
@@ -26,8 +50,10 @@ fn row_to_ref_data(row: &sqlx::sqlite::SqliteRow) -> RefData {
 		expires_at: expires_at.map(Timestamp),
 		count: count.and_then(|c| u32::try_from(c).ok()),
 		resource_id: row.get("resource_id"),
-		access_level: access_level.and_then(|s| s.chars().next()),
+		access_level: first_char(row.get("access_level")),
 		params: row.get("params"),
+		link_mode: first_char(row.get("link_mode")),
+		password_protected: row.get("password_protected"),
 	}
 }
 
@@ -37,9 +63,8 @@ pub(crate) async fn list(
 	tn_id: TnId,
 	opts: &ListRefsOptions,
 ) -> ClResult<Vec<RefData>> {
-	let mut query = sqlx::QueryBuilder::new(
-		"SELECT ref_id, type, description, created_at, expires_at, count, resource_id, access_level, params FROM refs WHERE tn_id = ",
-	);
+	let mut query =
+		sqlx::QueryBuilder::new(format!("SELECT {REF_COLUMNS} FROM refs WHERE tn_id = "));
 	query.push_bind(tn_id.0);
 
 	if let Some(ref typ) = opts.typ {
@@ -80,10 +105,9 @@ pub(crate) async fn list(
 
 /// Get a single reference by ID
 pub(crate) async fn get(db: &SqlitePool, tn_id: TnId, ref_id: &str) -> ClResult<Option<RefData>> {
-	let row = sqlx::query(
-		"SELECT ref_id, type, description, created_at, expires_at, count, resource_id, access_level, params \
-		 FROM refs WHERE tn_id = ? AND ref_id = ?",
-	)
+	let row = sqlx::query(sqlx::AssertSqlSafe(format!(
+		"SELECT {REF_COLUMNS} FROM refs WHERE tn_id = ? AND ref_id = ?"
+	)))
 	.bind(tn_id.0)
 	.bind(ref_id)
 	.fetch_optional(db)
@@ -96,6 +120,7 @@ pub(crate) async fn get(db: &SqlitePool, tn_id: TnId, ref_id: &str) -> ClResult<
 /// Create a new reference
 pub(crate) async fn create(
 	db: &SqlitePool,
+	worker: &WorkerPool,
 	tn_id: TnId,
 	ref_id: &str,
 	opts: &CreateRefOptions,
@@ -104,9 +129,14 @@ pub(crate) async fn create(
 
 	// Convert access_level char to string for storage
 	let access_level_str = opts.access_level.map(|c| c.to_string());
+	let link_mode_str = opts.link_mode.map(|c| c.to_string());
+	let password_hash = match opts.password.as_deref() {
+		Some(password) => Some(hash_password(worker, password).await?),
+		None => None,
+	};
 
 	sqlx::query(
-		"INSERT INTO refs (tn_id, ref_id, type, description, created_at, expires_at, count, resource_id, access_level, params) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+		"INSERT INTO refs (tn_id, ref_id, type, description, created_at, expires_at, count, resource_id, access_level, params, link_mode, password_hash) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
 	)
 		.bind(tn_id.0)
 		.bind(ref_id)
@@ -118,6 +148,8 @@ pub(crate) async fn create(
 		.bind(opts.resource_id.as_deref())
 		.bind(access_level_str.as_deref())
 		.bind(opts.params.as_deref())
+		.bind(link_mode_str.as_deref())
+		.bind(password_hash.as_deref())
 		.execute(db)
 		.await
 		.db()?;
@@ -132,6 +164,8 @@ pub(crate) async fn create(
 		resource_id: opts.resource_id.clone().map(Into::into),
 		access_level: opts.access_level,
 		params: opts.params.clone().map(Into::into),
+		link_mode: opts.link_mode,
+		password_protected: password_hash.is_some(),
 	})
 }
 
@@ -154,6 +188,7 @@ pub(crate) async fn delete(db: &SqlitePool, tn_id: TnId, ref_id: &str) -> ClResu
 /// `refs_updated_at` trigger. Returns the post-update row via `RETURNING`.
 pub(crate) async fn update(
 	db: &SqlitePool,
+	worker: &WorkerPool,
 	tn_id: TnId,
 	ref_id: &str,
 	opts: &UpdateRefOptions,
@@ -163,12 +198,19 @@ pub(crate) async fn update(
 	let any_change = !opts.description.is_undefined()
 		|| !opts.expires_at.is_undefined()
 		|| !opts.count.is_undefined()
-		|| !opts.access_level.is_undefined();
+		|| !opts.access_level.is_undefined()
+		|| !opts.password.is_undefined();
 
 	if !any_change {
 		return get(db, tn_id, ref_id).await?.ok_or(Error::NotFound);
 	}
 
+	let password_hash: Patch<Box<str>> = match &opts.password {
+		Patch::Undefined => Patch::Undefined,
+		Patch::Null => Patch::Null,
+		Patch::Value(password) => Patch::Value(hash_password(worker, password).await?),
+	};
+
 	// A patch is "resurrecting" when it would re-enable a fully-used (count=0)
 	// ref: either raising the counter (Value(n>0)) or clearing it to unlimited
 	// (Null). For such patches, an extra WHERE clause blocks the UPDATE when
@@ -181,18 +223,16 @@ pub(crate) async fn update(
 	has = push_patch!(query, has, "description", &opts.description, |v| v.as_str());
 	has = push_patch!(query, has, "expires_at", &opts.expires_at, |v| v.0);
 	has = push_patch!(query, has, "count", &opts.count, |v| (*v).cast_signed());
+	has = push_patch!(query, has, "access_level", &opts.access_level, |c| c.to_string());
 	// Last field — no further chaining, so we don't reassign `has`.
-	let _: bool = push_patch!(query, has, "access_level", &opts.access_level, |c| c.to_string());
+	let _: bool = push_patch!(query, has, "password_hash", &password_hash, |h| h.to_string());
 
 	query.push(" WHERE tn_id = ").push_bind(tn_id.0);
 	query.push(" AND ref_id = ").push_bind(ref_id);
 	if is_resurrecting {
 		query.push(" AND (count IS NULL OR count > 0)");
 	}
-	query.push(
-		" RETURNING ref_id, type, description, created_at, expires_at, \
-		 count, resource_id, access_level, params",
-	);
+	query.push(" RETURNING ").push(REF_COLUMNS);
 
 	let row = query.build().fetch_optional(db).await.db()?;
 
@@ -221,7 +261,8 @@ pub(crate) async fn validate_ref(
 ) -> ClResult<(TnId, Box<str>, RefData)> {
 	// Look up the ref globally (across all tenants) and get tenant info
 	let row = sqlx::query(
-		"SELECT r.tn_id, r.ref_id, r.type, r.description, r.created_at, r.count, r.expires_at, r.resource_id, r.access_level, r.params, t.id_tag
+		"SELECT r.tn_id, r.ref_id, r.type, r.description, r.created_at, r.count, r.expires_at, r.resource_id, r.access_level, r.params,
+		 	r.link_mode, r.password_hash IS NOT NULL AS password_protected, t.id_tag
 		 FROM refs r
 		 INNER JOIN tenants t ON r.tn_id = t.tn_id
 		 WHERE r.ref_id = ?",
@@ -242,6 +283,8 @@ pub(crate) async fn validate_ref(
 	let resource_id: Option<Box<str>> = row.get("resource_id");
 	let access_level_str: Option<String> = row.get("access_level");
 	let params: Option<Box<str>> = row.get("params");
+	let link_mode: Option<String> = row.get("link_mode");
+	let password_protected: bool = row.get("password_protected");
 
 	// Validate ref type
 	if !expected_types.contains(&ref_type.as_str()) {
@@ -275,8 +318,10 @@ pub(crate) async fn validate_ref(
 		expires_at: expires_at.map(Timestamp),
 		count: count.and_then(|c| u32::try_from(c).ok()),
 		resource_id,
-		access_level: access_level_str.and_then(|s| s.chars().next()),
+		access_level: first_char(access_level_str),
 		params,
+		link_mode: first_char(link_mode),
+		password_protected,
 	};
 
 	Ok((TnId(u32::try_from(tn_id).map_err(|_| Error::DbError)?), id_tag.into(), ref_data))
@@ -295,7 +340,8 @@ pub(crate) async fn use_ref(
 
 	// Look up the ref globally (across all tenants) and get tenant info
 	let row = sqlx::query(
-		"SELECT r.tn_id, r.ref_id, r.type, r.description, r.created_at, r.count, r.expires_at, r.resource_id, r.access_level, r.params, t.id_tag
+		"SELECT r.tn_id, r.ref_id, r.type, r.description, r.created_at, r.count, r.expires_at, r.resource_id, r.access_level, r.params,
+		 	r.link_mode, r.password_hash IS NOT NULL AS password_protected, t.id_tag
 		 FROM refs r
 		 INNER JOIN tenants t ON r.tn_id = t.tn_id
 		 WHERE r.ref_id = ?",
@@ -316,6 +362,8 @@ pub(crate) async fn use_ref(
 	let resource_id: Option<Box<str>> = row.get("resource_id");
 	let access_level_str: Option<String> = row.get("access_level");
 	let params: Option<Box<str>> = row.get("params");
+	let link_mode: Option<String> = row.get("link_mode");
+	let password_protected: bool = row.get("password_protected");
 
 	// Validate ref type
 	if !expected_types.contains(&ref_type.as_str()) {
@@ -360,9 +408,35 @@ pub(crate) async fn use_ref(
 		expires_at: expires_at.map(Timestamp),
 		count: new_count, // None = unlimited
 		resource_id,
-		access_level: access_level_str.and_then(|s| s.chars().next()),
+		access_level: first_char(access_level_str),
 		params,
+		link_mode: first_char(link_mode),
+		password_protected,
 	};
 
 	Ok((TnId(u32::try_from(tn_id).map_err(|_| Error::DbError)?), id_tag.into(), ref_data))
 }
+
+/// Check a share link password — see `MetaAdapter::check_ref_password`.
+pub(crate) async fn check_password(
+	db: &SqlitePool,
+	worker: &WorkerPool,
+	ref_id: &str,
+	password: &str,
+) -> ClResult<()> {
+	let row = sqlx::query("SELECT password_hash FROM refs WHERE ref_id = ?")
+		.bind(ref_id)
+		.fetch_optional(db)
+		.await
+		.db()?
+		.ok_or(Error::PermissionDenied)?;
+	let Some(password_hash) = row.get::<Option<String>, _>("password_hash") else {
+		return Ok(());
+	};
+
+	let password = password.to_string();
+	let matches = worker
+		.try_run_immed(move || Ok(bcrypt::verify(password, &password_hash).unwrap_or(false)))
+		.await?;
+	if matches { Ok(()) } else { Err(Error::PermissionDenied) }
+}
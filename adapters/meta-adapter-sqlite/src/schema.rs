@@ -135,7 +135,7 @@ const SEARCH_FTS_TRIGGERS: [&str; 3] = [
 /// Initialize the database schema with all required tables and indexes
 pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 	// Current schema version - update this when adding new migrations
-	const CURRENT_DB_VERSION: i64 = 48;
+	const CURRENT_DB_VERSION: i64 = 57;
 
 	let mut tx = db.begin().await?;
 
@@ -293,6 +293,7 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 			updated_at INTEGER DEFAULT (unixepoch()),
 			broken_at INTEGER,			-- Tombstone written by the cross-context refresh endpoint
 			broken_reason TEXT,			-- BrokenReason enum: 'deleted' | 'revoked'
+			version_of text,			-- Version chain: file_id of the chain's first revision
 			PRIMARY KEY(f_id)
 		)",
 	)
@@ -334,6 +335,12 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 		)
 		.execute(&mut *tx)
 		.await?;
+	// Per-tenant variant scans: storage usage, and the blob GC's referenced set.
+	sqlx::query(
+		"CREATE INDEX IF NOT EXISTS idx_file_variants_tn ON file_variants(tn_id, variant_id)",
+	)
+	.execute(&mut *tx)
+	.await?;
 
 	// Refs
 	sqlx::query(
@@ -347,6 +354,8 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 			resource_id text,
 			access_level char(1),
 			params text,
+			link_mode char(1),			-- 'V' view only, 'U' upload only; NULL = access_level
+			password_hash text,			-- bcrypt; NULL = no password
 			created_at INTEGER DEFAULT (unixepoch()),
 			updated_at INTEGER DEFAULT (unixepoch()),
 			PRIMARY KEY(tn_id, ref_id)
@@ -401,7 +410,10 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 			comments integer DEFAULT 0,	-- total comment count, federated as STAT `c`
 			comments_ts integer,		-- last-comment timestamp (epoch seconds), federated as STAT `ct`
 			comments_read_at integer,	-- reader's comment read-watermark (epoch seconds)
+			seen_at integer,			-- when the reader marked it seen in the ranked feed (epoch seconds)
 			reposts integer,
+			votes json,						-- POLL tally, federated as STAT `v`
+			rsvps json,						-- EVENT RSVP tally, federated as STAT `rv`
 			stat_at INTEGER,				-- Highest created_at of any STAT applied to reactions/comments
 			visibility char(1) NOT NULL DEFAULT 'D',	-- D: Direct (owner only), P: Public, V: Verified,
 														-- 2: 2nd degree, F: Follower, C: Connected
@@ -420,6 +432,7 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 			-- ALTER of a populated table), so create() stamps it explicitly via
 			-- unixepoch(); see migration 36.
 			received_at INTEGER DEFAULT (unixepoch()),
+			edited_at INTEGER,				-- created_at of the EDIT that set `content`; NULL if never edited
 			updated_at INTEGER DEFAULT (unixepoch())
 		)",
 	)
@@ -461,6 +474,158 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 	.execute(&mut *tx)
 	.await?;
 
+	// Contents of edited actions, one row per content: the original and every EDIT
+	// applied to it. `edit_id` is the action that produced the content (the edited
+	// action's own id for the original), which makes replays of one edit idempotent.
+	// Never-edited actions have no rows.
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS action_revisions (
+			tn_id integer NOT NULL,
+			action_id text NOT NULL,
+			edit_id text NOT NULL,
+			content json,
+			edited_at INTEGER NOT NULL,		-- created_at of `edit_id`
+			PRIMARY KEY(tn_id, action_id, edit_id)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+
+	// Moderation audit trail: one row per decision a community's moderators took on
+	// reported content or its author. Append-only; `reports` lists the REPORT action
+	// ids the decision closed.
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS moderation_log (
+			ml_id integer PRIMARY KEY AUTOINCREMENT,
+			tn_id integer NOT NULL,
+			decision text NOT NULL,		-- dismiss, hide, restore, remove, warn, mute, ban
+			subject text,				-- the moderated action
+			target_tag text NOT NULL,	-- its author
+			moderator_tag text NOT NULL,
+			reports json,
+			note text,
+			created_at INTEGER DEFAULT (unixepoch())
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE INDEX IF NOT EXISTS idx_moderation_log_subject ON moderation_log(tn_id, subject)",
+	)
+	.execute(&mut *tx)
+	.await?;
+
+	// Content filters: the tenant's keyword / regex / hashtag / instance filters
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS content_filters (
+			cf_id integer PRIMARY KEY AUTOINCREMENT,
+			tn_id integer NOT NULL,
+			kind char(1) NOT NULL,		-- 'K': keyword, 'R': regex, 'H': hashtag, 'I': instance
+			pattern text NOT NULL,
+			mode char(1) NOT NULL,		-- 'W': warn, 'H': hide, 'D': drop
+			expires_at INTEGER,
+			created_at INTEGER DEFAULT (unixepoch())
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query("CREATE INDEX IF NOT EXISTS idx_content_filters_tn_id ON content_filters(tn_id)")
+		.execute(&mut *tx)
+		.await?;
+
+	// Hashtags: the normalized tags of POST / CMNT content, the tags the tenant
+	// follows, and the trending lists the scheduler recomputes per window
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS action_hashtags (
+			tn_id integer NOT NULL,
+			action_id text NOT NULL,
+			tag text NOT NULL,
+			PRIMARY KEY(tn_id, tag, action_id)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE INDEX IF NOT EXISTS idx_action_hashtags_action ON action_hashtags(tn_id, action_id)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS followed_hashtags (
+			tn_id integer NOT NULL,
+			tag text NOT NULL,
+			created_at INTEGER DEFAULT (unixepoch()),
+			PRIMARY KEY(tn_id, tag)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS trending_hashtags (
+			tn_id integer NOT NULL,
+			period text NOT NULL,		-- the window: '1h', '24h', '7d'
+			tag text NOT NULL,
+			uses integer NOT NULL,
+			accounts integer NOT NULL,
+			score real NOT NULL,
+			computed_at INTEGER DEFAULT (unixepoch()),
+			PRIMARY KEY(tn_id, period, tag)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+
+	// Mentions: the id_tags POST / CMNT content mentions
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS action_mentions (
+			tn_id integer NOT NULL,
+			action_id text NOT NULL,
+			id_tag text NOT NULL,
+			PRIMARY KEY(tn_id, id_tag, action_id)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE INDEX IF NOT EXISTS idx_action_mentions_action ON action_mentions(tn_id, action_id)",
+	)
+	.execute(&mut *tx)
+	.await?;
+
+	// Collections: private, user-owned lists of saved actions, files and profiles
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS collections (
+			col_id integer PRIMARY KEY AUTOINCREMENT,
+			tn_id integer NOT NULL,
+			owner_tag text NOT NULL,
+			name text NOT NULL,
+			description text,
+			created_at INTEGER DEFAULT (unixepoch()),
+			updated_at INTEGER DEFAULT (unixepoch())
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE INDEX IF NOT EXISTS idx_collections_owner ON collections(tn_id, owner_tag)",
+	)
+	.execute(&mut *tx)
+	.await?;
+	sqlx::query(
+		"CREATE TABLE IF NOT EXISTS saved_items (
+			tn_id integer NOT NULL,
+			col_id integer NOT NULL,
+			item_type char(1) NOT NULL,	-- 'A': action, 'F': file, 'P': profile
+			item_id text NOT NULL,
+			note text,
+			position integer NOT NULL,
+			created_at INTEGER DEFAULT (unixepoch()),
+			PRIMARY KEY(col_id, item_type, item_id)
+		)",
+	)
+	.execute(&mut *tx)
+	.await?;
+
 	// Task scheduler
 	sqlx::query(
 		"CREATE TABLE IF NOT EXISTS tasks (
@@ -833,6 +998,7 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 			store_tp char(4),
 			nav_param text,
 			search json,
+			triggers json,
 			x json,
 			status char(1) DEFAULT 'A',
 			created_at INTEGER DEFAULT (unixepoch()),
@@ -1336,6 +1502,13 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 		)
 		.execute(&mut *tx)
 		.await?;
+		// Version chain lookups (migration 50).
+		sqlx::query(
+			"CREATE INDEX IF NOT EXISTS idx_files_version ON files(tn_id, version_of) \
+			 WHERE version_of IS NOT NULL",
+		)
+		.execute(&mut *tx)
+		.await?;
 
 		set_db_version(&mut tx, CURRENT_DB_VERSION).await;
 		version = CURRENT_DB_VERSION;
@@ -2405,6 +2578,119 @@ pub(crate) async fn init_db(db: &SqlitePool) -> Result<(), sqlx::Error> {
 		set_db_version(&mut tx, 48).await;
 	}
 
+	if version < 49 {
+		// RTDB triggers are declared in the doc format manifest next to `search`, and
+		// stored the same way: opaque JSON the adapter never looks into.
+		add_column_if_missing(&mut tx, "doc_formats", "triggers", "json").await?;
+		set_db_version(&mut tx, 49).await;
+	}
+
+	if version < 50 {
+		// BLOB version chains. Every row of a chain names the chain's first revision in
+		// `version_of`; the earlier revisions wait in `VERSIONS_PARENT_ID`. Existing files
+		// are chains of one and keep a NULL.
+		add_column_if_missing(&mut tx, "files", "version_of", "text").await?;
+		sqlx::query(
+			"CREATE INDEX IF NOT EXISTS idx_files_version ON files(tn_id, version_of) \
+			 WHERE version_of IS NOT NULL",
+		)
+		.execute(&mut *tx)
+		.await?;
+		set_db_version(&mut tx, 50).await;
+	}
+
+	if version < 51 {
+		// Action edits. `action_revisions` is created unconditionally above; the column
+		// that marks the current content as edited is not, and existing rows were never
+		// edited, so they keep a NULL.
+		add_column_if_missing(&mut tx, "actions", "edited_at", "INTEGER").await?;
+		set_db_version(&mut tx, 51).await;
+	}
+
+	if version < 52 {
+		// Share link modes and passwords. Existing links keep NULL in both: full access
+		// at their `access_level`, no password.
+		add_column_if_missing(&mut tx, "refs", "link_mode", "char(1)").await?;
+		add_column_if_missing(&mut tx, "refs", "password_hash", "text").await?;
+		set_db_version(&mut tx, 52).await;
+	}
+
+	if version < 53 {
+		// Polls. Only POLL rows carry a tally; everything else keeps NULL.
+		add_column_if_missing(&mut tx, "actions", "votes", "json").await?;
+		set_db_version(&mut tx, 53).await;
+	}
+
+	if version < 54 {
+		// Events. Only EVENT rows carry an RSVP tally; everything else keeps NULL.
+		add_column_if_missing(&mut tx, "actions", "rsvps", "json").await?;
+		set_db_version(&mut tx, 54).await;
+	}
+
+	if version < 55 {
+		// Hashtags. The index starts out empty; fill it from the posts and comments
+		// stored so far — as the live indexer would, so not from their DEL / EDIT
+		// markers nor from deleted ones.
+		let rows = sqlx::query(
+			"SELECT tn_id, action_id, content FROM actions
+			 WHERE type IN ('POST', 'CMNT') AND content IS NOT NULL
+			 AND coalesce(sub_type, '') NOT IN ('DEL', 'EDIT') AND coalesce(status, 'A') != 'D'",
+		)
+		.fetch_all(&mut *tx)
+		.await?;
+		for row in &rows {
+			let tn_id: i64 = row.get("tn_id");
+			let action_id: String = row.get("action_id");
+			let content: String = row.get("content");
+			for tag in cloudillo_types::hashtags::stored_content_hashtags(&content) {
+				sqlx::query(
+					"INSERT OR IGNORE INTO action_hashtags (tn_id, action_id, tag) VALUES (?, ?, ?)",
+				)
+				.bind(tn_id)
+				.bind(&action_id)
+				.bind(&tag)
+				.execute(&mut *tx)
+				.await?;
+			}
+		}
+		set_db_version(&mut tx, 55).await;
+	}
+
+	if version < 56 {
+		// Ranked feed. Nothing is seen until the reader marks it.
+		add_column_if_missing(&mut tx, "actions", "seen_at", "INTEGER").await?;
+		set_db_version(&mut tx, 56).await;
+	}
+
+	if version < 57 {
+		// Mentions. The index starts out empty; fill it from the posts and comments
+		// stored so far, skipping the same DEL / EDIT markers and deleted actions as
+		// the hashtag backfill above.
+		let rows = sqlx::query(
+			"SELECT tn_id, action_id, content FROM actions
+			 WHERE type IN ('POST', 'CMNT') AND content IS NOT NULL
+			 AND coalesce(sub_type, '') NOT IN ('DEL', 'EDIT') AND coalesce(status, 'A') != 'D'",
+		)
+		.fetch_all(&mut *tx)
+		.await?;
+		for row in &rows {
+			let tn_id: i64 = row.get("tn_id");
+			let action_id: String = row.get("action_id");
+			let content: String = row.get("content");
+			for id_tag in cloudillo_types::mentions::stored_content_mentions(&content) {
+				sqlx::query(
+					"INSERT OR IGNORE INTO action_mentions (tn_id, action_id, id_tag) VALUES (?, ?, ?)",
+				)
+				.bind(tn_id)
+				.bind(&action_id)
+				.bind(&id_tag)
+				.execute(&mut *tx)
+				.await?;
+			}
+		}
+		set_db_version(&mut tx, 57).await;
+	}
+
 	tx.commit().await?;
 
 	Ok(())
@@ -1267,6 +1267,18 @@ fn push_search_filters(
 			.push(")");
 	}
 
+	// A saved item matches the rows of its object; a saved file's deep parts
+	// (`'D'`, keyed by the file id) come with it.
+	if let Some(collection_id) = opts.collection_id {
+		query
+			.push(" AND EXISTS (SELECT 1 FROM saved_items si WHERE si.tn_id=d.tn_id AND si.col_id=")
+			.push_bind(collection_id.cast_signed())
+			.push(
+				" AND si.item_id=d.obj_id \
+				 AND si.item_type=CASE d.obj_tp WHEN 'D' THEN 'F' ELSE d.obj_tp END)",
+			);
+	}
+
 	// `take` re-clamps what the handler already rejected past its cap: unbounded,
 	// ~33k values overrun SQLite's 32766 bound-variable limit and the request
 	// comes back a 500.
@@ -227,6 +227,15 @@ pub(crate) async fn update(
 const TENANT_CASCADE_TABLES: &[&str] = &[
 	"tasks",
 	"action_tokens",
+	"action_revisions",
+	"moderation_log",
+	"content_filters",
+	"action_hashtags",
+	"followed_hashtags",
+	"trending_hashtags",
+	"action_mentions",
+	"saved_items",
+	"collections",
 	"actions",
 	"file_variants",
 	"files",
@@ -0,0 +1,159 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Action edits — the newest edit becomes the content, the original and every edit
+//! stay in the history, and late or replayed edits never roll the content back.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{Action, MetaAdapter, UpdateActionDataOptions};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Create an active POST `a1~post` with `content`, created at `created_at`.
+async fn add_post(adapter: &MetaAdapterSqlite, content: &str, created_at: i64) {
+	let action = Action {
+		action_id: "a1~post",
+		typ: "POST",
+		sub_typ: Some("TEXT"),
+		issuer_tag: "alice",
+		parent_id: None,
+		root_id: None,
+		audience_tag: None,
+		content: Some(content),
+		attachments: None,
+		subject: None,
+		created_at: Timestamp(created_at),
+		expires_at: None,
+		visibility: Some('P'),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			"a1~post",
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+}
+
+async fn content(adapter: &MetaAdapterSqlite) -> serde_json::Value {
+	let view = adapter.get_action(TN, "a1~post").await.expect("get").expect("present");
+	view.content.expect("content")
+}
+
+#[tokio::test]
+async fn edit_replaces_content_and_keeps_the_original() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_post(&adapter, "\"helo\"", 100).await;
+
+	let changed = adapter
+		.apply_action_edit(TN, "a1~post", "a1~edit", Some("\"hello\""), Timestamp(200))
+		.await
+		.expect("edit");
+	assert!(changed);
+	assert_eq!(content(&adapter).await, "hello");
+	let view = adapter.get_action(TN, "a1~post").await.expect("get").expect("present");
+	assert_eq!(view.edited_at, Some(Timestamp(200)));
+	assert_eq!(view.action_id.as_ref(), "a1~post");
+
+	let revisions = adapter.list_action_revisions(TN, "a1~post").await.expect("revisions");
+	let ids: Vec<&str> = revisions.iter().map(|r| r.edit_id.as_ref()).collect();
+	assert_eq!(ids, ["a1~edit", "a1~post"]);
+	assert_eq!(revisions[1].content.as_ref().expect("original"), "helo");
+	assert_eq!(revisions[1].edited_at, Timestamp(100));
+}
+
+#[tokio::test]
+async fn late_edit_lands_in_history_without_rolling_back() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_post(&adapter, "\"one\"", 100).await;
+
+	adapter
+		.apply_action_edit(TN, "a1~post", "a1~three", Some("\"three\""), Timestamp(300))
+		.await
+		.expect("newer edit");
+	let changed = adapter
+		.apply_action_edit(TN, "a1~post", "a1~two", Some("\"two\""), Timestamp(200))
+		.await
+		.expect("older edit");
+	assert!(!changed);
+	assert_eq!(content(&adapter).await, "three");
+
+	let revisions = adapter.list_action_revisions(TN, "a1~post").await.expect("revisions");
+	let ids: Vec<&str> = revisions.iter().map(|r| r.edit_id.as_ref()).collect();
+	assert_eq!(ids, ["a1~three", "a1~two", "a1~post"]);
+}
+
+#[tokio::test]
+async fn replayed_edit_is_a_no_op() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_post(&adapter, "\"one\"", 100).await;
+
+	let edit = |id: &'static str, text: &'static str, at: i64| {
+		adapter.apply_action_edit(TN, "a1~post", id, Some(text), Timestamp(at))
+	};
+	assert!(edit("a1~two", "\"two\"", 200).await.expect("edit"));
+	assert!(edit("a1~three", "\"three\"", 300).await.expect("edit"));
+	assert!(!edit("a1~two", "\"two\"", 200).await.expect("replay"));
+
+	assert_eq!(content(&adapter).await, "three");
+	assert_eq!(adapter.list_action_revisions(TN, "a1~post").await.expect("revisions").len(), 3);
+}
+
+#[tokio::test]
+async fn unknown_or_deleted_action_cannot_be_edited() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let res = adapter
+		.apply_action_edit(TN, "a1~missing", "a1~edit", Some("\"x\""), Timestamp(200))
+		.await;
+	assert!(res.is_err());
+
+	add_post(&adapter, "\"one\"", 100).await;
+	adapter.delete_action(TN, "a1~post").await.expect("delete");
+	let res = adapter
+		.apply_action_edit(TN, "a1~post", "a1~edit", Some("\"x\""), Timestamp(200))
+		.await;
+	assert!(res.is_err());
+	assert!(
+		adapter
+			.list_action_revisions(TN, "a1~post")
+			.await
+			.expect("revisions")
+			.is_empty()
+	);
+}
+
+#[tokio::test]
+async fn never_edited_action_has_no_revisions() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_post(&adapter, "\"one\"", 100).await;
+
+	let view = adapter.get_action(TN, "a1~post").await.expect("get").expect("present");
+	assert_eq!(view.edited_at, None);
+	assert!(
+		adapter
+			.list_action_revisions(TN, "a1~post")
+			.await
+			.expect("revisions")
+			.is_empty()
+	);
+}
@@ -0,0 +1,120 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Events — RSVP answers group by subtype into one count, and an event's tally is
+//! stored and surfaced as `stat.rsvps`.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	Action, ActionCountGroupBy, ListActionOptions, MetaAdapter, UpdateActionDataOptions,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Create an active action `action_id`.
+async fn add_action(
+	adapter: &MetaAdapterSqlite,
+	action_id: &str,
+	typ: &str,
+	sub_typ: Option<&str>,
+	issuer_tag: &str,
+	subject: Option<&str>,
+	content: Option<&str>,
+) {
+	let action = Action {
+		action_id,
+		typ,
+		sub_typ,
+		issuer_tag,
+		parent_id: None,
+		root_id: None,
+		audience_tag: None,
+		content,
+		attachments: None,
+		subject,
+		created_at: Timestamp(100),
+		expires_at: None,
+		visibility: Some('P'),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			action_id,
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+}
+
+#[tokio::test]
+async fn answers_are_counted_by_subtype_and_the_tally_is_listed_as_stat_rsvps() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let event = r#"{"title":"Meetup","startsAt":100}"#;
+	add_action(&adapter, "a1~ev", "EVENT", None, "alice", None, Some(event)).await;
+	add_action(&adapter, "a1~r1", "RSVP", Some("GOING"), "bob", Some("a1~ev"), None).await;
+	add_action(&adapter, "a1~r2", "RSVP", Some("GOING"), "carol", Some("a1~ev"), None).await;
+	add_action(&adapter, "a1~r3", "RSVP", Some("MAYBE"), "dave", Some("a1~ev"), None).await;
+
+	let opts = ListActionOptions {
+		typ: Some(vec!["RSVP".into()]),
+		subject: Some(vec!["a1~ev".into()]),
+		..Default::default()
+	};
+	let mut grouped = adapter
+		.count_actions_grouped(TN, &opts, ActionCountGroupBy::SubType)
+		.await
+		.expect("count");
+	grouped.sort();
+	assert_eq!(grouped, [(Some("GOING".to_string()), 2), (Some("MAYBE".to_string()), 1)]);
+
+	let tally = r#"{"going":2,"maybe":1,"declined":0}"#;
+	adapter
+		.update_action_data(
+			TN,
+			"a1~ev",
+			&UpdateActionDataOptions { rsvps: Patch::Value(tally.into()), ..Default::default() },
+		)
+		.await
+		.expect("store tally");
+
+	let data = adapter.get_action_data(TN, "a1~ev").await.expect("data").unwrap();
+	assert_eq!(data.rsvps.as_deref(), Some(tally));
+	assert!(data.votes.is_none());
+
+	let view = adapter.get_action(TN, "a1~ev").await.expect("get").expect("present");
+	assert_eq!(view.stat.expect("stat")["rsvps"]["going"], serde_json::json!(2));
+
+	let listed = adapter
+		.list_actions(
+			TN,
+			&ListActionOptions { typ: Some(vec!["EVENT".into()]), ..Default::default() },
+		)
+		.await
+		.expect("list");
+	assert_eq!(listed.len(), 1);
+	assert_eq!(
+		listed[0].stat.as_ref().expect("stat")["rsvps"],
+		serde_json::json!({ "going": 2, "maybe": 1, "declined": 0 })
+	);
+}
+
+// vim: ts=4
@@ -0,0 +1,143 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Polls — identical ballots group into one count, and a poll's tally is stored
+//! and surfaced as `stat.votes`.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	Action, ActionCountGroupBy, ListActionOptions, MetaAdapter, UpdateActionDataOptions,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Create an active action `action_id`.
+async fn add_action(
+	adapter: &MetaAdapterSqlite,
+	action_id: &str,
+	typ: &str,
+	issuer_tag: &str,
+	subject: Option<&str>,
+	content: Option<&str>,
+) {
+	let action = Action {
+		action_id,
+		typ,
+		sub_typ: None,
+		issuer_tag,
+		parent_id: None,
+		root_id: None,
+		audience_tag: None,
+		content,
+		attachments: None,
+		subject,
+		created_at: Timestamp(100),
+		expires_at: None,
+		visibility: Some('P'),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			action_id,
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+}
+
+#[tokio::test]
+async fn identical_ballots_are_counted_together() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let poll = r#"{"question":"Lunch?","options":["pizza","soup"]}"#;
+	add_action(&adapter, "a1~poll", "POLL", "alice", None, Some(poll)).await;
+	add_action(&adapter, "a1~v1", "VOTE", "bob", Some("a1~poll"), Some(r#"{"choices":[0]}"#)).await;
+	add_action(&adapter, "a1~v2", "VOTE", "carol", Some("a1~poll"), Some(r#"{"choices":[0]}"#))
+		.await;
+	add_action(&adapter, "a1~v3", "VOTE", "dave", Some("a1~poll"), Some(r#"{"choices":[1]}"#))
+		.await;
+
+	let opts = ListActionOptions {
+		typ: Some(vec!["VOTE".into()]),
+		subject: Some(vec!["a1~poll".into()]),
+		..Default::default()
+	};
+	let mut grouped = adapter
+		.count_actions_grouped(TN, &opts, ActionCountGroupBy::Content)
+		.await
+		.expect("count");
+	grouped.sort();
+	assert_eq!(
+		grouped,
+		[
+			(Some(r#"{"choices":[0]}"#.to_string()), 2),
+			(Some(r#"{"choices":[1]}"#.to_string()), 1)
+		]
+	);
+}
+
+#[tokio::test]
+async fn a_stored_tally_is_read_back_and_listed_as_stat_votes() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let poll = r#"{"question":"Lunch?","options":["pizza","soup"]}"#;
+	add_action(&adapter, "a1~poll", "POLL", "alice", None, Some(poll)).await;
+	assert!(
+		adapter
+			.get_action_data(TN, "a1~poll")
+			.await
+			.expect("data")
+			.unwrap()
+			.votes
+			.is_none()
+	);
+
+	let tally = r#"{"voters":3,"counts":[2,1]}"#;
+	adapter
+		.update_action_data(
+			TN,
+			"a1~poll",
+			&UpdateActionDataOptions { votes: Patch::Value(tally.into()), ..Default::default() },
+		)
+		.await
+		.expect("store tally");
+
+	let data = adapter.get_action_data(TN, "a1~poll").await.expect("data").unwrap();
+	assert_eq!(data.votes.as_deref(), Some(tally));
+
+	let view = adapter.get_action(TN, "a1~poll").await.expect("get").expect("present");
+	let stat = view.stat.expect("stat");
+	assert_eq!(stat["votes"], serde_json::json!({ "voters": 3, "counts": [2, 1] }));
+
+	let listed = adapter
+		.list_actions(
+			TN,
+			&ListActionOptions { typ: Some(vec!["POLL".into()]), ..Default::default() },
+		)
+		.await
+		.expect("list");
+	assert_eq!(listed.len(), 1);
+	assert_eq!(
+		listed[0].stat.as_ref().expect("stat")["votes"]["counts"],
+		serde_json::json!([2, 1])
+	);
+}
+
+// vim: ts=4
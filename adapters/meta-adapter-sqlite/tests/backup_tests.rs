@@ -0,0 +1,143 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Tenant backup tests — `export_tenant` → `import_tenant` under a new `tn_id`,
+//! with the surrogate keys renumbered and the rows referencing them following.
+#![allow(clippy::panic, clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::error::Error;
+use cloudillo_types::meta_adapter::{
+	CalendarObjectExtracted, CreateCalendarData, CreateFile, FileId, FileStatus, FileVariant,
+	ListCalendarObjectOptions, MetaAdapter,
+};
+use cloudillo_types::types::{TableDump, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	(adapter, temp_dir)
+}
+
+async fn populate(adapter: &MetaAdapterSqlite, tn_id: TnId) {
+	adapter.create_tenant(tn_id, "alice.example.com").await.expect("create tenant");
+	// Variants attach to a pending file, which is then finalized.
+	let f_id = match adapter
+		.create_file(
+			tn_id,
+			CreateFile {
+				content_type: "image/jpeg".into(),
+				file_name: "photo.jpg".into(),
+				file_tp: Some("BLOB".into()),
+				status: Some(FileStatus::Pending),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("create file")
+	{
+		FileId::FId(f_id) => f_id,
+		FileId::FileId(_) => panic!("expected a pending file"),
+	};
+	adapter
+		.create_file_variant(
+			tn_id,
+			f_id,
+			FileVariant {
+				variant_id: "b1~photo-sd",
+				variant: "vis.sd",
+				format: "webp",
+				size: 1234,
+				resolution: (720, 480),
+				available: true,
+				global: false,
+				duration: None,
+				bitrate: None,
+				page_count: None,
+			},
+		)
+		.await
+		.expect("create variant");
+	adapter.finalize_file(tn_id, f_id, "f1~photo").await.expect("finalize file");
+
+	let cal = adapter
+		.create_calendar(tn_id, &CreateCalendarData { name: "Work".into(), ..Default::default() })
+		.await
+		.expect("create calendar");
+	adapter
+		.upsert_calendar_object(
+			tn_id,
+			cal.cal_id,
+			"event-uid",
+			"BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n",
+			"etag-v1",
+			&CalendarObjectExtracted {
+				component: "VEVENT".into(),
+				summary: Some("Sync".into()),
+				dtstart: Some(Timestamp(1_700_000_000)),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("create calendar object");
+}
+
+#[tokio::test]
+async fn import_renumbers_surrogate_keys_and_keeps_references() {
+	let (adapter, _temp) = create_test_adapter().await;
+	populate(&adapter, TnId(1)).await;
+	let dump = adapter.export_tenant(TnId(1)).await.expect("export");
+	assert!(dump.iter().all(|t| &*t.table != "search_docs" && &*t.table != "tasks"));
+
+	let restored = TnId(7);
+	adapter.import_tenant(restored, &dump).await.expect("import");
+
+	let file = adapter.read_file(restored, "f1~photo").await.expect("read file").expect("file");
+	assert_eq!(&*file.file_name, "photo.jpg");
+	let old_f_id = adapter.read_f_id_by_file_id(TnId(1), "f1~photo").await.unwrap();
+	let new_f_id = adapter.read_f_id_by_file_id(restored, "f1~photo").await.unwrap();
+	assert_ne!(old_f_id, new_f_id);
+	let variants = adapter
+		.list_file_variants(restored, FileId::FileId("f1~photo"))
+		.await
+		.expect("list variants");
+	assert_eq!(variants.len(), 1);
+	assert_eq!(&*variants[0].variant_id, "b1~photo-sd");
+
+	let calendars = adapter.list_calendars(restored).await.expect("list calendars");
+	assert_eq!(calendars.len(), 1);
+	let objects = adapter
+		.list_calendar_objects(restored, calendars[0].cal_id, &ListCalendarObjectOptions::default())
+		.await
+		.expect("list calendar objects");
+	assert_eq!(objects.len(), 1);
+
+	// The source tenant is untouched.
+	assert_eq!(adapter.list_calendars(TnId(1)).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn import_refuses_an_existing_tenant_and_unknown_tables() {
+	let (adapter, _temp) = create_test_adapter().await;
+	populate(&adapter, TnId(1)).await;
+	let dump = adapter.export_tenant(TnId(1)).await.expect("export");
+
+	let res = adapter.import_tenant(TnId(1), &dump).await;
+	assert!(matches!(res, Err(Error::Conflict(_))), "{res:?}");
+
+	let mut bad = dump.clone();
+	bad.push(TableDump { table: "tasks".into(), rows: Vec::new() });
+	let res = adapter.import_tenant(TnId(2), &bad).await;
+	assert!(matches!(res, Err(Error::ValidationError(_))), "{res:?}");
+	// All-or-nothing: the refused import left nothing behind.
+	assert!(adapter.read_file(TnId(2), "f1~photo").await.unwrap().is_none());
+}
+
+// vim: ts=4
@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Collections — private to their owner, ordered by position, upserted on save,
+//! and usable as a search filter.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::error::Error;
+use cloudillo_types::meta_adapter::{
+	CreateCollection, MetaAdapter, SaveItem, SavedItem, SavedItemType, SearchObject, SearchOptions,
+	SearchPart, UpdateCollectionOptions, UpdateSavedItemOptions,
+};
+use cloudillo_types::types::{Patch, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+async fn create(adapter: &MetaAdapterSqlite, owner: &str, name: &str) -> u64 {
+	let collection = CreateCollection { name: name.into(), description: None };
+	adapter
+		.create_collection(TN, owner, &collection)
+		.await
+		.expect("create")
+		.collection_id
+}
+
+fn save(item_type: SavedItemType, item_id: &str, position: Option<i64>) -> SaveItem {
+	SaveItem { item_type, item_id: item_id.into(), note: None, position }
+}
+
+fn order(items: &[SavedItem]) -> Vec<(&str, i64)> {
+	items.iter().map(|i| (i.item_id.as_ref(), i.position)).collect()
+}
+
+#[tokio::test]
+async fn collections_are_private_to_their_owner() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let col = create(&adapter, "bob", "Reading list").await;
+
+	assert_eq!(adapter.list_collections(TN, "bob").await.expect("list").len(), 1);
+	assert!(adapter.list_collections(TN, "carol").await.expect("list").is_empty());
+
+	let opts = UpdateCollectionOptions { name: Patch::Value("Mine".into()), ..Default::default() };
+	let not_found = |r: Result<_, Error>| matches!(r, Err(Error::NotFound));
+	assert!(not_found(adapter.read_collection(TN, "carol", col).await.map(|_| ())));
+	assert!(not_found(adapter.update_collection(TN, "carol", col, &opts).await.map(|_| ())));
+	assert!(not_found(adapter.list_saved_items(TN, "carol", col).await.map(|_| ())));
+	let item = save(SavedItemType::Action, "a1~x", None);
+	assert!(not_found(adapter.save_item(TN, "carol", col, &item).await.map(|_| ())));
+	assert!(not_found(adapter.delete_collection(TN, "carol", col).await));
+
+	let updated = adapter.update_collection(TN, "bob", col, &opts).await.expect("update");
+	assert_eq!(updated.name.as_ref(), "Mine");
+}
+
+#[tokio::test]
+async fn items_keep_their_order_and_are_upserted() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let col = create(&adapter, "bob", "Saved").await;
+	for id in ["a1~one", "a1~two", "a1~three"] {
+		adapter
+			.save_item(TN, "bob", col, &save(SavedItemType::Action, id, None))
+			.await
+			.expect("save");
+	}
+	let items = adapter.list_saved_items(TN, "bob", col).await.expect("list");
+	assert_eq!(order(&items), [("a1~one", 0), ("a1~two", 1), ("a1~three", 2)]);
+
+	// Inserting at a position moves the items at and after it down.
+	adapter
+		.save_item(TN, "bob", col, &save(SavedItemType::File, "f1~doc", Some(1)))
+		.await
+		.expect("insert");
+	let items = adapter.list_saved_items(TN, "bob", col).await.expect("list");
+	assert_eq!(order(&items), [("a1~one", 0), ("f1~doc", 1), ("a1~two", 2), ("a1~three", 3)]);
+
+	// Saving it again updates the note and leaves the item where it is.
+	let resave =
+		SaveItem { note: Some("for later".into()), ..save(SavedItemType::File, "f1~doc", None) };
+	let saved = adapter.save_item(TN, "bob", col, &resave).await.expect("resave");
+	assert_eq!((saved.position, saved.note.as_deref()), (1, Some("for later")));
+
+	let moved = UpdateSavedItemOptions { position: Patch::Value(0), ..Default::default() };
+	adapter
+		.update_saved_item(TN, "bob", col, SavedItemType::Action, "a1~three", &moved)
+		.await
+		.expect("move");
+	let items = adapter.list_saved_items(TN, "bob", col).await.expect("list");
+	assert_eq!(order(&items)[0], ("a1~three", 0));
+	assert_eq!(order(&items)[1], ("a1~one", 1));
+
+	adapter
+		.remove_saved_item(TN, "bob", col, SavedItemType::Action, "a1~one")
+		.await
+		.expect("remove");
+	let err = adapter
+		.remove_saved_item(TN, "bob", col, SavedItemType::Action, "a1~one")
+		.await
+		.expect_err("already removed");
+	assert!(matches!(err, Error::NotFound));
+	assert_eq!(adapter.read_collection(TN, "bob", col).await.expect("read").item_count, 3);
+
+	adapter.delete_collection(TN, "bob", col).await.expect("delete");
+	let col = create(&adapter, "bob", "Again").await;
+	assert!(adapter.list_saved_items(TN, "bob", col).await.expect("list").is_empty());
+}
+
+#[tokio::test]
+async fn search_narrows_to_a_collection() {
+	let (adapter, _temp) = create_test_adapter().await;
+	for file_id in ["f1~kept", "f1~other"] {
+		adapter
+			.replace_search_object(
+				TN,
+				&SearchObject {
+					obj_tp: 'D',
+					obj_id: file_id,
+					content_type: Some("cloudillo/notillo"),
+					visibility: Some('P'),
+					..Default::default()
+				},
+				&[SearchPart {
+					part_id: "page1",
+					body: Some("lighthouse keeper"),
+					..Default::default()
+				}],
+			)
+			.await
+			.expect("index");
+	}
+	let col = create(&adapter, "bob", "Docs").await;
+	adapter
+		.save_item(TN, "bob", col, &save(SavedItemType::File, "f1~kept", None))
+		.await
+		.expect("save");
+
+	let opts = SearchOptions { q: "lighthouse".into(), limit: 20, ..Default::default() };
+	assert_eq!(adapter.search(TN, &opts).await.expect("search").len(), 2);
+
+	// A saved file brings its document parts along.
+	let opts = SearchOptions { collection_id: Some(col), ..opts };
+	let hits = adapter.search(TN, &opts).await.expect("search");
+	let ids: Vec<&str> = hits.iter().map(|h| h.obj_id.as_ref()).collect();
+	assert_eq!(ids, ["f1~kept"]);
+	assert_eq!(adapter.count_search(TN, &opts).await.expect("count"), 1);
+}
+
+// vim: ts=4
@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Content filters — created, patched and deleted per tenant; expired filters are
+//! not listed.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::error::Error;
+use cloudillo_types::meta_adapter::{
+	ContentFilterKind, ContentFilterMode, CreateContentFilter, MetaAdapter,
+	UpdateContentFilterOptions,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+const OTHER: TnId = TnId(2);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	adapter.create_tenant(OTHER, "bob").await.ok();
+	(adapter, temp_dir)
+}
+
+fn filter(
+	kind: ContentFilterKind,
+	pattern: &str,
+	mode: ContentFilterMode,
+	expires_at: Option<Timestamp>,
+) -> CreateContentFilter {
+	CreateContentFilter { kind, pattern: pattern.into(), mode, expires_at }
+}
+
+#[tokio::test]
+async fn filters_are_created_patched_and_deleted_per_tenant() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let keyword = adapter
+		.create_content_filter(
+			TN,
+			&filter(ContentFilterKind::Keyword, "spoiler", ContentFilterMode::Warn, None),
+		)
+		.await
+		.expect("create keyword");
+	assert_eq!(keyword.kind, ContentFilterKind::Keyword);
+	assert_eq!(keyword.mode, ContentFilterMode::Warn);
+	let instance = adapter
+		.create_content_filter(
+			TN,
+			&filter(ContentFilterKind::Instance, "spam.example", ContentFilterMode::Drop, None),
+		)
+		.await
+		.expect("create instance");
+
+	let listed = adapter.list_content_filters(TN).await.expect("list");
+	let ids: Vec<u64> = listed.iter().map(|f| f.filter_id).collect();
+	assert_eq!(ids, [keyword.filter_id, instance.filter_id]);
+	assert!(adapter.list_content_filters(OTHER).await.expect("list other").is_empty());
+
+	let opts = UpdateContentFilterOptions {
+		mode: Patch::Value(ContentFilterMode::Hide),
+		expires_at: Patch::Value(Timestamp(Timestamp::now().0 + 3600)),
+		..Default::default()
+	};
+	let updated = adapter
+		.update_content_filter(TN, keyword.filter_id, &opts)
+		.await
+		.expect("update");
+	assert_eq!(updated.mode, ContentFilterMode::Hide);
+	assert_eq!(&*updated.pattern, "spoiler");
+	assert!(updated.expires_at.is_some());
+
+	let unchanged = adapter
+		.update_content_filter(TN, keyword.filter_id, &UpdateContentFilterOptions::default())
+		.await
+		.expect("empty update");
+	assert_eq!(unchanged.mode, ContentFilterMode::Hide);
+
+	let err = adapter
+		.update_content_filter(OTHER, keyword.filter_id, &opts)
+		.await
+		.expect_err("other tenant");
+	assert!(matches!(err, Error::NotFound));
+
+	adapter.delete_content_filter(TN, instance.filter_id).await.expect("delete");
+	let err = adapter.delete_content_filter(TN, instance.filter_id).await.expect_err("gone");
+	assert!(matches!(err, Error::NotFound));
+	assert_eq!(adapter.list_content_filters(TN).await.expect("list").len(), 1);
+}
+
+#[tokio::test]
+async fn expired_filters_are_not_listed() {
+	let (adapter, _temp) = create_test_adapter().await;
+	adapter
+		.create_content_filter(
+			TN,
+			&filter(
+				ContentFilterKind::Hashtag,
+				"crypto",
+				ContentFilterMode::Hide,
+				Some(Timestamp(1)),
+			),
+		)
+		.await
+		.expect("create expired");
+	adapter
+		.create_content_filter(
+			TN,
+			&filter(ContentFilterKind::Regex, r"buy\s+now", ContentFilterMode::Hide, None),
+		)
+		.await
+		.expect("create live");
+
+	let listed = adapter.list_content_filters(TN).await.expect("list");
+	assert_eq!(listed.len(), 1);
+	assert_eq!(listed[0].kind, ContentFilterKind::Regex);
+}
+
+// vim: ts=4
@@ -735,6 +735,7 @@ async fn doc_format_claim_round_trips_and_deletes() {
 	let (adapter, _dir) = create_test_adapter().await;
 	let tn_id = TnId(1);
 	let rules = serde_json::json!({ "v": 1, "parts": [{ "kind": "p", "title": ["ti"] }] });
+	let triggers = serde_json::json!([{ "name": "t", "cron": "0 * * * *", "do": [] }]);
 
 	adapter
 		.upsert_doc_format(
@@ -747,6 +748,7 @@ async fn doc_format_claim_round_trips_and_deletes() {
 				store_tp: Some("RTDB"),
 				nav_param: Some("nav"),
 				search: Some(&rules),
+				triggers: Some(&triggers),
 				x: None,
 			},
 		)
@@ -761,6 +763,7 @@ async fn doc_format_claim_round_trips_and_deletes() {
 	assert_eq!(&*fmt.app_name, "notillo");
 	assert_eq!(fmt.nav_param.as_deref(), Some("nav"));
 	assert_eq!(fmt.search.as_ref(), Some(&rules));
+	assert_eq!(fmt.triggers.as_ref(), Some(&triggers));
 	// The column must be INTEGER-declared: a TEXT affinity coerces the bound i64 back
 	// to a string, and `map_row`'s panicking accessor then dies on the next read.
 	assert_eq!(fmt.format_version, Some(1_000_000));
@@ -779,6 +782,7 @@ async fn doc_format_claim_round_trips_and_deletes() {
 				store_tp: Some("RTDB"),
 				nav_param: Some("nav"),
 				search: Some(&rules),
+				triggers: None,
 				x: None,
 			},
 		)
@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! File drops — content dropped through an upload-only share link dedups only
+//! against what the same link dropped, never against the owner's own files.
+#![allow(clippy::panic, clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{CreateFile, FileId, FileStatus, FileVariant, MetaAdapter};
+use cloudillo_types::types::TnId;
+use cloudillo_types::worker::WorkerPool;
+use serde_json::json;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+const CONTENT: &str = "b1~same-bytes";
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice.example.com").await.expect("create tenant");
+	(adapter, temp_dir)
+}
+
+/// Upload `CONTENT`, dropped through `drop_link` if given.
+async fn upload(adapter: &MetaAdapterSqlite, drop_link: Option<&str>) -> FileId<Box<str>> {
+	adapter
+		.create_file(
+			TN,
+			CreateFile {
+				preset: Some("default".into()),
+				orig_variant_id: Some(CONTENT.into()),
+				content_type: "application/pdf".into(),
+				file_name: "report.pdf".into(),
+				file_tp: Some("BLOB".into()),
+				parent_id: drop_link.map(|_| "f1~inbox".into()),
+				x: drop_link.map(|link| json!({ "dropLink": link })),
+				status: Some(FileStatus::Pending),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("create file")
+}
+
+/// Finish a pending upload as `file_id`, with `CONTENT` as its original.
+async fn finish(adapter: &MetaAdapterSqlite, created: FileId<Box<str>>, file_id: &str) {
+	let FileId::FId(f_id) = created else { panic!("expected a new file entry") };
+	adapter
+		.create_file_variant(
+			TN,
+			f_id,
+			FileVariant {
+				variant_id: CONTENT,
+				variant: "orig",
+				format: "pdf",
+				size: 1000,
+				resolution: (0, 0),
+				available: true,
+				global: false,
+				duration: None,
+				bitrate: None,
+				page_count: None,
+			},
+		)
+		.await
+		.expect("create variant");
+	adapter.finalize_file(TN, f_id, file_id).await.expect("finalize file");
+}
+
+#[tokio::test]
+async fn dropping_duplicate_content_creates_a_new_entry() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let created = upload(&adapter, None).await;
+	finish(&adapter, created, "f1~owned").await;
+
+	// The owner's copy is not handed to a dropper: the drop gets an entry of its own.
+	let created = upload(&adapter, Some("r1~link-a")).await;
+	finish(&adapter, created, "f1~dropped-a").await;
+
+	// Dropping it again through the same link finds that entry …
+	assert!(matches!(
+		upload(&adapter, Some("r1~link-a")).await,
+		FileId::FileId(id) if &*id == "f1~dropped-a"
+	));
+	// … another link does not, and the owner still dedups against their own file.
+	assert!(matches!(upload(&adapter, Some("r1~link-b")).await, FileId::FId(_)));
+	assert!(matches!(upload(&adapter, None).await, FileId::FileId(id) if &*id == "f1~owned"));
+}
+
+// vim: ts=4
@@ -0,0 +1,155 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Version chains — promotion moves folder, visibility and shares to the new
+//! revision, the old one waits in the versions folder, pruning hands it to the GC,
+//! and deleting the current revision takes the retained ones with it.
+#![allow(clippy::panic, clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	CreateFile, CreateShareEntry, FileId, FileStatus, MANAGED_PARENT_ID, MetaAdapter,
+	VERSIONS_PARENT_ID,
+};
+use cloudillo_types::types::TnId;
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Create and finalize `file_id` in `parent_id`, as a revision of `version_of` if given.
+async fn add_file(
+	adapter: &MetaAdapterSqlite,
+	file_id: &str,
+	parent_id: Option<&str>,
+	version_of: Option<&str>,
+) {
+	let f_id = match adapter
+		.create_file(
+			TN,
+			CreateFile {
+				content_type: "application/pdf".into(),
+				file_name: "report.pdf".into(),
+				file_tp: Some("BLOB".into()),
+				parent_id: parent_id.map(Into::into),
+				visibility: version_of.is_none().then_some('F'),
+				version_of: version_of.map(Into::into),
+				status: Some(FileStatus::Pending),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("create file")
+	{
+		FileId::FId(f_id) => f_id,
+		FileId::FileId(_) => panic!("expected a pending file"),
+	};
+	adapter.finalize_file(TN, f_id, file_id).await.expect("finalize");
+}
+
+fn grant(subject_id: &str) -> CreateShareEntry {
+	CreateShareEntry {
+		subject_type: 'U',
+		subject_id: subject_id.to_string(),
+		permission: 'R',
+		expires_at: None,
+	}
+}
+
+#[tokio::test]
+async fn promotion_hands_folder_visibility_and_shares_to_the_new_revision() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_file(&adapter, "f1~one", Some("f1~folder"), None).await;
+	adapter
+		.create_share_entry(TN, 'F', "f1~one", "alice", &grant("bob.example.com"))
+		.await
+		.expect("share");
+	add_file(&adapter, "f1~two", Some(VERSIONS_PARENT_ID), Some("f1~one")).await;
+
+	let previous = adapter.promote_file_version(TN, "f1~two").await.expect("promote");
+	assert_eq!(previous.as_deref(), Some("f1~one"));
+
+	let two = adapter.read_file(TN, "f1~two").await.unwrap().expect("current");
+	assert_eq!(two.parent_id.as_deref(), Some("f1~folder"));
+	assert_eq!(two.visibility, Some('F'));
+	let one = adapter.read_file(TN, "f1~one").await.unwrap().expect("retained");
+	assert_eq!(one.parent_id.as_deref(), Some(VERSIONS_PARENT_ID));
+	// The first revision joined its own chain on the first promotion.
+	assert_eq!(one.version_of.as_deref(), Some("f1~one"));
+
+	assert!(adapter.list_share_entries(TN, 'F', "f1~one").await.unwrap().is_empty());
+	assert_eq!(adapter.list_share_entries(TN, 'F', "f1~two").await.unwrap().len(), 1);
+
+	let versions = adapter.list_file_versions(TN, "f1~one").await.expect("list");
+	let listed: Vec<(&str, bool)> =
+		versions.iter().map(|v| (v.file_id.as_ref(), v.current)).collect();
+	assert_eq!(listed, vec![("f1~two", true), ("f1~one", false)]);
+
+	// Promoting the current revision again is a no-op.
+	assert_eq!(adapter.promote_file_version(TN, "f1~two").await.expect("again"), None);
+}
+
+#[tokio::test]
+async fn restore_and_prune() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_file(&adapter, "f1~one", None, None).await;
+	add_file(&adapter, "f1~two", Some(VERSIONS_PARENT_ID), Some("f1~one")).await;
+	adapter.promote_file_version(TN, "f1~two").await.expect("promote two");
+	add_file(&adapter, "f1~three", Some(VERSIONS_PARENT_ID), Some("f1~one")).await;
+	adapter.promote_file_version(TN, "f1~three").await.expect("promote three");
+
+	// Restoring the first revision retains the other two.
+	let previous = adapter.promote_file_version(TN, "f1~one").await.expect("restore");
+	assert_eq!(previous.as_deref(), Some("f1~three"));
+	let one = adapter.read_file(TN, "f1~one").await.unwrap().expect("current");
+	assert_eq!(one.parent_id, None);
+
+	// Keep the newest retained revision; the older one goes to the GC's folder.
+	let pruned = adapter.prune_file_versions(TN, "f1~one", 1).await.expect("prune");
+	assert_eq!(pruned, vec![Box::from("f1~two")]);
+	let two = adapter.read_file(TN, "f1~two").await.unwrap().expect("pruned");
+	assert_eq!(two.parent_id.as_deref(), Some(MANAGED_PARENT_ID));
+
+	let versions = adapter.list_file_versions(TN, "f1~one").await.expect("list");
+	let ids: Vec<&str> = versions.iter().map(|v| v.file_id.as_ref()).collect();
+	assert_eq!(ids, vec!["f1~three", "f1~one"]);
+}
+
+#[tokio::test]
+async fn deleting_the_current_revision_deletes_the_chain() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_file(&adapter, "f1~one", None, None).await;
+	add_file(&adapter, "f1~two", Some(VERSIONS_PARENT_ID), Some("f1~one")).await;
+	adapter.promote_file_version(TN, "f1~two").await.expect("promote");
+
+	let result = adapter.delete_file(TN, "f1~two").await.expect("delete");
+	assert_eq!(result.files_deleted, 2);
+	assert!(result.file_ids.iter().any(|id| id.as_ref() == "f1~one"));
+	let one = adapter.read_file(TN, "f1~one").await.unwrap().expect("tombstoned");
+	assert!(matches!(one.status, FileStatus::Deleted));
+}
+
+#[tokio::test]
+async fn a_plain_file_is_a_chain_of_one() {
+	let (adapter, _temp) = create_test_adapter().await;
+	add_file(&adapter, "f1~one", None, None).await;
+
+	let versions = adapter.list_file_versions(TN, "f1~one").await.expect("list");
+	assert_eq!(versions.len(), 1);
+	assert!(versions[0].current);
+	assert!(adapter.promote_file_version(TN, "f1~one").await.is_err());
+}
+
+// vim: ts=4
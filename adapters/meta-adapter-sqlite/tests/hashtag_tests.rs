@@ -0,0 +1,180 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Hashtags — the index backs the `tag` listing and the trending counts, followed
+//! hashtags feed the followed-hashtags listing, and trending lists are replaced
+//! per window.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::error::Error;
+use cloudillo_types::hashtags::extract_hashtags;
+use cloudillo_types::meta_adapter::{
+	Action, ListActionOptions, MetaAdapter, TrendingHashtag, UpdateActionDataOptions,
+	UpsertProfileFields,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Store an active POST and index its hashtags.
+async fn post(
+	adapter: &MetaAdapterSqlite,
+	action_id: &str,
+	issuer: &str,
+	text: &str,
+	visibility: char,
+	created_at: i64,
+) {
+	let content = serde_json::to_string(text).unwrap();
+	let action = Action {
+		action_id,
+		typ: "POST",
+		sub_typ: None,
+		issuer_tag: issuer,
+		parent_id: None,
+		root_id: None,
+		audience_tag: None,
+		content: Some(content.as_str()),
+		attachments: None,
+		subject: None,
+		created_at: Timestamp(created_at),
+		expires_at: None,
+		visibility: Some(visibility),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			action_id,
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+	adapter
+		.update_action_hashtags(TN, action_id, &extract_hashtags(text))
+		.await
+		.expect("index hashtags");
+}
+
+fn ids(actions: &[cloudillo_types::meta_adapter::ActionView]) -> Vec<&str> {
+	let mut ids: Vec<&str> = actions.iter().map(|a| a.action_id.as_ref()).collect();
+	ids.sort_unstable();
+	ids
+}
+
+#[tokio::test]
+async fn tag_listing_uses_the_index() {
+	let (adapter, _temp) = create_test_adapter().await;
+	post(&adapter, "a1~p1", "bob", "Off to the #Fediverse", 'P', 100).await;
+	post(&adapter, "a1~p2", "carol", "#fediverse_dev is not #fediverse?", 'P', 110).await;
+	post(&adapter, "a1~p3", "dave", "see https://x.example/page#fediverse", 'P', 120).await;
+
+	let opts = ListActionOptions { tag: Some("#FEDIVERSE".into()), ..Default::default() };
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	assert_eq!(ids(&listed), ["a1~p1", "a1~p2"]);
+
+	// An edit that drops the tag drops the action from the listing.
+	adapter.update_action_hashtags(TN, "a1~p1", &[]).await.expect("reindex");
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	assert_eq!(ids(&listed), ["a1~p2"]);
+
+	let opts = ListActionOptions { tag: Some("not a tag".into()), ..Default::default() };
+	assert!(adapter.list_actions(TN, &opts).await.expect("list invalid").is_empty());
+}
+
+#[tokio::test]
+async fn followed_hashtags_feed_lists_public_posts_of_followed_profiles() {
+	let (adapter, _temp) = create_test_adapter().await;
+	for id_tag in ["bob", "carol"] {
+		let profile = UpsertProfileFields {
+			name: Patch::Value(id_tag.into()),
+			following: Patch::Value(id_tag == "bob"),
+			..Default::default()
+		};
+		adapter.upsert_profile(TN, id_tag, &profile).await.expect("profile");
+	}
+
+	post(&adapter, "a1~p1", "bob", "#rust 1.90 is out", 'P', 100).await;
+	post(&adapter, "a1~p2", "bob", "private #rust notes", 'F', 110).await;
+	post(&adapter, "a1~p3", "carol", "#rust from a stranger", 'P', 120).await;
+	post(&adapter, "a1~p4", "bob", "#golang too", 'P', 130).await;
+
+	adapter.follow_hashtag(TN, "rust").await.expect("follow");
+	adapter.follow_hashtag(TN, "rust").await.expect("follow again");
+	let followed = adapter.list_followed_hashtags(TN).await.expect("list followed");
+	assert_eq!(followed.len(), 1);
+	assert_eq!(followed[0].tag.as_ref(), "rust");
+
+	let opts = ListActionOptions { followed_hashtags: Some(true), ..Default::default() };
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	assert_eq!(ids(&listed), ["a1~p1"]);
+
+	// A post to an excluded audience comes through when it carries a followed tag.
+	let opts = ListActionOptions {
+		exclude_audiences: Some(Box::from(["bob".to_string(), "carol".to_string()])),
+		..Default::default()
+	};
+	let listed = adapter.list_actions(TN, &opts).await.expect("list home");
+	assert_eq!(ids(&listed), ["a1~p1", "a1~p3"]);
+
+	adapter.unfollow_hashtag(TN, "rust").await.expect("unfollow");
+	let err = adapter.unfollow_hashtag(TN, "rust").await.expect_err("not followed");
+	assert!(matches!(err, Error::NotFound));
+}
+
+#[tokio::test]
+async fn counts_cover_public_posts_in_range_and_trending_is_replaced() {
+	let (adapter, _temp) = create_test_adapter().await;
+	post(&adapter, "a1~p1", "bob", "#eclipse now", 'P', 1000).await;
+	post(&adapter, "a1~p2", "bob", "#eclipse again", 'P', 1010).await;
+	post(&adapter, "a1~p3", "carol", "#Eclipse!", 'P', 1020).await;
+	post(&adapter, "a1~p4", "dave", "#eclipse for friends", 'F', 1030).await;
+	post(&adapter, "a1~p5", "dave", "#weather", 'P', 1040).await;
+	post(&adapter, "a1~p6", "erin", "#eclipse yesterday", 'P', 10).await;
+
+	let counts = adapter
+		.count_hashtags(TN, Timestamp(1000), Timestamp(2000), 10)
+		.await
+		.expect("count");
+	assert_eq!(counts.len(), 2);
+	assert_eq!(counts[0].tag.as_ref(), "eclipse");
+	assert_eq!((counts[0].uses, counts[0].accounts), (3, 2));
+	assert_eq!((counts[1].uses, counts[1].accounts), (1, 1));
+
+	let entry =
+		|tag: &str, score: f64| TrendingHashtag { tag: tag.into(), uses: 3, accounts: 2, score };
+	adapter
+		.replace_trending_hashtags(TN, "1h", &[entry("weather", 1.0), entry("eclipse", 4.0)])
+		.await
+		.expect("store");
+	adapter
+		.replace_trending_hashtags(TN, "24h", &[entry("other", 9.0)])
+		.await
+		.expect("store other window");
+	let trending = adapter.list_trending_hashtags(TN, "1h").await.expect("list");
+	let tags: Vec<&str> = trending.iter().map(|t| t.tag.as_ref()).collect();
+	assert_eq!(tags, ["eclipse", "weather"]);
+
+	adapter.replace_trending_hashtags(TN, "1h", &[]).await.expect("clear");
+	assert!(adapter.list_trending_hashtags(TN, "1h").await.expect("list").is_empty());
+	assert_eq!(adapter.list_trending_hashtags(TN, "24h").await.expect("list").len(), 1);
+}
+
+// vim: ts=4
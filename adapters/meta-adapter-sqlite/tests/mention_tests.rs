@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Mention index — the indexed id_tags surface as `mentions` on the action, and
+//! reindexing replaces them.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::mentions::extract_mentions;
+use cloudillo_types::meta_adapter::{
+	Action, ActionView, ListActionOptions, MetaAdapter, UpdateActionDataOptions,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice.example").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Store an active public post and index its mentions, as the pipeline does.
+async fn post(adapter: &MetaAdapterSqlite, action_id: &str, text: &str) {
+	let content = serde_json::to_string(text).expect("encode");
+	let action = Action {
+		action_id,
+		typ: "POST",
+		sub_typ: None,
+		issuer_tag: "bob.example",
+		parent_id: None,
+		root_id: None,
+		audience_tag: None,
+		content: Some(content.as_str()),
+		attachments: None,
+		subject: None,
+		created_at: Timestamp(100),
+		expires_at: None,
+		visibility: Some('P'),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			action_id,
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+	adapter
+		.update_action_mentions(TN, action_id, &extract_mentions(text))
+		.await
+		.expect("index mentions");
+}
+
+fn mentions(action: &ActionView) -> Vec<&str> {
+	let mut mentions: Vec<&str> = action.mentions.iter().flatten().map(AsRef::as_ref).collect();
+	mentions.sort_unstable();
+	mentions
+}
+
+#[tokio::test]
+async fn mentions_are_listed_on_the_action() {
+	let (adapter, _temp) = create_test_adapter().await;
+	post(&adapter, "a1~p1", "Thanks @Alice.example and @carol.example!").await;
+	post(&adapter, "a1~p2", "write to bob@carol.example").await;
+
+	let opts = ListActionOptions { action_id: Some("a1~p1".into()), ..Default::default() };
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	assert_eq!(mentions(&listed[0]), ["alice.example", "carol.example"]);
+
+	let action = adapter.get_action(TN, "a1~p2").await.expect("get").expect("exists");
+	assert_eq!(action.mentions, None);
+}
+
+#[tokio::test]
+async fn reindexing_replaces_the_mentions() {
+	let (adapter, _temp) = create_test_adapter().await;
+	post(&adapter, "a1~p1", "cc @carol.example").await;
+
+	adapter
+		.update_action_mentions(TN, "a1~p1", &["dave.example".to_string()])
+		.await
+		.expect("reindex");
+	let action = adapter.get_action(TN, "a1~p1").await.expect("get").expect("exists");
+	assert_eq!(mentions(&action), ["dave.example"]);
+
+	adapter.update_action_mentions(TN, "a1~p1", &[]).await.expect("clear");
+	let action = adapter.get_action(TN, "a1~p1").await.expect("get").expect("exists");
+	assert_eq!(action.mentions, None);
+}
+
+// vim: ts=4
@@ -0,0 +1,154 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Moderation — the audit trail lists newest first and filters by subject and
+//! target, and hidden actions drop out of default listings.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	Action, CreateModerationEntry, ListActionOptions, ListModerationLogOptions, MetaAdapter,
+	UpdateActionDataOptions,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "community").await.ok();
+	(adapter, temp_dir)
+}
+
+async fn log(
+	adapter: &MetaAdapterSqlite,
+	decision: &str,
+	subject: &str,
+	target: &str,
+	reports: &[Box<str>],
+) -> u64 {
+	let entry = CreateModerationEntry {
+		decision,
+		subject: Some(subject),
+		target_id_tag: target,
+		moderator_id_tag: "mod",
+		reports,
+		note: Some("checked"),
+	};
+	adapter.create_moderation_entry(TN, &entry).await.expect("log entry")
+}
+
+#[tokio::test]
+async fn the_log_lists_newest_first_and_filters_by_subject_and_target() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let first = log(&adapter, "hide", "a1~p1", "bob", &["a1~r1".into(), "a1~r2".into()]).await;
+	let second = log(&adapter, "warn", "a1~p2", "carol", &[]).await;
+	let third = log(&adapter, "remove", "a1~p1", "bob", &[]).await;
+
+	let all = adapter
+		.list_moderation_log(TN, &ListModerationLogOptions::default())
+		.await
+		.expect("list");
+	let ids: Vec<u64> = all.iter().map(|e| e.entry_id).collect();
+	assert_eq!(ids, [third, second, first]);
+	assert_eq!(all[2].decision.as_ref(), "hide");
+	assert_eq!(all[2].reports, ["a1~r1".into(), "a1~r2".into()] as [Box<str>; 2]);
+	assert_eq!(all[2].note.as_deref(), Some("checked"));
+
+	let on_p1 = adapter
+		.list_moderation_log(
+			TN,
+			&ListModerationLogOptions { subject: Some("a1~p1".into()), ..Default::default() },
+		)
+		.await
+		.expect("by subject");
+	assert_eq!(on_p1.len(), 2);
+
+	let carol = adapter
+		.list_moderation_log(
+			TN,
+			&ListModerationLogOptions { target_id_tag: Some("carol".into()), ..Default::default() },
+		)
+		.await
+		.expect("by target");
+	assert_eq!(carol.len(), 1);
+	assert_eq!(carol[0].entry_id, second);
+
+	let older = adapter
+		.list_moderation_log(
+			TN,
+			&ListModerationLogOptions {
+				before: Some(second),
+				limit: Some(5),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("page");
+	assert_eq!(older.len(), 1);
+	assert_eq!(older[0].entry_id, first);
+}
+
+#[tokio::test]
+async fn hidden_actions_are_left_out_of_default_listings() {
+	let (adapter, _temp) = create_test_adapter().await;
+	for action_id in ["a1~p1", "a1~p2"] {
+		let action = Action {
+			action_id,
+			typ: "POST",
+			sub_typ: None,
+			issuer_tag: "bob",
+			parent_id: None,
+			root_id: None,
+			audience_tag: Some("community"),
+			content: Some("hello"),
+			attachments: None,
+			subject: None,
+			created_at: Timestamp(100),
+			expires_at: None,
+			visibility: Some('P'),
+			flags: None,
+			x: None,
+		};
+		adapter.create_action(TN, &action, None).await.expect("create action");
+		adapter
+			.update_action_data(
+				TN,
+				action_id,
+				&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+			)
+			.await
+			.expect("activate");
+	}
+	adapter
+		.update_action_data(
+			TN,
+			"a1~p2",
+			&UpdateActionDataOptions { status: Patch::Value('H'), ..Default::default() },
+		)
+		.await
+		.expect("hide");
+
+	let opts = ListActionOptions { typ: Some(vec!["POST".into()]), ..Default::default() };
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	let ids: Vec<&str> = listed.iter().map(|a| a.action_id.as_ref()).collect();
+	assert_eq!(ids, ["a1~p1"]);
+
+	let opts = ListActionOptions {
+		typ: Some(vec!["POST".into()]),
+		status: Some(vec!["H".into()]),
+		..Default::default()
+	};
+	let hidden = adapter.list_actions(TN, &opts).await.expect("list hidden");
+	assert_eq!(hidden.len(), 1);
+}
+
+// vim: ts=4
@@ -0,0 +1,163 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Ranked feed inputs — the reader's interactions per issuer, the `seen`
+//! read-marker surfacing as `stat.seenAt`, and the candidate set of followed and
+//! connected issuers.
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	Action, ListActionOptions, MetaAdapter, ProfileConnectionStatus, UpdateActionDataOptions,
+	UpsertProfileFields,
+};
+use cloudillo_types::types::{Patch, Timestamp, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	adapter.create_tenant(TN, "alice").await.ok();
+	(adapter, temp_dir)
+}
+
+/// Store an active action.
+async fn store(
+	adapter: &MetaAdapterSqlite,
+	action_id: &str,
+	typ: &str,
+	issuer: &str,
+	parent_id: Option<&str>,
+	subject: Option<&str>,
+	created_at: i64,
+) {
+	let action = Action {
+		action_id,
+		typ,
+		sub_typ: None,
+		issuer_tag: issuer,
+		parent_id,
+		root_id: parent_id,
+		audience_tag: None,
+		content: Some("\"text\""),
+		attachments: None,
+		subject,
+		created_at: Timestamp(created_at),
+		expires_at: None,
+		visibility: Some('P'),
+		flags: None,
+		x: None,
+	};
+	adapter.create_action(TN, &action, None).await.expect("create action");
+	adapter
+		.update_action_data(
+			TN,
+			action_id,
+			&UpdateActionDataOptions { status: Patch::Value('A'), ..Default::default() },
+		)
+		.await
+		.expect("activate");
+}
+
+#[tokio::test]
+async fn interactions_are_counted_per_issuer_of_the_answered_action() {
+	let (adapter, _temp) = create_test_adapter().await;
+	store(&adapter, "a1~bob1", "POST", "bob", None, None, 100).await;
+	store(&adapter, "a1~bob2", "POST", "bob", None, None, 110).await;
+	store(&adapter, "a1~carol", "POST", "carol", None, None, 120).await;
+	store(&adapter, "a1~own", "POST", "alice", None, None, 130).await;
+
+	store(&adapter, "a1~r1", "REACT", "alice", None, Some("a1~bob1"), 200).await;
+	store(&adapter, "a1~c1", "CMNT", "alice", Some("a1~bob2"), None, 210).await;
+	store(&adapter, "a1~s1", "REPOST", "alice", None, Some("a1~carol"), 220).await;
+	// Answers to the reader's own posts, someone else's answers, and old ones
+	// do not count.
+	store(&adapter, "a1~c2", "CMNT", "alice", Some("a1~own"), None, 230).await;
+	store(&adapter, "a1~r2", "REACT", "dave", None, Some("a1~bob1"), 240).await;
+	store(&adapter, "a1~r3", "REACT", "alice", None, Some("a1~carol"), 50).await;
+
+	let mut counts: Vec<(String, u32)> = adapter
+		.count_interactions(TN, "alice", Timestamp(100))
+		.await
+		.expect("count")
+		.into_iter()
+		.map(|c| (c.id_tag.to_string(), c.interactions))
+		.collect();
+	counts.sort_unstable();
+	assert_eq!(counts, [("bob".to_string(), 2), ("carol".to_string(), 1)]);
+}
+
+#[tokio::test]
+async fn seen_marker_is_forward_only_and_listed_in_stat() {
+	let (adapter, _temp) = create_test_adapter().await;
+	store(&adapter, "a1~p1", "POST", "bob", None, None, 100).await;
+
+	let seen_at = |actions: &[cloudillo_types::meta_adapter::ActionView]| {
+		actions[0].stat.as_ref().and_then(|s| s.get("seenAt")).cloned()
+	};
+	let opts = ListActionOptions { action_id: Some("a1~p1".into()), ..Default::default() };
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	assert_eq!(seen_at(&listed), None);
+
+	adapter.set_read_marker(TN, "seen", "a1~p1", 5000).await.expect("mark seen");
+	adapter.set_read_marker(TN, "seen", "a1~p1", 4000).await.expect("earlier mark");
+	let listed = adapter.list_actions(TN, &opts).await.expect("list");
+	let expected = Timestamp(5000).to_iso_string();
+	assert_eq!(seen_at(&listed), Some(serde_json::Value::String(expected)));
+
+	let action = adapter.get_action(TN, "a1~p1").await.expect("get").expect("exists");
+	assert!(action.stat.as_ref().and_then(|s| s.get("seenAt")).is_some());
+}
+
+#[tokio::test]
+async fn related_issuers_reach_past_a_flood_of_newer_posts() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let friend = UpsertProfileFields {
+		name: Patch::Value("bob".into()),
+		connected: Patch::Value(ProfileConnectionStatus::Connected),
+		..Default::default()
+	};
+	adapter.upsert_profile(TN, "bob", &friend).await.expect("profile");
+	let followed = UpsertProfileFields {
+		name: Patch::Value("carol".into()),
+		following: Patch::Value(true),
+		..Default::default()
+	};
+	adapter.upsert_profile(TN, "carol", &followed).await.expect("profile");
+
+	store(&adapter, "a1~bob", "POST", "bob", None, None, 100).await;
+	store(&adapter, "a1~carol", "POST", "carol", None, None, 110).await;
+	// `list` returns one row past `limit` (the caller's has-more probe).
+	for i in 0..=300 {
+		store(&adapter, &format!("a1~s{i}"), "POST", "dave", None, None, 200 + i).await;
+	}
+
+	let opts = ListActionOptions {
+		typ: Some(vec!["POST".into()]),
+		limit: Some(300),
+		..Default::default()
+	};
+	let newest = adapter.list_actions(TN, &opts).await.expect("list newest");
+	assert!(newest.iter().all(|a| a.issuer.id_tag.as_ref() == "dave"));
+
+	let opts = ListActionOptions { related_issuers: Some(true), ..opts };
+	let mut related: Vec<String> = adapter
+		.list_actions(TN, &opts)
+		.await
+		.expect("list related")
+		.into_iter()
+		.map(|a| a.action_id.to_string())
+		.collect();
+	related.sort_unstable();
+	assert_eq!(related, ["a1~bob", "a1~carol"]);
+}
+
+// vim: ts=4
@@ -33,6 +33,8 @@ async fn seed_share_ref(adapter: &MetaAdapterSqlite, tn_id: TnId, ref_id: &str)
 		resource_id: Some("file-abc".to_string()),
 		access_level: Some('R'),
 		params: None,
+		link_mode: None,
+		password: None,
 	};
 	adapter.create_ref(tn_id, ref_id, &opts).await.expect("seed ref");
 }
@@ -150,6 +152,8 @@ async fn test_update_ref_resurrect_count_blocked_by_sql_guard() {
 		resource_id: Some("file-xyz".to_string()),
 		access_level: Some('R'),
 		params: None,
+		link_mode: None,
+		password: None,
 	};
 	adapter.create_ref(tn_id, "ref-used", &opts).await.expect("seed used ref");
 
@@ -178,6 +182,8 @@ async fn test_update_ref_resurrect_zero_count_returns_validation_error() {
 		resource_id: Some("file-zzz".to_string()),
 		access_level: Some('R'),
 		params: None,
+		link_mode: None,
+		password: None,
 	};
 	adapter.create_ref(tn_id, "ref-used-2", &opts).await.expect("seed used ref");
 
@@ -207,6 +213,8 @@ async fn test_update_ref_non_share_file_adapter_allows() {
 		resource_id: None,
 		access_level: None,
 		params: None,
+		link_mode: None,
+		password: None,
 	};
 	adapter.create_ref(tn_id, "ref-inv", &opts).await.expect("seed invite ref");
 
@@ -221,4 +229,84 @@ async fn test_update_ref_non_share_file_adapter_allows() {
 	assert_eq!(updated.description.as_deref(), Some("hijacked"));
 }
 
+#[tokio::test]
+async fn test_share_link_password_is_hashed_and_checked() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let tn_id = TnId(1);
+	adapter.create_tenant(tn_id, "alice").await.expect("create tenant");
+
+	let opts = CreateRefOptions {
+		typ: "share.file".to_string(),
+		resource_id: Some("file-abc".to_string()),
+		access_level: Some('R'),
+		password: Some("open sesame".to_string()),
+		..Default::default()
+	};
+	let created = adapter.create_ref(tn_id, "ref-pw", &opts).await.expect("create");
+	assert!(created.password_protected);
+
+	adapter
+		.check_ref_password("ref-pw", "open sesame")
+		.await
+		.expect("right password");
+	assert!(matches!(
+		adapter.check_ref_password("ref-pw", "open sesam").await,
+		Err(Error::PermissionDenied)
+	));
+	// An unknown ref answers exactly like a wrong password.
+	assert!(matches!(
+		adapter.check_ref_password("ref-missing", "open sesame").await,
+		Err(Error::PermissionDenied)
+	));
+
+	let (_, _, validated) =
+		adapter.validate_ref("ref-pw", &["share.file"]).await.expect("validate");
+	assert!(validated.password_protected);
+
+	// Clearing the password opens the link; a ref without one accepts anything.
+	let patch = UpdateRefOptions { password: Patch::Null, ..Default::default() };
+	let updated = adapter.update_ref(tn_id, "ref-pw", &patch).await.expect("clear password");
+	assert!(!updated.password_protected);
+	adapter.check_ref_password("ref-pw", "").await.expect("no password set");
+
+	let patch =
+		UpdateRefOptions { password: Patch::Value("new".to_string()), ..Default::default() };
+	let updated = adapter.update_ref(tn_id, "ref-pw", &patch).await.expect("set password");
+	assert!(updated.password_protected);
+	adapter.check_ref_password("ref-pw", "new").await.expect("new password");
+	assert!(adapter.check_ref_password("ref-pw", "open sesame").await.is_err());
+}
+
+#[tokio::test]
+async fn test_share_link_mode_round_trips() {
+	let (adapter, _temp) = create_test_adapter().await;
+	let tn_id = TnId(1);
+	adapter.create_tenant(tn_id, "alice").await.expect("create tenant");
+
+	let opts = CreateRefOptions {
+		typ: "share.file".to_string(),
+		resource_id: Some("folder-abc".to_string()),
+		access_level: Some('W'),
+		link_mode: Some('U'),
+		count: None,
+		..Default::default()
+	};
+	let created = adapter.create_ref(tn_id, "ref-drop", &opts).await.expect("create");
+	assert_eq!(created.link_mode, Some('U'));
+	assert!(!created.password_protected);
+
+	let read = adapter.get_ref(tn_id, "ref-drop").await.expect("get").expect("present");
+	assert_eq!(read.link_mode, Some('U'));
+	let (_, _, used) = adapter.use_ref("ref-drop", &["share.file"]).await.expect("use");
+	assert_eq!(used.link_mode, Some('U'));
+
+	// The mode survives an unrelated patch.
+	let patch = UpdateRefOptions {
+		description: Patch::Value("client uploads".to_string()),
+		..Default::default()
+	};
+	let updated = adapter.update_ref(tn_id, "ref-drop", &patch).await.expect("update");
+	assert_eq!(updated.link_mode, Some('U'));
+}
+
 // vim: ts=4
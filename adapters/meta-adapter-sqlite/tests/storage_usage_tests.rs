@@ -0,0 +1,132 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Storage usage accounting — `read_storage_usage` counts each local blob once,
+//! splits out the trash, and drops files as soon as they are tombstoned.
+#![allow(clippy::panic, clippy::expect_used, clippy::unwrap_used)]
+
+use std::sync::Arc;
+
+use cloudillo_meta_adapter_sqlite::MetaAdapterSqlite;
+use cloudillo_types::meta_adapter::{
+	CreateFile, FileId, FileStatus, FileVariant, MetaAdapter, StorageUsage, TRASH_PARENT_ID,
+	UpdateFileOptions,
+};
+use cloudillo_types::types::{Patch, TnId};
+use cloudillo_types::worker::WorkerPool;
+use tempfile::TempDir;
+
+const TN: TnId = TnId(1);
+
+async fn create_test_adapter() -> (MetaAdapterSqlite, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let worker_pool = Arc::new(WorkerPool::new(1, 1, 1));
+	let adapter = MetaAdapterSqlite::new(worker_pool, temp_dir.path())
+		.await
+		.expect("Failed to create adapter");
+	(adapter, temp_dir)
+}
+
+/// Create a finalized file `file_id` carrying `variants` as
+/// `(variant_id, variant, size, global)`.
+async fn add_file(
+	adapter: &MetaAdapterSqlite,
+	file_id: &str,
+	variants: &[(&str, &str, u64, bool)],
+) {
+	let f_id = match adapter
+		.create_file(
+			TN,
+			CreateFile {
+				content_type: "image/jpeg".into(),
+				file_name: format!("{file_id}.jpg").into(),
+				file_tp: Some("BLOB".into()),
+				status: Some(FileStatus::Pending),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("create file")
+	{
+		FileId::FId(f_id) => f_id,
+		FileId::FileId(_) => panic!("expected a pending file"),
+	};
+	for (variant_id, variant, size, global) in variants {
+		adapter
+			.create_file_variant(
+				TN,
+				f_id,
+				FileVariant {
+					variant_id,
+					variant,
+					format: "webp",
+					size: *size,
+					resolution: (100, 100),
+					available: true,
+					global: *global,
+					duration: None,
+					bitrate: None,
+					page_count: None,
+				},
+			)
+			.await
+			.expect("create variant");
+	}
+	adapter.finalize_file(TN, f_id, file_id).await.expect("finalize file");
+}
+
+#[tokio::test]
+async fn shared_blobs_count_once_and_the_shared_store_not_at_all() {
+	let (adapter, _temp) = create_test_adapter().await;
+	adapter.create_tenant(TN, "alice.example.com").await.expect("create tenant");
+	assert_eq!(adapter.read_storage_usage(TN).await.unwrap(), StorageUsage::default());
+
+	add_file(&adapter, "f1~a", &[("b1~a", "orig", 1000, false), ("b1~tn", "vis.tn", 100, false)])
+		.await;
+	add_file(&adapter, "f1~b", &[("b1~b", "orig", 2000, false), ("b1~tn", "vis.tn", 100, false)])
+		.await;
+	add_file(&adapter, "f1~c", &[("b1~g", "vis.sd", 5000, true)]).await;
+
+	let usage = adapter.read_storage_usage(TN).await.unwrap();
+	assert_eq!(
+		usage,
+		StorageUsage { total: 3100, originals: 3000, variants: 100, trash: 0, blobs: 3 }
+	);
+	// Per tenant: another tenant's usage is unaffected.
+	assert_eq!(adapter.read_storage_usage(TnId(2)).await.unwrap(), StorageUsage::default());
+}
+
+#[tokio::test]
+async fn trash_still_counts_until_the_file_is_deleted() {
+	let (adapter, _temp) = create_test_adapter().await;
+	adapter.create_tenant(TN, "alice.example.com").await.expect("create tenant");
+	add_file(&adapter, "f1~a", &[("b1~a", "orig", 1000, false), ("b1~tn", "vis.tn", 100, false)])
+		.await;
+	add_file(&adapter, "f1~b", &[("b1~b", "orig", 2000, false), ("b1~tn", "vis.tn", 100, false)])
+		.await;
+
+	adapter
+		.update_file_data(
+			TN,
+			"f1~b",
+			&UpdateFileOptions {
+				parent_id: Patch::Value(TRASH_PARENT_ID.to_string()),
+				..Default::default()
+			},
+		)
+		.await
+		.expect("move to trash");
+	let usage = adapter.read_storage_usage(TN).await.unwrap();
+	assert_eq!(usage.total, 3100);
+	// The thumbnail is still in use by a live file, so only the original is trash.
+	assert_eq!(usage.trash, 2000);
+
+	adapter.delete_file(TN, "f1~b").await.expect("delete file");
+	let usage = adapter.read_storage_usage(TN).await.unwrap();
+	assert_eq!(
+		usage,
+		StorageUsage { total: 1100, originals: 1000, variants: 100, trash: 0, blobs: 2 }
+	);
+}
+
+// vim: ts=4
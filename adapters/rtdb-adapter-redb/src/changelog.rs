@@ -0,0 +1,212 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Per-database write sequence and change log.
+//!
+//! Every write a transaction makes takes the next number in its database's
+//! sequence and is appended to [`storage::TABLE_CHANGES`] under it, inside the
+//! same redb transaction as the write itself, so the log can never disagree with
+//! the documents. A client that was offline replays the log from the last
+//! sequence it saw instead of re-querying everything.
+//!
+//! Retention is bounded: each commit drops the entries that fell more than the
+//! configured number of writes behind, and a client whose last-seen sequence
+//! lies before the truncation point is told to resync.
+
+use crate::error::from_redb_error;
+use crate::storage;
+use cloudillo_types::prelude::*;
+use cloudillo_types::rtdb_adapter::{ChangeEvent, ChangeRecord, ChangeSet};
+use redb::{ReadableDatabase, ReadableTable};
+
+/// Metadata key holding a database's current sequence number.
+fn seq_key(db_prefix: &str) -> String {
+	format!("{}_sync/seq", db_prefix)
+}
+
+/// Metadata key holding the highest truncated sequence number. Every entry
+/// after it is still in the log.
+fn floor_key(db_prefix: &str) -> String {
+	format!("{}_sync/floor", db_prefix)
+}
+
+/// Zero-padded so that key order is sequence order.
+fn change_key(db_prefix: &str, seq: u64) -> String {
+	format!("{}{:020}", db_prefix, seq)
+}
+
+fn read_counter<T>(meta: &T, key: &str) -> ClResult<u64>
+where
+	T: ReadableTable<&'static str, &'static str>,
+{
+	match meta.get(key).map_err(from_redb_error)? {
+		Some(v) => v
+			.value()
+			.parse()
+			.map_err(|_| Error::Internal(format!("corrupt rtdb sequence counter {}", key))),
+		None => Ok(0),
+	}
+}
+
+/// The last sequence number a database has handed out, 0 before its first
+/// logged write.
+pub(crate) fn current_seq<T>(meta: &T, db_prefix: &str) -> ClResult<u64>
+where
+	T: ReadableTable<&'static str, &'static str>,
+{
+	read_counter(meta, &seq_key(db_prefix))
+}
+
+/// Append one write to the log and stamp the document's version with it.
+///
+/// `old_data` is left out of the logged event: a syncing client only applies
+/// the new state, and keeping it would double the log for nothing.
+pub(crate) fn record(
+	tx: &redb::WriteTransaction,
+	db_prefix: &str,
+	doc_key: &str,
+	seq: u64,
+	event: &ChangeEvent,
+) -> ClResult<()> {
+	let logged = match event {
+		ChangeEvent::Update { path, data, .. } => {
+			ChangeEvent::Update { path: path.clone(), data: data.clone(), old_data: None }
+		}
+		ChangeEvent::Delete { path, .. } => {
+			ChangeEvent::Delete { path: path.clone(), old_data: None }
+		}
+		other => other.clone(),
+	};
+	let json = serde_json::to_string(&logged)?;
+
+	let mut changes = tx.open_table(storage::TABLE_CHANGES).map_err(from_redb_error)?;
+	changes
+		.insert(change_key(db_prefix, seq).as_str(), json.as_str())
+		.map_err(from_redb_error)?;
+
+	let mut versions = tx.open_table(storage::TABLE_VERSIONS).map_err(from_redb_error)?;
+	versions.insert(doc_key, seq.to_string().as_str()).map_err(from_redb_error)?;
+
+	Ok(())
+}
+
+/// Persist the sequence a transaction reached and truncate the log to
+/// `retention` entries.
+///
+/// Runs inside the committing transaction, so truncation costs each commit about
+/// as many removals as it made writes.
+pub(crate) fn finish(
+	tx: &redb::WriteTransaction,
+	db_prefix: &str,
+	seq: u64,
+	retention: u64,
+) -> ClResult<()> {
+	let mut meta = tx.open_table(storage::TABLE_METADATA).map_err(from_redb_error)?;
+	meta.insert(seq_key(db_prefix).as_str(), seq.to_string().as_str())
+		.map_err(from_redb_error)?;
+
+	let floor = read_counter(&meta, &floor_key(db_prefix))?;
+	let new_floor = seq.saturating_sub(retention);
+	if new_floor <= floor {
+		return Ok(());
+	}
+
+	let mut changes = tx.open_table(storage::TABLE_CHANGES).map_err(from_redb_error)?;
+	let mut versions = tx.open_table(storage::TABLE_VERSIONS).map_err(from_redb_error)?;
+
+	let start = change_key(db_prefix, floor.saturating_add(1));
+	let end = change_key(db_prefix, new_floor);
+	let dropped: Vec<(String, String)> = {
+		let range = changes.range(start.as_str()..=end.as_str()).map_err(from_redb_error)?;
+		let mut dropped = Vec::new();
+		for item in range {
+			let (key, value) = item.map_err(from_redb_error)?;
+			dropped.push((key.value().to_string(), value.value().to_string()));
+		}
+		dropped
+	};
+
+	for (key, json) in dropped {
+		changes.remove(key.as_str()).map_err(from_redb_error)?;
+
+		// A deleted document's version is a tombstone, there only so a replayed
+		// write can see the delete. Once the delete is truncated every client
+		// that could have missed it resyncs anyway. A tombstone overwritten by a
+		// later write holds that write's sequence and stays.
+		if let Ok(ChangeEvent::Delete { path, .. }) = serde_json::from_str::<ChangeEvent>(&json) {
+			let doc_key = format!("{}{}", db_prefix, path);
+			let entry_seq = key[db_prefix.len()..].trim_start_matches('0');
+			let is_tombstone = versions
+				.get(doc_key.as_str())
+				.map_err(from_redb_error)?
+				.is_some_and(|v| v.value() == entry_seq);
+			if is_tombstone {
+				versions.remove(doc_key.as_str()).map_err(from_redb_error)?;
+			}
+		}
+	}
+
+	meta.insert(floor_key(db_prefix).as_str(), new_floor.to_string().as_str())
+		.map_err(from_redb_error)?;
+
+	Ok(())
+}
+
+/// A document's stored version, see `Transaction::version`.
+pub(crate) fn version<T>(versions: &T, doc_key: &str) -> ClResult<Option<u64>>
+where
+	T: ReadableTable<&'static str, &'static str>,
+{
+	match versions.get(doc_key).map_err(from_redb_error)? {
+		Some(v) => v
+			.value()
+			.parse()
+			.map(Some)
+			.map_err(|_| Error::Internal(format!("corrupt rtdb version for {}", doc_key))),
+		None => Ok(None),
+	}
+}
+
+/// Read the changes after `since`, at most `limit` of them.
+///
+/// Synchronous — run on the blocking pool.
+pub(crate) fn changes_since(
+	db: &redb::Database,
+	db_prefix: &str,
+	since: u64,
+	limit: usize,
+) -> ClResult<ChangeSet> {
+	let tx = db.begin_read().map_err(from_redb_error)?;
+	let meta = tx.open_table(storage::TABLE_METADATA).map_err(from_redb_error)?;
+	let seq = current_seq(&meta, db_prefix)?;
+	let floor = read_counter(&meta, &floor_key(db_prefix))?;
+
+	// Behind the truncation point, or ahead of the database altogether — a client
+	// that last synced against a database since deleted and recreated
+	if since < floor || since > seq {
+		return Ok(ChangeSet::resync(seq));
+	}
+
+	let table = tx.open_table(storage::TABLE_CHANGES).map_err(from_redb_error)?;
+	let start = change_key(db_prefix, since.saturating_add(1));
+	let end = change_key(db_prefix, seq);
+	let mut changes = Vec::new();
+	let mut has_more = false;
+	for item in table.range(start.as_str()..=end.as_str()).map_err(from_redb_error)? {
+		if changes.len() >= limit {
+			has_more = true;
+			break;
+		}
+		let (key, value) = item.map_err(from_redb_error)?;
+		let entry_seq = key.value()[db_prefix.len()..]
+			.parse()
+			.map_err(|_| Error::Internal(format!("corrupt rtdb change key {}", key.value())))?;
+		let event: ChangeEvent = serde_json::from_str(value.value())?;
+		changes.push(ChangeRecord { seq: entry_seq, event });
+	}
+
+	let resume = changes.last().map_or(seq, |c| c.seq);
+	Ok(ChangeSet { changes, seq: resume, has_more, resync: false })
+}
+
+// vim: ts=4
@@ -3,12 +3,140 @@
 
 use crate::{DatabaseInstance, storage};
 use cloudillo_types::error::ClResult;
+use cloudillo_types::rtdb_adapter::IndexSpec;
 use cloudillo_types::types::TnId;
 use redb::ReadableTable;
 use serde_json::Value;
 use std::sync::Arc;
 
-/// Create an index on a field.
+/// Separator between the encoded field values of a composite index key.
+pub(crate) const COMPOSITE_SEP: char = '\x1f';
+
+/// An index as the adapter keeps it in memory and in `{path}/_meta/indexes`.
+///
+/// A single-field index keeps the original key layout,
+/// `{root}/_idx/{field}/{value}/{doc_id}`, so indexes created before composite
+/// support keep working untouched. A composite index lives under
+/// `{root}/_cidx/{f1+f2+..}/{enc1}\x1f{enc2}../{doc_id}`, with every value run
+/// through [`storage::sortable_key`] so the key order is the value order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexDef {
+	pub fields: Vec<Box<str>>,
+}
+
+impl IndexDef {
+	pub fn is_composite(&self) -> bool {
+		self.fields.len() > 1
+	}
+
+	/// The single indexed field, for a non-composite index.
+	pub fn single_field(&self) -> Option<&str> {
+		match self.fields.as_slice() {
+			[field] => Some(field),
+			_ => None,
+		}
+	}
+
+	/// Key segment naming a composite index.
+	pub fn name(&self) -> String {
+		self.fields.join("+")
+	}
+
+	/// Key prefix shared by every entry of a composite index.
+	pub fn composite_base(&self, root: &str) -> String {
+		format!("{}/_cidx/{}/", root, self.name())
+	}
+
+	/// Parse one entry of the persisted index list: a field name, or an array of
+	/// field names for a composite index.
+	pub fn from_stored(value: &Value) -> Option<Self> {
+		match value {
+			Value::String(field) => Some(Self { fields: vec![field.as_str().into()] }),
+			Value::Array(fields) => {
+				let fields: Option<Vec<Box<str>>> =
+					fields.iter().map(|f| f.as_str().map(Into::into)).collect();
+				fields.filter(|f| !f.is_empty()).map(|fields| Self { fields })
+			}
+			_ => None,
+		}
+	}
+
+	/// The persisted form, the inverse of [`Self::from_stored`].
+	pub fn to_stored(&self) -> Value {
+		match self.single_field() {
+			Some(field) => Value::String(field.to_string()),
+			None => {
+				Value::Array(self.fields.iter().map(|f| Value::String(f.to_string())).collect())
+			}
+		}
+	}
+}
+
+impl From<&IndexSpec> for IndexDef {
+	fn from(spec: &IndexSpec) -> Self {
+		Self { fields: spec.fields.iter().map(|f| f.as_str().into()).collect() }
+	}
+}
+
+/// Every index key a document contributes to one index.
+///
+/// A single-field index skips documents without the field. A composite index
+/// covers every document — a missing field encodes as the lowest value — because
+/// a sort served from it must not lose the documents a scan would have sorted
+/// first. Array fields fan out to one entry per scalar element; a document with
+/// several array fields gets the cross product.
+pub(crate) fn index_entry_keys(
+	root: &str,
+	def: &IndexDef,
+	doc: &Value,
+	doc_id: &str,
+) -> Vec<String> {
+	if let Some(field) = def.single_field() {
+		return match doc.get(field) {
+			Some(value) => storage::values_to_index_strings(value)
+				.into_iter()
+				.map(|value_str| format!("{}/_idx/{}/{}/{}", root, field, value_str, doc_id))
+				.collect(),
+			None => Vec::new(),
+		};
+	}
+
+	let mut tuples = vec![String::new()];
+	for (i, field) in def.fields.iter().enumerate() {
+		let value = doc.get(field.as_ref());
+		let mut components: Vec<String> = match value {
+			Some(Value::Array(arr)) => arr
+				.iter()
+				.filter(|v| !v.is_array() && !v.is_object())
+				.map(|v| storage::sortable_key(Some(v)))
+				.collect(),
+			_ => Vec::new(),
+		};
+		components.sort();
+		components.dedup();
+		// Scalars, and arrays without scalar elements, index as a single value
+		if components.is_empty() {
+			components.push(storage::sortable_key(value));
+		}
+
+		tuples = tuples
+			.iter()
+			.flat_map(|prefix| {
+				components.iter().map(move |c| {
+					if i == 0 { c.clone() } else { format!("{}{}{}", prefix, COMPOSITE_SEP, c) }
+				})
+			})
+			.collect();
+	}
+
+	let base = def.composite_base(root);
+	tuples
+		.into_iter()
+		.map(|tuple| format!("{}{}/{}", base, tuple, doc_id))
+		.collect()
+}
+
+/// Create an index on one or more fields.
 ///
 /// Entire body is sync redb work wrapped in `spawn_blocking` so
 /// `begin_write()` never parks the tokio worker.
@@ -17,49 +145,54 @@ pub async fn create_index_impl(
 	tn_id: TnId,
 	db_id: &str,
 	path: &str,
-	field: &str,
+	spec: &IndexSpec,
 	per_tenant_files: bool,
 ) -> ClResult<()> {
+	if spec.fields.is_empty() || spec.fields.iter().any(|f| f.is_empty() || f.contains('/')) {
+		return Err(cloudillo_types::error::Error::ValidationError(
+			"index fields must be non-empty top-level field names".into(),
+		));
+	}
+
 	let instance = Arc::clone(instance);
 	let db_id = db_id.to_string();
 	let path = path.to_string();
-	let field = field.to_string();
+	let def = IndexDef::from(spec);
 
 	tokio::task::spawn_blocking(move || -> ClResult<()> {
 		use crate::error::from_redb_error;
 
-		let meta_key = if per_tenant_files {
-			format!("{}/_meta/indexes", path)
-		} else {
-			format!("{}/{}/_meta/indexes", tn_id.0, path)
-		};
+		let root = storage::index_root(tn_id, &path, per_tenant_files);
+		let meta_key = format!("{}/_meta/indexes", root);
 
 		let db = instance.db()?;
 		let tx = db.begin_write().map_err(from_redb_error)?;
 
 		// Load existing indexes
-		let mut indexes: Vec<String> = {
+		let mut indexes: Vec<IndexDef> = {
 			let meta_table = tx.open_table(storage::TABLE_METADATA).map_err(from_redb_error)?;
 
 			match meta_table.get(meta_key.as_str()) {
 				Ok(Some(v)) => {
-					let json_str = v.value().to_string();
-					serde_json::from_str(&json_str)?
+					let stored: Vec<Value> = serde_json::from_str(v.value())?;
+					stored.iter().filter_map(IndexDef::from_stored).collect()
 				}
 				Ok(None) => Vec::new(),
 				Err(e) => return Err(from_redb_error(e).into()),
 			}
 		};
 
-		// Add field if not already indexed
-		if !indexes.contains(&field) {
-			indexes.push(field.clone());
+		// Already indexed: nothing to build
+		if indexes.contains(&def) {
+			return Ok(());
 		}
+		indexes.push(def.clone());
 
 		// Save updated indexes
 		{
 			let mut meta_table = tx.open_table(storage::TABLE_METADATA).map_err(from_redb_error)?;
-			let json = serde_json::to_string(&indexes)?;
+			let stored: Vec<Value> = indexes.iter().map(IndexDef::to_stored).collect();
+			let json = serde_json::to_string(&stored)?;
 			meta_table.insert(meta_key.as_str(), json.as_str()).map_err(from_redb_error)?;
 		}
 
@@ -91,17 +224,8 @@ pub async fn create_index_impl(
 				}
 
 				let doc: Value = serde_json::from_str(value.value())?;
-
-				if let Some(field_value) = doc.get(&field) {
-					let doc_id = remainder.to_string();
-					for value_str in storage::values_to_index_strings(field_value) {
-						let index_key = if per_tenant_files {
-							format!("{}/_idx/{}/{}/{}", path, field, value_str, doc_id)
-						} else {
-							format!("{}/{}/_idx/{}/{}/{}", tn_id.0, path, field, value_str, doc_id)
-						};
-						index_table.insert(index_key.as_str(), "").map_err(from_redb_error)?;
-					}
+				for index_key in index_entry_keys(&root, &def, &doc, remainder) {
+					index_table.insert(index_key.as_str(), "").map_err(from_redb_error)?;
 				}
 			}
 		}
@@ -109,14 +233,14 @@ pub async fn create_index_impl(
 		tx.commit().map_err(from_redb_error)?;
 
 		// Update in-memory cache (sync RwLock — safe here since we're on the blocking pool).
-		// Idempotent: if this field is already indexed, don't duplicate it.
+		// Idempotent: if this index already exists, don't duplicate it.
 		{
-			let mut indexed_fields = instance.indexed_fields.write().map_err(|_| {
-				cloudillo_types::error::Error::Internal("indexed_fields rwlock poisoned".into())
+			let mut cached = instance.indexes.write().map_err(|_| {
+				cloudillo_types::error::Error::Internal("indexes rwlock poisoned".into())
 			})?;
-			let entry = indexed_fields.entry(path.into()).or_default();
-			if !entry.iter().any(|f| f.as_ref() == field.as_str()) {
-				entry.push(field.into());
+			let entry = cached.entry(path.into()).or_default();
+			if !entry.contains(&def) {
+				entry.push(def);
 			}
 		}
 
@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use crate::index::IndexDef;
 use crate::storage;
 use cloudillo_types::prelude::*;
 use cloudillo_types::rtdb_adapter::{ChangeEvent, LockInfo};
@@ -11,7 +12,7 @@ use std::sync::RwLock;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::warn;
 
-type IndexedFieldsMap = HashMap<Box<str>, Vec<Box<str>>>;
+type IndexMap = HashMap<Box<str>, Vec<IndexDef>>;
 
 /// An active database instance with real-time subscription support
 #[derive(Debug)]
@@ -27,7 +28,7 @@ pub struct DatabaseInstance {
 	///
 	/// Sync `RwLock` so the write-transaction actor (which runs on a
 	/// blocking-pool thread) can read it without bouncing through async — the
-	/// same reasoning as `indexed_fields` and `locks` below. `None` only ever
+	/// same reasoning as `indexes` and `locks` below. `None` only ever
 	/// while a compaction of this file is in flight, which no operation can
 	/// observe: operations hold a read guard on the file's barrier, the
 	/// compaction holds the write guard.
@@ -39,13 +40,13 @@ pub struct DatabaseInstance {
 	/// Last access timestamp (Unix seconds)
 	pub(crate) last_accessed: Arc<AtomicU64>,
 
-	/// Cached indexed fields per collection. Sync `RwLock` so the
+	/// Cached index definitions per collection. Sync `RwLock` so the
 	/// write-transaction actor (which runs on a blocking-pool thread)
 	/// can read it without bouncing through async.
-	pub(crate) indexed_fields: Arc<RwLock<IndexedFieldsMap>>,
+	pub(crate) indexes: Arc<RwLock<IndexMap>>,
 
 	/// In-memory locks on document paths (ephemeral, not persisted).
-	/// Sync `RwLock` — same reasoning as `indexed_fields`.
+	/// Sync `RwLock` — same reasoning as `indexes`.
 	pub(crate) locks: Arc<RwLock<HashMap<Box<str>, LockInfo>>>,
 }
 
@@ -59,7 +60,7 @@ impl DatabaseInstance {
 			db: RwLock::new(Some(db)),
 			change_tx,
 			last_accessed: Arc::new(AtomicU64::new(storage::now_timestamp())),
-			indexed_fields: Arc::new(RwLock::new(HashMap::new())),
+			indexes: Arc::new(RwLock::new(HashMap::new())),
 			locks: Arc::new(RwLock::new(HashMap::new())),
 		}
 	}
@@ -110,20 +111,20 @@ impl DatabaseInstance {
 		self.last_accessed.load(Ordering::Acquire)
 	}
 
-	/// Load indexed fields from database metadata.
+	/// Load index definitions from database metadata.
 	///
 	/// Synchronous — must be called from a blocking context (e.g. inside
 	/// `tokio::task::spawn_blocking`). redb's `begin_read` does sync file I/O.
-	pub fn load_indexed_fields(&self) -> ClResult<()> {
+	pub fn load_indexes(&self) -> ClResult<()> {
 		let db = self.db()?;
 		let tx = db.begin_read().map_err(crate::error::from_redb_error)?;
 		let meta_table =
 			tx.open_table(storage::TABLE_METADATA).map_err(crate::error::from_redb_error)?;
 
-		let mut indexed_fields = self
-			.indexed_fields
+		let mut indexes = self
+			.indexes
 			.write()
-			.map_err(|_| Error::Internal("indexed_fields rwlock poisoned".into()))?;
+			.map_err(|_| Error::Internal("indexes rwlock poisoned".into()))?;
 
 		// Iterate all metadata keys looking for ".../_meta/indexes" entries.
 		// Keys have formats like "posts/_meta/indexes" (per_tenant) or
@@ -142,9 +143,12 @@ impl DatabaseInstance {
 					.filter(|(prefix, _)| prefix.chars().all(|c| c.is_ascii_digit()))
 					.map_or(collection, |(_, rest)| rest);
 
-				if let Ok(fields) = serde_json::from_str::<Vec<String>>(value.value()) {
-					indexed_fields
-						.insert(path.into(), fields.into_iter().map(Into::into).collect());
+				// Entries are field names, or field-name arrays for composite indexes
+				if let Ok(stored) = serde_json::from_str::<Vec<serde_json::Value>>(value.value()) {
+					indexes.insert(
+						path.into(),
+						stored.iter().filter_map(IndexDef::from_stored).collect(),
+					);
 				}
 			}
 		}
@@ -1,6 +1,7 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+mod changelog;
 mod error;
 mod index;
 mod instance;
@@ -25,8 +26,9 @@ pub use error::Error;
 
 use cloudillo_types::prelude::*;
 use cloudillo_types::rtdb_adapter::{
-	ChangeEvent, DbStats, LockInfo, LockMode, QueryOptions, RtdbAdapter, SubscriptionOptions,
-	SubscriptionScope, Transaction, project_doc, selection_changed,
+	ChangeEvent, ChangeSet, DbStats, FilterTransition, IndexSpec, LockInfo, LockMode, QueryOptions,
+	RtdbAdapter, SubscriptionOptions, SubscriptionScope, Transaction, project_doc,
+	selection_changed,
 };
 use cloudillo_types::types::CompactReport;
 
@@ -87,6 +89,10 @@ pub struct AdapterConfig {
 
 	/// Enable background eviction task for idle databases
 	pub auto_evict: bool,
+
+	/// Change log entries kept per database for offline sync. A client further
+	/// behind than this must resync.
+	pub change_log_retention: u64,
 }
 
 impl Default for AdapterConfig {
@@ -96,6 +102,7 @@ impl Default for AdapterConfig {
 			idle_timeout_secs: 600,
 			broadcast_capacity: 1000,
 			auto_evict: true,
+			change_log_retention: 10_000,
 		}
 	}
 }
@@ -305,6 +312,10 @@ impl RtdbAdapterRedb {
 						tx.open_table(storage::TABLE_INDEXES).map_err(error::from_redb_error)?;
 					let _ =
 						tx.open_table(storage::TABLE_METADATA).map_err(error::from_redb_error)?;
+					let _ =
+						tx.open_table(storage::TABLE_CHANGES).map_err(error::from_redb_error)?;
+					let _ =
+						tx.open_table(storage::TABLE_VERSIONS).map_err(error::from_redb_error)?;
 					tx.commit().map_err(error::from_redb_error)?;
 					Ok(Arc::new(db))
 				})
@@ -402,9 +413,9 @@ impl RtdbAdapterRedb {
 		let db = self.open_db_file_guarded(db_path).await?;
 		let (change_tx, _) = tokio::sync::broadcast::channel(self.config.broadcast_capacity);
 		let instance = Arc::new(DatabaseInstance::new(db, change_tx));
-		// load_indexed_fields does sync redb I/O; run on the blocking pool.
+		// load_indexes does sync redb I/O; run on the blocking pool.
 		let instance_for_load = Arc::clone(&instance);
-		tokio::task::spawn_blocking(move || instance_for_load.load_indexed_fields())
+		tokio::task::spawn_blocking(move || instance_for_load.load_indexes())
 			.await
 			.map_err(error::Error::from)??;
 
@@ -481,9 +492,15 @@ impl RtdbAdapter for RtdbAdapterRedb {
 		let (instance, guard) = self.get_or_open_instance(tn_id, db_id).await?;
 		// The guard goes with the actor, not with this call: the transaction
 		// outlives `transaction()` and holds a write handle the whole time.
-		let redb_tx =
-			RedbTransaction::spawn(self.per_tenant_files, tn_id, db_id.into(), instance, guard)
-				.await?;
+		let redb_tx = RedbTransaction::spawn(
+			self.per_tenant_files,
+			tn_id,
+			db_id.into(),
+			instance,
+			guard,
+			self.config.change_log_retention,
+		)
+		.await?;
 		Ok(Box::new(redb_tx))
 	}
 
@@ -662,6 +679,27 @@ impl RtdbAdapter for RtdbAdapterRedb {
 										continue;
 									}
 								}
+								// An update can move a document out of the result
+								// set. Dropping it would leave the subscriber
+								// holding a match that no longer is one, so it
+								// becomes a removal — the one event every client
+								// already handles by forgetting the document.
+								ChangeEvent::Update { path, data, old_data } => {
+									match filter.transition(old_data.as_ref(), Some(data)) {
+										FilterTransition::Enter | FilterTransition::Stay => {}
+										FilterTransition::Outside => continue,
+										FilterTransition::Leave => {
+											let old_data = match (&select, old_data) {
+												(Some(select), Some(old)) => {
+													Some(project_doc(old, select))
+												}
+												(_, old) => old.clone(),
+											};
+											yield ChangeEvent::Delete { path: path.clone(), old_data };
+											continue;
+										}
+									}
+								}
 								_ => {
 									if let Some(data) = event.data()
 										&& !filter.matches(data)
@@ -731,8 +769,41 @@ impl RtdbAdapter for RtdbAdapterRedb {
 		field: &str,
 	) -> ClResult<()> {
 		let (instance, _guard) = self.get_or_open_instance(tn_id, db_id).await?;
+		let spec = IndexSpec::field(field);
+
+		index::create_index_impl(&instance, tn_id, db_id, path, &spec, self.per_tenant_files).await
+	}
+
+	async fn create_composite_index(
+		&self,
+		tn_id: TnId,
+		db_id: &str,
+		path: &str,
+		spec: &IndexSpec,
+	) -> ClResult<()> {
+		let (instance, _guard) = self.get_or_open_instance(tn_id, db_id).await?;
+
+		index::create_index_impl(&instance, tn_id, db_id, path, spec, self.per_tenant_files).await
+	}
 
-		index::create_index_impl(&instance, tn_id, db_id, path, field, self.per_tenant_files).await
+	async fn changes_since(
+		&self,
+		tn_id: TnId,
+		db_id: &str,
+		since: u64,
+		limit: u32,
+	) -> ClResult<ChangeSet> {
+		let (instance, _guard) = self.get_or_open_instance(tn_id, db_id).await?;
+		let db_prefix = if self.per_tenant_files {
+			format!("{}/", db_id)
+		} else {
+			format!("{}/{}/", tn_id.0, db_id)
+		};
+
+		tokio::task::spawn_blocking(move || {
+			changelog::changes_since(&*instance.db()?, &db_prefix, since, limit as usize)
+		})
+		.await?
 	}
 
 	async fn export_all(&self, tn_id: TnId, db_id: &str) -> ClResult<Vec<(Box<str>, Value)>> {
@@ -1114,8 +1185,13 @@ impl RtdbAdapter for RtdbAdapterRedb {
 			tokio::task::spawn_blocking(move || -> ClResult<()> {
 				use redb::ReadableTable;
 
-				const TENANT_TABLES: &[redb::TableDefinition<&str, &str>] =
-					&[storage::TABLE_DOCUMENTS, storage::TABLE_INDEXES, storage::TABLE_METADATA];
+				const TENANT_TABLES: &[redb::TableDefinition<&str, &str>] = &[
+					storage::TABLE_DOCUMENTS,
+					storage::TABLE_INDEXES,
+					storage::TABLE_METADATA,
+					storage::TABLE_CHANGES,
+					storage::TABLE_VERSIONS,
+				];
 				const CHUNK: usize = 1000;
 
 				for table_def in TENANT_TABLES {
@@ -2,11 +2,12 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use crate::error::from_redb_error;
+use crate::index::{COMPOSITE_SEP, IndexDef};
 use crate::{DatabaseInstance, storage};
 use cloudillo_types::error::ClResult;
 use cloudillo_types::rtdb_adapter::{
-	AggregateOp, AggregateOptions, QueryFilter, QueryOptions, SortField, compare_json_values,
-	project_doc,
+	AggregateOp, AggregateOptions, QueryFilter, QueryOptions, QueryPlan, QueryStrategy, RtdbCursor,
+	SortField, compare_documents, compare_json_values, project_doc,
 };
 use cloudillo_types::types::TnId;
 use redb::{ReadableDatabase, ReadableTable};
@@ -44,6 +45,8 @@ pub(crate) struct QueryTables<'a, T> {
 /// Query context grouping related parameters
 struct QueryContext<'a> {
 	scope: &'a QueryScope<'a>,
+	opts: &'a QueryOptions,
+	/// The query's filter, or an empty one
 	filter: &'a QueryFilter,
 }
 
@@ -64,26 +67,54 @@ pub fn execute_query(
 }
 
 /// Execute a query against already-open tables.
+///
+/// With `opts.explain` the result is a single [`QueryPlan`] instead of the
+/// documents.
 pub(crate) fn execute_query_tables<T>(
 	instance: &Arc<DatabaseInstance>,
 	tables: &QueryTables<'_, T>,
 	scope: &QueryScope<'_>,
 	opts: &QueryOptions,
 ) -> ClResult<Vec<Value>>
+where
+	T: ReadableTable<&'static str, &'static str>,
+{
+	let mut plan = QueryPlan::scan();
+	let results = run_query(instance, tables, scope, opts, &mut plan)?;
+
+	if opts.explain {
+		plan.docs_returned = results.len() as u64;
+		return Ok(vec![serde_json::to_value(&plan)?]);
+	}
+
+	Ok(results)
+}
+
+fn run_query<T>(
+	instance: &Arc<DatabaseInstance>,
+	tables: &QueryTables<'_, T>,
+	scope: &QueryScope<'_>,
+	opts: &QueryOptions,
+	plan: &mut QueryPlan,
+) -> ClResult<Vec<Value>>
 where
 	T: ReadableTable<&'static str, &'static str>,
 {
 	// Dispatch to aggregation if requested
 	if let Some(ref aggregate) = opts.aggregate {
-		return execute_aggregate(instance, tables, scope, opts, aggregate);
+		return execute_aggregate(instance, tables, scope, opts, aggregate, plan);
 	}
 
+	opts.validate_cursors()?;
+
+	let empty = QueryFilter::default();
+	let ctx = QueryContext { scope, opts, filter: opts.filter.as_ref().unwrap_or(&empty) };
+
 	// Try index-based query first
-	if let Some(ref filter) = opts.filter {
-		let ctx = QueryContext { scope, filter };
-		if let Some(docs) = try_index_query(instance, tables, &ctx)? {
-			return Ok(apply_sort_limit(docs, opts));
-		}
+	if let Some(access) = plan_index_access(instance, &ctx)? {
+		access.describe(plan);
+		let docs = execute_index_access(tables, &ctx, &access, plan)?;
+		return Ok(apply_sort_limit(docs, opts, plan.sorted_by_index));
 	}
 
 	// Fall back to collection scan
@@ -93,8 +124,15 @@ where
 		format!("{}/{}/{}/", scope.tn_id.0, scope.db_id, scope.path)
 	};
 
+	// Unsorted, the scan runs in id order — the cursor order — so a cursor is a
+	// place to start rather than a filter to test every earlier document against.
+	let start = match (&opts.sort, &opts.start_after) {
+		(None, Some(cursor)) => format!("{}{}", prefix, cursor.id),
+		_ => prefix.clone(),
+	};
+
 	let mut results = Vec::new();
-	let range = tables.docs.range(prefix.as_str()..).map_err(from_redb_error)?;
+	let range = tables.docs.range(start.as_str()..).map_err(from_redb_error)?;
 
 	for item in range {
 		let (key, value) = item.map_err(from_redb_error)?;
@@ -112,20 +150,21 @@ where
 
 		let mut doc: Value = serde_json::from_str(value.value())?;
 		storage::inject_doc_id(&mut doc, remainder);
+		plan.docs_examined += 1;
 
 		// Apply filter
-		if let Some(ref filter) = opts.filter
-			&& !filter.matches(&doc)
-		{
+		if !ctx.filter.matches(&doc) || !opts.within_cursors(&doc) {
 			continue;
 		}
 
 		results.push(doc);
 
 		// Early exit if we have enough (only when not sorting, since unseen
-		// docs may sort ahead of what we already have)
+		// docs may sort ahead of what we already have, and not when paging
+		// back, which keeps the page nearest the end cursor)
 		if let Some(limit) = opts.limit
 			&& opts.sort.is_none()
+			&& opts.end_before.is_none()
 		{
 			let needed = opts.offset.unwrap_or(0) as usize + limit as usize;
 			if results.len() >= needed {
@@ -134,175 +173,467 @@ where
 		}
 	}
 
-	Ok(apply_sort_limit(results, opts))
+	Ok(apply_sort_limit(results, opts, false))
+}
+
+/// How the planner chose to read a collection through an index.
+enum IndexAccess<'a> {
+	/// A single-field index, probed once per value (several for `arrayContainsAny`).
+	Field { field: &'a str, values: Vec<&'a Value> },
+
+	/// An ordered walk over part of a composite index.
+	Composite(CompositeAccess<'a>),
+}
+
+/// A bound of a range constraint: the value and whether it is inclusive.
+type RangeBound<'a> = Option<(&'a Value, bool)>;
+
+struct CompositeAccess<'a> {
+	def: IndexDef,
+	/// Values pinning the leading index fields, in index order
+	eq: Vec<&'a Value>,
+	/// Range constraint on the first index field after `eq`
+	lower: RangeBound<'a>,
+	upper: RangeBound<'a>,
+	/// `Some(reverse)` when walking the index yields the requested sort order
+	sorted: Option<bool>,
+}
+
+impl IndexAccess<'_> {
+	/// Ranking among candidate indexes: equality fields first, then a bounded
+	/// range, then a served sort. A lexicographic order rather than a weighted
+	/// sum so the choice stays predictable from the plan alone.
+	fn score(&self) -> (usize, bool, bool) {
+		match self {
+			IndexAccess::Field { .. } => (1, false, false),
+			IndexAccess::Composite(c) => {
+				(c.eq.len(), c.lower.is_some() || c.upper.is_some(), c.sorted.is_some())
+			}
+		}
+	}
+
+	fn describe(&self, plan: &mut QueryPlan) {
+		plan.strategy = QueryStrategy::Index;
+		match self {
+			IndexAccess::Field { field, .. } => {
+				plan.index = Some(vec![(*field).to_string()]);
+				plan.equality_fields = 1;
+			}
+			IndexAccess::Composite(c) => {
+				plan.index = Some(c.def.fields.iter().map(ToString::to_string).collect());
+				plan.equality_fields = u32::try_from(c.eq.len()).unwrap_or(u32::MAX);
+				plan.range = c.lower.is_some() || c.upper.is_some();
+				plan.sorted_by_index = c.sorted.is_some();
+			}
+		}
+	}
 }
 
-/// Try to execute a query using an index if available
-fn try_index_query<T>(
+fn is_scalar(value: &Value) -> bool {
+	!value.is_array() && !value.is_object()
+}
+
+/// Pick the best index for a query, or `None` to scan.
+fn plan_index_access<'a>(
 	instance: &Arc<DatabaseInstance>,
-	tables: &QueryTables<'_, T>,
-	ctx: &QueryContext,
-) -> ClResult<Option<Vec<Value>>>
-where
-	T: ReadableTable<&'static str, &'static str>,
-{
-	let indexed_fields = instance.indexed_fields.read().map_err(|_| {
-		cloudillo_types::error::Error::Internal("indexed_fields rwlock poisoned".into())
-	})?;
-	let indexed = match indexed_fields.get(ctx.scope.path) {
-		Some(f) => f.clone(),
+	ctx: &QueryContext<'a>,
+) -> ClResult<Option<IndexAccess<'a>>> {
+	let indexes = instance
+		.indexes
+		.read()
+		.map_err(|_| cloudillo_types::error::Error::Internal("indexes rwlock poisoned".into()))?;
+	let defs = match indexes.get(ctx.scope.path) {
+		Some(d) => d.clone(),
 		None => return Ok(None),
 	};
+	drop(indexes);
+
+	// A composite index has to do strictly better than a single-field one to
+	// replace it, so collections without composite indexes plan as they always did.
+	let mut best = field_access(&defs, ctx.filter);
+	for def in defs.iter().filter(|d| d.is_composite()) {
+		if let Some(candidate) = composite_access(def, ctx).map(IndexAccess::Composite)
+			&& best.as_ref().is_none_or(|b| candidate.score() > b.score())
+		{
+			best = Some(candidate);
+		}
+	}
+
+	Ok(best)
+}
 
-	drop(indexed_fields);
+/// A single-field index serving an equality or array-membership constraint.
+fn field_access<'a>(defs: &[IndexDef], filter: &'a QueryFilter) -> Option<IndexAccess<'a>> {
+	let indexed = |field: &str| defs.iter().any(|d| d.single_field() == Some(field));
 
-	// Check if any filter field is indexed
-	for (field, value) in &ctx.filter.equals {
-		if indexed.iter().any(|f| f.as_ref() == field.as_str()) {
-			return Ok(Some(execute_index_query(tables, ctx, field, value)?));
+	for (field, value) in &filter.equals {
+		if indexed(field) {
+			return Some(IndexAccess::Field { field, values: vec![value] });
 		}
 	}
 
-	// Check if any arrayContains field is indexed
-	for (field, value) in &ctx.filter.array_contains {
-		if indexed.iter().any(|f| f.as_ref() == field.as_str()) {
-			return Ok(Some(execute_index_query(tables, ctx, field, value)?));
+	for (field, value) in &filter.array_contains {
+		if indexed(field) {
+			return Some(IndexAccess::Field { field, values: vec![value] });
 		}
 	}
 
-	// Check if any arrayContainsAny field is indexed
-	for (field, values) in &ctx.filter.array_contains_any {
-		if indexed.iter().any(|f| f.as_ref() == field.as_str()) {
-			return Ok(Some(execute_index_query_any(tables, ctx, field, values)?));
+	for (field, values) in &filter.array_contains_any {
+		if indexed(field) {
+			return Some(IndexAccess::Field { field, values: values.iter().collect() });
 		}
 	}
 
-	// Check if any arrayContainsAll field is indexed (use first value for index scan)
-	for (field, values) in &ctx.filter.array_contains_all {
-		if !values.is_empty() && indexed.iter().any(|f| f.as_ref() == field.as_str()) {
-			return Ok(Some(execute_index_query(tables, ctx, field, &values[0])?));
+	// arrayContainsAll: one value narrows the candidates, the filter checks the rest
+	for (field, values) in &filter.array_contains_all {
+		if let Some(first) = values.first()
+			&& indexed(field)
+		{
+			return Some(IndexAccess::Field { field, values: vec![first] });
+		}
+	}
+
+	None
+}
+
+/// How much of a query a composite index can serve, if anything.
+///
+/// Equality (`equals`, or `arrayContains` against a multi-entry field) must pin
+/// a prefix of the index fields. The field after that prefix may carry a range
+/// constraint, and the requested sort is served when it continues the index
+/// fields in one direction. A sort alone is only worth an index walk with a
+/// limit: without one every document is fetched anyway.
+fn composite_access<'a>(def: &IndexDef, ctx: &QueryContext<'a>) -> Option<CompositeAccess<'a>> {
+	let filter = ctx.filter;
+
+	let mut eq = Vec::new();
+	for field in &def.fields {
+		match filter
+			.equals
+			.get(field.as_ref())
+			.or_else(|| filter.array_contains.get(field.as_ref()))
+		{
+			Some(value) if is_scalar(value) => eq.push(value),
+			_ => break,
 		}
 	}
 
-	Ok(None)
+	let (lower, upper) = match def.fields.get(eq.len()) {
+		Some(field) => range_bounds(filter, field),
+		None => (None, None),
+	};
+
+	let sorted = ctx
+		.opts
+		.sort
+		.as_deref()
+		.and_then(|sort| sort_direction(def, eq.len(), filter, sort));
+
+	let usable = !eq.is_empty()
+		|| lower.is_some()
+		|| upper.is_some()
+		|| (sorted.is_some() && ctx.opts.limit.is_some());
+
+	usable.then(|| CompositeAccess { def: def.clone(), eq, lower, upper, sorted })
+}
+
+/// Range constraints on one field usable as index bounds.
+///
+/// Either of `>`/`>=` (and `<`/`<=`) bounds the walk; when both are given the
+/// filter re-check applies the tighter one.
+fn range_bounds<'a>(filter: &'a QueryFilter, field: &str) -> (RangeBound<'a>, RangeBound<'a>) {
+	let bound = |inclusive: &'a HashMap<String, Value>, exclusive: &'a HashMap<String, Value>| {
+		inclusive
+			.get(field)
+			.filter(|v| is_scalar(v))
+			.map(|v| (v, true))
+			.or_else(|| exclusive.get(field).filter(|v| is_scalar(v)).map(|v| (v, false)))
+	};
+
+	(
+		bound(&filter.greater_than_or_equal, &filter.greater_than),
+		bound(&filter.less_than_or_equal, &filter.less_than),
+	)
+}
+
+/// Whether walking `def` after `eq_len` pinned fields yields `sort`'s order:
+/// `Some(false)` forward, `Some(true)` backward, `None` if it cannot.
+fn sort_direction(
+	def: &IndexDef,
+	eq_len: usize,
+	filter: &QueryFilter,
+	sort: &[SortField],
+) -> Option<bool> {
+	// A field pinned by equality is constant across the result, so sorting by it
+	// is a no-op wherever it appears.
+	let remaining: Vec<&SortField> =
+		sort.iter().filter(|s| !filter.equals.contains_key(&s.field)).collect();
+
+	let Some(first) = remaining.first() else { return Some(false) };
+	if remaining.iter().any(|s| s.ascending != first.ascending) {
+		return None;
+	}
+
+	// Ties are broken by id in the direction of the last sort field
+	// (`compare_documents`), and the walk breaks them by the id ending every
+	// key, in the walk's direction.
+	if sort.last().is_some_and(|s| s.ascending != first.ascending) {
+		return None;
+	}
+
+	// The sort has to cover every remaining index field: trailing index fields
+	// the sort does not name would order ties before the id does.
+	let tail = def.fields.get(eq_len..)?;
+	if remaining.len() != tail.len() {
+		return None;
+	}
+
+	remaining
+		.iter()
+		.zip(tail)
+		.all(|(s, f)| s.field == f.as_ref())
+		.then_some(!first.ascending)
 }
 
-/// Execute a query using an index
-fn execute_index_query<T>(
+/// Fetch the candidates an index access yields, keeping those the full filter
+/// accepts. Every candidate is re-checked: an index only narrows.
+fn execute_index_access<T>(
 	tables: &QueryTables<'_, T>,
 	ctx: &QueryContext,
-	field: &str,
-	value: &Value,
+	access: &IndexAccess,
+	plan: &mut QueryPlan,
 ) -> ClResult<Vec<Value>>
 where
 	T: ReadableTable<&'static str, &'static str>,
 {
-	let value_str = storage::value_to_string(value);
-	let index_prefix = if ctx.scope.per_tenant_files {
-		format!("{}/_idx/{}/{}/", ctx.scope.path, field, value_str)
-	} else {
-		format!("{}/{}/_idx/{}/{}/", ctx.scope.tn_id.0, ctx.scope.path, field, value_str)
-	};
+	let root = storage::index_root(ctx.scope.tn_id, ctx.scope.path, ctx.scope.per_tenant_files);
 
-	let mut results = Vec::new();
-	let range = tables.idx.range(index_prefix.as_str()..).map_err(from_redb_error)?;
+	match access {
+		IndexAccess::Field { field, values } => {
+			let mut seen_ids = HashSet::new();
+			let mut results = Vec::new();
 
-	for item in range {
-		let (key, _) = item.map_err(from_redb_error)?;
-		let key_str = key.value();
+			for value in values {
+				let value_str = storage::value_to_string(value);
+				let index_prefix = format!("{}/_idx/{}/{}/", root, field, value_str);
+				let range = tables.idx.range(index_prefix.as_str()..).map_err(from_redb_error)?;
 
-		if !key_str.starts_with(&index_prefix) {
-			break;
-		}
-
-		// Extract doc_id from index key
-		let doc_id = extract_doc_id_from_index_key(key_str);
+				for item in range {
+					let (key, _) = item.map_err(from_redb_error)?;
+					let key_str = key.value();
 
-		// Build document key - must match the key format used in create/update
-		let doc_key = doc_key(ctx.scope, &doc_id);
+					if !key_str.starts_with(&index_prefix) {
+						break;
+					}
 
-		// Fetch document
-		if let Some(json) = tables.docs.get(doc_key.as_str()).map_err(from_redb_error)? {
-			let mut doc: Value = serde_json::from_str(json.value())?;
-			storage::inject_doc_id(&mut doc, &doc_id);
+					let doc_id = extract_doc_id_from_index_key(key_str);
+					if !seen_ids.insert(doc_id.clone()) {
+						continue;
+					}
 
-			// Apply full filter
-			if ctx.filter.matches(&doc) {
-				results.push(doc);
+					if let Some(doc) = fetch_matching(tables, ctx, &doc_id, plan)? {
+						results.push(doc);
+					}
+				}
 			}
-		}
-	}
-
-	Ok(results)
-}
 
-/// The storage key of one document, in whichever layout this adapter runs.
-fn doc_key(scope: &QueryScope<'_>, doc_id: &str) -> String {
-	if scope.per_tenant_files {
-		format!("{}/{}/{}", scope.db_id, scope.path, doc_id)
-	} else {
-		format!("{}/{}/{}/{}", scope.tn_id.0, scope.db_id, scope.path, doc_id)
+			Ok(results)
+		}
+		IndexAccess::Composite(access) => {
+			execute_composite_access(tables, ctx, access, &root, plan)
+		}
 	}
 }
 
-/// Execute a query using an index, scanning for any of several values and deduplicating results
-fn execute_index_query_any<T>(
+/// Walk the slice of a composite index selected by its equality prefix and
+/// range bounds, in sort order when the index serves the sort.
+fn execute_composite_access<T>(
 	tables: &QueryTables<'_, T>,
 	ctx: &QueryContext,
-	field: &str,
-	values: &[Value],
+	access: &CompositeAccess,
+	root: &str,
+	plan: &mut QueryPlan,
 ) -> ClResult<Vec<Value>>
 where
 	T: ReadableTable<&'static str, &'static str>,
 {
-	let mut seen_ids = HashSet::new();
-	let mut results = Vec::new();
+	let mut prefix = access.def.composite_base(root);
+	for (i, value) in access.eq.iter().enumerate() {
+		if i > 0 {
+			prefix.push(COMPOSITE_SEP);
+		}
+		prefix.push_str(&storage::sortable_key(Some(value)));
+	}
+	if access.eq.len() == access.def.fields.len() {
+		prefix.push('/');
+	} else if !access.eq.is_empty() {
+		prefix.push(COMPOSITE_SEP);
+	}
+
+	let lower = access.lower.map(|(v, inclusive)| (storage::sortable_key(Some(v)), inclusive));
+	let upper = access.upper.map(|(v, inclusive)| (storage::sortable_key(Some(v)), inclusive));
+
+	// Narrow the walk to the bounds. Both ends may still admit keys the bound
+	// excludes (exclusive bounds, and longer strings sharing the bound's
+	// encoding as a prefix), so each key is also checked below.
+	let start = match &lower {
+		Some((enc, _)) => format!("{}{}", prefix, enc),
+		None => prefix.clone(),
+	};
+	let end = match &upper {
+		Some((enc, _)) => format!("{}{}\x7f", prefix, enc),
+		None => storage::prefix_end(&prefix),
+	};
+
+	let reverse = access.sorted == Some(true);
+	let needed = match (access.sorted, ctx.opts.limit, &ctx.opts.end_before) {
+		(Some(_), Some(limit), None) => {
+			Some(ctx.opts.offset.unwrap_or(0) as usize + limit as usize)
+		}
+		_ => None,
+	};
 
-	for value in values {
-		let value_str = storage::value_to_string(value);
-		let index_prefix = if ctx.scope.per_tenant_files {
-			format!("{}/_idx/{}/{}/", ctx.scope.path, field, value_str)
+	// In a walk that is the cursor order, a cursor bounds the walk itself, so a
+	// deep page costs no more than the first one.
+	let (mut start, mut end) = (start, end);
+	if access.sorted.is_some() {
+		let sort = ctx.opts.sort_fields();
+		let seek = |cursor: &RtdbCursor| cursor_key(&prefix, cursor, sort, ctx.filter);
+		let (walk_from, walk_to) = if reverse {
+			(&ctx.opts.end_before, &ctx.opts.start_after)
 		} else {
-			format!("{}/{}/_idx/{}/{}/", ctx.scope.tn_id.0, ctx.scope.path, field, value_str)
+			(&ctx.opts.start_after, &ctx.opts.end_before)
 		};
+		if let Some(key) = walk_from.as_ref().and_then(seek)
+			&& key > start
+		{
+			start = key;
+		}
+		if let Some(key) = walk_to.as_ref().and_then(seek)
+			&& key < end
+		{
+			// Exclusive end; the cursor's own entry is excluded below anyway
+			end = format!("{}\x7f", key);
+		}
+	}
+
+	let mut seen_ids = HashSet::new();
+	let mut results = Vec::new();
+	let mut range = tables.idx.range(start.as_str()..end.as_str()).map_err(from_redb_error)?;
 
-		let range = tables.idx.range(index_prefix.as_str()..).map_err(from_redb_error)?;
+	loop {
+		let item = if reverse { range.next_back() } else { range.next() };
+		let Some(item) = item else { break };
+		let (key, _) = item.map_err(from_redb_error)?;
+		let key_str = key.value();
 
-		for item in range {
-			let (key, _) = item.map_err(from_redb_error)?;
-			let key_str = key.value();
+		let rest = key_str.get(prefix.len()..).unwrap_or_default();
+		let (tuple, doc_id) = rest.rsplit_once('/').unwrap_or(("", rest));
 
-			if !key_str.starts_with(&index_prefix) {
+		let component = tuple.split(COMPOSITE_SEP).next().unwrap_or_default();
+		if let Some((enc, inclusive)) = &lower
+			&& (component < enc.as_str() || (!inclusive && component == enc))
+		{
+			continue;
+		}
+		if let Some((enc, inclusive)) = &upper
+			&& (component > enc.as_str() || (!inclusive && component == enc))
+		{
+			continue;
+		}
+
+		if !seen_ids.insert(doc_id.to_string()) {
+			continue;
+		}
+
+		if let Some(doc) = fetch_matching(tables, ctx, doc_id, plan)? {
+			results.push(doc);
+			if needed.is_some_and(|n| results.len() >= n) {
 				break;
 			}
+		}
+	}
 
-			let doc_id = extract_doc_id_from_index_key(key_str);
+	Ok(results)
+}
 
-			if !seen_ids.insert(doc_id.clone()) {
-				continue;
-			}
+/// Load one document by id, returning it only if it passes the query filter.
+fn fetch_matching<T>(
+	tables: &QueryTables<'_, T>,
+	ctx: &QueryContext,
+	doc_id: &str,
+	plan: &mut QueryPlan,
+) -> ClResult<Option<Value>>
+where
+	T: ReadableTable<&'static str, &'static str>,
+{
+	let doc_key = doc_key(ctx.scope, doc_id);
+	let Some(json) = tables.docs.get(doc_key.as_str()).map_err(from_redb_error)? else {
+		return Ok(None);
+	};
 
-			let doc_key = doc_key(ctx.scope, &doc_id);
+	plan.docs_examined += 1;
+	let mut doc: Value = serde_json::from_str(json.value())?;
+	storage::inject_doc_id(&mut doc, doc_id);
 
-			if let Some(json) = tables.docs.get(doc_key.as_str()).map_err(from_redb_error)? {
-				let mut doc: Value = serde_json::from_str(json.value())?;
-				storage::inject_doc_id(&mut doc, &doc_id);
+	Ok((ctx.filter.matches(&doc) && ctx.opts.within_cursors(&doc)).then_some(doc))
+}
 
-				if ctx.filter.matches(&doc) {
-					results.push(doc);
-				}
-			}
+/// The composite index key a cursor sits at, for a walk whose order is the
+/// cursor order: the walked index fields are exactly the sort fields not pinned
+/// by equality (see `sort_direction`). `None` for a cursor holding an array
+/// value, which the index stores per element rather than whole.
+fn cursor_key(
+	prefix: &str,
+	cursor: &RtdbCursor,
+	sort: &[SortField],
+	filter: &QueryFilter,
+) -> Option<String> {
+	let mut encoded = Vec::new();
+	for (i, field) in sort.iter().enumerate() {
+		if filter.equals.contains_key(&field.field) {
+			continue;
 		}
+		let value = cursor.value(i);
+		if value.is_some_and(|v| !is_scalar(v)) {
+			return None;
+		}
+		encoded.push(storage::sortable_key(value));
 	}
 
-	Ok(results)
+	if encoded.is_empty() {
+		// Every index field is pinned: the prefix already ends at the id
+		return Some(format!("{}{}", prefix, cursor.id));
+	}
+	let sep = COMPOSITE_SEP.to_string();
+	Some(format!("{}{}/{}", prefix, encoded.join(&sep), cursor.id))
 }
 
-/// Apply sorting, pagination and the field projection to results
-fn apply_sort_limit(mut docs: Vec<Value>, opts: &QueryOptions) -> Vec<Value> {
-	// Apply sorting
-	if let Some(ref sort_fields) = opts.sort {
-		docs.sort_by(|a, b| compare_documents(a, b, sort_fields));
+/// The storage key of one document, in whichever layout this adapter runs.
+fn doc_key(scope: &QueryScope<'_>, doc_id: &str) -> String {
+	if scope.per_tenant_files {
+		format!("{}/{}/{}", scope.db_id, scope.path, doc_id)
+	} else {
+		format!("{}/{}/{}/{}", scope.tn_id.0, scope.db_id, scope.path, doc_id)
+	}
+}
+
+/// Apply sorting, pagination and the field projection to results.
+///
+/// `presorted` skips the sort for results that came out of an index walk
+/// already in the requested order.
+fn apply_sort_limit(mut docs: Vec<Value>, opts: &QueryOptions, presorted: bool) -> Vec<Value> {
+	// Apply sorting. Cursors page through the id-completed order even when
+	// unsorted, and index lookups do not return in id order.
+	let paged = opts.start_after.is_some() || opts.end_before.is_some();
+	if !presorted && (opts.sort.is_some() || paged) {
+		docs.sort_by(|a, b| compare_documents(a, b, opts.sort_fields()));
+	}
+
+	// Paging back from `end_before` counts offset and limit from the end
+	let backward = opts.end_before.is_some() && opts.start_after.is_none();
+	if backward {
+		docs.reverse();
 	}
 
 	// Apply offset
@@ -314,32 +645,17 @@ fn apply_sort_limit(mut docs: Vec<Value>, opts: &QueryOptions) -> Vec<Value> {
 	// Apply limit
 	let end = opts.limit.map_or(docs.len(), |l| (start + l as usize).min(docs.len()));
 
-	let page = &docs[start..end];
+	let mut page = docs.drain(start..end).collect::<Vec<_>>();
+	if backward {
+		page.reverse();
+	}
 
 	// Projection runs last, so filtering and sorting still see fields the caller
 	// asked not to receive - `.orderBy('o')` must work under `.select('ti')`.
 	match opts.select {
 		Some(ref select) => page.iter().map(|doc| project_doc(doc, select)).collect(),
-		None => page.to_vec(),
-	}
-}
-
-/// Compare two documents for sorting
-fn compare_documents(a: &Value, b: &Value, sort_fields: &[SortField]) -> Ordering {
-	for field in sort_fields {
-		let a_val = a.get(&field.field);
-		let b_val = b.get(&field.field);
-
-		let ord = compare_json_values(a_val, b_val);
-
-		let ord = if field.ascending { ord } else { ord.reverse() };
-
-		if ord != Ordering::Equal {
-			return ord;
-		}
+		None => page,
 	}
-
-	Ordering::Equal
 }
 
 /// Extract document ID from an index key
@@ -487,6 +803,7 @@ fn execute_aggregate<T>(
 	scope: &QueryScope<'_>,
 	opts: &QueryOptions,
 	aggregate: &AggregateOptions,
+	plan: &mut QueryPlan,
 ) -> ClResult<Vec<Value>>
 where
 	T: ReadableTable<&'static str, &'static str>,
@@ -494,18 +811,21 @@ where
 	// Index-only path: no filter, no data-dependent ops (count only), field is indexed
 	let can_use_index =
 		opts.filter.as_ref().is_none_or(QueryFilter::is_empty) && aggregate.ops.is_empty() && {
-			let indexed_fields = instance.indexed_fields.read().map_err(|_| {
-				cloudillo_types::error::Error::Internal("indexed_fields rwlock poisoned".into())
+			let indexes = instance.indexes.read().map_err(|_| {
+				cloudillo_types::error::Error::Internal("indexes rwlock poisoned".into())
 			})?;
-			indexed_fields
-				.get(scope.path)
-				.is_some_and(|fields| fields.iter().any(|f| f.as_ref() == aggregate.group_by))
+			indexes.get(scope.path).is_some_and(|defs| {
+				defs.iter().any(|d| d.single_field() == Some(aggregate.group_by.as_str()))
+			})
 		};
 
 	if can_use_index {
+		plan.strategy = QueryStrategy::AggregateIndex;
+		plan.index = Some(vec![aggregate.group_by.clone()]);
 		execute_aggregate_index_only(tables.idx, scope, opts, aggregate)
 	} else {
-		execute_aggregate_scan(tables.docs, scope, opts, aggregate)
+		plan.strategy = QueryStrategy::AggregateScan;
+		execute_aggregate_scan(tables.docs, scope, opts, aggregate, plan)
 	}
 }
 
@@ -585,6 +905,7 @@ fn execute_aggregate_scan<T>(
 	scope: &QueryScope<'_>,
 	opts: &QueryOptions,
 	aggregate: &AggregateOptions,
+	plan: &mut QueryPlan,
 ) -> ClResult<Vec<Value>>
 where
 	T: ReadableTable<&'static str, &'static str>,
@@ -614,6 +935,7 @@ where
 
 		let mut doc: Value = serde_json::from_str(value.value())?;
 		storage::inject_doc_id(&mut doc, remainder);
+		plan.docs_examined += 1;
 
 		// Apply filter
 		if let Some(ref filter) = opts.filter
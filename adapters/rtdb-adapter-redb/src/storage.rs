@@ -3,6 +3,7 @@
 
 use cloudillo_types::error::ClResult;
 use cloudillo_types::rtdb_adapter::{ChangeEvent, SubscriptionScope};
+use cloudillo_types::types::TnId;
 use serde_json::Value;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -15,6 +16,14 @@ pub const TABLE_INDEXES: redb::TableDefinition<&str, &str> = redb::TableDefiniti
 /// Metadata storage table
 pub const TABLE_METADATA: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("meta");
 
+/// Change log table: `{db}/{seq:020}` -> serialized `ChangeEvent`
+pub const TABLE_CHANGES: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("chlog");
+
+/// Document versions: document key -> sequence number of its last write. A
+/// deleted document keeps its entry as a tombstone until the log entry of the
+/// delete is truncated.
+pub const TABLE_VERSIONS: redb::TableDefinition<&str, &str> = redb::TableDefinition::new("vers");
+
 /// Get current Unix timestamp
 pub fn now_timestamp() -> u64 {
 	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
@@ -47,6 +56,58 @@ pub fn values_to_index_strings(value: &Value) -> Vec<String> {
 	}
 }
 
+/// Encode a JSON value so that byte order of the encodings follows value order.
+///
+/// Used by composite index keys, which serve sorts and range filters by walking
+/// the key space in order — something `value_to_string` cannot do (`"10"` sorts
+/// before `"9"`). A leading type tag keeps each JSON type in its own band; within
+/// a band numbers order numerically and strings by their UTF-8 bytes, matching
+/// `compare_json_values`. The output never contains `/` or `\x1f`, the two
+/// separators of a composite key.
+pub fn sortable_key(value: Option<&Value>) -> String {
+	match value {
+		None => "0".to_string(),
+		Some(Value::Null) => "1".to_string(),
+		Some(Value::Bool(b)) => if *b { "21" } else { "20" }.to_string(),
+		Some(Value::Number(n)) => {
+			let f = n.as_f64().unwrap_or_default();
+			// -0.0 and 0.0 compare equal, so they must encode equally
+			let bits = if f == 0.0 { 0.0f64.to_bits() } else { f.to_bits() };
+			let ordered = if bits >> 63 == 1 { !bits } else { bits | (1 << 63) };
+			format!("3{:016x}", ordered)
+		}
+		Some(Value::String(s)) => format!("4{}", hex_bytes(s.as_bytes())),
+		Some(other) => format!("5{}", hex_bytes(other.to_string().as_bytes())),
+	}
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+	use std::fmt::Write;
+
+	let mut out = String::with_capacity(bytes.len() * 2);
+	for b in bytes {
+		let _ = write!(out, "{:02x}", b);
+	}
+	out
+}
+
+/// Smallest string greater than every string that starts with `prefix`.
+///
+/// Index prefixes are ASCII, so bumping the last byte is enough. Used as the
+/// exclusive end of a prefix range, which a reverse scan needs.
+pub fn prefix_end(prefix: &str) -> String {
+	let mut bytes = prefix.as_bytes().to_vec();
+	if let Some(last) = bytes.last_mut() {
+		*last = last.saturating_add(1);
+	}
+	String::from_utf8(bytes).unwrap_or_else(|_| format!("{}\u{10ffff}", prefix))
+}
+
+/// Root of a collection's index keys, in whichever layout the adapter runs.
+pub fn index_root(tn_id: TnId, path: &str, per_tenant_files: bool) -> String {
+	if per_tenant_files { path.to_string() } else { format!("{}/{}", tn_id.0, path) }
+}
+
 /// Check if an event matches a subscription path (prefix match with boundary check)
 pub fn event_matches_path(event: &ChangeEvent, subscription_path: &str) -> bool {
 	let event_path = event.path();
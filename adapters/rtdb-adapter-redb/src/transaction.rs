@@ -17,7 +17,7 @@
 //! the dedicated blocking thread — never on the tokio worker.
 
 use crate::query::{QueryScope, QueryTables};
-use crate::{DatabaseInstance, storage};
+use crate::{DatabaseInstance, changelog, index, storage};
 use async_trait::async_trait;
 use cloudillo_types::prelude::*;
 use cloudillo_types::rtdb_adapter::{ChangeEvent, LockInfo, QueryOptions, Transaction};
@@ -58,6 +58,8 @@ enum TxCommand {
 	Get { path: String, reply: TxOneshot<Option<Value>> },
 	Query { path: String, opts: Box<QueryOptions>, reply: TxOneshot<Vec<Value>> },
 	CheckLock { path: String, reply: TxOneshot<Option<LockInfo>> },
+	Version { path: String, reply: TxOneshot<Option<u64>> },
+	Seq { reply: TxOneshot<Option<u64>> },
 	Commit { reply: TxOneshot<()> },
 	Rollback,
 }
@@ -90,12 +92,16 @@ impl RedbTransaction {
 	/// the second one forever while itself waiting on the first. The transaction
 	/// hangs, and with it the whole nightly sweep. Every read a caller needs
 	/// inside a transaction is on the handle: `get`, `query`, `check_lock`.
+	///
+	/// `retention` is how many change log entries the database keeps; see
+	/// [`crate::changelog`].
 	pub async fn spawn(
 		per_tenant_files: bool,
 		tn_id: TnId,
 		db_id: Box<str>,
 		instance: Arc<DatabaseInstance>,
 		barrier: tokio::sync::OwnedRwLockReadGuard<()>,
+		retention: u64,
 	) -> ClResult<Self> {
 		// Capacity 1: `send_cmd` awaits its reply before the next send, so
 		// at most one command is in flight per transaction.
@@ -135,6 +141,8 @@ impl RedbTransaction {
 				tx: Some(write_tx),
 				write_cache: HashMap::new(),
 				pending_events: Vec::new(),
+				seq: None,
+				retention,
 				instance,
 				tn_id,
 				db_id: db_id.clone(),
@@ -183,6 +191,13 @@ impl RedbTransaction {
 						let result = state.check_lock(&path);
 						let _ = reply.send(result);
 					}
+					TxCommand::Version { path, reply } => {
+						let result = state.version(&path);
+						let _ = reply.send(result);
+					}
+					TxCommand::Seq { reply } => {
+						let _ = reply.send(Ok(state.seq));
+					}
 					TxCommand::Commit { reply } => {
 						let result = state.commit();
 						let _ = reply.send(result);
@@ -254,6 +269,15 @@ impl Transaction for RedbTransaction {
 		self.send_cmd(|reply| TxCommand::CheckLock { path, reply }).await
 	}
 
+	async fn version(&self, path: &str) -> ClResult<Option<u64>> {
+		let path = path.to_string();
+		self.send_cmd(|reply| TxCommand::Version { path, reply }).await
+	}
+
+	async fn seq(&self) -> ClResult<Option<u64>> {
+		self.send_cmd(|reply| TxCommand::Seq { reply }).await
+	}
+
 	async fn commit(&mut self) -> ClResult<()> {
 		self.send_cmd(|reply| TxCommand::Commit { reply }).await
 	}
@@ -279,6 +303,11 @@ struct TxState {
 	/// `Some(data)` = document exists; `None` = deleted.
 	write_cache: HashMap<String, Option<Value>>,
 	pending_events: Vec<ChangeEvent>,
+	/// Last sequence number this transaction handed out, `None` before its
+	/// first write. Persisted on commit.
+	seq: Option<u64>,
+	/// Change log entries to keep, see [`changelog::finish`]
+	retention: u64,
 	instance: Arc<DatabaseInstance>,
 	tn_id: TnId,
 	db_id: Box<str>,
@@ -306,26 +335,23 @@ impl TxState {
 		}
 	}
 
-	fn build_index_keys(
-		&self,
-		collection: &str,
-		field: &str,
-		value: &Value,
-		doc_id: &str,
-	) -> Vec<String> {
-		storage::values_to_index_strings(value)
-			.into_iter()
-			.map(|value_str| {
-				if self.per_tenant_files {
-					format!("{}/_idx/{}/{}/{}", collection, field, value_str, doc_id)
-				} else {
-					format!(
-						"{}/{}/_idx/{}/{}/{}",
-						self.tn_id.0, collection, field, value_str, doc_id
-					)
-				}
-			})
-			.collect()
+	/// Log a write under the next sequence number and stamp the document's
+	/// version with it.
+	fn log_change(&mut self, path: &str, event: &ChangeEvent) -> ClResult<()> {
+		use crate::error::from_redb_error;
+
+		let db_prefix = self.build_key("");
+		let current = if let Some(seq) = self.seq {
+			seq
+		} else {
+			let meta =
+				self.tx_ref()?.open_table(storage::TABLE_METADATA).map_err(from_redb_error)?;
+			changelog::current_seq(&meta, &db_prefix)?
+		};
+		let seq = current.saturating_add(1);
+		self.seq = Some(seq);
+
+		changelog::record(self.tx_ref()?, &db_prefix, &self.build_key(path), seq, event)
 	}
 
 	fn update_indexes_for_document(
@@ -337,24 +363,22 @@ impl TxState {
 	) -> ClResult<()> {
 		use crate::error::from_redb_error;
 
-		// Read indexed-fields snapshot (sync RwLock — briefly held).
-		let indexed_fields = self.instance.indexed_fields.read().map_err(|_| {
-			cloudillo_types::error::Error::Internal("indexed_fields rwlock poisoned".into())
+		// Read index snapshot (sync RwLock — briefly held).
+		let indexes = self.instance.indexes.read().map_err(|_| {
+			cloudillo_types::error::Error::Internal("indexes rwlock poisoned".into())
 		})?;
-		let fields = match indexed_fields.get(collection) {
-			Some(f) => f.clone(),
+		let defs = match indexes.get(collection) {
+			Some(d) => d.clone(),
 			None => return Ok(()),
 		};
-		drop(indexed_fields);
+		drop(indexes);
 
 		// Build all index keys before acquiring the table.
-		let mut index_keys = Vec::new();
-		for field in &fields {
-			if let Some(value) = data.get(field.as_ref()) {
-				let keys = self.build_index_keys(collection, field, value, doc_id);
-				index_keys.extend(keys);
-			}
-		}
+		let root = storage::index_root(self.tn_id, collection, self.per_tenant_files);
+		let index_keys: Vec<String> = defs
+			.iter()
+			.flat_map(|def| index::index_entry_keys(&root, def, data, doc_id))
+			.collect();
 
 		let mut index_table =
 			self.tx_mut()?.open_table(storage::TABLE_INDEXES).map_err(from_redb_error)?;
@@ -396,8 +420,10 @@ impl TxState {
 		// Update indexes
 		self.update_indexes_for_document(path, &doc_id, &data, true)?;
 
-		// Buffer change event
-		self.pending_events.push(ChangeEvent::Create { path: full_path.into(), data });
+		// Log and buffer change event
+		let event = ChangeEvent::Create { path: full_path.as_str().into(), data };
+		self.log_change(&full_path, &event)?;
+		self.pending_events.push(event);
 
 		Ok(doc_id.into())
 	}
@@ -442,9 +468,10 @@ impl TxState {
 		}
 		self.update_indexes_for_document(&collection, &doc_id, &data, true)?;
 
-		// Buffer change event
-		self.pending_events
-			.push(ChangeEvent::Update { path: path.into(), data, old_data });
+		// Log and buffer change event
+		let event = ChangeEvent::Update { path: path.into(), data, old_data };
+		self.log_change(path, &event)?;
+		self.pending_events.push(event);
 
 		Ok(())
 	}
@@ -486,9 +513,10 @@ impl TxState {
 			self.update_indexes_for_document(&collection, &doc_id, data, false)?;
 		}
 
-		// Buffer change event
-		self.pending_events
-			.push(ChangeEvent::Delete { path: path.into(), old_data: data });
+		// Log and buffer change event
+		let event = ChangeEvent::Delete { path: path.into(), old_data: data };
+		self.log_change(path, &event)?;
+		self.pending_events.push(event);
 
 		Ok(())
 	}
@@ -564,9 +592,22 @@ impl TxState {
 		Ok(None)
 	}
 
+	/// A document's stored version, through the open transaction so the
+	/// transaction's own writes count.
+	fn version(&self, path: &str) -> ClResult<Option<u64>> {
+		use crate::error::from_redb_error;
+
+		let versions =
+			self.tx_ref()?.open_table(storage::TABLE_VERSIONS).map_err(from_redb_error)?;
+		changelog::version(&versions, &self.build_key(path))
+	}
+
 	fn commit(&mut self) -> ClResult<()> {
 		use crate::error::from_redb_error;
 
+		if let Some(seq) = self.seq {
+			changelog::finish(self.tx_ref()?, &self.build_key(""), seq, self.retention)?;
+		}
 		if let Some(tx) = self.tx.take() {
 			tx.commit().map_err(from_redb_error)?;
 		}
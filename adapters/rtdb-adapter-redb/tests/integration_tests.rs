@@ -5,7 +5,8 @@
 
 use cloudillo_rtdb_adapter_redb::{AdapterConfig, RtdbAdapterRedb};
 use cloudillo_types::rtdb_adapter::{
-	AggregateOp, AggregateOptions, QueryFilter, QueryOptions, RtdbAdapter, SortField,
+	AggregateOp, AggregateOptions, ChangeSet, IndexSpec, QueryFilter, QueryOptions, QueryPlan,
+	QueryStrategy, RtdbAdapter, RtdbCursor, SortField,
 };
 use cloudillo_types::types::TnId;
 use serde_json::{Value, json};
@@ -22,6 +23,7 @@ async fn create_test_adapter(per_tenant_files: bool) -> (RtdbAdapterRedb, TempDi
 		idle_timeout_secs: 300,
 		broadcast_capacity: 100,
 		auto_evict: false,
+		change_log_retention: 10_000,
 	};
 
 	let adapter = RtdbAdapterRedb::new(storage_path, per_tenant_files, config)
@@ -1913,3 +1915,594 @@ async fn subtree_scope_is_the_default_and_still_matches_descendants() {
 		other => panic!("expected Create, got {other:?}"),
 	}
 }
+
+// ── Composite indexes and query plans ──
+
+/// Seed a task list: two owners, numeric priorities that sort wrongly as text
+/// (9 vs 10), and labels for multi-entry lookups.
+async fn create_task_docs(adapter: &RtdbAdapterRedb) {
+	let tasks = [
+		("alice", 10, json!(["bug"])),
+		("alice", 9, json!(["bug", "ui"])),
+		("bob", 5, json!(["ui"])),
+		("alice", 1, json!([])),
+		("bob", 12, json!(["bug"])),
+		("alice", 20, json!(["ui"])),
+	];
+
+	for (owner, prio, labels) in tasks {
+		let mut tx = adapter
+			.transaction(TnId(1), "test_db")
+			.await
+			.expect("Failed to create transaction");
+		tx.create("tasks", json!({"owner": owner, "prio": prio, "labels": labels}))
+			.await
+			.expect("Failed to create document");
+		tx.commit().await.expect("Failed to commit");
+	}
+}
+
+async fn explain(adapter: &RtdbAdapterRedb, opts: QueryOptions) -> QueryPlan {
+	let results = adapter
+		.query(TnId(1), "test_db", "tasks", opts.with_explain(true))
+		.await
+		.expect("Failed to query");
+	assert_eq!(results.len(), 1, "explain returns exactly the plan");
+	serde_json::from_value(results[0].clone()).expect("plan should deserialize")
+}
+
+fn prios(docs: &[Value]) -> Vec<i64> {
+	docs.iter().filter_map(|d| d["prio"].as_i64()).collect()
+}
+
+#[tokio::test]
+async fn test_composite_index_serves_filter_and_sort() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let opts = QueryOptions::new()
+		.with_filter(QueryFilter::equals_one("owner", json!("alice")))
+		.with_sort(vec![SortField::asc("prio")])
+		.with_limit(2);
+
+	// Without the index: a scan, sorted in memory
+	let scanned = adapter.query(TnId(1), "test_db", "tasks", opts.clone()).await.expect("query");
+	assert_eq!(prios(&scanned), vec![1, 9]);
+	assert_eq!(explain(&adapter, opts.clone()).await.strategy, QueryStrategy::Scan);
+
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["owner", "prio"]),
+		)
+		.await
+		.expect("Failed to create index");
+
+	let indexed = adapter.query(TnId(1), "test_db", "tasks", opts.clone()).await.expect("query");
+	assert_eq!(prios(&indexed), vec![1, 9], "numeric order, not textual");
+
+	let plan = explain(&adapter, opts).await;
+	assert_eq!(plan.strategy, QueryStrategy::Index);
+	assert_eq!(plan.index, Some(vec!["owner".to_string(), "prio".to_string()]));
+	assert_eq!(plan.equality_fields, 1);
+	assert!(plan.sorted_by_index);
+	assert_eq!(plan.docs_examined, 2, "the walk stops at the limit");
+	assert_eq!(plan.docs_returned, 2);
+}
+
+#[tokio::test]
+async fn test_composite_index_range_and_descending_sort() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["owner", "prio"]),
+		)
+		.await
+		.expect("Failed to create index");
+	create_task_docs(&adapter).await;
+
+	let filter = QueryFilter::equals_one("owner", json!("alice"))
+		.with_greater_than_or_equal("prio", json!(9))
+		.with_less_than("prio", json!(20));
+	let opts = QueryOptions::new().with_filter(filter).with_sort(vec![SortField::desc("prio")]);
+
+	let results = adapter.query(TnId(1), "test_db", "tasks", opts.clone()).await.expect("query");
+	assert_eq!(prios(&results), vec![10, 9]);
+
+	let plan = explain(&adapter, opts).await;
+	assert!(plan.range);
+	assert!(plan.sorted_by_index);
+	assert_eq!(plan.docs_examined, 2, "the range bounds the walk");
+}
+
+#[tokio::test]
+async fn test_composite_index_array_contains_is_multi_entry() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["labels", "prio"]),
+		)
+		.await
+		.expect("Failed to create index");
+	create_task_docs(&adapter).await;
+
+	let opts = QueryOptions::new()
+		.with_filter(QueryFilter::new().with_array_contains("labels", json!("bug")))
+		.with_sort(vec![SortField::asc("prio")]);
+
+	let results = adapter.query(TnId(1), "test_db", "tasks", opts.clone()).await.expect("query");
+	assert_eq!(prios(&results), vec![9, 10, 12]);
+
+	let plan = explain(&adapter, opts).await;
+	assert_eq!(plan.strategy, QueryStrategy::Index);
+	assert!(plan.sorted_by_index);
+}
+
+#[tokio::test]
+async fn test_composite_index_follows_updates_and_deletes() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["owner", "prio"]),
+		)
+		.await
+		.expect("Failed to create index");
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	let a = tx.create("tasks", json!({"owner": "alice", "prio": 3})).await.expect("create");
+	let b = tx.create("tasks", json!({"owner": "alice", "prio": 4})).await.expect("create");
+	tx.commit().await.expect("Failed to commit");
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.update(&format!("tasks/{a}"), json!({"owner": "bob", "prio": 3}))
+		.await
+		.expect("update");
+	tx.delete(&format!("tasks/{b}")).await.expect("delete");
+	tx.commit().await.expect("Failed to commit");
+
+	let alice = QueryOptions::new()
+		.with_filter(QueryFilter::equals_one("owner", json!("alice")))
+		.with_sort(vec![SortField::asc("prio")]);
+	let results = adapter.query(TnId(1), "test_db", "tasks", alice.clone()).await.expect("query");
+	assert!(results.is_empty(), "stale entries must be gone");
+	assert_eq!(explain(&adapter, alice).await.docs_examined, 0);
+
+	let bob = QueryOptions::new().with_filter(QueryFilter::equals_one("owner", json!("bob")));
+	let results = adapter.query(TnId(1), "test_db", "tasks", bob).await.expect("query");
+	assert_eq!(prios(&results), vec![3]);
+}
+
+#[tokio::test]
+async fn test_composite_index_survives_reopen() {
+	let (adapter, _temp) = create_test_adapter(false).await;
+	create_task_docs(&adapter).await;
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["owner", "prio"]),
+		)
+		.await
+		.expect("Failed to create index");
+	adapter.close_db(TnId(1), "test_db").await.expect("Failed to close");
+
+	let opts = QueryOptions::new()
+		.with_filter(QueryFilter::equals_one("owner", json!("bob")))
+		.with_sort(vec![SortField::asc("prio")]);
+	let plan = explain(&adapter, opts).await;
+	assert_eq!(plan.strategy, QueryStrategy::Index);
+	assert_eq!(plan.docs_returned, 2);
+}
+
+#[tokio::test]
+async fn test_single_field_index_is_preferred_over_a_weaker_composite() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+	adapter.create_index(TnId(1), "test_db", "tasks", "owner").await.expect("index");
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["prio", "owner"]),
+		)
+		.await
+		.expect("Failed to create index");
+
+	// The composite cannot use `owner` (not its leading field), so the plan keeps
+	// the single-field index
+	let opts = QueryOptions::new().with_filter(QueryFilter::equals_one("owner", json!("bob")));
+	let plan = explain(&adapter, opts).await;
+	assert_eq!(plan.index, Some(vec!["owner".to_string()]));
+	assert_eq!(plan.docs_examined, 2);
+}
+
+// ── Cursor pagination and boolean filters ──
+
+/// Page through `tasks` with cursors, `page` documents at a time.
+async fn page_through(adapter: &RtdbAdapterRedb, sort: &[SortField], page: u32) -> Vec<Vec<i64>> {
+	let mut pages = Vec::new();
+	let mut cursor: Option<RtdbCursor> = None;
+	loop {
+		let mut opts = QueryOptions::new().with_sort(sort.to_vec()).with_limit(page);
+		if let Some(cursor) = cursor.take() {
+			opts = opts.with_start_after(cursor);
+		}
+		let docs = adapter.query(TnId(1), "test_db", "tasks", opts).await.expect("Failed to query");
+		let Some(last) = docs.last() else { break };
+		cursor = RtdbCursor::for_doc(last, sort);
+		pages.push(prios(&docs));
+	}
+	pages
+}
+
+#[tokio::test]
+async fn test_cursor_pages_cover_the_collection_once() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let sort = [SortField::desc("prio")];
+	let scanned = page_through(&adapter, &sort, 4).await;
+	assert_eq!(scanned, vec![vec![20, 12, 10, 9], vec![5, 1]]);
+
+	// The same pages when a composite index serves the sort
+	adapter
+		.create_composite_index(
+			TnId(1),
+			"test_db",
+			"tasks",
+			&IndexSpec::composite(["prio", "owner"]),
+		)
+		.await
+		.expect("Failed to create index");
+	assert_eq!(page_through(&adapter, &sort, 4).await, scanned);
+}
+
+#[tokio::test]
+async fn test_cursor_paging_skips_and_repeats_nothing_across_writes() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let sort = vec![SortField::asc("prio")];
+	let first = adapter
+		.query(
+			TnId(1),
+			"test_db",
+			"tasks",
+			QueryOptions::new().with_sort(sort.clone()).with_limit(3),
+		)
+		.await
+		.expect("Failed to query");
+	assert_eq!(prios(&first), vec![1, 5, 9]);
+
+	// A document lands on the first page after it was read. An offset would now
+	// repeat `9`; the cursor carries on after it.
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.create("tasks", json!({"owner": "carol", "prio": 2}))
+		.await
+		.expect("Failed to create");
+	tx.commit().await.expect("Failed to commit");
+
+	let cursor = RtdbCursor::for_doc(first.last().expect("a first page"), &sort).expect("cursor");
+	let opts = QueryOptions::new().with_sort(sort).with_limit(3).with_start_after(cursor);
+	let second = adapter.query(TnId(1), "test_db", "tasks", opts).await.expect("Failed to query");
+	assert_eq!(prios(&second), vec![10, 12, 20]);
+}
+
+#[tokio::test]
+async fn test_end_before_pages_backwards() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let sort = vec![SortField::asc("prio")];
+	let all = adapter
+		.query(TnId(1), "test_db", "tasks", QueryOptions::new().with_sort(sort.clone()))
+		.await
+		.expect("Failed to query");
+	let cursor = RtdbCursor::for_doc(&all[4], &sort).expect("cursor");
+
+	// The page *immediately before* the cursor, still in query order
+	let opts = QueryOptions::new().with_sort(sort).with_limit(2).with_end_before(cursor);
+	let docs = adapter.query(TnId(1), "test_db", "tasks", opts).await.expect("Failed to query");
+	assert_eq!(prios(&docs), vec![9, 10]);
+}
+
+#[tokio::test]
+async fn test_cursor_from_another_sort_is_rejected() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let cursor = RtdbCursor::for_doc(&json!({"id": "x", "prio": 1}), &[SortField::asc("prio")])
+		.expect("cursor");
+	let opts = QueryOptions::new()
+		.with_sort(vec![SortField::desc("prio")])
+		.with_start_after(cursor);
+	let result = adapter.query(TnId(1), "test_db", "tasks", opts).await;
+	assert!(result.is_err(), "a cursor replayed under a different sort must be rejected");
+}
+
+#[tokio::test]
+async fn test_or_and_not_filters() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+	create_task_docs(&adapter).await;
+
+	let sort = vec![SortField::asc("prio")];
+	let filter = QueryFilter::new().with_or(vec![
+		QueryFilter::equals_one("owner", json!("bob")),
+		QueryFilter::new().with_greater_than("prio", json!(15)),
+	]);
+	let opts = QueryOptions::new().with_filter(filter).with_sort(sort.clone());
+	let docs = adapter.query(TnId(1), "test_db", "tasks", opts).await.expect("Failed to query");
+	assert_eq!(prios(&docs), vec![5, 12, 20]);
+
+	let filter = QueryFilter::new()
+		.with_not(QueryFilter::new().with_array_contains("labels", json!("bug")))
+		.with_equals("owner", json!("alice"));
+	let opts = QueryOptions::new().with_filter(filter).with_sort(sort);
+	let docs = adapter.query(TnId(1), "test_db", "tasks", opts).await.expect("Failed to query");
+	assert_eq!(prios(&docs), vec![1, 20]);
+}
+
+/// An update that moves a document out of a filtered subscription arrives as a
+/// removal rather than not at all.
+#[tokio::test]
+async fn test_subscribe_with_or_filter_reports_documents_leaving() {
+	use cloudillo_types::rtdb_adapter::{ChangeEvent, SubscriptionOptions};
+
+	let (adapter, _temp) = create_test_adapter(true).await;
+	let tn_id = TnId(1);
+	let db_id = "test_db";
+
+	let mut tx = adapter.transaction(tn_id, db_id).await.expect("Failed to create transaction");
+	let x_id = tx
+		.create("p", json!({"status": "open", "ti": "Ex"}))
+		.await
+		.expect("Failed to create document");
+	tx.commit().await.expect("Failed to commit");
+
+	let filter = QueryFilter::new().with_or(vec![
+		QueryFilter::equals_one("status", json!("open")),
+		QueryFilter::equals_one("status", json!("review")),
+	]);
+	let mut stream = adapter
+		.subscribe(tn_id, db_id, SubscriptionOptions::filtered("p", filter))
+		.await
+		.expect("Failed to subscribe");
+	assert!(matches!(
+		next_event(&mut stream, "the initial Create").await,
+		ChangeEvent::Create { .. }
+	));
+	assert!(matches!(next_event(&mut stream, "Ready").await, ChangeEvent::Ready { .. }));
+
+	// Still matching through the other branch of the `or`
+	let mut tx = adapter.transaction(tn_id, db_id).await.expect("Failed to create transaction");
+	tx.update(&format!("p/{x_id}"), json!({"status": "review", "ti": "Ex"}))
+		.await
+		.expect("Failed to update document");
+	tx.commit().await.expect("Failed to commit");
+	match next_event(&mut stream, "the in-set Update").await {
+		ChangeEvent::Update { data, .. } => assert_eq!(data["status"], "review"),
+		other => panic!("expected Update, got {other:?}"),
+	}
+
+	// Matching neither branch: the subscriber is told to drop it
+	let mut tx = adapter.transaction(tn_id, db_id).await.expect("Failed to create transaction");
+	tx.update(&format!("p/{x_id}"), json!({"status": "closed", "ti": "Ex"}))
+		.await
+		.expect("Failed to update document");
+	tx.commit().await.expect("Failed to commit");
+	match next_event(&mut stream, "the leaving Delete").await {
+		ChangeEvent::Delete { path, old_data } => {
+			assert!(path.ends_with(&x_id as &str), "expected X's removal, got {path}");
+			assert_eq!(old_data.expect("the pre-update document")["status"], "review");
+		}
+		other => panic!("expected Delete, got {other:?}"),
+	}
+}
+
+// ── Change log and offline sync ──
+
+/// An adapter keeping only `retention` change log entries per database.
+async fn create_adapter_with_retention(retention: u64) -> (RtdbAdapterRedb, TempDir) {
+	let temp_dir = TempDir::new().expect("Failed to create temp directory");
+	let config = AdapterConfig {
+		max_instances: 10,
+		idle_timeout_secs: 300,
+		broadcast_capacity: 100,
+		auto_evict: false,
+		change_log_retention: retention,
+	};
+	let adapter = RtdbAdapterRedb::new(PathBuf::from(temp_dir.path()), true, config)
+		.await
+		.expect("Failed to create adapter");
+	(adapter, temp_dir)
+}
+
+fn actions(changes: &ChangeSet) -> Vec<(u64, &'static str)> {
+	use cloudillo_types::rtdb_adapter::ChangeEvent;
+
+	changes
+		.changes
+		.iter()
+		.map(|c| {
+			let action = match c.event {
+				ChangeEvent::Create { .. } => "create",
+				ChangeEvent::Update { .. } => "update",
+				ChangeEvent::Delete { .. } => "delete",
+				_ => "other",
+			};
+			(c.seq, action)
+		})
+		.collect()
+}
+
+#[tokio::test]
+async fn test_writes_are_sequenced_and_logged() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	let id = tx.create("notes", json!({"t": "a"})).await.expect("Failed to create");
+	tx.update(&format!("notes/{id}"), json!({"t": "b"}))
+		.await
+		.expect("Failed to update");
+	assert_eq!(tx.seq().await.expect("seq"), Some(2));
+	tx.commit().await.expect("Failed to commit");
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.delete(&format!("notes/{id}")).await.expect("Failed to delete");
+	tx.commit().await.expect("Failed to commit");
+
+	let changes = adapter.changes_since(TnId(1), "test_db", 0, 100).await.expect("changes");
+	assert!(!changes.resync);
+	assert_eq!(actions(&changes), vec![(1, "create"), (2, "update"), (3, "delete")]);
+	assert_eq!(changes.seq, 3);
+
+	// Paged, and resumable from the last sequence seen
+	let page = adapter.changes_since(TnId(1), "test_db", 1, 1).await.expect("changes");
+	assert_eq!(actions(&page), vec![(2, "update")]);
+	assert!(page.has_more);
+	let rest = adapter.changes_since(TnId(1), "test_db", page.seq, 10).await.expect("changes");
+	assert_eq!(actions(&rest), vec![(3, "delete")]);
+	assert!(!rest.has_more);
+
+	// Up to date
+	let none = adapter.changes_since(TnId(1), "test_db", 3, 10).await.expect("changes");
+	assert!(none.changes.is_empty() && !none.resync);
+	assert_eq!(none.seq, 3);
+}
+
+#[tokio::test]
+async fn test_sequences_are_per_database() {
+	let (adapter, _temp) = create_test_adapter(false).await;
+
+	for (tn_id, db_id) in [(TnId(1), "a"), (TnId(1), "b"), (TnId(2), "a")] {
+		let mut tx = adapter.transaction(tn_id, db_id).await.expect("Failed to create transaction");
+		tx.create("notes", json!({"t": db_id})).await.expect("Failed to create");
+		tx.commit().await.expect("Failed to commit");
+	}
+
+	for (tn_id, db_id) in [(TnId(1), "a"), (TnId(1), "b"), (TnId(2), "a")] {
+		let changes = adapter.changes_since(tn_id, db_id, 0, 10).await.expect("changes");
+		assert_eq!(actions(&changes), vec![(1, "create")], "{db_id} of tenant {}", tn_id.0);
+	}
+}
+
+#[tokio::test]
+async fn test_truncated_log_asks_for_resync() {
+	let (adapter, _temp) = create_adapter_with_retention(2).await;
+
+	for i in 0..5 {
+		let mut tx = adapter
+			.transaction(TnId(1), "test_db")
+			.await
+			.expect("Failed to create transaction");
+		tx.create("notes", json!({"i": i})).await.expect("Failed to create");
+		tx.commit().await.expect("Failed to commit");
+	}
+
+	let behind = adapter.changes_since(TnId(1), "test_db", 1, 10).await.expect("changes");
+	assert!(behind.resync, "a client behind the retained log must resync");
+	assert_eq!(behind.seq, 5);
+
+	let recent = adapter.changes_since(TnId(1), "test_db", 3, 10).await.expect("changes");
+	assert!(!recent.resync);
+	assert_eq!(actions(&recent), vec![(4, "create"), (5, "create")]);
+
+	// A client that knows a later sequence than the database has must start over
+	let ahead = adapter.changes_since(TnId(1), "test_db", 9, 10).await.expect("changes");
+	assert!(ahead.resync);
+}
+
+#[tokio::test]
+async fn test_document_versions_follow_writes() {
+	let (adapter, _temp) = create_adapter_with_retention(2).await;
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	let id = tx.create("notes", json!({"t": "a"})).await.expect("Failed to create");
+	let path = format!("notes/{id}");
+	assert_eq!(tx.version(&path).await.expect("version"), Some(1));
+	assert_eq!(tx.version("notes/missing").await.expect("version"), None);
+	tx.commit().await.expect("Failed to commit");
+
+	// A delete leaves a tombstone version, so a replayed write sees it
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.delete(&path).await.expect("Failed to delete");
+	tx.commit().await.expect("Failed to commit");
+	let tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	assert_eq!(tx.version(&path).await.expect("version"), Some(2));
+	drop(tx);
+
+	// ...until the delete falls out of the log
+	for i in 0..2 {
+		let mut tx = adapter
+			.transaction(TnId(1), "test_db")
+			.await
+			.expect("Failed to create transaction");
+		tx.create("other", json!({"i": i})).await.expect("Failed to create");
+		tx.commit().await.expect("Failed to commit");
+	}
+	let tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	assert_eq!(tx.version(&path).await.expect("version"), None);
+}
+
+#[tokio::test]
+async fn test_rolled_back_writes_take_no_sequence() {
+	let (adapter, _temp) = create_test_adapter(true).await;
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.create("notes", json!({"t": "lost"})).await.expect("Failed to create");
+	tx.rollback().await.expect("Failed to roll back");
+	drop(tx);
+
+	let mut tx = adapter
+		.transaction(TnId(1), "test_db")
+		.await
+		.expect("Failed to create transaction");
+	tx.create("notes", json!({"t": "kept"})).await.expect("Failed to create");
+	tx.commit().await.expect("Failed to commit");
+
+	let changes = adapter.changes_since(TnId(1), "test_db", 0, 10).await.expect("changes");
+	assert_eq!(actions(&changes), vec![(1, "create")]);
+}
@@ -28,6 +28,7 @@ async fn create_test_adapter(per_tenant_files: bool) -> (RtdbAdapterRedb, TempDi
 		idle_timeout_secs: 300,
 		broadcast_capacity: 100,
 		auto_evict: false,
+		change_log_retention: 10_000,
 	};
 
 	let adapter = RtdbAdapterRedb::new(storage_path, per_tenant_files, config)
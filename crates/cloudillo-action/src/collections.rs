@@ -0,0 +1,333 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Collections: private lists of saved actions, files and profiles.
+//!
+//! A collection belongs to the user who created it, on the tenant they created it
+//! on — the tenant owner on their own node, or a member on a community's — and
+//! nobody else can list, change or even name it. Items are kept in order
+//! (`position`) and may carry a note.
+//!
+//! Saving an item grants nothing. An item is listed only while its saver may still
+//! open it under the same checks its own endpoint applies: action visibility as
+//! `GET /api/actions` filters it, file access as `GET /api/files/{file_id}` resolves
+//! it, and a profile while the tenant still mirrors it. An item that falls out of
+//! reach stays in the collection and comes back if access does.
+//!
+//! `GET /api/search?collection=` narrows a search to a collection's items.
+
+use axum::{
+	Json,
+	extract::{Path, State},
+	http::StatusCode,
+};
+use std::collections::HashSet;
+
+use cloudillo_core::{
+	IdTag,
+	extract::{Auth, OptionalRequestId},
+	file_access::{self, FileAccessCtx},
+};
+use cloudillo_types::auth_adapter::AuthCtx;
+use cloudillo_types::meta_adapter::{
+	Collection, CreateCollection, FileStatus, SaveItem, SavedItem, SavedItemType,
+	UpdateCollectionOptions, UpdateSavedItemOptions,
+};
+use cloudillo_types::types::ApiResponse;
+
+use crate::filter::filter_actions_by_visibility;
+use crate::prelude::*;
+
+/// Most collections one user may keep on a tenant.
+pub const MAX_COLLECTIONS: usize = 100;
+
+/// Most items one collection may hold.
+pub const MAX_SAVED_ITEMS: u32 = 1000;
+
+/// Longest collection name, in characters.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Longest collection description or item note, in characters.
+const MAX_NOTE_LENGTH: usize = 1000;
+
+/// Longest saved item id, in characters.
+const MAX_ITEM_ID_LENGTH: usize = 256;
+
+/// The owner of the caller's collections. A scoped token (share link, app) is
+/// handed to whoever holds it and identifies nobody, so it has none.
+fn collection_owner(auth: &AuthCtx) -> ClResult<&str> {
+	if auth.scope.is_some() {
+		return Err(Error::PermissionDenied);
+	}
+	Ok(&auth.id_tag)
+}
+
+fn check_length(field: &str, value: &str, max: usize) -> ClResult<()> {
+	if value.chars().count() > max {
+		return Err(Error::ValidationError(format!("{field} is longer than {max} characters")));
+	}
+	Ok(())
+}
+
+fn normalize_name(name: &str) -> ClResult<String> {
+	let name = name.trim();
+	if name.is_empty() {
+		return Err(Error::ValidationError("name cannot be empty".into()));
+	}
+	check_length("name", name, MAX_NAME_LENGTH)?;
+	Ok(name.to_string())
+}
+
+fn check_position(position: i64) -> ClResult<()> {
+	if position < 0 {
+		return Err(Error::ValidationError("position cannot be negative".into()));
+	}
+	Ok(())
+}
+
+/// Whether the caller may still open a file, as `GET /api/files/{file_id}` decides.
+async fn file_accessible(
+	app: &App,
+	tn_id: TnId,
+	tenant_id_tag: &str,
+	auth: &AuthCtx,
+	file_id: &str,
+) -> bool {
+	let ctx = FileAccessCtx { user_id_tag: &auth.id_tag, tenant_id_tag, user_roles: &auth.roles };
+	match file_access::check_file_access_with_scope(app, tn_id, file_id, &ctx, None, None).await {
+		Ok(access) => matches!(access.file_view.status, FileStatus::Active),
+		Err(_) => false,
+	}
+}
+
+/// Keep the items the caller may still open. Actions go through one
+/// `filter_actions_by_visibility` pass, so a page of saved posts costs one
+/// relationship lookup rather than one per post.
+async fn accessible_items(
+	app: &App,
+	tn_id: TnId,
+	tenant_id_tag: &str,
+	auth: &AuthCtx,
+	items: Vec<SavedItem>,
+) -> ClResult<Vec<SavedItem>> {
+	let mut actions = Vec::new();
+	for item in items.iter().filter(|i| i.item_type == SavedItemType::Action) {
+		if let Some(action) = app.meta_adapter.get_action(tn_id, &item.item_id).await? {
+			// Left out of listings while verifying, failed or hidden by a moderator.
+			if !matches!(action.status.as_deref(), Some("V" | "F" | "H")) {
+				actions.push(action);
+			}
+		}
+	}
+	let visible_actions: HashSet<Box<str>> =
+		filter_actions_by_visibility(app, tn_id, &auth.id_tag, true, tenant_id_tag, actions)
+			.await?
+			.into_iter()
+			.map(|a| a.action_id)
+			.collect();
+
+	let mut accessible = Vec::with_capacity(items.len());
+	for item in items {
+		let keep = match item.item_type {
+			SavedItemType::Action => visible_actions.contains(&item.item_id),
+			SavedItemType::File => {
+				file_accessible(app, tn_id, tenant_id_tag, auth, &item.item_id).await
+			}
+			SavedItemType::Profile => {
+				app.meta_adapter.read_profile(tn_id, &item.item_id).await.is_ok()
+			}
+		};
+		if keep {
+			accessible.push(item);
+		}
+	}
+	Ok(accessible)
+}
+
+/// GET /api/collections - The caller's collections
+pub async fn list_collections(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<Collection>>>)> {
+	let owner = collection_owner(&auth)?;
+	let collections = app.meta_adapter.list_collections(tn_id, owner).await?;
+	let response = ApiResponse::new(collections).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// POST /api/collections - Create a collection
+pub async fn post_collection(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(mut req): Json<CreateCollection>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Collection>>)> {
+	let owner = collection_owner(&auth)?;
+	req.name = normalize_name(&req.name)?;
+	if let Some(description) = &req.description {
+		check_length("description", description, MAX_NOTE_LENGTH)?;
+	}
+	let existing = app.meta_adapter.list_collections(tn_id, owner).await?;
+	if existing.len() >= MAX_COLLECTIONS {
+		return Err(Error::ValidationError(format!(
+			"At most {MAX_COLLECTIONS} collections are allowed"
+		)));
+	}
+
+	let created = app.meta_adapter.create_collection(tn_id, owner, &req).await?;
+	info!("Collection {} created by {}", created.collection_id, owner);
+	let response = ApiResponse::new(created).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// PATCH /api/collections/{collection_id} - Rename a collection or change its description
+pub async fn patch_collection(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path(collection_id): Path<u64>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(mut req): Json<UpdateCollectionOptions>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Collection>>)> {
+	let owner = collection_owner(&auth)?;
+	match &req.name {
+		Patch::Null => return Err(Error::ValidationError("name cannot be cleared".into())),
+		Patch::Value(name) => req.name = Patch::Value(normalize_name(name)?),
+		Patch::Undefined => {}
+	}
+	if let Patch::Value(description) = &req.description {
+		check_length("description", description, MAX_NOTE_LENGTH)?;
+	}
+
+	let updated = app.meta_adapter.update_collection(tn_id, owner, collection_id, &req).await?;
+	let response = ApiResponse::new(updated).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// DELETE /api/collections/{collection_id} - Delete a collection with its items
+pub async fn delete_collection(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path(collection_id): Path<u64>,
+) -> ClResult<StatusCode> {
+	let owner = collection_owner(&auth)?;
+	app.meta_adapter.delete_collection(tn_id, owner, collection_id).await?;
+	info!("Collection {} deleted by {}", collection_id, owner);
+	Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/collections/{collection_id}/items - A collection's items the caller may
+/// still open, in order
+pub async fn list_saved_items(
+	State(app): State<App>,
+	tn_id: TnId,
+	IdTag(tenant_id_tag): IdTag,
+	Auth(auth): Auth,
+	Path(collection_id): Path<u64>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<SavedItem>>>)> {
+	let owner = collection_owner(&auth)?;
+	let items = app.meta_adapter.list_saved_items(tn_id, owner, collection_id).await?;
+	let items = accessible_items(&app, tn_id, &tenant_id_tag, &auth, items).await?;
+	let response = ApiResponse::new(items).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// POST /api/collections/{collection_id}/items - Save an item, or update the note
+/// and position of one already saved
+pub async fn post_saved_item(
+	State(app): State<App>,
+	tn_id: TnId,
+	IdTag(tenant_id_tag): IdTag,
+	Auth(auth): Auth,
+	Path(collection_id): Path<u64>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<SaveItem>,
+) -> ClResult<(StatusCode, Json<ApiResponse<SavedItem>>)> {
+	let owner = collection_owner(&auth)?;
+	if req.item_id.is_empty() {
+		return Err(Error::ValidationError("itemId cannot be empty".into()));
+	}
+	check_length("itemId", &req.item_id, MAX_ITEM_ID_LENGTH)?;
+	if let Some(note) = &req.note {
+		check_length("note", note, MAX_NOTE_LENGTH)?;
+	}
+	if let Some(position) = req.position {
+		check_position(position)?;
+	}
+	let collection = app.meta_adapter.read_collection(tn_id, owner, collection_id).await?;
+	if collection.item_count >= MAX_SAVED_ITEMS {
+		return Err(Error::ValidationError(format!(
+			"A collection holds at most {MAX_SAVED_ITEMS} items"
+		)));
+	}
+
+	// Something the caller cannot open is as good as absent: saving it must not
+	// confirm that it exists.
+	let probe = SavedItem {
+		item_type: req.item_type,
+		item_id: req.item_id.as_str().into(),
+		note: None,
+		position: 0,
+		created_at: Timestamp::now(),
+	};
+	if accessible_items(&app, tn_id, &tenant_id_tag, &auth, vec![probe])
+		.await?
+		.is_empty()
+	{
+		return Err(Error::NotFound);
+	}
+
+	let saved = app.meta_adapter.save_item(tn_id, owner, collection_id, &req).await?;
+	let response = ApiResponse::new(saved).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// PATCH /api/collections/{collection_id}/items/{item_type}/{item_id} - Change a
+/// saved item's note or move it
+pub async fn patch_saved_item(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path((collection_id, item_type, item_id)): Path<(u64, SavedItemType, String)>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<UpdateSavedItemOptions>,
+) -> ClResult<(StatusCode, Json<ApiResponse<SavedItem>>)> {
+	let owner = collection_owner(&auth)?;
+	if let Patch::Value(note) = &req.note {
+		check_length("note", note, MAX_NOTE_LENGTH)?;
+	}
+	match req.position {
+		Patch::Null => return Err(Error::ValidationError("position cannot be cleared".into())),
+		Patch::Value(position) => check_position(position)?,
+		Patch::Undefined => {}
+	}
+
+	let updated = app
+		.meta_adapter
+		.update_saved_item(tn_id, owner, collection_id, item_type, &item_id, &req)
+		.await?;
+	let response = ApiResponse::new(updated).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// DELETE /api/collections/{collection_id}/items/{item_type}/{item_id} - Remove an
+/// item from a collection
+pub async fn delete_saved_item(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path((collection_id, item_type, item_id)): Path<(u64, SavedItemType, String)>,
+) -> ClResult<StatusCode> {
+	let owner = collection_owner(&auth)?;
+	app.meta_adapter
+		.remove_saved_item(tn_id, owner, collection_id, item_type, &item_id)
+		.await?;
+	Ok(StatusCode::NO_CONTENT)
+}
+
+// vim: ts=4
@@ -23,6 +23,12 @@ pub fn get_definitions() -> Vec<ActionDefinition> {
 		comment_definition(),
 		message_definition(),
 		repost_definition(),
+		poll_definition(),
+		vote_definition(),
+		event_definition(),
+		rsvp_definition(),
+		report_definition(),
+		mod_definition(),
 		aprv_definition(),
 		stat_definition(),
 		idp_reg_definition(),
@@ -173,6 +179,7 @@ fn post_definition() -> ActionDefinition {
 			map.insert("TEXT".to_string(), "Text post".to_string());
 			map.insert("IMG".to_string(), "Image post".to_string());
 			map.insert("VID".to_string(), "Video post".to_string());
+			map.insert("EDIT".to_string(), "Edit post".to_string());
 			map.insert("DEL".to_string(), "Delete post".to_string());
 			map
 		}),
@@ -231,6 +238,8 @@ fn react_definition() -> ActionDefinition {
 			deprecated: None,
 			experimental: None,
 		}),
+		// The built-in reactions. Any Unicode emoji or a custom `:name:` emoji is a
+		// subtype too — see `cloudillo_types::reactions` and `native_hooks::react`.
 		subtypes: Some({
 			let mut map = HashMap::new();
 			map.insert("LIKE".to_string(), "Like reaction".to_string());
@@ -289,6 +298,7 @@ fn comment_definition() -> ActionDefinition {
 		}),
 		subtypes: Some({
 			let mut map = HashMap::new();
+			map.insert("EDIT".to_string(), "Edit comment".to_string());
 			map.insert("DEL".to_string(), "Delete comment".to_string());
 			map
 		}),
@@ -357,6 +367,7 @@ fn message_definition() -> ActionDefinition {
 		}),
 		subtypes: Some({
 			let mut map = HashMap::new();
+			map.insert("EDIT".to_string(), "Edit message".to_string());
 			map.insert("DEL".to_string(), "Delete message".to_string());
 			map
 		}),
@@ -495,6 +506,510 @@ fn repost_definition() -> ActionDefinition {
 	}
 }
 
+/// POLL - Broadcast question with options to vote on
+fn poll_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "POLL".to_string(),
+		version: "1.0".to_string(),
+		description: "Broadcast a poll to followers".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("content".to_string()),
+			tags: Some(vec!["poll".to_string(), "broadcast".to_string(), "content".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: Some({
+			let mut map = HashMap::new();
+			map.insert("DEL".to_string(), "Delete poll".to_string());
+			map
+		}),
+		fields: FieldConstraints { content: Some(FieldConstraint::Required), ..Default::default() },
+		schema: Some(ContentSchemaWrapper {
+			content: Some(ContentSchema {
+				content_type: ContentType::Object,
+				min_length: None,
+				max_length: None,
+				pattern: None,
+				r#enum: None,
+				properties: Some({
+					let mut props = HashMap::new();
+					props.insert(
+						"question".to_string(),
+						SchemaField {
+							field_type: FieldType::String,
+							min_length: Some(1),
+							max_length: Some(1000),
+							r#enum: None,
+							items: None,
+						},
+					);
+					// Count (2 to MAX_POLL_OPTIONS) is checked by `native_hooks::poll`.
+					props.insert(
+						"options".to_string(),
+						SchemaField {
+							field_type: FieldType::Array,
+							min_length: None,
+							max_length: None,
+							r#enum: None,
+							items: Some(Box::new(SchemaField {
+								field_type: FieldType::String,
+								min_length: Some(1),
+								max_length: Some(200),
+								r#enum: None,
+								items: None,
+							})),
+						},
+					);
+					for flag in ["multiple", "anonymous"] {
+						props.insert(
+							flag.to_string(),
+							SchemaField {
+								field_type: FieldType::Boolean,
+								min_length: None,
+								max_length: None,
+								r#enum: None,
+								items: None,
+							},
+						);
+					}
+					// Epoch seconds
+					props.insert(
+						"closesAt".to_string(),
+						SchemaField {
+							field_type: FieldType::Number,
+							min_length: None,
+							max_length: None,
+							r#enum: None,
+							items: None,
+						},
+					);
+					props
+				}),
+				required: Some(vec!["question".to_string(), "options".to_string()]),
+				description: Some("Poll question, options and voting rules".to_string()),
+			}),
+		}),
+		behavior: BehaviorFlags {
+			broadcast: Some(true),
+			allow_unknown: Some(false),
+			requires_acceptance: Some(false),
+			approvable: Some(true),
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			// Native hooks registered via the registry (see native_hooks/poll.rs):
+			// the owner opens the tally and schedules the close.
+			on_create: HookImplementation::None,
+			on_receive: HookImplementation::None,
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("followers".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		key_pattern: None,
+		search: Some(serde_json::json!({
+			"v": 1,
+			"title": ["content.question"],
+			"body": [{ "field": "content.options", "extract": "text" }]
+		})),
+	}
+}
+
+/// VOTE - Vote on a poll
+fn vote_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "VOTE".to_string(),
+		version: "1.0".to_string(),
+		description: "Vote on a poll".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("interaction".to_string()),
+			tags: Some(vec!["poll".to_string(), "vote".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: Some({
+			let mut map = HashMap::new();
+			map.insert("DEL".to_string(), "Withdraw vote".to_string());
+			map
+		}),
+		fields: FieldConstraints {
+			// Required except on VOTE:DEL; `native_hooks::poll` checks it against the poll.
+			content: None,
+			audience: None,
+			parent: Some(FieldConstraint::Forbidden), // Like REACT, the poll is the subject
+			attachments: Some(FieldConstraint::Forbidden),
+			subject: Some(FieldConstraint::Required), // The poll voted on
+		},
+		schema: Some(ContentSchemaWrapper {
+			content: Some(ContentSchema {
+				content_type: ContentType::Object,
+				min_length: None,
+				max_length: None,
+				pattern: None,
+				r#enum: None,
+				properties: Some({
+					let mut props = HashMap::new();
+					props.insert(
+						"choices".to_string(),
+						SchemaField {
+							field_type: FieldType::Array,
+							min_length: None,
+							max_length: None,
+							r#enum: None,
+							items: Some(Box::new(SchemaField {
+								field_type: FieldType::Number,
+								min_length: None,
+								max_length: None,
+								r#enum: None,
+								items: None,
+							})),
+						},
+					);
+					props
+				}),
+				required: Some(vec!["choices".to_string()]),
+				description: Some("Indexes of the chosen poll options".to_string()),
+			}),
+		}),
+		behavior: BehaviorFlags {
+			broadcast: Some(false),
+			allow_unknown: Some(true),
+			requires_acceptance: Some(false),
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			on_create: HookImplementation::None, // Native hook registered via registry
+			on_receive: HookImplementation::None, // Native hook registered via registry
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("any".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		key_pattern: Some("{type}:{subject}:{issuer}".to_string()), // One vote per user per poll
+		search: None,
+	}
+}
+
+/// EVENT - Broadcast a public event people can RSVP to
+fn event_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "EVENT".to_string(),
+		version: "1.0".to_string(),
+		description: "Broadcast an event to followers".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("content".to_string()),
+			tags: Some(vec!["event".to_string(), "broadcast".to_string(), "content".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: Some({
+			let mut map = HashMap::new();
+			map.insert("DEL".to_string(), "Delete event".to_string());
+			map
+		}),
+		fields: FieldConstraints { content: Some(FieldConstraint::Required), ..Default::default() },
+		schema: Some(ContentSchemaWrapper {
+			content: Some(ContentSchema {
+				content_type: ContentType::Object,
+				min_length: None,
+				max_length: None,
+				pattern: None,
+				r#enum: None,
+				properties: Some({
+					let mut props = HashMap::new();
+					for (name, max_length) in
+						[("title", 1000), ("description", 10000), ("location", 1000)]
+					{
+						props.insert(
+							name.to_string(),
+							SchemaField {
+								field_type: FieldType::String,
+								min_length: Some(1),
+								max_length: Some(max_length),
+								r#enum: None,
+								items: None,
+							},
+						);
+					}
+					// Epoch seconds; the range is checked by `native_hooks::event`.
+					for name in ["startsAt", "endsAt", "capacity"] {
+						props.insert(
+							name.to_string(),
+							SchemaField {
+								field_type: FieldType::Number,
+								min_length: None,
+								max_length: None,
+								r#enum: None,
+								items: None,
+							},
+						);
+					}
+					props.insert(
+						"allDay".to_string(),
+						SchemaField {
+							field_type: FieldType::Boolean,
+							min_length: None,
+							max_length: None,
+							r#enum: None,
+							items: None,
+						},
+					);
+					props
+				}),
+				required: Some(vec!["title".to_string(), "startsAt".to_string()]),
+				description: Some("Event title, time, place and capacity".to_string()),
+			}),
+		}),
+		behavior: BehaviorFlags {
+			broadcast: Some(true),
+			allow_unknown: Some(false),
+			requires_acceptance: Some(false),
+			approvable: Some(true),
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			// Native hooks registered via the registry (see native_hooks/event.rs):
+			// the owner opens the RSVP tally.
+			on_create: HookImplementation::None,
+			on_receive: HookImplementation::None,
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("followers".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		key_pattern: None,
+		search: Some(serde_json::json!({
+			"v": 1,
+			"title": ["content.title"],
+			"body": ["content.description", "content.location"]
+		})),
+	}
+}
+
+/// RSVP - Answer an event
+fn rsvp_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "RSVP".to_string(),
+		version: "1.0".to_string(),
+		description: "Answer an event invitation".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("interaction".to_string()),
+			tags: Some(vec!["event".to_string(), "rsvp".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: Some({
+			let mut map = HashMap::new();
+			map.insert("GOING".to_string(), "Attending".to_string());
+			map.insert("MAYBE".to_string(), "Maybe attending".to_string());
+			map.insert("DECLINED".to_string(), "Not attending".to_string());
+			map.insert("DEL".to_string(), "Withdraw answer".to_string());
+			map
+		}),
+		fields: FieldConstraints {
+			content: Some(FieldConstraint::Forbidden), // The subtype is the answer
+			audience: None,
+			parent: Some(FieldConstraint::Forbidden), // Like REACT, the event is the subject
+			attachments: Some(FieldConstraint::Forbidden),
+			subject: Some(FieldConstraint::Required), // The event answered
+		},
+		schema: None,
+		behavior: BehaviorFlags {
+			broadcast: Some(false),
+			allow_unknown: Some(true),
+			requires_acceptance: Some(false),
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			on_create: HookImplementation::None, // Native hook registered via registry
+			on_receive: HookImplementation::None, // Native hook registered via registry
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("any".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		key_pattern: Some("{type}:{subject}:{issuer}".to_string()), // One answer per user per event
+		search: None,
+	}
+}
+
+/// REPORT - Flag content to the moderators of the community hosting it
+fn report_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "REPORT".to_string(),
+		version: "1.0".to_string(),
+		description: "Report content to the moderators of the community hosting it".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("moderation".to_string()),
+			tags: Some(vec!["moderation".to_string(), "report".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: None,
+		fields: FieldConstraints {
+			content: Some(FieldConstraint::Required),
+			// Filled in with the hosting community by `native_hooks::report`.
+			audience: Some(FieldConstraint::Required),
+			parent: Some(FieldConstraint::Forbidden),
+			attachments: Some(FieldConstraint::Forbidden),
+			subject: Some(FieldConstraint::Required), // The reported post, comment or message
+		},
+		schema: Some(ContentSchemaWrapper {
+			content: Some(ContentSchema {
+				content_type: ContentType::Object,
+				min_length: None,
+				max_length: None,
+				pattern: None,
+				r#enum: None,
+				properties: Some({
+					let mut props = HashMap::new();
+					props.insert(
+						"reason".to_string(),
+						SchemaField {
+							field_type: FieldType::String,
+							min_length: None,
+							max_length: None,
+							r#enum: Some(
+								crate::native_hooks::report::REPORT_REASONS
+									.iter()
+									.map(|reason| serde_json::Value::String((*reason).to_string()))
+									.collect(),
+							),
+							items: None,
+						},
+					);
+					props.insert(
+						"note".to_string(),
+						SchemaField {
+							field_type: FieldType::String,
+							min_length: None,
+							max_length: Some(1000),
+							r#enum: None,
+							items: None,
+						},
+					);
+					props
+				}),
+				required: Some(vec!["reason".to_string()]),
+				description: Some("Reason category and an optional note".to_string()),
+			}),
+		}),
+		behavior: BehaviorFlags {
+			broadcast: Some(false),
+			allow_unknown: Some(true),
+			requires_acceptance: Some(false),
+			default_visibility: Some('D'), // Only the reporter and the moderators
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			on_create: HookImplementation::None, // Native hook registered via registry
+			on_receive: HookImplementation::None, // Native hook registered via registry
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("any".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		key_pattern: Some("{type}:{subject}:{issuer}".to_string()), // One open report per user per item
+		search: None,
+	}
+}
+
+/// MOD - A community's moderation decision on content it hosts
+fn mod_definition() -> ActionDefinition {
+	ActionDefinition {
+		r#type: "MOD".to_string(),
+		version: "1.0".to_string(),
+		description: "Moderation decision by the community hosting the content".to_string(),
+		metadata: Some(ActionMetadata {
+			category: Some("moderation".to_string()),
+			tags: Some(vec!["moderation".to_string()]),
+			deprecated: None,
+			experimental: None,
+		}),
+		subtypes: Some({
+			let mut map = HashMap::new();
+			map.insert("HIDE".to_string(), "Hide the content".to_string());
+			map.insert("RESTORE".to_string(), "Show hidden content again".to_string());
+			map.insert("REMOVE".to_string(), "Remove the content".to_string());
+			map.insert("WARN".to_string(), "Warn the author".to_string());
+			map
+		}),
+		fields: FieldConstraints {
+			content: None,  // MOD:WARN carries the moderator's message
+			audience: None, // MOD:WARN is addressed to the author; the rest broadcast
+			parent: Some(FieldConstraint::Forbidden),
+			attachments: Some(FieldConstraint::Forbidden),
+			subject: Some(FieldConstraint::Required), // The moderated action
+		},
+		schema: Some(ContentSchemaWrapper {
+			content: Some(ContentSchema {
+				content_type: ContentType::Object,
+				min_length: None,
+				max_length: None,
+				pattern: None,
+				r#enum: None,
+				properties: Some({
+					let mut props = HashMap::new();
+					props.insert(
+						"note".to_string(),
+						SchemaField {
+							field_type: FieldType::String,
+							min_length: None,
+							max_length: Some(1000),
+							r#enum: None,
+							items: None,
+						},
+					);
+					props
+				}),
+				required: None,
+				description: Some("Message to the author".to_string()),
+			}),
+		}),
+		behavior: BehaviorFlags {
+			broadcast: Some(true),
+			allow_unknown: Some(false),
+			requires_acceptance: Some(false),
+			..Default::default()
+		},
+		hooks: ActionHooks {
+			on_create: HookImplementation::None, // Native hook registered via registry
+			on_receive: HookImplementation::None, // Native hook registered via registry
+			on_accept: HookImplementation::None,
+			on_reject: HookImplementation::None,
+		},
+		permissions: Some(PermissionRules {
+			can_create: Some("authenticated".to_string()),
+			can_receive: Some("any".to_string()),
+			requires_following: Some(false),
+			requires_connected: Some(false),
+		}),
+		// The latest HIDE/RESTORE/REMOVE stands; a WARN is keyed apart by its audience.
+		key_pattern: Some("{type}:{subject}:{audience}".to_string()),
+		search: None,
+	}
+}
+
 /// APRV - Approval action
 /// Sent to signal trust and acceptance of an action, allowing further federation
 fn aprv_definition() -> ActionDefinition {
@@ -1453,7 +1968,7 @@ mod tests {
 		// FSHR is the tempting omission: its content is `{contentType, fileName,
 		// fileTp}` — metadata, no prose — and the shared file's own `'F'` row
 		// already makes it findable by name.
-		assert_eq!(indexed, ["CMNT", "CONV", "MSG", "POST"]);
+		assert_eq!(indexed, ["CMNT", "CONV", "EVENT", "MSG", "POLL", "POST"]);
 	}
 
 	// Regression: CONV defaults to 'S'; MSG/SUBS have no own default and inherit
@@ -412,7 +412,7 @@ impl ExpressionEvaluator {
 	}
 
 	/// Convert value to boolean (truthy/falsy)
-	fn to_bool(value: &Value) -> bool {
+	pub(crate) fn to_bool(value: &Value) -> bool {
 		match value {
 			Value::Null => false,
 			Value::Bool(b) => *b,
@@ -487,6 +487,7 @@ mod tests {
 			is_outbound: true,
 			pre_approved: false,
 			client_address: None,
+			rtdb: None,
 			vars: HashMap::new(),
 		}
 	}
@@ -11,6 +11,7 @@ pub mod definitions;
 pub mod engine;
 pub mod expression;
 pub mod operations;
+pub mod trigger;
 pub mod types;
 pub mod validator;
 
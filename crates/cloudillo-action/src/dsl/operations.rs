@@ -8,6 +8,7 @@
 //! - Action operations (create_action, get_action, update_action, delete_action)
 //! - Control flow (if, switch, foreach, return)
 //! - Data operations (set, get, merge)
+//! - RTDB operations (rtdb_get, rtdb_set, rtdb_update, rtdb_create, rtdb_delete)
 //! - Federation operations (broadcast, send)
 //! - Notification operations
 //! - Utility operations (log, abort)
@@ -135,6 +136,17 @@ impl<'a> OperationExecutor<'a> {
 			Operation::Get { var, from } => self.execute_get(var, from, context).await,
 			Operation::Merge { objects, r#as } => self.execute_merge(objects, r#as, context).await,
 
+			// RTDB operations
+			Operation::RtdbGet { path, r#as } => self.execute_rtdb_get(path, r#as, context).await,
+			Operation::RtdbSet { path, data } => self.execute_rtdb_set(path, data, context).await,
+			Operation::RtdbUpdate { path, set } => {
+				self.execute_rtdb_update(path, set, context).await
+			}
+			Operation::RtdbCreate { path, data, r#as } => {
+				self.execute_rtdb_create(path, data, r#as.as_ref(), context).await
+			}
+			Operation::RtdbDelete { path } => self.execute_rtdb_delete(path, context).await,
+
 			// Federation operations
 			Operation::BroadcastToFollowers { action_id, token } => {
 				self.execute_broadcast_to_followers(action_id, token, context).await
@@ -799,6 +811,144 @@ impl<'a> OperationExecutor<'a> {
 		Ok(())
 	}
 
+	// RTDB Operations
+	//
+	// Each one is its own RTDB transaction, so a read-modify-write such as an
+	// `increment` cannot interleave with a concurrent write to the same document.
+
+	/// The database the running trigger belongs to, and `path` evaluated.
+	fn rtdb_target(
+		&mut self,
+		path: &Expression,
+		context: &HookContext,
+	) -> ClResult<(String, String)> {
+		let Some(db_id) = context.rtdb.clone() else {
+			return Err(Error::ValidationError(
+				"rtdb operations are only available in RTDB triggers".to_string(),
+			));
+		};
+		let Value::String(path) = self.evaluator.evaluate(path, context)? else {
+			return Err(Error::ValidationError("path must be a string".to_string()));
+		};
+		Ok((db_id, path))
+	}
+
+	async fn execute_rtdb_get(
+		&mut self,
+		path: &Expression,
+		as_var: &str,
+		context: &mut HookContext,
+	) -> ClResult<()> {
+		let (db_id, path) = self.rtdb_target(path, context)?;
+		tracing::debug!("DSL: rtdb_get db={} path={}", db_id, path);
+
+		let doc = self.app.rtdb_adapter.get(context.tn_id, &db_id, &path).await?;
+		context.vars.insert(as_var.to_string(), doc.unwrap_or(Value::Null));
+		Ok(())
+	}
+
+	async fn execute_rtdb_set(
+		&mut self,
+		path: &Expression,
+		data: &Expression,
+		context: &mut HookContext,
+	) -> ClResult<()> {
+		let (db_id, path) = self.rtdb_target(path, context)?;
+		let data = self.evaluator.evaluate(data, context)?;
+		tracing::debug!("DSL: rtdb_set db={} path={}", db_id, path);
+
+		let mut tx = self.app.rtdb_adapter.transaction(context.tn_id, &db_id).await?;
+		tx.update(&path, data).await?;
+		tx.commit().await
+	}
+
+	async fn execute_rtdb_update(
+		&mut self,
+		path: &Expression,
+		updates: &HashMap<String, UpdateValue>,
+		context: &mut HookContext,
+	) -> ClResult<()> {
+		let (db_id, path) = self.rtdb_target(path, context)?;
+		tracing::debug!("DSL: rtdb_update db={} path={}", db_id, path);
+
+		// Evaluated before the transaction opens, so no expression runs while it
+		// holds the database.
+		let mut patch = Vec::with_capacity(updates.len());
+		for (field, update_value) in updates {
+			let change = match update_value {
+				UpdateValue::Direct(expr) | UpdateValue::Set { set: expr } => {
+					FieldChange::Set(self.evaluator.evaluate(expr, context)?)
+				}
+				UpdateValue::Increment { increment } => {
+					FieldChange::Add(rtdb_number(&self.evaluator.evaluate(increment, context)?)?)
+				}
+				UpdateValue::Decrement { decrement } => {
+					FieldChange::Add(-rtdb_number(&self.evaluator.evaluate(decrement, context)?)?)
+				}
+			};
+			patch.push((field, change));
+		}
+
+		let mut tx = self.app.rtdb_adapter.transaction(context.tn_id, &db_id).await?;
+		// A missing document is created, so a counter needs no seeding
+		let mut doc = match tx.get(&path).await? {
+			Some(Value::Object(doc)) => doc,
+			Some(_) => {
+				return Err(Error::ValidationError(format!("{} is not an object", path)));
+			}
+			None => serde_json::Map::new(),
+		};
+		for (field, change) in patch {
+			let value = match change {
+				FieldChange::Set(value) => value,
+				FieldChange::Add(delta) => {
+					let current = match doc.get(field) {
+						None | Some(Value::Null) => 0.0,
+						Some(v) => rtdb_number(v)?,
+					};
+					number_value(current + delta)?
+				}
+			};
+			doc.insert(field.clone(), value);
+		}
+		tx.update(&path, Value::Object(doc)).await?;
+		tx.commit().await
+	}
+
+	async fn execute_rtdb_create(
+		&mut self,
+		path: &Expression,
+		data: &Expression,
+		as_var: Option<&String>,
+		context: &mut HookContext,
+	) -> ClResult<()> {
+		let (db_id, path) = self.rtdb_target(path, context)?;
+		let data = self.evaluator.evaluate(data, context)?;
+		tracing::debug!("DSL: rtdb_create db={} collection={}", db_id, path);
+
+		let mut tx = self.app.rtdb_adapter.transaction(context.tn_id, &db_id).await?;
+		let doc_id = tx.create(&path, data).await?;
+		tx.commit().await?;
+
+		if let Some(var_name) = as_var {
+			context.vars.insert(var_name.clone(), Value::String(doc_id.into()));
+		}
+		Ok(())
+	}
+
+	async fn execute_rtdb_delete(
+		&mut self,
+		path: &Expression,
+		context: &mut HookContext,
+	) -> ClResult<()> {
+		let (db_id, path) = self.rtdb_target(path, context)?;
+		tracing::debug!("DSL: rtdb_delete db={} path={}", db_id, path);
+
+		let mut tx = self.app.rtdb_adapter.transaction(context.tn_id, &db_id).await?;
+		tx.delete(&path).await?;
+		tx.commit().await
+	}
+
 	// Federation Operations
 
 	async fn execute_broadcast_to_followers(
@@ -1087,4 +1237,29 @@ impl<'a> OperationExecutor<'a> {
 	}
 }
 
+/// One field of an `rtdb_update`, evaluated.
+enum FieldChange {
+	Set(Value),
+	Add(f64),
+}
+
+/// A counter's current value or delta. Strings are not coerced: a document field
+/// holding `"3"` is a bug in whatever wrote it, not something to count on.
+fn rtdb_number(value: &Value) -> ClResult<f64> {
+	value.as_f64().ok_or_else(|| {
+		Error::ValidationError(format!("Type mismatch: expected number, got {}", value))
+	})
+}
+
+/// Whole results stay integers, so a counter reads back as `3`, not `3.0`.
+fn number_value(n: f64) -> ClResult<Value> {
+	#[allow(clippy::cast_possible_truncation)]
+	if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+		return Ok(Value::from(n as i64));
+	}
+	serde_json::Number::from_f64(n).map(Value::Number).ok_or_else(|| {
+		Error::ValidationError("Invalid number result (NaN or infinity)".to_string())
+	})
+}
+
 // vim: ts=4
@@ -0,0 +1,510 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! RTDB triggers: DSL operations run on an RTDB database's writes, or on a cron.
+//!
+//! Declared per content type in the doc format manifest's `triggers` block, so they
+//! apply to every database of that type the tenant holds:
+//!
+//! ```json
+//! "triggers": [{
+//!   "name": "comment-count",
+//!   "on": ["create", "delete"],
+//!   "path": "posts/{post}/comments/*",
+//!   "do": [{ "op": "rtdb_update", "path": "posts/{params.post}", "set": {
+//!     "comments": { "increment": { "if": { "eq": ["{event}", "create"] }, "then": 1, "else": -1 } }
+//!   }}]
+//! }, {
+//!   "name": "daily-digest",
+//!   "cron": "0 8 * * *",
+//!   "do": [{ "op": "create_action", "type": "POST", "content": "Daily digest" }]
+//! }]
+//! ```
+//!
+//! A change trigger matches a document path segment by segment: `*` takes any one
+//! segment, `{name}` takes one and binds it under `params`. It sees `event`
+//! (`"create"`, `"update"`, `"delete"`), `path`, `params`, `data` (null on delete),
+//! `old` (null on create) and `db`, and runs only if its optional `when` expression
+//! is truthy. A cron trigger runs once per database of the content type, with
+//! `event` set to `"cron"`.
+//!
+//! Triggers run after the commit, on their own task: a write never waits on one,
+//! and a failing trigger is logged rather than failing the write. The `rtdb_*`
+//! operations they write with go straight to the adapter rather than through the
+//! websocket transaction path that dispatches triggers, so a trigger never fires
+//! another one — no cascades and no loops. Subscribers still see those writes.
+
+use super::expression::ExpressionEvaluator;
+use super::operations::{EARLY_RETURN_MARKER, OperationExecutor};
+use super::types::{Expression, Operation};
+use crate::hooks::HookContext;
+use crate::prelude::*;
+use async_trait::async_trait;
+use cloudillo_core::scheduler::{CronSchedule, Task, TaskId};
+use cloudillo_types::meta_adapter::{ListFileOptions, ProfileType, Tenant};
+use cloudillo_types::rtdb_adapter::ChangeEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Triggers one manifest may declare.
+const MAX_TRIGGERS: usize = 32;
+/// Same budget as an action hook.
+const TRIGGER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Databases a cron run lists per page.
+const PAGE: u32 = 100;
+
+/// The write kinds a change trigger can listen to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerEvent {
+	Create,
+	Update,
+	Delete,
+}
+
+impl TriggerEvent {
+	fn as_str(self) -> &'static str {
+		match self {
+			TriggerEvent::Create => "create",
+			TriggerEvent::Update => "update",
+			TriggerEvent::Delete => "delete",
+		}
+	}
+}
+
+/// One entry of a manifest's `triggers` block.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RtdbTrigger {
+	/// Unique within the manifest; keys the cron task.
+	pub name: String,
+	/// Write kinds a change trigger runs on.
+	#[serde(default)]
+	pub on: Vec<TriggerEvent>,
+	/// Document path pattern a change trigger runs on.
+	pub path: Option<String>,
+	/// Five-field cron expression, for a scheduled trigger.
+	pub cron: Option<String>,
+	/// Guard evaluated before `do`; a falsy result skips the run.
+	pub when: Option<Expression>,
+	pub r#do: Vec<Operation>,
+}
+
+impl RtdbTrigger {
+	/// The `params` a change on `path` binds, if this trigger runs on it.
+	fn matches(&self, event: TriggerEvent, path: &str) -> Option<serde_json::Map<String, Value>> {
+		if !self.on.contains(&event) {
+			return None;
+		}
+		match_path(self.path.as_deref()?, path)
+	}
+}
+
+/// Match `path` against a trigger pattern, returning the `{name}` captures.
+fn match_path(pattern: &str, path: &str) -> Option<serde_json::Map<String, Value>> {
+	let mut params = serde_json::Map::new();
+	let mut segments = path.trim_matches('/').split('/');
+	for part in pattern.trim_matches('/').split('/') {
+		let segment = segments.next()?;
+		if let Some(name) = part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+			params.insert(name.to_string(), Value::String(segment.to_string()));
+		} else if part != "*" && part != segment {
+			return None;
+		}
+	}
+	if segments.next().is_some() {
+		return None;
+	}
+	Some(params)
+}
+
+/// Parse and validate a manifest's `triggers` block.
+pub fn parse_triggers(value: &Value) -> ClResult<Vec<RtdbTrigger>> {
+	let triggers: Vec<RtdbTrigger> = serde_json::from_value(value.clone())
+		.map_err(|e| Error::ValidationError(format!("Invalid triggers: {}", e)))?;
+	if triggers.len() > MAX_TRIGGERS {
+		return Err(Error::ValidationError(format!(
+			"Too many triggers ({}), maximum is {}",
+			triggers.len(),
+			MAX_TRIGGERS
+		)));
+	}
+
+	let mut names = HashSet::new();
+	for trigger in &triggers {
+		let invalid = |why: &str| {
+			Error::ValidationError(format!("Invalid trigger '{}': {}", trigger.name, why))
+		};
+		if trigger.name.is_empty() || trigger.name.len() > 64 {
+			return Err(invalid("name must be 1-64 characters"));
+		}
+		if !names.insert(trigger.name.as_str()) {
+			return Err(invalid("duplicate name"));
+		}
+		match (&trigger.path, &trigger.cron) {
+			(Some(path), None) => {
+				if trigger.on.is_empty() {
+					return Err(invalid("a path trigger needs at least one 'on' event"));
+				}
+				validate_pattern(path).map_err(|why| invalid(&why))?;
+			}
+			(None, Some(cron)) => {
+				if !trigger.on.is_empty() || trigger.when.is_some() {
+					return Err(invalid("a cron trigger takes no 'on' or 'when'"));
+				}
+				CronSchedule::parse(cron)?;
+			}
+			_ => return Err(invalid("exactly one of 'path' and 'cron' is required")),
+		}
+		super::validator::validate_operation_list(&trigger.r#do, "do").map_err(|errors| {
+			let first = errors
+				.first()
+				.map_or_else(String::new, |e| format!("{} at {}", e.message, e.path));
+			invalid(&first)
+		})?;
+	}
+	Ok(triggers)
+}
+
+fn validate_pattern(pattern: &str) -> Result<(), String> {
+	let pattern = pattern.trim_matches('/');
+	if pattern.is_empty() {
+		return Err("empty path".to_string());
+	}
+	for part in pattern.split('/') {
+		let valid = match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+			Some(name) => {
+				!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+			}
+			None => !part.is_empty() && !part.contains(['{', '}', '*']) || part == "*",
+		};
+		if !valid {
+			return Err(format!("bad path segment '{}'", part));
+		}
+	}
+	Ok(())
+}
+
+/// The triggers governing database `db_id`, resolved through its content type.
+///
+/// A stored block that no longer parses is logged and treated as none, like a bad
+/// `search` block: the write that got here has already committed.
+async fn triggers_for(app: &App, tn_id: TnId, content_type: &str) -> ClResult<Vec<RtdbTrigger>> {
+	let Some(format) = cloudillo_core::doc_format::resolve(app, tn_id, content_type).await? else {
+		return Ok(Vec::new());
+	};
+	let Some(block) = &format.triggers else { return Ok(Vec::new()) };
+	Ok(parse_triggers(block).unwrap_or_else(|e| {
+		warn!(tn_id = %tn_id, %content_type, error = %e, "Ignoring an invalid triggers block");
+		Vec::new()
+	}))
+}
+
+fn tenant_type(tenant: &Tenant<Box<str>>) -> &'static str {
+	match tenant.typ {
+		ProfileType::Community => "community",
+		ProfileType::Person => "person",
+	}
+}
+
+/// Run one trigger's `when` and `do` in `context`.
+async fn run_trigger(app: &App, trigger: &RtdbTrigger, mut context: HookContext) -> ClResult<()> {
+	if let Some(when) = &trigger.when {
+		let guard = ExpressionEvaluator::new().evaluate(when, &context)?;
+		if !ExpressionEvaluator::to_bool(&guard) {
+			return Ok(());
+		}
+	}
+
+	let execution = async {
+		let mut executor = OperationExecutor::new(app);
+		for operation in &trigger.r#do {
+			match executor.execute(operation, &mut context).await {
+				Ok(()) => {}
+				Err(Error::ValidationError(ref msg)) if msg == EARLY_RETURN_MARKER => break,
+				Err(e) => return Err(e),
+			}
+		}
+		Ok(())
+	};
+	timeout(TRIGGER_TIMEOUT, execution).await.map_err(|_| Error::Timeout)?
+}
+
+/// Hand one committed transaction's writes to the database's change triggers.
+///
+/// Returns at once; the triggers run on a spawned task, in write order, so a
+/// counter maintained by one sees the writes in the order they were made.
+pub fn dispatch(app: &App, tn_id: TnId, db_id: &str, events: Vec<ChangeEvent>) {
+	if events.is_empty() {
+		return;
+	}
+	let app = app.clone();
+	let db_id = db_id.to_string();
+	tokio::spawn(async move {
+		if let Err(e) = run_change_triggers(&app, tn_id, &db_id, &events).await {
+			warn!(tn_id = %tn_id, db = %db_id, error = %e, "RTDB triggers failed");
+		}
+	});
+}
+
+async fn run_change_triggers(
+	app: &App,
+	tn_id: TnId,
+	db_id: &str,
+	events: &[ChangeEvent],
+) -> ClResult<()> {
+	let Some(content_type) = app
+		.meta_adapter
+		.read_file(tn_id, db_id)
+		.await?
+		.and_then(|file| file.content_type)
+	else {
+		return Ok(());
+	};
+	let triggers = triggers_for(app, tn_id, &content_type).await?;
+	if triggers.iter().all(|t| t.path.is_none()) {
+		return Ok(());
+	}
+	let tenant = app.meta_adapter.read_tenant(tn_id).await?;
+
+	for change in events {
+		let (event, path, data, old) = match change {
+			ChangeEvent::Create { path, data } => (TriggerEvent::Create, path, Some(data), None),
+			ChangeEvent::Update { path, data, old_data } => {
+				(TriggerEvent::Update, path, Some(data), old_data.as_ref())
+			}
+			ChangeEvent::Delete { path, old_data } => {
+				(TriggerEvent::Delete, path, None, old_data.as_ref())
+			}
+			_ => continue,
+		};
+		for trigger in &triggers {
+			let Some(params) = trigger.matches(event, path) else { continue };
+			let vars = HashMap::from([
+				("event".to_string(), Value::String(event.as_str().to_string())),
+				("path".to_string(), Value::String(path.to_string())),
+				("params".to_string(), Value::Object(params)),
+				("data".to_string(), data.cloned().unwrap_or(Value::Null)),
+				("old".to_string(), old.cloned().unwrap_or(Value::Null)),
+				("db".to_string(), Value::String(db_id.to_string())),
+			]);
+			let context = HookContext::builder()
+				.tenant(tn_id, &*tenant.id_tag, tenant_type(&tenant))
+				.rtdb(db_id)
+				.vars(vars)
+				.build();
+			if let Err(e) = run_trigger(app, trigger, context).await {
+				warn!(tn_id = %tn_id, db = %db_id, trigger = %trigger.name, %path, error = %e,
+					"RTDB trigger failed");
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Schedule every cron trigger of a just-stored `triggers` block.
+pub async fn schedule(app: &App, tn_id: TnId, content_type: &str, block: &Value) -> ClResult<()> {
+	for trigger in parse_triggers(block)? {
+		let Some(cron) = trigger.cron else { continue };
+		let key = format!("rtdb.trigger:{}:{}:{}", tn_id.0, content_type, trigger.name);
+		let task = TriggerCronTask {
+			tn_id,
+			content_type: content_type.into(),
+			name: trigger.name.into(),
+			cron: cron.as_str().into(),
+		};
+		app.scheduler.task(Arc::new(task)).key(key).cron(cron).schedule().await?;
+	}
+	Ok(())
+}
+
+/// One cron trigger of one `(tenant, content type)`, run over each of its databases.
+///
+/// Never unscheduled: a trigger since dropped from the manifest, or re-registered
+/// under a different cron, finds no matching declaration and does nothing. The
+/// cron is part of the parameters, so re-registering it re-points this task.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TriggerCronTask {
+	tn_id: TnId,
+	content_type: Box<str>,
+	name: Box<str>,
+	cron: Box<str>,
+}
+
+#[async_trait]
+impl Task<App> for TriggerCronTask {
+	fn kind() -> &'static str {
+		"rtdb.trigger"
+	}
+
+	fn kind_of(&self) -> &'static str {
+		Self::kind()
+	}
+
+	fn build(_id: TaskId, ctx: &str) -> ClResult<Arc<dyn Task<App>>> {
+		Ok(Arc::new(serde_json::from_str::<Self>(ctx)?))
+	}
+
+	fn serialize(&self) -> String {
+		// Built by hand for the same reason as `StatEmitTask::serialize`: no
+		// fallback that would persist a row `build` cannot read back.
+		let mut obj = serde_json::Map::with_capacity(4);
+		obj.insert("tn_id".into(), self.tn_id.0.into());
+		obj.insert("content_type".into(), self.content_type.as_ref().into());
+		obj.insert("name".into(), self.name.as_ref().into());
+		obj.insert("cron".into(), self.cron.as_ref().into());
+		Value::Object(obj).to_string()
+	}
+
+	async fn run(&self, app: &App) -> ClResult<()> {
+		let triggers = triggers_for(app, self.tn_id, &self.content_type).await?;
+		let Some(trigger) = triggers
+			.iter()
+			.find(|t| *t.name == *self.name && t.cron.as_deref() == Some(&*self.cron))
+		else {
+			debug!(tn_id = %self.tn_id, content_type = %self.content_type, trigger = %self.name,
+				"Cron trigger no longer declared; skipping");
+			return Ok(());
+		};
+		let tenant = app.meta_adapter.read_tenant(self.tn_id).await?;
+
+		let mut cursor: Option<String> = None;
+		loop {
+			let opts = ListFileOptions {
+				limit: Some(PAGE),
+				cursor: cursor.clone(),
+				file_type: Some(vec!["RTDB".to_owned()]),
+				content_type: Some(vec![self.content_type.to_string()]),
+				include_tree_children: true,
+				..Default::default()
+			};
+			let files = app.meta_adapter.list_files(self.tn_id, &opts).await?;
+			for file in &files {
+				let vars = HashMap::from([
+					("event".to_string(), Value::String("cron".to_string())),
+					("db".to_string(), Value::String(file.file_id.to_string())),
+				]);
+				let context = HookContext::builder()
+					.tenant(self.tn_id, &*tenant.id_tag, tenant_type(&tenant))
+					.rtdb(&*file.file_id)
+					.vars(vars)
+					.build();
+				if let Err(e) = run_trigger(app, trigger, context).await {
+					warn!(tn_id = %self.tn_id, db = %file.file_id, trigger = %self.name,
+						error = %e, "RTDB cron trigger failed");
+				}
+			}
+
+			let Some(last) = files.last().filter(|_| files.len() >= PAGE as usize) else {
+				break;
+			};
+			cursor = Some(
+				cloudillo_types::types::CursorData::new(
+					"created",
+					last.created_at.0.into(),
+					&last.file_id,
+				)
+				.encode(),
+			);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_match_path_binds_named_segments() {
+		let params = match_path("posts/{post}/comments/*", "posts/p1/comments/c9")
+			.expect("pattern should match");
+		assert_eq!(params.get("post"), Some(&Value::String("p1".into())));
+		assert_eq!(params.len(), 1);
+
+		assert!(match_path("posts/{post}/comments/*", "posts/p1").is_none());
+		assert!(match_path("posts/{post}", "posts/p1/comments/c9").is_none());
+		assert!(match_path("posts/*", "users/u1").is_none());
+		assert!(match_path("/posts/*/", "posts/p1").is_some());
+	}
+
+	#[test]
+	fn test_parse_triggers_accepts_change_and_cron_triggers() {
+		let triggers = parse_triggers(&serde_json::json!([
+			{
+				"name": "count",
+				"on": ["create", "delete"],
+				"path": "posts/{post}/comments/*",
+				"when": { "ne": ["{data.spam}", true] },
+				"do": [{ "op": "rtdb_update", "path": "posts/{params.post}",
+					"set": { "comments": { "increment": 1 } } }]
+			},
+			{ "name": "digest", "cron": "0 8 * * *", "do": [{ "op": "log", "message": "tick" }] }
+		]))
+		.expect("valid triggers");
+		assert_eq!(triggers.len(), 2);
+		assert!(triggers[0].matches(TriggerEvent::Create, "posts/p1/comments/c1").is_some());
+		assert!(triggers[0].matches(TriggerEvent::Update, "posts/p1/comments/c1").is_none());
+		assert!(triggers[1].matches(TriggerEvent::Create, "posts/p1").is_none());
+	}
+
+	#[test]
+	fn test_parse_triggers_rejects_malformed_blocks() {
+		let ops = serde_json::json!([{ "op": "log", "message": "x" }]);
+		let cases = [
+			("not an array", serde_json::json!({ "name": "t" })),
+			("neither path nor cron", serde_json::json!([{ "name": "t", "do": ops }])),
+			(
+				"both path and cron",
+				serde_json::json!([{ "name": "t", "on": ["create"], "path": "a/*",
+					"cron": "* * * * *", "do": ops }]),
+			),
+			("path without events", serde_json::json!([{ "name": "t", "path": "a/*", "do": ops }])),
+			("bad cron", serde_json::json!([{ "name": "t", "cron": "every day", "do": ops }])),
+			(
+				"bad pattern",
+				serde_json::json!([{ "name": "t", "on": ["create"], "path": "a//b", "do": ops }]),
+			),
+			(
+				"duplicate names",
+				serde_json::json!([
+					{ "name": "t", "cron": "* * * * *", "do": ops },
+					{ "name": "t", "cron": "* * * * *", "do": ops }
+				]),
+			),
+			(
+				"unknown field",
+				serde_json::json!([{ "name": "t", "cron": "* * * * *", "do": ops, "x": 1 }]),
+			),
+			(
+				"unknown event",
+				serde_json::json!([{ "name": "t", "on": ["upsert"], "path": "a/*", "do": ops }]),
+			),
+		];
+		for (why, block) in cases {
+			assert!(matches!(parse_triggers(&block), Err(Error::ValidationError(_))), "{why}");
+		}
+	}
+
+	#[test]
+	fn test_cron_task_serialization_round_trips() {
+		let task = TriggerCronTask {
+			tn_id: TnId(7),
+			content_type: "cloudillo/board".into(),
+			name: "digest".into(),
+			cron: "0 8 * * *".into(),
+		};
+		let back: TriggerCronTask = serde_json::from_str(&Task::<App>::serialize(&task))
+			.expect("serialized task should parse");
+		assert_eq!(back.tn_id, TnId(7));
+		assert_eq!(&*back.content_type, "cloudillo/board");
+		assert_eq!(&*back.name, "digest");
+		assert_eq!(&*back.cron, "0 8 * * *");
+	}
+}
+
+// vim: ts=4
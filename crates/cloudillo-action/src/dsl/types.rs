@@ -491,6 +491,29 @@ pub enum Operation {
 		r#as: String,
 	},
 
+	// RTDB operations — RTDB triggers only, on the database the trigger runs against
+	RtdbGet {
+		path: Expression,
+		r#as: String,
+	},
+	RtdbSet {
+		path: Expression,
+		data: Expression,
+	},
+	RtdbUpdate {
+		path: Expression,
+		set: HashMap<String, UpdateValue>,
+	},
+	RtdbCreate {
+		path: Expression,
+		data: Expression,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		r#as: Option<String>,
+	},
+	RtdbDelete {
+		path: Expression,
+	},
+
 	// Federation operations
 	BroadcastToFollowers {
 		action_id: Expression,
@@ -99,6 +99,14 @@ pub fn validate_definition(def: &ActionDefinition) -> Result<(), Vec<ValidationE
 	if errors.is_empty() { Ok(()) } else { Err(errors) }
 }
 
+/// Validate a free-standing operation list, such as an RTDB trigger's `do` block,
+/// against the same limits as a hook.
+pub fn validate_operation_list(ops: &[Operation], path: &str) -> Result<(), Vec<ValidationError>> {
+	let mut errors = Vec::new();
+	validate_operations(ops, path, &mut errors);
+	if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
 fn validate_action_type(action_type: &str) -> Result<(), String> {
 	// Must be 2-16 uppercase letters/numbers
 	if !ACTION_TYPE_RE.is_match(action_type) {
@@ -0,0 +1,392 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Ranked "for you" feed, next to the chronological home feed of `GET /api/actions`.
+//!
+//! The candidates are the home feed's posts and reposts of the last
+//! [`CANDIDATE_WINDOW_SECS`], with the reader's own left out: the newest
+//! [`CANDIDATE_LIMIT`], and as many of the newest from profiles the reader
+//! follows or is connected to, taken on their own so a flood of newer posts from
+//! others cannot push them out before they are scored. Each is scored on
+//! four signals in `[0, 1]`, combined as a weighted mean:
+//!
+//! - **connection** — the reader's relationship to the issuer: 1 connected,
+//!   ½ following, 0 otherwise (a stranger posting to a community);
+//! - **interaction** — how often the reader reacted to, commented on or
+//!   reposted the issuer's actions in the last [`INTERACTION_WINDOW_SECS`],
+//!   saturating at [`INTERACTION_SATURATION`];
+//! - **engagement** — reactions, comments and reposts (from `stat`) per hour
+//!   of the post's age, so a post drawing answers now beats one that drew them
+//!   last week;
+//! - **recency** — halving every [`RECENCY_HALF_LIFE_HOURS`].
+//!
+//! The weights are the reader's `feed.ranked.weight.*` settings. A post the
+//! reader marked seen (`PUT /api/read-marker`, scope `seen`) keeps
+//! [`SEEN_FACTOR`] of its score, or is left out with `unseen=true`.
+//!
+//! Everything is computed on this node from what it already stores; no signal
+//! leaves it.
+
+use axum::{
+	Json,
+	extract::{Query, State},
+	http::StatusCode,
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use cloudillo_core::{
+	IdTag,
+	extract::{Auth, OptionalRequestId},
+};
+use cloudillo_types::meta_adapter::{ActionView, ListActionOptions};
+use cloudillo_types::reactions::decode_reaction_counts;
+use cloudillo_types::types::ApiResponse;
+
+use crate::filter::{ContentFilters, filter_actions_by_visibility};
+use crate::handler::hidden_home_communities;
+use crate::native_hooks::edit::EDIT_SUBTYPE;
+use crate::prelude::*;
+
+/// How far back candidates are taken from.
+pub const CANDIDATE_WINDOW_SECS: i64 = 3 * 86_400;
+
+/// Most candidates taken per set (newest overall, newest from followed and
+/// connected profiles) per request.
+const CANDIDATE_LIMIT: u32 = 300;
+
+/// How far back the reader's interactions are counted.
+pub const INTERACTION_WINDOW_SECS: i64 = 30 * 86_400;
+
+/// Interactions with one issuer at which the interaction signal reaches 1.
+pub const INTERACTION_SATURATION: u32 = 20;
+
+/// Age, in hours, at which the recency signal halves.
+pub const RECENCY_HALF_LIFE_HOURS: f64 = 12.0;
+
+/// Share of its score a post keeps once the reader marked it seen.
+pub const SEEN_FACTOR: f64 = 0.2;
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 50;
+
+/// The weight settings (0–100 each, registered in [`crate::settings`]), with the
+/// signal each weighs and its default.
+pub(crate) const WEIGHT_SETTINGS: [(&str, &str, i64); 4] = [
+	("feed.ranked.weight.connection", "connection strength", 35),
+	("feed.ranked.weight.interaction", "past interactions", 25),
+	("feed.ranked.weight.engagement", "reaction and comment velocity", 15),
+	("feed.ranked.weight.recency", "recency", 25),
+];
+
+/// The reader's weights, in [`WEIGHT_SETTINGS`] order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Weights {
+	connection: f64,
+	interaction: f64,
+	engagement: f64,
+	recency: f64,
+}
+
+impl Weights {
+	async fn load(app: &App, tn_id: TnId) -> Self {
+		let mut w = [0.0; 4];
+		for (slot, (key, _, default)) in w.iter_mut().zip(WEIGHT_SETTINGS) {
+			let value = app.settings.get_int(tn_id, key).await.unwrap_or(default);
+			#[allow(clippy::cast_precision_loss)]
+			let value = value.clamp(0, 100) as f64;
+			*slot = value;
+		}
+		Self { connection: w[0], interaction: w[1], engagement: w[2], recency: w[3] }
+	}
+}
+
+/// One candidate's signals, each in `[0, 1]`.
+#[derive(Debug, Clone, Copy, Default)]
+struct Signals {
+	connection: f64,
+	interaction: f64,
+	engagement: f64,
+	recency: f64,
+}
+
+impl Signals {
+	/// The weighted mean. All-zero weights fall back to recency alone.
+	fn score(&self, w: &Weights) -> f64 {
+		let total = w.connection + w.interaction + w.engagement + w.recency;
+		if total <= 0.0 {
+			return self.recency;
+		}
+		(w.connection * self.connection
+			+ w.interaction * self.interaction
+			+ w.engagement * self.engagement
+			+ w.recency * self.recency)
+			/ total
+	}
+}
+
+fn connection_signal(following: bool, connected: bool) -> f64 {
+	if connected {
+		1.0
+	} else if following {
+		0.5
+	} else {
+		0.0
+	}
+}
+
+fn interaction_signal(interactions: u32) -> f64 {
+	let n = f64::from(interactions.min(INTERACTION_SATURATION));
+	n.ln_1p() / f64::from(INTERACTION_SATURATION).ln_1p()
+}
+
+/// Reactions, and comments and reposts at twice their weight, per hour of age
+/// (plus two, so a fresh post's first reaction does not max it out), mapped onto
+/// `[0, 1)`.
+fn engagement_signal(stat: Option<&serde_json::Value>, age_hours: f64) -> f64 {
+	let count = |key: &str| stat.and_then(|s| s.get(key)).and_then(serde_json::Value::as_u64);
+	let reactions = stat
+		.and_then(|s| s.get("reactions"))
+		.and_then(serde_json::Value::as_str)
+		.map_or(0, |r| decode_reaction_counts(r).1);
+	let answers = count("commentCount").unwrap_or(0) + count("reposts").unwrap_or(0);
+	#[allow(clippy::cast_precision_loss)]
+	let weighted = f64::from(reactions) + 2.0 * answers as f64;
+	let velocity = weighted / (age_hours + 2.0);
+	velocity / (velocity + 1.0)
+}
+
+fn recency_signal(age_hours: f64) -> f64 {
+	0.5_f64.powf(age_hours / RECENCY_HALF_LIFE_HOURS)
+}
+
+fn is_seen(action: &ActionView) -> bool {
+	action.stat.as_ref().and_then(|s| s.get("seenAt")).is_some()
+}
+
+/// The newest candidates overall, with the newest from followed and connected
+/// profiles that did not make it among them.
+fn merge_candidates(mut newest: Vec<ActionView>, related: Vec<ActionView>) -> Vec<ActionView> {
+	let seen: HashSet<Box<str>> = newest.iter().map(|a| a.action_id.clone()).collect();
+	newest.extend(related.into_iter().filter(|a| !seen.contains(&a.action_id)));
+	newest
+}
+
+/// Score and order the candidates, best first.
+fn rank(
+	candidates: Vec<ActionView>,
+	weights: &Weights,
+	relationships: &HashMap<String, (bool, bool)>,
+	interactions: &HashMap<Box<str>, u32>,
+	now: Timestamp,
+) -> Vec<ActionView> {
+	let mut scored: Vec<(f64, ActionView)> = candidates
+		.into_iter()
+		.map(|action| {
+			let issuer = action.issuer.id_tag.as_ref();
+			let (following, connected) = relationships.get(issuer).copied().unwrap_or_default();
+			#[allow(clippy::cast_precision_loss)]
+			let age_hours = (now.0 - action.created_at.0).max(0) as f64 / 3600.0;
+			let signals = Signals {
+				connection: connection_signal(following, connected),
+				interaction: interaction_signal(interactions.get(issuer).copied().unwrap_or(0)),
+				engagement: engagement_signal(action.stat.as_ref(), age_hours),
+				recency: recency_signal(age_hours),
+			};
+			let mut score = signals.score(weights);
+			if is_seen(&action) {
+				score *= SEEN_FACTOR;
+			}
+			(score, action)
+		})
+		.collect();
+	scored.sort_by(|(a_score, a), (b_score, b)| {
+		b_score
+			.total_cmp(a_score)
+			.then_with(|| b.created_at.0.cmp(&a.created_at.0))
+			.then_with(|| a.action_id.cmp(&b.action_id))
+	});
+	scored.into_iter().map(|(_, action)| action).collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedFeedQuery {
+	pub limit: Option<u32>,
+	pub offset: Option<u32>,
+	/// Leave out the posts the reader marked seen.
+	pub unseen: Option<bool>,
+}
+
+/// GET /api/feed/ranked - The reader's home feed, ranked
+pub async fn get_ranked_feed(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	IdTag(tenant_id_tag): IdTag,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Query(query): Query<RankedFeedQuery>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<ActionView>>>)> {
+	// The ranking reads the owner's relationships, interactions and settings: it
+	// is the owner's home feed, ranked, and nobody else's.
+	if tenant_id_tag.as_ref() != auth.id_tag.as_ref() {
+		return Err(Error::PermissionDenied);
+	}
+	let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+	let offset = query.offset.unwrap_or(0) as usize;
+	let now = Timestamp::now();
+
+	let opts = ListActionOptions {
+		typ: Some(vec!["POST".into(), "REPOST".into()]),
+		created_after: Some(Timestamp(now.0 - CANDIDATE_WINDOW_SECS)),
+		limit: Some(CANDIDATE_LIMIT),
+		viewer_id_tag: Some(auth.id_tag.to_string()),
+		exclude_own_issuer: Some(true),
+		exclude_sub_typ: Some(Box::from([Box::from(EDIT_SUBTYPE)])),
+		exclude_audiences: hidden_home_communities(&app, tn_id).await?,
+		..Default::default()
+	};
+	let newest = app.meta_adapter.list_actions(tn_id, &opts).await?;
+	let related_opts = ListActionOptions { related_issuers: Some(true), ..opts };
+	let related = app.meta_adapter.list_actions(tn_id, &related_opts).await?;
+	let candidates = merge_candidates(newest, related);
+	let candidates =
+		filter_actions_by_visibility(&app, tn_id, &auth.id_tag, true, &tenant_id_tag, candidates)
+			.await?;
+	let mut candidates = ContentFilters::load(&app, tn_id).await?.apply(&tenant_id_tag, candidates);
+	if query.unseen == Some(true) {
+		candidates.retain(|action| !is_seen(action));
+	}
+
+	let mut issuers: Vec<&str> = candidates.iter().map(|a| a.issuer.id_tag.as_ref()).collect();
+	issuers.sort_unstable();
+	issuers.dedup();
+	let relationships = app.meta_adapter.get_relationships(tn_id, &issuers).await?;
+	let interactions: HashMap<Box<str>, u32> = app
+		.meta_adapter
+		.count_interactions(tn_id, &auth.id_tag, Timestamp(now.0 - INTERACTION_WINDOW_SECS))
+		.await?
+		.into_iter()
+		.map(|c| (c.id_tag, c.interactions))
+		.collect();
+	let weights = Weights::load(&app, tn_id).await;
+
+	let ranked = rank(candidates, &weights, &relationships, &interactions, now);
+	let total = ranked.len();
+	let page: Vec<ActionView> = ranked.into_iter().skip(offset).take(limit).collect();
+	let response = ApiResponse::with_pagination(page, offset, limit, total)
+		.with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cloudillo_types::meta_adapter::{ProfileInfo, ProfileType};
+
+	const NOW: Timestamp = Timestamp(1_000_000);
+
+	fn post(action_id: &str, issuer: &str, age_hours: i64, stat: serde_json::Value) -> ActionView {
+		ActionView {
+			action_id: action_id.into(),
+			typ: "POST".into(),
+			sub_typ: None,
+			parent_id: None,
+			root_id: None,
+			issuer: ProfileInfo {
+				id_tag: issuer.into(),
+				name: issuer.into(),
+				typ: ProfileType::Person,
+				profile_pic: None,
+			},
+			audience: None,
+			content: None,
+			attachments: None,
+			subject: None,
+			subject_profile: None,
+			subject_action: None,
+			mentions: None,
+			created_at: Timestamp(NOW.0 - age_hours * 3600),
+			received_at: None,
+			edited_at: None,
+			expires_at: None,
+			status: Some("A".into()),
+			stat: Some(stat),
+			visibility: Some('P'),
+			flags: None,
+			sub_level: None,
+			x: None,
+			filtered: None,
+			token: None,
+		}
+	}
+
+	fn ids(actions: &[ActionView]) -> Vec<&str> {
+		actions.iter().map(|a| a.action_id.as_ref()).collect()
+	}
+
+	const DEFAULT_WEIGHTS: Weights =
+		Weights { connection: 35.0, interaction: 25.0, engagement: 15.0, recency: 25.0 };
+
+	#[test]
+	fn close_connections_outrank_busy_strangers() {
+		let candidates = vec![
+			post("a1~busy", "stranger", 1, serde_json::json!({ "reactions": "40,L40" })),
+			post("a1~friend", "friend", 3, serde_json::json!({})),
+		];
+		let relationships = HashMap::from([("friend".to_string(), (true, true))]);
+		let interactions = HashMap::from([(Box::from("friend"), 5)]);
+		let ranked = rank(candidates, &DEFAULT_WEIGHTS, &relationships, &interactions, NOW);
+		assert_eq!(ids(&ranked), ["a1~friend", "a1~busy"]);
+
+		// With only engagement weighted, the busy post wins.
+		let weights =
+			Weights { connection: 0.0, interaction: 0.0, engagement: 100.0, recency: 0.0 };
+		let candidates = vec![
+			post("a1~busy", "stranger", 1, serde_json::json!({ "reactions": "40,L40" })),
+			post("a1~friend", "friend", 3, serde_json::json!({})),
+		];
+		let ranked = rank(candidates, &weights, &relationships, &interactions, NOW);
+		assert_eq!(ids(&ranked), ["a1~busy", "a1~friend"]);
+	}
+
+	#[test]
+	fn older_connection_post_outranks_a_full_page_of_newer_strangers() {
+		let newest: Vec<ActionView> = (0..CANDIDATE_LIMIT)
+			.map(|i| {
+				let id = format!("a1~s{i}");
+				post(&id, "stranger", 0, serde_json::json!({}))
+			})
+			.collect();
+		let related = vec![
+			post("a1~s0", "stranger", 0, serde_json::json!({})),
+			post("a1~friend", "friend", 48, serde_json::json!({})),
+		];
+		let candidates = merge_candidates(newest, related);
+		assert_eq!(candidates.len(), CANDIDATE_LIMIT as usize + 1);
+
+		let relationships = HashMap::from([("friend".to_string(), (true, true))]);
+		let ranked = rank(candidates, &DEFAULT_WEIGHTS, &relationships, &HashMap::new(), NOW);
+		assert_eq!(ranked[0].action_id.as_ref(), "a1~friend");
+	}
+
+	#[test]
+	fn seen_posts_sink_and_zero_weights_fall_back_to_recency() {
+		let seen = serde_json::json!({ "seenAt": "2026-01-01T00:00:00Z" });
+		let relationships = HashMap::from([("friend".to_string(), (true, true))]);
+		let candidates = vec![
+			post("a1~seen", "friend", 1, seen),
+			post("a1~new", "friend", 20, serde_json::json!({})),
+		];
+		let ranked = rank(candidates, &DEFAULT_WEIGHTS, &relationships, &HashMap::new(), NOW);
+		assert_eq!(ids(&ranked), ["a1~new", "a1~seen"]);
+
+		let zero = Weights { connection: 0.0, interaction: 0.0, engagement: 0.0, recency: 0.0 };
+		let signals = Signals { recency: 0.25, connection: 1.0, ..Default::default() };
+		assert!((signals.score(&zero) - 0.25).abs() < f64::EPSILON);
+		assert!((interaction_signal(INTERACTION_SATURATION * 3) - 1.0).abs() < f64::EPSILON);
+		assert!((recency_signal(RECENCY_HALF_LIFE_HOURS) - 0.5).abs() < f64::EPSILON);
+	}
+}
+
+// vim: ts=4
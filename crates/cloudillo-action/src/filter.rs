@@ -1,13 +1,27 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-//! Visibility filtering for actions
+//! Visibility filtering for actions, and the tenant's content filters
+//!
+//! Content filters are the owner's own rules on what reaches their feeds: a
+//! keyword, a regular expression, a hashtag or a whole instance, each with a mode.
+//! `drop` discards a matching action on arrival (see `process.rs`), `hide` keeps it
+//! out of the owner's listings and `warn` lists it marked with the filter it matched.
+//! Above them sits the admin's instance blocklist (`federation.blocked_instances`),
+//! which refuses every action from a listed domain before its key is even fetched.
 
 use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 
+use lru::LruCache;
+use regex::{Regex, RegexBuilder};
+
 use cloudillo_core::abac::{ViewCheckContext, can_view_item};
-use cloudillo_types::meta_adapter::{ActionView, ListActionOptions};
+use cloudillo_types::meta_adapter::{
+	ActionView, ContentFilter, ContentFilterKind, ContentFilterMatch, ContentFilterMode,
+	ListActionOptions,
+};
 
 use crate::{dsl::DslEngine, prelude::*};
 
@@ -234,6 +248,326 @@ pub(crate) async fn subject_has_peer_relation_to_tenant(
 	}
 }
 
+// Content filters
+//*****************
+
+/// Longest pattern a content filter may have.
+pub const MAX_FILTER_PATTERN: usize = 200;
+
+/// Most content filters a tenant may keep.
+pub const MAX_CONTENT_FILTERS: usize = 200;
+
+/// Compiled-size ceiling for a `regex` filter: every listing runs every filter, so a
+/// pathological pattern must not be able to make them expensive.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Normalize an instance pattern: trimmed, lowercase, without a leading `*.` or `@`.
+/// `None` if what is left is not a domain name.
+pub fn normalize_instance(pattern: &str) -> Option<String> {
+	let domain = pattern.trim().trim_start_matches("*.").trim_start_matches('@');
+	let domain = domain.to_ascii_lowercase();
+	let valid = domain.contains('.')
+		&& domain.len() <= 253
+		&& domain.split('.').all(|label| {
+			!label.is_empty()
+				&& !label.starts_with('-')
+				&& !label.ends_with('-')
+				&& label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+		});
+	valid.then_some(domain)
+}
+
+/// Whether `id_tag` is `domain` itself or lives under it.
+pub fn is_on_instance(id_tag: &str, domain: &str) -> bool {
+	let id_tag = id_tag.to_ascii_lowercase();
+	let domain = domain.to_ascii_lowercase();
+	id_tag == domain || id_tag.strip_suffix(&domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Whether the admin's instance blocklist covers `id_tag`.
+pub async fn is_instance_blocked(app: &App, id_tag: &str) -> ClResult<bool> {
+	let Some(list) = app.settings.get_json_opt(TnId(0), "federation.blocked_instances").await?
+	else {
+		return Ok(false);
+	};
+	Ok(list.as_array().is_some_and(|domains| {
+		domains
+			.iter()
+			.filter_map(serde_json::Value::as_str)
+			.any(|d| is_on_instance(id_tag, d))
+	}))
+}
+
+/// Check a filter pattern against its kind and return the form to store: a hashtag
+/// without its `#` and lowercased, an instance normalized by [`normalize_instance`].
+pub fn normalize_pattern(kind: ContentFilterKind, pattern: &str) -> ClResult<String> {
+	let pattern = pattern.trim();
+	if pattern.is_empty() {
+		return Err(Error::ValidationError("Filter pattern is empty".into()));
+	}
+	if pattern.chars().count() > MAX_FILTER_PATTERN {
+		return Err(Error::ValidationError(format!(
+			"Filter pattern is longer than {} characters",
+			MAX_FILTER_PATTERN
+		)));
+	}
+	match kind {
+		ContentFilterKind::Keyword => Ok(pattern.to_string()),
+		ContentFilterKind::Regex => {
+			build_regex(pattern)
+				.map_err(|e| Error::ValidationError(format!("Invalid filter regex: {}", e)))?;
+			Ok(pattern.to_string())
+		}
+		ContentFilterKind::Hashtag => {
+			let tag = pattern.strip_prefix('#').unwrap_or(pattern);
+			if tag.is_empty() || !tag.chars().all(|c| c.is_alphanumeric() || c == '_') {
+				return Err(Error::ValidationError(format!("Invalid hashtag '{}'", pattern)));
+			}
+			Ok(tag.to_lowercase())
+		}
+		ContentFilterKind::Instance => normalize_instance(pattern)
+			.ok_or_else(|| Error::ValidationError(format!("Invalid instance '{}'", pattern))),
+	}
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, regex::Error> {
+	RegexBuilder::new(pattern)
+		.case_insensitive(true)
+		.size_limit(REGEX_SIZE_LIMIT)
+		.build()
+}
+
+/// `needle` as a whole word: not preceded or followed by a word character.
+fn build_word_regex(needle: &str) -> Result<Regex, regex::Error> {
+	build_regex(&format!(r"(?:^|\W){}(?:\W|$)", regex::escape(needle)))
+}
+
+enum Matcher {
+	Text(Regex),
+	Instance(Box<str>),
+}
+
+struct CompiledFilter {
+	filter_id: u64,
+	pattern: Box<str>,
+	mode: ContentFilterMode,
+	matcher: Matcher,
+}
+
+/// A tenant's content filters, compiled for matching.
+#[derive(Default)]
+pub struct ContentFilters {
+	filters: Vec<CompiledFilter>,
+	/// When the first of these filters expires, and the compiled set with it.
+	valid_until: Option<Timestamp>,
+}
+
+impl ContentFilters {
+	/// Compile `filters`, skipping expired ones. A stored pattern that no longer
+	/// compiles is skipped too, rather than failing every listing.
+	pub fn compile(filters: Vec<ContentFilter>) -> Self {
+		let now = Timestamp::now();
+		let filters: Vec<ContentFilter> = filters
+			.into_iter()
+			.filter(|f| f.expires_at.is_none_or(|exp| exp.0 > now.0))
+			.collect();
+		let valid_until = filters.iter().filter_map(|f| f.expires_at).min_by_key(|exp| exp.0);
+		let filters = filters
+			.into_iter()
+			.filter_map(|f| {
+				let matcher = match f.kind {
+					ContentFilterKind::Keyword => build_word_regex(&f.pattern).map(Matcher::Text),
+					ContentFilterKind::Hashtag => {
+						build_word_regex(&format!("#{}", f.pattern)).map(Matcher::Text)
+					}
+					ContentFilterKind::Regex => build_regex(&f.pattern).map(Matcher::Text),
+					ContentFilterKind::Instance => Ok(Matcher::Instance(f.pattern.clone())),
+				};
+				match matcher {
+					Ok(matcher) => Some(CompiledFilter {
+						filter_id: f.filter_id,
+						pattern: f.pattern,
+						mode: f.mode,
+						matcher,
+					}),
+					Err(e) => {
+						warn!("Skipping content filter {}: {}", f.filter_id, e);
+						None
+					}
+				}
+			})
+			.collect();
+		Self { filters, valid_until }
+	}
+
+	/// The tenant's compiled filters, from [`ContentFilterCache`] while they are
+	/// current, or loaded and compiled.
+	pub async fn load(app: &App, tn_id: TnId) -> ClResult<Arc<Self>> {
+		// Absent when `App` is built without the server crate's extension set — in
+		// tests. Load uncached rather than fail: the cache is an optimization.
+		let cache = app.ext::<Arc<ContentFilterCache>>().ok();
+		if let Some(filters) = cache.and_then(|c| c.get(tn_id)) {
+			return Ok(filters);
+		}
+		let filters = Arc::new(Self::compile(app.meta_adapter.list_content_filters(tn_id).await?));
+		if let Some(cache) = cache {
+			cache.put(tn_id, filters.clone());
+		}
+		Ok(filters)
+	}
+
+	fn is_current(&self) -> bool {
+		self.valid_until.is_none_or(|exp| exp.0 > Timestamp::now().0)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.filters.is_empty()
+	}
+
+	/// The strongest filter (`drop` over `hide` over `warn`) an action from `issuer`
+	/// with `content` matches. Text filters match every string in the content.
+	fn check(
+		&self,
+		issuer: &str,
+		content: Option<&serde_json::Value>,
+	) -> Option<(ContentFilterMode, ContentFilterMatch)> {
+		let mut text: Option<String> = None;
+		let mut strongest: Option<&CompiledFilter> = None;
+		for filter in &self.filters {
+			if strongest.is_some_and(|s| s.mode >= filter.mode) {
+				continue;
+			}
+			let matched = match &filter.matcher {
+				Matcher::Instance(domain) => is_on_instance(issuer, domain),
+				Matcher::Text(re) => {
+					let text =
+						text.get_or_insert_with(|| content.map(content_text).unwrap_or_default());
+					re.is_match(text)
+				}
+			};
+			if matched {
+				strongest = Some(filter);
+			}
+		}
+		strongest.map(|f| {
+			(f.mode, ContentFilterMatch { filter_id: f.filter_id, pattern: f.pattern.clone() })
+		})
+	}
+
+	/// The mode of the strongest filter an inbound action matches.
+	pub fn check_inbound(
+		&self,
+		issuer: &str,
+		content: Option<&serde_json::Value>,
+	) -> Option<ContentFilterMode> {
+		self.check(issuer, content).map(|(mode, _)| mode)
+	}
+
+	/// Apply the filters to a listing the owner reads: `drop` and `hide` matches are
+	/// left out, `warn` matches are marked. The owner's own actions are never filtered;
+	/// a repost is judged by itself and by what it shares.
+	pub fn apply(&self, tenant_id_tag: &str, actions: Vec<ActionView>) -> Vec<ActionView> {
+		if self.is_empty() {
+			return actions;
+		}
+		actions
+			.into_iter()
+			.filter_map(|mut action| {
+				if action.issuer.id_tag.as_ref() == tenant_id_tag {
+					return Some(action);
+				}
+				let own = self.check(&action.issuer.id_tag, action.content.as_ref());
+				let shared = action
+					.subject_action
+					.as_deref()
+					.and_then(|s| self.check(&s.issuer.id_tag, s.content.as_ref()));
+				let strongest = match (own, shared) {
+					(Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+					(a, b) => a.or(b),
+				};
+				match strongest {
+					Some((ContentFilterMode::Warn, matched)) => {
+						action.filtered = Some(matched);
+						Some(action)
+					}
+					Some(_) => None,
+					None => Some(action),
+				}
+			})
+			.collect()
+	}
+}
+
+/// Compiled content filters per tenant.
+///
+/// Every inbound action is checked against its tenant's `drop` filters, and every
+/// feed listing against the rest; without this each would read the filters and
+/// compile up to [`MAX_CONTENT_FILTERS`] regexes again. A tenant with no filters
+/// is cached too, as the empty set — the common case, and the one that would
+/// otherwise cost a read per action. An entry lapses when its first filter
+/// expires, and is invalidated by [`invalidate_content_filters`] on every write
+/// through `/api/filters`. Bounded, as the compiled regexes are not small.
+pub struct ContentFilterCache {
+	entries: parking_lot::Mutex<LruCache<TnId, Arc<ContentFilters>>>,
+}
+
+impl ContentFilterCache {
+	pub fn new(capacity: usize) -> Self {
+		let n = NonZeroUsize::new(capacity.max(1)).unwrap_or(NonZeroUsize::MIN);
+		Self { entries: parking_lot::Mutex::new(LruCache::new(n)) }
+	}
+
+	fn get(&self, tn_id: TnId) -> Option<Arc<ContentFilters>> {
+		let mut entries = self.entries.lock();
+		match entries.get(&tn_id) {
+			Some(filters) if filters.is_current() => Some(filters.clone()),
+			Some(_) => {
+				entries.pop(&tn_id);
+				None
+			}
+			None => None,
+		}
+	}
+
+	fn put(&self, tn_id: TnId, filters: Arc<ContentFilters>) {
+		self.entries.lock().put(tn_id, filters);
+	}
+
+	fn pop(&self, tn_id: TnId) {
+		self.entries.lock().pop(&tn_id);
+	}
+}
+
+/// Tenants whose compiled filters are kept.
+const CONTENT_FILTER_CACHE_CAPACITY: usize = 256;
+
+/// Build an empty [`ContentFilterCache`] for the server crate to register.
+pub fn new_content_filter_cache() -> Arc<ContentFilterCache> {
+	Arc::new(ContentFilterCache::new(CONTENT_FILTER_CACHE_CAPACITY))
+}
+
+/// Forget a tenant's compiled filters after one of them changed.
+pub fn invalidate_content_filters(app: &App, tn_id: TnId) {
+	if let Ok(cache) = app.ext::<Arc<ContentFilterCache>>() {
+		cache.pop(tn_id);
+	}
+}
+
+/// Every string in an action's content, one per line.
+fn content_text(content: &serde_json::Value) -> String {
+	fn collect<'a>(value: &'a serde_json::Value, out: &mut Vec<&'a str>) {
+		match value {
+			serde_json::Value::String(s) => out.push(s),
+			serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+			serde_json::Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+			_ => {}
+		}
+	}
+	let mut parts = Vec::new();
+	collect(content, &mut parts);
+	parts.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -263,8 +597,10 @@ mod tests {
 			subject: subject.map(Into::into),
 			subject_profile: None,
 			subject_action: None,
+			mentions: None,
 			created_at: Timestamp(0),
 			received_at: None,
+			edited_at: None,
 			expires_at: None,
 			status: Some("A".into()),
 			stat: None,
@@ -272,6 +608,7 @@ mod tests {
 			flags: None,
 			sub_level: None,
 			x: None,
+			filtered: None,
 			token: None,
 		}
 	}
@@ -299,6 +636,96 @@ mod tests {
 		assert_eq!(subscribed_container_id(&invt), "a1~conv");
 	}
 
+	fn content_filter(
+		filter_id: u64,
+		kind: ContentFilterKind,
+		pattern: &str,
+		mode: ContentFilterMode,
+	) -> ContentFilter {
+		ContentFilter {
+			filter_id,
+			kind,
+			pattern: pattern.into(),
+			mode,
+			expires_at: None,
+			created_at: Timestamp(0),
+		}
+	}
+
+	#[test]
+	fn content_filters_match_words_tags_patterns_and_instances() {
+		let filters = ContentFilters::compile(vec![
+			content_filter(1, ContentFilterKind::Keyword, "spoiler", ContentFilterMode::Warn),
+			content_filter(2, ContentFilterKind::Hashtag, "crypto", ContentFilterMode::Hide),
+			content_filter(3, ContentFilterKind::Regex, r"buy\s+now", ContentFilterMode::Hide),
+			content_filter(4, ContentFilterKind::Instance, "spam.example", ContentFilterMode::Drop),
+		]);
+		let check = |issuer: &str, text: &str| {
+			filters.check_inbound(issuer, Some(&serde_json::json!({ "text": text })))
+		};
+
+		assert_eq!(check("bob.example", "Big SPOILER ahead"), Some(ContentFilterMode::Warn));
+		assert_eq!(check("bob.example", "no spoilers here"), None);
+		assert_eq!(check("bob.example", "to the moon #Crypto"), Some(ContentFilterMode::Hide));
+		assert_eq!(check("bob.example", "#cryptography"), None);
+		assert_eq!(check("bob.example", "Buy   now!"), Some(ContentFilterMode::Hide));
+		assert_eq!(check("eve.spam.example", "hello"), Some(ContentFilterMode::Drop));
+		assert_eq!(check("notspam.example", "hello"), None);
+		// The strongest mode wins.
+		assert_eq!(check("eve.spam.example", "spoiler"), Some(ContentFilterMode::Drop));
+	}
+
+	#[test]
+	fn expired_content_filters_do_not_apply() {
+		let mut expired =
+			content_filter(1, ContentFilterKind::Keyword, "spoiler", ContentFilterMode::Hide);
+		expired.expires_at = Some(Timestamp(1));
+		let filters = ContentFilters::compile(vec![expired]);
+		assert!(filters.is_empty());
+	}
+
+	#[test]
+	fn cached_content_filters_lapse_at_their_first_expiry() {
+		let mut soon =
+			content_filter(1, ContentFilterKind::Keyword, "spoiler", ContentFilterMode::Hide);
+		soon.expires_at = Some(Timestamp(Timestamp::now().0 + 2));
+		let filters = ContentFilters::compile(vec![
+			content_filter(2, ContentFilterKind::Keyword, "crypto", ContentFilterMode::Hide),
+			soon,
+		]);
+		assert!(filters.is_current());
+
+		let cache = ContentFilterCache::new(4);
+		cache.put(TnId(1), Arc::new(filters));
+		assert!(cache.get(TnId(1)).is_some());
+		assert!(cache.get(TnId(2)).is_none());
+		cache.put(
+			TnId(2),
+			Arc::new(ContentFilters {
+				valid_until: Some(Timestamp(1)),
+				..ContentFilters::compile(Vec::new())
+			}),
+		);
+		assert!(cache.get(TnId(2)).is_none());
+		cache.pop(TnId(1));
+		assert!(cache.get(TnId(1)).is_none());
+	}
+
+	#[test]
+	fn filter_patterns_are_normalized_per_kind() {
+		assert_eq!(normalize_pattern(ContentFilterKind::Hashtag, "#Rust").unwrap(), "rust");
+		assert!(normalize_pattern(ContentFilterKind::Hashtag, "#two words").is_err());
+		assert_eq!(
+			normalize_pattern(ContentFilterKind::Instance, "*.Spam.Example").unwrap(),
+			"spam.example"
+		);
+		assert!(normalize_pattern(ContentFilterKind::Instance, "localhost").is_err());
+		assert!(normalize_pattern(ContentFilterKind::Regex, "(unclosed").is_err());
+		assert!(normalize_pattern(ContentFilterKind::Keyword, "   ").is_err());
+		assert!(is_on_instance("alice.spam.example", "spam.example"));
+		assert!(!is_on_instance("alicespam.example", "spam.example"));
+	}
+
 	#[test]
 	fn test_subscribed_container_id_root_beats_subject() {
 		// General rule is root_id ?? subject ?? action_id — root wins when both present.
@@ -10,6 +10,7 @@
 //!
 //! - `forward_action`: Called after an action is created or received to notify connected users
 
+use crate::native_hooks::edit::EDIT_SUBTYPE;
 use crate::prelude::*;
 use cloudillo_core::ws_broadcast::{BroadcastMessage, DeliveryResult};
 use cloudillo_types::auth_adapter::ActionToken;
@@ -194,8 +195,8 @@ fn build_action_message(params: &ForwardActionParams<'_>) -> BroadcastMessage {
 /// Returns true if the action type is configured to send push notifications
 /// when the user is offline.
 pub fn should_push_notify(action_type: &str, sub_type: Option<&str>) -> bool {
-	// DEL subtypes don't trigger notifications
-	if sub_type.is_some_and(|s| s == "DEL") {
+	// DEL and EDIT subtypes don't trigger notifications
+	if matches!(sub_type, Some("DEL" | EDIT_SUBTYPE)) {
 		return false;
 	}
 
@@ -310,9 +311,9 @@ pub fn get_push_setting_key(action_type: &str) -> &'static str {
 /// Unlike `should_push_notify` (which excludes FLLW/REACT/POST), this admits
 /// every type that has a type-specific email cadence key — i.e. every type
 /// `get_email_setting_key` maps to something other than the bare `notify.email`
-/// master-switch fallback. DEL subtypes never notify.
+/// master-switch fallback. DEL and EDIT subtypes never notify.
 pub fn is_email_notifiable(action_type: &str, sub_type: Option<&str>) -> bool {
-	if sub_type == Some("DEL") {
+	if matches!(sub_type, Some("DEL" | EDIT_SUBTYPE)) {
 		return false;
 	}
 	// A type-specific cadence key exists (not the bare master-switch fallback).
@@ -355,6 +356,9 @@ mod tests {
 		// DEL subtypes don't notify
 		assert!(!should_push_notify("MSG", Some("DEL")));
 		assert!(!should_push_notify("CONN", Some("DEL")));
+		// Neither do edits: a fixed typo is not a new message
+		assert!(!should_push_notify("MSG", Some("EDIT")));
+		assert!(!should_push_notify("CMNT", Some("EDIT")));
 	}
 
 	#[test]
@@ -377,6 +381,7 @@ mod tests {
 		// DEL subtypes never notify.
 		assert!(!is_email_notifiable("REACT", Some("DEL")));
 		assert!(!is_email_notifiable("CMNT", Some("DEL")));
+		assert!(!is_email_notifiable("CMNT", Some("EDIT")));
 
 		// Unknown types (no specific key → master-switch fallback) are skipped.
 		assert!(!is_email_notifiable("UNKNOWN", None));
@@ -3,8 +3,9 @@
 
 use axum::{
 	Json,
+	body::Body,
 	extract::{ConnectInfo, Path, Query, State},
-	http::StatusCode,
+	http::{Response, StatusCode, header},
 };
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
@@ -25,12 +26,33 @@ use cloudillo_types::types::{self, ApiResponse};
 
 use crate::{
 	dsl::DslEngine,
-	filter::filter_actions_by_visibility,
-	helpers,
+	filter::{self, ContentFilters, filter_actions_by_visibility},
+	helpers, native_hooks,
 	prelude::*,
 	task::{self, ActionVerifierTask, CreateAction},
 };
 
+/// The communities the reader opted out of home (`profiles.hidden_in_home = 1`),
+/// as `ListActionOptions::exclude_audiences` takes them. `None` when there are none.
+pub(crate) async fn hidden_home_communities(
+	app: &App,
+	tn_id: TnId,
+) -> ClResult<Option<Box<[String]>>> {
+	let communities = app
+		.meta_adapter
+		.list_profiles(
+			tn_id,
+			&meta_adapter::ListProfileOptions {
+				typ: Some(meta_adapter::ProfileType::Community),
+				hidden_in_home: Some(true),
+				..Default::default()
+			},
+		)
+		.await?;
+	let hidden: Vec<String> = communities.into_iter().map(|p| p.id_tag.to_string()).collect();
+	Ok((!hidden.is_empty()).then(|| hidden.into_boxed_slice()))
+}
+
 pub async fn list_actions(
 	State(app): State<App>,
 	tn_id: TnId,
@@ -50,13 +72,22 @@ pub async fn list_actions(
 		opts.viewer_id_tag = Some(subject_id_tag.to_string());
 	}
 
+	// EDIT rows are applied to the action they name, whose revisions are listed
+	// under `/actions/{id}/revisions`. Listed on their own they would show up as a
+	// second post or comment, so only a fetch by id returns one.
+	if opts.action_id.is_none() {
+		opts.exclude_sub_typ = Some(Box::from([Box::from(native_hooks::edit::EDIT_SUBTYPE)]));
+	}
+
 	// Home feed composition: when serving the merged home feed (no explicit
 	// audience/audienceType and not a thread/single-action fetch), drop posts
 	// addressed to communities the reader opted out of home
 	// (`profiles.hidden_in_home = 1`). Explicit community/profile feeds and
 	// thread/comment fetches (parent_id/root_id/action_id/subject) are left
-	// untouched so a hidden community's own feed and threads still resolve.
+	// untouched so a hidden community's own feed and threads still resolve, and so
+	// is a hashtag listing, which searches everything the reader may see.
 	let is_home_feed = is_authenticated
+		&& opts.tag.is_none()
 		&& opts.audience.is_none()
 		&& opts.audience_type.is_none()
 		&& opts.parent_id.is_none()
@@ -64,21 +95,7 @@ pub async fn list_actions(
 		&& opts.action_id.is_none()
 		&& opts.subject.is_none();
 	if is_home_feed {
-		let communities = app
-			.meta_adapter
-			.list_profiles(
-				tn_id,
-				&meta_adapter::ListProfileOptions {
-					typ: Some(meta_adapter::ProfileType::Community),
-					hidden_in_home: Some(true),
-					..Default::default()
-				},
-			)
-			.await?;
-		let hidden: Vec<String> = communities.into_iter().map(|p| p.id_tag.to_string()).collect();
-		if !hidden.is_empty() {
-			opts.exclude_audiences = Some(hidden.into_boxed_slice());
-		}
+		opts.exclude_audiences = hidden_home_communities(&app, tn_id).await?;
 	}
 
 	let limit = opts.limit.unwrap_or(20) as usize;
@@ -142,6 +159,13 @@ pub async fn list_actions(
 		None
 	};
 
+	// The owner's content filters, applied after the cursor is taken so a page of
+	// filtered-out rows does not end the listing early. A fetch by id is left alone.
+	if is_authenticated && subject_id_tag == tenant_id_tag.as_ref() && opts.action_id.is_none() {
+		let content_filters = ContentFilters::load(&app, tn_id).await?;
+		filtered = content_filters.apply(&tenant_id_tag, filtered);
+	}
+
 	let response = ApiResponse::with_cursor_pagination(filtered, next_cursor, has_more)
 		.with_req_id(req_id.unwrap_or_default());
 
@@ -150,7 +174,7 @@ pub async fn list_actions(
 
 /// Body for `PUT /api/read-marker`. `scope` selects the watermark column,
 /// `key` is the entity id (context/peer id_tag for feed/msg, action_id for
-/// thread), `position` is the forward-only watermark value. `position` is a
+/// thread/seen), `position` is the forward-only watermark value. `position` is a
 /// `Timestamp`, so it accepts an ISO 8601 string (the client's wire format) or
 /// a raw epoch-seconds integer.
 #[derive(Deserialize)]
@@ -172,8 +196,8 @@ pub async fn put_read_marker(
 	// Caller must be the tenant owner. All read-watermarks are reader-local rows
 	// in this tenant's own DB: `profiles.feed_read_at`/`msg_read_at` on the
 	// reader's per-context/per-peer profile row (keyed by that id_tag), and
-	// `actions.comments_read_at` on the reader's cached action row. The `tn_id`
-	// scoping is the authorization boundary; the owner check just stops a
+	// `actions.comments_read_at`/`seen_at` on the reader's cached action row. The
+	// `tn_id` scoping is the authorization boundary; the owner check just stops a
 	// principal authenticated on this node from moving the owner's markers.
 	if id_tag.as_ref() != auth.id_tag.as_ref() {
 		return Err(Error::PermissionDenied);
@@ -181,11 +205,11 @@ pub async fn put_read_marker(
 	// Verify the target row exists in this tenant before the UPDATE so a bogus key
 	// is a 404 rather than a silently-dropped watermark (consistent across scopes,
 	// and avoids a silent no-op masquerading as success). `feed`/`msg` resolve the
-	// reader's per-context/per-peer `profiles` row; `thread` the cached action row.
-	// An unknown scope falls through to `set_read_marker`, which returns a
-	// ValidationError for it.
+	// reader's per-context/per-peer `profiles` row; `thread` and `seen` the cached
+	// action row. An unknown scope falls through to `set_read_marker`, which returns
+	// a ValidationError for it.
 	match body.scope.as_str() {
-		"thread" => {
+		"thread" | "seen" => {
 			app.meta_adapter.get_action(tn_id, &body.key).await?.ok_or(Error::NotFound)?;
 		}
 		"feed" | "msg" => {
@@ -260,6 +284,12 @@ pub async fn post_action(
 		return Err(Error::PermissionDenied);
 	}
 
+	// MOD actions carry a community's moderation decisions and are minted only by the
+	// moderation API (`crate::moderation`), which checks the moderator's role first.
+	if helpers::extract_type_and_subtype(&action.typ).0 == "MOD" {
+		return Err(Error::PermissionDenied);
+	}
+
 	// Role-hierarchy guard on community member removal (CONN:DEL). The authoritative
 	// outbound check: only moderators+ may remove a member, and an actor may only remove a
 	// member strictly below them (leaders may also remove peer leaders). Self-leave
@@ -462,6 +492,47 @@ pub async fn get_action_by_id(
 	}
 }
 
+/// GET /api/actions/:action_id/revisions - Content history of an edited action
+///
+/// Newest first; the first entry is the current content. Empty for an action that
+/// was never edited. Access is the action's own (ABAC read on `action_id`).
+pub async fn list_action_revisions(
+	State(app): State<App>,
+	tn_id: TnId,
+	Path(action_id): Path<String>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<meta_adapter::ActionRevision>>>)> {
+	let revisions = app.meta_adapter.list_action_revisions(tn_id, &action_id).await?;
+
+	let response = ApiResponse::new(revisions).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// GET /api/actions/:action_id/ics - An EVENT as an iCalendar file
+///
+/// For guests without a Cloudillo account, whose calendar app imports the file.
+/// Access is the event's own (ABAC read on `action_id`), so a public event is a
+/// public download.
+pub async fn get_action_ics(
+	State(app): State<App>,
+	tn_id: TnId,
+	Path(action_id): Path<String>,
+) -> ClResult<Response<Body>> {
+	let action = app.meta_adapter.get_action(tn_id, &action_id).await?.ok_or(Error::NotFound)?;
+	if action.typ.as_ref() != "EVENT" {
+		return Err(Error::NotFound);
+	}
+	let event = native_hooks::event::EventContent::parse(action.content.as_ref())?;
+	let input = native_hooks::event::to_calendar_input(&action, &event, None);
+	let ics = cloudillo_calendar::ical::generate(&input);
+
+	Ok(Response::builder()
+		.status(StatusCode::OK)
+		.header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+		.header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.ics\"", action_id))
+		.body(Body::from(ics))?)
+}
+
 /// DELETE /api/actions/:action_id - Delete action
 pub async fn delete_action(
 	State(app): State<App>,
@@ -1412,4 +1483,101 @@ pub async fn get_outbox(
 	Ok((StatusCode::OK, Json(response)))
 }
 
+// Content filters
+//*****************
+
+/// Reject an `expiresAt` that is not in the future (mirrors share entries).
+fn check_filter_expiry(expires_at: Option<Timestamp>) -> ClResult<()> {
+	if let Some(exp) = expires_at
+		&& exp.0 <= Timestamp::now().0
+	{
+		return Err(Error::ValidationError("Expiration time must be in the future".into()));
+	}
+	Ok(())
+}
+
+/// GET /api/filters - The tenant's content filters that have not expired
+pub async fn list_content_filters(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<meta_adapter::ContentFilter>>>)> {
+	let filters = app.meta_adapter.list_content_filters(auth.tn_id).await?;
+	let response = ApiResponse::new(filters).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// POST /api/filters - Create a content filter
+pub async fn post_content_filter(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(mut req): Json<meta_adapter::CreateContentFilter>,
+) -> ClResult<(StatusCode, Json<ApiResponse<meta_adapter::ContentFilter>>)> {
+	req.pattern = filter::normalize_pattern(req.kind, &req.pattern)?;
+	check_filter_expiry(req.expires_at)?;
+	let existing = app.meta_adapter.list_content_filters(auth.tn_id).await?;
+	if existing.len() >= filter::MAX_CONTENT_FILTERS {
+		return Err(Error::ValidationError(format!(
+			"At most {} content filters are allowed",
+			filter::MAX_CONTENT_FILTERS
+		)));
+	}
+
+	let created = app.meta_adapter.create_content_filter(auth.tn_id, &req).await?;
+	filter::invalidate_content_filters(&app, auth.tn_id);
+	info!(
+		"Content filter {} created by {}: {:?} '{}' ({:?})",
+		created.filter_id, auth.id_tag, created.kind, created.pattern, created.mode
+	);
+	let response = ApiResponse::new(created).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// PATCH /api/filters/{filter_id} - Update a content filter's pattern, mode or expiry
+pub async fn patch_content_filter(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	Path(filter_id): Path<u64>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(mut req): Json<meta_adapter::UpdateContentFilterOptions>,
+) -> ClResult<(StatusCode, Json<ApiResponse<meta_adapter::ContentFilter>>)> {
+	if req.pattern.is_null() || req.mode.is_null() {
+		return Err(Error::ValidationError("pattern and mode cannot be cleared".into()));
+	}
+	if let types::Patch::Value(pattern) = &req.pattern {
+		// The pattern is checked against the filter's kind, which only the row knows.
+		let kind = app
+			.meta_adapter
+			.list_content_filters(auth.tn_id)
+			.await?
+			.into_iter()
+			.find(|f| f.filter_id == filter_id)
+			.ok_or(Error::NotFound)?
+			.kind;
+		req.pattern = types::Patch::Value(filter::normalize_pattern(kind, pattern)?);
+	}
+	if let types::Patch::Value(exp) = req.expires_at {
+		check_filter_expiry(Some(exp))?;
+	}
+
+	let updated = app.meta_adapter.update_content_filter(auth.tn_id, filter_id, &req).await?;
+	filter::invalidate_content_filters(&app, auth.tn_id);
+	let response = ApiResponse::new(updated).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// DELETE /api/filters/{filter_id} - Delete a content filter
+pub async fn delete_content_filter(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	Path(filter_id): Path<u64>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<()>>)> {
+	app.meta_adapter.delete_content_filter(auth.tn_id, filter_id).await?;
+	filter::invalidate_content_filters(&app, auth.tn_id);
+	let response = ApiResponse::new(()).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
 // vim: ts=4
@@ -0,0 +1,290 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Hashtags: the per-tenant tag index, hashtag listings, followed hashtags and
+//! trending topics.
+//!
+//! The hashtags of every POST and CMNT are extracted and normalized as the action
+//! is stored, whether created here or received (see
+//! [`cloudillo_types::hashtags`] for what counts as a tag), and re-extracted when
+//! an edit replaces the content. The index backs `tag` in
+//! [`ListActionOptions`](cloudillo_types::meta_adapter::ListActionOptions) and
+//! `GET /api/hashtags/{tag}/actions`, which lists what the viewer may see.
+//!
+//! A followed hashtag pulls public posts into the home feed: `followedHashtags`
+//! lists the public posts of followed profiles carrying one, and a post that
+//! carries one is kept even when its community is hidden from home.
+//!
+//! [`TrendingHashtagsTask`] recomputes each tenant's trending lists every
+//! [`TRENDING_CRON`] over the [`TRENDING_WINDOWS`]. A tag's score is
+//! `accounts² / (previous accounts + 1)`, where `accounts` counts the distinct
+//! issuers who used it in public posts and comments during the window and
+//! `previous accounts` those of the window before it, so a tag rises by spreading
+//! to new people faster than it used to, not by one account repeating it.
+
+use async_trait::async_trait;
+use axum::{
+	Json,
+	extract::{Path, Query, State},
+	http::StatusCode,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cloudillo_core::{
+	IdTag,
+	extract::{Auth, OptionalAuth, OptionalRequestId},
+	scheduler::{Task, TaskId},
+};
+use cloudillo_types::hashtags::{HASHTAG_ACTION_TYPES, normalize_hashtag, stored_content_hashtags};
+use cloudillo_types::meta_adapter::{
+	self, FollowedHashtag, HashtagCount, ListTenantsMetaOptions, TrendingHashtag,
+};
+use cloudillo_types::types::ApiResponse;
+
+use crate::native_hooks::edit::EDIT_SUBTYPE;
+use crate::prelude::*;
+
+/// The trending windows, by name, with their length in seconds.
+pub const TRENDING_WINDOWS: [(&str, i64); 3] = [("1h", 3600), ("24h", 86_400), ("7d", 604_800)];
+
+/// Window served when the request names none.
+const DEFAULT_TRENDING_WINDOW: &str = "24h";
+
+/// How often the trending lists are recomputed.
+pub const TRENDING_CRON: &str = "*/15 * * * *";
+
+/// Most tags kept per trending list.
+const TRENDING_LIMIT: usize = 20;
+
+/// Fewest distinct accounts a tag needs in the window to trend.
+const TRENDING_MIN_ACCOUNTS: u32 = 2;
+
+/// Most-used tags of the window that are scored.
+const TRENDING_CANDIDATES: u32 = 200;
+
+/// Most tags of the previous window looked up for the baseline. A candidate
+/// below this cut scores against a baseline of zero.
+const TRENDING_BASELINE: u32 = 1000;
+
+/// Most hashtags one tenant may follow.
+pub const MAX_FOLLOWED_HASHTAGS: usize = 500;
+
+/// Index the hashtags of a stored action's content (the JSON-encoded
+/// `actions.content`). Only POST and CMNT are indexed, and not their DEL / EDIT
+/// markers: an edit reindexes the action it edits instead. Failures are logged,
+/// never fatal — the action is stored either way.
+pub(crate) async fn index_action(
+	app: &App,
+	tn_id: TnId,
+	action_id: &str,
+	typ: &str,
+	sub_typ: Option<&str>,
+	content: Option<&str>,
+) {
+	if !HASHTAG_ACTION_TYPES.contains(&typ) || matches!(sub_typ, Some("DEL" | EDIT_SUBTYPE)) {
+		return;
+	}
+	let tags = content.map(stored_content_hashtags).unwrap_or_default();
+	if let Err(e) = app.meta_adapter.update_action_hashtags(tn_id, action_id, &tags).await {
+		warn!(action_id = %action_id, error = %e, "Failed to index hashtags");
+	}
+}
+
+/// Normalize a hashtag from a request path.
+fn hashtag_param(tag: &str) -> ClResult<String> {
+	normalize_hashtag(tag).ok_or_else(|| Error::ValidationError(format!("invalid hashtag: {tag}")))
+}
+
+/// GET /api/hashtags/{tag}/actions - Actions carrying a hashtag, as `GET /api/actions`
+/// lists them (posts unless `type` says otherwise).
+pub async fn list_hashtag_actions(
+	State(app): State<App>,
+	tn_id: TnId,
+	id_tag: IdTag,
+	auth: OptionalAuth,
+	req_id: OptionalRequestId,
+	Path(tag): Path<String>,
+	Query(mut opts): Query<meta_adapter::ListActionOptions>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<meta_adapter::ActionView>>>)> {
+	opts.tag = Some(hashtag_param(&tag)?);
+	if opts.typ.is_none() {
+		opts.typ = Some(vec!["POST".into()]);
+	}
+	crate::handler::list_actions(State(app), tn_id, id_tag, auth, req_id, Query(opts)).await
+}
+
+#[derive(Deserialize)]
+pub struct TrendingQuery {
+	window: Option<String>,
+}
+
+/// GET /api/hashtags/trending - The trending hashtags of a window (`1h`, `24h`, `7d`)
+pub async fn list_trending_hashtags(
+	State(app): State<App>,
+	tn_id: TnId,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Query(query): Query<TrendingQuery>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<TrendingHashtag>>>)> {
+	let window = query.window.as_deref().unwrap_or(DEFAULT_TRENDING_WINDOW);
+	if !TRENDING_WINDOWS.iter().any(|(name, _)| *name == window) {
+		return Err(Error::ValidationError(format!("unknown trending window: {window}")));
+	}
+	let trending = app.meta_adapter.list_trending_hashtags(tn_id, window).await?;
+	let response = ApiResponse::new(trending).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// GET /api/hashtags/followed - The hashtags the tenant follows
+pub async fn list_followed_hashtags(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<FollowedHashtag>>>)> {
+	let followed = app.meta_adapter.list_followed_hashtags(auth.tn_id).await?;
+	let response = ApiResponse::new(followed).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// PUT /api/hashtags/followed/{tag} - Follow a hashtag
+pub async fn put_followed_hashtag(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	Path(tag): Path<String>,
+) -> ClResult<StatusCode> {
+	let tag = hashtag_param(&tag)?;
+	let followed = app.meta_adapter.list_followed_hashtags(auth.tn_id).await?;
+	if followed.iter().any(|f| *f.tag == *tag) {
+		return Ok(StatusCode::NO_CONTENT);
+	}
+	if followed.len() >= MAX_FOLLOWED_HASHTAGS {
+		return Err(Error::ValidationError(format!(
+			"At most {MAX_FOLLOWED_HASHTAGS} hashtags may be followed"
+		)));
+	}
+	app.meta_adapter.follow_hashtag(auth.tn_id, &tag).await?;
+	info!("Hashtag #{} followed by {}", tag, auth.id_tag);
+	Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/hashtags/followed/{tag} - Unfollow a hashtag
+pub async fn delete_followed_hashtag(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	Path(tag): Path<String>,
+) -> ClResult<StatusCode> {
+	let tag = hashtag_param(&tag)?;
+	app.meta_adapter.unfollow_hashtag(auth.tn_id, &tag).await?;
+	info!("Hashtag #{} unfollowed by {}", tag, auth.id_tag);
+	Ok(StatusCode::NO_CONTENT)
+}
+
+/// Score the tags used in a window against the window before it, best first.
+fn rank_trending(current: Vec<HashtagCount>, previous: &[HashtagCount]) -> Vec<TrendingHashtag> {
+	let baseline: HashMap<&str, u32> =
+		previous.iter().map(|c| (c.tag.as_ref(), c.accounts)).collect();
+	let mut trending: Vec<TrendingHashtag> = current
+		.into_iter()
+		.filter(|c| c.accounts >= TRENDING_MIN_ACCOUNTS)
+		.map(|c| {
+			let before = baseline.get(c.tag.as_ref()).copied().unwrap_or(0);
+			let score = f64::from(c.accounts).powi(2) / f64::from(before + 1);
+			TrendingHashtag { tag: c.tag, uses: c.uses, accounts: c.accounts, score }
+		})
+		.collect();
+	trending.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+	trending.truncate(TRENDING_LIMIT);
+	trending
+}
+
+/// Recompute one tenant's trending lists.
+async fn compute_trending(app: &App, tn_id: TnId, now: i64) -> ClResult<()> {
+	for (window, secs) in TRENDING_WINDOWS {
+		let current = app
+			.meta_adapter
+			.count_hashtags(tn_id, Timestamp(now - secs), Timestamp(now), TRENDING_CANDIDATES)
+			.await?;
+		let previous = app
+			.meta_adapter
+			.count_hashtags(
+				tn_id,
+				Timestamp(now - 2 * secs),
+				Timestamp(now - secs),
+				TRENDING_BASELINE,
+			)
+			.await?;
+		let trending = rank_trending(current, &previous);
+		app.meta_adapter.replace_trending_hashtags(tn_id, window, &trending).await?;
+	}
+	Ok(())
+}
+
+/// Periodic recomputation of every tenant's trending hashtags.
+#[derive(Debug, Default)]
+pub struct TrendingHashtagsTask;
+
+#[async_trait]
+impl Task<App> for TrendingHashtagsTask {
+	fn kind() -> &'static str {
+		"action.hashtags_trending"
+	}
+	fn kind_of(&self) -> &'static str {
+		Self::kind()
+	}
+
+	fn build(_id: TaskId, _ctx: &str) -> ClResult<Arc<dyn Task<App>>> {
+		Ok(Arc::new(TrendingHashtagsTask))
+	}
+
+	fn serialize(&self) -> String {
+		String::new()
+	}
+
+	async fn run(&self, app: &App) -> ClResult<()> {
+		let now = Timestamp::now().0;
+		let tenants = app.meta_adapter.list_tenants(&ListTenantsMetaOptions::default()).await?;
+		for tenant in tenants {
+			// One tenant's failure must not cost the others their lists.
+			if let Err(e) = compute_trending(app, tenant.tn_id, now).await {
+				warn!("hashtags: trending for tenant {} failed: {}", tenant.tn_id, e);
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Schedule the trending recomputation.
+pub async fn schedule(app: &App) -> ClResult<()> {
+	let task: Arc<dyn Task<App>> = Arc::new(TrendingHashtagsTask);
+	app.scheduler
+		.task(task)
+		.key("action.hashtags_trending")
+		.cron(TRENDING_CRON)
+		.run_on_startup()
+		.schedule()
+		.await?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn count(tag: &str, uses: u32, accounts: u32) -> HashtagCount {
+		HashtagCount { tag: tag.into(), uses, accounts }
+	}
+
+	#[test]
+	fn new_tags_outrank_steady_ones() {
+		let current = vec![count("weather", 40, 10), count("eclipse", 12, 6), count("spam", 50, 1)];
+		let previous = vec![count("weather", 45, 11)];
+		let trending = rank_trending(current, &previous);
+		let tags: Vec<&str> = trending.iter().map(|t| t.tag.as_ref()).collect();
+		// eclipse: 36 / 1; weather: 100 / 12; spam: one account only.
+		assert_eq!(tags, ["eclipse", "weather"]);
+		assert!((trending[0].score - 36.0).abs() < f64::EPSILON);
+	}
+}
+
+// vim: ts=4
@@ -185,6 +185,10 @@ pub struct HookContext {
 	/// Client IP address (available for inbound actions)
 	pub client_address: Option<String>,
 
+	/// The RTDB database (file id) an RTDB trigger runs against, which the
+	/// `rtdb_*` operations write to. `None` in action hooks.
+	pub rtdb: Option<String>,
+
 	// Variables set by operations
 	pub vars: HashMap<String, serde_json::Value>,
 }
@@ -216,6 +220,7 @@ pub struct HookContextBuilder {
 	is_outbound: bool,
 	pre_approved: bool,
 	client_address: Option<String>,
+	rtdb: Option<String>,
 	vars: HashMap<String, serde_json::Value>,
 }
 
@@ -240,6 +245,7 @@ impl Default for HookContextBuilder {
 			is_outbound: false,
 			pre_approved: false,
 			client_address: None,
+			rtdb: None,
 			vars: HashMap::new(),
 		}
 	}
@@ -346,6 +352,12 @@ impl HookContextBuilder {
 		self
 	}
 
+	/// Set the RTDB database the `rtdb_*` operations act on
+	pub fn rtdb(mut self, db_id: impl Into<String>) -> Self {
+		self.rtdb = Some(db_id.into());
+		self
+	}
+
 	/// Set variables
 	pub fn vars(mut self, vars: HashMap<String, serde_json::Value>) -> Self {
 		self.vars = vars;
@@ -373,6 +385,7 @@ impl HookContextBuilder {
 			is_outbound: self.is_outbound,
 			pre_approved: self.pre_approved,
 			client_address: self.client_address,
+			rtdb: self.rtdb,
 			vars: self.vars,
 		}
 	}
@@ -524,6 +537,7 @@ mod tests {
 			is_outbound: true,
 			pre_approved: false,
 			client_address: None,
+			rtdb: None,
 			vars: HashMap::new(),
 		};
 
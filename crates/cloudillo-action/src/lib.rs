@@ -3,16 +3,21 @@
 
 //! Action subsystem. Actions are small signed documents representing a user action (e.g. post, comment, connection request).
 
+pub mod collections;
 pub mod delivery;
 pub mod dsl;
 pub mod fanout;
+pub mod feed;
 pub mod filter;
 pub mod forward;
 pub mod handler;
+pub mod hashtags;
 pub(crate) mod helpers;
 pub mod history_sync;
 pub mod hooks;
 pub(crate) mod key_cache;
+pub mod mentions;
+pub mod moderation;
 pub mod native_hooks;
 pub mod perm;
 pub(crate) mod post_store;
@@ -43,10 +48,18 @@ pub fn init(app: &App) -> ClResult<()> {
 	app.scheduler.register::<delivery::ActionDeliveryTask>()?;
 	app.scheduler.register::<history_sync::HistoryFetchTask>()?;
 	app.scheduler.register::<native_hooks::stat_emit_task::StatEmitTask>()?;
+	app.scheduler.register::<native_hooks::poll::PollCloseTask>()?;
+	app.scheduler.register::<dsl::trigger::TriggerCronTask>()?;
+	app.scheduler.register::<hashtags::TrendingHashtagsTask>()?;
 
 	// Register native hooks (must be called after app is fully initialized)
 	// This is done asynchronously during bootstrap
 	Ok(())
 }
 
+/// Schedule recurring action-subsystem jobs (currently the trending hashtags).
+pub async fn schedule_recurring(app: &App) -> ClResult<()> {
+	hashtags::schedule(app).await
+}
+
 // vim: ts=4
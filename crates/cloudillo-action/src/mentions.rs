@@ -0,0 +1,235 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Mentions: the per-tenant mention index, delivery to mentioned profiles and
+//! mention notifications.
+//!
+//! The `@id_tag` mentions of every POST and CMNT are extracted as the action is
+//! stored, whether created here or received (see [`cloudillo_types::mentions`]
+//! for what counts as a mention), re-extracted when an edit replaces the
+//! content, and listed on the action as `mentions`.
+//!
+//! A post or comment created here is also delivered to every profile it
+//! mentions that its visibility admits — anyone for a public action, connections
+//! (or followers) for one shared with connections (or followers), nobody outside
+//! the audience for a direct or group-scoped one. Its DEL and EDIT follow it to
+//! the same profiles. On the receiving side the mention is a reason to accept
+//! the action from a stranger and to send a push notification, unless the
+//! tenant limits mentions to their connections with [`CONNECTIONS_ONLY_SETTING`].
+
+use cloudillo_core::abac::{VisibilityLevel, relationship_level};
+use cloudillo_core::scheduler::RetryPolicy;
+use cloudillo_types::auth_adapter::ActionToken;
+use cloudillo_types::mentions::{MENTION_ACTION_TYPES, stored_content_mentions};
+use cloudillo_types::meta_adapter::{self, ProfileStatus};
+
+use crate::delivery::ActionDeliveryTask;
+use crate::native_hooks::edit::EDIT_SUBTYPE;
+use crate::prelude::*;
+
+/// Setting limiting mentions to connections: a mention from anyone else is
+/// neither a reason to accept their action nor a notification.
+pub const CONNECTIONS_ONLY_SETTING: &str = "mentions.connections_only";
+
+/// Index the mentions of a stored action's content (the JSON-encoded
+/// `actions.content`). Only POST and CMNT are indexed, and not their DEL / EDIT
+/// markers: an edit reindexes the action it edits instead. Failures are logged,
+/// never fatal — the action is stored either way.
+pub(crate) async fn index_action(
+	app: &App,
+	tn_id: TnId,
+	action_id: &str,
+	typ: &str,
+	sub_typ: Option<&str>,
+	content: Option<&str>,
+) {
+	if !MENTION_ACTION_TYPES.contains(&typ) || matches!(sub_typ, Some("DEL" | EDIT_SUBTYPE)) {
+		return;
+	}
+	let mentions = content.map(stored_content_mentions).unwrap_or_default();
+	if let Err(e) = app.meta_adapter.update_action_mentions(tn_id, action_id, &mentions).await {
+		warn!(action_id = %action_id, error = %e, "Failed to index mentions");
+	}
+}
+
+async fn connections_only(app: &App, tn_id: TnId) -> bool {
+	app.settings.get_bool(tn_id, CONNECTIONS_ONLY_SETTING).await.unwrap_or(false)
+}
+
+/// Schedule delivery of an outbound post or comment to the profiles it mentions.
+///
+/// `covered` are the recipients the regular delivery already reaches (audience,
+/// subscribers); followers reached by a broadcast share its
+/// `delivery:{action_id}:{recipient}` task key, which the scheduler dedups.
+pub(crate) async fn schedule_delivery(
+	app: &App,
+	tn_id: TnId,
+	action: &meta_adapter::Action<Box<str>>,
+	covered: &[Box<str>],
+) -> ClResult<()> {
+	if !MENTION_ACTION_TYPES.contains(&action.typ.as_ref()) {
+		return Ok(());
+	}
+	// A DEL or EDIT goes where the action it changes went.
+	let (mentions, visibility) = match action.sub_typ.as_deref() {
+		Some("DEL" | EDIT_SUBTYPE) => {
+			let Some(subject_id) = action.subject.as_deref() else {
+				return Ok(());
+			};
+			match app.meta_adapter.get_action(tn_id, subject_id).await? {
+				Some(subject) => (
+					subject.mentions.unwrap_or_default().into_iter().map(String::from).collect(),
+					subject.visibility,
+				),
+				None => return Ok(()),
+			}
+		}
+		_ => (
+			action.content.as_deref().map(stored_content_mentions).unwrap_or_default(),
+			action.visibility,
+		),
+	};
+	let visibility = VisibilityLevel::from_char(visibility);
+
+	let mut recipients: Vec<Box<str>> = Vec::new();
+	for id_tag in mentions {
+		if id_tag == action.issuer_tag.as_ref()
+			|| action.audience_tag.as_deref() == Some(id_tag.as_str())
+			|| covered.iter().any(|c| c.as_ref() == id_tag)
+		{
+			continue;
+		}
+		// A stranger is an authenticated reader with no relationship to us. Moderated
+		// profiles get nothing, as with every other recipient list.
+		let level = match app.meta_adapter.read_profile(tn_id, &id_tag).await {
+			Ok((_, p)) => {
+				if matches!(
+					p.status,
+					Some(ProfileStatus::Suspended | ProfileStatus::Blocked | ProfileStatus::Banned)
+				) {
+					continue;
+				}
+				relationship_level(false, p.connected.is_connected(), p.follower, true)
+			}
+			Err(Error::NotFound) => relationship_level(false, false, false, true),
+			Err(e) => return Err(e),
+		};
+		if level.can_access(visibility) {
+			recipients.push(id_tag.into());
+		}
+	}
+	if recipients.is_empty() {
+		return Ok(());
+	}
+	info!("→ MENTIONS: {} → [{}]", action.action_id, recipients.join(", "));
+
+	let retry_policy = RetryPolicy::new((10, 43200), 50);
+	for recipient_tag in recipients {
+		let delivery_task = ActionDeliveryTask::new_with_related(
+			tn_id,
+			action.action_id.clone(),
+			recipient_tag.clone(),
+			recipient_tag.clone(),
+			None,
+		);
+		let task_key = format!("delivery:{}:{}", action.action_id, recipient_tag);
+		app.scheduler
+			.task(delivery_task)
+			.key(&task_key)
+			.with_retry(retry_policy.clone())
+			.schedule()
+			.await?;
+	}
+	Ok(())
+}
+
+/// Whether an inbound action from an issuer we neither follow nor are connected
+/// to is admitted because it mentions the tenant: a POST or CMNT naming us, or
+/// its issuer's DEL / EDIT of one we hold.
+pub(crate) async fn admits_inbound(app: &App, tn_id: TnId, action: &ActionToken) -> ClResult<bool> {
+	let (base_type, sub_type) = crate::helpers::extract_type_and_subtype(&action.t);
+	if !MENTION_ACTION_TYPES.contains(&base_type.as_str()) || connections_only(app, tn_id).await {
+		return Ok(false);
+	}
+	let tenant = app.meta_adapter.read_tenant(tn_id).await?;
+	let mentions = match sub_type.as_deref() {
+		None => action.c.as_ref().map(cloudillo_types::mentions::content_mentions),
+		Some("DEL" | EDIT_SUBTYPE) => match action.sub.as_deref() {
+			Some(subject_id) => app
+				.meta_adapter
+				.get_action(tn_id, subject_id)
+				.await?
+				.filter(|s| s.issuer.id_tag == action.iss)
+				.and_then(|s| s.mentions)
+				.map(|m| m.into_iter().map(String::from).collect()),
+			None => None,
+		},
+		Some(_) => None,
+	};
+	Ok(mentions.is_some_and(|m| m.iter().any(|id_tag| id_tag.as_str() == tenant.id_tag.as_ref())))
+}
+
+/// Send a push notification when a received post or comment mentions the tenant,
+/// whether or not the tenant is online — a profile co-hosted on this node receives
+/// it the same way. With [`CONNECTIONS_ONLY_SETTING`] on, only a connection's
+/// mention notifies.
+pub(crate) async fn notify(app: &App, tn_id: TnId, action: &meta_adapter::Action<Box<str>>) {
+	use cloudillo_push::{NotificationPayload, send_to_tenant};
+
+	if action.sub_typ.is_some() || !MENTION_ACTION_TYPES.contains(&action.typ.as_ref()) {
+		return;
+	}
+	let Some(content) = action.content.as_deref() else {
+		return;
+	};
+	let Ok(tenant) = app.meta_adapter.read_tenant(tn_id).await else {
+		return;
+	};
+	let mentioned = stored_content_mentions(content)
+		.iter()
+		.any(|m| m.as_str() == tenant.id_tag.as_ref());
+	if !mentioned || action.issuer_tag == tenant.id_tag {
+		return;
+	}
+	if connections_only(app, tn_id).await {
+		let connected = matches!(
+			app.meta_adapter.read_profile(tn_id, &action.issuer_tag).await,
+			Ok((_, p)) if p.connected.is_connected()
+		);
+		if !connected {
+			return;
+		}
+	}
+	if !app.settings.get_bool(tn_id, "notify.push").await.unwrap_or(true)
+		|| !app.settings.get_bool(tn_id, "notify.push.mention").await.unwrap_or(true)
+	{
+		return;
+	}
+
+	// The comment's own id is not a permalink; the post it hangs off is.
+	let post = if action.typ.as_ref() == "CMNT" {
+		action.parent_id.as_deref().unwrap_or(&action.action_id)
+	} else {
+		&action.action_id
+	};
+	let payload = NotificationPayload {
+		title: format!("{} mentioned you", action.issuer_tag),
+		body: serde_json::from_str::<serde_json::Value>(content)
+			.ok()
+			.and_then(|c| c.as_str().map(|s| s.chars().take(100).collect::<String>()))
+			.unwrap_or_default(),
+		path: Some(format!("/~/app/feed/{post}")),
+		image: None,
+		tag: Some("MENTION".to_string()),
+	};
+	match send_to_tenant(app, tn_id, &payload).await {
+		Ok(count) => {
+			info!(action_id = %action.action_id, sent_count = %count, "Mention notification sent");
+		}
+		Err(e) => {
+			warn!(action_id = %action.action_id, error = %e, "Failed to send mention notification");
+		}
+	}
+}
+
+// vim: ts=4
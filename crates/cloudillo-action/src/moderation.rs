@@ -0,0 +1,352 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Community moderation API: the queue of open reports, decisions on them, and the
+//! audit trail.
+//!
+//! Members flag content with a REPORT action addressed to the community hosting it
+//! (see [`crate::native_hooks::report`]). The community's moderators and leaders
+//! work through the open reports here and decide on each reported action:
+//!
+//! - `dismiss` — nothing to do;
+//! - `hide`, `restore`, `remove` — the community signs a `MOD:HIDE`, `MOD:RESTORE` or
+//!   `MOD:REMOVE` and broadcasts it, so every follower's copy changes with ours;
+//! - `warn` — a `MOD:WARN` with the moderator's note, addressed to the author alone;
+//! - `mute`, `ban` — the author's profile on the community's node turns Muted (may
+//!   read, but no longer post, comment, react or report here) or Banned (refused
+//!   outright and left out of every fan-out).
+//!
+//! A moderator may only act against authors ranked below them (leaders also against
+//! peer leaders), as with member removal. Every decision closes the open reports on
+//! the action and is appended to the audit trail
+//! ([`cloudillo_types::meta_adapter::ModerationEntry`]).
+
+use axum::{
+	Json,
+	extract::{Query, State},
+	http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use cloudillo_core::{
+	extract::{Auth, OptionalRequestId},
+	roles,
+};
+use cloudillo_types::auth_adapter::AuthCtx;
+use cloudillo_types::meta_adapter::{
+	self, ActionView, CreateModerationEntry, ListActionOptions, ModerationEntry, ProfileStatus,
+	ProfileType, UpdateActionDataOptions, UpdateProfileData, UpsertProfileFields,
+};
+use cloudillo_types::types::ApiResponse;
+
+use crate::native_hooks::report::{MAX_NOTE_LENGTH, moderating_community};
+use crate::prelude::*;
+use crate::task::{self, CreateAction};
+
+/// Most open reports one queue listing reads.
+const QUEUE_LIMIT: u32 = 500;
+
+/// What a moderator decided on a reported action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+	Dismiss,
+	Hide,
+	Restore,
+	Remove,
+	Warn,
+	Mute,
+	Ban,
+}
+
+impl Decision {
+	/// The name recorded in the audit trail.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Decision::Dismiss => "dismiss",
+			Decision::Hide => "hide",
+			Decision::Restore => "restore",
+			Decision::Remove => "remove",
+			Decision::Warn => "warn",
+			Decision::Mute => "mute",
+			Decision::Ban => "ban",
+		}
+	}
+
+	/// The MOD subtype that federates the decision, if it has one.
+	fn mod_subtype(self) -> Option<&'static str> {
+		match self {
+			Decision::Hide => Some("HIDE"),
+			Decision::Restore => Some("RESTORE"),
+			Decision::Remove => Some("REMOVE"),
+			Decision::Warn => Some("WARN"),
+			Decision::Dismiss | Decision::Mute | Decision::Ban => None,
+		}
+	}
+
+	/// The profile status the decision gives the author, if any.
+	fn profile_status(self) -> Option<ProfileStatus> {
+		match self {
+			Decision::Mute => Some(ProfileStatus::Muted),
+			Decision::Ban => Some(ProfileStatus::Banned),
+			_ => None,
+		}
+	}
+}
+
+/// One reported action in the queue, with its open reports, oldest first.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationQueueItem {
+	pub action_id: Box<str>,
+	/// The reported action; `None` once it is gone from this node.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub action: Option<ActionView>,
+	pub reports: Vec<ActionView>,
+}
+
+/// Body of `POST /api/moderation/decisions`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DecisionRequest {
+	/// The reported action.
+	pub action_id: String,
+	pub decision: Decision,
+	/// Recorded in the audit trail; for `warn` also the message the author receives.
+	pub note: Option<String>,
+}
+
+/// The community's id_tag, if the caller moderates it.
+async fn require_moderator(app: &App, auth: &AuthCtx) -> ClResult<Box<str>> {
+	let tenant = app.meta_adapter.read_tenant(auth.tn_id).await?;
+	if tenant.typ != ProfileType::Community
+		|| roles::highest_role_level(&auth.roles) < roles::MODERATOR_LEVEL
+	{
+		warn!(
+			"Rejecting moderation request by {}: not a moderator of {}",
+			auth.id_tag, tenant.id_tag
+		);
+		return Err(Error::PermissionDenied);
+	}
+	Ok(tenant.id_tag)
+}
+
+/// Open reports addressed to `community`, oldest first, optionally on one action.
+async fn open_reports(
+	app: &App,
+	tn_id: TnId,
+	community: &str,
+	action_id: Option<&str>,
+) -> ClResult<Vec<ActionView>> {
+	let opts = ListActionOptions {
+		typ: Some(vec!["REPORT".into()]),
+		status: Some(vec![crate::status::ACTIVE.to_string()]),
+		audience: Some(community.to_string()),
+		subject: action_id.map(|id| vec![id.to_string()]),
+		sort_dir: Some("asc".into()),
+		limit: Some(QUEUE_LIMIT),
+		..Default::default()
+	};
+	app.meta_adapter.list_actions(tn_id, &opts).await
+}
+
+/// GET /api/moderation/reports - The queue: reported actions with their open reports,
+/// longest-waiting first
+pub async fn list_moderation_queue(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<ModerationQueueItem>>>)> {
+	let community = require_moderator(&app, &auth).await?;
+	let tn_id = auth.tn_id;
+
+	let mut items: Vec<ModerationQueueItem> = Vec::new();
+	let mut index: HashMap<Box<str>, usize> = HashMap::new();
+	for report in open_reports(&app, tn_id, &community, None).await? {
+		let Some(action_id) = report.subject.clone() else {
+			continue;
+		};
+		let i = *index.entry(action_id.clone()).or_insert_with(|| {
+			items.push(ModerationQueueItem { action_id, action: None, reports: Vec::new() });
+			items.len() - 1
+		});
+		items[i].reports.push(report);
+	}
+	for item in &mut items {
+		item.action = app.meta_adapter.get_action(tn_id, &item.action_id).await?;
+	}
+
+	let response = ApiResponse::new(items).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// POST /api/moderation/decisions - Decide on a reported action
+pub async fn post_moderation_decision(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<DecisionRequest>,
+) -> ClResult<(StatusCode, Json<ApiResponse<ModerationEntry>>)> {
+	let community = require_moderator(&app, &auth).await?;
+	let tn_id = auth.tn_id;
+	let decision = req.decision;
+	let note = req.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+	if note.is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+		return Err(Error::ValidationError("Note is too long".into()));
+	}
+
+	let action = app
+		.meta_adapter
+		.get_action(tn_id, &req.action_id)
+		.await?
+		.ok_or(Error::NotFound)?;
+	if moderating_community(&app, tn_id, &action).await?.as_deref() != Some(&*community) {
+		return Err(Error::ValidationError(format!(
+			"{} is not hosted by this community",
+			req.action_id
+		)));
+	}
+	let author = action.issuer.id_tag.clone();
+
+	// Same rank rule as member removal: only against authors strictly below the
+	// moderator (leaders also against peer leaders). Dismissing touches no one.
+	if decision != Decision::Dismiss {
+		if author == community {
+			return Err(Error::ValidationError(
+				"Cannot moderate the community's own content".into(),
+			));
+		}
+		let author_roles = match app.meta_adapter.read_profile_roles(tn_id, &author).await {
+			Ok(roles) => roles,
+			Err(Error::NotFound) => None,
+			Err(e) => return Err(e),
+		}
+		.unwrap_or_default();
+		if !roles::can_manage_member_by_roles(&auth.roles, &author_roles) {
+			warn!(
+				"Rejecting moderation decision {} by {} against {}: insufficient role (actor_level={}, target_level={})",
+				decision.as_str(),
+				auth.id_tag,
+				author,
+				roles::highest_role_level(&auth.roles),
+				roles::highest_role_level(&author_roles)
+			);
+			return Err(Error::PermissionDenied);
+		}
+	}
+	if decision == Decision::Restore && action.status.as_deref() != Some("H") {
+		return Err(Error::ValidationError(format!("{} is not hidden", req.action_id)));
+	}
+
+	if let Some(sub_typ) = decision.mod_subtype() {
+		let create = if decision == Decision::Warn {
+			CreateAction {
+				typ: "MOD".into(),
+				sub_typ: Some(sub_typ.into()),
+				subject: Some(req.action_id.as_str().into()),
+				audience_tag: Some(author.clone()),
+				content: note.map(|note| serde_json::json!({ "note": note })),
+				visibility: Some('D'),
+				..Default::default()
+			}
+		} else {
+			// The note stays in the audit trail; followers only learn the decision.
+			CreateAction {
+				typ: "MOD".into(),
+				sub_typ: Some(sub_typ.into()),
+				subject: Some(req.action_id.as_str().into()),
+				..Default::default()
+			}
+		};
+		task::create_action(&app, tn_id, &community, create).await?;
+	}
+	if let Some(status) = decision.profile_status() {
+		let upsert = UpsertProfileFields::from_update(UpdateProfileData {
+			status: Patch::Value(status),
+			..Default::default()
+		});
+		app.meta_adapter.upsert_profile(tn_id, &author, &upsert).await?;
+		if upsert.affects_search_index() {
+			cloudillo_core::search_index_profile(&app, tn_id, &author);
+		}
+	}
+
+	// Close the open reports the decision answers.
+	let mut reports: Vec<Box<str>> = Vec::new();
+	let closed = UpdateActionDataOptions {
+		status: Patch::Value(crate::status::NOTIFICATION),
+		..Default::default()
+	};
+	for report in open_reports(&app, tn_id, &community, Some(&req.action_id)).await? {
+		app.meta_adapter.update_action_data(tn_id, &report.action_id, &closed).await?;
+		reports.push(report.action_id);
+	}
+
+	let entry = CreateModerationEntry {
+		decision: decision.as_str(),
+		subject: Some(&req.action_id),
+		target_id_tag: &author,
+		moderator_id_tag: &auth.id_tag,
+		reports: &reports,
+		note,
+	};
+	let entry_id = app.meta_adapter.create_moderation_entry(tn_id, &entry).await?;
+	info!(
+		"Moderator {} decided {} on {} by {} ({} reports closed)",
+		auth.id_tag,
+		decision.as_str(),
+		req.action_id,
+		author,
+		reports.len()
+	);
+
+	let entry = ModerationEntry {
+		entry_id,
+		decision: decision.as_str().into(),
+		subject: Some(req.action_id.into()),
+		target_id_tag: author,
+		moderator_id_tag: auth.id_tag.clone(),
+		reports,
+		note: note.map(Into::into),
+		created_at: Timestamp::now(),
+	};
+	let response = ApiResponse::new(entry).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// GET /api/moderation/log - The audit trail, newest first
+pub async fn list_moderation_log(
+	State(app): State<App>,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Query(opts): Query<meta_adapter::ListModerationLogOptions>,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<ModerationEntry>>>)> {
+	require_moderator(&app, &auth).await?;
+	let entries = app.meta_adapter.list_moderation_log(auth.tn_id, &opts).await?;
+
+	let response = ApiResponse::new(entries).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decisions_parse_lowercase_and_map_to_their_effects() {
+		let decision: Decision = serde_json::from_str(r#""remove""#).expect("parse");
+		assert_eq!(decision, Decision::Remove);
+		assert!(serde_json::from_str::<Decision>(r#""delete""#).is_err());
+
+		assert_eq!(Decision::Hide.mod_subtype(), Some("HIDE"));
+		assert_eq!(Decision::Warn.mod_subtype(), Some("WARN"));
+		assert_eq!(Decision::Ban.mod_subtype(), None);
+		assert_eq!(Decision::Mute.profile_status(), Some(ProfileStatus::Muted));
+		assert_eq!(Decision::Ban.profile_status(), Some(ProfileStatus::Banned));
+		assert_eq!(Decision::Dismiss.profile_status(), None);
+		assert_eq!(Decision::Dismiss.as_str(), "dismiss");
+	}
+}
+
+// vim: ts=4
@@ -7,6 +7,8 @@
 //! - on_create: Recomputes the parent action's comment stats for local comments
 //! - on_receive: Recomputes the parent action's comment stats for incoming comments
 //!
+//! CMNT:EDIT leaves the counters alone and is handed to `native_hooks::edit`.
+//!
 //! `actions.comments` holds the total comment count (federated as STAT `c`) and
 //! `actions.comments_ts` the last-comment timestamp (epoch seconds = the newest
 //! active child comment's created_at, federated as STAT `ct`). Both are
@@ -24,6 +26,7 @@
 //! real time instead of waiting for the next outbox poll.
 
 use crate::hooks::{HookContext, HookResult};
+use crate::native_hooks::edit;
 use crate::native_hooks::ownership::owns_subject;
 use crate::native_hooks::stat_emit::emit_stat_for_subject;
 use crate::prelude::*;
@@ -38,6 +41,9 @@ use cloudillo_types::meta_adapter::{
 /// subtypes recompute, so the count and unread dot stay correct without drift.
 pub async fn on_create(app: App, context: HookContext) -> ClResult<HookResult> {
 	tracing::debug!("Native hook: CMNT on_create for action {}", context.action_id);
+	if edit::is_edit(&context) {
+		return edit::on_create(app, context).await;
+	}
 
 	let tn_id = context.tn_id;
 	let Some(parent_id) = &context.parent else {
@@ -116,6 +122,9 @@ pub async fn on_create(app: App, context: HookContext) -> ClResult<HookResult> {
 /// recompute, so the count and unread dot stay correct without drift.
 pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
 	tracing::debug!("Native hook: CMNT on_receive for action {}", context.action_id);
+	if edit::is_edit(&context) {
+		return edit::on_receive(app, context).await;
+	}
 
 	let tn_id = context.tn_id;
 	let Some(parent_id) = &context.parent else {
@@ -181,7 +190,7 @@ pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult>
 }
 
 /// Recompute `(comment_count, last_comment_ts)` for `parent_id` from its live
-/// children of `child_types`: grouped count excluding DEL markers, plus the
+/// children of `child_types`: grouped count excluding DEL/EDIT markers, plus the
 /// newest active child's `created_at` (drives the unread dot). `(0, 0)` when
 /// empty. `child_types` selects the children — `&["CMNT"]` for a post's comment
 /// thread, `&["MSG"]` for a group CONV (structurally a post with comments, so it
@@ -194,7 +203,7 @@ pub(crate) async fn recompute_comment_stats(
 ) -> ClResult<(u32, i64)> {
 	let typ: Vec<String> = child_types.iter().map(|t| (*t).to_string()).collect();
 
-	// Count active children (exclude DEL markers), like count_reposts.
+	// Count active children (exclude DEL and EDIT markers), like count_reposts.
 	let count_opts = ListActionOptions {
 		typ: Some(typ.clone()),
 		parent_id: Some(parent_id.to_string()),
@@ -206,7 +215,7 @@ pub(crate) async fn recompute_comment_stats(
 		.await?;
 	let total: i64 = grouped
 		.into_iter()
-		.filter(|(sub_type, _)| sub_type.as_deref() != Some("DEL"))
+		.filter(|(sub_type, _)| !matches!(sub_type.as_deref(), Some("DEL" | edit::EDIT_SUBTYPE)))
 		.map(|(_, cnt)| cnt)
 		.sum();
 	let count = u32::try_from(total).unwrap_or(u32::MAX);
@@ -218,7 +227,10 @@ pub(crate) async fn recompute_comment_stats(
 		sort: Some("created".into()),
 		sort_dir: Some("desc".into()),
 		limit: Some(1),
-		exclude_sub_typ: Some(Box::from([Box::from("DEL") as Box<str>])),
+		exclude_sub_typ: Some(Box::from([
+			Box::from("DEL") as Box<str>,
+			Box::from(edit::EDIT_SUBTYPE),
+		])),
 		..Default::default()
 	};
 	let newest = app.meta_adapter.list_actions(tn_id, &newest_opts).await?;
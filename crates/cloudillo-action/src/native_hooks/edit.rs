@@ -0,0 +1,209 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! EDIT subtype native hooks (POST:EDIT, CMNT:EDIT, MSG:EDIT)
+//!
+//! An edit is an ordinary signed action whose `subject` names the action it edits
+//! and whose `content` is the new content. It federates along the same paths as the
+//! type it edits, and every node holding the edited action applies it in place: the
+//! content is replaced, while the action id — and with it reactions, comments,
+//! reposts and the thread below — stays. The earlier contents are kept as
+//! revisions (`MetaAdapter::apply_action_edit`).
+//!
+//! Only the author may edit: the edit's issuer must be the edited action's issuer,
+//! and the types must match, so a CMNT:EDIT cannot rewrite a POST. Edits that fail
+//! these checks, or whose subject is not cached here, are stored like any action but
+//! change nothing.
+//!
+//! The EDIT row itself is a marker, like DEL: it is excluded from listings, comment
+//! counts, notifications, the search index and the hashtag and mention indexes, and
+//! the edited action is reindexed instead.
+
+use crate::hooks::{HookContext, HookResult};
+use crate::prelude::*;
+use cloudillo_types::meta_adapter::ActionView;
+
+/// Subtype of an edit, shared by every editable type.
+pub const EDIT_SUBTYPE: &str = "EDIT";
+
+/// Whether `context` is an edit.
+pub(crate) fn is_edit(context: &HookContext) -> bool {
+	context.subtype.as_deref() == Some(EDIT_SUBTYPE)
+}
+
+/// Whether an action issued by `issuer` as `typ` may edit `subject`: same author,
+/// same type, and `subject` is itself content rather than a DEL or EDIT marker.
+pub(crate) fn may_edit(subject: &ActionView, typ: &str, issuer: &str) -> bool {
+	subject.typ.as_ref() == typ
+		&& subject.issuer.id_tag.as_ref() == issuer
+		&& !matches!(subject.sub_typ.as_deref(), Some("DEL" | EDIT_SUBTYPE))
+}
+
+/// EDIT on_create hook — apply a local edit to our own action.
+pub async fn on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!(
+		"Native hook: {}:EDIT on_create for action {}",
+		context.r#type,
+		context.action_id
+	);
+	apply(&app, &context, "on_create").await
+}
+
+/// EDIT on_receive hook — apply a federated edit to the cached action.
+pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!(
+		"Native hook: {}:EDIT on_receive for action {}",
+		context.r#type,
+		context.action_id
+	);
+	apply(&app, &context, "on_receive").await
+}
+
+async fn apply(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	if !is_edit(context) {
+		return Ok(HookResult::default());
+	}
+
+	let tn_id = context.tn_id;
+	let Some(subject_id) = &context.subject else {
+		tracing::warn!("{}:EDIT {}: no subject specified", context.r#type, phase);
+		return Ok(HookResult::default());
+	};
+
+	let Some(subject) = app.meta_adapter.get_action(tn_id, subject_id).await? else {
+		tracing::debug!(
+			"{}:EDIT {}: subject {} not found locally",
+			context.r#type,
+			phase,
+			subject_id
+		);
+		return Ok(HookResult::default());
+	};
+
+	if !may_edit(&subject, &context.r#type, &context.issuer) {
+		tracing::warn!(
+			"{}:EDIT {}: {} may not edit {} {} by {} — ignoring",
+			context.r#type,
+			phase,
+			context.issuer,
+			subject.typ,
+			subject_id,
+			subject.issuer.id_tag
+		);
+		return Ok(HookResult::default());
+	}
+
+	let Ok(edited_at) = context.created_at.parse::<i64>() else {
+		tracing::warn!(
+			"{}:EDIT {}: unparseable created_at {:?}",
+			context.r#type,
+			phase,
+			context.created_at
+		);
+		return Ok(HookResult::default());
+	};
+
+	let content = crate::helpers::serialize_content(context.content.as_ref());
+	let changed = app
+		.meta_adapter
+		.apply_action_edit(
+			tn_id,
+			subject_id,
+			&context.action_id,
+			content.as_deref(),
+			Timestamp(edited_at),
+		)
+		.await?;
+
+	tracing::info!(
+		"{}:EDIT {}: {} edited {} (current={})",
+		context.r#type,
+		phase,
+		context.issuer,
+		subject_id,
+		changed
+	);
+	if changed {
+		cloudillo_core::search_index_action(app, tn_id, subject_id);
+		crate::hashtags::index_action(
+			app,
+			tn_id,
+			subject_id,
+			&subject.typ,
+			subject.sub_typ.as_deref(),
+			content.as_deref(),
+		)
+		.await;
+		crate::mentions::index_action(
+			app,
+			tn_id,
+			subject_id,
+			&subject.typ,
+			subject.sub_typ.as_deref(),
+			content.as_deref(),
+		)
+		.await;
+	}
+
+	Ok(HookResult::default())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cloudillo_types::meta_adapter::{ProfileInfo, ProfileType};
+
+	fn view(typ: &str, sub_typ: Option<&str>, issuer: &str) -> ActionView {
+		ActionView {
+			action_id: "a1~x".into(),
+			typ: typ.into(),
+			sub_typ: sub_typ.map(Into::into),
+			parent_id: None,
+			root_id: None,
+			issuer: ProfileInfo {
+				id_tag: issuer.into(),
+				name: issuer.into(),
+				typ: ProfileType::Person,
+				profile_pic: None,
+			},
+			audience: None,
+			content: None,
+			attachments: None,
+			subject: None,
+			subject_profile: None,
+			subject_action: None,
+			mentions: None,
+			created_at: Timestamp(0),
+			received_at: None,
+			edited_at: None,
+			expires_at: None,
+			status: Some("A".into()),
+			stat: None,
+			visibility: None,
+			flags: None,
+			sub_level: None,
+			x: None,
+			filtered: None,
+			token: None,
+		}
+	}
+
+	#[test]
+	fn only_the_author_edits_content_of_the_same_type() {
+		let post = view("POST", Some("TEXT"), "alice.example");
+		assert!(may_edit(&post, "POST", "alice.example"));
+		assert!(!may_edit(&post, "POST", "bob.example"));
+		assert!(!may_edit(&post, "CMNT", "alice.example"));
+
+		let comment = view("CMNT", None, "alice.example");
+		assert!(may_edit(&comment, "CMNT", "alice.example"));
+	}
+
+	#[test]
+	fn markers_are_not_editable() {
+		assert!(!may_edit(&view("POST", Some("DEL"), "alice.example"), "POST", "alice.example"));
+		assert!(!may_edit(&view("POST", Some("EDIT"), "alice.example"), "POST", "alice.example"));
+	}
+}
+
+// vim: ts=4
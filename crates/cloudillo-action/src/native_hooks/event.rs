@@ -0,0 +1,501 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! EVENT and RSVP action native hooks
+//!
+//! An EVENT is a broadcast, discoverable happening: `{title, description?, location?,
+//! startsAt, endsAt?, allDay?, capacity?}` with epoch-second times. Calendars stay
+//! private PIM data; an EVENT is the social side that people on other instances can
+//! find and answer. An RSVP names the event as its `subject` (like REACT, never
+//! `parent`) and answers with its subtype — `RSVP:GOING`, `RSVP:MAYBE` or
+//! `RSVP:DECLINED`. RSVP is keyed `{type}:{subject}:{issuer}`, so a new answer
+//! replaces the previous one and RSVP:DEL withdraws it.
+//!
+//! Only the event's authoritative node (see [`crate::native_hooks::ownership`]) tallies:
+//! on every RSVP it recounts the live answers, stores the [`RsvpTally`] in
+//! `actions.rsvps` and emits a STAT carrying it as `rv`, which mirrors apply like the
+//! reaction counters (see `stat.rs`). It rejects answers to an event that has ended,
+//! and a GOING beyond `capacity`.
+//!
+//! The attendee list is the owner's RSVP rows, listed with `type=RSVP&subject=…`. Each
+//! RSVP takes its event's visibility on every node, whatever the attendee's node
+//! signed, so the list is exactly as visible as the event itself.
+//!
+//! An RSVP created with `x.calendar` set to one of the user's calendar ids also files
+//! the event in that calendar through `cloudillo-calendar`: GOING and MAYBE write it
+//! (as an accepted or tentative attendee), DECLINED and DEL remove it. Guests without
+//! a Cloudillo account get the same event as an `.ics` file from
+//! `GET /api/actions/{id}/ics` (see [`to_calendar_input`]).
+
+use serde::{Deserialize, Serialize};
+
+use cloudillo_calendar::types::{Attendee, CalendarObjectInput, EventInput};
+use cloudillo_types::action_types::CreateAction;
+use cloudillo_types::meta_adapter::{
+	ActionCountGroupBy, ActionView, ListActionOptions, UpdateActionDataOptions,
+};
+
+use crate::hooks::{HookContext, HookResult};
+use crate::native_hooks::ownership::owns_subject;
+use crate::native_hooks::stat_emit::emit_stat_for_subject;
+use crate::prelude::*;
+
+/// The RSVP answers, as subtypes.
+pub const RSVP_ANSWERS: [&str; 3] = ["GOING", "MAYBE", "DECLINED"];
+
+/// EVENT content.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventContent {
+	pub title: String,
+	pub description: Option<String>,
+	pub location: Option<String>,
+	/// Epoch seconds.
+	pub starts_at: i64,
+	/// Epoch seconds. `None` ends the event when it starts.
+	pub ends_at: Option<i64>,
+	/// Whole days: only the dates of `startsAt` / `endsAt` count.
+	#[serde(default)]
+	pub all_day: bool,
+	/// Most GOING answers the event accepts. `None` is unlimited.
+	pub capacity: Option<u32>,
+}
+
+impl EventContent {
+	/// Parse and check an EVENT's content.
+	pub fn parse(content: Option<&serde_json::Value>) -> ClResult<Self> {
+		let content =
+			content.ok_or_else(|| Error::ValidationError("EVENT requires content".into()))?;
+		let event: EventContent = serde_json::from_value(content.clone())
+			.map_err(|e| Error::ValidationError(format!("Invalid EVENT content: {}", e)))?;
+		if event.title.trim().is_empty() {
+			return Err(Error::ValidationError("EVENT requires a title".into()));
+		}
+		if event.ends_at.is_some_and(|ends_at| ends_at < event.starts_at) {
+			return Err(Error::ValidationError("EVENT endsAt is before startsAt".into()));
+		}
+		if event.capacity == Some(0) {
+			return Err(Error::ValidationError("EVENT capacity must be positive".into()));
+		}
+		Ok(event)
+	}
+
+	/// Whether the event is over at `now`, so answers no longer count.
+	pub fn has_ended_at(&self, now: Timestamp) -> bool {
+		now.0 >= self.ends_at.unwrap_or(self.starts_at)
+	}
+
+	/// Whether `tally` leaves no room for another GOING.
+	pub fn is_full(&self, tally: &RsvpTally) -> bool {
+		self.capacity.is_some_and(|capacity| tally.going >= capacity)
+	}
+}
+
+/// An event's answers, stored in `actions.rsvps` and federated as STAT `rv`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RsvpTally {
+	pub going: u32,
+	pub maybe: u32,
+	pub declined: u32,
+}
+
+impl RsvpTally {
+	/// Count `n` answers of `answer`; anything but an RSVP answer counts for nothing.
+	fn add(&mut self, answer: &str, n: u32) {
+		let count = match answer {
+			"GOING" => &mut self.going,
+			"MAYBE" => &mut self.maybe,
+			"DECLINED" => &mut self.declined,
+			_ => return,
+		};
+		*count = count.saturating_add(n);
+	}
+}
+
+/// Recount the live answers to `event_id`.
+pub(crate) async fn count_rsvps(app: &App, tn_id: TnId, event_id: &str) -> ClResult<RsvpTally> {
+	let opts = ListActionOptions {
+		typ: Some(vec!["RSVP".into()]),
+		subject: Some(vec![event_id.to_string()]),
+		exclude_sub_typ: Some(Box::from([Box::from("DEL")])),
+		..Default::default() // status unset → default "active" filter
+	};
+	let grouped = app
+		.meta_adapter
+		.count_actions_grouped(tn_id, &opts, ActionCountGroupBy::SubType)
+		.await?;
+	let mut tally = RsvpTally::default();
+	for (answer, cnt) in grouped {
+		if let Some(answer) = answer {
+			tally.add(&answer, u32::try_from(cnt).unwrap_or(0));
+		}
+	}
+	Ok(tally)
+}
+
+async fn read_tally(app: &App, tn_id: TnId, event_id: &str) -> ClResult<Option<RsvpTally>> {
+	let data = app.meta_adapter.get_action_data(tn_id, event_id).await?;
+	Ok(data.and_then(|d| d.rsvps).and_then(|v| serde_json::from_str(&v).ok()))
+}
+
+async fn store_tally(app: &App, tn_id: TnId, event_id: &str, tally: &RsvpTally) -> ClResult<()> {
+	let update_opts = UpdateActionDataOptions {
+		rsvps: Patch::Value(serde_json::to_string(tally)?),
+		..Default::default()
+	};
+	app.meta_adapter.update_action_data(tn_id, event_id, &update_opts).await
+}
+
+/// The node an RSVP must reach: the event's audience if community-hosted, else its
+/// issuer — the same node [`owns_subject`] names.
+fn event_owner(event_action: &ActionView) -> &str {
+	event_action
+		.audience
+		.as_ref()
+		.map_or(&event_action.issuer.id_tag, |a| &a.id_tag)
+}
+
+/// The event as a calendar object, UID'd by its action id. `attendee` adds the user
+/// with the PARTSTAT their RSVP answer maps to.
+pub fn to_calendar_input(
+	event_action: &ActionView,
+	event: &EventContent,
+	attendee: Option<(&str, &str)>,
+) -> CalendarObjectInput {
+	let organizer = event_owner(event_action);
+	let attendees = attendee
+		.map(|(id_tag, answer)| Attendee {
+			address: format!("https://{}/", id_tag),
+			cn: Some(id_tag.to_string()),
+			partstat: Some(if answer == "GOING" { "ACCEPTED" } else { "TENTATIVE" }.to_string()),
+			..Default::default()
+		})
+		.into_iter()
+		.collect();
+	CalendarObjectInput {
+		uid: Some(event_action.action_id.to_string()),
+		recurrence_id: None,
+		event: Some(EventInput {
+			summary: Some(event.title.clone()),
+			description: event.description.clone(),
+			location: event.location.clone(),
+			dtstart: Some(cloudillo_calendar::ical::ts_to_iso(
+				Timestamp(event.starts_at),
+				event.all_day,
+			)),
+			dtend: event.ends_at.map(|ends_at| {
+				cloudillo_calendar::ical::ts_to_iso(Timestamp(ends_at), event.all_day)
+			}),
+			all_day: event.all_day,
+			status: Some("CONFIRMED".into()),
+			organizer: Some(format!("https://{}/", organizer)),
+			attendees,
+			..Default::default()
+		}),
+		todo: None,
+	}
+}
+
+/// Create-path checks for EVENT and RSVP, run before signing. The owner checks the
+/// same and rejects an answer that fails, so rejecting it here is what tells the user.
+///
+/// An RSVP without an audience is addressed to the event's owner, and takes the
+/// event's visibility.
+pub(crate) async fn validate_create(
+	app: &App,
+	tn_id: TnId,
+	base_type: &str,
+	sub_typ: Option<&str>,
+	action: &mut CreateAction,
+) -> ClResult<()> {
+	match base_type {
+		"EVENT" if sub_typ.is_none() => {
+			let event = EventContent::parse(action.content.as_ref())?;
+			if event.has_ended_at(Timestamp::now()) {
+				return Err(Error::ValidationError("EVENT must not be over".into()));
+			}
+		}
+		"RSVP" => {
+			let answer = sub_typ.ok_or_else(|| {
+				Error::ValidationError("RSVP requires an answer: GOING, MAYBE or DECLINED".into())
+			})?;
+			let event_id = action
+				.subject
+				.as_deref()
+				.ok_or_else(|| Error::ValidationError("RSVP requires a subject".into()))?;
+			let event_action =
+				app.meta_adapter.get_action(tn_id, event_id).await?.ok_or(Error::NotFound)?;
+			if event_action.typ.as_ref() != "EVENT" {
+				return Err(Error::ValidationError("RSVP subject must be an EVENT".into()));
+			}
+			let event = EventContent::parse(event_action.content.as_ref())?;
+			if answer != "DEL" {
+				if event.has_ended_at(Timestamp::now()) {
+					return Err(Error::ValidationError("Event is over".into()));
+				}
+				// The mirrored tally is what this node knows; the owner has the last word.
+				if answer == "GOING"
+					&& read_tally(app, tn_id, event_id)
+						.await?
+						.is_some_and(|tally| event.is_full(&tally))
+				{
+					return Err(Error::ValidationError("Event is full".into()));
+				}
+			}
+			if action.audience_tag.is_none() {
+				action.audience_tag = Some(event_owner(&event_action).into());
+			}
+			action.visibility = event_action.visibility;
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+/// EVENT on_create / on_receive — open the tally on the event's owner.
+async fn open(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	if context.subtype.is_some() {
+		return Ok(HookResult::default());
+	}
+	let tn_id = context.tn_id;
+	let event_id = context.action_id.as_str();
+	let Some(event_action) = app.meta_adapter.get_action(tn_id, event_id).await? else {
+		return Ok(HookResult::default());
+	};
+	if !owns_subject(&event_action, &context.tenant_tag) {
+		tracing::debug!("EVENT {}: {} not owned by us — STAT mirror path tallies", phase, event_id);
+		return Ok(HookResult::default());
+	}
+	if let Err(e) = EventContent::parse(context.content.as_ref()) {
+		tracing::warn!("EVENT {}: {} is not a valid event, not tallying: {}", phase, event_id, e);
+		return Ok(HookResult::default());
+	}
+
+	// A replayed EVENT keeps the tally it has.
+	if read_tally(app, tn_id, event_id).await?.is_none() {
+		store_tally(app, tn_id, event_id, &RsvpTally::default()).await?;
+	}
+	tracing::info!("EVENT {}: opened {} for RSVPs", phase, event_id);
+	Ok(HookResult::default())
+}
+
+/// EVENT on_create hook — an event we publish ourselves.
+pub async fn event_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: EVENT on_create for action {}", context.action_id);
+	open(&app, &context, "on_create").await
+}
+
+/// EVENT on_receive hook — an event posted to a community we host, or a mirror copy.
+pub async fn event_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: EVENT on_receive for action {}", context.action_id);
+	open(&app, &context, "on_receive").await
+}
+
+/// File the event in (or take it out of) the calendar the user named in `x.calendar`.
+/// Best-effort: the RSVP stands whether or not the calendar write succeeds.
+async fn file_in_calendar(
+	app: &App,
+	context: &HookContext,
+	event_action: &ActionView,
+	event: &EventContent,
+) {
+	let tn_id = context.tn_id;
+	let cal_id = match app.meta_adapter.get_action(tn_id, &context.action_id).await {
+		Ok(Some(rsvp)) => rsvp
+			.x
+			.as_ref()
+			.and_then(|x| x.get("calendar"))
+			.and_then(serde_json::Value::as_u64),
+		_ => None,
+	};
+	let Some(cal_id) = cal_id else {
+		return;
+	};
+	let result = match context.subtype.as_deref() {
+		Some(answer @ ("GOING" | "MAYBE")) => {
+			let input =
+				to_calendar_input(event_action, event, Some((context.tenant_tag.as_str(), answer)));
+			cloudillo_calendar::handler::upsert_object(app, tn_id, cal_id, input)
+				.await
+				.map(|_| ())
+		}
+		_ => match app
+			.meta_adapter
+			.delete_calendar_object(tn_id, cal_id, &event_action.action_id)
+			.await
+		{
+			Err(Error::NotFound) => Ok(()),
+			res => res,
+		},
+	};
+	if let Err(e) = result {
+		tracing::warn!(
+			"RSVP: failed to update calendar {} for event {}: {}",
+			cal_id,
+			event_action.action_id,
+			e
+		);
+	}
+}
+
+/// RSVP on_create / on_receive — recount the event on its owner.
+async fn tally(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	let tn_id = context.tn_id;
+	let Some(event_id) = context.subject.as_deref() else {
+		tracing::warn!("RSVP {}: no subject specified", phase);
+		return Ok(HookResult::default());
+	};
+	let Some(event_action) = app.meta_adapter.get_action(tn_id, event_id).await? else {
+		tracing::debug!("RSVP {}: event {} not found locally", phase, event_id);
+		return Ok(HookResult::default());
+	};
+	if event_action.typ.as_ref() != "EVENT" {
+		tracing::warn!(
+			"RSVP {}: subject {} is a {}, not an EVENT",
+			phase,
+			event_id,
+			event_action.typ
+		);
+		return Ok(HookResult::default());
+	}
+	let Ok(event) = EventContent::parse(event_action.content.as_ref()) else {
+		return Ok(HookResult::default());
+	};
+
+	// Whatever the attendee's node signed, an RSVP is as visible as its event here.
+	if context.is_inbound {
+		let update_opts = UpdateActionDataOptions {
+			visibility: event_action.visibility.map_or(Patch::Null, Patch::Value),
+			..Default::default()
+		};
+		app.meta_adapter
+			.update_action_data(tn_id, &context.action_id, &update_opts)
+			.await?;
+	} else if context.issuer == context.tenant_tag {
+		file_in_calendar(app, context, &event_action, &event).await;
+	}
+
+	if !owns_subject(&event_action, &context.tenant_tag) {
+		return Ok(HookResult::default());
+	}
+
+	let reject = HookResult { status: Some('D'), ..Default::default() };
+	let answer = context.subtype.as_deref();
+	if answer != Some("DEL") {
+		if !answer.is_some_and(|a| RSVP_ANSWERS.contains(&a)) {
+			tracing::info!(
+				"RSVP {}: {} answered event {} with {:?} — rejected",
+				phase,
+				context.issuer,
+				event_id,
+				answer
+			);
+			return Ok(reject);
+		}
+		if event.has_ended_at(Timestamp::now()) {
+			tracing::info!(
+				"RSVP {}: {} answered event {} after it ended — rejected",
+				phase,
+				context.issuer,
+				event_id
+			);
+			return Ok(reject);
+		}
+	}
+
+	let mut tally = count_rsvps(app, tn_id, event_id).await?;
+	// The count includes this answer, so only more GOINGs than seats is over.
+	if answer == Some("GOING") && event.capacity.is_some_and(|capacity| tally.going > capacity) {
+		tracing::info!(
+			"RSVP {}: event {} is full, {} not admitted — rejected",
+			phase,
+			event_id,
+			context.issuer
+		);
+		tally.going -= 1;
+		store_tally(app, tn_id, event_id, &tally).await?;
+		emit_stat_for_subject(app, tn_id, &context.tenant_tag, event_id).await;
+		return Ok(reject);
+	}
+	tracing::info!(
+		"RSVP:{} {}: {} on event {} (going: {}) → STAT broadcast",
+		answer.unwrap_or_default(),
+		phase,
+		context.issuer,
+		event_id,
+		tally.going
+	);
+	if let Err(e) = store_tally(app, tn_id, event_id, &tally).await {
+		tracing::warn!("RSVP {}: failed to store tally of event {}: {}", phase, event_id, e);
+		return Ok(HookResult::default());
+	}
+	emit_stat_for_subject(app, tn_id, &context.tenant_tag, event_id).await;
+	Ok(HookResult::default())
+}
+
+/// RSVP on_create hook — a local answer, filed in the user's calendar on request and
+/// counted here when we own the event.
+pub async fn rsvp_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: RSVP on_create for action {}", context.action_id);
+	tally(&app, &context, "on_create").await
+}
+
+/// RSVP on_receive hook — an answer to an event we own.
+pub async fn rsvp_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: RSVP on_receive for action {}", context.action_id);
+	tally(&app, &context, "on_receive").await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn an_event_needs_a_title_and_a_sane_time_range() {
+		let parse = |v: serde_json::Value| EventContent::parse(Some(&v));
+		assert!(parse(serde_json::json!({ "title": "Meetup" })).is_err());
+		assert!(parse(serde_json::json!({ "title": " ", "startsAt": 100 })).is_err());
+		assert!(
+			parse(serde_json::json!({ "title": "Meetup", "startsAt": 100, "endsAt": 99 })).is_err()
+		);
+		assert!(
+			parse(serde_json::json!({ "title": "Meetup", "startsAt": 100, "capacity": 0 }))
+				.is_err()
+		);
+
+		let ok = parse(serde_json::json!({ "title": "Meetup", "startsAt": 100, "endsAt": 200 }))
+			.expect("valid");
+		assert!(!ok.all_day && ok.capacity.is_none());
+		assert!(!ok.has_ended_at(Timestamp(199)));
+		assert!(ok.has_ended_at(Timestamp(200)));
+
+		let instant =
+			parse(serde_json::json!({ "title": "Launch", "startsAt": 100 })).expect("valid");
+		assert!(instant.has_ended_at(Timestamp(100)));
+	}
+
+	#[test]
+	fn a_tally_counts_answers_and_fills_up_at_capacity() {
+		let mut tally = RsvpTally::default();
+		tally.add("GOING", 2);
+		tally.add("MAYBE", 1);
+		tally.add("DECLINED", 4);
+		tally.add("DEL", 9);
+		assert_eq!(tally, RsvpTally { going: 2, maybe: 1, declined: 4 });
+
+		let event = EventContent::parse(Some(&serde_json::json!({
+			"title": "Dinner",
+			"startsAt": 100,
+			"capacity": 3,
+		})))
+		.expect("valid");
+		assert!(!event.is_full(&tally));
+		tally.add("GOING", 1);
+		assert!(event.is_full(&tally));
+
+		let json = serde_json::to_string(&tally).expect("json");
+		assert_eq!(json, r#"{"going":3,"maybe":1,"declined":4}"#);
+	}
+}
+
+// vim: ts=4
@@ -11,12 +11,16 @@
 //! - cmnt: Comment counter management (CMNT)
 //! - conn: Connection lifecycle management (CONN)
 //! - conv: Conversation management (CONV)
+//! - edit: Content edits (POST:EDIT, CMNT:EDIT, MSG:EDIT)
+//! - event: Event RSVP tallies and calendar filing (EVENT, RSVP)
 //! - fllw: Follow relationship management (FLLW)
 //! - fshr: File sharing lifecycle management (FSHR)
 //! - idp: Identity provider operations (IDP:REG)
 //! - invt: Invitation management (INVT)
+//! - poll: Poll tallies and closing (POLL, VOTE)
 //! - prinvt: Profile invite notification (PRINVT)
 //! - react: Reaction management (REACT)
+//! - report: Content reports and moderation decisions (REPORT, MOD)
 //! - stat: Statistics action normalization (STAT)
 //! - subs: Subscription management (SUBS)
 
@@ -24,14 +28,18 @@ pub mod aprv;
 pub mod cmnt;
 pub mod conn;
 pub mod conv;
+pub mod edit;
+pub mod event;
 pub mod fllw;
 pub mod fshr;
 pub mod idp;
 pub mod invt;
 pub mod msg;
 pub(crate) mod ownership;
+pub mod poll;
 pub mod prinvt;
 pub mod react;
+pub mod report;
 pub mod repost;
 pub mod stat;
 mod stat_emit;
@@ -145,6 +153,98 @@ pub async fn register_native_hooks(app: &App) -> ClResult<()> {
 		tracing::info!("Registered native hooks for REPOST action type");
 	}
 
+	// POLL hooks — open the tally (and schedule the close) on the poll's owner.
+	{
+		let poll_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(poll::poll_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(poll::poll_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("POLL", poll_hooks);
+		tracing::info!("Registered native hooks for POLL action type");
+	}
+
+	// VOTE hooks — recount the poll and emit STAT when we own it.
+	{
+		let vote_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(poll::vote_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(poll::vote_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("VOTE", vote_hooks);
+		tracing::info!("Registered native hooks for VOTE action type");
+	}
+
+	// EVENT hooks — open the RSVP tally on the event's owner.
+	{
+		let event_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(event::event_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(event::event_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("EVENT", event_hooks);
+		tracing::info!("Registered native hooks for EVENT action type");
+	}
+
+	// RSVP hooks — file the event in the user's calendar, and recount and emit STAT
+	// when we own the event.
+	{
+		let rsvp_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(event::rsvp_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(event::rsvp_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("RSVP", rsvp_hooks);
+		tracing::info!("Registered native hooks for RSVP action type");
+	}
+
+	// REPORT hooks — admit reports on content we host into the moderation queue.
+	{
+		let report_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(report::report_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(report::report_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("REPORT", report_hooks);
+		tracing::info!("Registered native hooks for REPORT action type");
+	}
+
+	// MOD hooks — apply a hosting community's decision to our copy of the content.
+	{
+		let mod_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(report::mod_on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(report::mod_on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("MOD", mod_hooks);
+		tracing::info!("Registered native hooks for MOD action type");
+	}
+
+	// POST hooks — only edits need one; broadcasting is handled by the system.
+	{
+		let post_hooks = ActionTypeHooks {
+			on_create: Some(Arc::new(|app, ctx| Box::pin(edit::on_create(app, ctx)))),
+			on_receive: Some(Arc::new(|app, ctx| Box::pin(edit::on_receive(app, ctx)))),
+			on_accept: None,
+			on_reject: None,
+		};
+
+		registry.register_type("POST", post_hooks);
+		tracing::info!("Registered native hooks for POST action type");
+	}
+
 	// CMNT hooks
 	{
 		let cmnt_hooks = ActionTypeHooks {
@@ -275,8 +375,20 @@ mod tests {
 		let _ = fshr::on_create;
 		let _ = fshr::on_receive;
 		let _ = fshr::on_accept;
+		let _ = event::event_on_create;
+		let _ = event::event_on_receive;
+		let _ = event::rsvp_on_create;
+		let _ = event::rsvp_on_receive;
+		let _ = poll::poll_on_create;
+		let _ = poll::poll_on_receive;
+		let _ = poll::vote_on_create;
+		let _ = poll::vote_on_receive;
 		let _ = react::on_create;
 		let _ = react::on_receive;
+		let _ = report::report_on_create;
+		let _ = report::report_on_receive;
+		let _ = report::mod_on_create;
+		let _ = report::mod_on_receive;
 		let _ = stat::on_receive;
 		let _ = subs::on_create;
 		let _ = subs::on_receive;
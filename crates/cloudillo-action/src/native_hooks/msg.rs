@@ -19,6 +19,8 @@
 //! persisting we emit a coalesced STAT so subscribers learn the new
 //! `commentCount`/`lastCommentAt` (the group-unread badge) in real time.
 //!
+//! MSG:EDIT is not a new message; it goes to `native_hooks::edit` instead.
+//!
 //! Notifications (push/email/WS) are NOT minted here: MSG already flows through
 //! the generic `forward_*` + `send_push_notification` path
 //! (`forward::should_push_notify("MSG", None)`), delivering to the DM peer and,
@@ -26,6 +28,7 @@
 
 use crate::hooks::{HookContext, HookResult};
 use crate::native_hooks::cmnt::recompute_comment_stats;
+use crate::native_hooks::edit;
 use crate::native_hooks::ownership::owns_subject;
 use crate::native_hooks::stat_emit::emit_stat_for_subject;
 use crate::prelude::*;
@@ -35,6 +38,9 @@ use cloudillo_types::meta_adapter::UpdateActionDataOptions;
 /// outgoing group message. See the module doc for the scoping/ownership rules.
 pub async fn on_create(app: App, context: HookContext) -> ClResult<HookResult> {
 	tracing::debug!("Native hook: MSG on_create for action {}", context.action_id);
+	if edit::is_edit(&context) {
+		return edit::on_create(app, context).await;
+	}
 	update_conv_counters(&app, &context, "on_create").await
 }
 
@@ -42,6 +48,9 @@ pub async fn on_create(app: App, context: HookContext) -> ClResult<HookResult> {
 /// inbound federated group message. See the module doc for the rules.
 pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
 	tracing::debug!("Native hook: MSG on_receive for action {}", context.action_id);
+	if edit::is_edit(&context) {
+		return edit::on_receive(app, context).await;
+	}
 	update_conv_counters(&app, &context, "on_receive").await
 }
 
@@ -63,8 +63,10 @@ mod tests {
 			subject: None,
 			subject_profile: None,
 			subject_action: None,
+			mentions: None,
 			created_at: Timestamp(0),
 			received_at: None,
+			edited_at: None,
 			expires_at: None,
 			status: None,
 			stat: None,
@@ -72,6 +74,7 @@ mod tests {
 			flags: None,
 			sub_level: None,
 			x: None,
+			filtered: None,
 			token: None,
 		}
 	}
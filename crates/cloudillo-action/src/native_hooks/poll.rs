@@ -0,0 +1,519 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! POLL and VOTE action native hooks
+//!
+//! A POLL is a broadcast question: `{question, options, multiple?, anonymous?,
+//! closesAt?}` with 2 to [`MAX_POLL_OPTIONS`] options. A VOTE names the poll as its
+//! `subject` (like REACT, never `parent`) and carries `{choices: [option index, ...]}`
+//! — exactly one index unless the poll is `multiple`. VOTE is keyed
+//! `{type}:{subject}:{issuer}`, so a second vote replaces the first and VOTE:DEL
+//! withdraws it.
+//!
+//! Only the poll's authoritative node (see [`crate::native_hooks::ownership`]) tallies:
+//! on every VOTE it recounts the live ballots, stores the [`PollTally`] in
+//! `actions.votes` and emits a STAT carrying it as `v`, which mirrors apply like the
+//! reaction counters (see `stat.rs`). It also rejects votes that arrive after
+//! `closesAt` or whose choices don't fit the poll. At `closesAt` a [`PollCloseTask`]
+//! marks the tally `closed` and broadcasts the final result; a closed tally is never
+//! recounted.
+//!
+//! An anonymous poll publishes only counts: its votes are stored Direct — visible to
+//! the voter and the poll's owner — on every node, whatever the voter's node signed.
+//! The owning node still holds the ballots; anonymity is from the audience, not from
+//! the host.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use cloudillo_core::scheduler::{Task, TaskId};
+use cloudillo_types::action_types::CreateAction;
+use cloudillo_types::meta_adapter::{
+	ActionCountGroupBy, ActionView, ListActionOptions, UpdateActionDataOptions,
+};
+
+use crate::hooks::{HookContext, HookResult};
+use crate::native_hooks::ownership::owns_subject;
+use crate::native_hooks::stat_emit::emit_stat_for_subject;
+use crate::prelude::*;
+
+/// Most options a poll may offer.
+pub const MAX_POLL_OPTIONS: usize = 20;
+
+/// POLL content.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PollContent {
+	pub question: String,
+	pub options: Vec<String>,
+	/// Voters may pick more than one option.
+	#[serde(default)]
+	pub multiple: bool,
+	/// Publish counts only — see the module docs.
+	#[serde(default)]
+	pub anonymous: bool,
+	/// Epoch seconds after which no vote counts. `None` keeps the poll open.
+	pub closes_at: Option<i64>,
+}
+
+/// VOTE content.
+#[derive(Debug, Deserialize)]
+struct Ballot {
+	choices: Vec<usize>,
+}
+
+impl PollContent {
+	/// Parse and check a POLL's content.
+	pub fn parse(content: Option<&serde_json::Value>) -> ClResult<Self> {
+		let content =
+			content.ok_or_else(|| Error::ValidationError("POLL requires content".into()))?;
+		let poll: PollContent = serde_json::from_value(content.clone())
+			.map_err(|e| Error::ValidationError(format!("Invalid POLL content: {}", e)))?;
+		if poll.question.trim().is_empty() {
+			return Err(Error::ValidationError("POLL requires a question".into()));
+		}
+		if !(2..=MAX_POLL_OPTIONS).contains(&poll.options.len()) {
+			return Err(Error::ValidationError(format!(
+				"POLL needs 2 to {} options",
+				MAX_POLL_OPTIONS
+			)));
+		}
+		if poll.options.iter().any(|o| o.trim().is_empty()) {
+			return Err(Error::ValidationError("POLL options must not be empty".into()));
+		}
+		Ok(poll)
+	}
+
+	/// Whether no vote counts any more at `now`.
+	pub fn is_closed_at(&self, now: Timestamp) -> bool {
+		self.closes_at.is_some_and(|closes_at| now.0 >= closes_at)
+	}
+
+	/// The option indexes a VOTE's content picks, if they fit this poll: at least
+	/// one, each in range and none twice, and only one unless the poll is `multiple`.
+	pub fn parse_ballot(&self, content: Option<&serde_json::Value>) -> ClResult<Vec<usize>> {
+		let content =
+			content.ok_or_else(|| Error::ValidationError("VOTE requires content".into()))?;
+		let ballot: Ballot = serde_json::from_value(content.clone())
+			.map_err(|e| Error::ValidationError(format!("Invalid VOTE content: {}", e)))?;
+		let choices = ballot.choices;
+		if choices.is_empty() {
+			return Err(Error::ValidationError("VOTE must choose an option".into()));
+		}
+		if !self.multiple && choices.len() > 1 {
+			return Err(Error::ValidationError("This poll allows a single choice".into()));
+		}
+		if choices.iter().any(|&c| c >= self.options.len()) {
+			return Err(Error::ValidationError("VOTE chooses an unknown option".into()));
+		}
+		let mut seen = vec![false; self.options.len()];
+		for &c in &choices {
+			if std::mem::replace(&mut seen[c], true) {
+				return Err(Error::ValidationError("VOTE chooses an option twice".into()));
+			}
+		}
+		Ok(choices)
+	}
+}
+
+/// A poll's result, stored in `actions.votes` and federated as STAT `v`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollTally {
+	/// Live ballots. With `multiple` this is less than the sum of `counts`.
+	pub voters: u32,
+	/// Votes per option, in option order.
+	pub counts: Vec<u32>,
+	/// Set by [`PollCloseTask`]; the counts are final.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub closed: bool,
+}
+
+impl PollTally {
+	fn empty(poll: &PollContent) -> Self {
+		Self { voters: 0, counts: vec![0; poll.options.len()], closed: false }
+	}
+
+	/// Count `n` identical ballots.
+	fn add(&mut self, choices: &[usize], n: u32) {
+		self.voters = self.voters.saturating_add(n);
+		for &c in choices {
+			if let Some(count) = self.counts.get_mut(c) {
+				*count = count.saturating_add(n);
+			}
+		}
+	}
+}
+
+/// Recount the live ballots on `poll_id`. Identical ballots are counted in one
+/// group (see [`ActionCountGroupBy::Content`]); ballots that don't fit the poll
+/// count for nothing.
+pub(crate) async fn count_votes(
+	app: &App,
+	tn_id: TnId,
+	poll_id: &str,
+	poll: &PollContent,
+) -> ClResult<PollTally> {
+	let opts = ListActionOptions {
+		typ: Some(vec!["VOTE".into()]),
+		subject: Some(vec![poll_id.to_string()]),
+		exclude_sub_typ: Some(Box::from([Box::from("DEL")])),
+		..Default::default() // status unset → default "active" filter
+	};
+	let grouped = app
+		.meta_adapter
+		.count_actions_grouped(tn_id, &opts, ActionCountGroupBy::Content)
+		.await?;
+	let mut tally = PollTally::empty(poll);
+	for (content, cnt) in grouped {
+		let Some(content) = content.and_then(|c| serde_json::from_str(&c).ok()) else {
+			continue;
+		};
+		let Ok(choices) = poll.parse_ballot(Some(&content)) else {
+			continue;
+		};
+		tally.add(&choices, u32::try_from(cnt).unwrap_or(0));
+	}
+	Ok(tally)
+}
+
+async fn read_tally(app: &App, tn_id: TnId, poll_id: &str) -> ClResult<Option<PollTally>> {
+	let data = app.meta_adapter.get_action_data(tn_id, poll_id).await?;
+	Ok(data.and_then(|d| d.votes).and_then(|v| serde_json::from_str(&v).ok()))
+}
+
+async fn store_tally(app: &App, tn_id: TnId, poll_id: &str, tally: &PollTally) -> ClResult<()> {
+	let update_opts = UpdateActionDataOptions {
+		votes: Patch::Value(serde_json::to_string(tally)?),
+		..Default::default()
+	};
+	app.meta_adapter.update_action_data(tn_id, poll_id, &update_opts).await
+}
+
+/// The node a VOTE must reach: the poll's audience if community-hosted, else its
+/// issuer — the same node [`owns_subject`] names.
+fn poll_owner(poll_action: &ActionView) -> &str {
+	poll_action.audience.as_ref().map_or(&poll_action.issuer.id_tag, |a| &a.id_tag)
+}
+
+/// Create-path checks for POLL and VOTE, run before signing. Receivers check the
+/// same and reject a vote that fails, so rejecting it here is what tells the voter.
+///
+/// A VOTE without an audience is addressed to the poll's owner, and a vote on an
+/// anonymous poll is made Direct.
+pub(crate) async fn validate_create(
+	app: &App,
+	tn_id: TnId,
+	base_type: &str,
+	sub_typ: Option<&str>,
+	action: &mut CreateAction,
+) -> ClResult<()> {
+	match base_type {
+		"POLL" if sub_typ.is_none() => {
+			let poll = PollContent::parse(action.content.as_ref())?;
+			if poll.is_closed_at(Timestamp::now()) {
+				return Err(Error::ValidationError("POLL closesAt must be in the future".into()));
+			}
+		}
+		"VOTE" => {
+			let poll_id = action
+				.subject
+				.as_deref()
+				.ok_or_else(|| Error::ValidationError("VOTE requires a subject".into()))?;
+			let poll_action =
+				app.meta_adapter.get_action(tn_id, poll_id).await?.ok_or(Error::NotFound)?;
+			if poll_action.typ.as_ref() != "POLL" {
+				return Err(Error::ValidationError("VOTE subject must be a POLL".into()));
+			}
+			let poll = PollContent::parse(poll_action.content.as_ref())?;
+			if sub_typ != Some("DEL") {
+				if poll.is_closed_at(Timestamp::now()) {
+					return Err(Error::ValidationError("Poll is closed".into()));
+				}
+				poll.parse_ballot(action.content.as_ref())?;
+			}
+			if action.audience_tag.is_none() {
+				action.audience_tag = Some(poll_owner(&poll_action).into());
+			}
+			if poll.anonymous {
+				action.visibility = None;
+			}
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+/// Schedule the close of `poll_id` at `closes_at`. Best-effort like STAT emits:
+/// votes after `closesAt` are rejected whether or not the task runs; the task only
+/// freezes and broadcasts the final tally.
+async fn schedule_close(app: &App, tn_id: TnId, tenant_tag: &str, poll_id: &str, closes_at: i64) {
+	let task =
+		Arc::new(PollCloseTask { tn_id, poll_id: poll_id.into(), tenant_tag: tenant_tag.into() });
+	if let Err(e) = app
+		.scheduler
+		.task(task)
+		.key(format!("poll.close:{}:{}", tn_id, poll_id))
+		.schedule_at(Timestamp(closes_at))
+		.schedule()
+		.await
+	{
+		warn!(tn_id = %tn_id, poll_id = %poll_id, error = %e, "Failed to schedule poll close");
+	}
+}
+
+/// POLL on_create / on_receive — open the tally on the poll's owner.
+async fn open(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	if context.subtype.is_some() {
+		return Ok(HookResult::default());
+	}
+	let tn_id = context.tn_id;
+	let poll_id = context.action_id.as_str();
+	let Some(poll_action) = app.meta_adapter.get_action(tn_id, poll_id).await? else {
+		return Ok(HookResult::default());
+	};
+	if !owns_subject(&poll_action, &context.tenant_tag) {
+		tracing::debug!("POLL {}: {} not owned by us — STAT mirror path tallies", phase, poll_id);
+		return Ok(HookResult::default());
+	}
+	let poll = match PollContent::parse(context.content.as_ref()) {
+		Ok(poll) => poll,
+		Err(e) => {
+			tracing::warn!("POLL {}: {} is not a valid poll, not tallying: {}", phase, poll_id, e);
+			return Ok(HookResult::default());
+		}
+	};
+
+	// A replayed POLL keeps the tally it has.
+	if read_tally(app, tn_id, poll_id).await?.is_none() {
+		store_tally(app, tn_id, poll_id, &PollTally::empty(&poll)).await?;
+	}
+	if let Some(closes_at) = poll.closes_at {
+		schedule_close(app, tn_id, &context.tenant_tag, poll_id, closes_at).await;
+	}
+	tracing::info!(
+		"POLL {}: opened {} with {} options (closes_at={:?})",
+		phase,
+		poll_id,
+		poll.options.len(),
+		poll.closes_at
+	);
+	Ok(HookResult::default())
+}
+
+/// POLL on_create hook — a poll we publish ourselves.
+pub async fn poll_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: POLL on_create for action {}", context.action_id);
+	open(&app, &context, "on_create").await
+}
+
+/// POLL on_receive hook — a poll posted to a community we host, or a mirror copy.
+pub async fn poll_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: POLL on_receive for action {}", context.action_id);
+	open(&app, &context, "on_receive").await
+}
+
+/// VOTE on_create / on_receive — recount the poll on its owner.
+async fn tally(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	let tn_id = context.tn_id;
+	let Some(poll_id) = context.subject.as_deref() else {
+		tracing::warn!("VOTE {}: no subject specified", phase);
+		return Ok(HookResult::default());
+	};
+	let Some(poll_action) = app.meta_adapter.get_action(tn_id, poll_id).await? else {
+		tracing::debug!("VOTE {}: poll {} not found locally", phase, poll_id);
+		return Ok(HookResult::default());
+	};
+	if poll_action.typ.as_ref() != "POLL" {
+		tracing::warn!("VOTE {}: subject {} is a {}, not a POLL", phase, poll_id, poll_action.typ);
+		return Ok(HookResult::default());
+	}
+	let Ok(poll) = PollContent::parse(poll_action.content.as_ref()) else {
+		return Ok(HookResult::default());
+	};
+
+	// Whatever the voter's node signed, a vote on an anonymous poll is Direct here.
+	if poll.anonymous && context.is_inbound {
+		let update_opts = UpdateActionDataOptions { visibility: Patch::Null, ..Default::default() };
+		app.meta_adapter
+			.update_action_data(tn_id, &context.action_id, &update_opts)
+			.await?;
+	}
+
+	if !owns_subject(&poll_action, &context.tenant_tag) {
+		return Ok(HookResult::default());
+	}
+
+	let reject = HookResult { status: Some('D'), ..Default::default() };
+	let previous = read_tally(app, tn_id, poll_id).await?;
+	if poll.is_closed_at(Timestamp::now()) || previous.as_ref().is_some_and(|t| t.closed) {
+		tracing::info!(
+			"VOTE {}: {} voted on closed poll {} — rejected",
+			phase,
+			context.issuer,
+			poll_id
+		);
+		return Ok(reject);
+	}
+	if context.subtype.as_deref() != Some("DEL")
+		&& let Err(e) = poll.parse_ballot(context.content.as_ref())
+	{
+		tracing::info!("VOTE {}: {} on poll {} rejected: {}", phase, context.issuer, poll_id, e);
+		return Ok(reject);
+	}
+
+	let tally = count_votes(app, tn_id, poll_id, &poll).await?;
+	tracing::info!(
+		"VOTE{} {}: {} on poll {} (voters: {}) → STAT broadcast",
+		if context.subtype.as_deref() == Some("DEL") { ":DEL" } else { "" },
+		phase,
+		context.issuer,
+		poll_id,
+		tally.voters
+	);
+	if let Err(e) = store_tally(app, tn_id, poll_id, &tally).await {
+		tracing::warn!("VOTE {}: failed to store tally of poll {}: {}", phase, poll_id, e);
+		return Ok(HookResult::default());
+	}
+	emit_stat_for_subject(app, tn_id, &context.tenant_tag, poll_id).await;
+	Ok(HookResult::default())
+}
+
+/// VOTE on_create hook — a local vote, counted here when we own the poll.
+pub async fn vote_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: VOTE on_create for action {}", context.action_id);
+	tally(&app, &context, "on_create").await
+}
+
+/// VOTE on_receive hook — a vote on a poll we own.
+pub async fn vote_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: VOTE on_receive for action {}", context.action_id);
+	tally(&app, &context, "on_receive").await
+}
+
+/// Scheduled close of a poll, keyed `poll.close:{tn_id}:{poll_id}` and run at its
+/// `closesAt` on the poll's owner. Recounts once more, freezes the tally and emits
+/// the STAT that tells subscribers the result is final.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollCloseTask {
+	pub tn_id: TnId,
+	pub poll_id: Box<str>,
+	pub tenant_tag: Box<str>,
+}
+
+#[async_trait]
+impl Task<App> for PollCloseTask {
+	fn kind() -> &'static str {
+		"poll.close"
+	}
+
+	fn kind_of(&self) -> &'static str {
+		Self::kind()
+	}
+
+	fn build(_id: TaskId, ctx: &str) -> ClResult<Arc<dyn Task<App>>> {
+		let task: PollCloseTask = serde_json::from_str(ctx)?;
+		Ok(Arc::new(task))
+	}
+
+	fn serialize(&self) -> String {
+		// Built by hand for the same reason as `StatEmitTask::serialize`.
+		let mut obj = serde_json::Map::with_capacity(3);
+		obj.insert("tn_id".into(), self.tn_id.0.into());
+		obj.insert("poll_id".into(), self.poll_id.as_ref().into());
+		obj.insert("tenant_tag".into(), self.tenant_tag.as_ref().into());
+		serde_json::Value::Object(obj).to_string()
+	}
+
+	async fn run(&self, app: &App) -> ClResult<()> {
+		// Deleted since it was scheduled: nothing to close.
+		let Some(poll_action) = app.meta_adapter.get_action(self.tn_id, &self.poll_id).await?
+		else {
+			return Ok(());
+		};
+		let poll = PollContent::parse(poll_action.content.as_ref())?;
+		let mut tally = count_votes(app, self.tn_id, &self.poll_id, &poll).await?;
+		tally.closed = true;
+		store_tally(app, self.tn_id, &self.poll_id, &tally).await?;
+		info!(poll_id = %self.poll_id, voters = tally.voters, "Poll closed");
+		emit_stat_for_subject(app, self.tn_id, &self.tenant_tag, &self.poll_id).await;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn poll(multiple: bool) -> PollContent {
+		PollContent::parse(Some(&serde_json::json!({
+			"question": "Lunch?",
+			"options": ["pizza", "soup", "salad"],
+			"multiple": multiple,
+		})))
+		.expect("valid poll")
+	}
+
+	#[test]
+	fn a_poll_needs_a_question_and_two_to_max_options() {
+		let parse = |v: serde_json::Value| PollContent::parse(Some(&v));
+		assert!(parse(serde_json::json!({ "question": "?", "options": ["a"] })).is_err());
+		assert!(parse(serde_json::json!({ "question": " ", "options": ["a", "b"] })).is_err());
+		assert!(parse(serde_json::json!({ "question": "?", "options": ["a", ""] })).is_err());
+		let many: Vec<String> = (0..=MAX_POLL_OPTIONS).map(|i| i.to_string()).collect();
+		assert!(parse(serde_json::json!({ "question": "?", "options": many })).is_err());
+
+		let ok =
+			parse(serde_json::json!({ "question": "?", "options": ["a", "b"], "closesAt": 100 }))
+				.expect("valid");
+		assert!(!ok.multiple && !ok.anonymous);
+		assert!(!ok.is_closed_at(Timestamp(99)));
+		assert!(ok.is_closed_at(Timestamp(100)));
+	}
+
+	#[test]
+	fn ballots_must_fit_the_poll() {
+		let ballot = |choices: serde_json::Value| serde_json::json!({ "choices": choices });
+		let single = poll(false);
+		assert_eq!(single.parse_ballot(Some(&ballot(serde_json::json!([1])))).ok(), Some(vec![1]));
+		assert!(single.parse_ballot(Some(&ballot(serde_json::json!([0, 1])))).is_err());
+		assert!(single.parse_ballot(Some(&ballot(serde_json::json!([])))).is_err());
+		assert!(single.parse_ballot(Some(&ballot(serde_json::json!([3])))).is_err());
+		assert!(single.parse_ballot(None).is_err());
+
+		let multi = poll(true);
+		assert_eq!(
+			multi.parse_ballot(Some(&ballot(serde_json::json!([0, 2])))).ok(),
+			Some(vec![0, 2])
+		);
+		assert!(multi.parse_ballot(Some(&ballot(serde_json::json!([2, 2])))).is_err());
+	}
+
+	#[test]
+	fn a_tally_counts_voters_once_and_choices_each() {
+		let mut tally = PollTally::empty(&poll(true));
+		tally.add(&[0, 2], 3);
+		tally.add(&[1], 2);
+		assert_eq!(tally, PollTally { voters: 5, counts: vec![3, 2, 3], closed: false });
+
+		// `closed` is only on the wire once set.
+		assert!(!serde_json::to_string(&tally).expect("json").contains("closed"));
+		tally.closed = true;
+		let json = serde_json::to_string(&tally).expect("json");
+		assert_eq!(serde_json::from_str::<PollTally>(&json).ok(), Some(tally));
+	}
+
+	#[test]
+	fn close_task_serialize_build_roundtrip() {
+		let task = PollCloseTask {
+			tn_id: TnId(7),
+			poll_id: "a1~poll".into(),
+			tenant_tag: "owner.example".into(),
+		};
+		let json = Task::<App>::serialize(&task);
+		let built = PollCloseTask::build(0, &json).expect("build should succeed");
+		assert_eq!(built.kind_of(), "poll.close");
+	}
+}
+
+// vim: ts=4
@@ -17,11 +17,18 @@
 //!
 //! Note: REACT uses `subject` field to reference the action being reacted to,
 //! NOT `parent`. This is because reactions don't create visible hierarchy.
+//!
+//! The subtype is the reaction: a built-in name (LIKE, ...), any Unicode emoji,
+//! or a custom emoji `:name:` (see [`cloudillo_types::reactions`]). A custom
+//! emoji belongs to the subject's owner — typically a community — which
+//! publishes its set in `profile.custom_emoji` and serves it at `/api/me/emoji`
+//! for peers to render. The owner rejects a `:name:` it doesn't publish.
 
 use crate::hooks::{HookContext, HookResult};
 use crate::native_hooks::ownership::owns_subject;
 use crate::native_hooks::stat_emit::emit_stat_for_subject;
 use crate::prelude::*;
+use cloudillo_types::action_types::CreateAction;
 use cloudillo_types::meta_adapter::{
 	ActionCountGroupBy, ListActionOptions, UpdateActionDataOptions,
 };
@@ -40,7 +47,7 @@ pub(crate) async fn count_reactions(app: &App, tn_id: TnId, subject_id: &str) ->
 		.meta_adapter
 		.count_actions_grouped(tn_id, &opts, ActionCountGroupBy::SubType)
 		.await?;
-	let mut counts: Vec<(char, u32)> = Vec::new();
+	let mut counts: Vec<(String, u32)> = Vec::new();
 	let mut total: u32 = 0;
 	for (sub_type, cnt) in grouped {
 		let Some(sub_type) = sub_type else { continue };
@@ -58,6 +65,60 @@ pub(crate) async fn count_reactions(app: &App, tn_id: TnId, subject_id: &str) ->
 	Ok(reactions::encode_reaction_counts(counts, total))
 }
 
+/// Whether tenant `tn_id` publishes the custom emoji `name` in its
+/// `profile.custom_emoji` set.
+pub(crate) async fn custom_emoji_is_published(
+	app: &App,
+	tn_id: TnId,
+	name: &str,
+) -> ClResult<bool> {
+	let set = app.settings.get_json_opt(tn_id, "profile.custom_emoji").await?;
+	Ok(set.as_ref().and_then(|set| set.get(name)).is_some())
+}
+
+/// Create-path check for REACT, run before signing: the subtype must name a
+/// reaction, and a custom emoji on a subject we own must be one we publish. A
+/// custom emoji on someone else's subject is checked by its owner.
+pub(crate) async fn validate_create(
+	app: &App,
+	tn_id: TnId,
+	tenant_tag: &str,
+	base_type: &str,
+	sub_typ: Option<&str>,
+	action: &CreateAction,
+) -> ClResult<()> {
+	if base_type != "REACT" {
+		return Ok(());
+	}
+	let Some(sub_typ) = sub_typ.filter(|s| *s != "DEL") else {
+		return Ok(());
+	};
+	if reactions::reaction_type_key(sub_typ).is_none() {
+		return Err(Error::ValidationError(format!("Unknown reaction '{}'", sub_typ)));
+	}
+	if let Some(name) = reactions::custom_emoji_name(sub_typ)
+		&& let Some(subject_id) = action.subject.as_deref()
+		&& let Some(subject_action) = app.meta_adapter.get_action(tn_id, subject_id).await?
+		&& owns_subject(&subject_action, tenant_tag)
+		&& !custom_emoji_is_published(app, tn_id, name).await?
+	{
+		return Err(Error::ValidationError(format!("Unknown custom emoji '{}'", sub_typ)));
+	}
+	Ok(())
+}
+
+/// Owner-side check shared by both hooks: a custom emoji we don't publish.
+async fn is_unpublished_custom_emoji(
+	app: &App,
+	tn_id: TnId,
+	subtype: Option<&str>,
+) -> ClResult<bool> {
+	match subtype.and_then(reactions::custom_emoji_name) {
+		Some(name) => Ok(!custom_emoji_is_published(app, tn_id, name).await?),
+		None => Ok(false),
+	}
+}
+
 /// REACT on_create hook - Handle local reaction creation
 ///
 /// Counts active reactions per type for the subject and updates the stored
@@ -158,6 +219,17 @@ pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult>
 		return Ok(HookResult::default());
 	}
 
+	// Our own reactions were checked on create; a peer may name any `:name:`.
+	if is_unpublished_custom_emoji(&app, tn_id, context.subtype.as_deref()).await? {
+		tracing::info!(
+			"REACT on_receive: {} reacted to {} with unpublished custom emoji {:?} — rejected",
+			context.issuer,
+			subject_id,
+			context.subtype
+		);
+		return Ok(HookResult { status: Some('D'), ..Default::default() });
+	}
+
 	// Count active reactions per type for the subject
 	let new_reactions = count_reactions(&app, tn_id, subject_id).await?;
 
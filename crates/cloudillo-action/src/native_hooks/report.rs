@@ -0,0 +1,300 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! REPORT and MOD action native hooks
+//!
+//! A REPORT flags a post, comment or message — its `subject` — to the moderators of
+//! the community hosting it, with `{reason, note?}` content where `reason` is one of
+//! [`REPORT_REASONS`]. The reporter's node addresses it to that community (see
+//! [`moderating_community`]) and makes it Direct, so only the reporter and the
+//! community see it. REPORT is keyed `{type}:{subject}:{issuer}`: reporting the same
+//! item again replaces the earlier report.
+//!
+//! The community keeps a report it hosts the subject of at status `A` — open, in the
+//! moderation queue (`crate::moderation`) — until a moderator decides on it, which
+//! rests it at `N`. A report on content the community does not host is rejected.
+//!
+//! A MOD is the community's decision, signed by the community and naming the moderated
+//! action as its `subject`. `MOD:HIDE` withdraws the content from listings (status
+//! [`crate::status::HIDDEN`]), `MOD:RESTORE` brings hidden content back and
+//! `MOD:REMOVE` deletes it. These three broadcast to the community's followers and
+//! share one key, so the latest decision stands. `MOD:WARN` is addressed to the author
+//! alone and rests there as a notification. Every node holding the subject applies a
+//! MOD only when its issuer is the community moderating the subject; any other MOD is
+//! stored but changes nothing.
+
+use serde::Deserialize;
+
+use cloudillo_types::action_types::CreateAction;
+use cloudillo_types::meta_adapter::{ActionView, ProfileType, UpdateActionDataOptions};
+
+use crate::hooks::{HookContext, HookResult};
+use crate::prelude::*;
+
+/// The reason categories a REPORT may name.
+pub const REPORT_REASONS: [&str; 8] = [
+	"spam",
+	"harassment",
+	"hate",
+	"violence",
+	"sexual",
+	"misinformation",
+	"illegal",
+	"other",
+];
+
+/// Longest free-text note on a REPORT or a MOD:WARN.
+pub const MAX_NOTE_LENGTH: usize = 1000;
+
+/// REPORT content.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportContent {
+	pub reason: String,
+	pub note: Option<String>,
+}
+
+impl ReportContent {
+	/// Parse and check a REPORT's content.
+	pub fn parse(content: Option<&serde_json::Value>) -> ClResult<Self> {
+		let content =
+			content.ok_or_else(|| Error::ValidationError("REPORT requires content".into()))?;
+		let report: ReportContent = serde_json::from_value(content.clone())
+			.map_err(|e| Error::ValidationError(format!("Invalid REPORT content: {}", e)))?;
+		if !REPORT_REASONS.contains(&report.reason.as_str()) {
+			return Err(Error::ValidationError(format!(
+				"Unknown REPORT reason '{}'",
+				report.reason
+			)));
+		}
+		if report.note.as_ref().is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+			return Err(Error::ValidationError("REPORT note is too long".into()));
+		}
+		Ok(report)
+	}
+}
+
+/// Whether `id_tag` is a community, as far as this node knows.
+async fn is_community(app: &App, tn_id: TnId, id_tag: &str) -> ClResult<bool> {
+	let tenant = app.meta_adapter.read_tenant(tn_id).await?;
+	if tenant.id_tag.as_ref() == id_tag {
+		return Ok(tenant.typ == ProfileType::Community);
+	}
+	match app.meta_adapter.read_profile(tn_id, id_tag).await {
+		Ok((_, profile)) => Ok(profile.typ == ProfileType::Community),
+		Err(Error::NotFound) => Ok(false),
+		Err(e) => Err(e),
+	}
+}
+
+/// The community whose moderators answer for `subject`, if any.
+///
+/// That is the subject's owner (audience if set, else issuer — see
+/// [`crate::native_hooks::ownership`]) when it is a community, otherwise the owner
+/// of the thread's root: a comment or a message is moderated by the community
+/// hosting the post or conversation it belongs to.
+pub(crate) async fn moderating_community(
+	app: &App,
+	tn_id: TnId,
+	subject: &ActionView,
+) -> ClResult<Option<Box<str>>> {
+	let owner = subject.audience.as_ref().unwrap_or(&subject.issuer);
+	if is_community(app, tn_id, &owner.id_tag).await? {
+		return Ok(Some(owner.id_tag.clone()));
+	}
+	let Some(root_id) = subject.root_id.as_deref().filter(|id| *id != &*subject.action_id) else {
+		return Ok(None);
+	};
+	let Some(root) = app.meta_adapter.get_action(tn_id, root_id).await? else {
+		return Ok(None);
+	};
+	let owner = root.audience.as_ref().unwrap_or(&root.issuer);
+	if is_community(app, tn_id, &owner.id_tag).await? {
+		return Ok(Some(owner.id_tag.clone()));
+	}
+	Ok(None)
+}
+
+/// Create-path checks for REPORT, run before signing. The community checks the same
+/// and rejects a report that fails, so rejecting it here is what tells the reporter.
+///
+/// A REPORT is addressed to the community hosting its subject and made Direct.
+pub(crate) async fn validate_create(
+	app: &App,
+	tn_id: TnId,
+	id_tag: &str,
+	base_type: &str,
+	action: &mut CreateAction,
+) -> ClResult<()> {
+	if base_type != "REPORT" {
+		return Ok(());
+	}
+	ReportContent::parse(action.content.as_ref())?;
+	let subject_id = action
+		.subject
+		.as_deref()
+		.ok_or_else(|| Error::ValidationError("REPORT requires a subject".into()))?;
+	let subject = app.meta_adapter.get_action(tn_id, subject_id).await?.ok_or(Error::NotFound)?;
+	if subject.issuer.id_tag.as_ref() == id_tag {
+		return Err(Error::ValidationError("Cannot report your own content".into()));
+	}
+	let community = moderating_community(app, tn_id, &subject).await?.ok_or_else(|| {
+		Error::ValidationError("Only content hosted by a community can be reported".into())
+	})?;
+	action.audience_tag = Some(community);
+	action.visibility = Some('D');
+	Ok(())
+}
+
+/// REPORT on_create / on_receive — admit a report into the queue of the community
+/// it is addressed to.
+async fn file_report(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	if context.audience.as_deref() != Some(context.tenant_tag.as_str()) {
+		return Ok(HookResult::default());
+	}
+	let reject = HookResult { status: Some('D'), ..Default::default() };
+	if let Err(e) = ReportContent::parse(context.content.as_ref()) {
+		tracing::info!(
+			"REPORT {}: {} from {} rejected: {}",
+			phase,
+			context.action_id,
+			context.issuer,
+			e
+		);
+		return Ok(reject);
+	}
+	let Some(subject_id) = context.subject.as_deref() else {
+		return Ok(reject);
+	};
+	let Some(subject) = app.meta_adapter.get_action(context.tn_id, subject_id).await? else {
+		tracing::info!(
+			"REPORT {}: {} reported {}, which is not here — rejected",
+			phase,
+			context.issuer,
+			subject_id
+		);
+		return Ok(reject);
+	};
+	if moderating_community(app, context.tn_id, &subject).await?.as_deref()
+		!= Some(context.tenant_tag.as_str())
+	{
+		tracing::info!(
+			"REPORT {}: {} reported {}, which we do not host — rejected",
+			phase,
+			context.issuer,
+			subject_id
+		);
+		return Ok(reject);
+	}
+	tracing::info!("REPORT {}: {} reported {} — queued", phase, context.issuer, subject_id);
+	Ok(HookResult::default())
+}
+
+/// REPORT on_create hook — a report filed on the community's own node.
+pub async fn report_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: REPORT on_create for action {}", context.action_id);
+	file_report(&app, &context, "on_create").await
+}
+
+/// REPORT on_receive hook — a report from a member's node.
+pub async fn report_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: REPORT on_receive for action {}", context.action_id);
+	file_report(&app, &context, "on_receive").await
+}
+
+/// The status a MOD subtype gives the moderated action, given its current status.
+/// `None` leaves it alone.
+fn moderated_status(decision: &str, current: Option<&str>) -> Option<char> {
+	let current = current.unwrap_or("A");
+	match decision {
+		"HIDE" if current == "A" => Some(crate::status::HIDDEN),
+		"RESTORE" if current == "H" => Some(crate::status::ACTIVE),
+		"REMOVE" => Some(crate::status::DELETED),
+		_ => None,
+	}
+}
+
+/// MOD on_create / on_receive — apply the community's decision to our copy.
+async fn apply(app: &App, context: &HookContext, phase: &str) -> ClResult<HookResult> {
+	let tn_id = context.tn_id;
+	let Some(subject_id) = context.subject.as_deref() else {
+		return Ok(HookResult::default());
+	};
+	let Some(subject) = app.meta_adapter.get_action(tn_id, subject_id).await? else {
+		tracing::debug!("MOD {}: {} not held here", phase, subject_id);
+		return Ok(HookResult::default());
+	};
+	if moderating_community(app, tn_id, &subject).await?.as_deref() != Some(context.issuer.as_str())
+	{
+		tracing::warn!(
+			"MOD {}: {} does not moderate {} — ignored",
+			phase,
+			context.issuer,
+			subject_id
+		);
+		return Ok(HookResult::default());
+	}
+
+	let decision = context.subtype.as_deref().unwrap_or_default();
+	if decision == "WARN" {
+		// The warning itself is what the author sees; nothing else changes.
+		if context.audience.as_deref() == Some(context.tenant_tag.as_str()) {
+			return Ok(HookResult {
+				status: Some(crate::status::NOTIFICATION),
+				..Default::default()
+			});
+		}
+		return Ok(HookResult::default());
+	}
+	let Some(status) = moderated_status(decision, subject.status.as_deref()) else {
+		return Ok(HookResult::default());
+	};
+	let update_opts =
+		UpdateActionDataOptions { status: Patch::Value(status), ..Default::default() };
+	app.meta_adapter.update_action_data(tn_id, subject_id, &update_opts).await?;
+	cloudillo_core::search_index_action(app, tn_id, subject_id);
+	tracing::info!("MOD {}: {} {} by {}", phase, decision, subject_id, context.issuer);
+	Ok(HookResult::default())
+}
+
+/// MOD on_create hook — a decision our moderators took.
+pub async fn mod_on_create(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: MOD on_create for action {}", context.action_id);
+	apply(&app, &context, "on_create").await
+}
+
+/// MOD on_receive hook — a decision of a community whose content we hold.
+pub async fn mod_on_receive(app: App, context: HookContext) -> ClResult<HookResult> {
+	tracing::debug!("Native hook: MOD on_receive for action {}", context.action_id);
+	apply(&app, &context, "on_receive").await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn report_content_needs_a_known_reason() {
+		let parse = |v: serde_json::Value| ReportContent::parse(Some(&v));
+		assert!(parse(serde_json::json!({ "reason": "spam" })).is_ok());
+		assert!(parse(serde_json::json!({ "reason": "other", "note": "see the link" })).is_ok());
+		assert!(parse(serde_json::json!({ "reason": "boring" })).is_err());
+		assert!(parse(serde_json::json!({ "note": "no reason" })).is_err());
+		assert!(ReportContent::parse(None).is_err());
+
+		let long = "x".repeat(MAX_NOTE_LENGTH + 1);
+		assert!(parse(serde_json::json!({ "reason": "spam", "note": long })).is_err());
+	}
+
+	#[test]
+	fn decisions_move_between_active_hidden_and_deleted() {
+		assert_eq!(moderated_status("HIDE", None), Some('H'));
+		assert_eq!(moderated_status("HIDE", Some("A")), Some('H'));
+		assert_eq!(moderated_status("HIDE", Some("D")), None);
+		assert_eq!(moderated_status("RESTORE", Some("H")), Some('A'));
+		assert_eq!(moderated_status("RESTORE", Some("A")), None);
+		assert_eq!(moderated_status("REMOVE", Some("H")), Some('D'));
+		assert_eq!(moderated_status("WARN", Some("A")), None);
+	}
+}
+
+// vim: ts=4
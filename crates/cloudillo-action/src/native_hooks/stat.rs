@@ -6,7 +6,9 @@
 //! On receive this hook does two things:
 //!
 //! 1. Applies the broadcasted `content.r` (reactions string), `content.c`
-//!    (comment count), and `content.ct` (last-comment timestamp) to the
+//!    (comment count), `content.ct` (last-comment timestamp) and, for a
+//!    POLL, `content.v` (its tally) or, for an EVENT, `content.rv` (its
+//!    RSVP tally) to the
 //!    subject's local `actions_data` row
 //!    when the STAT comes from the subject's authoritative owner and
 //!    we don't own the subject ourselves (the
@@ -18,9 +20,11 @@
 //!    a guard against STAT-vs-REACT/CMNT races, because those paths
 //!    are disjoint by node (see [`crate::native_hooks::ownership`]).
 //! 2. Normalizes the STAT's own `content.r` to the canonical wire
-//!    format: "<total>,<code><count>,..." with at most 5 per-type
-//!    entries, sorted DESC by count then ASC by code. Lenient — never
-//!    rejects; only re-encodes if the sender's form deviates.
+//!    format — v1 "<total>,<code><count>,..." for built-in reactions,
+//!    v2 "v2;<total>;<key>=<count>;..." once an emoji is counted (see
+//!    [`cloudillo_types::reactions`]) — with the entries capped and
+//!    sorted DESC by count then ASC by key. Lenient — never rejects;
+//!    only re-encodes if the sender's form deviates.
 
 use crate::hooks::{HookContext, HookResult};
 use crate::prelude::*;
@@ -88,6 +92,8 @@ pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult>
 					let c_count = content_val.get("c").and_then(serde_json::Value::as_u64);
 					let ct_ts = content_val.get("ct").and_then(serde_json::Value::as_i64);
 					let rp_int = content_val.get("rp").and_then(serde_json::Value::as_u64);
+					let v_obj = content_val.get("v").filter(|v| v.is_object());
+					let rv_obj = content_val.get("rv").filter(|v| v.is_object());
 
 					let reactions_patch = match r_str {
 						Some(s) => Patch::Value(s.to_string()),
@@ -106,17 +112,29 @@ pub async fn on_receive(app: App, context: HookContext) -> ClResult<HookResult>
 						Some(n) => Patch::Value(u32::try_from(n).unwrap_or(u32::MAX)),
 						None => Patch::Undefined,
 					};
+					let votes_patch = match v_obj {
+						Some(v) => Patch::Value(v.to_string()),
+						None => Patch::Undefined,
+					};
+					let rsvps_patch = match rv_obj {
+						Some(v) => Patch::Value(v.to_string()),
+						None => Patch::Undefined,
+					};
 
 					if !reactions_patch.is_undefined()
 						|| !comments_patch.is_undefined()
 						|| !comments_ts_patch.is_undefined()
 						|| !reposts_patch.is_undefined()
+						|| !votes_patch.is_undefined()
+						|| !rsvps_patch.is_undefined()
 					{
 						let update_opts = UpdateActionDataOptions {
 							reactions: reactions_patch,
 							comments: comments_patch,
 							comments_ts: comments_ts_patch,
 							reposts: reposts_patch,
+							votes: votes_patch,
+							rsvps: rsvps_patch,
 							stat_at: Patch::Value(Timestamp(incoming_created_at)),
 							..Default::default()
 						};
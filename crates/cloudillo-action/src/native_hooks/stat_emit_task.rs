@@ -19,6 +19,10 @@
 //! decrement their mirrored counters after the last REACT or CMNT on a
 //! subject is deleted; if the fields were omitted, receivers would
 //! retain their previous non-zero counts indefinitely.
+//!
+//! A POLL's STAT additionally carries its tally as `v` (see
+//! `native_hooks::poll`) and an EVENT's its RSVP tally as `rv` (see
+//! `native_hooks::event`); other subjects have neither and omit them.
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -98,6 +102,14 @@ impl Task<App> for StatEmitTask {
 		content.insert("c".into(), serde_json::Value::from(comment_count));
 		content.insert("ct".into(), serde_json::Value::from(comments_ts));
 		content.insert("rp".into(), serde_json::Value::from(reposts));
+		let votes = comment_data.as_ref().and_then(|d| d.votes.clone());
+		if let Some(v) = votes.as_deref().and_then(|v| serde_json::from_str(v).ok()) {
+			content.insert("v".into(), v);
+		}
+		let rsvps = comment_data.as_ref().and_then(|d| d.rsvps.clone());
+		if let Some(rv) = rsvps.as_deref().and_then(|v| serde_json::from_str(v).ok()) {
+			content.insert("rv".into(), rv);
+		}
 
 		let create = CreateAction {
 			typ: "STAT".into(),
@@ -133,7 +145,13 @@ impl Task<App> for StatEmitTask {
 		let post_comment_data =
 			app.meta_adapter.get_action_data(self.tn_id, &self.subject_id).await?;
 		let post_comment_count = post_comment_data.as_ref().and_then(|d| d.comments).unwrap_or(0);
-		if post_reactions != reactions || post_comment_count != comment_count {
+		let post_votes = post_comment_data.as_ref().and_then(|d| d.votes.clone());
+		let post_rsvps = post_comment_data.and_then(|d| d.rsvps);
+		if post_reactions != reactions
+			|| post_comment_count != comment_count
+			|| post_votes != votes
+			|| post_rsvps != rsvps
+		{
 			crate::native_hooks::stat_emit::emit_stat_for_subject(
 				app,
 				self.tn_id,
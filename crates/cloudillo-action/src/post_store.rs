@@ -103,8 +103,8 @@ struct HookExecutionResult {
 /// 1. Execute hook (on_create for outbound, on_receive for inbound)
 /// 2. Forward to WebSocket clients
 /// 3. Fan-out to subscribers of subscribable parent chain
-/// 4. Schedule delivery (with broadcast check for self-posting)
-/// 5. Direction-specific: auto-approve (inbound), push notifications (inbound)
+/// 4. Schedule delivery (with broadcast check for self-posting, and to mentioned profiles)
+/// 5. Direction-specific: auto-approve (inbound), mention notifications (inbound)
 pub async fn process_after_store(
 	app: &App,
 	tn_id: TnId,
@@ -126,6 +126,26 @@ pub async fn process_after_store(
 		return Ok(result);
 	}
 
+	// 1b. Index the hashtags and mentions of posts and comments, created or received.
+	crate::hashtags::index_action(
+		app,
+		tn_id,
+		&action.action_id,
+		&action.typ,
+		action.sub_typ.as_deref(),
+		action.content.as_deref(),
+	)
+	.await;
+	crate::mentions::index_action(
+		app,
+		tn_id,
+		&action.action_id,
+		&action.typ,
+		action.sub_typ.as_deref(),
+		action.content.as_deref(),
+	)
+	.await;
+
 	// 2. Forward to WebSocket clients.
 	//
 	// The DB row is still 'V' (verifying) at this point — the action's resting
@@ -170,8 +190,12 @@ pub async fn process_after_store(
 		.await?
 	};
 
-	// 4. Schedule delivery (with broadcast check)
+	// 4. Schedule delivery (with broadcast check), then to the mentioned profiles
+	// the regular delivery does not reach.
 	schedule_delivery(app, tn_id, action, &fanout_recipients, &ctx).await?;
+	if ctx.is_outbound() {
+		crate::mentions::schedule_delivery(app, tn_id, action, &fanout_recipients).await?;
+	}
 
 	// 5. Direction-specific processing
 	if let ProcessingContext::Inbound { is_sync, .. } = &ctx
@@ -180,6 +204,11 @@ pub async fn process_after_store(
 		// Auto-approve approvable actions from trusted sources
 		try_auto_approve(app, tn_id, action).await;
 	}
+	// A received post or comment that mentions the tenant notifies them, online or
+	// not (see `crate::mentions`).
+	if ctx.is_inbound() && accepted {
+		crate::mentions::notify(app, tn_id, action).await;
+	}
 
 	// 6. Sync involved profiles for inbound actions. ensure_profile early-returns
 	// when the profile already exists, so this is a no-op for known peers.
@@ -406,6 +435,7 @@ pub(crate) async fn forward_to_websocket(
 			"User offline - may need push notification"
 		);
 		// TODO: Send push notification for inbound actions when user is offline
+		// (mentions are pushed regardless of presence, see `process_after_store`).
 
 		// Offline email only fires for *persisted inbound* actions (the
 		// `ctx.is_inbound()` guard). The recipient is always this node's own
@@ -10,7 +10,7 @@ use std::sync::Arc;
 use cloudillo_core::abac::VisibilityLevel;
 use cloudillo_core::rate_limit::{PenaltyReason, PowPenaltyReason, RateLimitApi};
 use cloudillo_types::auth_adapter::ActionToken;
-use cloudillo_types::meta_adapter::{self, AttachmentView};
+use cloudillo_types::meta_adapter::{self, AttachmentView, ContentFilterMode};
 
 use crate::{
 	dsl::DslEngine,
@@ -125,6 +125,13 @@ pub async fn verify_action_token(
 			.inspect_err(|e| warn!("  rejected: {e}"))?;
 	}
 
+	// The admin's instance blocklist refuses a blocked domain here, before its key
+	// is fetched: nothing from it is verified, stored or answered.
+	if crate::filter::is_instance_blocked(app, issuer).await? {
+		warn!("  rejected: {} is on a blocked instance", issuer);
+		return Err(Error::PermissionDenied);
+	}
+
 	info!("→ VERIFY: from={} key={}", issuer, key_id);
 
 	let key_cache = app.ext::<Arc<KeyFetchCache>>()?;
@@ -342,6 +349,17 @@ async fn process_inbound_action_token_inner(
 		check_inbound_flags(app, tn_id, &action, definition).await?;
 	}
 
+	// 5c. The tenant's `drop` content filters discard a matching action unseen
+	if !skip_permission_check && is_dropped_by_content_filter(app, tn_id, &action).await? {
+		info!(
+			action_id = %action_id,
+			action_type = %action.t,
+			issuer = %action.iss,
+			"Dropping inbound action matched by a content filter"
+		);
+		return Ok(None);
+	}
+
 	// Check if this is an ephemeral action (forward only, don't persist)
 	let is_ephemeral = definition.behavior.ephemeral.unwrap_or(false);
 
@@ -590,6 +608,42 @@ fn resolve_definition<'a>(
 	Err(Error::ValidationError(format!("Action type not supported: {}", action_type)))
 }
 
+/// Whether one of the tenant's `drop` content filters matches an inbound action.
+/// `hide` and `warn` filters apply when the owner lists their feeds instead.
+async fn is_dropped_by_content_filter(
+	app: &App,
+	tn_id: TnId,
+	action: &ActionToken,
+) -> ClResult<bool> {
+	let filters = crate::filter::ContentFilters::load(app, tn_id).await?;
+	Ok(filters.check_inbound(&action.iss, action.c.as_ref()) == Some(ContentFilterMode::Drop))
+}
+
+/// Refuse anything but a DEL from an issuer this community has muted: muted members
+/// may still read, withdraw what they wrote and leave, but no longer post, comment,
+/// react or report here. Persons' own mutes only quiet their feed and refuse nothing.
+/// See `crate::moderation`.
+async fn check_community_mute(app: &App, tn_id: TnId, action: &ActionToken) -> ClResult<()> {
+	use cloudillo_types::meta_adapter::{ProfileStatus, ProfileType};
+
+	if helpers::extract_type_and_subtype(&action.t).1.as_deref() == Some("DEL") {
+		return Ok(());
+	}
+	let muted = matches!(
+		app.meta_adapter.read_profile(tn_id, &action.iss).await,
+		Ok((_, p)) if p.status == Some(ProfileStatus::Muted)
+	);
+	if muted && app.meta_adapter.read_tenant(tn_id).await?.typ == ProfileType::Community {
+		warn!(
+			issuer = %action.iss,
+			action_type = %action.t,
+			"Inbound action refused: issuer is muted in this community"
+		);
+		return Err(Error::PermissionDenied);
+	}
+	Ok(())
+}
+
 /// Check permissions based on action type's allow_unknown setting
 async fn check_inbound_permissions(
 	app: &App,
@@ -597,6 +651,9 @@ async fn check_inbound_permissions(
 	action: &ActionToken,
 	definition: &crate::dsl::types::ActionDefinition,
 ) -> ClResult<()> {
+	// Whatever the type admits from strangers, a muted community member is refused.
+	check_community_mute(app, tn_id, action).await?;
+
 	if definition.behavior.allow_unknown.unwrap_or(false) {
 		return Ok(());
 	}
@@ -643,7 +700,7 @@ async fn check_inbound_permissions(
 	// stranger-engagement on public content work without per-type branches.
 	//
 	// R1 — accept engagement on your own content. If an engagement action
-	// (REACT/REPOST only) references a locally-owned action via `subject` whose
+	// (REACT/REPOST/VOTE/RSVP only) references a locally-owned action via `subject` whose
 	// visibility admits the sender (public ⇒ anyone), accept regardless of
 	// follow state. This is what lets the original poster accept a REPOST/REACT
 	// from a non-follower. Restricting to the engagement allowlist keeps the
@@ -652,7 +709,7 @@ async fn check_inbound_permissions(
 	// gate). The owner predicate (audience if set, else issuer) is the shared
 	// `owns_subject` helper.
 	let (base_type, _sub) = helpers::extract_type_and_subtype(&action.t);
-	if matches!(base_type.as_str(), "REACT" | "REPOST")
+	if matches!(base_type.as_str(), "REACT" | "REPOST" | "VOTE" | "RSVP")
 		&& let Some(subject_id) = action.sub.as_deref()
 		&& let Ok(Some(subject)) = app.meta_adapter.get_action(tn_id, subject_id).await
 		&& let Ok(tenant) = app.meta_adapter.read_tenant(tn_id).await
@@ -677,6 +734,16 @@ async fn check_inbound_permissions(
 		return Ok(());
 	}
 
+	// R2b — likewise a MOD for content we hold. Its receive hook
+	// (`native_hooks::report`) applies it only when the issuer is the community
+	// moderating that content, so a stranger's MOD is stored but changes nothing.
+	if helpers::extract_type_and_subtype(&action.t).0 == "MOD"
+		&& let Some(target_id) = action.sub.as_deref()
+		&& matches!(app.meta_adapter.get_action(tn_id, target_id).await, Ok(Some(_)))
+	{
+		return Ok(());
+	}
+
 	// R3 — accept a vouching APRV from the owner of a relay_children container we
 	// hold. The APRV's JWT is signature-verified above, so its vouch can't be
 	// forged; the bundled related token is bound to APRV.subject and re-checked in
@@ -694,6 +761,13 @@ async fn check_inbound_permissions(
 		return Ok(());
 	}
 
+	// R4 — accept a post or comment that mentions us, and its issuer's DEL / EDIT
+	// of one we hold, unless the tenant limits mentions to connections (a
+	// connection has already passed the gate above). See `crate::mentions`.
+	if crate::mentions::admits_inbound(app, tn_id, action).await? {
+		return Ok(());
+	}
+
 	warn!(
 		issuer = %action.iss,
 		action_type = %action.t,
@@ -825,10 +899,9 @@ async fn check_inbound_flags(
 	definition: &crate::dsl::types::ActionDefinition,
 ) -> ClResult<()> {
 	let (_action_type, sub_type) = helpers::extract_type_and_subtype(&action.t);
-	let is_delete = sub_type.as_deref() == Some("DEL");
-
-	// DEL subtypes should not be gated (deletions must always be allowed through)
-	if is_delete {
+	// DEL and EDIT subtypes should not be gated: they only touch something the flag
+	// already admitted, and deletions must always be allowed through
+	if matches!(sub_type.as_deref(), Some("DEL" | crate::native_hooks::edit::EDIT_SUBTYPE)) {
 		return Ok(());
 	}
 
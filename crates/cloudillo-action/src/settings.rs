@@ -1,7 +1,8 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-//! Federation/action settings registration (admin-only infrastructure settings)
+//! Federation/action settings registration (admin-only infrastructure settings),
+//! plus the user-facing weights of the ranked feed and the mention limit
 
 use crate::prelude::*;
 use cloudillo_core::settings::{
@@ -31,6 +32,18 @@ pub fn register_settings(registry: &mut SettingsRegistry) -> ClResult<()> {
 			.build()?,
 	)?;
 
+	// Instance blocklist — actions from these domains (and their subdomains) are
+	// refused before the issuer's key is fetched, on every tenant of this node.
+	registry.register(
+		SettingDefinition::builder("federation.blocked_instances")
+			.description("Domains whose actions this node refuses: a list of domain names")
+			.scope(SettingScope::Global)
+			.permission(PermissionLevel::Admin)
+			.optional(true)
+			.validator(validate_blocked_instances)
+			.build()?,
+	)?;
+
 	// Federation history sync: age window in days
 	registry.register(
 		SettingDefinition::builder("federation.history_sync.since_days")
@@ -79,6 +92,59 @@ pub fn register_settings(registry: &mut SettingsRegistry) -> ClResult<()> {
 			.build()?,
 	)?;
 
+	// Ranked feed weights (see `crate::feed`) — the reader's own tuning.
+	for (key, signal, default) in crate::feed::WEIGHT_SETTINGS {
+		registry.register(
+			SettingDefinition::builder(key)
+				.description(format!("Weight of {signal} in the ranked feed (0-100)"))
+				.default(SettingValue::Int(default))
+				.scope(SettingScope::Tenant)
+				.permission(PermissionLevel::User)
+				.validator(|v| match v {
+					SettingValue::Int(n) if (0..=100).contains(n) => Ok(()),
+					_ => Err(Error::ValidationError(
+						"Feed weights must be integers between 0 and 100".into(),
+					)),
+				})
+				.build()?,
+		)?;
+	}
+
+	// Mentions from connections only (see `crate::mentions`)
+	registry.register(
+		SettingDefinition::builder(crate::mentions::CONNECTIONS_ONLY_SETTING)
+			.description("Only accept and notify mentions from your connections")
+			.default(SettingValue::Bool(false))
+			.scope(SettingScope::Tenant)
+			.permission(PermissionLevel::User)
+			.build()?,
+	)?;
+
+	Ok(())
+}
+
+/// Most domains the instance blocklist may hold.
+const MAX_BLOCKED_INSTANCES: usize = 10_000;
+
+fn validate_blocked_instances(v: &SettingValue) -> ClResult<()> {
+	let SettingValue::Json(serde_json::Value::Array(domains)) = v else {
+		return Err(Error::ValidationError("Blocked instances must be a list of domains".into()));
+	};
+	if domains.len() > MAX_BLOCKED_INSTANCES {
+		return Err(Error::ValidationError(format!(
+			"At most {} instances may be blocked",
+			MAX_BLOCKED_INSTANCES
+		)));
+	}
+	for domain in domains {
+		let domain = domain.as_str().unwrap_or_default();
+		if crate::filter::normalize_instance(domain).as_deref() != Some(domain) {
+			return Err(Error::ValidationError(format!(
+				"Invalid blocked instance '{}': use a lowercase domain name",
+				domain
+			)));
+		}
+	}
 	Ok(())
 }
 
@@ -19,6 +19,7 @@ use crate::{
 	dsl::DslEngine,
 	fanout::schedule_subscriber_fanout,
 	helpers,
+	native_hooks::{edit, event, poll, react, report},
 	post_store::{self, ProcessingContext},
 	prelude::*,
 	process,
@@ -136,12 +137,14 @@ pub async fn create_action(
 		}
 	}
 
-	// Outbound validation: generic flag gating from BehaviorFlags
+	// Outbound validation: generic flag gating from BehaviorFlags. Deletes and edits
+	// only touch something the flag already admitted, so they are not gated.
 	{
 		let (_action_type, sub_type) = helpers::extract_type_and_subtype(&action.typ);
-		let is_delete = sub_type.as_deref() == Some("DEL");
+		let sub_type = sub_type.as_deref().or(action.sub_typ.as_deref());
+		let is_exempt = matches!(sub_type, Some("DEL" | edit::EDIT_SUBTYPE));
 
-		if !is_delete {
+		if !is_exempt {
 			if let Some(flag) = behavior.as_ref().and_then(|b| b.gated_by_parent_flag)
 				&& let Some(ref parent_id) = action.parent_id
 				&& !parent_id.starts_with('@')
@@ -167,6 +170,24 @@ pub async fn create_action(
 		}
 	}
 
+	// EDIT validation: the edited action must be here, and ours, and of the same type.
+	// Receivers check the same (`native_hooks::edit`) and ignore an edit that fails, so
+	// rejecting it here is what tells the author.
+	if action.sub_typ.as_deref() == Some(edit::EDIT_SUBTYPE) {
+		let subject_id = action
+			.subject
+			.as_deref()
+			.ok_or_else(|| Error::ValidationError("EDIT requires a subject".into()))?;
+		let subject =
+			app.meta_adapter.get_action(tn_id, subject_id).await?.ok_or(Error::NotFound)?;
+		if !edit::may_edit(&subject, &action.typ, id_tag) {
+			return Err(Error::ValidationError(format!(
+				"Cannot edit {} {} as {}",
+				subject.typ, subject_id, action.typ
+			)));
+		}
+	}
+
 	// Serialize content Value to string for storage (always JSON-encode)
 	let content_str = helpers::serialize_content(action.content.as_ref());
 
@@ -259,6 +280,20 @@ pub async fn create_action(
 		}
 	}
 
+	// POLL/VOTE, EVENT/RSVP, REACT and REPORT validation; also addresses a vote or an RSVP
+	// to its subject's owner and a report to the hosting community, makes a vote on an
+	// anonymous poll and a report Direct, and gives an RSVP its event's visibility —
+	// hence re-reading the visibility.
+	{
+		let (base_type, sub_typ_x) = helpers::extract_type_and_subtype(&action.typ);
+		let sub_typ = action.sub_typ.as_deref().or(sub_typ_x.as_deref()).map(str::to_owned);
+		poll::validate_create(app, tn_id, &base_type, sub_typ.as_deref(), &mut action).await?;
+		event::validate_create(app, tn_id, &base_type, sub_typ.as_deref(), &mut action).await?;
+		react::validate_create(app, tn_id, id_tag, &base_type, sub_typ.as_deref(), &action).await?;
+		report::validate_create(app, tn_id, id_tag, &base_type, &mut action).await?;
+	}
+	let visibility = action.visibility;
+
 	// Enforce declared field constraints + content schema on the create path, using
 	// the final canonicalized field set (post REPOST subject-flattening / visibility
 	// resolution) — the exact shape that will be signed and federated. Mirrors the
@@ -0,0 +1,658 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Tenant backup and restore.
+//!
+//! A backup is a plain ustar archive, streamed as it is written:
+//!
+//! - `auth.json` — [`AuthAdapter::export_tenant`]: login, profile signing keys,
+//!   certificate, passkeys, API keys
+//! - `meta.json` — [`MetaAdapter::export_tenant`]
+//! - `blobs/{blob_id}` — every blob, as stored
+//! - `crdt/{doc_id}.json` — a CRDT document's update log
+//! - `rtdb/{db_id}.json` — an RTDB database's documents, as `[path, document]` pairs
+//! - `manifest.json` — format version, and size and hash of every entry above
+//!
+//! The manifest comes last because the hashes are only known once everything
+//! has been written, which also makes it the completeness marker: an export that
+//! failed midway leaves an archive without one, and restore refuses it.
+//!
+//! Each store is read consistently on its own, not all five at once. A tenant
+//! that keeps writing during an export may come out with documents newer than
+//! its file list; suspend it first for an exact point in time.
+//!
+//! Restore verifies the whole archive before it writes anything, then loads it
+//! as a new tenant under the ID tag it was taken from — on this node or another.
+//! A failure after the tenant was created purges what was loaded so far. The
+//! search index is not in the archive; a reindex is scheduled instead.
+//!
+//! Gated by `admin::perm::require_admin` (server-wide `SADM`) through the router
+//! it is mounted on.
+//!
+//! [`AuthAdapter::export_tenant`]: cloudillo_types::auth_adapter::AuthAdapter::export_tenant
+//! [`MetaAdapter::export_tenant`]: cloudillo_types::meta_adapter::MetaAdapter::export_tenant
+
+use axum::{
+	Json,
+	body::Body,
+	extract::{Path, State},
+	http::{StatusCode, header},
+	response::Response,
+};
+use base64::Engine;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::path::Path as FsPath;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_util::io::ReaderStream;
+
+use cloudillo_core::scheduler::RetryPolicy;
+use cloudillo_search::reindex::{ReindexScope, ReindexTask};
+use cloudillo_types::crdt_adapter::CrdtUpdate;
+use cloudillo_types::hasher::Hasher;
+use cloudillo_types::meta_adapter::ListFileOptions;
+use cloudillo_types::types::{ApiResponse, CursorData, TableDump};
+
+use crate::prelude::*;
+
+const FORMAT: &str = "cloudillo-tenant-backup";
+const VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const BLOCK: usize = 512;
+/// Files fetched per page while looking for RTDB databases.
+const PAGE: u32 = 200;
+
+/// The archive's last entry.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+	pub format: Box<str>,
+	pub version: u32,
+	pub id_tag: Box<str>,
+	pub created_at: i64,
+	pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+	pub path: Box<str>,
+	pub size: u64,
+	/// [`Hasher`] digest of the entry's bytes.
+	pub hash: Box<str>,
+}
+
+/// A CRDT update as archived.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedUpdate {
+	/// Base64 of the raw update.
+	data: String,
+	client_id: Option<Box<str>>,
+}
+
+/// What an archive entry holds, by its path.
+#[derive(Debug, PartialEq, Eq)]
+enum Entry<'a> {
+	Auth,
+	Meta,
+	Blob(&'a str),
+	Crdt(&'a str),
+	Rtdb(&'a str),
+}
+
+impl<'a> Entry<'a> {
+	fn parse(path: &'a str) -> ClResult<Self> {
+		let id = |rest: &'a str| Some(rest).filter(|id| !id.is_empty() && !id.contains('/'));
+		let entry = match path {
+			"auth.json" => Some(Self::Auth),
+			"meta.json" => Some(Self::Meta),
+			_ => {
+				if let Some(rest) = path.strip_prefix("blobs/") {
+					id(rest).map(Self::Blob)
+				} else if let Some(rest) = path.strip_prefix("crdt/") {
+					rest.strip_suffix(".json").and_then(id).map(Self::Crdt)
+				} else if let Some(rest) = path.strip_prefix("rtdb/") {
+					rest.strip_suffix(".json").and_then(id).map(Self::Rtdb)
+				} else {
+					None
+				}
+			}
+		};
+		entry.ok_or_else(|| Error::ValidationError(format!("unexpected backup entry {}", path)))
+	}
+}
+
+// Archive writing
+//*****************
+
+struct ArchiveWriter<W> {
+	out: W,
+	entries: Vec<ManifestEntry>,
+	mtime: u64,
+}
+
+impl<W: AsyncWrite + Unpin> ArchiveWriter<W> {
+	fn new(out: W) -> Self {
+		Self { out, entries: Vec::new(), mtime: Timestamp::now().0.unsigned_abs() }
+	}
+
+	async fn header(&mut self, path: &str, size: u64) -> ClResult<()> {
+		let mut header = tar::Header::new_ustar();
+		header
+			.set_path(path)
+			.map_err(|e| Error::Internal(format!("cannot archive {}: {}", path, e)))?;
+		header.set_size(size);
+		header.set_mode(0o644);
+		header.set_mtime(self.mtime);
+		header.set_entry_type(tar::EntryType::Regular);
+		header.set_cksum();
+		self.out.write_all(header.as_bytes()).await?;
+		Ok(())
+	}
+
+	async fn finish_entry(&mut self, path: &str, size: u64, hasher: Hasher) -> ClResult<()> {
+		let tail = padding(size);
+		if tail > 0 {
+			self.out.write_all(&[0u8; BLOCK][..tail]).await?;
+		}
+		self.entries.push(ManifestEntry {
+			path: path.into(),
+			size,
+			hash: hasher.finalize("").into(),
+		});
+		Ok(())
+	}
+
+	async fn add(&mut self, path: &str, data: &[u8]) -> ClResult<()> {
+		let size = data.len() as u64;
+		self.header(path, size).await?;
+		self.out.write_all(data).await?;
+		let mut hasher = Hasher::new();
+		hasher.update(data);
+		self.finish_entry(path, size, hasher).await
+	}
+
+	/// Stream an entry whose size is known up front, as a tar header needs it.
+	async fn add_stream<S>(&mut self, path: &str, size: u64, mut stream: S) -> ClResult<()>
+	where
+		S: Stream<Item = Result<axum::body::Bytes, std::io::Error>> + Unpin,
+	{
+		self.header(path, size).await?;
+		let mut hasher = Hasher::new();
+		let mut written = 0u64;
+		while let Some(chunk) = stream.next().await {
+			let chunk = chunk?;
+			written += chunk.len() as u64;
+			if written > size {
+				break;
+			}
+			hasher.update(&chunk);
+			self.out.write_all(&chunk).await?;
+		}
+		if written != size {
+			return Err(Error::Internal(format!("{} changed size while being archived", path)));
+		}
+		self.finish_entry(path, size, hasher).await
+	}
+
+	async fn finish(mut self, id_tag: &str) -> ClResult<()> {
+		let manifest = Manifest {
+			format: FORMAT.into(),
+			version: VERSION,
+			id_tag: id_tag.into(),
+			created_at: Timestamp::now().0,
+			entries: std::mem::take(&mut self.entries),
+		};
+		let data = serde_json::to_vec_pretty(&manifest)?;
+		self.header(MANIFEST, data.len() as u64).await?;
+		self.out.write_all(&data).await?;
+		let tail = padding(data.len() as u64);
+		if tail > 0 {
+			self.out.write_all(&[0u8; BLOCK][..tail]).await?;
+		}
+		// End of archive: two empty blocks
+		self.out.write_all(&[0u8; BLOCK * 2]).await?;
+		self.out.shutdown().await?;
+		Ok(())
+	}
+}
+
+/// Zero bytes closing an entry of `size` bytes out to a whole block.
+#[allow(clippy::cast_possible_truncation)] // always < BLOCK
+fn padding(size: u64) -> usize {
+	match (size % BLOCK as u64) as usize {
+		0 => 0,
+		rem => BLOCK - rem,
+	}
+}
+
+// Archive reading
+//*****************
+
+struct ArchiveReader<R> {
+	input: R,
+}
+
+struct EntryHeader {
+	path: String,
+	size: u64,
+}
+
+impl<R: AsyncRead + Unpin> ArchiveReader<R> {
+	fn new(input: R) -> Self {
+		Self { input }
+	}
+
+	/// The next entry's header, or `None` at the end of the archive. Its data
+	/// must then be consumed with exactly one of the methods below.
+	async fn next(&mut self) -> ClResult<Option<EntryHeader>> {
+		let mut block = [0u8; BLOCK];
+		match self.input.read_exact(&mut block).await {
+			Ok(_) => {}
+			// A missing end marker only matters if the manifest is missing too,
+			// which `verify` reports on its own.
+			Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+			Err(e) => return Err(e.into()),
+		}
+		if block.iter().all(|b| *b == 0) {
+			return Ok(None);
+		}
+
+		let corrupt = |e: std::io::Error| Error::ValidationError(format!("corrupt backup: {}", e));
+		let header = tar::Header::from_byte_slice(&block);
+		if header.entry_type() != tar::EntryType::Regular {
+			return Err(Error::ValidationError("backup holds a non-file entry".into()));
+		}
+		let size = header.entry_size().map_err(corrupt)?;
+		let path = header
+			.path()
+			.map_err(corrupt)?
+			.to_str()
+			.ok_or_else(|| Error::ValidationError("backup entry path is not UTF-8".into()))?
+			.to_owned();
+		Ok(Some(EntryHeader { path, size }))
+	}
+
+	async fn skip_padding(&mut self, size: u64) -> ClResult<()> {
+		let mut pad = [0u8; BLOCK];
+		self.input.read_exact(&mut pad[..padding(size)]).await?;
+		Ok(())
+	}
+
+	async fn read_to_vec(&mut self, size: u64) -> ClResult<Vec<u8>> {
+		let mut data = Vec::new();
+		(&mut self.input).take(size).read_to_end(&mut data).await?;
+		if data.len() as u64 != size {
+			return Err(Error::ValidationError("backup is truncated".into()));
+		}
+		self.skip_padding(size).await?;
+		Ok(data)
+	}
+
+	async fn hash(&mut self, size: u64) -> ClResult<Box<str>> {
+		let mut body = (&mut self.input).take(size);
+		let mut hasher = Hasher::new();
+		let mut buf = vec![0u8; 64 * 1024];
+		let mut read = 0u64;
+		loop {
+			let n = body.read(&mut buf).await?;
+			if n == 0 {
+				break;
+			}
+			read += n as u64;
+			hasher.update(&buf[..n]);
+		}
+		if read != size {
+			return Err(Error::ValidationError("backup is truncated".into()));
+		}
+		self.skip_padding(size).await?;
+		Ok(hasher.finalize("").into())
+	}
+
+	async fn read_json<T: serde::de::DeserializeOwned>(
+		&mut self,
+		entry: &EntryHeader,
+	) -> ClResult<T> {
+		let data = self.read_to_vec(entry.size).await?;
+		serde_json::from_slice(&data).map_err(|e| {
+			Error::ValidationError(format!("backup entry {} is malformed: {}", entry.path, e))
+		})
+	}
+}
+
+// Export
+//********
+
+/// Write a backup of the tenant to `out`.
+pub async fn export_tenant<W: AsyncWrite + Unpin>(app: &App, tn_id: TnId, out: W) -> ClResult<()> {
+	let id_tag = app.auth_adapter.read_id_tag(tn_id).await?;
+	let mut archive = ArchiveWriter::new(out);
+
+	let auth = app.auth_adapter.export_tenant(tn_id).await?;
+	archive.add("auth.json", &serde_json::to_vec(&auth)?).await?;
+	let meta = app.meta_adapter.export_tenant(tn_id).await?;
+	archive.add("meta.json", &serde_json::to_vec(&meta)?).await?;
+
+	let mut blobs = app.blob_adapter.list_blobs(tn_id).await?;
+	while let Some(blob_id) = blobs.next().await {
+		let blob_id = blob_id?;
+		// Gone since it was listed: garbage-collected, so nothing references it
+		let Some(stat) = app.blob_adapter.stat_blob(tn_id, &blob_id).await else {
+			continue;
+		};
+		let stream = app.blob_adapter.read_blob_stream(tn_id, &blob_id).await?;
+		archive.add_stream(&format!("blobs/{}", blob_id), stat.size, stream).await?;
+	}
+
+	for doc_id in app.crdt_adapter.list_docs(tn_id).await? {
+		let updates: Vec<ArchivedUpdate> = app
+			.crdt_adapter
+			.get_updates(tn_id, &doc_id)
+			.await?
+			.into_iter()
+			.map(|u| ArchivedUpdate {
+				data: base64::engine::general_purpose::STANDARD.encode(&u.data),
+				client_id: u.client_id,
+			})
+			.collect();
+		archive
+			.add(&format!("crdt/{}.json", doc_id), &serde_json::to_vec(&updates)?)
+			.await?;
+	}
+
+	// The RTDB has no listing of its own; its databases are the tenant's RTDB files,
+	// trashed ones included.
+	let mut cursor: Option<String> = None;
+	loop {
+		let opts = ListFileOptions {
+			limit: Some(PAGE),
+			cursor: cursor.clone(),
+			file_type: Some(vec!["RTDB".to_owned()]),
+			local_only: true,
+			include_tree_children: true,
+			sweep_all: true,
+			..Default::default()
+		};
+		let files = app.meta_adapter.list_files(tn_id, &opts).await?;
+		for file in &files {
+			let docs = app.rtdb_adapter.export_all(tn_id, &file.file_id).await?;
+			if !docs.is_empty() {
+				archive
+					.add(&format!("rtdb/{}.json", file.file_id), &serde_json::to_vec(&docs)?)
+					.await?;
+			}
+		}
+
+		let Some(last) = files.last().filter(|_| files.len() >= PAGE as usize) else {
+			break;
+		};
+		cursor = Some(CursorData::new("created", last.created_at.0.into(), &last.file_id).encode());
+	}
+
+	archive.finish(&id_tag).await?;
+	info!(tn_id = %tn_id, %id_tag, "tenant backup written");
+	Ok(())
+}
+
+// Restore
+//*********
+
+/// Check every entry of the archive at `path` against its manifest, before
+/// anything is written.
+async fn verify(path: &FsPath) -> ClResult<Manifest> {
+	let mut reader = ArchiveReader::new(BufReader::new(tokio::fs::File::open(path).await?));
+	let mut found = Vec::new();
+	let mut manifest: Option<Manifest> = None;
+	while let Some(entry) = reader.next().await? {
+		if entry.path == MANIFEST {
+			manifest = Some(reader.read_json(&entry).await?);
+		} else {
+			Entry::parse(&entry.path)?;
+			let hash = reader.hash(entry.size).await?;
+			found.push(ManifestEntry { path: entry.path.into(), size: entry.size, hash });
+		}
+	}
+
+	let manifest = manifest
+		.ok_or_else(|| Error::ValidationError("backup has no manifest; it is incomplete".into()))?;
+	if &*manifest.format != FORMAT || manifest.version != VERSION {
+		return Err(Error::ValidationError(format!(
+			"unsupported backup format {} version {}",
+			manifest.format, manifest.version
+		)));
+	}
+	if manifest.entries.len() != found.len() {
+		return Err(Error::ValidationError("backup entries do not match its manifest".into()));
+	}
+	if let Some((_, entry)) = manifest.entries.iter().zip(&found).find(|(want, got)| want != got) {
+		return Err(Error::ValidationError(format!("backup entry {} is corrupt", entry.path)));
+	}
+	Ok(manifest)
+}
+
+/// Load the verified archive at `path`. `tn_id` is set as soon as the tenant
+/// exists, so the caller knows what to clean up if this fails.
+async fn load(app: &App, path: &FsPath, tn_id: &mut Option<TnId>) -> ClResult<()> {
+	let mut reader = ArchiveReader::new(BufReader::new(tokio::fs::File::open(path).await?));
+	let mut meta_loaded = false;
+	let out_of_order = || Error::ValidationError("backup entries are out of order".into());
+
+	while let Some(entry) = reader.next().await? {
+		if entry.path == MANIFEST {
+			reader.read_to_vec(entry.size).await?;
+			continue;
+		}
+		match Entry::parse(&entry.path)? {
+			Entry::Auth => {
+				if tn_id.is_some() {
+					return Err(out_of_order());
+				}
+				let tables: Vec<TableDump> = reader.read_json(&entry).await?;
+				*tn_id = Some(app.auth_adapter.import_tenant(&tables).await?);
+			}
+			Entry::Meta => {
+				let tn_id = tn_id.ok_or_else(out_of_order)?;
+				let tables: Vec<TableDump> = reader.read_json(&entry).await?;
+				app.meta_adapter.import_tenant(tn_id, &tables).await?;
+				meta_loaded = true;
+			}
+			_ if !meta_loaded => return Err(out_of_order()),
+			Entry::Blob(blob_id) => {
+				let tn_id = tn_id.ok_or_else(out_of_order)?;
+				let mut body = (&mut reader.input).take(entry.size);
+				// The blob adapter checks the content against its id
+				app.blob_adapter.create_blob_stream(tn_id, blob_id, &mut body).await?;
+				reader.skip_padding(entry.size).await?;
+			}
+			Entry::Crdt(doc_id) => {
+				let tn_id = tn_id.ok_or_else(out_of_order)?;
+				let updates: Vec<ArchivedUpdate> = reader.read_json(&entry).await?;
+				for update in updates {
+					let data = base64::engine::general_purpose::STANDARD
+						.decode(&update.data)
+						.map_err(|_| {
+							Error::ValidationError(format!(
+								"backup entry {} is malformed",
+								entry.path
+							))
+						})?;
+					let update = CrdtUpdate { data, client_id: update.client_id, seq: None };
+					app.crdt_adapter.store_update(tn_id, doc_id, update).await?;
+				}
+			}
+			Entry::Rtdb(db_id) => {
+				let tn_id = tn_id.ok_or_else(out_of_order)?;
+				let docs: Vec<(Box<str>, serde_json::Value)> = reader.read_json(&entry).await?;
+				let mut tx = app.rtdb_adapter.transaction(tn_id, db_id).await?;
+				for (path, doc) in docs {
+					tx.update(&path, doc).await?;
+				}
+				tx.commit().await?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Restore the backup archive at `path` as a new tenant, and return its id and
+/// ID tag.
+pub async fn restore_tenant(app: &App, path: &FsPath) -> ClResult<(TnId, Box<str>)> {
+	let manifest = verify(path).await?;
+
+	let mut tn_id = None;
+	if let Err(err) = load(app, path, &mut tn_id).await {
+		if let Some(tn_id) = tn_id {
+			warn!(tn_id = %tn_id, id_tag = %manifest.id_tag, error = ?err,
+				"tenant restore failed; removing what was loaded");
+			if let Err(e) = crate::tenant::purge_tenant(app, tn_id).await {
+				warn!(tn_id = %tn_id, id_tag = %manifest.id_tag, error = ?e,
+					"cleanup after failed tenant restore failed");
+			}
+		}
+		return Err(err);
+	}
+	let tn_id = tn_id.ok_or_else(|| Error::ValidationError("backup holds no tenant".into()))?;
+
+	// Non-fatal from here on: the tenant is restored.
+	if let Err(e) = app
+		.scheduler
+		.task(std::sync::Arc::new(ReindexTask { scope: ReindexScope::Tenant { tn_id } }))
+		.key(format!("search.reindex:{}", tn_id.0))
+		.with_retry(RetryPolicy::default())
+		.now()
+		.await
+	{
+		warn!(tn_id = %tn_id, error = ?e, "tenant restore: scheduling search reindex failed");
+	}
+	if let Err(e) = cloudillo_core::reload_site_cache_for_tenant(app, tn_id).await {
+		warn!(tn_id = %tn_id, error = ?e, "tenant restore: site cache reload failed");
+	}
+
+	info!(tn_id = %tn_id, id_tag = %manifest.id_tag, "tenant restored");
+	Ok((tn_id, manifest.id_tag))
+}
+
+// Handlers
+//**********
+
+/// GET /api/admin/tenants/{id_tag}/backup - Stream a backup archive of a tenant
+///
+/// The archive is written while it downloads, so a failure midway can only cut
+/// it short — and a cut-short archive has no manifest, which restore rejects.
+#[axum::debug_handler]
+pub async fn get_tenant_backup(
+	State(app): State<App>,
+	Path(id_tag): Path<String>,
+) -> ClResult<Response<Body>> {
+	let tn_id = app.auth_adapter.read_tn_id(&id_tag).await?;
+	info!(tn_id = %tn_id, %id_tag, "GET /api/admin/tenants/:id_tag/backup - Exporting tenant");
+
+	let (reader, writer) = tokio::io::duplex(64 * 1024);
+	tokio::spawn(async move {
+		if let Err(e) = export_tenant(&app, tn_id, writer).await {
+			warn!(tn_id = %tn_id, error = ?e, "tenant backup failed");
+		}
+	});
+
+	Ok(Response::builder()
+		.status(StatusCode::OK)
+		.header(header::CONTENT_TYPE, "application/x-tar")
+		.header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.tar\"", id_tag))
+		.body(Body::from_stream(ReaderStream::new(reader)))?)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreTenantResponse {
+	pub tn_id: u32,
+	pub id_tag: String,
+}
+
+/// POST /api/admin/tenants/restore - Restore a tenant from a backup archive
+///
+/// The archive is spooled to disk first: it is read twice, once to verify and
+/// once to load.
+#[axum::debug_handler]
+pub async fn post_tenant_restore(
+	State(app): State<App>,
+	body: Body,
+) -> ClResult<(StatusCode, Json<ApiResponse<RestoreTenantResponse>>)> {
+	let path = app
+		.opts
+		.tmp_dir
+		.join(format!("restore_{}", cloudillo_types::utils::random_id()?));
+
+	let res = async {
+		let mut file = tokio::fs::File::create(&path).await?;
+		let mut stream = body.into_data_stream();
+		while let Some(chunk) = stream.next().await {
+			let chunk = chunk.map_err(|e| Error::Internal(format!("body read error: {}", e)))?;
+			file.write_all(&chunk).await?;
+		}
+		file.flush().await?;
+		restore_tenant(&app, &path).await
+	}
+	.await;
+	if let Err(e) = tokio::fs::remove_file(&path).await
+		&& e.kind() != std::io::ErrorKind::NotFound
+	{
+		warn!("tenant restore: removing {:?} failed: {}", path, e);
+	}
+	let (tn_id, id_tag) = res?;
+
+	let response =
+		ApiResponse::new(RestoreTenantResponse { tn_id: tn_id.0, id_tag: id_tag.into() });
+	Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_entry_parse_accepts_only_archive_layout() {
+		assert_eq!(Entry::parse("auth.json").ok(), Some(Entry::Auth));
+		assert_eq!(Entry::parse("blobs/b1~abc").ok(), Some(Entry::Blob("b1~abc")));
+		assert_eq!(Entry::parse("crdt/f1~doc.json").ok(), Some(Entry::Crdt("f1~doc")));
+		assert_eq!(Entry::parse("rtdb/f1~db.json").ok(), Some(Entry::Rtdb("f1~db")));
+
+		for path in ["blobs/", "blobs/../x", "crdt/f1~doc", "rtdb/.json", "other.json"] {
+			assert!(Entry::parse(path).is_err(), "{path}");
+		}
+	}
+
+	#[tokio::test]
+	async fn test_archive_round_trips_and_hashes_entries() {
+		let mut bytes = Vec::new();
+		let mut archive = ArchiveWriter::new(&mut bytes);
+		archive.add("auth.json", b"[]").await.unwrap();
+		let blob = vec![7u8; 1000];
+		let stream = futures::stream::iter(
+			blob.chunks(300)
+				.map(|c| Ok(axum::body::Bytes::copy_from_slice(c)))
+				.collect::<Vec<_>>(),
+		);
+		archive.add_stream("blobs/b1~x", 1000, stream).await.unwrap();
+		archive.finish("alice.example").await.unwrap();
+		assert_eq!(bytes.len() % BLOCK, 0);
+
+		let mut reader = ArchiveReader::new(bytes.as_slice());
+		let auth = reader.next().await.unwrap().unwrap();
+		assert_eq!((auth.path.as_str(), auth.size), ("auth.json", 2));
+		assert_eq!(reader.read_to_vec(auth.size).await.unwrap(), b"[]");
+
+		let blob_entry = reader.next().await.unwrap().unwrap();
+		let mut hasher = Hasher::new();
+		hasher.update(&blob);
+		assert_eq!(&*reader.hash(blob_entry.size).await.unwrap(), hasher.finalize("").as_str());
+
+		let manifest_entry = reader.next().await.unwrap().unwrap();
+		let manifest: Manifest = reader.read_json(&manifest_entry).await.unwrap();
+		assert_eq!(&*manifest.id_tag, "alice.example");
+		assert_eq!(manifest.entries.len(), 2);
+		assert_eq!(manifest.entries[1].size, 1000);
+		assert!(reader.next().await.unwrap().is_none());
+	}
+}
+
+// vim: ts=4
@@ -23,8 +23,10 @@ use cloudillo_ref::service::{CreateRefInternalParams, create_ref_internal};
 use cloudillo_types::{
 	action_types::ACCESS_TOKEN_EXPIRY,
 	auth_adapter::{self, ListTenantsOptions},
-	meta_adapter::{ListRefsOptions, PASSWORD_REF_TYPE, SHARE_FILE_REF_TYPE, WELCOME_REF_TYPE},
-	types::{AccessLevel, ApiResponse},
+	meta_adapter::{
+		ListRefsOptions, PASSWORD_REF_TYPE, RefData, SHARE_FILE_REF_TYPE, WELCOME_REF_TYPE,
+	},
+	types::{AccessLevel, ApiResponse, TokenScope},
 	utils::decode_jwt_no_verify,
 };
 
@@ -299,6 +301,114 @@ fn derived_sub(auth: &auth_adapter::AuthCtx) -> Option<&str> {
 	(!auth.anonymous).then_some(&*auth.id_tag)
 }
 
+/// Map a failure to validate or use a share link ref to what its holder may learn.
+fn share_link_error(ref_id: &str, what: &str, e: Error) -> Error {
+	warn!("Failed to {} ref {}: {}", what, ref_id, e);
+	match e {
+		Error::NotFound => Error::ValidationError("Invalid or expired share link".into()),
+		Error::ValidationError(_) => e,
+		_ => Error::ValidationError("Invalid share link".into()),
+	}
+}
+
+/// Whether the caller holds a token issued for the share link `ref_data` — its
+/// scope is exactly the one this link mints: same file, same access and, for a
+/// file drop, the same link digest. A token of another link on the same file
+/// (an unprotected view-only one, say) does not stand in for this link's password.
+fn holds_link_scope(
+	auth: Option<&auth_adapter::AuthCtx>,
+	ref_id: &str,
+	ref_data: &RefData,
+) -> bool {
+	let (Some(scope), Some(file_id)) =
+		(auth.and_then(|auth| auth.scope.as_deref()), ref_data.resource_id.as_deref())
+	else {
+		return false;
+	};
+	share_link_scope(ref_id, file_id, ref_data).is_ok_and(|(link_scope, _)| scope == link_scope)
+}
+
+/// The scope a share link's token carries, and the access level it reads at.
+///
+/// - `file:{id}:{R|C|W}` — a plain link at its `access_level`
+/// - `file:{id}:V` — view only, at `Read`
+/// - `file:{id}:U:{link}` — a file drop, which reads nothing; `link` is the ref's
+///   redacted digest, which the uploads it makes carry as their attribution
+fn share_link_scope(
+	ref_id: &str,
+	file_id: &str,
+	ref_data: &RefData,
+) -> ClResult<(String, AccessLevel)> {
+	match ref_data.link_mode {
+		Some('V') => Ok((format!("file:{}:V", file_id), AccessLevel::Read)),
+		Some('U') => {
+			let link = cloudillo_types::hasher::hash("r", ref_id.as_bytes());
+			Ok((format!("file:{}:U:{}", file_id, link), AccessLevel::None))
+		}
+		None => {
+			// `to_scope_char` caps admin at 'W', so a ref that somehow carried 'A' cannot emit
+			// `file:{id}:A` — which `TokenScope::parse` rejects outright, silently denying the
+			// link.
+			let access_level = AccessLevel::from_perm_char(ref_data.access_level.unwrap_or('R'));
+			// `from_perm_char` never returns `None`, so `to_scope_char`'s `None` arm is
+			// unreachable; deny rather than mint an unsupported scope.
+			let scope_char = access_level.to_scope_char().ok_or(Error::PermissionDenied)?;
+			Ok((format!("file:{}:{}", file_id, scope_char), access_level))
+		}
+		// A mode this build does not know grants nothing.
+		Some(_) => Err(Error::PermissionDenied),
+	}
+}
+
+/// Mint the anonymous, scoped access token a redeemed share link stands for.
+async fn issue_share_link_token(
+	app: &App,
+	tn_id: TnId,
+	id_tag: &str,
+	ref_id: &str,
+	ref_data: &RefData,
+) -> ClResult<serde_json::Value> {
+	// Extract resource_id (file_id) and access_level
+	let file_id = ref_data
+		.resource_id
+		.as_deref()
+		.ok_or_else(|| Error::ValidationError("Share link missing resource_id".into()))?;
+	let (scope, access_level) = share_link_scope(ref_id, file_id, ref_data)?;
+	debug!("Creating scoped access token with scope={}", scope);
+
+	let token_result = app
+		.auth_adapter
+		.create_access_token(
+			tn_id,
+			&auth_adapter::AccessToken {
+				iss: id_tag,
+				sub: None, // Anonymous/guest access
+				r: None,   // No roles for share link access
+				scope: Some(&scope),
+				exp: Timestamp::from_now(ACCESS_TOKEN_EXPIRY),
+			},
+		)
+		.await?;
+
+	info!("Issued access token: id_tag={} sub=anonymous scope={} via=ref_id", id_tag, scope);
+	let mut result = json!({
+		"token": token_result,
+		"scope": scope,
+		"resourceId": file_id,
+		// Same cap as the scope: a share link never reports admin.
+		"accessLevel": access_level.min(AccessLevel::Write).as_str(),
+	});
+	match ref_data.link_mode {
+		Some('V') => result["linkMode"] = json!("view"),
+		Some('U') => result["linkMode"] = json!("upload"),
+		_ => {}
+	}
+	if let Some(ref params) = ref_data.params {
+		result["params"] = json!(params);
+	}
+	Ok(result)
+}
+
 pub async fn get_access_token(
 	State(app): State<App>,
 	tn_id: TnId,
@@ -314,8 +424,6 @@ pub async fn get_access_token(
 
 	// Cross-document link: get scoped token for target file via source file
 	if let Some(ref via_file_id) = query.via {
-		use cloudillo_types::types::TokenScope;
-
 		// Requires scope param: "file:{target_file_id}:{R|W}"
 		let scope_str = query
 			.scope
@@ -513,26 +621,13 @@ pub async fn get_access_token(
 		let is_refresh = query.refresh.unwrap_or(false);
 		debug!("Exchanging ref_id {} for scoped access token (refresh={})", ref_id, is_refresh);
 
-		// For refresh: validate without decrementing counter
-		// For initial access: validate and decrement counter
-		let (ref_tn_id, _ref_id_tag, ref_data) = if is_refresh {
-			app.meta_adapter.validate_ref(&ref_id, &[SHARE_FILE_REF_TYPE]).await
-		} else {
-			app.meta_adapter.use_ref(&ref_id, &[SHARE_FILE_REF_TYPE]).await
-		}
-		.map_err(|e| {
-			warn!(
-				"Failed to {} ref {}: {}",
-				if is_refresh { "validate" } else { "use" },
-				ref_id,
-				e
-			);
-			match e {
-				Error::NotFound => Error::ValidationError("Invalid or expired share link".into()),
-				Error::ValidationError(_) => e,
-				_ => Error::ValidationError("Invalid share link".into()),
-			}
-		})?;
+		// Validate first: a password-protected link is redeemed through
+		// `POST /api/auth/share-link`, and refusing it here must not use it up.
+		let (ref_tn_id, _ref_id_tag, ref_data) = app
+			.meta_adapter
+			.validate_ref(&ref_id, &[SHARE_FILE_REF_TYPE])
+			.await
+			.map_err(|e| share_link_error(&ref_id, "validate", e))?;
 
 		// Validate ref belongs to this tenant
 		if ref_tn_id != tn_id {
@@ -543,46 +638,28 @@ pub async fn get_access_token(
 			return Err(Error::PermissionDenied);
 		}
 
-		// Extract resource_id (file_id) and access_level
-		let file_id = ref_data
-			.resource_id
-			.ok_or_else(|| Error::ValidationError("Share link missing resource_id".into()))?;
-		// `to_scope_char` caps admin at 'W', so a ref that somehow carried 'A' cannot emit
-		// `file:{id}:A` — which `TokenScope::parse` rejects outright, silently denying the link.
-		let access_level = AccessLevel::from_perm_char(ref_data.access_level.unwrap_or('R'));
-
-		// Scope format: "file:{file_id}:{R|C|W}". `from_perm_char` never returns `None`, so
-		// `to_scope_char`'s `None` arm is unreachable; deny rather than mint an unsupported scope.
-		let scope_char = access_level.to_scope_char().ok_or(Error::PermissionDenied)?;
-		let scope = format!("file:{}:{}", file_id, scope_char);
-		debug!("Creating scoped access token with scope={}", scope);
+		// A refresh of a password-protected link is honored only for the holder of a
+		// token it already issued — the password is not asked again every expiry.
+		if ref_data.password_protected
+			&& !(is_refresh && holds_link_scope(maybe_auth.as_ref(), &ref_id, &ref_data))
+		{
+			return Err(Error::PreconditionRequired("share link requires a password".into()));
+		}
 
-		let token_result = app
-			.auth_adapter
-			.create_access_token(
-				tn_id,
-				&auth_adapter::AccessToken {
-					iss: &id_tag.0,
-					sub: None, // Anonymous/guest access
-					r: None,   // No roles for share link access
-					scope: Some(&scope),
-					exp: Timestamp::from_now(ACCESS_TOKEN_EXPIRY),
-				},
-			)
-			.await?;
+		// For refresh: validate without decrementing counter
+		// For initial access: validate and decrement counter
+		let ref_data = if is_refresh {
+			ref_data
+		} else {
+			let (_, _, ref_data) = app
+				.meta_adapter
+				.use_ref(&ref_id, &[SHARE_FILE_REF_TYPE])
+				.await
+				.map_err(|e| share_link_error(&ref_id, "use", e))?;
+			ref_data
+		};
 
-		info!("Issued access token: id_tag={} sub=anonymous scope={} via=ref_id", id_tag.0, scope);
-		debug!("Got scoped access token for share link");
-		let mut result = json!({
-			"token": token_result,
-			"scope": scope,
-			"resourceId": file_id.to_string(),
-			// Same cap as the scope: a share link never reports admin.
-			"accessLevel": access_level.min(AccessLevel::Write).as_str(),
-		});
-		if let Some(ref params) = ref_data.params {
-			result["params"] = json!(params);
-		}
+		let result = issue_share_link_token(&app, tn_id, &id_tag.0, &ref_id, &ref_data).await?;
 		let response = ApiResponse::new(result).with_req_id(req_id.unwrap_or_default());
 		Ok((StatusCode::OK, Json(response)))
 	} else if let Some(api_key) = query.api_key {
@@ -702,6 +779,54 @@ pub async fn get_access_token(
 	}
 }
 
+/// # POST /api/auth/share-link
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLinkReq {
+	ref_id: String,
+	password: String,
+}
+
+/// Redeem a password-protected share link for the same token
+/// `GET /api/auth/access-token?refId=` mints for an open one. A wrong password is
+/// penalized like a failed login, so guessing runs into the same auth ban.
+pub async fn post_share_link(
+	State(app): State<App>,
+	tn_id: TnId,
+	IdTag(id_tag): IdTag,
+	ConnectInfo(addr): ConnectInfo<SocketAddr>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<ShareLinkReq>,
+) -> ClResult<(StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+	if app.meta_adapter.check_ref_password(&req.ref_id, &req.password).await.is_err() {
+		if let Err(e) = app.rate_limiter.penalize(&addr.ip(), PenaltyReason::AuthFailure, 1) {
+			warn!("Failed to record auth penalty for {}: {}", addr.ip(), e);
+		}
+		tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+		return Err(Error::PermissionDenied);
+	}
+
+	// Check the tenant before using the link up.
+	let (ref_tn_id, _ref_id_tag, _) = app
+		.meta_adapter
+		.validate_ref(&req.ref_id, &[SHARE_FILE_REF_TYPE])
+		.await
+		.map_err(|e| share_link_error(&req.ref_id, "validate", e))?;
+	if ref_tn_id != tn_id {
+		warn!("Ref tenant mismatch: ref belongs to {:?} but request is for {:?}", ref_tn_id, tn_id);
+		return Err(Error::PermissionDenied);
+	}
+	let (_, _, ref_data) = app
+		.meta_adapter
+		.use_ref(&req.ref_id, &[SHARE_FILE_REF_TYPE])
+		.await
+		.map_err(|e| share_link_error(&req.ref_id, "use", e))?;
+
+	let result = issue_share_link_token(&app, tn_id, &id_tag, &req.ref_id, &ref_data).await?;
+	let response = ApiResponse::new(result).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
 /// # GET /api/auth/proxy-token
 /// Generate a proxy token for federation (allows this user to authenticate on behalf of the server)
 /// If `idTag` query parameter is provided and different from the current server, this will
@@ -1224,6 +1349,44 @@ mod tests {
 		// there is no person to name.
 		assert_eq!(derived_sub(&auth_ctx(true, Some("file:f1~abc:R"))), None);
 	}
+
+	fn link(access_level: char, link_mode: Option<char>) -> RefData {
+		RefData {
+			ref_id: "r1".into(),
+			r#type: SHARE_FILE_REF_TYPE.into(),
+			description: None,
+			created_at: Timestamp(0),
+			expires_at: None,
+			count: None,
+			resource_id: Some("f1~abc".into()),
+			access_level: Some(access_level),
+			params: None,
+			link_mode,
+			password_protected: true,
+		}
+	}
+
+	#[test]
+	fn link_refresh_needs_this_links_own_scope() {
+		let protected = link('W', None);
+		let own = auth_ctx(true, Some("file:f1~abc:W"));
+		assert!(holds_link_scope(Some(&own), "ref-b", &protected));
+
+		// A token from another link on the same file — view only, or read — must not
+		// refresh the password-protected write link.
+		let view_only = auth_ctx(true, Some("file:f1~abc:V"));
+		assert!(!holds_link_scope(Some(&view_only), "ref-b", &protected));
+		let read = auth_ctx(true, Some("file:f1~abc:R"));
+		assert!(!holds_link_scope(Some(&read), "ref-b", &protected));
+		assert!(!holds_link_scope(None, "ref-b", &protected));
+
+		// A file drop is bound to its own link digest.
+		let drop = link('R', Some('U'));
+		let (drop_a, _) = share_link_scope("ref-a", "f1~abc", &drop).unwrap();
+		let from_a = auth_ctx(true, Some(&drop_a));
+		assert!(holds_link_scope(Some(&from_a), "ref-a", &drop));
+		assert!(!holds_link_scope(Some(&from_a), "ref-b", &drop));
+	}
 }
 
 // vim: ts=4
@@ -393,6 +393,19 @@ pub async fn put_object(
 	Ok((StatusCode::OK, Json(resp)))
 }
 
+/// Create or replace an object in calendar `cal_id` from outside the REST API, with the
+/// semantics of `PUT …/{uid}`. `cloudillo-action` files the EVENTs a user RSVPs to through
+/// this, keyed by the event's action id so a changed answer replaces the same object.
+pub async fn upsert_object(
+	app: &App,
+	tn_id: TnId,
+	cal_id: u64,
+	input: CalendarObjectInput,
+) -> ClResult<CalendarObjectOutput> {
+	app.meta_adapter.get_calendar(tn_id, cal_id).await?.ok_or(Error::NotFound)?;
+	write_object(app, tn_id, cal_id, input).await
+}
+
 /// Apply an `EventPatch` onto an existing `EventInput` in place.
 ///
 /// Scalar fields replace when `Some(_)`, keep when `None`. Collection fields (`attendees`,
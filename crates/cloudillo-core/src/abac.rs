@@ -556,7 +556,9 @@ impl PermissionChecker {
 			return self.check_visibility(subject, object);
 		}
 
-		// Create operations - check quota/limits in future
+		// Create operations. The storage quota is not checked here: an object has
+		// no size, so the upload paths check it once the size is known (see
+		// `crate::storage_quota::check`).
 		if operation == "create" {
 			debug!(subject = %subject.id_tag, action = action, "Create operation allowed");
 			return true; // Allow for now
@@ -131,6 +131,9 @@ impl BundledAppRegistry {
 					store_tp: ct.store_tp.as_deref().map(Into::into),
 					nav_param: ct.nav_param.as_deref().map(Into::into),
 					search: ct.search.clone(),
+					// Cron triggers are scheduled when a tenant registers its manifest,
+					// and a bundled entry is never registered — so it declares none.
+					triggers: None,
 					x: None,
 					updated_at: *updated_at,
 				};
@@ -525,6 +528,7 @@ mod tests {
 			store_tp: Some("RTDB".into()),
 			nav_param: Some(nav.into()),
 			search: Some(search),
+			triggers: None,
 			x: None,
 			updated_at: Timestamp(0),
 		};
@@ -152,13 +152,13 @@ async fn load_subject_attrs(
 		"free".into()
 	};
 
-	// Calculate quota remaining (in bytes)
-	// TODO: Query from meta_adapter to get user's actual used quota
-	let quota_bytes = match tier.as_ref() {
-		"premium" => 1024 * 1024 * 1024, // 1GB
-		"standard" => 100 * 1024 * 1024, // 100MB
-		_ => 10 * 1024 * 1024,           // 10MB (free tier)
-	};
+	// Storage left in the tenant being written to — a quota is per tenant, not
+	// per user. Unlimited reads as the largest value, so a `quota_remaining > n`
+	// policy holds for it.
+	let quota_bytes = crate::storage_quota::read(app, auth_ctx.tn_id)
+		.await?
+		.remaining()
+		.unwrap_or(u64::MAX);
 
 	// Get rate limit remaining (per hour)
 	// TODO: Query from meta_adapter or time-based tracker for actual rate limit tracking
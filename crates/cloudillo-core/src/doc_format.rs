@@ -185,6 +185,7 @@ mod tests {
 			store_tp: Some("CRDT".into()),
 			nav_param: Some(nav_param.into()),
 			search: None,
+			triggers: None,
 			x: None,
 			updated_at: Timestamp(0),
 		}
@@ -326,7 +326,9 @@ pub async fn get_access_level(
 /// Get access level for a user on a file, considering scoped tokens
 ///
 /// Determines access level based on:
-/// 1. Scoped token — file:{file_id}:{R|C|W} grants Read/Comment/Write access
+/// 1. Scoped token — file:{file_id}:{R|C|W} grants Read/Comment/Write access,
+///    file:{file_id}:V grants Read (the original-download ban is the file handlers'),
+///    a file drop grants nothing
 ///    (also checks document tree: a token for a root grants access to children)
 /// 2. Everything [`get_access_level`] resolves, in its order
 /// 3. No access — returns None
@@ -343,19 +345,20 @@ pub async fn get_access_level_with_scope(
 	if let Some(scope_str) = scope {
 		// Use typed TokenScope for safe parsing
 		if let Some(token_scope) = TokenScope::parse(scope_str) {
-			match &token_scope {
-				TokenScope::File { file_id: scope_file_id, access } => {
+			// A view-only link reads like a `Read` one; a file drop reads nothing.
+			match token_scope.file_grant() {
+				Some((scope_file_id, access)) => {
 					// Direct match: scope matches this file_id
 					if scope_file_id == file_id {
-						return *access;
+						return access;
 					}
 
 					// Document tree check: scope is for a root, this file is a child
 					// Depth-1 invariant: root_id always points directly to a top-level file
 					if let Some(root) = root_id
-						&& scope_file_id.as_str() == root
+						&& scope_file_id == root
 					{
-						return *access;
+						return access;
 					}
 
 					// Cross-document link: file-type share entry ('F')
@@ -368,7 +371,7 @@ pub async fn get_access_level_with_scope(
 						.await
 					{
 						// Cap at min(scope_access, share_permission)
-						return (*access).min(AccessLevel::from_perm_char(perm));
+						return access.min(AccessLevel::from_perm_char(perm));
 					}
 
 					// Folder share: scope targets a folder; grant the scope's level
@@ -396,7 +399,7 @@ pub async fn get_access_level_with_scope(
 							.await
 							.unwrap_or(false);
 						if nested_under_scope {
-							return *access;
+							return access;
 						}
 					} else {
 						warn!("DirCache extension missing; folder-share scope grant skipped");
@@ -405,8 +408,8 @@ pub async fn get_access_level_with_scope(
 					// Scope exists for a different file - deny access
 					return AccessLevel::None;
 				}
-				TokenScope::ApkgPublish => {
-					// APKG publish scope has no file access
+				None => {
+					// File drop and APKG publish scopes have no file access
 					return AccessLevel::None;
 				}
 			}
@@ -519,21 +522,21 @@ pub fn check_scope_allows_file(
 	let Some(scope_str) = scope else { return ScopeCheck::NoScope };
 	// If a scope string is present but can't be parsed, deny access (least privilege)
 	let Some(token_scope) = TokenScope::parse(scope_str) else { return ScopeCheck::Denied };
-	match &token_scope {
-		TokenScope::File { file_id: scope_file_id, access } => {
+	match token_scope.file_grant() {
+		Some((scope_file_id, access)) => {
 			// Direct match: scope matches this file_id
 			if scope_file_id == file_id {
-				return ScopeCheck::Allowed(*access);
+				return ScopeCheck::Allowed(access);
 			}
 			// Document tree check: scope is for a root, this file is a child
 			if let Some(root) = root_id
-				&& scope_file_id.as_str() == root
+				&& scope_file_id == root
 			{
-				return ScopeCheck::Allowed(*access);
+				return ScopeCheck::Allowed(access);
 			}
 			ScopeCheck::Denied
 		}
-		TokenScope::ApkgPublish => ScopeCheck::Denied,
+		None => ScopeCheck::Denied,
 	}
 }
 
@@ -550,6 +553,10 @@ pub fn check_scope_allows_file(
 /// - `parent_id == scope_file_id` (direct child of the shared folder)
 /// - `parent_id` is a descendant of `scope_file_id` (nested subfolder)
 ///
+/// A file drop scope allows exactly one placement: a top-level file directly in
+/// the drop folder. Dropped files never join a document tree or a subfolder the
+/// dropper could not see anyway.
+///
 /// Returns `Ok(())` if allowed, `Err(Error::PermissionDenied)` if denied.
 pub async fn check_scope_allows_create_in(
 	meta: &Arc<dyn meta_adapter::MetaAdapter>,
@@ -586,6 +593,16 @@ pub async fn check_scope_allows_create_in(
 			}
 			Err(Error::PermissionDenied)
 		}
+		TokenScope::FileDrop { folder_id, .. } => {
+			if root_id.is_none()
+				&& parent_id == Some(folder_id.as_str())
+				&& scope_target_is_folder(meta, cache, tn_id, folder_id).await?
+			{
+				return Ok(());
+			}
+			Err(Error::PermissionDenied)
+		}
+		TokenScope::FileView { .. } => Err(Error::PermissionDenied),
 		TokenScope::ApkgPublish => Ok(()), // Middleware already restricts to /api/files/apkg/
 	}
 }
@@ -593,19 +610,34 @@ pub async fn check_scope_allows_create_in(
 /// Returns true when a scoped token is itself sufficient authorization for a
 /// collection-level operation, letting the middleware skip the role/quota path.
 ///
-/// A file share link with Write access authorizes file *creation* only; the
-/// file handlers (`check_scope_allows_create_in`) then enforce the scope's
-/// subtree boundary. It must NOT authorize action/app creation, trash emptying,
-/// or any other collection operation.
+/// A file share link with Write access — or a file drop — authorizes file
+/// *creation* only; the file handlers (`check_scope_allows_create_in`) then
+/// enforce the scope's subtree boundary. It must NOT authorize action/app
+/// creation, trash emptying, or any other collection operation.
 pub fn scope_grants_collection_op(scope: Option<&str>, resource_type: &str, action: &str) -> bool {
 	let Some(scope) = scope else { return false };
 	// `Write` is the top of the scope vocabulary — `AccessLevel::to_scope_char` caps `Admin` at
 	// `'W'` and `TokenScope::parse` refuses any other char, so `Admin` is unreachable here.
-	matches!(TokenScope::parse(scope), Some(TokenScope::File { access: AccessLevel::Write, .. }))
-		&& resource_type == "file"
+	matches!(
+		TokenScope::parse(scope),
+		Some(TokenScope::File { access: AccessLevel::Write, .. } | TokenScope::FileDrop { .. })
+	) && resource_type == "file"
 		&& action == "create"
 }
 
+/// Whether `scope` is a view-only share link, to which no original is served.
+pub fn is_view_only_scope(scope: Option<&str>) -> bool {
+	matches!(scope.and_then(TokenScope::parse), Some(TokenScope::FileView { .. }))
+}
+
+/// The folder and link of a file drop scope — `None` for any other scope.
+pub fn drop_scope(scope: Option<&str>) -> Option<(String, String)> {
+	match scope.and_then(TokenScope::parse) {
+		Some(TokenScope::FileDrop { folder_id, link }) => Some((folder_id, link)),
+		_ => None,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -636,6 +668,34 @@ mod tests {
 		assert!(!scope_grants_collection_op(Some("not-a-valid-scope"), "file", "create"));
 	}
 
+	#[test]
+	fn drop_scope_grants_file_create_only() {
+		let s = Some("file:f1~dir:U:r1~link");
+		assert!(scope_grants_collection_op(s, "file", "create"));
+		assert!(!scope_grants_collection_op(s, "action", "create"));
+		assert!(!scope_grants_collection_op(Some("file:f1~abc:V"), "file", "create"));
+	}
+
+	#[test]
+	fn view_scope_reads_its_tree_and_drop_scope_reads_nothing() {
+		let view = Some("file:f1~doc:V");
+		assert!(matches!(
+			check_scope_allows_file(view, "f1~doc", None),
+			ScopeCheck::Allowed(AccessLevel::Read)
+		));
+		assert!(matches!(
+			check_scope_allows_file(view, "f1~child", Some("f1~doc")),
+			ScopeCheck::Allowed(AccessLevel::Read)
+		));
+		assert!(is_view_only_scope(view));
+		assert!(!is_view_only_scope(Some("file:f1~doc:R")));
+
+		let drop = Some("file:f1~dir:U:r1~link");
+		assert!(matches!(check_scope_allows_file(drop, "f1~dir", None), ScopeCheck::Denied));
+		assert_eq!(drop_scope(drop), Some(("f1~dir".to_string(), "r1~link".to_string())));
+		assert_eq!(drop_scope(view), None);
+	}
+
 	#[test]
 	fn role_access_level_requires_a_known_role() {
 		// The federated-stranger case.
@@ -34,6 +34,7 @@ pub mod scheduler;
 pub mod scope;
 pub mod settings;
 pub mod share_access;
+pub mod storage_quota;
 pub mod ws_broadcast;
 pub mod ws_bus;
 
@@ -116,6 +117,32 @@ pub type CreateActionFn = Box<
 /// so a write path must never await or fail on it.
 pub type SearchIndexFn = Box<dyn Fn(&app::App, cloudillo_types::types::TnId, &str) + Send + Sync>;
 
+/// Type-erased function reading a CRDT document back as `(path, value)` pairs, in the
+/// shape [`cloudillo_types::rtdb_adapter::RtdbAdapter::export_all`] returns for an RTDB
+/// one. Registered by the server's app module (delegates to
+/// `cloudillo_search::crdt::export_all`).
+///
+/// Materialising a Yjs update log is the search crate's business, and
+/// `cloudillo-file` — which exports documents into archive downloads — is one of its
+/// dependencies, not a dependent.
+pub type CrdtExportFn = Box<
+	dyn for<'a> Fn(
+			&'a app::App,
+			cloudillo_types::types::TnId,
+			&'a str,
+		) -> Pin<
+			Box<
+				dyn Future<
+						Output = cloudillo_types::error::ClResult<
+							Vec<(Box<str>, serde_json::Value)>,
+						>,
+					> + Send
+					+ 'a,
+			>,
+		> + Send
+		+ Sync,
+>;
+
 /// Type-erased hook asking for one **whole object** — a file, a profile, an action —
 /// to be re-indexed. The counterpart of [`SearchIndexFn`], which covers the deep parts
 /// of a document.
@@ -187,6 +214,43 @@ pub fn search_index_action(app: &app::App, tn_id: cloudillo_types::types::TnId,
 pub type ActionSearchRulesFn =
 	Box<dyn Fn(&str, Option<&str>) -> Option<(Box<str>, Option<serde_json::Value>)> + Send + Sync>;
 
+/// Type-erased hook handing one committed RTDB transaction's writes to the
+/// database's triggers. Registered by the server's app module (delegates to
+/// `cloudillo_action::dsl::trigger::dispatch`).
+///
+/// Exists because the triggers run on the action DSL, and `cloudillo-rtdb` must not
+/// depend on `cloudillo-action`. Same contract as [`SearchIndexFn`]: synchronous and
+/// infallible, the hook only spawns the run, so a write never waits on a trigger.
+pub type RtdbTriggerFn = Box<
+	dyn Fn(
+			&app::App,
+			cloudillo_types::types::TnId,
+			&str,
+			Vec<cloudillo_types::rtdb_adapter::ChangeEvent>,
+		) + Send
+		+ Sync,
+>;
+
+/// Type-erased check of a doc format manifest's `triggers` block, so
+/// `cloudillo-search` can reject a bad one at registration the way it rejects bad
+/// `search` rules. Registered by the server's app module.
+pub type RtdbTriggerCheckFn =
+	Box<dyn Fn(&serde_json::Value) -> cloudillo_types::error::ClResult<()> + Send + Sync>;
+
+/// Type-erased function scheduling the cron triggers of a just-stored `triggers`
+/// block for one `(tenant, content type)`. Registered by the server's app module.
+pub type RtdbTriggerScheduleFn = Box<
+	dyn for<'a> Fn(
+			&'a app::App,
+			cloudillo_types::types::TnId,
+			&'a str,
+			&'a serde_json::Value,
+		) -> Pin<
+			Box<dyn Future<Output = cloudillo_types::error::ClResult<()>> + Send + 'a>,
+		> + Send
+		+ Sync,
+>;
+
 /// Parameters passed to a `ScheduleEmailFn` invocation. Mirrors
 /// `cloudillo_email::EmailTaskParams` but lives in core so the ACME renewal
 /// task (and other core-side tasks) can schedule emails without a cyclic
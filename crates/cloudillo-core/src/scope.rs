@@ -5,8 +5,8 @@
 //!
 //! Two unrelated credential families carry a `scope` string:
 //!
-//! - **Delegated tokens** — share links (`file:{file_id}:{R|C|W}`) and app
-//!   publishing (`apkg:publish`), parsed by
+//! - **Delegated tokens** — share links (`file:{file_id}:{R|C|W|V}`, file drops
+//!   `file:{folder_id}:U:{link}`) and app publishing (`apkg:publish`), parsed by
 //!   [`cloudillo_types::types::TokenScope`].
 //! - **Capability scopes** — the comma-separated `carddav:*` / `caldav:*` list a
 //!   user types into the `scopes` field of `POST /api/auth/api-keys`.
@@ -79,6 +79,23 @@ pub fn scope_permits(scope: Option<&str>, method: &Method, path: &str) -> bool {
 				// a scoped bearer; the bare branch rejects scoped tokens in the handler.
 				|| path == "/api/auth/access-token"
 		}
+		// View only: the read half of the surface above. Which bytes a read may
+		// return (previews, never originals) is the file handlers' call.
+		Some(TokenScope::FileView { .. }) => {
+			is_read_method(method)
+				&& (path.starts_with("/api/files/")
+					|| path == "/api/files"
+					|| path == "/api/search"
+					|| path.starts_with("/ws/rtdb/")
+					|| path.starts_with("/ws/crdt/")
+					|| path == "/api/auth/access-token")
+		}
+		// File drop: uploads, and refreshing the token that makes them. The upload
+		// handler confines the placement to the drop folder itself.
+		Some(TokenScope::FileDrop { .. }) => {
+			(method == Method::POST && path.starts_with("/api/files/"))
+				|| (method == Method::GET && path == "/api/auth/access-token")
+		}
 		// Deliberately narrow: only what app publishing needs, to limit the blast
 		// radius of a compromised token.
 		Some(TokenScope::ApkgPublish) => {
@@ -156,6 +173,33 @@ mod tests {
 		assert!(!scope_permits(s, &Method::PUT, "/api/doc-formats/cloudillo%2Fnotillo"));
 	}
 
+	#[test]
+	fn view_scope_only_reads() {
+		let s = Some("file:f1~abc:V");
+		assert!(scope_permits(s, &Method::GET, "/api/files/f1~abc"));
+		assert!(scope_permits(s, &Method::GET, "/api/files"));
+		assert!(scope_permits(s, &Method::GET, "/ws/crdt/f1~abc"));
+		assert!(scope_permits(s, &Method::GET, "/api/auth/access-token"));
+		assert!(!scope_permits(s, &Method::POST, "/api/files/default/a.jpg"));
+		assert!(!scope_permits(s, &Method::PATCH, "/api/files/f1~abc"));
+		assert!(!scope_permits(s, &Method::DELETE, "/api/files/f1~abc"));
+		assert!(!scope_permits(s, &Method::GET, "/api/actions"));
+	}
+
+	#[test]
+	fn drop_scope_only_uploads() {
+		let s = Some("file:f1~dir:U:r1~link");
+		assert!(scope_permits(s, &Method::POST, "/api/files/default/a.jpg"));
+		assert!(scope_permits(s, &Method::GET, "/api/auth/access-token"));
+		// No listing, no reads, no edits.
+		assert!(!scope_permits(s, &Method::GET, "/api/files"));
+		assert!(!scope_permits(s, &Method::GET, "/api/files/f1~dir"));
+		assert!(!scope_permits(s, &Method::GET, "/ws/crdt/f1~dir"));
+		assert!(!scope_permits(s, &Method::GET, "/api/search"));
+		assert!(!scope_permits(s, &Method::PATCH, "/api/files/f1~dir"));
+		assert!(!scope_permits(s, &Method::POST, "/api/actions"));
+	}
+
 	#[test]
 	fn apkg_scope_stays_on_the_publish_surface() {
 		let s = Some("apkg:publish");
@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Per-tenant storage quota.
+//!
+//! Usage is not a running counter: [`MetaAdapter::read_storage_usage`] derives it
+//! from the tenant's `file_variants` rows on every read, so uploads, deletes,
+//! emptying the trash and the file GC keep it current without any of them having
+//! to account for what they changed. The limit is `limits.max_storage_gb`, a
+//! tenant setting only an admin may change; zero or less means unlimited.
+//!
+//! The setting is registered by `cloudillo-file`. Without it the quota reads as
+//! unlimited, so callers here need not know whether file storage is wired in.
+//!
+//! [`MetaAdapter::read_storage_usage`]: cloudillo_types::meta_adapter::MetaAdapter::read_storage_usage
+
+use cloudillo_types::meta_adapter::StorageUsage;
+
+use crate::prelude::*;
+
+pub const MAX_STORAGE_SETTING: &str = "limits.max_storage_gb";
+
+const BYTES_PER_GIB: u64 = 1 << 30;
+
+/// A tenant's storage usage against its limit.
+#[derive(Debug, Clone)]
+pub struct StorageQuota {
+	pub usage: StorageUsage,
+	/// In bytes. `None` is unlimited.
+	pub limit: Option<u64>,
+}
+
+impl StorageQuota {
+	/// Bytes left before the limit; `None` when unlimited.
+	pub fn remaining(&self) -> Option<u64> {
+		self.limit.map(|limit| limit.saturating_sub(self.usage.total))
+	}
+
+	/// Whether `incoming` more bytes fit. An upload that merely reaches the limit
+	/// still fits; the next one does not.
+	pub fn fits(&self, incoming: u64) -> bool {
+		self.limit
+			.is_none_or(|limit| self.usage.total.saturating_add(incoming) <= limit)
+	}
+
+	/// Usage as a whole percentage of the limit, rounded down; `None` when unlimited.
+	pub fn percent(&self) -> Option<u64> {
+		self.limit.map(|limit| match limit {
+			0 => 100,
+			limit => u64::try_from(u128::from(self.usage.total) * 100 / u128::from(limit))
+				.unwrap_or(u64::MAX),
+		})
+	}
+}
+
+/// The tenant's storage limit in bytes, `None` for unlimited.
+pub async fn read_limit(app: &App, tn_id: TnId) -> ClResult<Option<u64>> {
+	let gib = app.settings.get_int_opt(tn_id, MAX_STORAGE_SETTING).await?;
+	Ok(gib
+		.and_then(|gib| u64::try_from(gib).ok())
+		.filter(|gib| *gib > 0)
+		.map(|gib| gib.saturating_mul(BYTES_PER_GIB)))
+}
+
+pub async fn read(app: &App, tn_id: TnId) -> ClResult<StorageQuota> {
+	let usage = app.meta_adapter.read_storage_usage(tn_id).await?;
+	let limit = read_limit(app, tn_id).await?;
+	Ok(StorageQuota { usage, limit })
+}
+
+/// Refuse a write of `incoming` bytes the tenant's quota has no room for. Pass
+/// zero when the size is not known yet: that still refuses a tenant already over.
+pub async fn check(app: &App, tn_id: TnId, incoming: u64) -> ClResult<()> {
+	let Some(limit) = read_limit(app, tn_id).await? else {
+		return Ok(());
+	};
+	let quota = StorageQuota {
+		usage: app.meta_adapter.read_storage_usage(tn_id).await?,
+		limit: Some(limit),
+	};
+	if quota.fits(incoming) {
+		Ok(())
+	} else {
+		info!(tn_id = %tn_id, used = quota.usage.total, limit, incoming, "Storage quota exceeded");
+		Err(Error::QuotaExceeded)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn quota(total: u64, limit: Option<u64>) -> StorageQuota {
+		StorageQuota { usage: StorageUsage { total, ..Default::default() }, limit }
+	}
+
+	#[test]
+	fn test_an_upload_may_fill_the_quota_but_not_pass_it() {
+		let q = quota(90, Some(100));
+		assert!(q.fits(10));
+		assert!(!q.fits(11));
+		assert_eq!(q.remaining(), Some(10));
+		assert!(!quota(100, Some(100)).fits(1));
+		assert!(quota(100, Some(100)).fits(0));
+	}
+
+	#[test]
+	fn test_over_quota_saturates() {
+		let q = quota(150, Some(100));
+		assert_eq!(q.remaining(), Some(0));
+		assert_eq!(q.percent(), Some(150));
+		assert!(!q.fits(0));
+	}
+
+	#[test]
+	fn test_unlimited_always_fits() {
+		let q = quota(u64::MAX, None);
+		assert!(q.fits(u64::MAX));
+		assert_eq!(q.remaining(), None);
+		assert_eq!(q.percent(), None);
+	}
+}
+
+// vim: ts=4
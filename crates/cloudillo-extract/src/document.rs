@@ -0,0 +1,235 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! The text of an uploaded document, page by page.
+//!
+//! One entry point for every format a file may be stored as: the caller knows a
+//! content type and has the bytes, and gets back the numbered [`Page`]s a search hit
+//! can deep-link to. A PDF's pages are its pages, a deck's are its slides and a
+//! workbook's its sheets; a format with no pages of its own — a word-processor
+//! document, a text file — comes back as a single page, and [`DocumentFormat::unit`]
+//! says so.
+
+use cloudillo_types::prelude::*;
+
+use crate::office::{self, OfficeFormat};
+use crate::plain::{ExtractedText, Flat};
+use crate::{pdf, plain};
+
+/// The formats [`extract_pages`] reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+	Pdf,
+	Office(OfficeFormat),
+	/// Plain text and Markdown.
+	Text,
+}
+
+impl DocumentFormat {
+	/// The format stored under `content_type`, or `None` for one this crate has no
+	/// extractor for. Parameters (`; charset=…`) are ignored.
+	pub fn from_content_type(content_type: &str) -> Option<Self> {
+		let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+		Some(match mime.as_str() {
+			"application/pdf" => Self::Pdf,
+			"application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+				Self::Office(OfficeFormat::Docx)
+			}
+			"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+				Self::Office(OfficeFormat::Xlsx)
+			}
+			"application/vnd.openxmlformats-officedocument.presentationml.presentation" => {
+				Self::Office(OfficeFormat::Pptx)
+			}
+			"application/vnd.oasis.opendocument.text" => Self::Office(OfficeFormat::Odt),
+			"application/vnd.oasis.opendocument.spreadsheet" => Self::Office(OfficeFormat::Ods),
+			"application/vnd.oasis.opendocument.presentation" => Self::Office(OfficeFormat::Odp),
+			"text/plain" | "text/markdown" | "text/x-markdown" => Self::Text,
+			_ => return None,
+		})
+	}
+
+	/// What one [`Page`] of this format is — `"page"`, `"slide"` or `"sheet"` — or
+	/// `None` when the whole document is one page.
+	pub fn unit(self) -> Option<&'static str> {
+		match self {
+			Self::Pdf => Some("page"),
+			Self::Office(OfficeFormat::Pptx | OfficeFormat::Odp) => Some("slide"),
+			Self::Office(OfficeFormat::Xlsx | OfficeFormat::Ods) => Some("sheet"),
+			Self::Office(OfficeFormat::Docx | OfficeFormat::Odt) | Self::Text => None,
+		}
+	}
+
+	/// Largest input [`extract_pages`] accepts for this format.
+	pub fn max_input_bytes(self) -> usize {
+		match self {
+			Self::Pdf => pdf::MAX_INPUT_BYTES,
+			Self::Office(_) => office::MAX_INPUT_BYTES,
+			Self::Text => plain::MAX_INPUT_BYTES,
+		}
+	}
+}
+
+/// One page of extracted text.
+#[derive(Debug, Clone)]
+pub struct Page {
+	/// 1-based, as the reader counts. Pages with no text are left out of the result
+	/// but keep their number, so the numbers are the document's own.
+	pub number: u32,
+	pub text: ExtractedText,
+}
+
+/// How much text a document may contribute, in characters.
+///
+/// Two bounds because they protect different things: `page_chars` what one page row
+/// may hold, `total_chars` what the whole document may hold in memory at once.
+/// Past the total the remaining pages are dropped; the last page returned says
+/// [`ExtractedText::truncated`].
+#[derive(Debug, Clone, Copy)]
+pub struct PageBudget {
+	pub page_chars: usize,
+	pub total_chars: usize,
+}
+
+impl PageBudget {
+	pub fn new(page_chars: usize, total_chars: usize) -> Self {
+		Self { page_chars, total_chars }
+	}
+}
+
+/// Extract the text of a document, page by page.
+///
+/// CPU-bound, and for a PDF a child process: run it off the async runtime. Input
+/// past [`DocumentFormat::max_input_bytes`] and malformed documents are
+/// [`Error::ValidationError`]s — permanent properties of the bytes.
+pub fn extract_pages(
+	format: DocumentFormat,
+	bytes: &[u8],
+	budget: PageBudget,
+) -> ClResult<Vec<Page>> {
+	match format {
+		DocumentFormat::Pdf => pdf::extract_pages(bytes, budget),
+		DocumentFormat::Office(office) => office::extract_pages(bytes, office, budget),
+		DocumentFormat::Text => {
+			let text = plain::extract_text(bytes, budget.page_chars.min(budget.total_chars))?;
+			Ok(if text.text.is_empty() { Vec::new() } else { vec![Page { number: 1, text }] })
+		}
+	}
+}
+
+/// Page-at-a-time collector behind [`PageBudget`], shared by the paged extractors.
+pub(crate) struct Pages {
+	budget: PageBudget,
+	/// Characters still available to the document.
+	remaining: usize,
+	/// Budget of the open page.
+	page_cap: usize,
+	current: Option<Flat>,
+	number: u32,
+	done: Vec<Page>,
+}
+
+impl Pages {
+	pub(crate) fn new(budget: PageBudget) -> Self {
+		Self {
+			budget,
+			remaining: budget.total_chars,
+			page_cap: 0,
+			current: None,
+			number: 0,
+			done: Vec::new(),
+		}
+	}
+
+	/// Open the next page, closing the open one if any.
+	pub(crate) fn begin(&mut self) {
+		self.end();
+		self.number += 1;
+		self.page_cap = self.budget.page_chars.min(self.remaining);
+		self.current = Some(Flat::new(self.page_cap));
+	}
+
+	/// Close the open page. One with no text is dropped, its number spent.
+	pub(crate) fn end(&mut self) {
+		let Some(mut flat) = self.current.take() else { return };
+		let text = flat.take();
+		self.remaining = self.remaining.saturating_sub(text.text.chars().count());
+		if !text.text.is_empty() {
+			self.done.push(Page { number: self.number, text });
+		}
+	}
+
+	pub(crate) fn push(&mut self, text: &str) {
+		if let Some(flat) = self.current.as_mut() {
+			flat.push(text);
+		}
+	}
+
+	pub(crate) fn mark_boundary(&mut self) {
+		if let Some(flat) = self.current.as_mut() {
+			flat.mark_boundary();
+		}
+	}
+
+	/// The open page has reached its budget.
+	pub(crate) fn page_is_full(&self) -> bool {
+		self.current.as_ref().is_some_and(Flat::is_full)
+	}
+
+	/// The document has: nothing more can be written to any page.
+	pub(crate) fn is_full(&self) -> bool {
+		self.remaining == 0 || (self.page_is_full() && self.page_cap == self.remaining)
+	}
+
+	pub(crate) fn finish(mut self) -> Vec<Page> {
+		self.end();
+		self.done
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn content_types_map_to_formats() {
+		assert_eq!(DocumentFormat::from_content_type("application/pdf"), Some(DocumentFormat::Pdf));
+		assert_eq!(
+			DocumentFormat::from_content_type("text/markdown; charset=utf-8"),
+			Some(DocumentFormat::Text)
+		);
+		assert_eq!(DocumentFormat::from_content_type("image/png"), None);
+		assert_eq!(DocumentFormat::Pdf.unit(), Some("page"));
+		assert_eq!(DocumentFormat::Office(OfficeFormat::Docx).unit(), None);
+	}
+
+	#[test]
+	fn the_total_budget_drops_the_tail() {
+		let mut pages = Pages::new(PageBudget::new(4, 6));
+		for text in ["aaaa", "bbbb", "cccc"] {
+			pages.begin();
+			pages.push(text);
+		}
+		let pages = pages.finish();
+		let got: Vec<(u32, &str, bool)> = pages
+			.iter()
+			.map(|p| (p.number, p.text.text.as_str(), p.text.truncated))
+			.collect();
+		assert_eq!(got, vec![(1, "aaaa", false), (2, "bb", true)]);
+	}
+
+	#[test]
+	fn plain_text_is_one_page() {
+		let pages =
+			extract_pages(DocumentFormat::Text, b"notes\n", PageBudget::new(64, 64)).expect("text");
+		assert_eq!(pages.len(), 1);
+		assert_eq!(pages[0].text.text, "notes");
+		assert!(
+			extract_pages(DocumentFormat::Text, b" \n", PageBudget::new(64, 64))
+				.expect("blank")
+				.is_empty()
+		);
+	}
+}
+
+// vim: ts=4
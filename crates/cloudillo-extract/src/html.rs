@@ -10,15 +10,7 @@ use cloudillo_types::prelude::*;
 use lol_html::html_content::TextType;
 use lol_html::{EndTagHandler, HtmlRewriter, Settings, doc_text, element};
 
-/// Flattened visible text, plus whether the budget cut the walk short.
-#[derive(Debug, Default, Clone)]
-pub struct ExtractedText {
-	/// Whitespace-normalised text: no run of blanks, no leading or trailing one.
-	pub text: String,
-	/// `true` when `max_chars` was reached before the end of the input, so the
-	/// caller knows the text is a prefix rather than the whole document.
-	pub truncated: bool,
-}
+use crate::plain::{ExtractedText, Flat};
 
 /// Elements whose start is a word boundary.
 ///
@@ -115,15 +107,9 @@ pub fn extract_text(html: &str, max_chars: usize) -> ClResult<ExtractedText> {
 	Ok(acc.take())
 }
 
-/// Whitespace-normalising text accumulator with a character budget.
+/// [`Flat`], plus the tail of a character reference split across two chunks.
 struct Acc {
-	out: String,
-	budget: usize,
-	chars: usize,
-	truncated: bool,
-	/// A boundary is owed before the next visible character. Held rather than
-	/// written so the result never opens or closes with a blank.
-	pending: bool,
+	flat: Flat,
 	/// A trailing fragment of what may be a character reference, waiting for the
 	/// rest of it to arrive in the next chunk. See [`Acc::push_chunk`].
 	held: String,
@@ -131,14 +117,7 @@ struct Acc {
 
 impl Acc {
 	fn new(budget: usize) -> Self {
-		Self {
-			out: String::new(),
-			budget,
-			chars: 0,
-			truncated: false,
-			pending: false,
-			held: String::new(),
-		}
+		Self { flat: Flat::new(budget), held: String::new() }
 	}
 
 	/// Take one text chunk, resolving its character references *before* they
@@ -163,7 +142,7 @@ impl Acc {
 			text[at..].clone_into(&mut self.held);
 			text.truncate(at);
 		}
-		self.push(&decode_entities(&text));
+		self.flat.push(&decode_entities(&text));
 	}
 
 	/// A word boundary from the document's structure. Any held reference candidate
@@ -171,45 +150,13 @@ impl Acc {
 	/// whatever is held is page text and belongs *before* the boundary.
 	fn separate(&mut self) {
 		self.flush_held();
-		self.mark_boundary();
-	}
-
-	/// The boundary alone, for [`Acc::push`] — flushing the held tail from there
-	/// would re-enter `push` on its own input.
-	fn mark_boundary(&mut self) {
-		if self.chars > 0 {
-			self.pending = true;
-		}
+		self.flat.mark_boundary();
 	}
 
 	fn flush_held(&mut self) {
 		if !self.held.is_empty() {
 			let held = std::mem::take(&mut self.held);
-			self.push(&held);
-		}
-	}
-
-	fn push(&mut self, text: &str) {
-		for c in text.chars() {
-			if self.chars >= self.budget {
-				self.truncated = true;
-				return;
-			}
-			if c.is_whitespace() {
-				self.mark_boundary();
-				continue;
-			}
-			if self.pending {
-				self.out.push(' ');
-				self.chars += 1;
-				self.pending = false;
-				if self.chars >= self.budget {
-					self.truncated = true;
-					return;
-				}
-			}
-			self.out.push(c);
-			self.chars += 1;
+			self.flat.push(&held);
 		}
 	}
 
@@ -217,7 +164,7 @@ impl Acc {
 		// A candidate reference the document ended in the middle of never was one:
 		// it is a bare `&` and whatever followed it, and it is page text.
 		self.flush_held();
-		ExtractedText { text: std::mem::take(&mut self.out), truncated: self.truncated }
+		self.flat.take()
 	}
 }
 
@@ -227,7 +174,7 @@ impl Acc {
 /// escaping has to be undone here or `&amp;` is indexed as three tokens. Unknown
 /// references are kept verbatim: an unrecognised `&foo;` is far more likely page text
 /// than a reference this table is missing.
-fn decode_entities(input: &str) -> String {
+pub(crate) fn decode_entities(input: &str) -> String {
 	if !input.contains('&') {
 		return input.to_owned();
 	}
@@ -4,17 +4,24 @@
 //! Text and metadata extraction from stored content.
 //!
 //! One home for "turn bytes of some format into something the platform can index
-//! or preview". It starts with HTML — the search index needs the visible text of
-//! a published site page — and is the intended home of the extractors that follow
-//! it: link-preview metadata for a pasted URL, and page text from PDF, doc and ODF
-//! attachments.
+//! or preview". HTML came first — the search index needs the visible text of a
+//! published site page — and [`document`] reads page text from PDF, office and
+//! plain-text attachments. [`link`] reads the OpenGraph and oEmbed metadata a
+//! link preview is built from.
 //!
 //! The crate deliberately knows nothing about files, containers or search rows. It
 //! takes bytes and answers with text, so the same call serves the publish path, a
 //! full reindex and the site verifier without any of them depending on each other.
 
+pub mod document;
 pub mod html;
+pub mod link;
+pub mod office;
+pub mod pdf;
+pub mod plain;
 
-pub use html::ExtractedText;
+pub use document::{DocumentFormat, Page, PageBudget};
+pub use link::LinkMetadata;
+pub use plain::ExtractedText;
 
 // vim: ts=4
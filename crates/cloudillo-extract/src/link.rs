@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Link-preview metadata: what a page says about itself when someone shares it.
+//!
+//! Three vocabularies, in the order they are trusted: OpenGraph (`og:*`), the
+//! Twitter card tags (`twitter:*`), and plain HTML — `<title>` and the description
+//! meta tag. A page usually carries several; each field is
+//! taken from the first vocabulary that has it. oEmbed is the fourth source, but a
+//! second request away: [`parse_html`] only reports the discovery link, and
+//! [`LinkMetadata::merge_oembed`] folds the answer in once the caller has it.
+//!
+//! URLs come back as the page wrote them. Resolving a relative `og:image` needs
+//! the page's final address after redirects, which only the fetcher knows.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cloudillo_types::prelude::*;
+use lol_html::{HtmlRewriter, Settings, element, text};
+use serde::Deserialize;
+
+use crate::html::decode_entities;
+use crate::plain::Flat;
+
+/// Largest HTML input [`parse_html`] reads. The tags it wants live in `<head>`; a
+/// fetcher reads at most this much of a page and hands over the prefix.
+pub const MAX_INPUT_BYTES: usize = 512 * 1024;
+
+/// Largest oEmbed response [`LinkMetadata::merge_oembed`] parses.
+pub const MAX_OEMBED_BYTES: usize = 64 * 1024;
+
+/// Longest title kept, in characters.
+const MAX_TITLE_CHARS: usize = 300;
+
+/// Longest description kept, in characters.
+const MAX_DESCRIPTION_CHARS: usize = 1_000;
+
+/// Longest URL kept. Past this it is not an address a preview should carry.
+const MAX_URL_LEN: usize = 2_048;
+
+/// What a page says about itself. Every field is optional: a page may say nothing.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LinkMetadata {
+	pub title: Option<String>,
+	pub description: Option<String>,
+	pub site_name: Option<String>,
+	/// Preview image, as written in the page — possibly relative.
+	pub image: Option<String>,
+	/// `og:type` or the oEmbed `type`: `website`, `article`, `video`, …
+	pub kind: Option<String>,
+	/// JSON oEmbed discovery link, if the page advertises one.
+	pub oembed_url: Option<String>,
+}
+
+impl LinkMetadata {
+	/// Whether there is anything to show.
+	pub fn is_empty(&self) -> bool {
+		self.title.is_none() && self.description.is_none() && self.image.is_none()
+	}
+
+	/// Each field this one lacks, taken from `other`.
+	fn or(self, other: Self) -> Self {
+		Self {
+			title: self.title.or(other.title),
+			description: self.description.or(other.description),
+			site_name: self.site_name.or(other.site_name),
+			image: self.image.or(other.image),
+			kind: self.kind.or(other.kind),
+			oembed_url: self.oembed_url.or(other.oembed_url),
+		}
+	}
+
+	/// Fill the fields the page left empty from an oEmbed JSON response.
+	///
+	/// The page's own tags win: oEmbed is asked for the embed, and its `title` is
+	/// often the bare video title where `og:title` names the page. A malformed
+	/// response is a [`Error::ValidationError`] and leaves `self` as it was.
+	pub fn merge_oembed(&mut self, json: &[u8]) -> ClResult<()> {
+		#[derive(Deserialize)]
+		struct OEmbed {
+			#[serde(rename = "type")]
+			kind: Option<String>,
+			title: Option<String>,
+			author_name: Option<String>,
+			provider_name: Option<String>,
+			thumbnail_url: Option<String>,
+		}
+
+		if json.len() > MAX_OEMBED_BYTES {
+			return Err(Error::ValidationError("oEmbed response exceeds the limit".into()));
+		}
+		let oembed: OEmbed = serde_json::from_slice(json)
+			.map_err(|_| Error::ValidationError("invalid oEmbed response".into()))?;
+		let found = Self {
+			title: oembed.title.as_deref().and_then(|t| text_value(t, MAX_TITLE_CHARS)),
+			description: oembed
+				.author_name
+				.as_deref()
+				.and_then(|a| text_value(a, MAX_DESCRIPTION_CHARS)),
+			site_name: oembed.provider_name.as_deref().and_then(|p| text_value(p, MAX_TITLE_CHARS)),
+			image: oembed.thumbnail_url.as_deref().and_then(url_value),
+			kind: oembed.kind.as_deref().and_then(|k| text_value(k, MAX_TITLE_CHARS)),
+			oembed_url: None,
+		};
+		*self = std::mem::take(self).or(found);
+		Ok(())
+	}
+}
+
+/// Read the preview metadata of an HTML page.
+///
+/// `html` may be a prefix of the page — a truncated document parses as far as it
+/// goes. Input past [`MAX_INPUT_BYTES`] is a [`Error::ValidationError`]; bytes that
+/// are not UTF-8 are replaced rather than refused, as a page's `<head>` is usually
+/// readable even when its charset is not the one assumed.
+pub fn parse_html(html: &[u8]) -> ClResult<LinkMetadata> {
+	if html.len() > MAX_INPUT_BYTES {
+		return Err(Error::ValidationError("HTML input exceeds the extraction limit".into()));
+	}
+	let html = String::from_utf8_lossy(html);
+
+	let found = Rc::new(RefCell::new(Found::default()));
+	let meta_found = Rc::clone(&found);
+	let link_found = Rc::clone(&found);
+	let title_found = Rc::clone(&found);
+
+	let mut rewriter = HtmlRewriter::new(
+		Settings {
+			element_content_handlers: vec![
+				element!("meta[content]", move |el| {
+					let key = el.get_attribute("property").or_else(|| el.get_attribute("name"));
+					if let (Some(key), Some(content), Ok(mut found)) =
+						(key, el.get_attribute("content"), meta_found.try_borrow_mut())
+					{
+						found.meta(&key.to_ascii_lowercase(), &decode_entities(&content));
+					}
+					Ok(())
+				}),
+				element!("link[href]", move |el| {
+					let rel = el.get_attribute("rel").unwrap_or_default().to_ascii_lowercase();
+					let typ = el.get_attribute("type").unwrap_or_default().to_ascii_lowercase();
+					if let (Some(href), Ok(mut found)) =
+						(el.get_attribute("href"), link_found.try_borrow_mut())
+					{
+						found.link(&rel, &typ, &decode_entities(&href));
+					}
+					Ok(())
+				}),
+				text!("title", move |chunk| {
+					if let Ok(mut found) = title_found.try_borrow_mut()
+						&& !found.title_done
+					{
+						found.title.push_str(chunk.as_str());
+						found.title_done = chunk.last_in_text_node();
+					}
+					Ok(())
+				}),
+			],
+			memory_settings: lol_html::MemorySettings {
+				max_allowed_memory_usage: MAX_INPUT_BYTES,
+				..lol_html::MemorySettings::default()
+			},
+			..Settings::default()
+		},
+		|_: &[u8]| {},
+	);
+	rewriter
+		.write(html.as_bytes())
+		.map_err(|e| Error::Internal(format!("link metadata extraction failed: {e}")))?;
+	rewriter
+		.end()
+		.map_err(|e| Error::Internal(format!("link metadata extraction failed: {e}")))?;
+
+	let found = found.take();
+	let mut plain = found.plain;
+	if plain.title.is_none() {
+		plain.title = text_value(&decode_entities(&found.title), MAX_TITLE_CHARS);
+	}
+	Ok(found.og.or(found.twitter).or(plain))
+}
+
+/// One slot per vocabulary, merged by [`parse_html`] in trust order. Within a slot
+/// the first tag wins — a page listing several `og:image`s puts its choice first.
+#[derive(Default)]
+struct Found {
+	og: LinkMetadata,
+	twitter: LinkMetadata,
+	plain: LinkMetadata,
+	/// `<title>` text so far; `lol_html` may deliver it in pieces.
+	title: String,
+	title_done: bool,
+}
+
+impl Found {
+	fn meta(&mut self, key: &str, content: &str) {
+		let (slot, field) = if let Some(field) = key.strip_prefix("og:") {
+			(&mut self.og, field)
+		} else if let Some(field) = key.strip_prefix("twitter:") {
+			(&mut self.twitter, field)
+		} else {
+			(&mut self.plain, key)
+		};
+		match field {
+			"title" => set_once(&mut slot.title, text_value(content, MAX_TITLE_CHARS)),
+			"description" => {
+				set_once(&mut slot.description, text_value(content, MAX_DESCRIPTION_CHARS));
+			}
+			"site_name" | "application-name" => {
+				set_once(&mut slot.site_name, text_value(content, MAX_TITLE_CHARS));
+			}
+			"image" | "image:url" | "image:secure_url" | "image:src" => {
+				set_once(&mut slot.image, url_value(content));
+			}
+			"type" => set_once(&mut slot.kind, text_value(content, MAX_TITLE_CHARS)),
+			_ => {}
+		}
+	}
+
+	fn link(&mut self, rel: &str, typ: &str, href: &str) {
+		if rel.split_ascii_whitespace().any(|r| r == "alternate")
+			&& typ == "application/json+oembed"
+		{
+			set_once(&mut self.plain.oembed_url, url_value(href));
+		}
+	}
+}
+
+fn set_once(slot: &mut Option<String>, value: Option<String>) {
+	if slot.is_none() {
+		*slot = value;
+	}
+}
+
+/// Whitespace-normalised and cut to `max_chars`; `None` when blank.
+fn text_value(value: &str, max_chars: usize) -> Option<String> {
+	let mut flat = Flat::new(max_chars);
+	flat.push(value);
+	let text = flat.take();
+	(!text.text.is_empty()).then_some(text.text)
+}
+
+/// Trimmed; `None` when blank or unreasonably long.
+fn url_value(value: &str) -> Option<String> {
+	let value = value.trim();
+	(!value.is_empty() && value.len() <= MAX_URL_LEN).then(|| value.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn opengraph_wins_over_twitter_and_plain_html() {
+		let html = br#"<html><head>
+			<title>Plain  title</title>
+			<meta name="description" content="plain description">
+			<meta name="twitter:title" content="Card title">
+			<meta name="twitter:image" content="/card.png">
+			<meta property="og:title" content="OG &amp; title">
+			<meta property="og:image" content="https://cdn.example/og.png">
+			<meta property="og:image" content="https://cdn.example/second.png">
+			<meta property="og:site_name" content="Example">
+			<link rel="alternate" type="application/json+oembed" href="/oembed?url=x&amp;format=json">
+		</head><body><title>not this</title></body></html>"#;
+		let meta = parse_html(html).expect("parse");
+		assert_eq!(meta.title.as_deref(), Some("OG & title"));
+		assert_eq!(meta.description.as_deref(), Some("plain description"));
+		assert_eq!(meta.image.as_deref(), Some("https://cdn.example/og.png"));
+		assert_eq!(meta.site_name.as_deref(), Some("Example"));
+		assert_eq!(meta.oembed_url.as_deref(), Some("/oembed?url=x&format=json"));
+	}
+
+	#[test]
+	fn the_title_element_is_the_last_resort() {
+		let meta = parse_html(b"<title>A &lt;b&gt; \n page</title><p>body").expect("parse");
+		assert_eq!(meta.title.as_deref(), Some("A <b> page"));
+		assert!(!meta.is_empty());
+		assert!(parse_html(b"<p>nothing here</p>").expect("parse").is_empty());
+	}
+
+	#[test]
+	fn oembed_fills_only_what_the_page_left_empty() {
+		let mut meta = LinkMetadata { title: Some("Page".into()), ..LinkMetadata::default() };
+		meta.merge_oembed(
+			br#"{"type":"video","title":"Clip","provider_name":"Tube","thumbnail_url":"https://t/x.jpg"}"#,
+		)
+		.expect("merge");
+		assert_eq!(meta.title.as_deref(), Some("Page"));
+		assert_eq!(meta.kind.as_deref(), Some("video"));
+		assert_eq!(meta.site_name.as_deref(), Some("Tube"));
+		assert_eq!(meta.image.as_deref(), Some("https://t/x.jpg"));
+		assert!(matches!(meta.merge_oembed(b"<html>"), Err(Error::ValidationError(_))));
+	}
+}
+
+// vim: ts=4
@@ -0,0 +1,548 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Office documents: OOXML (docx, xlsx, pptx) and ODF (odt, ods, odp).
+//!
+//! Both families are a zip of XML parts, and the text is the character data of a
+//! few known elements. Nothing is rendered or laid out: a spreadsheet's text is its
+//! cells in document order, a presentation's is its slides' text frames. Styles,
+//! comments-as-metadata, field codes and deleted revisions are left out because
+//! they are not what the reader sees.
+//!
+//! A slide deck and a workbook come back one [`Page`] per slide or sheet; a text
+//! document comes back as one page, having no page breaks of its own until a
+//! word processor lays it out.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use cloudillo_types::prelude::*;
+use quick_xml::{Reader, events::Event};
+
+use crate::document::{Page, PageBudget, Pages};
+
+/// Largest office document this extractor will open.
+///
+/// The whole archive is held in memory to read its central directory, so this is
+/// the allocation bound; [`MAX_PART_BYTES`] is the one on inflated XML.
+pub const MAX_INPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest inflated XML part read out of the archive. A zip bomb is a valid
+/// document, so the declared size is not trusted and the inflate itself stops here.
+const MAX_PART_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Slides or sheets one document contributes. Past this the tail is dropped.
+pub const MAX_UNITS: usize = 1_000;
+
+/// The office formats this module reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfficeFormat {
+	Docx,
+	Xlsx,
+	Pptx,
+	Odt,
+	Ods,
+	Odp,
+}
+
+/// Extract the text of an office document, one [`Page`] per slide or sheet.
+///
+/// A malformed archive or part is a [`Error::ValidationError`] — a permanent
+/// property of the bytes, which a caller can skip rather than retry.
+pub fn extract_pages(
+	bytes: &[u8],
+	format: OfficeFormat,
+	budget: PageBudget,
+) -> ClResult<Vec<Page>> {
+	if bytes.len() > MAX_INPUT_BYTES {
+		return Err(Error::ValidationError("office input exceeds the extraction limit".into()));
+	}
+	let archive = Archive::open(bytes)?;
+	let mut pages = Pages::new(budget);
+	match format {
+		OfficeFormat::Docx => {
+			let xml = archive.require("word/document.xml")?;
+			pages.begin();
+			walk(&xml, &DOCX, &mut pages)?;
+			pages.end();
+		}
+		OfficeFormat::Pptx => {
+			for path in archive.numbered("ppt/slides/slide", ".xml").into_iter().take(MAX_UNITS) {
+				if pages.is_full() {
+					break;
+				}
+				let xml = archive.require(path)?;
+				pages.begin();
+				walk(&xml, &PPTX, &mut pages)?;
+				pages.end();
+			}
+		}
+		OfficeFormat::Xlsx => {
+			let shared = match archive.read("xl/sharedStrings.xml")? {
+				Some(xml) => shared_strings(&xml)?,
+				None => Vec::new(),
+			};
+			let sheets = archive.numbered("xl/worksheets/sheet", ".xml");
+			for path in sheets.into_iter().take(MAX_UNITS) {
+				if pages.is_full() {
+					break;
+				}
+				let xml = archive.require(path)?;
+				pages.begin();
+				walk_sheet(&xml, &shared, &mut pages)?;
+				pages.end();
+			}
+		}
+		OfficeFormat::Odt => {
+			let xml = archive.require("content.xml")?;
+			pages.begin();
+			walk(&xml, &ODT, &mut pages)?;
+			pages.end();
+		}
+		// Pages open and close inside the walk, one per `table:table` or `draw:page`.
+		OfficeFormat::Ods => walk(&archive.require("content.xml")?, &ODS, &mut pages)?,
+		OfficeFormat::Odp => walk(&archive.require("content.xml")?, &ODP, &mut pages)?,
+	}
+	Ok(pages.finish())
+}
+
+/// Where the text of one XML vocabulary is. Element names are local names: the
+/// prefixes are conventional, and a writer is free to pick others.
+struct Layout {
+	/// Elements whose character data is content. `None` takes every text node
+	/// outside [`Layout::skip`], which is how ODF carries text.
+	text: Option<&'static [&'static str]>,
+	/// Elements whose start and end are word boundaries.
+	boundaries: &'static [&'static str],
+	/// Elements skipped whole, text and all.
+	skip: &'static [&'static str],
+	/// The element one page is, for a vocabulary that holds every page in one part.
+	unit: Option<&'static str>,
+}
+
+/// `w:t` is typed text; `w:delText` (a tracked deletion) and `w:instrText` (a
+/// field code) are not on the page, and are skipped by not being listed.
+const DOCX: Layout = Layout {
+	text: Some(&["t"]),
+	boundaries: &["p", "tab", "br", "cr", "tc"],
+	skip: &[],
+	unit: None,
+};
+
+const PPTX: Layout = Layout { text: Some(&["t"]), boundaries: &["p", "br"], skip: &[], unit: None };
+
+/// Declarations, scripts and forms come before the body and are not text; of the
+/// body, only annotations are left out — a reviewer's margin note is not the document.
+const ODF_SKIP: &[&str] =
+	&["font-face-decls", "automatic-styles", "scripts", "forms", "annotation"];
+const ODF_BOUNDARIES: &[&str] =
+	&["p", "h", "s", "tab", "line-break", "list-item", "table-cell", "frame"];
+
+const ODT: Layout = Layout { text: None, boundaries: ODF_BOUNDARIES, skip: ODF_SKIP, unit: None };
+const ODS: Layout =
+	Layout { text: None, boundaries: ODF_BOUNDARIES, skip: ODF_SKIP, unit: Some("table") };
+const ODP: Layout =
+	Layout { text: None, boundaries: ODF_BOUNDARIES, skip: ODF_SKIP, unit: Some("page") };
+
+/// Walk one XML part into `pages`, opening a page per [`Layout::unit`] element when
+/// the layout has one and writing into the caller's open page otherwise.
+fn walk(xml: &[u8], layout: &Layout, pages: &mut Pages) -> ClResult<()> {
+	let mut reader = Reader::from_reader(xml);
+	let mut buf = Vec::new();
+	// Depth of the innermost open text element, and of the skipped one, if any.
+	let mut depth = 0usize;
+	let mut in_text: Vec<usize> = Vec::new();
+	let mut skip_at: Option<usize> = None;
+	let mut units = 0usize;
+	loop {
+		let event = reader.read_event_into(&mut buf).map_err(malformed)?;
+		match event {
+			Event::Start(e) => {
+				depth += 1;
+				let local_name = e.local_name();
+				let name = local(local_name.as_ref());
+				if skip_at.is_some() {
+					// Inside a skipped element — nothing below it matters.
+				} else if layout.skip.contains(&name) {
+					skip_at = Some(depth);
+				} else {
+					if layout.unit == Some(name) {
+						if units >= MAX_UNITS || pages.is_full() {
+							break;
+						}
+						units += 1;
+						pages.begin();
+					}
+					if layout.boundaries.contains(&name) {
+						pages.mark_boundary();
+					}
+					if layout.text.is_some_and(|t| t.contains(&name)) {
+						in_text.push(depth);
+					}
+				}
+			}
+			Event::Empty(e) if skip_at.is_none() => {
+				let local_name = e.local_name();
+				let name = local(local_name.as_ref());
+				if layout.unit == Some(name) {
+					// An empty sheet or slide still takes its number.
+					units += 1;
+					pages.begin();
+					pages.end();
+				} else if layout.boundaries.contains(&name) {
+					pages.mark_boundary();
+				}
+			}
+			Event::End(e) => {
+				if skip_at == Some(depth) {
+					skip_at = None;
+				} else if skip_at.is_none() {
+					let local_name = e.local_name();
+					let name = local(local_name.as_ref());
+					if layout.boundaries.contains(&name) {
+						pages.mark_boundary();
+					}
+					if in_text.last() == Some(&depth) {
+						in_text.pop();
+					}
+					if layout.unit == Some(name) {
+						pages.end();
+					}
+				}
+				depth = depth.saturating_sub(1);
+			}
+			Event::Text(t) if takes_text(layout, skip_at, &in_text) => {
+				pages.push(&t.decode().map_err(malformed)?);
+			}
+			Event::CData(t) if takes_text(layout, skip_at, &in_text) => {
+				pages.push(&t.decode().map_err(malformed)?);
+			}
+			Event::GeneralRef(r) if takes_text(layout, skip_at, &in_text) => {
+				if let Some(c) = resolve_ref(&r) {
+					pages.push(c.encode_utf8(&mut [0; 4]));
+				}
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+		// A layout without units is one page, and done once that page is; one with
+		// units goes on to the next unit until the document's budget is spent.
+		if pages.is_full() || (layout.unit.is_none() && pages.page_is_full()) {
+			break;
+		}
+		buf.clear();
+	}
+	Ok(())
+}
+
+fn takes_text(layout: &Layout, skip_at: Option<usize>, in_text: &[usize]) -> bool {
+	skip_at.is_none() && (layout.text.is_none() || !in_text.is_empty())
+}
+
+/// `xl/sharedStrings.xml`: the workbook's strings, indexed by a cell's `<v>`.
+///
+/// Rich-text runs are concatenated; phonetic guides (`rPh`) are left out, since they
+/// repeat the run they annotate in another script.
+fn shared_strings(xml: &[u8]) -> ClResult<Vec<String>> {
+	let mut reader = Reader::from_reader(xml);
+	let mut buf = Vec::new();
+	let mut strings = Vec::new();
+	let mut current: Option<String> = None;
+	let mut in_t = false;
+	let mut in_phonetic = false;
+	loop {
+		match reader.read_event_into(&mut buf).map_err(malformed)? {
+			Event::Start(e) => match e.local_name().as_ref() {
+				b"si" => current = Some(String::new()),
+				b"t" => in_t = true,
+				b"rPh" => in_phonetic = true,
+				_ => {}
+			},
+			Event::Empty(e) if e.local_name().as_ref() == b"si" => strings.push(String::new()),
+			Event::End(e) => match e.local_name().as_ref() {
+				b"si" => strings.extend(current.take()),
+				b"t" => in_t = false,
+				b"rPh" => in_phonetic = false,
+				_ => {}
+			},
+			Event::Text(t) if in_t && !in_phonetic => {
+				if let Some(s) = current.as_mut() {
+					s.push_str(&t.decode().map_err(malformed)?);
+				}
+			}
+			Event::GeneralRef(r) if in_t && !in_phonetic => {
+				if let (Some(s), Some(c)) = (current.as_mut(), resolve_ref(&r)) {
+					s.push(c);
+				}
+			}
+			Event::Eof => break,
+			_ => {}
+		}
+		buf.clear();
+	}
+	Ok(strings)
+}
+
+/// One worksheet's cells, in row order. A shared-string cell is resolved against
+/// `shared`; an inline string is taken as written; a number or a formula's cached
+/// result is taken as its text, and booleans and error values are left out.
+fn walk_sheet(xml: &[u8], shared: &[String], pages: &mut Pages) -> ClResult<()> {
+	let mut reader = Reader::from_reader(xml);
+	let mut buf = Vec::new();
+	let mut cell_type: Option<Vec<u8>> = None;
+	let mut value = String::new();
+	let mut in_value = false;
+	loop {
+		match reader.read_event_into(&mut buf).map_err(malformed)? {
+			Event::Start(e) => match e.local_name().as_ref() {
+				b"c" => {
+					cell_type = e
+						.attributes()
+						.flatten()
+						.find(|a| a.key.as_ref() == b"t")
+						.map(|a| a.value.into_owned());
+					value.clear();
+				}
+				b"v" | b"t" => in_value = true,
+				_ => {}
+			},
+			Event::End(e) => match e.local_name().as_ref() {
+				b"c" => {
+					let text = match cell_type.as_deref() {
+						Some(b"s") => {
+							value.trim().parse::<usize>().ok().and_then(|i| shared.get(i))
+						}
+						Some(b"b" | b"e") => None,
+						_ => Some(&value),
+					};
+					if let Some(text) = text {
+						pages.mark_boundary();
+						pages.push(text);
+					}
+					cell_type = None;
+				}
+				b"v" | b"t" => in_value = false,
+				_ => {}
+			},
+			Event::Text(t) if in_value => value.push_str(&t.decode().map_err(malformed)?),
+			Event::GeneralRef(r) if in_value => value.extend(resolve_ref(&r)),
+			Event::Eof => break,
+			_ => {}
+		}
+		if pages.page_is_full() {
+			break;
+		}
+		buf.clear();
+	}
+	Ok(())
+}
+
+/// An entity or character reference. XML predefines five names; anything else
+/// would need a DTD, which neither format uses.
+fn resolve_ref(r: &quick_xml::events::BytesRef<'_>) -> Option<char> {
+	if let Ok(Some(c)) = r.resolve_char_ref() {
+		return (!c.is_control() || matches!(c, '\t' | '\n' | '\r')).then_some(c);
+	}
+	let name = r.decode().ok()?;
+	quick_xml::escape::resolve_predefined_entity(&name)?.chars().next()
+}
+
+/// A local name as `&str`. Names are ASCII in both vocabularies; one that is not
+/// matches nothing.
+fn local(name: &[u8]) -> &str {
+	std::str::from_utf8(name).unwrap_or("")
+}
+
+fn malformed(e: impl std::fmt::Display) -> Error {
+	Error::ValidationError(format!("malformed office document: {e}"))
+}
+
+/// An office document's zip, with its entry names read once.
+struct Archive<'a> {
+	zip: rawzip::ZipSliceArchive<&'a [u8]>,
+	entries: HashMap<String, (rawzip::ZipArchiveEntryWayfinder, rawzip::CompressionMethod)>,
+}
+
+impl<'a> Archive<'a> {
+	fn open(bytes: &'a [u8]) -> ClResult<Self> {
+		let zip = rawzip::ZipArchive::from_slice(bytes).map_err(malformed)?;
+		let mut entries = HashMap::new();
+		for entry in zip.entries() {
+			let entry = entry.map_err(malformed)?;
+			if entry.is_dir() {
+				continue;
+			}
+			let Ok(path) = entry.file_path().try_normalize() else { continue };
+			entries
+				.insert(path.as_ref().to_owned(), (entry.wayfinder(), entry.compression_method()));
+		}
+		Ok(Self { zip, entries })
+	}
+
+	/// The entries `{prefix}{n}{suffix}`, ordered by `n` — `slide10` after `slide9`.
+	fn numbered(&self, prefix: &str, suffix: &str) -> Vec<&str> {
+		let mut found: Vec<(u32, &str)> = self
+			.entries
+			.keys()
+			.filter_map(|path| {
+				let n = path.strip_prefix(prefix)?.strip_suffix(suffix)?.parse().ok()?;
+				Some((n, path.as_str()))
+			})
+			.collect();
+		found.sort_unstable();
+		found.into_iter().map(|(_, path)| path).collect()
+	}
+
+	fn require(&self, path: &str) -> ClResult<Vec<u8>> {
+		self.read(path)?
+			.ok_or_else(|| Error::ValidationError(format!("office document has no {path}")))
+	}
+
+	/// One entry, inflated and bounded by [`MAX_PART_BYTES`].
+	fn read(&self, path: &str) -> ClResult<Option<Vec<u8>>> {
+		let Some(&(wayfinder, method)) = self.entries.get(path) else { return Ok(None) };
+		let entry = self.zip.get_entry(wayfinder).map_err(malformed)?;
+		let mut out = Vec::new();
+		// One byte past the cap is read on purpose: it is what tells a part that
+		// exactly fills the budget from one that overruns it.
+		let limit = MAX_PART_BYTES + 1;
+		if method == rawzip::CompressionMethod::DEFLATE {
+			flate2::read::DeflateDecoder::new(entry.data())
+				.take(limit)
+				.read_to_end(&mut out)
+		} else if method == rawzip::CompressionMethod::STORE {
+			entry.data().take(limit).read_to_end(&mut out)
+		} else {
+			return Err(Error::ValidationError(format!(
+				"office document part {path} uses an unsupported compression"
+			)));
+		}
+		.map_err(malformed)?;
+		if out.len() as u64 > MAX_PART_BYTES {
+			return Err(Error::ValidationError(format!(
+				"office document part {path} exceeds the extraction limit"
+			)));
+		}
+		Ok(Some(out))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A stored zip of `(path, content)` parts.
+	fn zip(parts: &[(&str, &str)]) -> Vec<u8> {
+		let mut archive = rawzip::ZipArchiveWriter::new(Vec::new());
+		for (path, content) in parts {
+			let (mut entry, config) = archive
+				.new_file(path)
+				.compression_method(rawzip::CompressionMethod::STORE)
+				.start()
+				.expect("entry");
+			let mut writer = config.wrap(&mut entry);
+			std::io::copy(&mut content.as_bytes(), &mut writer).expect("write");
+			let (_, descriptor) = writer.finish().expect("finish");
+			entry.finish(descriptor).expect("entry finish");
+		}
+		archive.finish().expect("archive")
+	}
+
+	fn budget() -> PageBudget {
+		PageBudget::new(1024, 4096)
+	}
+
+	#[test]
+	fn docx_takes_typed_text_only() {
+		let doc = zip(&[(
+			"word/document.xml",
+			r#"<w:document xmlns:w="x"><w:body>
+				<w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> wor</w:t></w:r><w:r><w:t>ld</w:t></w:r></w:p>
+				<w:p><w:r><w:instrText>PAGE</w:instrText><w:delText>gone</w:delText><w:t>Tom &amp; Jerry</w:t></w:r></w:p>
+			</w:body></w:document>"#,
+		)]);
+		let pages = extract_pages(&doc, OfficeFormat::Docx, budget()).expect("extract");
+		assert_eq!(pages.len(), 1);
+		assert_eq!(pages[0].text.text, "Hello world Tom & Jerry");
+	}
+
+	#[test]
+	fn pptx_slides_are_pages_in_numeric_order() {
+		let slide = |s: &str| {
+			format!(r#"<p:sld xmlns:a="a" xmlns:p="p"><a:p><a:t>{s}</a:t></a:p></p:sld>"#)
+		};
+		let (s1, s2, s10) = (slide("one"), slide("two"), slide("ten"));
+		let doc = zip(&[
+			("ppt/slides/slide10.xml", &s10),
+			("ppt/slides/slide2.xml", &s2),
+			("ppt/slides/slide1.xml", &s1),
+			("ppt/slides/_rels/slide1.xml.rels", "<Relationships/>"),
+		]);
+		let pages = extract_pages(&doc, OfficeFormat::Pptx, budget()).expect("extract");
+		let got: Vec<(u32, &str)> =
+			pages.iter().map(|p| (p.number, p.text.text.as_str())).collect();
+		assert_eq!(got, vec![(1, "one"), (2, "two"), (3, "ten")]);
+	}
+
+	#[test]
+	fn xlsx_resolves_shared_strings_and_skips_booleans() {
+		let doc = zip(&[
+			(
+				"xl/sharedStrings.xml",
+				"<sst><si><t>Name</t></si><si><r><t>Ali</t></r><r><t>ce</t></r><rPh><t>ありす</t></rPh></si></sst>",
+			),
+			(
+				"xl/worksheets/sheet1.xml",
+				r#"<worksheet><sheetData><row><c t="s"><v>0</v></c><c t="s"><v>1</v></c></row>
+				<row><c><v>42</v></c><c t="b"><v>1</v></c><c t="inlineStr"><is><t>inline</t></is></c></row></sheetData></worksheet>"#,
+			),
+		]);
+		let pages = extract_pages(&doc, OfficeFormat::Xlsx, budget()).expect("extract");
+		assert_eq!(pages.len(), 1);
+		assert_eq!(pages[0].text.text, "Name Alice 42 inline");
+	}
+
+	#[test]
+	fn ods_sheets_are_pages_and_annotations_are_skipped() {
+		let doc = zip(&[(
+			"content.xml",
+			r#"<office:document-content xmlns:office="o" xmlns:table="t" xmlns:text="x">
+				<office:automatic-styles><style:style name="ce1"/></office:automatic-styles>
+				<office:body><office:spreadsheet>
+				<table:table table:name="A"><table:table-row><table:table-cell><text:p>first</text:p></table:table-cell><table:table-cell><text:p>cell</text:p><office:annotation><text:p>note</text:p></office:annotation></table:table-cell></table:table-row></table:table>
+				<table:table table:name="B"/>
+				<table:table table:name="C"><table:table-row><table:table-cell><text:p>third<text:s/>sheet</text:p></table:table-cell></table:table-row></table:table>
+				</office:spreadsheet></office:body></office:document-content>"#,
+		)]);
+		let pages = extract_pages(&doc, OfficeFormat::Ods, budget()).expect("extract");
+		let got: Vec<(u32, &str)> =
+			pages.iter().map(|p| (p.number, p.text.text.as_str())).collect();
+		// The empty sheet has no page, but keeps its number: a hit on "third" must
+		// deep-link to the third sheet.
+		assert_eq!(got, vec![(1, "first cell"), (3, "third sheet")]);
+	}
+
+	#[test]
+	fn odt_is_one_page() {
+		let doc = zip(&[(
+			"content.xml",
+			"<office:document-content><office:body><office:text>
+				<text:h>Title</text:h><text:p>Body <text:span>text</text:span></text:p>
+				</office:text></office:body></office:document-content>",
+		)]);
+		let pages = extract_pages(&doc, OfficeFormat::Odt, budget()).expect("extract");
+		assert_eq!(pages.len(), 1);
+		assert_eq!(pages[0].text.text, "Title Body text");
+	}
+
+	#[test]
+	fn a_broken_archive_is_a_validation_error() {
+		let err = extract_pages(b"not a zip", OfficeFormat::Docx, budget());
+		assert!(matches!(err, Err(Error::ValidationError(_))));
+		let empty = zip(&[("other.xml", "<x/>")]);
+		let err = extract_pages(&empty, OfficeFormat::Docx, budget());
+		assert!(matches!(err, Err(Error::ValidationError(_))));
+	}
+}
+
+// vim: ts=4
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! PDF page text, via `pdftotext` from poppler-utils.
+//!
+//! The same toolkit `cloudillo-file` already renders PDF thumbnails and reads page
+//! counts with, so a node that can show a PDF can index one. A PDF's text is only
+//! recoverable through its fonts' encodings and `ToUnicode` maps; a parser of our
+//! own would be a second, weaker copy of that work.
+//!
+//! `pdftotext` ends every page with a form feed, which is what the page split below
+//! relies on.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use cloudillo_types::prelude::*;
+
+use crate::document::{Page, PageBudget, Pages};
+
+/// Largest PDF this extractor will hand to `pdftotext`.
+pub const MAX_INPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Pages one PDF contributes. Passed to `pdftotext` as its last page, so the pages
+/// past it are never decoded at all.
+pub const MAX_PAGES: u32 = 2_000;
+
+/// Largest `pdftotext` output read back. Text per page is bounded by the caller's
+/// budget, but the budget counts what survives normalisation — this bounds the
+/// bytes read to get there, runs of layout whitespace included.
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Extract the text of a PDF, one [`Page`] per page.
+///
+/// An unreadable or encrypted PDF is a [`Error::ValidationError`], as is input past
+/// [`MAX_INPUT_BYTES`]; a missing `pdftotext` is an [`Error::Internal`], because it
+/// is the node that is broken, not the file.
+pub fn extract_pages(pdf: &[u8], budget: PageBudget) -> ClResult<Vec<Page>> {
+	if pdf.len() > MAX_INPUT_BYTES {
+		return Err(Error::ValidationError("PDF input exceeds the extraction limit".into()));
+	}
+	let text = run_pdftotext(pdf)?;
+	Ok(split_pages(&text, budget))
+}
+
+/// Run `pdftotext` over `pdf` fed on stdin, and read its UTF-8 output.
+fn run_pdftotext(pdf: &[u8]) -> ClResult<String> {
+	let mut child = Command::new("pdftotext")
+		.args(["-q", "-enc", "UTF-8", "-l", &MAX_PAGES.to_string(), "-", "-"])
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.map_err(|e| Error::Internal(format!("pdftotext failed: {e}")))?;
+
+	let mut stdin = child.stdin.take().ok_or(Error::Internal("pdftotext has no stdin".into()))?;
+	let stdout = child.stdout.take().ok_or(Error::Internal("pdftotext has no stdout".into()))?;
+	// Fed from a second thread: `pdftotext` may start writing before it has read the
+	// whole input, and a full pipe each way would otherwise deadlock the two. The
+	// write's own error is not reported — a broken pipe is `pdftotext` giving up on
+	// the input, which its exit status says better.
+	let read = std::thread::scope(|scope| {
+		scope.spawn(move || {
+			let _ = stdin.write_all(pdf);
+		});
+		let mut out = Vec::new();
+		let read = stdout.take(MAX_OUTPUT_BYTES).read_to_end(&mut out);
+		// Past the cap the child may be blocked writing, and the feeding thread
+		// behind it; killing it is what lets the scope end.
+		if out.len() as u64 >= MAX_OUTPUT_BYTES {
+			let _ = child.kill();
+		}
+		read.map(|_| out)
+	});
+	let out = read.map_err(|e| Error::Internal(format!("pdftotext failed: {e}")))?;
+	let status = child.wait().map_err(|e| Error::Internal(format!("pdftotext failed: {e}")))?;
+	if !status.success() && (out.len() as u64) < MAX_OUTPUT_BYTES {
+		return Err(Error::ValidationError(format!("PDF text cannot be extracted ({status})")));
+	}
+	Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Split `pdftotext` output on its form feeds into budgeted pages.
+fn split_pages(text: &str, budget: PageBudget) -> Vec<Page> {
+	let mut pages = Pages::new(budget);
+	// Every page ends in a form feed, so the last piece is what follows the last
+	// page: nothing, or a cut-off tail past the output cap.
+	for page in text.split('\u{c}') {
+		if pages.is_full() {
+			break;
+		}
+		pages.begin();
+		pages.push(page);
+	}
+	pages.finish()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn form_feeds_split_pages_and_blank_pages_keep_their_number() {
+		let text = "Intro\n\n  page one\u{c}\u{c}Page   three\n\u{c}";
+		let pages = split_pages(text, PageBudget::new(64, 1024));
+		let got: Vec<(u32, &str)> =
+			pages.iter().map(|p| (p.number, p.text.text.as_str())).collect();
+		assert_eq!(got, vec![(1, "Intro page one"), (3, "Page three")]);
+	}
+
+	#[test]
+	fn each_page_is_bounded_and_so_is_the_document() {
+		let text = "aaaaaa\u{c}bbbbbb\u{c}cccccc\u{c}";
+		let pages = split_pages(text, PageBudget::new(4, 6));
+		let got: Vec<(u32, &str, bool)> = pages
+			.iter()
+			.map(|p| (p.number, p.text.text.as_str(), p.text.truncated))
+			.collect();
+		assert_eq!(got, vec![(1, "aaaa", true), (2, "bb", true)]);
+	}
+
+	#[test]
+	fn oversized_input_is_refused_before_spawning() {
+		let pdf = vec![0u8; MAX_INPUT_BYTES + 1];
+		let err = extract_pages(&pdf, PageBudget::new(16, 16));
+		assert!(matches!(err, Err(Error::ValidationError(_))));
+	}
+}
+
+// vim: ts=4
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Plain text and Markdown, and the whitespace-normalising accumulator every
+//! extractor in this crate writes through.
+//!
+//! Markdown is taken as it is written. Its markup is punctuation the search
+//! tokenizer already drops, so rendering it first would cost a parser and buy
+//! nothing the index can see.
+
+use cloudillo_types::prelude::*;
+
+/// Flattened visible text, plus whether the budget cut the walk short.
+#[derive(Debug, Default, Clone)]
+pub struct ExtractedText {
+	/// Whitespace-normalised text: no run of blanks, no leading or trailing one.
+	pub text: String,
+	/// `true` when `max_chars` was reached before the end of the input, so the
+	/// caller knows the text is a prefix rather than the whole document.
+	pub truncated: bool,
+}
+
+/// Largest plain-text document this extractor will read.
+///
+/// Same ceiling as [`crate::html::MAX_INPUT_BYTES`]: `max_chars` bounds the answer,
+/// and the lossy UTF-8 pass below is over the whole input regardless.
+pub const MAX_INPUT_BYTES: usize = 8 * 1024 * 1024;
+
+/// Extract the text of a plain-text or Markdown document.
+///
+/// Bytes rather than `&str`: a `.txt` upload is whatever encoding its editor wrote,
+/// and one stray Latin-1 byte must not cost the file its whole index entry. Invalid
+/// sequences become U+FFFD, which the tokenizer drops.
+pub fn extract_text(bytes: &[u8], max_chars: usize) -> ClResult<ExtractedText> {
+	if bytes.len() > MAX_INPUT_BYTES {
+		return Err(Error::ValidationError("text input exceeds the extraction limit".into()));
+	}
+	let mut flat = Flat::new(max_chars);
+	flat.push(&String::from_utf8_lossy(bytes));
+	Ok(flat.take())
+}
+
+/// Whitespace-normalising text accumulator with a character budget.
+///
+/// Format-specific walkers own the structure — where a word boundary is — and hand
+/// the text over in whatever pieces their parser produces; this owns the rest.
+#[derive(Debug)]
+pub(crate) struct Flat {
+	out: String,
+	budget: usize,
+	chars: usize,
+	truncated: bool,
+	/// A boundary is owed before the next visible character. Held rather than
+	/// written so the result never opens or closes with a blank.
+	pending: bool,
+}
+
+impl Flat {
+	pub(crate) fn new(budget: usize) -> Self {
+		Self { out: String::new(), budget, chars: 0, truncated: false, pending: false }
+	}
+
+	/// A word boundary from the document's structure.
+	pub(crate) fn mark_boundary(&mut self) {
+		if self.chars > 0 {
+			self.pending = true;
+		}
+	}
+
+	/// Whether the budget has been reached, so a walker can stop early.
+	pub(crate) fn is_full(&self) -> bool {
+		self.truncated
+	}
+
+	pub(crate) fn push(&mut self, text: &str) {
+		for c in text.chars() {
+			if self.chars >= self.budget {
+				self.truncated = true;
+				return;
+			}
+			if c.is_whitespace() {
+				self.mark_boundary();
+				continue;
+			}
+			if self.pending {
+				self.out.push(' ');
+				self.chars += 1;
+				self.pending = false;
+				if self.chars >= self.budget {
+					self.truncated = true;
+					return;
+				}
+			}
+			self.out.push(c);
+			self.chars += 1;
+		}
+	}
+
+	pub(crate) fn take(&mut self) -> ExtractedText {
+		ExtractedText { text: std::mem::take(&mut self.out), truncated: self.truncated }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn whitespace_is_normalised() {
+		let out = extract_text(b"  # Title\n\n\tsome   *text*  \r\n", 64).expect("extract");
+		assert_eq!(out.text, "# Title some *text*");
+		assert!(!out.truncated);
+	}
+
+	/// One stray byte from a legacy editor is not a reason to lose the document.
+	#[test]
+	fn invalid_utf8_is_replaced_rather_than_refused() {
+		let out = extract_text(b"caf\xe9 au lait", 64).expect("extract");
+		assert_eq!(out.text, "caf\u{fffd} au lait");
+	}
+
+	#[test]
+	fn the_budget_truncates_and_says_so() {
+		let out = extract_text(b"one two three", 7).expect("extract");
+		assert_eq!(out.text, "one two");
+		assert!(out.truncated);
+	}
+}
+
+// vim: ts=4
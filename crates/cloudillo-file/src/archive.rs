@@ -0,0 +1,525 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! ZIP downloads of folders and multi-selections.
+//!
+//! `GET /api/files/archive?fileId=a,b,c` walks the selection — folders recursively —
+//! and streams one archive while it downloads. Nothing is buffered beyond the chunk
+//! in flight: a BLOB's original is copied out of the blob store as it is read, and
+//! the ZIP's sizes and checksums go into data descriptors behind each entry, so no
+//! entry has to be known in full before its header is written.
+//!
+//! A BLOB is archived as its best original variant, stored as is — its content is
+//! already compressed more often than not. A CRDT or RTDB document has no bytes of
+//! its own, so it is exported as JSON: one object of `"{collection}/{doc_id}"` keys,
+//! the shape the search indexer reads, deflated.
+//!
+//! Access is checked per entry, not per selection: a folder may hold files shared
+//! more narrowly than itself, and those are left out rather than failing the walk.
+//! Only the selection itself must be readable as a whole.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+
+use axum::{
+	body::Body,
+	extract::{Query, State},
+	http::{Response, StatusCode, header},
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+use crate::descriptor;
+use crate::handler::GetFileVariantSelector;
+use crate::prelude::*;
+use cloudillo_core::extract::{IdTag, OptionalAuth};
+use cloudillo_core::file_access;
+use cloudillo_types::meta_adapter::{FileId, FileStatus, FileView};
+
+/// Most entries one archive may hold; a walk that finds more is refused.
+const MAX_ENTRIES: usize = 10_000;
+
+/// Deepest folder nesting the walk follows.
+const MAX_DEPTH: usize = 32;
+
+/// Files named in one request.
+const MAX_SELECTION: usize = 1_000;
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+	/// Comma-separated ids of the files and folders to archive
+	#[serde(rename = "fileId")]
+	file_id: String,
+	/// Download name, without the `.zip`; defaults to the selected folder's name
+	name: Option<String>,
+}
+
+/// What one entry's bytes come from.
+enum EntrySource {
+	Dir,
+	Blob { blob_tn: TnId, variant_id: Box<str> },
+	Crdt { file_id: Box<str> },
+	Rtdb { file_id: Box<str> },
+}
+
+struct ArchiveEntry {
+	path: String,
+	modified: Timestamp,
+	source: EntrySource,
+}
+
+/// Who the walk checks access for.
+struct Reader<'a> {
+	ctx: file_access::FileAccessCtx<'a>,
+	scope: Option<&'a str>,
+}
+
+impl Reader<'_> {
+	async fn can_read(&self, app: &App, tn_id: TnId, file_id: &str) -> bool {
+		file_access::check_file_access_with_scope(app, tn_id, file_id, &self.ctx, self.scope, None)
+			.await
+			.is_ok()
+	}
+}
+
+/// A file or folder name made safe as one ZIP path segment.
+///
+/// Separators would open directories the walk never made, and `.`/`..` would step
+/// out of the ones it did.
+fn entry_name(name: &str) -> String {
+	let name: String = name
+		.trim()
+		.chars()
+		.map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+		.collect();
+	if name.is_empty() || name == "." || name == ".." { "_".into() } else { name }
+}
+
+/// `name`, or `name (2).ext`, `name (3).ext`… — whichever `taken` does not hold
+/// yet. Two files may share a name in one folder; two ZIP entries may not.
+fn unique_name(taken: &mut HashSet<String>, name: String) -> String {
+	if taken.insert(name.to_lowercase()) {
+		return name;
+	}
+	let (stem, ext) = match name.rfind('.') {
+		Some(dot) if dot > 0 => name.split_at(dot),
+		_ => (name.as_str(), ""),
+	};
+	let mut n = 2;
+	loop {
+		let candidate = format!("{stem} ({n}){ext}");
+		if taken.insert(candidate.to_lowercase()) {
+			return candidate;
+		}
+		n += 1;
+	}
+}
+
+/// The segment a file is archived under: documents gain a `.json`, since that is
+/// what they are exported as.
+fn file_segment(file: &FileView) -> String {
+	let name = entry_name(&file.file_name);
+	match file.file_tp.as_deref() {
+		Some("CRDT" | "RTDB") if !name.to_lowercase().ends_with(".json") => format!("{name}.json"),
+		_ => name,
+	}
+}
+
+/// Walks the selection into a flat list of entries, parents before children.
+struct Plan<'a> {
+	app: &'a App,
+	tn_id: TnId,
+	reader: Reader<'a>,
+	entries: Vec<ArchiveEntry>,
+	visited: HashSet<Box<str>>,
+}
+
+impl Plan<'_> {
+	fn push(&mut self, entry: ArchiveEntry) -> ClResult<()> {
+		if self.entries.len() >= MAX_ENTRIES {
+			return Err(Error::ValidationError(format!(
+				"archive would hold more than {MAX_ENTRIES} entries"
+			)));
+		}
+		self.entries.push(entry);
+		Ok(())
+	}
+
+	/// Add `file` under `dir`, and a folder's readable contents below it.
+	async fn add(
+		&mut self,
+		dir: &str,
+		segment: String,
+		file: FileView,
+		depth: usize,
+	) -> ClResult<()> {
+		if !self.visited.insert(file.file_id.clone()) {
+			return Ok(());
+		}
+		let path = if dir.is_empty() { segment } else { format!("{dir}/{segment}") };
+		let modified = file.modified_at.unwrap_or(file.created_at);
+
+		let source = match file.file_tp.as_deref().unwrap_or("BLOB") {
+			"FLDR" => {
+				self.push(ArchiveEntry { path: path.clone(), modified, source: EntrySource::Dir })?;
+				if depth < MAX_DEPTH {
+					Box::pin(self.add_children(&path, &file.file_id, depth + 1)).await?;
+				}
+				return Ok(());
+			}
+			"CRDT" => EntrySource::Crdt { file_id: file.file_id },
+			"RTDB" => EntrySource::Rtdb { file_id: file.file_id },
+			_ => {
+				let mut variants = self
+					.app
+					.meta_adapter
+					.list_file_variants(self.tn_id, FileId::FileId(&file.file_id))
+					.await?;
+				variants.sort();
+				let selector = GetFileVariantSelector {
+					variant: None,
+					min_x: None,
+					min_y: None,
+					min_res: None,
+				};
+				// Metadata-only or unsynced: nothing on disk to put in the archive.
+				let Ok(variant) = descriptor::get_best_file_variant(&variants, &selector) else {
+					debug!("archive: no local variant of {}, skipped", file.file_id);
+					return Ok(());
+				};
+				EntrySource::Blob {
+					blob_tn: if variant.global { TnId(0) } else { self.tn_id },
+					variant_id: variant.variant_id.clone(),
+				}
+			}
+		};
+		self.push(ArchiveEntry { path, modified, source })
+	}
+
+	/// The readable, live children of `folder_id`, each under a name unique in `dir`.
+	async fn add_children(&mut self, dir: &str, folder_id: &str, depth: usize) -> ClResult<()> {
+		let f_ids = self
+			.app
+			.meta_adapter
+			.list_files_by_parent(self.tn_id, folder_id, Timestamp::now().add_seconds(1))
+			.await?;
+		let mut taken = HashSet::new();
+		for f_id in f_ids {
+			let file_id = self.app.meta_adapter.get_file_id(self.tn_id, f_id).await?;
+			let Some(file) = self.app.meta_adapter.read_file(self.tn_id, &file_id).await? else {
+				continue;
+			};
+			if !matches!(file.status, FileStatus::Active)
+				|| !self.reader.can_read(self.app, self.tn_id, &file_id).await
+			{
+				continue;
+			}
+			let segment = unique_name(&mut taken, file_segment(&file));
+			self.add(dir, segment, file, depth).await?;
+		}
+		Ok(())
+	}
+}
+
+// Writing
+//*********
+
+/// The `Write` end rawzip writes into. It only collects: [`drain`] hands what it
+/// holds to the response as soon as the write that produced it returns.
+#[derive(Clone, Default)]
+struct Spool(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Spool {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+async fn drain<W: AsyncWrite + Unpin>(spool: &Spool, out: &mut W) -> ClResult<()> {
+	let buf = std::mem::take(&mut *spool.0.lock());
+	if !buf.is_empty() {
+		out.write_all(&buf).await?;
+	}
+	Ok(())
+}
+
+#[allow(clippy::needless_pass_by_value)] // shaped for `map_err`
+fn zip_error(e: rawzip::Error) -> Error {
+	Error::Internal(format!("zip write failed: {e}"))
+}
+
+/// A document's content as the JSON object the archive stores.
+async fn export_document(app: &App, tn_id: TnId, source: &EntrySource) -> ClResult<Vec<u8>> {
+	let docs = match source {
+		EntrySource::Crdt { file_id } => {
+			let export = app.ext::<cloudillo_core::CrdtExportFn>()?;
+			export(app, tn_id, file_id).await?
+		}
+		EntrySource::Rtdb { file_id } => app.rtdb_adapter.export_all(tn_id, file_id).await?,
+		EntrySource::Dir | EntrySource::Blob { .. } => Vec::new(),
+	};
+	let object: serde_json::Map<String, serde_json::Value> =
+		docs.into_iter().map(|(path, value)| (path.into(), value)).collect();
+	Ok(serde_json::to_vec_pretty(&object)?)
+}
+
+/// Write the archive of `entries` to `out`.
+///
+/// An entry whose content cannot be opened is left out; one that fails after its
+/// header went out cannot be, and cuts the archive short — a truncated ZIP has no
+/// central directory, so no reader mistakes it for a whole one.
+async fn write_archive<W: AsyncWrite + Unpin>(
+	app: &App,
+	tn_id: TnId,
+	entries: Vec<ArchiveEntry>,
+	mut out: W,
+) -> ClResult<()> {
+	let spool = Spool::default();
+	let mut archive = rawzip::ZipArchiveWriter::new(spool.clone());
+
+	for entry in entries {
+		let modified = rawzip::time::UtcDateTime::from_unix(entry.modified.0);
+		match &entry.source {
+			EntrySource::Dir => {
+				archive
+					.new_dir(format!("{}/", entry.path).as_str())
+					.last_modified(modified)
+					.create()
+					.map_err(zip_error)?;
+			}
+			EntrySource::Blob { blob_tn, variant_id } => {
+				let mut stream = match app.blob_adapter.read_blob_stream(*blob_tn, variant_id).await
+				{
+					Ok(stream) => stream,
+					Err(e) => {
+						warn!("archive: blob {} unreadable, skipped: {}", variant_id, e);
+						continue;
+					}
+				};
+				let (mut file, config) = archive
+					.new_file(entry.path.as_str())
+					.last_modified(modified)
+					.start()
+					.map_err(zip_error)?;
+				let mut data = config.wrap(&mut file);
+				while let Some(chunk) = stream.next().await {
+					data.write_all(&chunk?)?;
+					drain(&spool, &mut out).await?;
+				}
+				let (_, descriptor) = data.finish().map_err(zip_error)?;
+				file.finish(descriptor).map_err(zip_error)?;
+			}
+			EntrySource::Crdt { .. } | EntrySource::Rtdb { .. } => {
+				let json = match export_document(app, tn_id, &entry.source).await {
+					Ok(json) => json,
+					Err(e) => {
+						warn!("archive: document {} not exported, skipped: {}", entry.path, e);
+						continue;
+					}
+				};
+				let (mut file, config) = archive
+					.new_file(entry.path.as_str())
+					.compression_method(rawzip::CompressionMethod::DEFLATE)
+					.last_modified(modified)
+					.start()
+					.map_err(zip_error)?;
+				let encoder =
+					flate2::write::DeflateEncoder::new(&mut file, flate2::Compression::default());
+				let mut data = config.wrap(encoder);
+				data.write_all(&json)?;
+				let (encoder, descriptor) = data.finish().map_err(zip_error)?;
+				encoder.finish()?;
+				file.finish(descriptor).map_err(zip_error)?;
+			}
+		}
+		drain(&spool, &mut out).await?;
+	}
+
+	archive.finish().map_err(zip_error)?;
+	drain(&spool, &mut out).await?;
+	out.shutdown().await?;
+	Ok(())
+}
+
+/// `attachment` disposition for `name`.zip, with an ASCII fallback for clients
+/// that ignore `filename*`.
+fn content_disposition(name: &str) -> String {
+	let ascii: String = name
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || " ._-()".contains(c) { c } else { '_' })
+		.collect();
+	let encoded: String = format!("{name}.zip")
+		.bytes()
+		.map(|b| {
+			if b.is_ascii_alphanumeric() || b"._-".contains(&b) {
+				char::from(b).to_string()
+			} else {
+				format!("%{b:02X}")
+			}
+		})
+		.collect();
+	format!("attachment; filename=\"{ascii}.zip\"; filename*=UTF-8''{encoded}")
+}
+
+// Handlers
+//**********
+
+/// GET /api/files/archive?fileId=a,b,c - Stream a ZIP of files and folders
+///
+/// Every selected file must be readable; the walk below a folder keeps only the
+/// entries that are. Files above the caller's share scope are out of reach like
+/// they are for any other read. An archive is a download of originals, so a
+/// view-only share link gets none.
+pub async fn get_archive(
+	State(app): State<App>,
+	tn_id: TnId,
+	IdTag(tenant_id_tag): IdTag,
+	OptionalAuth(maybe_auth): OptionalAuth,
+	Query(query): Query<ArchiveQuery>,
+) -> ClResult<Response<Body>> {
+	let file_ids: Vec<&str> =
+		query.file_id.split(',').map(str::trim).filter(|id| !id.is_empty()).collect();
+	if file_ids.is_empty() {
+		return Err(Error::ValidationError("fileId is required".into()));
+	}
+	if file_ids.len() > MAX_SELECTION {
+		return Err(Error::ValidationError(format!(
+			"at most {MAX_SELECTION} files can be archived at once"
+		)));
+	}
+
+	let (user_id_tag, user_roles, scope) = match &maybe_auth {
+		Some(auth) => (auth.id_tag.as_ref(), &auth.roles[..], auth.scope.as_deref()),
+		None => ("guest", &[][..], None),
+	};
+	if file_access::is_view_only_scope(scope) {
+		return Err(Error::PermissionDenied);
+	}
+	let reader = Reader {
+		ctx: file_access::FileAccessCtx { user_id_tag, tenant_id_tag: &tenant_id_tag, user_roles },
+		scope,
+	};
+
+	let mut selection = Vec::with_capacity(file_ids.len());
+	for file_id in &file_ids {
+		let access = file_access::check_file_access_with_scope(
+			&app,
+			tn_id,
+			file_id,
+			&reader.ctx,
+			scope,
+			None,
+		)
+		.await
+		.map_err(|e| match e {
+			file_access::FileAccessError::NotFound => Error::NotFound,
+			file_access::FileAccessError::AccessDenied => Error::PermissionDenied,
+			file_access::FileAccessError::InternalError(m) => Error::Internal(m),
+		})?;
+		if !matches!(access.file_view.status, FileStatus::Active) {
+			return Err(Error::NotFound);
+		}
+		selection.push(access.file_view);
+	}
+
+	let name = match (query.name.as_deref().map(str::trim), &selection[..]) {
+		(Some(name), _) if !name.is_empty() => entry_name(name),
+		(_, [only]) => entry_name(&only.file_name),
+		_ => "files".into(),
+	};
+
+	let mut plan = Plan { app: &app, tn_id, reader, entries: Vec::new(), visited: HashSet::new() };
+	let mut taken = HashSet::new();
+	for file in selection {
+		let segment = unique_name(&mut taken, file_segment(&file));
+		plan.add("", segment, file, 0).await?;
+	}
+	let entries = plan.entries;
+	info!(
+		"Archiving {} entries of {} selected files as {}.zip",
+		entries.len(),
+		file_ids.len(),
+		name
+	);
+
+	let (reader, writer) = tokio::io::duplex(64 * 1024);
+	let task_app = app.clone();
+	tokio::spawn(async move {
+		if let Err(e) = write_archive(&task_app, tn_id, entries, writer).await {
+			warn!(tn_id = %tn_id, error = ?e, "file archive cut short");
+		}
+	});
+
+	Ok(Response::builder()
+		.status(StatusCode::OK)
+		.header(header::CONTENT_TYPE, "application/zip")
+		.header(header::CONTENT_DISPOSITION, content_disposition(&name))
+		.header(header::CACHE_CONTROL, "no-store")
+		.body(Body::from_stream(ReaderStream::new(reader)))?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn names_cannot_leave_their_folder() {
+		assert_eq!(entry_name("a/b\\c"), "a_b_c");
+		assert_eq!(entry_name(".."), "_");
+		assert_eq!(entry_name("  "), "_");
+		assert_eq!(entry_name("report.pdf"), "report.pdf");
+	}
+
+	#[test]
+	fn clashing_names_are_numbered_before_the_extension() {
+		let mut taken = HashSet::new();
+		assert_eq!(unique_name(&mut taken, "a.txt".into()), "a.txt");
+		assert_eq!(unique_name(&mut taken, "A.txt".into()), "A (2).txt");
+		assert_eq!(unique_name(&mut taken, "a.txt".into()), "a (3).txt");
+		assert_eq!(unique_name(&mut taken, ".env".into()), ".env");
+		assert_eq!(unique_name(&mut taken, ".env".into()), ".env (2)");
+	}
+
+	#[test]
+	fn disposition_keeps_the_name_for_clients_that_read_it() {
+		assert_eq!(
+			content_disposition("Fotók 2024"),
+			"attachment; filename=\"Fot_k 2024.zip\"; filename*=UTF-8''Fot%C3%B3k%202024.zip"
+		);
+	}
+
+	#[test]
+	fn spooled_archive_reads_back() {
+		let spool = Spool::default();
+		let mut out = Vec::new();
+		let mut archive = rawzip::ZipArchiveWriter::new(spool.clone());
+		archive.new_dir("docs/").create().expect("dir");
+		let (mut file, config) = archive.new_file("docs/a.txt").start().expect("file");
+		let mut data = config.wrap(&mut file);
+		data.write_all(b"hello").expect("write");
+		futures::executor::block_on(drain(&spool, &mut out)).expect("drain");
+		let (_, descriptor) = data.finish().expect("finish data");
+		file.finish(descriptor).expect("finish file");
+		archive.finish().expect("finish archive");
+		futures::executor::block_on(drain(&spool, &mut out)).expect("drain");
+
+		let archive = rawzip::ZipArchive::from_slice(&out).expect("readable");
+		let mut entries = archive.entries();
+		let mut paths = Vec::new();
+		while let Some(entry) = entries.next_entry().expect("entry") {
+			paths.push(entry.file_path().try_normalize().expect("path").as_ref().to_string());
+		}
+		assert_eq!(paths, vec!["docs/", "docs/a.txt"]);
+	}
+}
+
+// vim: ts=4
@@ -328,6 +328,243 @@ pub fn inflate_bounded(deflate_data: &[u8], max_output: u64) -> Result<Vec<u8>,
 	Ok(output)
 }
 
+/// Bytes read off the end of a container to find its end-of-central-directory record:
+/// the record itself, the longest comment it may carry, and the zip64 locator before it.
+pub const EOCD_SEARCH_BYTES: u64 = 22 + 0xFFFF + 20;
+
+/// Largest central directory [`parse_central_directory`] will read.
+///
+/// A stream container holds a playlist and one media file per rendition, so its
+/// directory is a few hundred bytes; this bounds what a crafted one could make a cold
+/// open allocate.
+pub const MAX_CENTRAL_DIRECTORY_BYTES: u64 = 1024 * 1024;
+
+/// Largest entry count [`parse_central_directory`] accepts. Each entry costs a
+/// local-header range read on a cold open, so the count is the real cost here.
+pub const MAX_TAIL_ENTRIES: usize = 256;
+
+/// Size of a local file header up to its file name.
+pub const LOCAL_HEADER_BYTES: u64 = 30;
+
+const EOCD_SIG: u32 = 0x0605_4b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x0706_4b50;
+const ZIP64_EOCD_SIG: u32 = 0x0606_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+
+fn le_u16(data: &[u8], at: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn le_u64(data: &[u8], at: usize) -> Option<u64> {
+	Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn invalid(what: &str) -> Error {
+	Error::ValidationError(format!("Invalid zip archive: {what}"))
+}
+
+/// Where a container's central directory sits, read off its tail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailLocation {
+	/// The central directory: `(offset, size)`.
+	CentralDirectory(u64, u64),
+	/// A zip64 archive: the offset of its zip64 end-of-central-directory record, which
+	/// has to be read before the directory can be found.
+	Zip64Record(u64),
+}
+
+/// Find the central directory from the last bytes of a container.
+///
+/// `tail` is the blob's final bytes — [`EOCD_SEARCH_BYTES`] of them, or all of a smaller
+/// blob — and `tail_offset` where they start. The record is searched for backwards, as
+/// the comment after it may contain anything.
+pub fn locate_central_directory(tail: &[u8], tail_offset: u64) -> ClResult<TailLocation> {
+	let eocd = (0..tail.len().saturating_sub(21))
+		.rev()
+		.find(|&at| le_u32(tail, at) == Some(EOCD_SIG))
+		.ok_or_else(|| invalid("no end of central directory"))?;
+	let cd_size = le_u32(tail, eocd + 12).ok_or_else(|| invalid("truncated record"))?;
+	let cd_offset = le_u32(tail, eocd + 16).ok_or_else(|| invalid("truncated record"))?;
+
+	if (cd_size == u32::MAX || cd_offset == u32::MAX)
+		&& let Some(locator) = eocd.checked_sub(20)
+		&& le_u32(tail, locator) == Some(ZIP64_LOCATOR_SIG)
+	{
+		let record = le_u64(tail, locator + 8).ok_or_else(|| invalid("truncated locator"))?;
+		return Ok(TailLocation::Zip64Record(record));
+	}
+
+	let (cd_offset, cd_size) = (u64::from(cd_offset), u64::from(cd_size));
+	if cd_offset.saturating_add(cd_size) > tail_offset + eocd as u64 {
+		return Err(invalid("central directory past its end record"));
+	}
+	Ok(TailLocation::CentralDirectory(cd_offset, cd_size))
+}
+
+/// The `(offset, size)` of the central directory, from a zip64 end-of-central-directory
+/// record.
+pub fn parse_zip64_record(record: &[u8]) -> ClResult<(u64, u64)> {
+	if le_u32(record, 0) != Some(ZIP64_EOCD_SIG) {
+		return Err(invalid("bad zip64 end of central directory"));
+	}
+	let size = le_u64(record, 40).ok_or_else(|| invalid("truncated zip64 record"))?;
+	let offset = le_u64(record, 48).ok_or_else(|| invalid("truncated zip64 record"))?;
+	Ok((offset, size))
+}
+
+/// One central directory entry, before its local header has been read.
+#[derive(Debug, Clone)]
+pub struct CentralEntry {
+	pub path: Box<str>,
+	pub local_header_offset: u64,
+	pub compressed_size: u64,
+	pub uncompressed_size: u64,
+	pub crc32: u32,
+	pub is_deflated: bool,
+}
+
+impl CentralEntry {
+	/// The index entry, once the local header at `local_header_offset` has said how
+	/// long its name and extra field are — see [`local_data_offset`].
+	pub fn into_info(self, data_offset: u64) -> (Box<str>, ZipEntryInfo) {
+		let content_type = mime_from_path(&self.path);
+		let info = ZipEntryInfo {
+			data_offset,
+			compressed_size: self.compressed_size,
+			uncompressed_size: self.uncompressed_size,
+			crc32: self.crc32,
+			is_deflated: self.is_deflated,
+			content_type,
+		};
+		(self.path, info)
+	}
+}
+
+/// Parse a central directory read on its own, without the rest of the archive.
+///
+/// Applies [`parse_zip_index`]'s rules: directories are skipped, a path that does not
+/// normalize fails the container, and a compression method this reader cannot honour
+/// leaves the entry out.
+pub fn parse_central_directory(cd: &[u8]) -> ClResult<Vec<CentralEntry>> {
+	let mut entries = Vec::new();
+	let mut at = 0;
+	while at < cd.len() {
+		if le_u32(cd, at) != Some(CENTRAL_HEADER_SIG) {
+			return Err(invalid("bad central directory header"));
+		}
+		let field = |off: usize| le_u32(cd, at + off).ok_or_else(|| invalid("truncated header"));
+		let short = |off: usize| le_u16(cd, at + off).ok_or_else(|| invalid("truncated header"));
+		let method = short(10)?;
+		let crc32 = field(16)?;
+		let mut compressed_size = u64::from(field(20)?);
+		let mut uncompressed_size = u64::from(field(24)?);
+		let name_len = usize::from(short(28)?);
+		let extra_len = usize::from(short(30)?);
+		let comment_len = usize::from(short(32)?);
+		let mut local_header_offset = u64::from(field(42)?);
+
+		let name_start = at + 46;
+		let extra_start = name_start + name_len;
+		let next = extra_start + extra_len + comment_len;
+		let name = cd.get(name_start..extra_start).ok_or_else(|| invalid("truncated name"))?;
+		let extra = cd
+			.get(extra_start..extra_start + extra_len)
+			.ok_or_else(|| invalid("truncated extra"))?;
+		if next > cd.len() {
+			return Err(invalid("truncated central directory"));
+		}
+		at = next;
+
+		// Zip64 extended information: only the fields whose 32-bit value is saturated,
+		// in this order.
+		let mut x = 0;
+		while x + 4 <= extra.len() {
+			let id = le_u16(extra, x).unwrap_or(0);
+			let len = usize::from(le_u16(extra, x + 2).unwrap_or(0));
+			let body = extra.get(x + 4..x + 4 + len).ok_or_else(|| invalid("truncated extra"))?;
+			if id == 0x0001 {
+				let mut fields = body.chunks_exact(8).map(|c| le_u64(c, 0).unwrap_or(0));
+				for value in
+					[&mut uncompressed_size, &mut compressed_size, &mut local_header_offset]
+				{
+					if *value == u64::from(u32::MAX) {
+						*value = fields.next().ok_or_else(|| invalid("short zip64 field"))?;
+					}
+				}
+			}
+			x += 4 + len;
+		}
+
+		let path = rawzip::path::ZipFilePath::from_bytes(name);
+		if path.is_dir() {
+			continue;
+		}
+		let normalized = path.try_normalize().map_err(|e| invalid(&e.to_string()))?;
+		let is_deflated = match method {
+			8 => true,
+			0 => false,
+			_ => {
+				warn!(path = %normalized.as_str(), method, "Skipping container entry with unsupported compression");
+				continue;
+			}
+		};
+		entries.push(CentralEntry {
+			path: normalized.as_str().into(),
+			local_header_offset,
+			compressed_size,
+			uncompressed_size,
+			crc32,
+			is_deflated,
+		});
+		if entries.len() > MAX_TAIL_ENTRIES {
+			return Err(invalid("too many entries"));
+		}
+	}
+	Ok(entries)
+}
+
+/// Where an entry's data starts, from the first [`LOCAL_HEADER_BYTES`] of its local
+/// header. The local name and extra field need not match the central directory's, so
+/// only the local header can say.
+pub fn local_data_offset(header: &[u8], local_header_offset: u64) -> ClResult<u64> {
+	if le_u32(header, 0) != Some(LOCAL_HEADER_SIG) {
+		return Err(invalid("bad local header"));
+	}
+	let name_len = le_u16(header, 26).ok_or_else(|| invalid("truncated local header"))?;
+	let extra_len = le_u16(header, 28).ok_or_else(|| invalid("truncated local header"))?;
+	Ok(local_header_offset + LOCAL_HEADER_BYTES + u64::from(name_len) + u64::from(extra_len))
+}
+
+/// Write `files` as an uncompressed zip, in the order given.
+///
+/// Stored rather than deflated: what goes in here is already-compressed media, and a
+/// stored entry is what lets a byte range of the entry be served straight off the blob.
+pub fn write_stored_zip<W, R>(out: W, files: impl IntoIterator<Item = (String, R)>) -> ClResult<W>
+where
+	W: std::io::Write,
+	R: std::io::Read,
+{
+	let zip_err = |e: rawzip::Error| Error::Internal(format!("zip write failed: {e}"));
+	let mut archive = rawzip::ZipArchiveWriter::new(out);
+	for (path, mut reader) in files {
+		let (mut entry, config) = archive
+			.new_file(path.as_str())
+			.compression_method(rawzip::CompressionMethod::STORE)
+			.start()
+			.map_err(zip_err)?;
+		let mut writer = config.wrap(&mut entry);
+		std::io::copy(&mut reader, &mut writer)?;
+		let (_, descriptor) = writer.finish().map_err(zip_err)?;
+		entry.finish(descriptor).map_err(zip_err)?;
+	}
+	archive.finish().map_err(zip_err)
+}
+
 /// Infer MIME type from file path extension
 fn mime_from_path(path: &str) -> &'static str {
 	let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
@@ -351,6 +588,13 @@ fn mime_from_path(path: &str) -> &'static str {
 		"txt" => "text/plain; charset=utf-8",
 		"xml" => "application/xml; charset=utf-8",
 		"map" => "application/json",
+		// Adaptive streaming
+		"m3u8" => "application/vnd.apple.mpegurl",
+		"mpd" => "application/dash+xml",
+		"m4s" => "video/iso.segment",
+		"mp4" => "video/mp4",
+		"m4a" => "audio/mp4",
+		"ts" => "video/mp2t",
 		_ => "application/octet-stream",
 	}
 }
@@ -512,6 +756,53 @@ mod tests {
 		}
 	}
 
+	/// Read through the tail alone, a container must index exactly as the whole-blob
+	/// parse does — same entries, same data offsets.
+	#[test]
+	fn the_tail_parse_agrees_with_the_whole_blob_parse() {
+		let files = [
+			("master.m3u8".to_string(), b"#EXTM3U\n".to_vec()),
+			("sd/index.m3u8".to_string(), b"#EXTM3U\n#EXT-X-VERSION:7\n".to_vec()),
+			("sd/stream.m4s".to_string(), vec![7_u8; 4096]),
+		];
+		let blob = write_stored_zip(
+			Vec::new(),
+			files.iter().map(|(path, data)| (path.clone(), data.as_slice())),
+		)
+		.expect("write");
+		let whole = parse_zip_index(&blob, "v1").expect("whole parse");
+
+		let start = blob.len().saturating_sub(usize::try_from(EOCD_SEARCH_BYTES).unwrap_or(0));
+		let TailLocation::CentralDirectory(offset, size) =
+			locate_central_directory(&blob[start..], start as u64).expect("locate")
+		else {
+			panic!("a small archive needs no zip64 record");
+		};
+		let cd = &blob[usize::try_from(offset).unwrap()..usize::try_from(offset + size).unwrap()];
+		let entries = parse_central_directory(cd).expect("central directory");
+		assert_eq!(entries.len(), 3);
+		for entry in entries {
+			let lho = usize::try_from(entry.local_header_offset).unwrap();
+			let data_offset =
+				local_data_offset(&blob[lho..lho + 30], entry.local_header_offset).expect("local");
+			let (path, info) = entry.into_info(data_offset);
+			let expected = whole.entries.get(&path).expect("same entry");
+			assert_eq!(info.data_offset, expected.data_offset, "{path}");
+			assert_eq!(info.compressed_size, expected.compressed_size, "{path}");
+			assert!(!info.is_deflated);
+		}
+		let m4s = whole.entries.get("sd/stream.m4s").expect("segment");
+		assert_eq!(m4s.content_type, "video/iso.segment");
+		let at = usize::try_from(m4s.data_offset).unwrap();
+		assert_eq!(&blob[at..at + 4096], &[7_u8; 4096][..]);
+	}
+
+	#[test]
+	fn a_tail_without_an_end_record_is_refused() {
+		assert!(locate_central_directory(&[0_u8; 64], 0).is_err());
+		assert!(parse_central_directory(&[1, 2, 3, 4]).is_err());
+	}
+
 	/// The declared uncompressed size comes off the zip header, so this cap is all that
 	/// stands between a crafted entry and the heap — and one that exactly fills the budget
 	/// is still legitimate.
@@ -10,6 +10,12 @@
 //! Format examples:
 //! d1~tn:b1~abc123:f=webp:s=2048:r=128x128,sd:b1~def456:f=webp:s=10240:r=720x720
 //! d2,vis.tn:b1,abc123:f=webp:s=2048:r=128x128;vis.sd:b1,def456:f=webp:s=10240:r=720x720:dur=120.5:br=5000
+//!
+//! A d2 descriptor may open with file-level entries before the variants: `R=<root_id>;`
+//! for a document-tree child, then `P=<placeholder>;` for an image placeholder (see
+//! [`crate::placeholder`]), then `D=<link>;` for a file dropped through an upload-only
+//! share link (the link's digest, as kept in `x.dropLink`). The last keeps a drop from
+//! sharing its content id with a file the owner already has.
 
 use async_trait::async_trait;
 use itertools::Itertools;
@@ -17,19 +23,25 @@ use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, fmt::Write, sync::Arc};
 
 use crate::handler::GetFileVariantSelector;
+use crate::placeholder;
 use crate::prelude::*;
-use crate::variant::{Variant, VariantClass};
+use crate::variant::{Variant, VariantClass, VariantQuality};
+use crate::version;
 use cloudillo_core::scheduler::{Task, TaskId};
 use cloudillo_types::hasher::Hasher;
 use cloudillo_types::meta_adapter;
 
 /// Generate a d2 file descriptor.
 ///
-/// `root_id` must be passed explicitly. Pass `None` for files with no document-tree root,
-/// `Some(root)` otherwise. Forgetting it means the bytes will not match the file_id hash.
+/// `root_id`, `placeholder` and `drop_link` must be passed explicitly. Pass `None` for
+/// files with no document-tree root, no placeholder or not dropped through a link,
+/// `Some(..)` otherwise. Forgetting any of them means the bytes will not match the
+/// file_id hash.
 pub fn get_file_descriptor<S: AsRef<str> + Debug + Eq>(
 	variants: &[meta_adapter::FileVariant<S>],
 	root_id: Option<&str>,
+	placeholder: Option<&str>,
+	drop_link: Option<&str>,
 ) -> String {
 	let mut result = String::from("d2,");
 	if let Some(root) = root_id {
@@ -37,6 +49,16 @@ pub fn get_file_descriptor<S: AsRef<str> + Debug + Eq>(
 		result.push_str(root);
 		result.push(';');
 	}
+	if let Some(placeholder) = placeholder {
+		result.push_str("P=");
+		result.push_str(placeholder);
+		result.push(';');
+	}
+	if let Some(link) = drop_link {
+		result.push_str("D=");
+		result.push_str(link);
+		result.push(';');
+	}
 	let body = variants
 		.iter()
 		.map(|v| {
@@ -124,24 +146,32 @@ fn parse_variant_entry(
 	}
 }
 
+/// A descriptor's file-level entries, and its variants.
+pub type ParsedDescriptor<'a> =
+	(Option<&'a str>, Option<&'a str>, Option<&'a str>, Vec<meta_adapter::FileVariant<&'a str>>);
+
 /// Parse file descriptor (supports both d1 and d2 formats).
 ///
-/// Returns the optional `root_id` (only carried by d2) alongside the variants so the
-/// descriptor can be regenerated byte-identically.
-pub fn parse_file_descriptor(
-	descriptor: &str,
-) -> ClResult<(Option<&str>, Vec<meta_adapter::FileVariant<&str>>)> {
+/// Returns the optional `root_id`, `placeholder` and `drop_link` (only carried by d2) alongside the
+/// variants so the descriptor can be regenerated byte-identically.
+pub fn parse_file_descriptor(descriptor: &str) -> ClResult<ParsedDescriptor<'_>> {
 	if let Some(body) = descriptor.strip_prefix("d2,") {
 		let mut root_id: Option<&str> = None;
+		let mut placeholder: Option<&str> = None;
+		let mut drop_link: Option<&str> = None;
 		let mut variants = Vec::new();
 		for entry in body.split(';').filter(|s| !s.is_empty()) {
 			if let Some(rest) = entry.strip_prefix("R=") {
 				root_id = Some(rest);
+			} else if let Some(rest) = entry.strip_prefix("P=") {
+				placeholder = Some(rest);
+			} else if let Some(rest) = entry.strip_prefix("D=") {
+				drop_link = Some(rest);
 			} else {
 				variants.push(parse_variant_entry(entry, ',')?);
 			}
 		}
-		Ok((root_id, variants))
+		Ok((root_id, placeholder, drop_link, variants))
 	} else if let Some(body) = descriptor.strip_prefix("d1~") {
 		// V1 never carried a root_id.
 		let variants = body
@@ -149,7 +179,7 @@ pub fn parse_file_descriptor(
 			.filter(|s| !s.is_empty())
 			.map(|entry| parse_variant_entry(entry, '~'))
 			.collect::<ClResult<_>>()?;
-		Ok((None, variants))
+		Ok((None, None, None, variants))
 	} else {
 		Err(Error::Parse)
 	}
@@ -263,10 +293,15 @@ pub fn get_best_file_variant<'a, S: AsRef<str> + Debug + Eq>(
 	// variant (e.g. `hd`/`md`/`xd`/`orig` never mention `pf`). When the only
 	// locally-available variant is off-chain — as with a sync'd profile pic
 	// where just `vis.pf` was fetched — serve the best available variant in
-	// the class rather than 404ing.
+	// the class rather than 404ing. A stream container is no playable file
+	// on its own, so it never stands in for one.
 	let best = best.or_else(|_| {
 		class_filtered
 			.iter()
+			.filter(|v| {
+				Variant::parse(v.variant.as_ref())
+					.is_none_or(|p| p.quality != VariantQuality::Stream)
+			})
 			.max_by_key(|v| u64::from(v.resolution.0) * u64::from(v.resolution.1))
 			.copied()
 			.ok_or(Error::NotFound)
@@ -319,15 +354,15 @@ impl Task<App> for FileIdGeneratorTask {
 			.await?;
 		variants.sort();
 
-		// Read root_id from file metadata (for document tree support)
+		// Read root_id (document tree support), the placeholder and the drop link from file metadata
 		let file_id_str = format!("@{}", self.f_id);
-		let root_id = app
-			.meta_adapter
-			.read_file(self.tn_id, &file_id_str)
-			.await?
-			.and_then(|f| f.root_id);
+		let file = app.meta_adapter.read_file(self.tn_id, &file_id_str).await?;
+		let root_id = file.as_ref().and_then(|f| f.root_id.clone());
+		let placeholder = file.as_ref().and_then(|f| placeholder::of_x(f.x.as_ref()));
+
+		let drop_link = file.as_ref().and_then(|f| crate::handler::drop_link_of_x(f.x.as_ref()));
 
-		let descriptor = get_file_descriptor(&variants, root_id.as_deref());
+		let descriptor = get_file_descriptor(&variants, root_id.as_deref(), placeholder, drop_link);
 
 		let mut hasher = Hasher::new();
 		hasher.update(descriptor.as_bytes());
@@ -339,6 +374,15 @@ impl Task<App> for FileIdGeneratorTask {
 		// `file_id` at all.
 		cloudillo_core::search_index_file(app, self.tn_id, &file_id);
 
+		// A revision uploaded with `versionOf` takes its file's place now that it has
+		// a content id to be listed and shared under.
+		if let Some(file) = &file
+			&& file.version_of.is_some()
+			&& file.parent_id.as_deref() == Some(meta_adapter::VERSIONS_PARENT_ID)
+		{
+			version::promote(app, self.tn_id, &file_id).await?;
+		}
+
 		// Broadcast FILE_ID_GENERATED event to all connections on this tenant
 		// Frontend clients will filter based on whether they're tracking this temp ID
 		let msg = cloudillo_core::ws_broadcast::BroadcastMessage::new(
@@ -365,7 +409,7 @@ mod tests {
 	#[test]
 	fn test_parse_d1_descriptor() {
 		let desc = "d1~tn:b1~abc123:f=webp:s=2048:r=128x128,sd:b1~def456:f=webp:s=10240:r=720x720";
-		let (root_id, variants) = parse_file_descriptor(desc).unwrap();
+		let (root_id, _, _, variants) = parse_file_descriptor(desc).unwrap();
 
 		assert_eq!(root_id, None);
 		assert_eq!(variants.len(), 2);
@@ -383,7 +427,7 @@ mod tests {
 	fn test_parse_d2_descriptor() {
 		// Note: variant_ids keep the ~ separator (b1~hash), only descriptor prefix uses comma (d2,)
 		let desc = "d2,vis.tn:b1~abc123:f=webp:s=2048:r=128x128;vis.sd:b1~def456:f=webp:s=10240:r=720x720:dur=120.5:br=5000";
-		let (root_id, variants) = parse_file_descriptor(desc).unwrap();
+		let (root_id, _, _, variants) = parse_file_descriptor(desc).unwrap();
 
 		assert_eq!(root_id, None);
 		assert_eq!(variants.len(), 2);
@@ -429,7 +473,7 @@ mod tests {
 			},
 		];
 
-		let desc = get_file_descriptor(&variants, None);
+		let desc = get_file_descriptor(&variants, None, None, None);
 		assert!(desc.starts_with("d2,"));
 		// variant_ids keep their ~ separator
 		assert!(desc.contains("vis.tn:b1~abc123"));
@@ -455,7 +499,7 @@ mod tests {
 			page_count: None,
 		}];
 
-		let desc = get_file_descriptor(&variants, Some("f1~rootid"));
+		let desc = get_file_descriptor(&variants, Some("f1~rootid"), None, None);
 		assert!(desc.starts_with("d2,R=f1~rootid;"));
 		assert!(desc.contains("vis.tn:b1~abc123"));
 	}
@@ -463,7 +507,7 @@ mod tests {
 	#[test]
 	fn test_parse_d2_descriptor_with_root() {
 		let desc = "d2,R=f1~rootid;vis.tn:b1~abc123:f=webp:s=2048:r=128x128";
-		let (root_id, variants) = parse_file_descriptor(desc).unwrap();
+		let (root_id, _, _, variants) = parse_file_descriptor(desc).unwrap();
 		assert_eq!(root_id, Some("f1~rootid"));
 		assert_eq!(variants.len(), 1);
 		assert_eq!(variants[0].variant, "vis.tn");
@@ -497,15 +541,64 @@ mod tests {
 				page_count: None,
 			},
 		];
-		let generated = get_file_descriptor(&variants, Some("f1~root"));
-		let (parsed_root, parsed_variants) = parse_file_descriptor(&generated).unwrap();
+		let generated = get_file_descriptor(&variants, Some("f1~root"), None, None);
+		let (parsed_root, _, _, parsed_variants) = parse_file_descriptor(&generated).unwrap();
 		assert_eq!(parsed_root, Some("f1~root"));
 		assert_eq!(parsed_variants.len(), 2);
 		// Regenerating from the parsed pieces must reproduce the original bytes exactly.
-		let regenerated = get_file_descriptor(&parsed_variants, parsed_root);
+		let regenerated = get_file_descriptor(&parsed_variants, parsed_root, None, None);
 		assert_eq!(regenerated, generated);
 	}
 
+	#[test]
+	fn test_placeholder_round_trips() {
+		let variants = vec![meta_adapter::FileVariant {
+			variant: "vis.tn",
+			variant_id: "b1~abc123",
+			format: "webp",
+			size: 2048,
+			resolution: (128, 96),
+			available: true,
+			global: false,
+			duration: None,
+			bitrate: None,
+			page_count: None,
+		}];
+		let generated = get_file_descriptor(
+			&variants,
+			Some("f1~root"),
+			Some("1QcSHQRnh493V4dIh4eXh1h4kJUI"),
+			None,
+		);
+		assert!(generated.starts_with("d2,R=f1~root;P=1QcSHQRnh493V4dIh4eXh1h4kJUI;vis.tn:"));
+		let (root, placeholder, drop_link, parsed) = parse_file_descriptor(&generated).unwrap();
+		assert_eq!(placeholder, Some("1QcSHQRnh493V4dIh4eXh1h4kJUI"));
+		assert_eq!(get_file_descriptor(&parsed, root, placeholder, drop_link), generated);
+	}
+
+	#[test]
+	fn test_drop_link_changes_the_file_id() {
+		let variants = vec![meta_adapter::FileVariant {
+			variant: "orig",
+			variant_id: "b1~abc123",
+			format: "pdf",
+			size: 2048,
+			resolution: (0, 0),
+			available: true,
+			global: false,
+			duration: None,
+			bitrate: None,
+			page_count: None,
+		}];
+		let owned = get_file_descriptor(&variants, None, None, None);
+		let dropped = get_file_descriptor(&variants, None, None, Some("r1~link"));
+		assert_ne!(owned, dropped);
+		assert!(dropped.starts_with("d2,D=r1~link;orig:"));
+		let (root, placeholder, drop_link, parsed) = parse_file_descriptor(&dropped).unwrap();
+		assert_eq!(drop_link, Some("r1~link"));
+		assert_eq!(get_file_descriptor(&parsed, root, placeholder, drop_link), dropped);
+	}
+
 	#[test]
 	fn test_variant_matches() {
 		// Direct match
@@ -0,0 +1,375 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Server-side image edits, stored as derived files.
+//!
+//! An edit never touches its source: the operations are applied to the source's
+//! best stored rendition and the result is uploaded as a new content-addressed
+//! file, variants and all, exactly as if the user had uploaded the edited image.
+//! The source keeps its id, so every post already attaching it keeps showing what
+//! it showed. What the new file was made from is recorded in its `x.derived` —
+//! the source id and the operation list, replayable against the source.
+
+use axum::{
+	Json,
+	extract::{Path, State},
+	http::StatusCode,
+};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::image::{self as img, ImageFormat, MAX_DECODE_DIM};
+use crate::prelude::*;
+use crate::{handler, preset::presets, quota};
+use cloudillo_core::dir_cache::DirCache;
+use cloudillo_core::extract::{Auth, IdTag, OptionalRequestId};
+use cloudillo_core::file_access;
+use cloudillo_core::storage_quota;
+use cloudillo_types::hasher;
+use cloudillo_types::meta_adapter;
+use cloudillo_types::types::ApiResponse;
+
+/// Operations one edit may apply. Each is a full pass over the pixels.
+pub const MAX_OPS: usize = 32;
+
+/// Largest blur radius accepted; blur cost grows with it.
+const MAX_BLUR_SIGMA: f32 = 50.0;
+
+/// One step of an edit, applied in list order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum ImageOp {
+	/// Keep the `width`×`height` rectangle whose top-left corner is at `x`,`y`,
+	/// in the coordinates of the image as it stands after the previous steps.
+	Crop {
+		x: u32,
+		y: u32,
+		width: u32,
+		height: u32,
+	},
+	/// Clockwise, in quarter turns: 90, 180 or 270.
+	Rotate {
+		degrees: u16,
+	},
+	Flip {
+		axis: FlipAxis,
+	},
+	/// Scale to fit inside `width`×`height`, keeping the aspect ratio.
+	Resize {
+		width: u32,
+		height: u32,
+	},
+	Grayscale,
+	/// Gaussian blur of standard deviation `sigma`, in pixels.
+	Blur {
+		sigma: f32,
+	},
+	/// Add `value` (-255..=255) to every channel.
+	Brighten {
+		value: i32,
+	},
+	/// Contrast change in percent (-100..=100); negative flattens.
+	Contrast {
+		value: f32,
+	},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlipAxis {
+	Horizontal,
+	Vertical,
+}
+
+impl ImageOp {
+	/// Reject parameters no image could satisfy, before anything is read or decoded.
+	fn validate(&self) -> ClResult<()> {
+		let ok = match *self {
+			Self::Crop { width, height, .. } => width > 0 && height > 0,
+			Self::Rotate { degrees } => matches!(degrees, 90 | 180 | 270),
+			Self::Resize { width, height } => {
+				(1..=MAX_DECODE_DIM).contains(&width) && (1..=MAX_DECODE_DIM).contains(&height)
+			}
+			Self::Blur { sigma } => sigma > 0.0 && sigma <= MAX_BLUR_SIGMA,
+			Self::Brighten { value } => (-255..=255).contains(&value),
+			Self::Contrast { value } => (-100.0..=100.0).contains(&value),
+			Self::Flip { .. } | Self::Grayscale => true,
+		};
+		if ok {
+			Ok(())
+		} else {
+			Err(Error::ValidationError(format!("invalid image operation: {self:?}")))
+		}
+	}
+
+	fn apply(&self, image: &DynamicImage) -> ClResult<DynamicImage> {
+		Ok(match *self {
+			Self::Crop { x, y, width, height } => {
+				let fits = x.checked_add(width).is_some_and(|r| r <= image.width())
+					&& y.checked_add(height).is_some_and(|b| b <= image.height());
+				if !fits {
+					return Err(Error::ValidationError(format!(
+						"crop {width}x{height}+{x}+{y} exceeds the {}x{} image",
+						image.width(),
+						image.height()
+					)));
+				}
+				image.crop_imm(x, y, width, height)
+			}
+			Self::Rotate { degrees: 90 } => image.rotate90(),
+			Self::Rotate { degrees: 180 } => image.rotate180(),
+			Self::Rotate { .. } => image.rotate270(),
+			Self::Flip { axis: FlipAxis::Horizontal } => image.fliph(),
+			Self::Flip { axis: FlipAxis::Vertical } => image.flipv(),
+			Self::Resize { width, height } => {
+				image.resize(width, height, image::imageops::FilterType::Lanczos3)
+			}
+			Self::Grayscale => image.grayscale(),
+			Self::Blur { sigma } => image.blur(sigma),
+			Self::Brighten { value } => image.brighten(value),
+			Self::Contrast { value } => image.adjust_contrast(value),
+		})
+	}
+}
+
+/// An edited image, encoded.
+pub(crate) struct EditedImage {
+	pub bytes: Vec<u8>,
+	pub dim: (u32, u32),
+}
+
+/// Decode `bytes`, apply `ops` in order and encode the result as `format`.
+///
+/// CPU-bound: run it on the worker pool.
+pub(crate) fn apply_ops(
+	bytes: &[u8],
+	ops: &[ImageOp],
+	format: ImageFormat,
+) -> ClResult<EditedImage> {
+	let mut image = img::decode_oriented(bytes)?;
+	for op in ops {
+		image = op.apply(&image)?;
+	}
+	let dim = (image.width(), image.height());
+	// JPEG has no alpha channel, and the encoder refuses an image that has one.
+	if matches!(format, ImageFormat::Jpeg) && image.color().has_alpha() {
+		image = DynamicImage::ImageRgb8(image.to_rgb8());
+	}
+	Ok(EditedImage { bytes: img::encode_image(&image, format)?, dim })
+}
+
+/// POST /api/files/:fileId/edit - Apply image operations, creating a derived file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditImageRequest {
+	pub ops: Vec<ImageOp>,
+	/// Output format; the source's own when omitted.
+	pub format: Option<ImageFormat>,
+	pub file_name: Option<String>,
+	pub parent_id: Option<String>,
+}
+
+pub async fn edit_image(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	IdTag(tenant_id_tag): IdTag,
+	Path(file_id): Path<String>,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<EditImageRequest>,
+) -> ClResult<(StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+	if req.ops.is_empty() && req.format.is_none() {
+		return Err(Error::ValidationError("no image operations given".into()));
+	}
+	if req.ops.len() > MAX_OPS {
+		return Err(Error::ValidationError(format!("at most {MAX_OPS} image operations")));
+	}
+	for op in &req.ops {
+		op.validate()?;
+	}
+
+	// Same access rule as duplication: the edit reads the source's pixels, so it
+	// needs read access to the source, and a scoped caller needs write access.
+	let ctx = file_access::FileAccessCtx {
+		user_id_tag: &auth.id_tag,
+		tenant_id_tag: &tenant_id_tag,
+		user_roles: &auth.roles,
+	};
+	let access = file_access::check_file_access_with_scope(
+		&app,
+		tn_id,
+		&file_id,
+		&ctx,
+		auth.scope.as_deref(),
+		None,
+	)
+	.await
+	.map_err(|e| match e {
+		file_access::FileAccessError::NotFound => Error::NotFound,
+		file_access::FileAccessError::AccessDenied => Error::PermissionDenied,
+		file_access::FileAccessError::InternalError(m) => Error::Internal(m),
+	})?;
+	if auth.scope.is_some() && !access.access_level.can_write() {
+		return Err(Error::PermissionDenied);
+	}
+	let file = access.file_view;
+
+	if file.file_tp.as_deref().unwrap_or("BLOB") != "BLOB" {
+		return Err(Error::ValidationError("only image files can be edited".into()));
+	}
+	let source_format = match file.content_type.as_deref() {
+		Some("image/jpeg") => ImageFormat::Jpeg,
+		Some("image/png") => ImageFormat::Png,
+		Some("image/webp") => ImageFormat::Webp,
+		Some("image/avif") => ImageFormat::Avif,
+		_ => return Err(Error::ValidationError("only raster image files can be edited".into())),
+	};
+	let format = req.format.unwrap_or(source_format);
+
+	let parent_id = req
+		.parent_id
+		.filter(|s| !s.is_empty())
+		.map(Box::from)
+		.or_else(|| file.parent_id.clone().filter(|s| !s.is_empty()));
+	let dir_cache = app.ext::<DirCache>()?;
+	file_access::check_scope_allows_create_in(
+		&app.meta_adapter,
+		dir_cache,
+		tn_id,
+		auth.scope.as_deref(),
+		parent_id.as_deref(),
+		file.root_id.as_deref(),
+	)
+	.await?;
+
+	let source = read_best_rendition(&app, tn_id, &file.file_id).await?;
+	let ops = req.ops.clone();
+	let edited = app
+		.worker
+		.run_slow(move || apply_ops(&source, &ops, format))
+		.await
+		.map_err(|e| Error::Internal(format!("Worker pool failed editing image: {e}")))??;
+
+	storage_quota::check(&app, tn_id, edited.bytes.len() as u64).await?;
+	let orig_variant_id = hasher::hash("b", &edited.bytes);
+	let preset = file.preset.as_deref().and_then(presets::get).unwrap_or_else(presets::default);
+	let file_name = req.file_name.unwrap_or_else(|| derived_file_name(&file.file_name, format));
+
+	let f_id = app
+		.meta_adapter
+		.create_file(
+			tn_id,
+			meta_adapter::CreateFile {
+				preset: Some(preset.name.clone().into()),
+				orig_variant_id: Some(orig_variant_id),
+				creator_tag: Some(auth.id_tag.clone()),
+				content_type: format!("image/{}", format.as_ref()).into(),
+				file_name: file_name.into(),
+				file_tp: Some("BLOB".into()),
+				tags: file.tags,
+				x: Some(json!({
+					"dim": edited.dim,
+					"derived": { "from": file.file_id, "ops": req.ops, "format": format },
+				})),
+				parent_id,
+				visibility: file.visibility,
+				..Default::default()
+			},
+		)
+		.await?;
+
+	match f_id {
+		meta_adapter::FileId::FId(f_id) => {
+			let data =
+				handler::handle_post_image(&app, tn_id, f_id, "", &edited.bytes, &preset).await?;
+			quota::note_usage(&app, tn_id).await;
+			info!("User {} derived file @{} from {}", auth.id_tag, f_id, file.file_id);
+			let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
+			Ok((StatusCode::CREATED, Json(response)))
+		}
+		meta_adapter::FileId::FileId(existing) => {
+			handler::build_dedup_response(&app, tn_id, &auth.id_tag, &existing, req_id).await
+		}
+	}
+}
+
+/// The bytes an edit starts from: the stored original, or — for presets that
+/// keep none, like profile pictures — the largest visual variant.
+async fn read_best_rendition(app: &App, tn_id: TnId, file_id: &str) -> ClResult<Vec<u8>> {
+	let variants = app
+		.meta_adapter
+		.list_file_variants(tn_id, meta_adapter::FileId::FileId(file_id))
+		.await?;
+	let best = variants
+		.iter()
+		.filter(|v| v.available)
+		.filter(|v| v.variant.as_ref() == "orig" || v.variant.starts_with("vis."))
+		.max_by_key(|v| (v.variant.as_ref() == "orig", v.resolution.0.max(v.resolution.1)))
+		.ok_or(Error::NotFound)?;
+	let blob_tn = if best.global { TnId(0) } else { tn_id };
+	Ok(app.blob_adapter.read_blob_buf(blob_tn, &best.variant_id).await?.into())
+}
+
+/// `photo.jpg` edited to WebP becomes `photo (edited).webp`.
+fn derived_file_name(source: &str, format: ImageFormat) -> String {
+	let stem = source.rsplit_once('.').map_or(source, |(stem, _)| stem);
+	let ext = match format {
+		ImageFormat::Jpeg => "jpg",
+		ImageFormat::Png => "png",
+		ImageFormat::Webp => "webp",
+		ImageFormat::Avif => "avif",
+	};
+	format!("{stem} (edited).{ext}")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn png(width: u32, height: u32) -> Vec<u8> {
+		let image = DynamicImage::new_rgba8(width, height);
+		img::encode_image(&image, ImageFormat::Png).expect("encode")
+	}
+
+	#[test]
+	fn ops_apply_in_order() {
+		let ops = [
+			ImageOp::Crop { x: 10, y: 0, width: 40, height: 20 },
+			ImageOp::Rotate { degrees: 90 },
+			ImageOp::Flip { axis: FlipAxis::Horizontal },
+			ImageOp::Grayscale,
+		];
+		let edited = apply_ops(&png(64, 32), &ops, ImageFormat::Jpeg).expect("edit");
+		assert_eq!(edited.dim, (20, 40));
+		assert_eq!(img::detect_image_type(&edited.bytes).as_deref(), Some("image/jpeg"));
+	}
+
+	#[test]
+	fn a_crop_outside_the_image_is_refused() {
+		let ops = [ImageOp::Crop { x: 50, y: 0, width: 20, height: 10 }];
+		let err = apply_ops(&png(64, 32), &ops, ImageFormat::Png);
+		assert!(matches!(err, Err(Error::ValidationError(_))));
+	}
+
+	#[test]
+	fn parameters_are_validated_and_the_wire_form_is_tagged() {
+		assert!(ImageOp::Rotate { degrees: 45 }.validate().is_err());
+		assert!(ImageOp::Blur { sigma: 500.0 }.validate().is_err());
+		assert!(ImageOp::Resize { width: 0, height: 10 }.validate().is_err());
+		let ops: Vec<ImageOp> = serde_json::from_str(
+			r#"[{"op":"resize","width":8,"height":8},{"op":"flip","axis":"vertical"}]"#,
+		)
+		.expect("parse");
+		assert_eq!(
+			ops,
+			vec![
+				ImageOp::Resize { width: 8, height: 8 },
+				ImageOp::Flip { axis: FlipAxis::Vertical }
+			]
+		);
+		assert_eq!(derived_file_name("photo.jpeg", ImageFormat::Webp), "photo (edited).webp");
+	}
+}
+
+// vim: ts=4
@@ -0,0 +1,781 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Embedded image metadata: reading EXIF/XMP at upload, and stripping it.
+//!
+//! The `image` decoders hand out the raw EXIF (a TIFF structure) and XMP (an XML
+//! packet) blocks; the few fields worth keeping — capture time, camera, GPS — are
+//! picked out here and stored in the file's `x.exif`. Orientation is not stored
+//! there: variants are rendered upright instead, see `image::resize_image_sync`.
+//!
+//! Stripping rewrites the container — JPEG segments, PNG chunks, WebP chunks — and
+//! never re-encodes pixels. Only the orientation survives, in a one-entry EXIF
+//! block, so an upright-in-the-viewfinder photo stays upright. Colour profiles are
+//! kept: they are not personal, and dropping them shifts colours.
+
+use image::{ImageDecoder, ImageReader};
+use serde::Serialize;
+use std::io::Cursor;
+
+use crate::prelude::*;
+
+/// Caps the IFD entries read from one directory. Real cameras write well under 100.
+const MAX_IFD_ENTRIES: usize = 512;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+const TAG_GPS_LONGITUDE: u16 = 0x0004;
+const TAG_GPS_ALTITUDE_REF: u16 = 0x0005;
+const TAG_GPS_ALTITUDE: u16 = 0x0006;
+
+/// Metadata read from an uploaded image, as stored in the file's `x.exif`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMetadata {
+	/// Capture time, ISO 8601. Carries an offset only when the camera recorded one.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub taken_at: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub camera_make: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub camera_model: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub lens: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub software: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub gps: Option<GpsPosition>,
+	/// EXIF orientation, 1..=8. Not serialized: variants are stored upright.
+	#[serde(skip)]
+	pub orientation: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GpsPosition {
+	pub lat: f64,
+	pub lon: f64,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub alt: Option<f64>,
+}
+
+impl ImageMetadata {
+	/// Whether there is anything worth storing.
+	pub fn is_empty(&self) -> bool {
+		self.taken_at.is_none()
+			&& self.camera_make.is_none()
+			&& self.camera_model.is_none()
+			&& self.lens.is_none()
+			&& self.software.is_none()
+			&& self.gps.is_none()
+	}
+
+	/// What is left once personal metadata is stripped: nothing but the capture time,
+	/// which the file's own timestamps reveal just as well.
+	pub fn stripped(self) -> Self {
+		Self { taken_at: self.taken_at, orientation: self.orientation, ..Self::default() }
+	}
+}
+
+/// Read the EXIF and XMP blocks of an encoded image. Unknown formats and missing or
+/// malformed blocks all yield what could be read — metadata never fails an upload.
+pub fn read_metadata(bytes: &[u8]) -> ImageMetadata {
+	let decoder = ImageReader::new(Cursor::new(bytes))
+		.with_guessed_format()
+		.ok()
+		.and_then(|reader| reader.into_decoder().ok());
+	let Some(mut decoder) = decoder else {
+		return ImageMetadata::default();
+	};
+	let mut meta = decoder
+		.exif_metadata()
+		.ok()
+		.flatten()
+		.map(|tiff| parse_exif(&tiff))
+		.unwrap_or_default();
+	if let Ok(Some(xmp)) = decoder.xmp_metadata() {
+		fill_from_xmp(&mut meta, &String::from_utf8_lossy(&xmp));
+	}
+	if meta.orientation == 0 {
+		meta.orientation = 1;
+	}
+	meta
+}
+
+// TIFF structure
+//****************
+
+#[derive(Clone, Copy)]
+struct Tiff<'a> {
+	data: &'a [u8],
+	big_endian: bool,
+}
+
+/// One IFD entry's type, count and value-or-offset field.
+#[derive(Clone, Copy)]
+struct Field {
+	tp: u16,
+	count: u32,
+	value: [u8; 4],
+}
+
+impl<'a> Tiff<'a> {
+	fn new(data: &'a [u8]) -> Option<Self> {
+		let big_endian = match data.get(0..4)? {
+			b"MM\0*" => true,
+			b"II*\0" => false,
+			_ => return None,
+		};
+		Some(Self { data, big_endian })
+	}
+
+	fn u16_at(self, bytes: &[u8]) -> Option<u16> {
+		let b: [u8; 2] = bytes.get(0..2)?.try_into().ok()?;
+		Some(if self.big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) })
+	}
+
+	fn u32_at(self, bytes: &[u8]) -> Option<u32> {
+		let b: [u8; 4] = bytes.get(0..4)?.try_into().ok()?;
+		Some(if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+	}
+
+	fn slice(self, offset: u32, len: usize) -> Option<&'a [u8]> {
+		let start = usize::try_from(offset).ok()?;
+		self.data.get(start..start.checked_add(len)?)
+	}
+
+	/// The entries of the IFD at `offset`, tag → field. Later duplicates are ignored.
+	fn ifd(self, offset: u32) -> Vec<(u16, Field)> {
+		let Some(count) = self.slice(offset, 2).and_then(|b| self.u16_at(b)) else {
+			return Vec::new();
+		};
+		(0..usize::from(count).min(MAX_IFD_ENTRIES))
+			.map_while(|i| {
+				let at = offset.checked_add(2)?.checked_add(u32::try_from(i * 12).ok()?)?;
+				let entry = self.slice(at, 12)?;
+				let value = entry.get(8..12)?.try_into().ok()?;
+				Some((
+					self.u16_at(entry)?,
+					Field {
+						tp: self.u16_at(&entry[2..])?,
+						count: self.u32_at(&entry[4..])?,
+						value,
+					},
+				))
+			})
+			.collect()
+	}
+
+	/// The bytes of a value too long to sit inline in its entry.
+	fn offset_bytes(self, field: Field, unit: usize) -> Option<&'a [u8]> {
+		let len = usize::try_from(field.count).ok()?.checked_mul(unit)?;
+		self.slice(self.u32_at(&field.value)?, len)
+	}
+
+	fn ascii(self, field: Field) -> Option<String> {
+		if field.tp != 2 {
+			return None;
+		}
+		let value = if field.count <= 4 {
+			let len = usize::try_from(field.count).ok()?;
+			String::from_utf8_lossy(field.value.get(..len)?).into_owned()
+		} else {
+			String::from_utf8_lossy(self.offset_bytes(field, 1)?).into_owned()
+		};
+		let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+		(!value.is_empty()).then(|| value.to_string())
+	}
+
+	fn uint(self, field: Field) -> Option<u32> {
+		match field.tp {
+			1 | 7 => field.value.first().copied().map(u32::from),
+			3 => self.u16_at(&field.value).map(u32::from),
+			4 => self.u32_at(&field.value),
+			_ => None,
+		}
+	}
+
+	fn rationals(self, field: Field) -> Option<Vec<f64>> {
+		if field.tp != 5 {
+			return None;
+		}
+		self.offset_bytes(field, 8)?
+			.chunks_exact(8)
+			.map(|pair| {
+				let (num, den) = (self.u32_at(pair)?, self.u32_at(&pair[4..])?);
+				(den != 0).then(|| f64::from(num) / f64::from(den))
+			})
+			.collect()
+	}
+}
+
+fn find(entries: &[(u16, Field)], tag: u16) -> Option<Field> {
+	entries.iter().find(|(t, _)| *t == tag).map(|(_, f)| *f)
+}
+
+/// Pick the stored fields out of a raw EXIF block (a TIFF header and IFDs).
+pub fn parse_exif(data: &[u8]) -> ImageMetadata {
+	let data = data.strip_prefix(b"Exif\0\0").unwrap_or(data);
+	let Some(tiff) = Tiff::new(data) else {
+		return ImageMetadata::default();
+	};
+	let Some(ifd0_offset) = tiff.u32_at(&data[4..]) else {
+		return ImageMetadata::default();
+	};
+	let ifd0 = tiff.ifd(ifd0_offset);
+	let exif = find(&ifd0, TAG_EXIF_IFD).and_then(|f| tiff.uint(f)).map(|o| tiff.ifd(o));
+	let exif = exif.unwrap_or_default();
+	let ascii = |entries: &[(u16, Field)], tag| find(entries, tag).and_then(|f| tiff.ascii(f));
+
+	let taken_at = ascii(&exif, TAG_DATE_TIME_ORIGINAL)
+		.or_else(|| ascii(&ifd0, TAG_DATE_TIME))
+		.and_then(|dt| exif_datetime(&dt, ascii(&exif, TAG_OFFSET_TIME_ORIGINAL).as_deref()));
+
+	let gps = find(&ifd0, TAG_GPS_IFD).and_then(|f| tiff.uint(f)).and_then(|offset| {
+		let gps = tiff.ifd(offset);
+		let coordinate = |tag, ref_tag, negative: &str| {
+			let dms = tiff.rationals(find(&gps, tag)?)?;
+			let value = dms_to_degrees(&dms)?;
+			let sign = ascii(&gps, ref_tag).filter(|r| r.eq_ignore_ascii_case(negative));
+			Some(if sign.is_some() { -value } else { value })
+		};
+		let lat = coordinate(TAG_GPS_LATITUDE, TAG_GPS_LATITUDE_REF, "S")?;
+		let lon = coordinate(TAG_GPS_LONGITUDE, TAG_GPS_LONGITUDE_REF, "W")?;
+		let alt = find(&gps, TAG_GPS_ALTITUDE)
+			.and_then(|f| tiff.rationals(f))
+			.and_then(|v| v.first().copied())
+			.map(|alt| {
+				let below = find(&gps, TAG_GPS_ALTITUDE_REF).and_then(|f| tiff.uint(f)) == Some(1);
+				if below { -alt } else { alt }
+			});
+		valid_position(lat, lon).then_some(GpsPosition { lat, lon, alt })
+	});
+
+	let orientation = find(&ifd0, TAG_ORIENTATION)
+		.and_then(|f| tiff.uint(f))
+		.and_then(|o| u8::try_from(o).ok())
+		.filter(|o| (1..=8).contains(o))
+		.unwrap_or(0);
+
+	ImageMetadata {
+		taken_at,
+		camera_make: ascii(&ifd0, TAG_MAKE),
+		camera_model: ascii(&ifd0, TAG_MODEL),
+		lens: ascii(&exif, TAG_LENS_MODEL),
+		software: ascii(&ifd0, TAG_SOFTWARE),
+		gps,
+		orientation,
+	}
+}
+
+fn dms_to_degrees(dms: &[f64]) -> Option<f64> {
+	let (d, m, s) = match dms {
+		[d, m, s, ..] => (*d, *m, *s),
+		[d, m] => (*d, *m, 0.0),
+		[d] => (*d, 0.0, 0.0),
+		[] => return None,
+	};
+	Some(d + m / 60.0 + s / 3600.0)
+}
+
+/// (0, 0) is what a phone writes when it has no fix.
+fn valid_position(lat: f64, lon: f64) -> bool {
+	(-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) && (lat, lon) != (0.0, 0.0)
+}
+
+/// `"2024:05:17 14:03:22"` (+ `"+02:00"`) → `"2024-05-17T14:03:22+02:00"`.
+fn exif_datetime(value: &str, offset: Option<&str>) -> Option<String> {
+	let (date, time) = value.split_once(' ')?;
+	let date: Vec<&str> = date.split([':', '-']).collect();
+	let [y, mo, d] = date.as_slice() else {
+		return None;
+	};
+	let all_digits = |s: &str, n: usize| s.len() == n && s.bytes().all(|b| b.is_ascii_digit());
+	if !all_digits(y, 4) || !all_digits(mo, 2) || !all_digits(d, 2) || *y == "0000" {
+		return None;
+	}
+	let time = time.get(..8).filter(|t| t.len() == 8)?;
+	let mut out = format!("{y}-{mo}-{d}T{time}");
+	if let Some(offset) = offset.filter(|o| o.len() == 6 && o.starts_with(['+', '-'])) {
+		out.push_str(offset);
+	}
+	Some(out)
+}
+
+// XMP
+//*****
+
+/// Fill whatever EXIF left empty from an XMP packet. Values may be written as
+/// attributes (`exif:GPSLatitude="47,29.5N"`) or as elements; both are looked for.
+fn fill_from_xmp(meta: &mut ImageMetadata, xmp: &str) {
+	if meta.taken_at.is_none() {
+		meta.taken_at = ["exif:DateTimeOriginal", "photoshop:DateCreated", "xmp:CreateDate"]
+			.iter()
+			.find_map(|name| xmp_value(xmp, name))
+			.and_then(|v| xmp_datetime(&v));
+	}
+	if meta.camera_make.is_none() {
+		meta.camera_make = xmp_value(xmp, "tiff:Make");
+	}
+	if meta.camera_model.is_none() {
+		meta.camera_model = xmp_value(xmp, "tiff:Model");
+	}
+	if meta.lens.is_none() {
+		meta.lens = xmp_value(xmp, "exifEX:LensModel").or_else(|| xmp_value(xmp, "aux:Lens"));
+	}
+	if meta.software.is_none() {
+		meta.software = xmp_value(xmp, "xmp:CreatorTool");
+	}
+	if meta.gps.is_none() {
+		let lat = xmp_value(xmp, "exif:GPSLatitude").and_then(|v| xmp_coordinate(&v));
+		let lon = xmp_value(xmp, "exif:GPSLongitude").and_then(|v| xmp_coordinate(&v));
+		if let (Some(lat), Some(lon)) = (lat, lon)
+			&& valid_position(lat, lon)
+		{
+			meta.gps = Some(GpsPosition { lat, lon, alt: None });
+		}
+	}
+	if meta.orientation == 0 {
+		meta.orientation = xmp_value(xmp, "tiff:Orientation")
+			.and_then(|v| v.parse().ok())
+			.filter(|o| (1..=8).contains(o))
+			.unwrap_or(0);
+	}
+}
+
+fn xmp_value(xmp: &str, name: &str) -> Option<String> {
+	let attr = format!("{name}=\"");
+	let value = if let Some(start) = xmp.find(&attr).map(|i| i + attr.len()) {
+		&xmp[start..start + xmp[start..].find('"')?]
+	} else {
+		let open = format!("<{name}>");
+		let start = xmp.find(&open)? + open.len();
+		&xmp[start..start + xmp[start..].find('<')?]
+	};
+	let value = value.trim();
+	(!value.is_empty()).then(|| value.to_string())
+}
+
+/// XMP dates are ISO 8601 already, possibly without seconds or time.
+fn xmp_datetime(value: &str) -> Option<String> {
+	let date = value.get(..10)?;
+	let bytes = date.as_bytes();
+	let shaped = bytes.iter().enumerate().all(|(i, b)| match i {
+		4 | 7 => *b == b'-',
+		_ => b.is_ascii_digit(),
+	});
+	shaped.then(|| value.to_string())
+}
+
+/// `"47,29.5N"` or `"47,29,30N"` → signed degrees.
+fn xmp_coordinate(value: &str) -> Option<f64> {
+	let dir = value.chars().last()?;
+	let sign = match dir.to_ascii_uppercase() {
+		'N' | 'E' => 1.0,
+		'S' | 'W' => -1.0,
+		_ => return None,
+	};
+	let parts = value[..value.len() - 1]
+		.split(',')
+		.map(|p| p.trim().parse::<f64>().ok())
+		.collect::<Option<Vec<_>>>()?;
+	Some(sign * dms_to_degrees(&parts)?)
+}
+
+// Stripping
+//***********
+
+/// A TIFF structure holding nothing but an orientation, for the stripped file.
+fn orientation_exif(orientation: u8) -> Vec<u8> {
+	let mut tiff = Vec::with_capacity(26);
+	tiff.extend_from_slice(b"MM\0*");
+	tiff.extend_from_slice(&8u32.to_be_bytes());
+	tiff.extend_from_slice(&1u16.to_be_bytes());
+	tiff.extend_from_slice(&TAG_ORIENTATION.to_be_bytes());
+	tiff.extend_from_slice(&3u16.to_be_bytes());
+	tiff.extend_from_slice(&1u32.to_be_bytes());
+	tiff.extend_from_slice(&u16::from(orientation).to_be_bytes());
+	tiff.extend_from_slice(&[0, 0]);
+	tiff.extend_from_slice(&0u32.to_be_bytes());
+	tiff
+}
+
+/// Remove EXIF, XMP, IPTC and comments from an encoded image, keeping only its
+/// `orientation`. `None` when the format is not one this can rewrite (AVIF, GIF)
+/// or its structure does not parse — the caller decides whether to keep the
+/// original then.
+pub fn strip_metadata(bytes: &[u8], orientation: u8) -> Option<Vec<u8>> {
+	let orientation = (orientation > 1 && orientation <= 8).then_some(orientation);
+	if bytes.starts_with(&[0xFF, 0xD8]) {
+		strip_jpeg(bytes, orientation)
+	} else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+		strip_png(bytes, orientation)
+	} else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+		strip_webp(bytes, orientation)
+	} else {
+		None
+	}
+}
+
+fn strip_jpeg(bytes: &[u8], orientation: Option<u8>) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(bytes.len());
+	out.extend_from_slice(&[0xFF, 0xD8]);
+	let mut exif_written = orientation.is_none();
+	let mut pos = 2;
+	loop {
+		if bytes.get(pos) != Some(&0xFF) {
+			return None;
+		}
+		let marker = *bytes.get(pos + 1)?;
+		match marker {
+			// Fill bytes before a marker
+			0xFF => {
+				pos += 1;
+				continue;
+			}
+			// Standalone markers carry no length
+			0x01 | 0xD0..=0xD7 => {
+				out.extend_from_slice(&bytes[pos..pos + 2]);
+				pos += 2;
+				continue;
+			}
+			0xD9 => {
+				out.extend_from_slice(&bytes[pos..pos + 2]);
+				return Some(out);
+			}
+			_ => {}
+		}
+		let len = usize::from(u16::from_be_bytes(bytes.get(pos + 2..pos + 4)?.try_into().ok()?));
+		// The length counts its own two bytes; anything shorter is malformed.
+		if len < 2 {
+			return None;
+		}
+		let segment = bytes.get(pos..pos + 2 + len)?;
+		let payload = segment.get(4..)?;
+		let keep = match marker {
+			// APP2 only for the ICC profile; MPF extension images may carry EXIF
+			0xE2 => payload.starts_with(b"ICC_PROFILE\0"),
+			// Other APPn: EXIF/XMP (APP1), IPTC (APP13), vendor blocks; COM.
+			// APP0 (JFIF) and APP14 (Adobe colour transform) stay.
+			0xE1 | 0xE3..=0xED | 0xEF | 0xFE => false,
+			_ => true,
+		};
+		// The EXIF block goes after the kept APPn segments (JFIF must stay first),
+		// ahead of the tables and the frame.
+		if !exif_written && !matches!(marker, 0xE0..=0xEF | 0xFE) {
+			let tiff = orientation_exif(orientation.unwrap_or(1));
+			let seg_len = u16::try_from(2 + 6 + tiff.len()).ok()?;
+			out.extend_from_slice(&[0xFF, 0xE1]);
+			out.extend_from_slice(&seg_len.to_be_bytes());
+			out.extend_from_slice(b"Exif\0\0");
+			out.extend_from_slice(&tiff);
+			exif_written = true;
+		}
+		if keep {
+			out.extend_from_slice(segment);
+		}
+		pos += 2 + len;
+		// Start of scan: entropy-coded data follows, copied as-is to the end.
+		if marker == 0xDA {
+			out.extend_from_slice(&bytes[pos..]);
+			return Some(out);
+		}
+	}
+}
+
+fn png_chunk(out: &mut Vec<u8>, tp: [u8; 4], data: &[u8]) -> Option<()> {
+	let mut crc = flate2::Crc::new();
+	crc.update(&tp);
+	crc.update(data);
+	out.extend_from_slice(&u32::try_from(data.len()).ok()?.to_be_bytes());
+	out.extend_from_slice(&tp);
+	out.extend_from_slice(data);
+	out.extend_from_slice(&crc.sum().to_be_bytes());
+	Some(())
+}
+
+fn strip_png(bytes: &[u8], orientation: Option<u8>) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(bytes.len());
+	out.extend_from_slice(&bytes[..8]);
+	let mut pos = 8;
+	while pos < bytes.len() {
+		let len =
+			usize::try_from(u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?)).ok()?;
+		let tp: [u8; 4] = bytes.get(pos + 4..pos + 8)?.try_into().ok()?;
+		let chunk = bytes.get(pos..pos.checked_add(12)?.checked_add(len)?)?;
+		pos += chunk.len();
+		if matches!(&tp, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+			continue;
+		}
+		out.extend_from_slice(chunk);
+		// eXIf must precede IDAT; right after IHDR is always valid.
+		if &tp == b"IHDR"
+			&& let Some(orientation) = orientation
+		{
+			png_chunk(&mut out, *b"eXIf", &orientation_exif(orientation))?;
+		}
+		if &tp == b"IEND" {
+			return Some(out);
+		}
+	}
+	None
+}
+
+fn strip_webp(bytes: &[u8], orientation: Option<u8>) -> Option<Vec<u8>> {
+	const VP8X_EXIF: u8 = 0x08;
+	const VP8X_XMP: u8 = 0x04;
+
+	let mut chunks = Vec::with_capacity(bytes.len());
+	let mut extended = false;
+	let mut pos = 12;
+	while pos < bytes.len() {
+		let fourcc: [u8; 4] = bytes.get(pos..pos + 4)?.try_into().ok()?;
+		let len =
+			usize::try_from(u32::from_le_bytes(bytes.get(pos + 4..pos + 8)?.try_into().ok()?))
+				.ok()?;
+		let padded = len + (len & 1);
+		let chunk = bytes.get(pos..pos.checked_add(8)?.checked_add(padded)?)?;
+		pos += chunk.len();
+		match &fourcc {
+			b"EXIF" | b"XMP " => {}
+			b"VP8X" => {
+				extended = true;
+				let start = chunks.len();
+				chunks.extend_from_slice(chunk);
+				let flags = chunks.get_mut(start + 8)?;
+				*flags &= !(VP8X_EXIF | VP8X_XMP);
+				if orientation.is_some() {
+					*flags |= VP8X_EXIF;
+				}
+			}
+			_ => chunks.extend_from_slice(chunk),
+		}
+	}
+	// Only the extended format can hold metadata; a simple file had none to strip
+	// and cannot gain an orientation without a VP8X header, so it stays as it was.
+	if !extended {
+		return Some(bytes.to_vec());
+	}
+	if let Some(orientation) = orientation {
+		let tiff = orientation_exif(orientation);
+		chunks.extend_from_slice(b"EXIF");
+		chunks.extend_from_slice(&u32::try_from(tiff.len()).ok()?.to_le_bytes());
+		chunks.extend_from_slice(&tiff);
+	}
+	let mut out = Vec::with_capacity(chunks.len() + 12);
+	out.extend_from_slice(b"RIFF");
+	out.extend_from_slice(&u32::try_from(chunks.len() + 4).ok()?.to_le_bytes());
+	out.extend_from_slice(b"WEBP");
+	out.extend_from_slice(&chunks);
+	Some(out)
+}
+
+/// An uploaded image's bytes ready to store, and its metadata ready for `x.exif`.
+///
+/// With `strip`, personal metadata is removed from both. A format that cannot be
+/// rewritten is stored unchanged, with a warning — refusing the upload would be worse.
+pub async fn prepare_upload(
+	app: &App,
+	bytes: axum::body::Bytes,
+	strip: bool,
+) -> ClResult<(axum::body::Bytes, ImageMetadata)> {
+	app.worker
+		.run_immed(move || {
+			let meta = read_metadata(&bytes);
+			if !strip {
+				return (bytes, meta);
+			}
+			let orientation = meta.orientation;
+			let meta = meta.stripped();
+			if let Some(stripped) = strip_metadata(&bytes, orientation) {
+				(stripped.into(), meta)
+			} else {
+				warn!("image metadata not stripped: unsupported or malformed container");
+				(bytes, meta)
+			}
+		})
+		.await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A little-endian TIFF with IFD0 (Make, Orientation, ExifIFD, GPS IFD),
+	/// an EXIF IFD (DateTimeOriginal, OffsetTimeOriginal) and a GPS IFD.
+	fn sample_exif() -> Vec<u8> {
+		struct Builder {
+			data: Vec<u8>,
+		}
+		impl Builder {
+			fn ifd(&mut self, entries: &[(u16, u16, u32, [u8; 4])]) {
+				self.data
+					.extend_from_slice(&u16::try_from(entries.len()).unwrap().to_le_bytes());
+				for (tag, tp, count, value) in entries {
+					self.data.extend_from_slice(&tag.to_le_bytes());
+					self.data.extend_from_slice(&tp.to_le_bytes());
+					self.data.extend_from_slice(&count.to_le_bytes());
+					self.data.extend_from_slice(value);
+				}
+				self.data.extend_from_slice(&0u32.to_le_bytes());
+			}
+		}
+		let off = |o: usize| u32::try_from(o).unwrap().to_le_bytes();
+		let mut b = Builder { data: b"II*\0".to_vec() };
+		b.data.extend_from_slice(&off(8));
+		// IFD0 at 8: 4 entries → ends at 8 + 2 + 48 + 4 = 62
+		let make_at = 62;
+		let exif_at = make_at + 6; // "Canon\0"
+		// EXIF IFD: 2 entries → 2 + 24 + 4 = 30
+		let dt_at = exif_at + 30;
+		let tz_at = dt_at + 20;
+		let gps_at = tz_at + 8; // "+02:00\0" padded
+		// GPS IFD: 4 entries → 2 + 48 + 4 = 54
+		let lat_at = gps_at + 54;
+		let lon_at = lat_at + 24;
+		b.ifd(&[
+			(TAG_MAKE, 2, 6, off(make_at)),
+			(TAG_ORIENTATION, 3, 1, [6, 0, 0, 0]),
+			(TAG_EXIF_IFD, 4, 1, off(exif_at)),
+			(TAG_GPS_IFD, 4, 1, off(gps_at)),
+		]);
+		b.data.extend_from_slice(b"Canon\0");
+		b.ifd(&[
+			(TAG_DATE_TIME_ORIGINAL, 2, 20, off(dt_at)),
+			(TAG_OFFSET_TIME_ORIGINAL, 2, 7, off(tz_at)),
+		]);
+		b.data.extend_from_slice(b"2024:05:17 14:03:22\0");
+		b.data.extend_from_slice(b"+02:00\0\0");
+		b.ifd(&[
+			(TAG_GPS_LATITUDE_REF, 2, 2, *b"N\0\0\0"),
+			(TAG_GPS_LATITUDE, 5, 3, off(lat_at)),
+			(TAG_GPS_LONGITUDE_REF, 2, 2, *b"W\0\0\0"),
+			(TAG_GPS_LONGITUDE, 5, 3, off(lon_at)),
+		]);
+		for (num, den) in [(47, 1), (30, 1), (0, 1), (19, 1), (3, 1), (36, 1)] {
+			b.data.extend_from_slice(&u32::to_le_bytes(num));
+			b.data.extend_from_slice(&u32::to_le_bytes(den));
+		}
+		b.data
+	}
+
+	#[test]
+	fn test_parse_exif() {
+		let meta = parse_exif(&sample_exif());
+		assert_eq!(meta.camera_make.as_deref(), Some("Canon"));
+		assert_eq!(meta.taken_at.as_deref(), Some("2024-05-17T14:03:22+02:00"));
+		assert_eq!(meta.orientation, 6);
+		let gps = meta.gps.unwrap();
+		assert!((gps.lat - 47.5).abs() < 1e-9);
+		assert!((gps.lon + 19.06).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_malformed_exif_yields_nothing() {
+		assert_eq!(parse_exif(b"II*\0\xff\xff\xff\xff"), ImageMetadata::default());
+		assert_eq!(parse_exif(b"garbage"), ImageMetadata::default());
+		let mut truncated = sample_exif();
+		truncated.truncate(70);
+		assert!(parse_exif(&truncated).gps.is_none());
+	}
+
+	#[test]
+	fn test_xmp_fills_gaps() {
+		let xmp = r#"<rdf:Description exif:GPSLatitude="47,30.0N" exif:GPSLongitude="19,3.6E"
+			xmp:CreateDate="2023-01-02T03:04:05Z"><tiff:Model>Pixel 8</tiff:Model></rdf:Description>"#;
+		let mut meta = ImageMetadata::default();
+		fill_from_xmp(&mut meta, xmp);
+		assert_eq!(meta.camera_model.as_deref(), Some("Pixel 8"));
+		assert_eq!(meta.taken_at.as_deref(), Some("2023-01-02T03:04:05Z"));
+		let gps = meta.gps.unwrap();
+		assert!((gps.lat - 47.5).abs() < 1e-9);
+		assert!((gps.lon - 19.06).abs() < 1e-9);
+	}
+
+	fn encoded(format: image::ImageFormat) -> Vec<u8> {
+		let img = image::DynamicImage::new_rgb8(4, 2);
+		let mut out = Cursor::new(Vec::new());
+		img.write_to(&mut out, format).unwrap();
+		out.into_inner()
+	}
+
+	fn orientation_of(bytes: &[u8]) -> image::metadata::Orientation {
+		let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format().unwrap();
+		reader.into_decoder().unwrap().orientation().unwrap()
+	}
+
+	#[test]
+	fn test_strip_jpeg_keeps_only_orientation() {
+		let jpeg = encoded(image::ImageFormat::Jpeg);
+		let exif = sample_exif();
+		let mut tagged = jpeg[..2].to_vec();
+		tagged.extend_from_slice(&[0xFF, 0xE1]);
+		tagged.extend_from_slice(&u16::try_from(exif.len() + 8).unwrap().to_be_bytes());
+		tagged.extend_from_slice(b"Exif\0\0");
+		tagged.extend_from_slice(&exif);
+		tagged.extend_from_slice(b"\xFF\xFE\0\x07hello");
+		tagged.extend_from_slice(&jpeg[2..]);
+		assert!(read_metadata(&tagged).gps.is_some());
+
+		let stripped = strip_metadata(&tagged, 6).unwrap();
+		let meta = read_metadata(&stripped);
+		assert!(meta.is_empty());
+		assert_eq!(meta.orientation, 6);
+		assert_eq!(orientation_of(&stripped), image::metadata::Orientation::Rotate90);
+		assert!(!stripped.windows(5).any(|w| w == b"hello"));
+		assert!(image::load_from_memory(&stripped).is_ok());
+	}
+
+	#[test]
+	fn test_strip_png_drops_text_and_exif() {
+		let png = encoded(image::ImageFormat::Png);
+		let mut tagged = png[..33].to_vec(); // signature + IHDR
+		png_chunk(&mut tagged, *b"eXIf", &sample_exif()).unwrap();
+		png_chunk(&mut tagged, *b"tEXt", b"Author\0someone").unwrap();
+		tagged.extend_from_slice(&png[33..]);
+		assert!(read_metadata(&tagged).camera_make.is_some());
+
+		let stripped = strip_metadata(&tagged, 1).unwrap();
+		assert_eq!(stripped, png);
+		assert!(image::load_from_memory(&stripped).is_ok());
+	}
+
+	#[test]
+	fn test_strip_refuses_unknown_formats() {
+		assert!(strip_metadata(b"GIF89a....", 1).is_none());
+		assert!(strip_metadata(&[0xFF, 0xD8, 0x00], 1).is_none());
+	}
+
+	#[test]
+	fn test_strip_refuses_short_jpeg_segments() {
+		let jpeg = encoded(image::ImageFormat::Jpeg);
+		for len in [0u8, 1] {
+			let mut tagged = jpeg[..2].to_vec();
+			tagged.extend_from_slice(&[0xFF, 0xE1, 0x00, len]);
+			tagged.extend_from_slice(&jpeg[2..]);
+			assert!(strip_metadata(&tagged, 1).is_none());
+		}
+	}
+
+	#[test]
+	fn test_exif_datetime() {
+		assert_eq!(
+			exif_datetime("2024:05:17 14:03:22", None).as_deref(),
+			Some("2024-05-17T14:03:22")
+		);
+		assert_eq!(exif_datetime("0000:00:00 00:00:00", None), None);
+		assert_eq!(exif_datetime("not a date", None), None);
+	}
+}
+
+// vim: ts=4
@@ -7,6 +7,7 @@
 //! - Probing media files (getting duration, resolution, codec info)
 //! - Extracting frames for thumbnails (smart frame selection)
 //! - Transcoding video to different qualities
+//! - Packaging video as adaptive streams (HLS, optionally DASH)
 //! - Extracting audio from video files
 
 use serde::{Deserialize, Serialize};
@@ -81,6 +82,55 @@ impl Default for VideoTranscodeOpts {
 	}
 }
 
+/// Adaptive streaming output: which playlists a stream container carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+	/// HLS master and media playlists.
+	Hls,
+	/// A DASH manifest, plus HLS playlists over the same segments.
+	HlsDash,
+}
+
+impl StreamFormat {
+	pub fn as_str(self) -> &'static str {
+		match self {
+			Self::Hls => "hls",
+			Self::HlsDash => "hls+dash",
+		}
+	}
+
+	/// Parse the `file.video_stream_format` setting; `None` for "none" and anything
+	/// unrecognised.
+	pub fn from_str_opt(s: &str) -> Option<Self> {
+		match s {
+			"hls" => Some(Self::Hls),
+			"hls+dash" => Some(Self::HlsDash),
+			_ => None,
+		}
+	}
+}
+
+/// One rendition of an adaptive stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamRendition {
+	/// Directory-safe name, e.g. "sd"
+	pub name: String,
+	/// Maximum dimension (video fits in max_dim x max_dim bounding box)
+	pub max_dim: u32,
+	/// Target video bitrate in kbps
+	pub bitrate: u32,
+}
+
+/// Target segment length in seconds. Keyframes are forced on this grid so every
+/// rendition cuts at the same instants and a player can switch between them.
+pub const STREAM_SEGMENT_SECS: u32 = 6;
+
+/// Name of the HLS master playlist in a stream container.
+pub const HLS_MASTER_PLAYLIST: &str = "master.m3u8";
+
+/// Name of the DASH manifest in a stream container.
+pub const DASH_MANIFEST: &str = "manifest.mpd";
+
 /// Audio extraction options
 #[derive(Debug, Clone)]
 pub struct AudioExtractOpts {
@@ -299,6 +349,143 @@ impl FFmpeg {
 		Ok(resolution)
 	}
 
+	/// Package a video as an adaptive stream: every rendition transcoded in one pass,
+	/// written into `out_dir` with its playlists. Each rendition is a single fMP4 file
+	/// addressed by byte ranges, so the directory holds a handful of files whatever the
+	/// video's length.
+	pub fn package_stream(
+		input: &Path,
+		out_dir: &Path,
+		renditions: &[StreamRendition],
+		format: StreamFormat,
+		has_audio: bool,
+	) -> ClResult<()> {
+		let input = input.to_str().ok_or(Error::Internal("invalid input path".into()))?;
+		let out_dir = out_dir.to_str().ok_or(Error::Internal("invalid output path".into()))?;
+		let args = Self::stream_args(input, out_dir, renditions, format, has_audio);
+
+		let status = Command::new("ffmpeg")
+			.args(&args)
+			.status()
+			.map_err(|e| Error::Internal(format!("ffmpeg failed: {}", e)))?;
+
+		if !status.success() {
+			return Err(Error::Internal("ffmpeg stream packaging failed".into()));
+		}
+		Ok(())
+	}
+
+	/// The ffmpeg arguments for [`Self::package_stream`].
+	fn stream_args(
+		input: &str,
+		out_dir: &str,
+		renditions: &[StreamRendition],
+		format: StreamFormat,
+		has_audio: bool,
+	) -> Vec<String> {
+		let mut args: Vec<String> = vec!["-y".into(), "-i".into(), input.into()];
+
+		for _ in renditions {
+			args.extend(["-map".into(), "0:v:0".into()]);
+		}
+		// HLS pairs an audio stream with every rendition; DASH keeps one audio
+		// adaptation set that every rendition shares.
+		let audio_maps = match (has_audio, format) {
+			(false, _) => 0,
+			(true, StreamFormat::Hls) => renditions.len(),
+			(true, StreamFormat::HlsDash) => 1,
+		};
+		for _ in 0..audio_maps {
+			args.extend(["-map".into(), "0:a:0".into()]);
+		}
+
+		args.extend(
+			[
+				"-c:v",
+				"libx264",
+				"-preset",
+				"medium",
+				"-force_key_frames",
+				&format!("expr:gte(t,n_forced*{STREAM_SEGMENT_SECS})"),
+				"-sc_threshold",
+				"0",
+			]
+			.map(String::from),
+		);
+		for (i, r) in renditions.iter().enumerate() {
+			args.extend([
+				format!("-filter:v:{i}"),
+				format!(
+					"scale='min({0},iw)':'min({0},ih)':force_original_aspect_ratio=decrease,crop=trunc(iw/2)*2:trunc(ih/2)*2",
+					r.max_dim
+				),
+				format!("-b:v:{i}"),
+				format!("{}k", r.bitrate),
+				format!("-maxrate:v:{i}"),
+				format!("{}k", r.bitrate * 3 / 2),
+				format!("-bufsize:v:{i}"),
+				format!("{}k", r.bitrate * 2),
+			]);
+		}
+		if audio_maps > 0 {
+			args.extend(["-c:a", "aac", "-b:a", "128k"].map(String::from));
+		}
+
+		match format {
+			StreamFormat::Hls => {
+				let stream_map = renditions
+					.iter()
+					.enumerate()
+					.map(|(i, r)| {
+						if has_audio {
+							format!("v:{i},a:{i},name:{}", r.name)
+						} else {
+							format!("v:{i},name:{}", r.name)
+						}
+					})
+					.collect::<Vec<_>>()
+					.join(" ");
+				args.extend([
+					"-f".into(),
+					"hls".into(),
+					"-hls_time".into(),
+					STREAM_SEGMENT_SECS.to_string(),
+					"-hls_playlist_type".into(),
+					"vod".into(),
+					"-hls_segment_type".into(),
+					"fmp4".into(),
+					"-hls_flags".into(),
+					"single_file+independent_segments".into(),
+					"-master_pl_name".into(),
+					HLS_MASTER_PLAYLIST.into(),
+					"-var_stream_map".into(),
+					stream_map,
+					format!("{out_dir}/%v/index.m3u8"),
+				]);
+			}
+			StreamFormat::HlsDash => {
+				let sets =
+					if has_audio { "id=0,streams=v id=1,streams=a" } else { "id=0,streams=v" };
+				args.extend([
+					"-f".into(),
+					"dash".into(),
+					"-seg_duration".into(),
+					STREAM_SEGMENT_SECS.to_string(),
+					"-single_file".into(),
+					"1".into(),
+					"-hls_playlist".into(),
+					"1".into(),
+					"-hls_master_name".into(),
+					HLS_MASTER_PLAYLIST.into(),
+					"-adaptation_sets".into(),
+					sets.into(),
+					format!("{out_dir}/{DASH_MANIFEST}"),
+				]);
+			}
+		}
+		args
+	}
+
 	/// Extract audio from a video or transcode audio file
 	pub fn extract_audio(input: &Path, output: &Path, opts: &AudioExtractOpts) -> ClResult<f64> {
 		let status = Command::new("ffmpeg")
@@ -326,4 +513,50 @@ impl FFmpeg {
 	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn renditions() -> Vec<StreamRendition> {
+		vec![
+			StreamRendition { name: "sd".into(), max_dim: 720, bitrate: 1500 },
+			StreamRendition { name: "md".into(), max_dim: 1280, bitrate: 3000 },
+		]
+	}
+
+	#[test]
+	fn test_hls_args_pair_audio_with_every_rendition() {
+		let args = FFmpeg::stream_args("in.mp4", "/out", &renditions(), StreamFormat::Hls, true);
+		assert_eq!(args.iter().filter(|a| *a == "0:a:0").count(), 2);
+		let map = args.iter().position(|a| a == "-var_stream_map").unwrap();
+		assert_eq!(args[map + 1], "v:0,a:0,name:sd v:1,a:1,name:md");
+		assert_eq!(args.last().unwrap(), "/out/%v/index.m3u8");
+		assert!(args.contains(&"-b:v:1".to_string()) && args.contains(&"3000k".to_string()));
+	}
+
+	#[test]
+	fn test_silent_video_maps_no_audio() {
+		let args = FFmpeg::stream_args("in.mp4", "/out", &renditions(), StreamFormat::Hls, false);
+		assert!(!args.iter().any(|a| a == "0:a:0" || a == "-c:a"));
+		let map = args.iter().position(|a| a == "-var_stream_map").unwrap();
+		assert_eq!(args[map + 1], "v:0,name:sd v:1,name:md");
+	}
+
+	#[test]
+	fn test_dash_shares_one_audio_stream() {
+		let args =
+			FFmpeg::stream_args("in.mp4", "/out", &renditions(), StreamFormat::HlsDash, true);
+		assert_eq!(args.iter().filter(|a| *a == "0:a:0").count(), 1);
+		assert!(args.contains(&"dash".to_string()));
+		assert_eq!(args.last().unwrap(), "/out/manifest.mpd");
+	}
+
+	#[test]
+	fn test_stream_format_setting_values() {
+		assert_eq!(StreamFormat::from_str_opt("hls"), Some(StreamFormat::Hls));
+		assert_eq!(StreamFormat::from_str_opt("hls+dash"), Some(StreamFormat::HlsDash));
+		assert_eq!(StreamFormat::from_str_opt("none"), None);
+	}
+}
+
 // vim: ts=4
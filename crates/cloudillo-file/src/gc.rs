@@ -130,6 +130,11 @@ async fn sweep_tenant(app: &App, tn_id: TnId, cutoff: i64) -> (u64, u64, u64, u6
 			(0, 0)
 		});
 
+	// Freed rows lower the tenant's usage; let the quota warning level follow.
+	if files_deleted > 0 && tn_id != SHARED_TN {
+		crate::quota::note_usage(app, tn_id).await;
+	}
+
 	(files_scanned, files_deleted, blobs_scanned, blobs_deleted)
 }
 
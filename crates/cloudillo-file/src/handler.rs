@@ -23,22 +23,26 @@ use crate::{
 	audio::AudioExtractorTask,
 	container::MAX_CONTAINER_BYTES,
 	descriptor::{self, FileIdGeneratorTask},
-	ffmpeg, filter, image,
+	exif, ffmpeg, filter, image,
 	image::ImageResizerTask,
-	pdf,
+	pdf, placeholder,
 	preset::{self, get_audio_tier, get_image_tier, get_video_tier, presets},
-	site_html, store, svg,
+	quota, site_html, store, svg,
 	variant::{self, VariantClass},
-	video::VideoTranscoderTask,
+	version,
+	video::{self, VideoStreamTask, VideoTranscoderTask},
 };
 use cloudillo_core::abac::relationship_level;
 use cloudillo_core::dir_cache::{DirCache, DirEntry};
 use cloudillo_core::extract::{Auth, IdTag, OptionalAuth, OptionalRequestId};
 use cloudillo_core::file_access;
+use cloudillo_core::storage_quota;
 use cloudillo_types::blob_adapter;
 use cloudillo_types::hasher;
 use cloudillo_types::meta_adapter;
-use cloudillo_types::meta_adapter::{MANAGED_PARENT_ID, ROOT_PARENT_ID, TRASH_PARENT_ID};
+use cloudillo_types::meta_adapter::{
+	MANAGED_PARENT_ID, ROOT_PARENT_ID, TRASH_PARENT_ID, VERSIONS_PARENT_ID,
+};
 use cloudillo_types::types::{self, AccessLevel, ApiResponse, TokenScope};
 use cloudillo_types::utils;
 
@@ -171,7 +175,7 @@ pub fn content_type_from_format(format: &str) -> &str {
 /// - `Some(Err(()))` — syntactically valid but unsatisfiable (caller → 416).
 /// - `Some(Ok((start, end)))` — satisfiable, `end` inclusive and clamped to
 ///   `total - 1`.
-fn parse_range(value: &str, total: u64) -> Option<Result<(u64, u64), ()>> {
+pub(crate) fn parse_range(value: &str, total: u64) -> Option<Result<(u64, u64), ()>> {
 	let spec = value.trim().strip_prefix("bytes=")?.trim();
 	// Multi-range is not supported; serve the full body instead.
 	if spec.contains(',') {
@@ -285,7 +289,9 @@ fn serve_file<S: AsRef<str> + Debug>(
 /// DB, so a `Some("__root__")` value never appears on a `FileView` from the
 /// adapter.
 fn is_terminal_parent(parent_id: &str) -> bool {
-	parent_id == TRASH_PARENT_ID || parent_id == MANAGED_PARENT_ID
+	parent_id == TRASH_PARENT_ID
+		|| parent_id == MANAGED_PARENT_ID
+		|| parent_id == VERSIONS_PARENT_ID
 }
 
 /// Best-effort folder resolve for breadcrumb/parent-name enrichment: a read
@@ -440,16 +446,20 @@ pub async fn get_file_list(
 	// share the same memoized parent-chain hops.
 	let dir_cache = app.ext::<DirCache>()?.clone();
 
+	// A file drop lists nothing — not even the folder it drops into.
+	if file_access::drop_scope(scope).is_some() {
+		return Err(Error::PermissionDenied);
+	}
+
 	let mut folder_scope_level: Option<types::AccessLevel> = None;
 	// For folder-share requests, the breadcrumb walk is bounded at this folder so
 	// ancestor names above the share root are not disclosed (set in every folder
 	// branch below).
 	let mut folder_scope_root: Option<String> = None;
-	if let Some((scope_fid, scope_access)) =
-		scope.and_then(TokenScope::parse).and_then(|ts| match ts {
-			TokenScope::File { file_id, access } => Some((file_id, access)),
-			TokenScope::ApkgPublish => None,
-		}) {
+	if let Some((scope_fid, scope_access)) = scope
+		.and_then(TokenScope::parse)
+		.and_then(|ts| ts.file_grant().map(|(file_id, access)| (file_id.to_owned(), access)))
+	{
 		let scope_is_folder =
 			file_access::scope_target_is_folder(&app.meta_adapter, &dir_cache, tn_id, &scope_fid)
 				.await?;
@@ -541,6 +551,7 @@ pub async fn get_file_list(
 		&& parent_id != ROOT_PARENT_ID
 		&& parent_id != TRASH_PARENT_ID
 		&& parent_id != MANAGED_PARENT_ID
+		&& parent_id != VERSIONS_PARENT_ID
 	{
 		inherited_share =
 			file_access::check_share_for_file(&app, tn_id, parent_id, subject_id_tag).await;
@@ -637,15 +648,25 @@ pub async fn get_file_list(
 	Ok((StatusCode::OK, Json(response)))
 }
 
+/// Whether `variant` names an original — never served to a view-only share link.
+fn is_original(variant: &str) -> bool {
+	variant::parse_quality(variant) == Some(variant::VariantQuality::Original)
+}
+
 /// GET /api/files/variant/{variant_id}
 pub async fn get_file_variant(
 	State(app): State<App>,
 	tn_id: TnId,
+	OptionalAuth(maybe_auth): OptionalAuth,
 	extract::Path(variant_id): extract::Path<String>,
 	headers: HeaderMap,
 ) -> ClResult<impl response::IntoResponse> {
 	let variant = app.meta_adapter.read_file_variant(tn_id, &variant_id).await?;
 	info!("variant: {:?}", variant);
+	let scope = maybe_auth.as_ref().and_then(|auth| auth.scope.as_deref());
+	if file_access::is_view_only_scope(scope) && is_original(&variant.variant) {
+		return Err(Error::PermissionDenied);
+	}
 	let blob_tn = if variant.global { TnId(0) } else { tn_id };
 
 	let range = headers
@@ -658,7 +679,7 @@ pub async fn get_file_variant(
 
 /// Build a `416 Range Not Satisfiable` response carrying the resource size in
 /// `Content-Range: bytes */{total}` and no body.
-fn range_not_satisfiable(total: u64) -> response::Response<axum::body::Body> {
+pub(crate) fn range_not_satisfiable(total: u64) -> response::Response<axum::body::Body> {
 	axum::response::Response::builder()
 		.status(StatusCode::RANGE_NOT_SATISFIABLE)
 		.header(axum::http::header::ACCEPT_RANGES, "bytes")
@@ -683,6 +704,7 @@ pub struct GetFileVariantSelector {
 pub async fn get_file_variant_file_id(
 	State(app): State<App>,
 	tn_id: TnId,
+	OptionalAuth(maybe_auth): OptionalAuth,
 	extract::Path(file_id): extract::Path<String>,
 	extract::Query(selector): extract::Query<GetFileVariantSelector>,
 	headers: HeaderMap,
@@ -691,6 +713,11 @@ pub async fn get_file_variant_file_id(
 		.meta_adapter
 		.list_file_variants(tn_id, meta_adapter::FileId::FileId(&file_id))
 		.await?;
+	// A view-only link sees the file through its previews: without the original,
+	// the usual quality fallback serves the largest one instead.
+	if file_access::is_view_only_scope(maybe_auth.as_ref().and_then(|a| a.scope.as_deref())) {
+		variants.retain(|v| !is_original(&v.variant));
+	}
 	variants.sort();
 	debug!("variants: {:?}", variants);
 
@@ -715,8 +742,11 @@ pub async fn get_file_variant_file_id(
 		Some(Err(())) => false, // unsatisfiable → 416, no body/descriptor
 	};
 	let descriptor = if needs_descriptor {
-		let root_id = app.meta_adapter.read_file(tn_id, &file_id).await?.and_then(|f| f.root_id);
-		Some(descriptor::get_file_descriptor(&variants, root_id.as_deref()))
+		let file = app.meta_adapter.read_file(tn_id, &file_id).await?;
+		let root_id = file.as_ref().and_then(|f| f.root_id.as_deref());
+		let placeholder = file.as_ref().and_then(|f| placeholder::of_x(f.x.as_ref()));
+		let drop_link = file.as_ref().and_then(|f| drop_link_of_x(f.x.as_ref()));
+		Some(descriptor::get_file_descriptor(&variants, root_id, placeholder, drop_link))
 	} else {
 		None
 	};
@@ -745,8 +775,11 @@ pub async fn get_file_descriptor(
 		.await?;
 	variants.sort();
 
-	let root_id = app.meta_adapter.read_file(tn_id, &file_id).await?.and_then(|f| f.root_id);
-	let descriptor = descriptor::get_file_descriptor(&variants, root_id.as_deref());
+	let file = app.meta_adapter.read_file(tn_id, &file_id).await?;
+	let root_id = file.as_ref().and_then(|f| f.root_id.as_deref());
+	let placeholder = file.as_ref().and_then(|f| placeholder::of_x(f.x.as_ref()));
+	let drop_link = file.as_ref().and_then(|f| drop_link_of_x(f.x.as_ref()));
+	let descriptor = descriptor::get_file_descriptor(&variants, root_id, placeholder, drop_link);
 
 	let response = ApiResponse::new(descriptor).with_req_id(req_id.unwrap_or_default());
 
@@ -771,6 +804,11 @@ pub struct PostFileQuery {
 	/// is ignored when this is set.
 	#[serde(rename = "as")]
 	as_kind: Option<String>,
+	/// Upload a new revision of this file instead of a new file. The revision
+	/// takes the file's place — folder, visibility, tags and shares — once its
+	/// content id is generated; `parentId`, `rootId` and `as` are ignored.
+	#[serde(rename = "versionOf")]
+	version_of: Option<String>,
 }
 
 /// Resolve the effective parent_id from a request's `as` and `parentId` fields.
@@ -779,6 +817,7 @@ pub struct PostFileQuery {
 /// Otherwise, the explicit `parentId` is honored — except a client cannot plant
 /// a file directly into `__managed__` or `__trash__` without the matching
 /// `as=managed` hint, since those would be silently GC'd or hidden in trash.
+/// `__versions__` is never accepted: only `versionOf` places a file there.
 fn resolve_managed_parent(
 	as_kind: Option<&str>,
 	parent_id: Option<&str>,
@@ -787,7 +826,7 @@ fn resolve_managed_parent(
 		return Ok(Some(MANAGED_PARENT_ID.to_string()));
 	}
 	if let Some(p) = parent_id
-		&& (p == MANAGED_PARENT_ID || p == TRASH_PARENT_ID)
+		&& (p == MANAGED_PARENT_ID || p == TRASH_PARENT_ID || p == VERSIONS_PARENT_ID)
 	{
 		return Err(Error::ValidationError(format!(
 			"parentId '{}' is reserved and requires as=managed",
@@ -841,7 +880,7 @@ impl PostFileRequest {
 	}
 }
 
-async fn handle_post_image(
+pub(crate) async fn handle_post_image(
 	app: &App,
 	tn_id: types::TnId,
 	f_id: u64,
@@ -858,6 +897,9 @@ async fn handle_post_image(
 	if let Some(thumb_id) = result.thumbnail_variant_id {
 		data["thumbnailVariantId"] = serde_json::Value::String(thumb_id);
 	}
+	if let Some(placeholder) = result.placeholder {
+		data["placeholder"] = serde_json::Value::String(placeholder);
+	}
 	Ok(data)
 }
 
@@ -1140,6 +1182,7 @@ async fn handle_post_video_stream(
 		thumbnail_result.height,
 		thumbnail_result.bytes.len()
 	);
+	let placeholder = placeholder::store(app, tn_id, f_id, &thumbnail_result.bytes).await;
 
 	// 5. Create tasks based on preset (async)
 	let mut task_ids = Vec::new();
@@ -1189,6 +1232,28 @@ async fn handle_post_video_stream(
 		}
 	}
 
+	// 5c. Package the same tiers as one adaptive stream, if enabled
+	let stream_format = app
+		.settings
+		.get_string(tn_id, "file.video_stream_format")
+		.await
+		.ok()
+		.and_then(|s| ffmpeg::StreamFormat::from_str_opt(&s));
+	if let Some(format) = stream_format {
+		let tiers = preset.video_variants.iter().filter_map(|name| {
+			let within = variant::Variant::parse(name).is_none_or(|v| v.quality <= max_quality);
+			within.then(|| get_video_tier(name)).flatten()
+		});
+		let tiers: Vec<Box<str>> = video::stream_tiers(tiers, resolution)
+			.into_iter()
+			.map(|t| Box::from(t.name))
+			.collect();
+		if !tiers.is_empty() {
+			let task = VideoStreamTask::new(tn_id, f_id, temp_path.to_owned(), format, tiers);
+			task_ids.push(app.scheduler.add(task).await?);
+		}
+	}
+
 	// 6. Optionally extract audio
 	if preset.extract_audio {
 		for variant_name in &preset.audio_variants {
@@ -1225,7 +1290,8 @@ async fn handle_post_video_stream(
 		"fileId": format!("@{}", f_id),
 		"duration": duration,
 		"resolution": [resolution.0, resolution.1],
-		"thumbnailVariantId": thumbnail_variant_id
+		"thumbnailVariantId": thumbnail_variant_id,
+		"placeholder": placeholder
 	}))
 }
 
@@ -1363,7 +1429,8 @@ async fn handle_post_pdf(
 
 	Ok(json!({
 		"fileId": format!("@{}", f_id),
-		"thumbnailVariantId": pdf_result.variant_id
+		"thumbnailVariantId": pdf_result.variant_id,
+		"placeholder": pdf_result.placeholder
 	}))
 }
 
@@ -1454,6 +1521,10 @@ pub async fn post_file(
 	)
 	.await?;
 
+	// New documents take no blob space yet, but a tenant already over its
+	// quota may not create more.
+	storage_quota::check(&app, tn_id, 0).await?;
+
 	// Cross-context creation (Hand verbs: Pin / Place) routes through a dedicated
 	// branch before the normal new-blob path. Triggered by the presence of
 	// `sourceFileId` + `sourceIdTag`.
@@ -1993,7 +2064,7 @@ pub async fn refresh_file(
 	Ok((StatusCode::OK, Json(ApiResponse::new(body).with_req_id(req_id.unwrap_or_default()))))
 }
 
-async fn build_dedup_response(
+pub(crate) async fn build_dedup_response(
 	app: &App,
 	tn_id: types::TnId,
 	id_tag: &str,
@@ -2013,11 +2084,46 @@ async fn build_dedup_response(
 	Ok((StatusCode::OK, Json(response)))
 }
 
+/// Dedup response for a file drop: only the id of the file the same link dropped
+/// before. The dropper can read nothing in the folder, so neither its metadata nor
+/// an access record is handed out.
+fn build_drop_dedup_response(
+	file_id: &str,
+	req_id: Option<String>,
+) -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+	info!("Drop dedup hit: file_id={}", file_id);
+	let data = json!({ "fileId": file_id, "existed": true });
+	(StatusCode::OK, Json(ApiResponse::new(data).with_req_id(req_id.unwrap_or_default())))
+}
+
+/// Key of the share link a dropped file came through, in its `x`.
+pub(crate) const DROP_LINK_X_KEY: &str = "dropLink";
+
+/// The share link a file was dropped through, kept in its `x` (see [`with_drop_link`]).
+pub(crate) fn drop_link_of_x(x: Option<&serde_json::Value>) -> Option<&str> {
+	x?.get(DROP_LINK_X_KEY)?.as_str()
+}
+
+/// `x` of a new upload, plus — for a file drop — the share link it came through
+/// (the link's redacted digest, as the Sharing panel shows it to readers).
+fn with_drop_link(
+	x: Option<serde_json::Value>,
+	drop_link: Option<&str>,
+) -> Option<serde_json::Value> {
+	let Some(link) = drop_link else { return x };
+	let mut x = x.unwrap_or_else(|| json!({}));
+	if let Some(obj) = x.as_object_mut() {
+		obj.insert(DROP_LINK_X_KEY.into(), link.into());
+	}
+	Some(x)
+}
+
 #[expect(clippy::too_many_arguments, reason = "file processing requires multiple parameters")]
 pub async fn post_file_blob(
 	State(app): State<App>,
 	tn_id: TnId,
 	Auth(auth): Auth,
+	IdTag(tenant_id_tag): IdTag,
 	extract::Path((preset_name, file_name)): extract::Path<(String, String)>,
 	query: Query<PostFileQuery>,
 	header: axum::http::HeaderMap,
@@ -2029,19 +2135,62 @@ pub async fn post_file_blob(
 	const DEFAULT_MAX_SIZE_MIB: i64 = 50;
 	const DEFAULT_MAX_STREAMING_SIZE_MIB: i64 = 100;
 
+	// A file drop uploads on behalf of its link, never as a new revision of a
+	// file the dropper cannot see.
+	let drop = file_access::drop_scope(auth.scope.as_deref());
+	if drop.is_some() && query.version_of.is_some() {
+		return Err(Error::PermissionDenied);
+	}
+
+	// A new revision of an existing file waits in the versions folder until its
+	// content id exists; the id generator then promotes it into the file's place.
+	let version = match query.version_of.as_deref() {
+		Some(file_id) => {
+			Some(version::resolve_upload_target(&app, tn_id, &auth, &tenant_id_tag, file_id).await?)
+		}
+		None => None,
+	};
+	let (placement, root_id) = match &version {
+		Some(v) => (
+			v.current.parent_id.as_deref().map(str::to_owned),
+			v.current.root_id.as_deref().map(str::to_owned),
+		),
+		None => (query.effective_parent_id()?, query.root_id.clone()),
+	};
+	let parent_id =
+		if version.is_some() { Some(VERSIONS_PARENT_ID.to_owned()) } else { placement.clone() };
+	let version_of = version.as_ref().map(|v| v.version_of.clone());
+
 	// Scope check: scoped tokens can only create children under the scoped root,
 	// or — for folder share links — anywhere within the scoped folder's subtree.
+	// A revision is checked where it will end up, in place of the current one.
 	let dir_cache = app.ext::<DirCache>()?;
 	file_access::check_scope_allows_create_in(
 		&app.meta_adapter,
 		dir_cache,
 		tn_id,
 		auth.scope.as_deref(),
-		query.effective_parent_id()?.as_deref(),
-		query.root_id.as_deref(),
+		placement.as_deref(),
+		root_id.as_deref(),
 	)
 	.await?;
 
+	// Past the scope check, a drop is known to land directly in its folder. It is
+	// processed under the folder's preset, not one the stranger picks, and is
+	// attributed to the link rather than to a person.
+	let preset_name = match &drop {
+		Some((folder_id, _)) => app
+			.meta_adapter
+			.read_file(tn_id, folder_id)
+			.await?
+			.and_then(|folder| folder.preset)
+			.filter(|name| presets::get(name).is_some())
+			.map_or_else(|| presets::default().name, Into::into),
+		None => preset_name,
+	};
+	let drop_link = drop.as_ref().map(|(_, link)| link.as_str());
+	let creator_tag = if drop.is_some() { None } else { Some(auth.id_tag.clone()) };
+
 	let content_type = header
 		.get(axum::http::header::CONTENT_TYPE)
 		.and_then(|v| v.to_str().ok())
@@ -2051,9 +2200,10 @@ pub async fn post_file_blob(
 		preset_name, content_type, query.root_id, query.parent_id
 	);
 
-	// Default visibility to 'C' (Connected) for community tenants
+	// Default visibility to 'C' (Connected) for community tenants. A drop cannot
+	// choose: publishing into the owner's space is not the dropper's call.
 	let tenant_meta = app.meta_adapter.read_tenant(tn_id).await?;
-	let visibility = match query.visibility {
+	let visibility = match query.visibility.filter(|_| drop.is_none()) {
 		Some(v) => Some(v),
 		None if matches!(tenant_meta.typ, meta_adapter::ProfileType::Community) => Some('C'),
 		None => None,
@@ -2094,6 +2244,15 @@ pub async fn post_file_blob(
 
 	info!("Media class: {:?}", media_class);
 
+	// Refuse up front when the declared length already cannot fit; the real
+	// size is checked again once the body is read.
+	let declared_len = header
+		.get(axum::http::header::CONTENT_LENGTH)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| v.parse::<u64>().ok())
+		.unwrap_or(0);
+	storage_quota::check(&app, tn_id, declared_len).await?;
+
 	let max_size_mib = app
 		.settings
 		.get_int(tn_id, "file.max_file_size_mb")
@@ -2117,13 +2276,26 @@ pub async fn post_file_blob(
 		// In-memory processing (small files)
 		VariantClass::Visual => {
 			let bytes = to_bytes(body, max_size_bytes).await?;
-			let orig_variant_id = hasher::hash("b", &bytes);
-			info!("Content id: {} ({} bytes)", orig_variant_id, bytes.len());
 
 			// Detect if this is an SVG (check content-type or content itself)
 			let is_svg = content_type == "image/svg+xml"
 				|| (content_type == "application/octet-stream" && svg::is_svg(&bytes));
 
+			// Embedded metadata is read, and stripped if asked, before anything is
+			// hashed: the content id must be that of the bytes actually stored.
+			let (bytes, exif) = if is_svg {
+				(bytes, None)
+			} else {
+				let strip = preset.strip_metadata
+					|| app.settings.get_bool(tn_id, "file.strip_metadata").await.unwrap_or(false);
+				let (bytes, meta) = exif::prepare_upload(&app, bytes, strip).await?;
+				(bytes, (!meta.is_empty()).then_some(meta))
+			};
+
+			let orig_variant_id = hasher::hash("b", &bytes);
+			info!("Content id: {} ({} bytes)", orig_variant_id, bytes.len());
+			storage_quota::check(&app, tn_id, bytes.len() as u64).await?;
+
 			// Get dimensions - SVG uses different parsing
 			let dim = if is_svg {
 				svg::parse_svg_dimensions(&bytes)?
@@ -2139,7 +2311,7 @@ pub async fn post_file_blob(
 					meta_adapter::CreateFile {
 						preset: Some(preset_name.clone().into()),
 						orig_variant_id: Some(orig_variant_id.clone()),
-						creator_tag: Some(auth.id_tag.clone()),
+						creator_tag: creator_tag.clone(),
 						content_type: if is_svg {
 							"image/svg+xml".into()
 						} else {
@@ -2149,9 +2321,16 @@ pub async fn post_file_blob(
 						file_tp: Some("BLOB".into()),
 						created_at: query.created_at,
 						tags: query.tags.as_ref().map(|s| s.split(',').map(Into::into).collect()),
-						x: Some(json!({ "dim": dim })),
-						root_id: query.root_id.clone().map(Into::into),
-						parent_id: query.effective_parent_id()?.map(Into::into),
+						x: with_drop_link(
+							Some(match exif {
+								Some(exif) => json!({ "dim": dim, "exif": exif }),
+								None => json!({ "dim": dim }),
+							}),
+							drop_link,
+						),
+						root_id: root_id.clone().map(Into::into),
+						parent_id: parent_id.clone().map(Into::into),
+						version_of: version_of.clone(),
 						visibility,
 						..Default::default()
 					},
@@ -2167,10 +2346,17 @@ pub async fn post_file_blob(
 					} else {
 						handle_post_image(&app, tn_id, f_id, content_type, &bytes, &preset).await?
 					};
+					quota::note_usage(&app, tn_id).await;
 					let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
 					Ok((StatusCode::CREATED, Json(response)))
 				}
 				meta_adapter::FileId::FileId(file_id) => {
+					if let Some(version) = &version {
+						version::on_dedup(&app, tn_id, version, &file_id).await?;
+					}
+					if drop.is_some() {
+						return Ok(build_drop_dedup_response(&file_id, req_id));
+					}
 					return build_dedup_response(&app, tn_id, &auth.id_tag, &file_id, req_id).await;
 				}
 			}
@@ -2180,6 +2366,7 @@ pub async fn post_file_blob(
 			let bytes = to_bytes(body, max_size_bytes).await?;
 			let orig_variant_id = hasher::hash("b", &bytes);
 			info!("Content id: {} ({} bytes)", orig_variant_id, bytes.len());
+			storage_quota::check(&app, tn_id, bytes.len() as u64).await?;
 
 			let f_id = app
 				.meta_adapter
@@ -2188,14 +2375,16 @@ pub async fn post_file_blob(
 					meta_adapter::CreateFile {
 						preset: Some(preset_name.clone().into()),
 						orig_variant_id: Some(orig_variant_id),
-						creator_tag: Some(auth.id_tag.clone()),
+						creator_tag: creator_tag.clone(),
+						x: with_drop_link(None, drop_link),
 						content_type: content_type.into(),
 						file_name: file_name.into(),
 						file_tp: Some("BLOB".into()),
 						created_at: query.created_at,
 						tags: query.tags.as_ref().map(|s| s.split(',').map(Into::into).collect()),
-						root_id: query.root_id.clone().map(Into::into),
-						parent_id: query.effective_parent_id()?.map(Into::into),
+						root_id: root_id.clone().map(Into::into),
+						parent_id: parent_id.clone().map(Into::into),
+						version_of: version_of.clone(),
 						visibility,
 						..Default::default()
 					},
@@ -2205,10 +2394,17 @@ pub async fn post_file_blob(
 			match f_id {
 				meta_adapter::FileId::FId(f_id) => {
 					let data = handle_post_pdf(&app, tn_id, f_id, &bytes).await?;
+					quota::note_usage(&app, tn_id).await;
 					let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
 					Ok((StatusCode::CREATED, Json(response)))
 				}
 				meta_adapter::FileId::FileId(file_id) => {
+					if let Some(version) = &version {
+						version::on_dedup(&app, tn_id, version, &file_id).await?;
+					}
+					if drop.is_some() {
+						return Ok(build_drop_dedup_response(&file_id, req_id));
+					}
 					return build_dedup_response(&app, tn_id, &auth.id_tag, &file_id, req_id).await;
 				}
 			}
@@ -2228,6 +2424,7 @@ pub async fn post_file_blob(
 				"Video upload streamed to {:?}, size: {} bytes, content id: {}",
 				temp_path, total_size, orig_blob_id
 			);
+			storage_quota::check(&app, tn_id, total_size).await?;
 
 			let media_info = ffmpeg::FFmpeg::probe(&temp_path)
 				.map_err(|e| Error::Internal(format!("ffprobe failed: {}", e)))?;
@@ -2254,14 +2451,16 @@ pub async fn post_file_blob(
 					meta_adapter::CreateFile {
 						preset: Some(preset_name.clone().into()),
 						orig_variant_id: Some(orig_blob_id.clone()),
-						creator_tag: Some(auth.id_tag.clone()),
+						creator_tag: creator_tag.clone(),
+						x: with_drop_link(None, drop_link),
 						content_type: content_type.into(),
 						file_name: file_name.into(),
 						file_tp: Some("BLOB".into()),
 						created_at: query.created_at,
 						tags: query.tags.as_ref().map(|s| s.split(',').map(Into::into).collect()),
-						root_id: query.root_id.clone().map(Into::into),
-						parent_id: query.effective_parent_id()?.map(Into::into),
+						root_id: root_id.clone().map(Into::into),
+						parent_id: parent_id.clone().map(Into::into),
+						version_of: version_of.clone(),
 						visibility,
 						..Default::default()
 					},
@@ -2290,11 +2489,18 @@ pub async fn post_file_blob(
 					.await?;
 					// Transcode tasks consume the temp file; keep it past this request.
 					temp_guard.keep();
+					quota::note_usage(&app, tn_id).await;
 					let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
 					Ok((StatusCode::CREATED, Json(response)))
 				}
 				meta_adapter::FileId::FileId(file_id) => {
 					// Dedup hit: keep relying on TempFileGuard's Drop to clean up.
+					if let Some(version) = &version {
+						version::on_dedup(&app, tn_id, version, &file_id).await?;
+					}
+					if drop.is_some() {
+						return Ok(build_drop_dedup_response(&file_id, req_id));
+					}
 					return build_dedup_response(&app, tn_id, &auth.id_tag, &file_id, req_id).await;
 				}
 			}
@@ -2311,6 +2517,7 @@ pub async fn post_file_blob(
 				"Audio upload streamed to {:?}, size: {} bytes, content id: {}",
 				temp_path, total_size, orig_blob_id
 			);
+			storage_quota::check(&app, tn_id, total_size).await?;
 
 			let media_info = ffmpeg::FFmpeg::probe(&temp_path)
 				.map_err(|e| Error::Internal(format!("ffprobe failed: {}", e)))?;
@@ -2336,14 +2543,16 @@ pub async fn post_file_blob(
 					meta_adapter::CreateFile {
 						preset: Some(preset_name.clone().into()),
 						orig_variant_id: Some(orig_blob_id.clone()),
-						creator_tag: Some(auth.id_tag.clone()),
+						creator_tag: creator_tag.clone(),
+						x: with_drop_link(None, drop_link),
 						content_type: content_type.into(),
 						file_name: file_name.into(),
 						file_tp: Some("BLOB".into()),
 						created_at: query.created_at,
 						tags: query.tags.as_ref().map(|s| s.split(',').map(Into::into).collect()),
-						root_id: query.root_id.clone().map(Into::into),
-						parent_id: query.effective_parent_id()?.map(Into::into),
+						root_id: root_id.clone().map(Into::into),
+						parent_id: parent_id.clone().map(Into::into),
+						version_of: version_of.clone(),
 						visibility,
 						..Default::default()
 					},
@@ -2371,11 +2580,18 @@ pub async fn post_file_blob(
 					.await?;
 					// Audio extractor task consumes the temp file; keep it past this request.
 					temp_guard.keep();
+					quota::note_usage(&app, tn_id).await;
 					let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
 					Ok((StatusCode::CREATED, Json(response)))
 				}
 				meta_adapter::FileId::FileId(file_id) => {
 					// Dedup hit: keep relying on TempFileGuard's Drop to clean up.
+					if let Some(version) = &version {
+						version::on_dedup(&app, tn_id, version, &file_id).await?;
+					}
+					if drop.is_some() {
+						return Ok(build_drop_dedup_response(&file_id, req_id));
+					}
 					return build_dedup_response(&app, tn_id, &auth.id_tag, &file_id, req_id).await;
 				}
 			}
@@ -2392,6 +2608,7 @@ pub async fn post_file_blob(
 				"Raw upload streamed to {:?}, size: {} bytes, content id: {}",
 				temp_path, total_size, orig_blob_id
 			);
+			storage_quota::check(&app, tn_id, total_size).await?;
 
 			// A published site's fragments are served from the tenant's own app
 			// domain, under a CSP carrying `script-src 'unsafe-inline'` — so the
@@ -2433,14 +2650,16 @@ pub async fn post_file_blob(
 					meta_adapter::CreateFile {
 						preset: Some(preset_name.clone().into()),
 						orig_variant_id: Some(orig_blob_id.clone()),
-						creator_tag: Some(auth.id_tag.clone()),
+						creator_tag: creator_tag.clone(),
+						x: with_drop_link(None, drop_link),
 						content_type: content_type.into(),
 						file_name: file_name.into(),
 						file_tp: Some("BLOB".into()),
 						created_at: query.created_at,
 						tags: query.tags.as_ref().map(|s| s.split(',').map(Into::into).collect()),
-						root_id: query.root_id.clone().map(Into::into),
-						parent_id: query.effective_parent_id()?.map(Into::into),
+						root_id: root_id.clone().map(Into::into),
+						parent_id: parent_id.clone().map(Into::into),
+						version_of: version_of.clone(),
 						visibility,
 						..Default::default()
 					},
@@ -2465,12 +2684,19 @@ pub async fn post_file_blob(
 					.await?;
 					// handle_post_raw_stream removed the temp file on success.
 					temp_guard.keep();
+					quota::note_usage(&app, tn_id).await;
 					let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
 					Ok((StatusCode::CREATED, Json(response)))
 				}
 				meta_adapter::FileId::FileId(file_id) => {
 					let _ = tokio::fs::remove_file(&temp_path).await;
 					temp_guard.keep();
+					if let Some(version) = &version {
+						version::on_dedup(&app, tn_id, version, &file_id).await?;
+					}
+					if drop.is_some() {
+						return Ok(build_drop_dedup_response(&file_id, req_id));
+					}
 					return build_dedup_response(&app, tn_id, &auth.id_tag, &file_id, req_id).await;
 				}
 			}
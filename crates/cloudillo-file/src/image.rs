@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
 use async_trait::async_trait;
-use image::ImageReader;
+use image::{DynamicImage, ImageDecoder, ImageReader, metadata::Orientation};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -38,7 +38,7 @@ fn f32_to_u32(v: f32) -> u32 {
 ///
 /// Guards against decompression bombs: a small, highly-compressed file can
 /// declare enormous dimensions and force the decoder to allocate gigabytes.
-const MAX_DECODE_DIM: u32 = 16_384;
+pub(crate) const MAX_DECODE_DIM: u32 = 16_384;
 
 /// Maximum number of bytes the image decoder may allocate for a single image.
 const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024; // 256 MiB
@@ -95,56 +95,72 @@ impl std::str::FromStr for ImageFormat {
 	}
 }
 
-// Sync image resizer
-fn resize_image_sync<'a>(
-	orig_buf: impl AsRef<[u8]> + 'a,
-	format: ImageFormat,
-	resize: (u32, u32),
-) -> Result<ResizeResult, image::error::ImageError> {
-	let now = std::time::Instant::now();
-	let mut reader = ImageReader::new(Cursor::new(orig_buf.as_ref())).with_guessed_format()?;
+/// Decode an image under [`decode_limits`], turned upright.
+///
+/// Variants carry no EXIF, so whatever turns the original upright must be
+/// applied to the pixels. A broken orientation tag is no reason to fail.
+pub(crate) fn decode_oriented(buf: &[u8]) -> Result<DynamicImage, image::error::ImageError> {
+	let mut reader = ImageReader::new(Cursor::new(buf)).with_guessed_format()?;
 	reader.limits(decode_limits());
-	let original = reader.decode()?;
-	debug!("decoded [{:.2}ms]", now.elapsed().as_millis());
-
-	let now = std::time::Instant::now();
-	let resized = original.resize(resize.0, resize.1, image::imageops::FilterType::Lanczos3);
-	let actual_width = resized.width();
-	let actual_height = resized.height();
-	debug!("resized [{:.2}ms]", now.elapsed().as_millis());
+	let mut decoder = reader.into_decoder()?;
+	let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+	let mut image = DynamicImage::from_decoder(decoder)?;
+	image.apply_orientation(orientation);
+	Ok(image)
+}
 
+/// Encode `image` in `format` at the quality variants are stored with.
+pub(crate) fn encode_image(
+	image: &DynamicImage,
+	format: ImageFormat,
+) -> Result<Vec<u8>, image::error::ImageError> {
 	let mut output = Cursor::new(Vec::new());
-	let now = std::time::Instant::now();
-
 	match format {
 		ImageFormat::Avif => {
 			let encoder =
 				image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut output, 4, 80)
 					.with_num_threads(Some(1));
-			resized.write_with_encoder(encoder)?;
+			image.write_with_encoder(encoder)?;
 		}
 		ImageFormat::Webp => {
 			// Use webp crate for lossy encoding with quality 80
-			let rgba = resized.to_rgba8();
-			let encoder = webp::Encoder::from_rgba(rgba.as_raw(), actual_width, actual_height);
+			let rgba = image.to_rgba8();
+			let encoder = webp::Encoder::from_rgba(rgba.as_raw(), image.width(), image.height());
 			let webp_data = encoder.encode(80.0); // Quality 0-100
 			output.get_mut().write_all(&webp_data)?;
 		}
 		ImageFormat::Jpeg => {
 			let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, 95);
-			resized.write_with_encoder(encoder)?;
+			image.write_with_encoder(encoder)?;
 		}
 		ImageFormat::Png => {
 			let encoder = image::codecs::png::PngEncoder::new(&mut output);
-			resized.write_with_encoder(encoder)?;
+			image.write_with_encoder(encoder)?;
 		}
 	}
+	Ok(output.into_inner())
+}
+
+// Sync image resizer
+fn resize_image_sync<'a>(
+	orig_buf: impl AsRef<[u8]> + 'a,
+	format: ImageFormat,
+	resize: (u32, u32),
+) -> Result<ResizeResult, image::error::ImageError> {
+	let now = std::time::Instant::now();
+	let original = decode_oriented(orig_buf.as_ref())?;
+	debug!("decoded [{:.2}ms]", now.elapsed().as_millis());
+
+	let now = std::time::Instant::now();
+	let resized = original.resize(resize.0, resize.1, image::imageops::FilterType::Lanczos3);
+	let actual_width = resized.width();
+	let actual_height = resized.height();
+	debug!("resized [{:.2}ms]", now.elapsed().as_millis());
+
+	let now = std::time::Instant::now();
+	let bytes = encode_image(&resized, format)?;
 	debug!("written [{:.2}ms]", now.elapsed().as_millis());
-	Ok(ResizeResult {
-		bytes: output.into_inner().into(),
-		width: actual_width,
-		height: actual_height,
-	})
+	Ok(ResizeResult { bytes: bytes.into(), width: actual_width, height: actual_height })
 }
 
 pub async fn resize_image(
@@ -162,11 +178,21 @@ pub async fn resize_image(
 		.map_err(|e| image::error::ImageError::IoError(std::io::Error::other(e.to_string())))?
 }
 
+/// The image's displayed dimensions: width and height swap when its EXIF
+/// orientation turns it by 90°, as the variants generated from it are.
 pub async fn get_image_dimensions(buf: &[u8]) -> Result<(u32, u32), image::error::ImageError> {
 	let now = std::time::Instant::now();
 	let mut reader = ImageReader::new(Cursor::new(&buf)).with_guessed_format()?;
 	reader.limits(decode_limits());
-	let dim = reader.into_dimensions()?;
+	let mut decoder = reader.into_decoder()?;
+	let (w, h) = decoder.dimensions();
+	let dim = match decoder.orientation().unwrap_or(Orientation::NoTransforms) {
+		Orientation::Rotate90
+		| Orientation::Rotate270
+		| Orientation::Rotate90FlipH
+		| Orientation::Rotate270FlipH => (h, w),
+		_ => (w, h),
+	};
 	debug!("dimensions read in [{:.2}ms]", now.elapsed().as_millis());
 	Ok(dim)
 }
@@ -193,13 +219,16 @@ pub struct ImageVariantResult {
 	pub file_id_task: TaskId,
 	/// Original image dimensions (width, height)
 	pub dim: (u32, u32),
+	/// Placeholder hash computed from the thumbnail, None without a thumbnail
+	pub placeholder: Option<String>,
 }
 
 /// Generate image variants based on preset configuration.
 ///
 /// This is the main helper function for image processing. It:
 /// 1. Creates "orig" variant record (blob stored only if preset.store_original is true)
-/// 2. Creates the thumbnail variant synchronously (from preset.thumbnail_variant)
+/// 2. Creates the thumbnail variant synchronously (from preset.thumbnail_variant), and
+///    the file's placeholder from it
 /// 3. Schedules tasks for remaining variants
 /// 4. Schedules FileIdGeneratorTask depending on all variant tasks
 ///
@@ -292,6 +321,7 @@ pub async fn generate_image_variants(
 	}
 
 	// Conditionally create thumbnail variant synchronously
+	let mut placeholder = None;
 	let thumbnail_variant_id = if let Some(ref tn_variant_name) = preset.thumbnail_variant {
 		let thumbnail_tier = preset::get_image_tier(tn_variant_name);
 
@@ -329,6 +359,7 @@ pub async fn generate_image_variants(
 				},
 			)
 			.await?;
+		placeholder = crate::placeholder::store(app, tn_id, f_id, &resized_tn.bytes).await;
 		Some(String::from(thumb_variant_id))
 	} else {
 		None
@@ -413,7 +444,7 @@ pub async fn generate_image_variants(
 	}
 	let file_id_task = builder.schedule().await?;
 
-	Ok(ImageVariantResult { thumbnail_variant_id, file_id_task, dim: orig_dim })
+	Ok(ImageVariantResult { thumbnail_variant_id, file_id_task, dim: orig_dim, placeholder })
 }
 
 /// Image resizer Task
@@ -4,27 +4,35 @@
 //! File subsystem. File storage, metadata, documents, etc.
 
 pub mod apkg;
+pub mod archive;
 pub(crate) mod audio;
 pub(crate) mod container;
 pub mod descriptor;
 pub(crate) mod duplicate;
+pub mod edit;
+pub(crate) mod exif;
 pub(crate) mod ffmpeg;
 pub mod filter;
 pub mod gc;
 pub mod handler;
 pub mod image;
+pub mod link_preview;
 pub mod management;
 pub(crate) mod pdf;
 pub mod perm;
+pub(crate) mod placeholder;
 pub mod preset;
+pub mod quota;
 pub mod settings;
 pub mod share;
 pub mod site_html;
 pub(crate) mod store;
+pub mod stream;
 pub(crate) mod svg;
 pub mod sync;
 pub mod tag;
 pub(crate) mod variant;
+pub mod version;
 pub(crate) mod video;
 
 mod prelude;
@@ -52,6 +60,11 @@ pub fn new_container_cache() -> Arc<ContainerCache> {
 	Arc::new(ContainerCache::new())
 }
 
+/// Create a new link-preview cache for registration in extensions
+pub fn new_link_preview_cache() -> Arc<link_preview::LinkPreviewCache> {
+	Arc::new(link_preview::LinkPreviewCache::new())
+}
+
 /// A container resolved once, and everything read out of it afterwards.
 ///
 /// Resolution — fileId → `orig` variant id → parsed index — happens in
@@ -149,8 +162,98 @@ pub async fn open_container(
 	Ok(Container { tn_id, index })
 }
 
+/// The variant an adaptive-streaming container is stored under. See
+/// `video::VideoStreamTask`.
+pub const STREAM_VARIANT: &str = "vid.stream";
+
+/// Open a video's adaptive-streaming container — its [`STREAM_VARIANT`] — by fileId.
+///
+/// Unlike [`open_container`] this never reads the whole blob: a stream container holds
+/// every rendition of the video, far past [`MAX_CONTAINER_BYTES`]. The index is built
+/// from the blob's tail — end record, central directory — plus one small read per
+/// entry's local header, which the few entries such a container has keep cheap. Cached
+/// beside the `orig` indexes under `<fileId>#<variant>`, single-flighted the same way.
+///
+/// Its entries are stored, not deflated, so [`Container::entry`] offsets can be served
+/// as byte ranges straight off the blob.
+pub async fn open_stream_container(app: &App, tn_id: TnId, file_id: &str) -> ClResult<Container> {
+	let cacheable = !file_id.starts_with('@');
+	let key = format!("{file_id}#{STREAM_VARIANT}");
+	let cache = app.ext::<Arc<ContainerCache>>()?;
+	if cacheable && let Some(index) = cache.get(&key) {
+		return Ok(Container { tn_id, index });
+	}
+
+	let gate = if cacheable { Some(cache.loading_gate(&key)) } else { None };
+	let _hold = match gate.as_ref() {
+		Some(gate) => Some(gate.lock().await),
+		None => None,
+	};
+	if cacheable && let Some(index) = cache.get(&key) {
+		return Ok(Container { tn_id, index });
+	}
+
+	let variants = app
+		.meta_adapter
+		.list_file_variants(tn_id, cloudillo_types::meta_adapter::FileId::FileId(file_id))
+		.await?;
+	let stream = variants
+		.iter()
+		.find(|v| v.variant.as_ref() == STREAM_VARIANT && v.available)
+		.ok_or(Error::NotFound)?;
+	let variant_id: &str = stream.variant_id.as_ref();
+	let size = stream.size;
+
+	let read = |offset: u64, len: u64| read_blob_range(app, tn_id, variant_id, offset, len);
+	let tail_offset = size.saturating_sub(container::EOCD_SEARCH_BYTES);
+	let tail = read(tail_offset, size - tail_offset).await?;
+	let (cd_offset, cd_size) = match container::locate_central_directory(&tail, tail_offset)? {
+		container::TailLocation::CentralDirectory(offset, size) => (offset, size),
+		container::TailLocation::Zip64Record(at) => {
+			container::parse_zip64_record(&read(at, 56).await?)?
+		}
+	};
+	if cd_size > container::MAX_CENTRAL_DIRECTORY_BYTES {
+		return Err(Error::ValidationError("container directory is too large".into()));
+	}
+	let central = container::parse_central_directory(&read(cd_offset, cd_size).await?)?;
+
+	let mut entries = std::collections::HashMap::with_capacity(central.len());
+	for entry in central {
+		let header = read(entry.local_header_offset, container::LOCAL_HEADER_BYTES).await?;
+		let data_offset = container::local_data_offset(&header, entry.local_header_offset)?;
+		let (path, info) = entry.into_info(data_offset);
+		entries.insert(path, info);
+	}
+	let index = Arc::new(container::ZipIndex { entries, variant_id: variant_id.into() });
+
+	if cacheable {
+		cache.put(&key, Arc::clone(&index));
+	}
+	Ok(Container { tn_id, index })
+}
+
+/// `len` bytes of a blob from `offset`, collected.
+async fn read_blob_range(
+	app: &App,
+	tn_id: TnId,
+	variant_id: &str,
+	offset: u64,
+	len: u64,
+) -> ClResult<Vec<u8>> {
+	let chunks: Vec<axum::body::Bytes> = app
+		.blob_adapter
+		.read_blob_range_stream(tn_id, variant_id, offset, len)
+		.await?
+		.try_collect()
+		.await
+		.map_err(|e| Error::Internal(format!("range read failed: {e}")))?;
+	Ok(chunks.concat())
+}
+
 impl Container {
-	/// The container's `orig` variant id — the blob its entries live in.
+	/// The variant id of the blob the container's entries live in — `orig`, or
+	/// `vid.stream` for a stream container.
 	pub fn variant_id(&self) -> &str {
 		&self.index.variant_id
 	}
@@ -362,6 +465,7 @@ pub fn init(app: &App) -> ClResult<()> {
 	app.scheduler.register::<image::ImageResizerTask>()?;
 	app.scheduler.register::<descriptor::FileIdGeneratorTask>()?;
 	app.scheduler.register::<video::VideoTranscoderTask>()?;
+	app.scheduler.register::<video::VideoStreamTask>()?;
 	app.scheduler.register::<audio::AudioExtractorTask>()?;
 	app.scheduler.register::<pdf::PdfProcessorTask>()?;
 	app.scheduler.register::<gc::GcTask>()?;
@@ -0,0 +1,294 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Link previews: the card a pasted URL unfurls into.
+//!
+//! The author's node fetches the page once, reads its OpenGraph / Twitter-card /
+//! oEmbed metadata, and stores the preview image as a managed file whose `x.link`
+//! carries the card's text. The client attaches that file to the POST or MSG like
+//! any image, and attachment sync hands recipients the image and — through the
+//! synced `x` — the card with it. No recipient node ever fetches the URL: the site
+//! sees one request, from the author, rather than one per follower, and a reader's
+//! node does not announce its reader by fetching.
+//!
+//! A card without an image has no file to travel in; the client gets its text and
+//! decides what to do with it. An unattached preview file is an ordinary managed
+//! file, reaped by the GC once its safety window has passed.
+
+use axum::{Json, extract::State, http::StatusCode};
+use lru::LruCache;
+use serde::Deserialize;
+use serde_json::json;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::prelude::*;
+use crate::{exif, image, preset::presets, quota};
+use cloudillo_core::extract::{Auth, OptionalRequestId};
+use cloudillo_core::file_access;
+use cloudillo_core::storage_quota;
+use cloudillo_extract::{LinkMetadata, link};
+use cloudillo_types::meta_adapter::{self, MANAGED_PARENT_ID};
+use cloudillo_types::types::ApiResponse;
+
+/// Longest URL accepted for a preview.
+const MAX_URL_LEN: usize = 2_048;
+
+/// Largest preview image fetched. A card is shown at thumbnail size; an image
+/// past this is not one a site meant as a preview.
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// How long a card is served from the cache. Kept well under the file GC's
+/// safety window, so a cached card never names a preview file already reaped.
+const CACHE_TTL: Duration = Duration::from_mins(10);
+
+const CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(512).unwrap();
+
+const HTML_ACCEPT: &str = "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1";
+const IMAGE_ACCEPT: &str = "image/avif,image/webp,image/png,image/jpeg;q=0.9";
+
+/// Cards by tenant, URL and image visibility, each with the time it was built.
+type CardEntries = LruCache<(TnId, Box<str>, Option<char>), (Instant, serde_json::Value)>;
+
+/// Recently built cards, per tenant, URL and image visibility.
+///
+/// The same link is pasted into a draft, edited, pasted again; without this each
+/// paste would be another fetch of the page and another stored image. Keyed by
+/// tenant because the card names a file in that tenant's store, and by visibility
+/// because that file was stored with the visibility it was asked for: a Direct
+/// post must not be handed the image of a Public one, nor the other way round.
+#[derive(Debug)]
+pub struct LinkPreviewCache {
+	entries: parking_lot::Mutex<CardEntries>,
+}
+
+impl Default for LinkPreviewCache {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LinkPreviewCache {
+	pub fn new() -> Self {
+		Self { entries: parking_lot::Mutex::new(LruCache::new(CACHE_CAPACITY)) }
+	}
+
+	fn get(&self, tn_id: TnId, url: &str, visibility: Option<char>) -> Option<serde_json::Value> {
+		let mut entries = self.entries.lock();
+		let key = (tn_id, Box::from(url), visibility);
+		match entries.get(&key) {
+			Some((at, card)) if at.elapsed() < CACHE_TTL => Some(card.clone()),
+			Some(_) => {
+				entries.pop(&key);
+				None
+			}
+			None => None,
+		}
+	}
+
+	fn insert(&self, tn_id: TnId, url: &str, visibility: Option<char>, card: serde_json::Value) {
+		self.entries
+			.lock()
+			.put((tn_id, Box::from(url), visibility), (Instant::now(), card));
+	}
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkPreviewReq {
+	pub url: String,
+	/// Visibility of the stored preview image; the post's own, so the image is
+	/// no more visible than the post it is attached to.
+	pub visibility: Option<char>,
+}
+
+/// POST /api/files/link-preview - Build the preview card of a URL
+///
+/// Responds `{ url, title, description, siteName, type, fileId }`. `fileId` names
+/// the stored preview image — `@<f_id>` while its content id is still being
+/// generated — or is `null` when the page has no usable image.
+pub async fn post_link_preview(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	OptionalRequestId(req_id): OptionalRequestId,
+	Json(req): Json<LinkPreviewReq>,
+) -> ClResult<(StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+	if !app.settings.get_bool(tn_id, "file.link_preview").await.unwrap_or(true) {
+		return Err(Error::PermissionDenied);
+	}
+	// A file drop uploads into its folder and nothing else; the link's holder is a
+	// stranger, who has no business driving this server's outbound fetches.
+	if file_access::drop_scope(auth.scope.as_deref()).is_some() {
+		return Err(Error::PermissionDenied);
+	}
+	let url = req.url.trim();
+	if url.is_empty() || url.len() > MAX_URL_LEN {
+		return Err(Error::ValidationError("invalid URL".into()));
+	}
+
+	let cache = app.ext::<Arc<LinkPreviewCache>>()?;
+	if let Some(mut card) = cache.get(tn_id, url, req.visibility) {
+		resolve_pending_file_id(&app, tn_id, &mut card).await;
+		let response = ApiResponse::new(card).with_req_id(req_id.unwrap_or_default());
+		return Ok((StatusCode::OK, Json(response)));
+	}
+
+	let page = app.request.get_external(url, HTML_ACCEPT, link::MAX_INPUT_BYTES).await?;
+	let is_html = page.content_type.as_deref().is_some_and(|ct| {
+		let mime = ct.split(';').next().unwrap_or("").trim();
+		mime.eq_ignore_ascii_case("text/html") || mime.eq_ignore_ascii_case("application/xhtml+xml")
+	});
+	let mut meta = if is_html {
+		let body = page.body.clone();
+		app.worker.run_immed(move || link::parse_html(&body)).await??
+	} else {
+		LinkMetadata::default()
+	};
+
+	// oEmbed only fills gaps, so it is not worth a second request when the page
+	// already said everything a card shows.
+	if (meta.title.is_none() || meta.image.is_none())
+		&& let Some(oembed_url) = meta.oembed_url.as_deref().and_then(|u| page.url.join(u).ok())
+	{
+		match app
+			.request
+			.get_external(oembed_url.as_str(), "application/json", link::MAX_OEMBED_BYTES)
+			.await
+		{
+			Ok(res) if !res.truncated => {
+				if let Err(e) = meta.merge_oembed(&res.body) {
+					debug!("link preview: oEmbed of {} unusable: {}", url, e);
+				}
+			}
+			Ok(_) => debug!("link preview: oEmbed of {} exceeds the limit", url),
+			Err(e) => debug!("link preview: oEmbed of {} failed: {}", url, e),
+		}
+	}
+
+	let mut card = json!({
+		"url": url,
+		"title": meta.title,
+		"description": meta.description,
+		"siteName": meta.site_name,
+		"type": meta.kind,
+	});
+
+	// The card is still worth having without its image, so a failed image fetch
+	// costs only the image.
+	let image_url = meta.image.as_deref().and_then(|u| page.url.join(u).ok());
+	let file_id = match image_url {
+		Some(image_url) => store_preview_image(
+			&app,
+			tn_id,
+			&auth.id_tag,
+			image_url.as_str(),
+			&card,
+			req.visibility,
+		)
+		.await
+		.inspect_err(|e| debug!("link preview: image of {} not stored: {}", url, e))
+		.ok(),
+		None => None,
+	};
+	card["fileId"] = json!(file_id);
+
+	info!("User {} built link preview for {}", auth.id_tag, url);
+	cache.insert(tn_id, url, req.visibility, card.clone());
+	let response = ApiResponse::new(card).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// Fetch the preview image and store it as a managed file carrying the card.
+///
+/// Returns the pending `@<f_id>` — the content id is generated by a task — or the
+/// existing id if the meta adapter deduplicated the upload into a file of the same
+/// visibility.
+async fn store_preview_image(
+	app: &App,
+	tn_id: TnId,
+	id_tag: &str,
+	image_url: &str,
+	card: &serde_json::Value,
+	visibility: Option<char>,
+) -> ClResult<String> {
+	let res = app.request.get_external(image_url, IMAGE_ACCEPT, MAX_IMAGE_BYTES).await?;
+	if res.truncated {
+		return Err(Error::ValidationError("preview image exceeds the size limit".into()));
+	}
+	let content_type = image::detect_image_type(&res.body)
+		.ok_or_else(|| Error::ValidationError("unsupported preview image format".into()))?;
+	storage_quota::check(app, tn_id, res.body.len() as u64).await?;
+
+	let preset = presets::link_preview();
+	let (bytes, _) = exif::prepare_upload(app, res.body, preset.strip_metadata).await?;
+	let dim = image::get_image_dimensions(&bytes).await?;
+
+	let f_id = app
+		.meta_adapter
+		.create_file(
+			tn_id,
+			meta_adapter::CreateFile {
+				preset: Some(preset.name.clone().into()),
+				parent_id: Some(MANAGED_PARENT_ID.into()),
+				creator_tag: Some(id_tag.into()),
+				content_type: content_type.into(),
+				file_name: "link-preview".into(),
+				file_tp: Some("BLOB".into()),
+				x: Some(json!({ "dim": dim, "link": card })),
+				visibility,
+				..Default::default()
+			},
+		)
+		.await?;
+	let f_id = match f_id {
+		meta_adapter::FileId::FId(f_id) => f_id,
+		// The same image stored for another post is only reused at the visibility
+		// asked for; otherwise the card goes without it.
+		meta_adapter::FileId::FileId(file_id) => {
+			let existing = app.meta_adapter.read_file(tn_id, &file_id).await?;
+			if existing.is_some_and(|f| f.visibility == visibility) {
+				return Ok(file_id.into());
+			}
+			return Err(Error::Conflict(
+				"preview image already stored with another visibility".into(),
+			));
+		}
+	};
+
+	image::generate_image_variants(app, tn_id, f_id, &bytes, &preset).await?;
+	quota::note_usage(app, tn_id).await;
+	Ok(format!("@{}", f_id))
+}
+
+/// A cached card may name its file by the pending `@<f_id>` it was built with;
+/// once the content id exists, answer with that instead.
+async fn resolve_pending_file_id(app: &App, tn_id: TnId, card: &mut serde_json::Value) {
+	let Some(f_id) = card["fileId"]
+		.as_str()
+		.and_then(|id| id.strip_prefix('@'))
+		.and_then(|id| id.parse::<u64>().ok())
+	else {
+		return;
+	};
+	if let Ok(file_id) = app.meta_adapter.get_file_id(tn_id, f_id).await {
+		card["fileId"] = json!(file_id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cached_cards_are_kept_apart_by_visibility() {
+		let cache = LinkPreviewCache::new();
+		let url = "https://example.com/article";
+		cache.insert(TnId(1), url, Some('P'), json!({ "fileId": "f1~public" }));
+		assert_eq!(cache.get(TnId(1), url, Some('P')), Some(json!({ "fileId": "f1~public" })));
+		assert_eq!(cache.get(TnId(1), url, None), None);
+		assert_eq!(cache.get(TnId(2), url, Some('P')), None);
+	}
+}
+
+// vim: ts=4
@@ -14,11 +14,12 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::prelude::*;
+use crate::version;
 use cloudillo_core::abac::VisibilityLevel;
 use cloudillo_core::dir_cache::DirCache;
 use cloudillo_core::extract::{Auth, IdTag, OptionalRequestId};
 use cloudillo_core::file_access;
-use cloudillo_types::meta_adapter::{self, UpdateFileOptions};
+use cloudillo_types::meta_adapter::{self, UpdateFileOptions, VERSIONS_PARENT_ID};
 use cloudillo_types::types::ApiResponse;
 use cloudillo_types::utils;
 
@@ -49,6 +50,15 @@ pub async fn patch_file(
 	Path(file_id): Path<String>,
 	Json(opts): Json<UpdateFileOptions>,
 ) -> ClResult<Json<PatchFileResponse>> {
+	// The versions folder is kept by promotion alone: a retained revision is restored,
+	// not moved, and nothing is moved in beside them.
+	if matches!(&opts.parent_id, Patch::Value(p) if p == VERSIONS_PARENT_ID) {
+		return Err(Error::ValidationError(
+			"files cannot be moved into the versions folder".into(),
+		));
+	}
+	version::reject_retained(&app, auth.tn_id, &file_id).await?;
+
 	app.meta_adapter.update_file_data(auth.tn_id, &file_id, &opts).await?;
 	invalidate_dir_cache(&app, auth.tn_id, &file_id);
 	if opts.affects_search_index() {
@@ -88,6 +98,11 @@ pub async fn delete_file(
 		warn!("delete_file: File {} not found", file_id);
 		Error::NotFound
 	})?;
+	if file.parent_id.as_deref() == Some(VERSIONS_PARENT_ID) {
+		return Err(Error::ValidationError(
+			"a retained revision is pruned through the versions endpoint".into(),
+		));
+	}
 
 	if query.permanent {
 		// Permanent delete - only allowed if file is in trash
@@ -117,6 +132,8 @@ pub async fn delete_file(
 			purged.share_entries_removed
 		);
 
+		crate::quota::note_usage(&app, auth.tn_id).await;
+
 		Ok(Json(DeleteFileResponse { file_id, permanent: true }))
 	} else {
 		// Soft delete - move to trash folder
@@ -258,6 +275,8 @@ pub async fn empty_trash(
 		auth.id_tag, deleted_count, files_deleted, refs_removed, share_entries_removed
 	);
 
+	crate::quota::note_usage(&app, auth.tn_id).await;
+
 	Ok(Json(EmptyTrashResponse { deleted_count }))
 }
 
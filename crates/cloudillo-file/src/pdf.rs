@@ -86,6 +86,7 @@ pub fn generate_pdf_thumbnail(input: &Path, output: &Path, dpi: u32) -> ClResult
 pub struct PdfThumbnailResult {
 	pub page_count: u32,
 	pub variant_id: String,
+	pub placeholder: Option<String>,
 }
 
 /// Generate PDF thumbnail synchronously during upload.
@@ -168,7 +169,13 @@ pub async fn generate_pdf_thumbnail_variant(
 		)
 		.await?;
 
-	Ok(PdfThumbnailResult { page_count: pdf_info.page_count, variant_id: variant_id.to_string() })
+	let placeholder = crate::placeholder::store(app, tn_id, f_id, &resize_result.bytes).await;
+
+	Ok(PdfThumbnailResult {
+		page_count: pdf_info.page_count,
+		variant_id: variant_id.to_string(),
+		placeholder,
+	})
 }
 
 /// PDF processor task - generates thumbnail and extracts metadata
@@ -16,6 +16,7 @@ use cloudillo_core::extract::{IdTag, OptionalAuth};
 use cloudillo_core::file_access;
 use cloudillo_core::middleware::PermissionCheckOutput;
 use cloudillo_types::auth_adapter::AuthCtx;
+use cloudillo_types::meta_adapter::VERSIONS_PARENT_ID;
 use cloudillo_types::types::FileAttrs;
 
 /// The one path parameter this guard authorizes on.
@@ -158,6 +159,18 @@ async fn load_file_attrs(
 
 	let file_view = file_view.ok_or(Error::NotFound)?;
 
+	// A retained revision is authorized as its chain's current one: the shares and
+	// visibility moved there with the promotion that retained it.
+	let (file_id, file_view) = match (file_view.parent_id.as_deref(), &file_view.version_of) {
+		(Some(VERSIONS_PARENT_ID), Some(version_of)) => {
+			match crate::version::read_current(app, tn_id, version_of).await? {
+				Some(current) => (Cow::Owned(current.file_id.to_string()), current),
+				None => (file_id, file_view),
+			}
+		}
+		_ => (file_id, file_view),
+	};
+
 	// Extract owner from nested ProfileInfo
 	// If no owner or owner has empty id_tag, file is owned by the tenant itself
 	let owner_id_tag = file_view
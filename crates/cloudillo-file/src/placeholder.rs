@@ -0,0 +1,267 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Low-quality image placeholders (ThumbHash).
+//!
+//! A ThumbHash is ~25 bytes describing a blurred version of an image, its aspect
+//! ratio and alpha included, so a client can paint something before `vis.tn`
+//! arrives. It is computed from the thumbnail every upload path already renders,
+//! kept in the file's `x.placeholder`, and carried in the descriptor as `P=` — which
+//! is how federated peers receive it with an action's attachments.
+//!
+//! Encoded as URL-safe base64 without padding, the alphabet content ids use; a
+//! client maps `-`/`_` back to `+`/`/` before handing it to a ThumbHash decoder.
+
+use base64::Engine;
+use std::f64::consts::PI;
+
+use crate::prelude::*;
+use cloudillo_types::meta_adapter;
+
+/// `x` key the placeholder is stored under.
+pub const X_KEY: &str = "placeholder";
+
+/// ThumbHash input is capped at 100x100.
+const MAX_INPUT_DIM: u32 = 100;
+
+/// The placeholder of an already encoded image, small enough to decode cheaply —
+/// in practice a thumbnail variant.
+pub fn from_encoded(bytes: &[u8]) -> Option<String> {
+	let img = image::load_from_memory(bytes).ok()?;
+	let img = img.thumbnail(MAX_INPUT_DIM, MAX_INPUT_DIM).to_rgba8();
+	let hash = thumbhash(img.width() as usize, img.height() as usize, img.as_raw())?;
+	Some(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(hash))
+}
+
+/// Compute the placeholder from a pending file's thumbnail and store it in the
+/// file's `x`, which the descriptor reads it from. Must run before the file's
+/// `FileIdGeneratorTask`; afterwards the descriptor would no longer hash to the
+/// file id.
+///
+/// Non-fatal: a file without a placeholder is still a file.
+pub async fn store(app: &App, tn_id: TnId, f_id: u64, thumbnail: &[u8]) -> Option<String> {
+	let bytes = thumbnail.to_vec();
+	let placeholder = match app.worker.run_immed(move || from_encoded(&bytes)).await {
+		Ok(Some(placeholder)) => placeholder,
+		Ok(None) => return None,
+		Err(e) => {
+			warn!(f_id, error = ?e, "placeholder computation failed");
+			return None;
+		}
+	};
+	if let Err(e) = write_x(app, tn_id, f_id, &placeholder).await {
+		warn!(f_id, error = ?e, "placeholder not stored");
+		return None;
+	}
+	Some(placeholder)
+}
+
+async fn write_x(app: &App, tn_id: TnId, f_id: u64, placeholder: &str) -> ClResult<()> {
+	let file_id = format!("@{f_id}");
+	let file = app.meta_adapter.read_file(tn_id, &file_id).await?.ok_or(Error::NotFound)?;
+	let mut x = match file.x {
+		Some(serde_json::Value::Object(map)) => map,
+		_ => serde_json::Map::new(),
+	};
+	x.insert(X_KEY.into(), placeholder.into());
+	let opts = meta_adapter::UpdateFileOptions {
+		x: Patch::Value(serde_json::Value::Object(x)),
+		..Default::default()
+	};
+	app.meta_adapter.update_file_data(tn_id, &file_id, &opts).await
+}
+
+/// The placeholder stored in a file's `x`, if any.
+pub fn of_x(x: Option<&serde_json::Value>) -> Option<&str> {
+	x?.get(X_KEY)?.as_str().filter(|s| is_valid(s))
+}
+
+/// Whether `s` can be carried in a descriptor: URL-safe base64, and short.
+pub fn is_valid(s: &str) -> bool {
+	!s.is_empty()
+		&& s.len() <= 64
+		&& s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// ThumbHash of a `w`x`h` RGBA image, per the reference encoder. `None` for an
+/// empty or oversized image.
+#[allow(
+	clippy::cast_possible_truncation,
+	clippy::cast_sign_loss,
+	clippy::cast_precision_loss,
+	clippy::many_single_char_names
+)]
+fn thumbhash(w: usize, h: usize, rgba: &[u8]) -> Option<Vec<u8>> {
+	if w == 0 || h == 0 || w > MAX_INPUT_DIM as usize || h > MAX_INPUT_DIM as usize {
+		return None;
+	}
+	if rgba.len() != w * h * 4 {
+		return None;
+	}
+	let pixels = w * h;
+
+	// Average colour, weighted by alpha
+	let (mut avg_r, mut avg_g, mut avg_b, mut avg_a) = (0.0, 0.0, 0.0, 0.0);
+	for px in rgba.chunks_exact(4) {
+		let alpha = f64::from(px[3]) / 255.0;
+		avg_r += alpha / 255.0 * f64::from(px[0]);
+		avg_g += alpha / 255.0 * f64::from(px[1]);
+		avg_b += alpha / 255.0 * f64::from(px[2]);
+		avg_a += alpha;
+	}
+	if avg_a > 0.0 {
+		avg_r /= avg_a;
+		avg_g /= avg_a;
+		avg_b /= avg_a;
+	}
+
+	let has_alpha = avg_a < pixels as f64;
+	let l_limit = if has_alpha { 5.0 } else { 7.0 };
+	let max_wh = w.max(h) as f64;
+	let lx = ((l_limit * w as f64 / max_wh).round() as usize).max(1);
+	let ly = ((l_limit * h as f64 / max_wh).round() as usize).max(1);
+
+	// RGBA → LPQA, composited atop the average colour
+	let mut l = Vec::with_capacity(pixels);
+	let mut p = Vec::with_capacity(pixels);
+	let mut q = Vec::with_capacity(pixels);
+	let mut a = Vec::with_capacity(pixels);
+	for px in rgba.chunks_exact(4) {
+		let alpha = f64::from(px[3]) / 255.0;
+		let r = avg_r * (1.0 - alpha) + alpha / 255.0 * f64::from(px[0]);
+		let g = avg_g * (1.0 - alpha) + alpha / 255.0 * f64::from(px[1]);
+		let b = avg_b * (1.0 - alpha) + alpha / 255.0 * f64::from(px[2]);
+		l.push((r + g + b) / 3.0);
+		p.push(f64::midpoint(r, g) - b);
+		q.push(r - g);
+		a.push(alpha);
+	}
+
+	// DCT into a DC term and AC terms normalized to 0..1
+	let encode_channel = |channel: &[f64], nx: usize, ny: usize| {
+		let (mut dc, mut ac, mut scale) = (0.0, Vec::new(), 0.0_f64);
+		let mut fx = vec![0.0; w];
+		for cy in 0..ny {
+			let mut cx = 0;
+			while cx * ny < nx * (ny - cy) {
+				for (x, f) in fx.iter_mut().enumerate() {
+					*f = (PI / w as f64 * cx as f64 * (x as f64 + 0.5)).cos();
+				}
+				let mut f = 0.0;
+				for y in 0..h {
+					let fy = (PI / h as f64 * cy as f64 * (y as f64 + 0.5)).cos();
+					for x in 0..w {
+						f += channel[x + y * w] * fx[x] * fy;
+					}
+				}
+				f /= pixels as f64;
+				if cx > 0 || cy > 0 {
+					ac.push(f);
+					scale = scale.max(f.abs());
+				} else {
+					dc = f;
+				}
+				cx += 1;
+			}
+		}
+		if scale > 0.0 {
+			for v in &mut ac {
+				*v = 0.5 + 0.5 / scale * *v;
+			}
+		}
+		(dc, ac, scale)
+	};
+	let (l_dc, l_ac, l_scale) = encode_channel(&l, lx.max(3), ly.max(3));
+	let (p_dc, p_ac, p_scale) = encode_channel(&p, 3, 3);
+	let (q_dc, q_ac, q_scale) = encode_channel(&q, 3, 3);
+	let alpha_channel = has_alpha.then(|| encode_channel(&a, 5, 5));
+
+	// Header
+	let is_landscape = w > h;
+	let header24 = (63.0 * l_dc).round() as u32
+		| (((31.5 + 31.5 * p_dc).round() as u32) << 6)
+		| (((31.5 + 31.5 * q_dc).round() as u32) << 12)
+		| (((31.0 * l_scale).round() as u32) << 18)
+		| (u32::from(has_alpha) << 23);
+	let header16 = (if is_landscape { ly } else { lx }) as u32
+		| (((63.0 * p_scale).round() as u32) << 3)
+		| (((63.0 * q_scale).round() as u32) << 9)
+		| (u32::from(is_landscape) << 15);
+	let mut hash = vec![
+		(header24 & 255) as u8,
+		((header24 >> 8) & 255) as u8,
+		(header24 >> 16) as u8,
+		(header16 & 255) as u8,
+		(header16 >> 8) as u8,
+	];
+	if let Some((a_dc, _, a_scale)) = &alpha_channel {
+		hash.push(((15.0 * a_dc).round() as u8) | (((15.0 * a_scale).round() as u8) << 4));
+	}
+
+	// AC terms, two per byte
+	let ac_start = hash.len();
+	let mut acs = vec![&l_ac, &p_ac, &q_ac];
+	if let Some((_, a_ac, _)) = &alpha_channel {
+		acs.push(a_ac);
+	}
+	for (i, f) in acs.into_iter().flatten().enumerate() {
+		let at = ac_start + (i >> 1);
+		if hash.len() <= at {
+			hash.push(0);
+		}
+		hash[at] |= ((15.0 * f).round() as u8) << ((i & 1) << 2);
+	}
+	Some(hash)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn solid(w: u32, h: u32, rgba: [u8; 4]) -> Vec<u8> {
+		let img = image::RgbaImage::from_pixel(w, h, image::Rgba(rgba));
+		let mut out = std::io::Cursor::new(Vec::new());
+		img.write_to(&mut out, image::ImageFormat::Png).unwrap();
+		out.into_inner()
+	}
+
+	#[test]
+	fn test_opaque_hash_shape() {
+		let hash = thumbhash(4, 2, &[200u8, 10, 10, 255].repeat(8)).unwrap();
+		// 5 header bytes, then 18 luminance + 2x5 chroma AC terms, a nibble each
+		assert_eq!(hash.len(), 5 + 14);
+		assert_eq!(hash[2] >> 7, 0, "no alpha flag");
+		assert_eq!(hash[4] >> 7, 1, "landscape flag");
+	}
+
+	#[test]
+	fn test_alpha_sets_the_flag() {
+		let hash = thumbhash(2, 2, &[0u8, 0, 0, 0].repeat(4)).unwrap();
+		assert_eq!(hash[2] >> 7, 1);
+	}
+
+	#[test]
+	fn test_oversized_or_empty_input_is_refused() {
+		assert!(thumbhash(0, 1, &[]).is_none());
+		assert!(thumbhash(101, 1, &[0; 404]).is_none());
+		assert!(thumbhash(2, 2, &[0; 3]).is_none());
+	}
+
+	#[test]
+	fn test_from_encoded_is_descriptor_safe() {
+		let placeholder = from_encoded(&solid(300, 200, [30, 120, 200, 255])).unwrap();
+		assert!(is_valid(&placeholder));
+		assert!(from_encoded(b"not an image").is_none());
+	}
+
+	#[test]
+	fn test_of_x() {
+		let x = serde_json::json!({ "dim": [1, 2], "placeholder": "abc_-9" });
+		assert_eq!(of_x(Some(&x)), Some("abc_-9"));
+		let bad = serde_json::json!({ "placeholder": "a;b" });
+		assert_eq!(of_x(Some(&bad)), None);
+		assert_eq!(of_x(None), None);
+	}
+}
+
+// vim: ts=4
@@ -13,6 +13,7 @@ use crate::variant::VariantClass;
 
 /// File processing preset configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct FilePreset {
 	/// Preset name (e.g., "default", "podcast", "archive")
 	pub name: String,
@@ -34,6 +35,9 @@ pub struct FilePreset {
 	pub thumbnail_variant: Option<String>,
 	/// Store original blob (file_variant record is always created, but blob storage is optional)
 	pub store_original: bool,
+	/// Strip location and other personal metadata (EXIF/XMP/IPTC) from uploaded images
+	/// before storing them. `file.strip_metadata` turns it on for every preset of a tenant.
+	pub strip_metadata: bool,
 }
 
 impl Default for FilePreset {
@@ -59,6 +63,7 @@ impl Default for FilePreset {
 			max_variant: Some("vid.hd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 }
@@ -98,6 +103,7 @@ pub mod presets {
 			max_variant: Some("vid.hd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -114,6 +120,7 @@ pub mod presets {
 			max_variant: Some("vid.sd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -136,6 +143,7 @@ pub mod presets {
 			max_variant: None, // Keep original only
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true, // Archive always stores original
+			strip_metadata: false,
 		}
 	}
 
@@ -167,6 +175,7 @@ pub mod presets {
 			max_variant: Some("vid.xd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -187,6 +196,7 @@ pub mod presets {
 			max_variant: Some("vid.md".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: false, // Mobile optimization - minimize storage
+			strip_metadata: false,
 		}
 	}
 
@@ -208,6 +218,7 @@ pub mod presets {
 			max_variant: Some("vid.hd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -230,6 +241,7 @@ pub mod presets {
 			max_variant: Some("vis.hd".into()),
 			thumbnail_variant: Some("vis.pf".into()),
 			store_original: false, // Generated variants are sufficient
+			strip_metadata: true,
 		}
 	}
 
@@ -251,6 +263,27 @@ pub mod presets {
 			max_variant: Some("vis.hd".into()),
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: false, // Generated variants are sufficient
+			strip_metadata: true,
+		}
+	}
+
+	/// Link preview preset - the card image of an unfurled URL
+	///
+	/// Shown at card size, so nothing past `vis.md`; the original is a third
+	/// party's image and is not kept.
+	pub fn link_preview() -> FilePreset {
+		FilePreset {
+			name: "link-preview".to_string(),
+			allowed_media_classes: vec![VariantClass::Visual],
+			image_variants: vec!["vis.tn".into(), "vis.sd".into(), "vis.md".into()],
+			video_variants: vec![],
+			audio_variants: vec![],
+			extract_audio: false,
+			generate_thumbnail: false, // vis.tn serves as thumbnail
+			max_variant: Some("vis.md".into()),
+			thumbnail_variant: Some("vis.tn".into()),
+			store_original: false,
+			strip_metadata: true,
 		}
 	}
 
@@ -273,6 +306,7 @@ pub mod presets {
 			max_variant: None,
 			thumbnail_variant: None,
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -289,6 +323,7 @@ pub mod presets {
 			max_variant: None,
 			thumbnail_variant: Some("vis.tn".into()),
 			store_original: false,
+			strip_metadata: false,
 		}
 	}
 
@@ -305,6 +340,7 @@ pub mod presets {
 			max_variant: None,
 			thumbnail_variant: Some("vis.pf".into()),
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -330,6 +366,7 @@ pub mod presets {
 			max_variant: None,
 			thumbnail_variant: None,
 			store_original: true,
+			strip_metadata: false,
 		}
 	}
 
@@ -345,6 +382,7 @@ pub mod presets {
 			"video" => Some(video()),
 			"profile-picture" => Some(profile_picture()),
 			"cover" => Some(cover()),
+			"link-preview" => Some(link_preview()),
 			"orig-only" => Some(orig_only()),
 			"thumbnail-only" => Some(thumbnail_only()),
 			"apkg" => Some(apkg()),
@@ -365,6 +403,7 @@ pub mod presets {
 			"video",
 			"profile-picture",
 			"cover",
+			"link-preview",
 			"orig-only",
 			"thumbnail-only",
 			"apkg",
@@ -0,0 +1,157 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Storage quota: the usage endpoint and the threshold warning emails.
+//!
+//! Enforcement is `cloudillo_core::storage_quota::check`, called by the upload
+//! paths. This module tells the tenant before that starts refusing: once usage
+//! crosses each of [`WARN_LEVELS`], one email. The highest level warned about is
+//! kept in `tenant_data`, and lowered again when usage drops, so freeing space and
+//! filling it up once more warns again.
+
+use axum::{Json, extract::State};
+use serde::Serialize;
+
+use crate::prelude::*;
+use cloudillo_core::storage_quota::{self, StorageQuota};
+use cloudillo_core::{Auth, ScheduleEmailFn, ScheduleEmailParams};
+use cloudillo_types::meta_adapter::StorageUsage;
+use cloudillo_types::types::ApiResponse;
+
+/// Usage levels, in percent of the limit, that trigger a warning email.
+pub const WARN_LEVELS: [u64; 3] = [80, 95, 100];
+
+const WARNED_KEY: &str = "file.quota_warned";
+
+/// The highest of [`WARN_LEVELS`] that `percent` has reached, or 0.
+fn warn_level(percent: u64) -> u64 {
+	WARN_LEVELS.iter().copied().filter(|level| percent >= *level).max().unwrap_or(0)
+}
+
+/// Re-evaluate the tenant's warning level after its usage changed, emailing the
+/// tenant when it crossed a new one. Non-fatal: logs and carries on, like the
+/// search index and site cache hooks.
+pub async fn note_usage(app: &App, tn_id: TnId) {
+	if let Err(e) = update_warning(app, tn_id).await {
+		warn!(tn_id = %tn_id, error = ?e, "storage quota warning check failed");
+	}
+}
+
+async fn update_warning(app: &App, tn_id: TnId) -> ClResult<()> {
+	let quota = storage_quota::read(app, tn_id).await?;
+	let level = quota.percent().map_or(0, warn_level);
+	let warned = app
+		.meta_adapter
+		.read_tenant_data(tn_id, WARNED_KEY)
+		.await?
+		.and_then(|v| v.parse::<u64>().ok())
+		.unwrap_or(0);
+	if level == warned {
+		return Ok(());
+	}
+	if level > warned {
+		send_warning(app, tn_id, &quota, level).await?;
+	}
+	let value = level.to_string();
+	app.meta_adapter
+		.write_tenant_data(tn_id, WARNED_KEY, (level > 0).then_some(value.as_str()))
+		.await
+}
+
+async fn send_warning(app: &App, tn_id: TnId, quota: &StorageQuota, level: u64) -> ClResult<()> {
+	let Ok(schedule_email) = app.ext::<ScheduleEmailFn>() else {
+		return Ok(());
+	};
+	let id_tag = app.auth_adapter.read_id_tag(tn_id).await?;
+	let Some(email) = app.auth_adapter.read_tenant(&id_tag).await?.email else {
+		info!(tn_id = %tn_id, %id_tag, level, "storage quota warning not sent: no email on file");
+		return Ok(());
+	};
+	let lang = match app.settings.get(tn_id, "profile.lang").await {
+		Ok(Some(cloudillo_core::settings::SettingValue::String(s))) => Some(s),
+		_ => None,
+	};
+	let base_id_tag = app.opts.base_id_tag.as_ref().map_or("cloudillo", AsRef::as_ref);
+
+	let params = ScheduleEmailParams {
+		to: email.to_string(),
+		template_name: "storage_quota_warning".to_string(),
+		template_vars: serde_json::json!({
+			"idTag": id_tag.as_ref(),
+			"percent": quota.percent(),
+			"used": format_size(quota.usage.total),
+			"limit": quota.limit.map(format_size),
+			"trash": (quota.usage.trash > 0).then(|| format_size(quota.usage.trash)),
+			"full": level >= 100,
+			"base_id_tag": base_id_tag,
+			"instance_name": "Cloudillo",
+		}),
+		lang,
+		custom_key: Some(format!("storage-quota:{}:{}", tn_id.0, level)),
+		from_name_override: Some(format!("Cloudillo | {}", base_id_tag.to_uppercase())),
+	};
+	info!(tn_id = %tn_id, %id_tag, level, "storage quota warning scheduled");
+	schedule_email(app, tn_id, params).await
+}
+
+/// Bytes for people: one decimal, binary units.
+#[allow(clippy::cast_precision_loss)] // display only
+fn format_size(bytes: u64) -> String {
+	const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+	let mut value = bytes as f64;
+	let mut unit = 0;
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+		value /= 1024.0;
+		unit += 1;
+	}
+	if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsageResponse {
+	#[serde(flatten)]
+	pub usage: StorageUsage,
+	/// In bytes; absent when unlimited.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub limit: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub remaining: Option<u64>,
+}
+
+/// GET /api/storage - The tenant's storage usage against its quota
+pub async fn get_storage_usage(
+	State(app): State<App>,
+	Auth(auth): Auth,
+) -> ClResult<Json<ApiResponse<StorageUsageResponse>>> {
+	let quota = storage_quota::read(&app, auth.tn_id).await?;
+	let remaining = quota.remaining();
+	Ok(Json(ApiResponse::new(StorageUsageResponse {
+		usage: quota.usage,
+		limit: quota.limit,
+		remaining,
+	})))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_warn_level_is_the_highest_level_reached() {
+		assert_eq!(warn_level(0), 0);
+		assert_eq!(warn_level(79), 0);
+		assert_eq!(warn_level(80), 80);
+		assert_eq!(warn_level(99), 95);
+		assert_eq!(warn_level(250), 100);
+	}
+
+	#[test]
+	fn test_format_size() {
+		assert_eq!(format_size(512), "512 B");
+		assert_eq!(format_size(1536), "1.5 KiB");
+		assert_eq!(format_size(100 << 30), "100.0 GiB");
+	}
+}
+
+// vim: ts=4
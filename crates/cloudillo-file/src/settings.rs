@@ -42,6 +42,50 @@ pub fn register_settings(registry: &mut SettingsRegistry) -> ClResult<()> {
 			.build()?,
 	)?;
 
+	// Strip personal metadata from uploaded images
+	registry.register(
+		SettingDefinition::builder("file.strip_metadata")
+			.description(
+				"Remove location, camera and other personal metadata from uploaded images before storing them",
+			)
+			.default(SettingValue::Bool(false))
+			.scope(SettingScope::Tenant)
+			.permission(PermissionLevel::User)
+			.build()?,
+	)?;
+
+	// Retained revisions per versioned file
+	registry.register(
+		SettingDefinition::builder("file.version_retention")
+			.description(
+				"Earlier revisions kept per versioned file; older ones are pruned (0 = keep all)",
+			)
+			.default(SettingValue::Int(20))
+			.scope(SettingScope::Tenant)
+			.permission(PermissionLevel::User)
+			.build()?,
+	)?;
+
+	// Outbound fetches for link previews
+	registry.register(
+		SettingDefinition::builder("file.link_preview")
+			.description("Fetch pasted URLs to build link preview cards")
+			.default(SettingValue::Bool(true))
+			.scope(SettingScope::Global)
+			.permission(PermissionLevel::Admin)
+			.build()?,
+	)?;
+
+	// Adaptive streaming container for uploaded video
+	registry.register(
+		SettingDefinition::builder("file.video_stream_format")
+			.description("Adaptive streaming output for uploaded video: none, hls, or hls+dash")
+			.default(SettingValue::String("none".into()))
+			.scope(SettingScope::Global)
+			.permission(PermissionLevel::Admin)
+			.build()?,
+	)?;
+
 	// Maximum size variant to download for caching
 	registry.register(
 		SettingDefinition::builder("file.max_cache_variant")
@@ -145,7 +189,7 @@ pub fn register_settings(registry: &mut SettingsRegistry) -> ClResult<()> {
 	// Storage quota
 	registry.register(
 		SettingDefinition::builder("limits.max_storage_gb")
-			.description("Maximum storage quota in gigabytes")
+			.description("Maximum storage quota in gigabytes (0 = unlimited)")
 			.default(SettingValue::Int(100))
 			.scope(SettingScope::Tenant)
 			.permission(PermissionLevel::Admin)
@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Adaptive stream serving: playlists and segments out of a video's `vid.stream`
+//! container.
+//!
+//! `video::VideoStreamTask` writes the container; this serves it by path, like APKG
+//! content, so a player pointed at `stream/master.m3u8` (or `stream/manifest.mpd`)
+//! resolves every rendition relative to it. Renditions are single-file fMP4 addressed
+//! by byte range, so nearly every request here is a `Range` request — answered
+//! straight from the stored entry's slice of the blob, never buffered.
+
+use axum::{
+	body::Body,
+	extract::{Path, State},
+	http::{HeaderMap, StatusCode, header},
+	response::Response,
+};
+
+use crate::handler::{parse_range, range_not_satisfiable};
+use crate::prelude::*;
+use cloudillo_types::worker::Priority;
+
+/// Serve one file out of a video's stream container
+///
+/// `GET /api/files/{file_id}/stream/{*path}`
+///
+/// Read access is checked by the route's ABAC layer.
+pub async fn get_stream_content(
+	State(app): State<App>,
+	tn_id: TnId,
+	headers: HeaderMap,
+	Path((file_id, path)): Path<(String, String)>,
+) -> ClResult<Response> {
+	// Sanitize path to prevent directory traversal
+	if path.contains("..") || path.starts_with('/') {
+		return Err(Error::NotFound);
+	}
+
+	let container = crate::open_stream_container(&app, tn_id, &file_id).await?;
+	let Some(info) = container.entry(&path) else {
+		return Err(Error::NotFound);
+	};
+
+	let builder = Response::builder()
+		.header(header::CONTENT_TYPE, info.content_type)
+		.header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+		.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+
+	// The packager writes stored entries only; a deflated one came from elsewhere and
+	// is served whole, inflated, the way APKG content is.
+	if info.is_deflated {
+		let bytes = container.read_bytes(&app, info, Priority::High).await?;
+		return builder
+			.status(StatusCode::OK)
+			.body(Body::from(bytes))
+			.map_err(|e| Error::Internal(format!("Failed to build response: {e}")));
+	}
+
+	let total = info.uncompressed_size;
+	let range = headers
+		.get(header::RANGE)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| parse_range(v, total));
+	let (status, start, end) = match range {
+		Some(Err(())) => return Ok(range_not_satisfiable(total)),
+		Some(Ok((start, end))) => (StatusCode::PARTIAL_CONTENT, start, end),
+		None if total == 0 => {
+			return builder
+				.status(StatusCode::OK)
+				.header(header::ACCEPT_RANGES, "bytes")
+				.body(Body::empty())
+				.map_err(|e| Error::Internal(format!("Failed to build response: {e}")));
+		}
+		None => (StatusCode::OK, 0, total - 1),
+	};
+
+	let stream = app
+		.blob_adapter
+		.read_blob_range_stream(
+			tn_id,
+			container.variant_id(),
+			info.data_offset + start,
+			end - start + 1,
+		)
+		.await?;
+
+	let mut builder = builder
+		.status(status)
+		.header(header::ACCEPT_RANGES, "bytes")
+		.header(header::CONTENT_LENGTH, end - start + 1);
+	if status == StatusCode::PARTIAL_CONTENT {
+		builder = builder.header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{total}"));
+	}
+	builder
+		.body(Body::from_stream(stream))
+		.map_err(|e| Error::Internal(format!("Failed to build response: {e}")))
+}
+
+// vim: ts=4
@@ -13,6 +13,7 @@ use serde::Deserialize;
 
 use crate::prelude::*;
 use crate::variant::{Variant, VariantClass, VariantQuality};
+use cloudillo_core::storage_quota;
 use cloudillo_types::hasher;
 use cloudillo_types::meta_adapter::{CreateFile, FileId, FileVariant, MANAGED_PARENT_ID};
 use cloudillo_types::types::ApiResponse;
@@ -108,6 +109,31 @@ fn primary_class(variants: &[cloudillo_types::meta_adapter::FileVariant<&str>])
 	VariantClass::Raw
 }
 
+/// The remote file's `x`, carrying the descriptor's placeholder and drop link: the
+/// local descriptor is regenerated from `x`, so it must hold exactly what the hashed
+/// one did, whatever the remote metadata says.
+fn with_descriptor_entries(
+	x: Option<serde_json::Value>,
+	placeholder: Option<&str>,
+	drop_link: Option<&str>,
+) -> Option<serde_json::Value> {
+	let mut x = match x {
+		Some(serde_json::Value::Object(map)) => map,
+		Some(other) => return Some(other),
+		None => serde_json::Map::new(),
+	};
+	for (key, value) in [
+		(super::placeholder::X_KEY, placeholder),
+		(super::handler::DROP_LINK_X_KEY, drop_link),
+	] {
+		match value {
+			Some(v) => x.insert(key.into(), v.into()),
+			None => x.remove(key),
+		};
+	}
+	(!x.is_empty()).then_some(serde_json::Value::Object(x))
+}
+
 /// Verify that content hash matches expected ID
 ///
 /// IDs are formatted as `prefix~hash` (e.g., `b1~abc123`, `f1~xyz789`, `d1~...`)
@@ -196,7 +222,8 @@ pub async fn sync_file_variants(
 	debug!("  fetched descriptor: {}", descriptor);
 
 	// 3. Parse descriptor to get variant info (parse first for debugging)
-	let (root_id, mut parsed_variants) = super::descriptor::parse_file_descriptor(descriptor)?;
+	let (root_id, placeholder, drop_link, mut parsed_variants) =
+		super::descriptor::parse_file_descriptor(descriptor)?;
 
 	// 2. Verify descriptor hash matches file_id
 	if let Err(e) = verify_content_hash(descriptor.as_bytes(), file_id) {
@@ -204,7 +231,12 @@ pub async fn sync_file_variants(
 		// the source's canonicalizer disagrees with ours. If it matches, the source's variants
 		// table has drifted from what was hashed at finalize.
 		parsed_variants.sort();
-		let local_descriptor = super::descriptor::get_file_descriptor(&parsed_variants, root_id);
+		let local_descriptor = super::descriptor::get_file_descriptor(
+			&parsed_variants,
+			root_id,
+			placeholder,
+			drop_link,
+		);
 		let computed = hasher::hash("f", descriptor.as_bytes());
 		if local_descriptor == *descriptor {
 			warn!(
@@ -366,7 +398,7 @@ pub async fn sync_file_variants(
 			file_name: remote_file.file_name.into(),
 			created_at: Some(remote_file.created_at),
 			visibility: Some(visibility.unwrap_or('D')),
-			x: remote_file.x,
+			x: with_descriptor_entries(remote_file.x, placeholder, drop_link),
 			..Default::default()
 		};
 
@@ -438,6 +470,10 @@ pub async fn sync_file_variants(
 				let store_tn = if shared { SHARED_TN } else { tn_id };
 				if shared {
 					info!("  storing variant {} to shared store TnId(0)", variant_id);
+				} else {
+					// Only the tenant's own store counts against its quota. The
+					// descriptor's size is what the fetch is about to write.
+					storage_quota::check(app, tn_id, variant.size).await?;
 				}
 				match fetch_and_store_blob(
 					app,
@@ -506,6 +542,10 @@ pub async fn sync_file_variants(
 		}
 	}
 
+	if !result.synced_variants.is_empty() {
+		crate::quota::note_usage(app, tn_id).await;
+	}
+
 	info!(
 		"File sync complete for {}: {} synced, {} skipped",
 		file_id,
@@ -565,4 +605,24 @@ async fn fetch_and_store_blob(
 	Ok(blob_size)
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn descriptor_entries_override_the_remote_x() {
+		let remote = Some(json!({ "dim": [4, 2], "dropLink": "r1~other" }));
+		assert_eq!(
+			with_descriptor_entries(remote.clone(), None, Some("r1~link")),
+			Some(json!({ "dim": [4, 2], "dropLink": "r1~link" }))
+		);
+		assert_eq!(with_descriptor_entries(remote, None, None), Some(json!({ "dim": [4, 2] })));
+		assert_eq!(
+			with_descriptor_entries(None, Some("ph"), Some("r1~link")),
+			Some(json!({ "placeholder": "ph", "dropLink": "r1~link" }))
+		);
+	}
+}
+
 // vim: ts=4
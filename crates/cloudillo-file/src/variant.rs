@@ -120,6 +120,8 @@ pub enum VariantQuality {
 	High,
 	/// Extra/Extreme Definition - 3840px images, 4K video
 	Extra,
+	/// Stream - adaptive stream container holding several tiers (HLS/DASH)
+	Stream,
 	/// Original - unprocessed source file
 	Original,
 }
@@ -134,6 +136,7 @@ impl VariantQuality {
 			Self::Medium => "md",
 			Self::High => "hd",
 			Self::Extra => "xd",
+			Self::Stream => "stream",
 			Self::Original => "orig",
 		}
 	}
@@ -147,6 +150,7 @@ impl VariantQuality {
 			"md" => Some(Self::Medium),
 			"hd" => Some(Self::High),
 			"xd" => Some(Self::Extra),
+			"stream" => Some(Self::Stream),
 			"orig" => Some(Self::Original),
 			_ => None,
 		}
@@ -243,6 +247,7 @@ mod tests {
 		assert_eq!(VariantQuality::from_str_opt("md"), Some(VariantQuality::Medium));
 		assert_eq!(VariantQuality::from_str_opt("hd"), Some(VariantQuality::High));
 		assert_eq!(VariantQuality::from_str_opt("xd"), Some(VariantQuality::Extra));
+		assert_eq!(VariantQuality::from_str_opt("stream"), Some(VariantQuality::Stream));
 		assert_eq!(VariantQuality::from_str_opt("orig"), Some(VariantQuality::Original));
 		assert_eq!(VariantQuality::from_str_opt("pf"), Some(VariantQuality::Profile));
 		assert_eq!(VariantQuality::from_str_opt("invalid"), None);
@@ -0,0 +1,275 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Version chains: one logical file over many content-addressed revisions.
+//!
+//! A BLOB cannot change under its id, so a new revision is uploaded as a new file
+//! (`POST /api/files/{preset}/{file_name}?versionOf=<file_id>`) that waits in
+//! [`VERSIONS_PARENT_ID`] until its content id exists. The id generator then
+//! promotes it: the new revision takes the folder, visibility, tags and shares of
+//! the current one, which moves into the versions folder in its place. Posts that
+//! attached a revision keep that revision — an attachment names content, not a
+//! document.
+//!
+//! A retained revision is authorized as the current one (see `perm`), so whoever
+//! may read the file may read its history. The oldest revisions past the tenant's
+//! `file.version_retention` are pruned into the managed folder, where the file GC
+//! reaps them unless a post still attaches them.
+
+use axum::{
+	Json,
+	extract::{Path, State},
+	http::StatusCode,
+};
+use serde_json::json;
+
+use crate::prelude::*;
+use cloudillo_core::extract::{Auth, OptionalRequestId};
+use cloudillo_core::file_access;
+use cloudillo_types::auth_adapter::AuthCtx;
+use cloudillo_types::meta_adapter::{
+	FileVersion, FileView, MANAGED_PARENT_ID, TRASH_PARENT_ID, UpdateFileOptions,
+	VERSIONS_PARENT_ID,
+};
+use cloudillo_types::types::ApiResponse;
+
+/// Retained revisions kept when `file.version_retention` cannot be read.
+const DEFAULT_RETENTION: i64 = 20;
+
+/// The chain an upload with `versionOf` joins.
+pub(crate) struct VersionTarget {
+	/// Logical id of the chain.
+	pub version_of: Box<str>,
+	/// The revision the upload is to replace.
+	pub current: FileView,
+}
+
+/// Resolve the file named by an upload's `versionOf` to the chain it joins.
+///
+/// Adding a revision changes what every holder of the file sees, so it needs write
+/// access to the current revision — read access is enough to duplicate a file, not
+/// to replace it.
+pub(crate) async fn resolve_upload_target(
+	app: &App,
+	tn_id: TnId,
+	auth: &AuthCtx,
+	tenant_id_tag: &str,
+	file_id: &str,
+) -> ClResult<VersionTarget> {
+	let ctx = file_access::FileAccessCtx {
+		user_id_tag: &auth.id_tag,
+		tenant_id_tag,
+		user_roles: &auth.roles,
+	};
+	let access = file_access::check_file_access_with_scope(
+		app,
+		tn_id,
+		file_id,
+		&ctx,
+		auth.scope.as_deref(),
+		None,
+	)
+	.await
+	.map_err(|e| match e {
+		file_access::FileAccessError::NotFound => Error::NotFound,
+		file_access::FileAccessError::AccessDenied => Error::PermissionDenied,
+		file_access::FileAccessError::InternalError(m) => Error::Internal(m),
+	})?;
+	if !access.access_level.can_write() {
+		return Err(Error::PermissionDenied);
+	}
+	let current = access.file_view;
+
+	if current.file_tp.as_deref().unwrap_or("BLOB") != "BLOB" {
+		return Err(Error::ValidationError("only BLOB files have versions".into()));
+	}
+	if current.file_id.starts_with('@') {
+		return Err(Error::ValidationError("file is still being processed".into()));
+	}
+	match current.parent_id.as_deref() {
+		Some(VERSIONS_PARENT_ID) => {
+			return Err(Error::ValidationError(
+				"a new revision replaces the current one, not a retained revision".into(),
+			));
+		}
+		Some(TRASH_PARENT_ID | MANAGED_PARENT_ID) => {
+			return Err(Error::ValidationError("file cannot have versions".into()));
+		}
+		_ => {}
+	}
+
+	let version_of = current.version_of.clone().unwrap_or_else(|| current.file_id.clone());
+	Ok(VersionTarget { version_of, current })
+}
+
+/// A revision upload hit content the tenant already stores under `file_id`.
+///
+/// Content from the chain's own past is a restore; content already current needs
+/// nothing. Content stored as some other file cannot join the chain: the content id
+/// is unique per tenant, and that file is someone's document.
+pub(crate) async fn on_dedup(
+	app: &App,
+	tn_id: TnId,
+	target: &VersionTarget,
+	file_id: &str,
+) -> ClResult<()> {
+	if file_id == target.current.file_id.as_ref() {
+		return Ok(());
+	}
+	let existing = app.meta_adapter.read_file(tn_id, file_id).await?.ok_or(Error::NotFound)?;
+	if existing.version_of.as_deref() != Some(target.version_of.as_ref()) {
+		return Err(Error::Conflict("identical content is stored as another file".into()));
+	}
+	promote(app, tn_id, file_id).await
+}
+
+/// Make `file_id` the current revision of its chain, then prune the chain to the
+/// tenant's retention.
+pub(crate) async fn promote(app: &App, tn_id: TnId, file_id: &str) -> ClResult<()> {
+	let Some(previous) = app.meta_adapter.promote_file_version(tn_id, file_id).await? else {
+		return Ok(());
+	};
+	// The index follows the folder: the new revision becomes searchable where the
+	// old one was, and the old one drops out with its move into the versions folder.
+	cloudillo_core::search_index_file(app, tn_id, file_id);
+	cloudillo_core::search_index_file(app, tn_id, &previous);
+	info!("File {} promoted over {}", file_id, previous);
+
+	let retention = app
+		.settings
+		.get_int(tn_id, "file.version_retention")
+		.await
+		.unwrap_or(DEFAULT_RETENTION);
+	if retention > 0 {
+		let Some(file) = app.meta_adapter.read_file(tn_id, file_id).await? else {
+			return Ok(());
+		};
+		if let Some(version_of) = file.version_of.as_deref() {
+			let keep = u32::try_from(retention).unwrap_or(u32::MAX);
+			let pruned = app.meta_adapter.prune_file_versions(tn_id, version_of, keep).await?;
+			if !pruned.is_empty() {
+				debug!("Pruned {} revisions of {}", pruned.len(), version_of);
+			}
+		}
+	}
+	Ok(())
+}
+
+/// The current revision of the chain `version_of`, if it has one.
+pub(crate) async fn read_current(
+	app: &App,
+	tn_id: TnId,
+	version_of: &str,
+) -> ClResult<Option<FileView>> {
+	let versions = app.meta_adapter.list_file_versions(tn_id, version_of).await?;
+	match versions.into_iter().find(|v| v.current) {
+		Some(current) => app.meta_adapter.read_file(tn_id, &current.file_id).await,
+		None => Ok(None),
+	}
+}
+
+/// Refuse to edit a retained revision in place.
+///
+/// It is authorized as the current revision, so the file-write guard lets it
+/// through; but its folder, visibility and tags are the chain's, held in trust for
+/// a restore.
+pub(crate) async fn reject_retained(app: &App, tn_id: TnId, file_id: &str) -> ClResult<()> {
+	let file = app.meta_adapter.read_file(tn_id, file_id).await?.ok_or(Error::NotFound)?;
+	if file.parent_id.as_deref() == Some(VERSIONS_PARENT_ID) {
+		return Err(Error::ValidationError("a retained revision is restored, not edited".into()));
+	}
+	Ok(())
+}
+
+/// Logical id of the chain `file_id` belongs to, and the revision `version_id`
+/// after checking it is one of the same chain.
+async fn chain_member(
+	app: &App,
+	tn_id: TnId,
+	file_id: &str,
+	version_id: &str,
+) -> ClResult<(Box<str>, FileView)> {
+	let file = app.meta_adapter.read_file(tn_id, file_id).await?.ok_or(Error::NotFound)?;
+	let version_of = file.version_of.unwrap_or(file.file_id);
+	let version = app.meta_adapter.read_file(tn_id, version_id).await?.ok_or(Error::NotFound)?;
+	if version.version_of.as_deref() != Some(version_of.as_ref()) {
+		return Err(Error::NotFound);
+	}
+	Ok((version_of, version))
+}
+
+/// GET /api/files/{file_id}/versions - Revisions of a file, newest first
+///
+/// `file_id` may name any revision of the chain.
+pub async fn list_versions(
+	State(app): State<App>,
+	tn_id: TnId,
+	Path(file_id): Path<String>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<FileVersion>>>)> {
+	let file = app.meta_adapter.read_file(tn_id, &file_id).await?.ok_or(Error::NotFound)?;
+	let version_of = file.version_of.unwrap_or(file.file_id);
+	let versions = app.meta_adapter.list_file_versions(tn_id, &version_of).await?;
+
+	let response = ApiResponse::new(versions).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// POST /api/files/{file_id}/versions/{version_id}/restore - Make an earlier
+/// revision current again
+///
+/// The revision it replaces is retained, so a restore can itself be undone.
+pub async fn restore_version(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path((file_id, version_id)): Path<(String, String)>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<serde_json::Value>>)> {
+	let (version_of, version) = chain_member(&app, tn_id, &file_id, &version_id).await?;
+	if version.parent_id.as_deref() != Some(VERSIONS_PARENT_ID) {
+		return Err(Error::ValidationError("revision is not a retained one".into()));
+	}
+	if read_current(&app, tn_id, &version_of)
+		.await?
+		.is_some_and(|current| current.parent_id.as_deref() == Some(TRASH_PARENT_ID))
+	{
+		return Err(Error::ValidationError("restore the file from the trash first".into()));
+	}
+
+	promote(&app, tn_id, &version_id).await?;
+	info!("User {} restored revision {} of {}", auth.id_tag, version_id, version_of);
+
+	let data = json!({ "fileId": version_id, "versionOf": version_of });
+	let response = ApiResponse::new(data).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+/// DELETE /api/files/{file_id}/versions/{version_id} - Prune one retained revision
+///
+/// The revision moves into the managed folder: the file GC reaps it once no post
+/// attaches it, and until then those posts keep showing it.
+pub async fn delete_version(
+	State(app): State<App>,
+	tn_id: TnId,
+	Auth(auth): Auth,
+	Path((file_id, version_id)): Path<(String, String)>,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<()>>)> {
+	let (version_of, version) = chain_member(&app, tn_id, &file_id, &version_id).await?;
+	if version.parent_id.as_deref() != Some(VERSIONS_PARENT_ID) {
+		return Err(Error::ValidationError("the current revision cannot be pruned".into()));
+	}
+
+	let opts = UpdateFileOptions {
+		parent_id: Patch::Value(MANAGED_PARENT_ID.into()),
+		..Default::default()
+	};
+	app.meta_adapter.update_file_data(tn_id, &version_id, &opts).await?;
+	info!("User {} pruned revision {} of {}", auth.id_tag, version_id, version_of);
+
+	let response = ApiResponse::new(()).with_req_id(req_id.unwrap_or_default());
+	Ok((StatusCode::OK, Json(response)))
+}
+
+// vim: ts=4
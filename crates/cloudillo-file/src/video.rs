@@ -5,6 +5,7 @@
 //!
 //! Uses FFmpeg via shell commands for video processing:
 //! - VideoTranscoderTask: Transcode video to different quality tiers
+//! - VideoStreamTask: Package all tiers as one adaptive stream container (HLS/DASH)
 //!
 //! Note: Thumbnail extraction is now done synchronously during upload in handler.rs
 
@@ -14,7 +15,8 @@ use serde::{Deserialize, Serialize};
 use std::{path::Path, sync::Arc};
 
 use crate::prelude::*;
-use crate::{ffmpeg, store};
+use crate::preset::VideoQualityTier;
+use crate::{STREAM_VARIANT, container, ffmpeg, store};
 use cloudillo_core::scheduler::{Task, TaskId};
 use cloudillo_types::blob_adapter;
 use cloudillo_types::meta_adapter;
@@ -164,6 +166,220 @@ impl Task<App> for VideoTranscoderTask {
 	}
 }
 
+/// The tiers worth packaging for a source of `resolution`: every one up to the first
+/// that already holds the source at full size. Higher ones would only upscale.
+pub(crate) fn stream_tiers<'a>(
+	tiers: impl IntoIterator<Item = &'a VideoQualityTier>,
+	resolution: (u32, u32),
+) -> Vec<&'a VideoQualityTier> {
+	let source_dim = resolution.0.max(resolution.1);
+	let mut picked = Vec::new();
+	for tier in tiers {
+		picked.push(tier);
+		if tier.max_dim >= source_dim {
+			break;
+		}
+	}
+	picked
+}
+
+/// The size `resolution` is scaled to by the transcode filter: fit in a `max_dim` box
+/// without upscaling, then cropped to even dimensions.
+fn fit_within(resolution: (u32, u32), max_dim: u32) -> (u32, u32) {
+	let (w, h) = (u64::from(resolution.0), u64::from(resolution.1));
+	let long = w.max(h).max(1);
+	let (w, h) = if long > u64::from(max_dim) {
+		(w * u64::from(max_dim) / long, h * u64::from(max_dim) / long)
+	} else {
+		(w, h)
+	};
+	let even = |v: u64| u32::try_from(v & !1).unwrap_or(u32::MAX);
+	(even(w), even(h))
+}
+
+/// Video stream task - packages every tier of a video as one adaptive stream
+///
+/// The renditions, their playlists and — for `hls+dash` — a DASH manifest go into a
+/// stored zip, kept as the file's `vid.stream` variant with the stream format as its
+/// `format`. `stream::get_stream_content` serves it by path, so players can switch
+/// bitrate mid-stream.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VideoStreamTask {
+	tn_id: TnId,
+	f_id: u64,
+	format: Box<str>,
+	/// Tier variant names, lowest first.
+	tiers: Vec<Box<str>>,
+	input_path: Box<Path>,
+}
+
+impl VideoStreamTask {
+	pub fn new(
+		tn_id: TnId,
+		f_id: u64,
+		input_path: impl Into<Box<Path>>,
+		format: ffmpeg::StreamFormat,
+		tiers: Vec<Box<str>>,
+	) -> Arc<Self> {
+		Arc::new(Self {
+			tn_id,
+			f_id,
+			format: format.as_str().into(),
+			tiers,
+			input_path: input_path.into(),
+		})
+	}
+}
+
+#[async_trait]
+impl Task<App> for VideoStreamTask {
+	fn kind() -> &'static str {
+		"video.stream"
+	}
+	fn kind_of(&self) -> &'static str {
+		Self::kind()
+	}
+
+	fn build(_id: TaskId, ctx: &str) -> ClResult<Arc<dyn Task<App>>> {
+		let (tn_id, f_id, format, tiers, path) =
+			ctx.splitn(5, ',').collect_tuple().ok_or(Error::Parse)?;
+		let format = ffmpeg::StreamFormat::from_str_opt(format).ok_or(Error::Parse)?;
+		let tiers = tiers.split('+').map(Box::from).collect();
+		let task = VideoStreamTask::new(
+			TnId(tn_id.parse()?),
+			f_id.parse()?,
+			Box::from(Path::new(path)),
+			format,
+			tiers,
+		);
+		Ok(task)
+	}
+
+	fn serialize(&self) -> String {
+		format!(
+			"{},{},{},{},{}",
+			self.tn_id,
+			self.f_id,
+			self.format,
+			self.tiers.join("+"),
+			self.input_path.to_string_lossy()
+		)
+	}
+
+	async fn run(&self, app: &App) -> ClResult<()> {
+		info!("Running task video.stream {:?} {} {:?}", self.input_path, self.format, self.tiers);
+		let format = ffmpeg::StreamFormat::from_str_opt(&self.format).ok_or(Error::Parse)?;
+		let tiers: Vec<&VideoQualityTier> =
+			self.tiers.iter().filter_map(|t| crate::preset::get_video_tier(t)).collect();
+		let Some(top) = tiers.last().copied() else {
+			return Err(Error::ValidationError("no stream tiers".into()));
+		};
+		let renditions: Vec<ffmpeg::StreamRendition> = tiers
+			.iter()
+			.map(|t| ffmpeg::StreamRendition {
+				name: t.name.rsplit('.').next().unwrap_or(t.name).to_string(),
+				max_dim: t.max_dim,
+				bitrate: t.bitrate,
+			})
+			.collect();
+
+		let out_dir = app.opts.tmp_dir.join(format!("stream_{}", self.f_id));
+		let zip_path = app.opts.tmp_dir.join(format!("stream_{}.zip", self.f_id));
+		// A retry starts over rather than packaging into a half-written directory.
+		let _ = tokio::fs::remove_dir_all(&out_dir).await;
+		tokio::fs::create_dir_all(&out_dir).await?;
+
+		let input_path = self.input_path.clone();
+		let (out, zip) = (out_dir.clone(), zip_path.clone());
+		let packaged = app
+			.worker
+			.try_run_slow(move || {
+				let info = ffmpeg::FFmpeg::probe(&input_path)?;
+				let has_audio = !info.audio_streams.is_empty();
+				ffmpeg::FFmpeg::package_stream(&input_path, &out, &renditions, format, has_audio)?;
+
+				let files = list_files(&out)?;
+				let entries = files
+					.iter()
+					.map(|(name, path)| Ok((name.clone(), std::fs::File::open(path)?)))
+					.collect::<ClResult<Vec<_>>>()?;
+				container::write_stored_zip(std::fs::File::create(&zip)?, entries)?;
+				Ok::<_, Error>(info)
+			})
+			.await;
+		let _ = tokio::fs::remove_dir_all(&out_dir).await;
+		let info = match packaged {
+			Ok(info) => info,
+			Err(e) => {
+				let _ = tokio::fs::remove_file(&zip_path).await;
+				return Err(e);
+			}
+		};
+
+		let file_size = tokio::fs::metadata(&zip_path).await?.len();
+		let variant_id = store::create_blob_from_file(
+			app,
+			self.tn_id,
+			&zip_path,
+			blob_adapter::CreateBlobOptions::default(),
+		)
+		.await;
+		let _ = tokio::fs::remove_file(&zip_path).await;
+		let variant_id = variant_id?;
+
+		let resolution = fit_within(info.video_resolution().unwrap_or((0, 0)), top.max_dim);
+		info!(
+			"Finished task video.stream {:?} → {} renditions up to {}x{}, {} bytes",
+			self.input_path,
+			tiers.len(),
+			resolution.0,
+			resolution.1,
+			file_size
+		);
+
+		app.meta_adapter
+			.create_file_variant(
+				self.tn_id,
+				self.f_id,
+				meta_adapter::FileVariant {
+					variant_id: &variant_id,
+					variant: STREAM_VARIANT,
+					format: format.as_str(),
+					resolution,
+					size: file_size,
+					available: true,
+					global: false,
+					duration: Some(info.duration),
+					bitrate: Some(top.bitrate),
+					page_count: None,
+				},
+			)
+			.await?;
+
+		Ok(())
+	}
+}
+
+/// Every file under `dir`, as `(path relative to dir, path)`, sorted so a container
+/// built from the same output is the same bytes.
+fn list_files(dir: &Path) -> ClResult<Vec<(String, std::path::PathBuf)>> {
+	let mut files = Vec::new();
+	let mut pending = vec![dir.to_path_buf()];
+	while let Some(current) = pending.pop() {
+		for entry in std::fs::read_dir(&current)? {
+			let path = entry?.path();
+			if path.is_dir() {
+				pending.push(path);
+			} else if let Ok(rel) = path.strip_prefix(dir) {
+				let name = rel.components().map(|c| c.as_os_str().to_string_lossy()).join("/");
+				files.push((name, path));
+			}
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -179,6 +395,37 @@ mod tests {
 		let rebuilt = VideoTranscoderTask::build(0, &serialized).unwrap();
 		assert_eq!(rebuilt.kind_of(), "video.transcode");
 	}
+
+	#[test]
+	fn test_video_stream_serialize_deserialize() {
+		let task = VideoStreamTask::new(
+			TnId(1),
+			42,
+			Path::new("/tmp/a,b.mp4"),
+			ffmpeg::StreamFormat::HlsDash,
+			vec!["vid.sd".into(), "vid.md".into()],
+		);
+		let serialized = Task::<App>::serialize(task.as_ref());
+		assert_eq!(serialized, "1,42,hls+dash,vid.sd+vid.md,/tmp/a,b.mp4");
+		let rebuilt = VideoStreamTask::build(0, &serialized).unwrap();
+		assert_eq!(Task::<App>::serialize(rebuilt.as_ref()), serialized);
+	}
+
+	#[test]
+	fn test_stream_tiers_stop_at_the_source_size() {
+		let tiers = crate::preset::VIDEO_TIERS;
+		let names = |res| stream_tiers(tiers, res).iter().map(|t| t.name).collect::<Vec<_>>();
+		assert_eq!(names((640, 360)), ["vid.sd"]);
+		assert_eq!(names((1280, 720)), ["vid.sd", "vid.md"]);
+		assert_eq!(names((1920, 1080)), ["vid.sd", "vid.md", "vid.hd"]);
+	}
+
+	#[test]
+	fn test_fit_within_keeps_aspect_and_even_sizes() {
+		assert_eq!(fit_within((1920, 1080), 1280), (1280, 720));
+		assert_eq!(fit_within((1080, 1920), 720), (404, 720));
+		assert_eq!(fit_within((641, 361), 1280), (640, 360));
+	}
 }
 
 // vim: ts=4
@@ -11,6 +11,8 @@ use axum::{
 	response::Response,
 };
 
+use serde::Serialize;
+
 use crate::prelude::*;
 use cloudillo_core::IdTag;
 use cloudillo_core::extract::{OptionalAuth, OptionalRequestId};
@@ -224,6 +226,42 @@ pub async fn get_tenant_app_domain(
 	Ok((StatusCode::OK, Json(response)))
 }
 
+/// A custom emoji of the tenant's published set.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomEmoji {
+	/// Used as the `:name:` reaction subtype.
+	pub name: String,
+	/// The image, fetched from this tenant's `/api/files/{fileId}`.
+	pub file_id: String,
+}
+
+/// Public: the tenant's custom emoji set (`profile.custom_emoji`), sorted by name.
+/// Peers rendering `:name:` reactions on this tenant's content fetch it here. Empty
+/// when the tenant publishes none.
+pub async fn get_tenant_emoji(
+	State(app): State<App>,
+	tn_id: TnId,
+	OptionalRequestId(req_id): OptionalRequestId,
+) -> ClResult<(StatusCode, Json<ApiResponse<Vec<CustomEmoji>>>)> {
+	let set = app.settings.get_json_opt(tn_id, "profile.custom_emoji").await?;
+	let mut emoji: Vec<CustomEmoji> = set
+		.as_ref()
+		.and_then(serde_json::Value::as_object)
+		.into_iter()
+		.flatten()
+		.filter_map(|(name, file_id)| {
+			Some(CustomEmoji { name: name.clone(), file_id: file_id.as_str()?.to_string() })
+		})
+		.collect();
+	emoji.sort_by(|a, b| a.name.cmp(&b.name));
+	let mut response = ApiResponse::new(emoji);
+	if let Some(id) = req_id {
+		response = response.with_req_id(id);
+	}
+	Ok((StatusCode::OK, Json(response)))
+}
+
 pub async fn get_tenant_profile(
 	State(app): State<App>,
 	IdTag(id_tag): IdTag,
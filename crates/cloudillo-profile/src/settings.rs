@@ -128,7 +128,66 @@ pub fn register_settings(registry: &mut SettingsRegistry) -> ClResult<()> {
 			.build()?,
 	)?;
 
+	// Custom emoji set: `{ "<name>": "<fileId>" }`, usable as `:name:` reactions on
+	// this profile's content and served publicly at `/api/me/emoji`. The files
+	// should be public images so peers can render them.
+	registry.register(
+		SettingDefinition::builder("profile.custom_emoji")
+			.description("Custom emoji published by this profile: name -> image file id")
+			.scope(SettingScope::Tenant)
+			.permission(PermissionLevel::User)
+			.optional(true)
+			.validator(validate_custom_emoji)
+			.build()?,
+	)?;
+
 	Ok(())
 }
 
+/// Most emoji a profile may publish.
+pub const MAX_CUSTOM_EMOJI: usize = 200;
+
+fn validate_custom_emoji(v: &SettingValue) -> ClResult<()> {
+	let SettingValue::Json(serde_json::Value::Object(set)) = v else {
+		return Err(Error::ValidationError("Custom emoji must be a name -> file id map".into()));
+	};
+	if set.len() > MAX_CUSTOM_EMOJI {
+		return Err(Error::ValidationError(format!(
+			"At most {} custom emoji are allowed",
+			MAX_CUSTOM_EMOJI
+		)));
+	}
+	for (name, file_id) in set {
+		if !cloudillo_types::reactions::is_custom_emoji_name(name) {
+			return Err(Error::ValidationError(format!(
+				"Invalid custom emoji name '{}': use 2 to {} of a-z, 0-9 and _",
+				name,
+				cloudillo_types::reactions::MAX_CUSTOM_EMOJI_NAME
+			)));
+		}
+		if file_id.as_str().is_none_or(str::is_empty) {
+			return Err(Error::ValidationError(format!("Custom emoji '{}' needs a file id", name)));
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn custom_emoji_sets_map_valid_names_to_file_ids() {
+		let check = |v: serde_json::Value| validate_custom_emoji(&SettingValue::Json(v));
+		assert!(check(serde_json::json!({ "party_parrot": "f1~abc", "ok": "f1~def" })).is_ok());
+		assert!(check(serde_json::json!({})).is_ok());
+		assert!(check(serde_json::json!({ "Party": "f1~abc" })).is_err());
+		assert!(check(serde_json::json!({ "x": "f1~abc" })).is_err());
+		assert!(check(serde_json::json!({ "ok": "" })).is_err());
+		assert!(check(serde_json::json!({ "ok": 7 })).is_err());
+		assert!(check(serde_json::json!(["ok"])).is_err());
+		assert!(validate_custom_emoji(&SettingValue::String("ok".into())).is_err());
+	}
+}
+
 // vim: ts=4
@@ -29,7 +29,9 @@ use axum::{
 use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
+use cloudillo_core::dir_cache::DirCache;
 use cloudillo_core::extract::{Auth, IdTag, OptionalAuth, OptionalRequestId};
+use cloudillo_core::file_access;
 use cloudillo_core::share_access::{
 	ShareStanding, ensure_grant_within, ensure_standing, require_share_manager, share_standing,
 };
@@ -169,6 +171,48 @@ fn parse_access_level(s: &str) -> ClResult<char> {
 	}
 }
 
+/// Parse a requested share-link mode into its char.
+///
+/// A mode fixes the link's level — `'V'` (view only) reads, `'U'` (upload only) writes into its
+/// folder — see [`mode_access_level`].
+fn parse_link_mode(s: &str) -> ClResult<char> {
+	match s {
+		"view" | "V" => Ok('V'),
+		"upload" | "U" => Ok('U'),
+		other => Err(Error::ValidationError(format!(
+			"Invalid linkMode '{}': must be 'view' or 'upload'",
+			other
+		))),
+	}
+}
+
+/// The access level a share-link mode implies.
+fn mode_access_level(mode: char) -> char {
+	if mode == 'U' { 'W' } else { 'R' }
+}
+
+/// The API name of a stored share-link mode.
+fn link_mode_name(mode: char) -> &'static str {
+	match mode {
+		'V' => "view",
+		'U' => "upload",
+		_ => "unknown",
+	}
+}
+
+/// Bcrypt only reads the first 72 bytes; a longer password would be silently truncated.
+const MAX_LINK_PASSWORD_BYTES: usize = 72;
+
+fn validate_link_password(password: &str) -> ClResult<()> {
+	if password.is_empty() || password.len() > MAX_LINK_PASSWORD_BYTES {
+		return Err(Error::ValidationError(format!(
+			"password must be 1 to {} bytes",
+			MAX_LINK_PASSWORD_BYTES
+		)));
+	}
+	Ok(())
+}
+
 /// Response structure for ref details (authenticated users get full data)
 #[serde_with::skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -195,6 +239,12 @@ pub struct RefResponse {
 	pub access_level: Option<String>,
 	/// Launch params as serialized query string
 	pub params: Option<String>,
+	/// Share link mode: "view" or "upload"; absent for a link that works at `accessLevel`
+	#[serde(rename = "linkMode")]
+	pub link_mode: Option<String>,
+	/// `true` when redeeming the link needs a password (`POST /api/auth/share-link`)
+	#[serde(rename = "passwordProtected", skip_serializing_if = "std::ops::Not::not")]
+	pub password_protected: bool,
 	/// `true` when `ref_id` has been replaced by an opaque digest (caller is a share *reader* but
 	/// not a *manager*); absent means `ref_id` is the real credential. `skip_serializing_none`
 	/// only elides `Option`s, hence the explicit skip attribute.
@@ -226,6 +276,8 @@ impl From<RefData> for RefResponse {
 				AccessLevel::from_perm_char(c).min(AccessLevel::Write).as_str().to_string()
 			}),
 			params: ref_data.params.map(|p| p.to_string()),
+			link_mode: ref_data.link_mode.map(|c| link_mode_name(c).to_string()),
+			password_protected: ref_data.password_protected,
 			redacted: false,
 		}
 	}
@@ -236,7 +288,8 @@ impl RefResponse {
 	///
 	/// Deterministic, so the frontend can keep keying its rows on `refId`; prefixed `r1~` per the
 	/// content-addressed id convention, and paired with `redacted: true` so no client mistakes it
-	/// for a usable link.
+	/// for a usable link. Files dropped through an upload-only link carry the same digest as
+	/// `x.dropLink`.
 	pub fn redact(mut self) -> Self {
 		self.ref_id = cloudillo_types::hasher::hash("r", self.ref_id.as_bytes()).into();
 		self.redacted = true;
@@ -275,6 +328,13 @@ pub struct CreateRefRequest {
 	pub access_level: Option<String>,
 	/// Launch params as serialized query string (e.g., "mode=present")
 	pub params: Option<String>,
+	/// Share link mode (share.file only): "view" — previews, never the original — or "upload" —
+	/// drop files into a folder without seeing its contents. Fixes the access level.
+	#[serde(rename = "linkMode")]
+	pub link_mode: Option<String>,
+	/// Share link password (share.file only); the link is then redeemed through
+	/// `POST /api/auth/share-link`
+	pub password: Option<String>,
 }
 
 /// Query parameters for listing refs
@@ -428,6 +488,35 @@ pub async fn create_ref(
 		return Err(Error::ValidationError("params too long (max 2048 bytes)".into()));
 	}
 
+	// Link mode and password only mean something on a share link.
+	let link_mode = match create_req.link_mode.as_deref() {
+		Some(s) => Some(parse_link_mode(s)?),
+		None => None,
+	};
+	if (link_mode.is_some() || create_req.password.is_some())
+		&& create_req.r#type != SHARE_FILE_REF_TYPE
+	{
+		return Err(Error::ValidationError(
+			"linkMode and password apply to share.file refs only".to_string(),
+		));
+	}
+	if let Some(ref password) = create_req.password {
+		validate_link_password(password)?;
+	}
+	let access_level_char = match link_mode {
+		Some(mode) => {
+			let level = mode_access_level(mode);
+			if create_req.access_level.is_some() && access_level_char != Some(level) {
+				return Err(Error::ValidationError(format!(
+					"a {} link has a fixed access level",
+					link_mode_name(mode)
+				)));
+			}
+			Some(level)
+		}
+		None => access_level_char,
+	};
+
 	// After body validation because the `FileShare` arm needs the parsed `access_level_char` to cap
 	// the grant; `reject_scoped` already ran, so the ordering only affects which of 400/403 a
 	// well-authenticated caller sees.
@@ -452,6 +541,22 @@ pub async fn create_ref(
 					resource_id,
 				)?;
 			}
+
+			// A file drop lands files directly in a folder; there is nothing to drop into
+			// a document.
+			if link_mode == Some('U')
+				&& !file_access::scope_target_is_folder(
+					&app.meta_adapter,
+					app.ext::<DirCache>()?,
+					tn_id,
+					resource_id,
+				)
+				.await?
+			{
+				return Err(Error::ValidationError(
+					"upload-only links can only be made for folders".to_string(),
+				));
+			}
 		}
 		// Unreachable today; deny rather than fall through, so widening `ref_create_gate` cannot
 		// silently inherit an open gate.
@@ -479,6 +584,8 @@ pub async fn create_ref(
 		resource_id: create_req.resource_id.clone(),
 		access_level: access_level_char,
 		params: create_req.params.clone(),
+		link_mode,
+		password: create_req.password.clone(),
 	};
 
 	let ref_data = app.meta_adapter.create_ref(tn_id, &ref_id, &opts).await.map_err(|e| {
@@ -623,6 +730,9 @@ pub struct UpdateRefRequest {
 	pub count: Patch<u32>,
 	#[serde(rename = "accessLevel", default)]
 	pub access_level: Patch<String>,
+	/// Share link password. Use `null` to remove it.
+	#[serde(default)]
+	pub password: Patch<String>,
 }
 
 /// PATCH /api/refs/{ref_id} - Update fields of an existing ref in place.
@@ -705,6 +815,23 @@ pub async fn update_ref(
 			"access_level cannot be set on register refs".to_string(),
 		));
 	}
+	// ...nor a password.
+	if existing.r#type.as_ref() == REGISTER_REF_TYPE && !req.password.is_undefined() {
+		return Err(Error::ValidationError("password cannot be set on register refs".to_string()));
+	}
+	// A view-only or upload-only link has the level its mode implies; the mode itself is
+	// immutable, like the resource.
+	if let Some(mode) = existing.link_mode
+		&& !req.access_level.is_undefined()
+	{
+		return Err(Error::ValidationError(format!(
+			"a {} link has a fixed access level",
+			link_mode_name(mode)
+		)));
+	}
+	if let Patch::Value(ref password) = req.password {
+		validate_link_password(password)?;
+	}
 
 	// Validate expires_at is in the future when set (matches create_ref behavior).
 	if let Patch::Value(exp) = req.expires_at
@@ -732,6 +859,7 @@ pub async fn update_ref(
 		&& req.expires_at.is_undefined()
 		&& req.count.is_undefined()
 		&& access_level_patch.is_undefined()
+		&& req.password.is_undefined()
 	{
 		return Err(Error::ValidationError("no fields to update".to_string()));
 	}
@@ -761,6 +889,7 @@ pub async fn update_ref(
 		expires_at: req.expires_at,
 		count: req.count,
 		access_level: access_level_patch,
+		password: req.password,
 	};
 
 	let updated = app.meta_adapter.update_ref(tn_id, &ref_id, &update_opts).await.map_err(|e| {
@@ -974,6 +1103,8 @@ mod tests {
 			resource_id: Some("f1~doc".to_string()),
 			access_level: Some("read".to_string()),
 			params: None,
+			link_mode: None,
+			password_protected: false,
 			redacted: false,
 		};
 
@@ -1008,6 +1139,8 @@ mod tests {
 				resource_id: Some("f1~doc".into()),
 				access_level: Some(c),
 				params: None,
+				link_mode: None,
+				password_protected: false,
 			})
 			.access_level
 		};
@@ -1023,6 +1156,24 @@ mod tests {
 			assert!(parse_access_level(&rendered).is_ok(), "{rendered} must be PATCH-able back");
 		}
 	}
+
+	#[test]
+	fn a_link_mode_fixes_the_access_level() {
+		assert_eq!(parse_link_mode("view").map(mode_access_level).ok(), Some('R'));
+		assert_eq!(parse_link_mode("upload").map(mode_access_level).ok(), Some('W'));
+		assert!(parse_link_mode("download").is_err());
+		for c in ['V', 'U'] {
+			assert_eq!(parse_link_mode(link_mode_name(c)).ok(), Some(c));
+		}
+	}
+
+	#[test]
+	fn link_passwords_fit_bcrypt() {
+		assert!(validate_link_password("").is_err());
+		assert!(validate_link_password("hunter2").is_ok());
+		assert!(validate_link_password(&"x".repeat(MAX_LINK_PASSWORD_BYTES)).is_ok());
+		assert!(validate_link_password(&"x".repeat(MAX_LINK_PASSWORD_BYTES + 1)).is_err());
+	}
 }
 
 // vim: ts=4
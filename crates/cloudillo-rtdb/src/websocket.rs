@@ -34,6 +34,9 @@ use tokio::sync::{Mutex, RwLock};
 /// Throttle interval for access/modification tracking (60 seconds)
 const TRACKING_THROTTLE_SECS: u64 = 60;
 
+/// Most changes one `sync` response carries; the client pages for the rest.
+const SYNC_PAGE_SIZE: u32 = 500;
+
 /// A message in the RTDB protocol
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RtdbMessage {
@@ -305,31 +308,14 @@ pub async fn handle_rtdb_connection(
 				continue;
 			}
 
-			// Convert ChangeEvent to change message matching TS client expectations
-			let (action, data) = match &event {
-				ChangeEvent::Create { data, .. } => ("create", Some(data.clone())),
-				ChangeEvent::Update { data, .. } => ("update", Some(data.clone())),
-				ChangeEvent::Delete { .. } => ("delete", None),
-				ChangeEvent::Lock { data, .. } => ("lock", Some(data.clone())),
-				ChangeEvent::Unlock { data, .. } => ("unlock", Some(data.clone())),
-				ChangeEvent::Ready { data, .. } => ("ready", data.clone()),
-				ChangeEvent::Replace { data, .. } => ("replace", data.clone()),
-			};
-
+			let event_obj = change_event_json(&event);
 			debug!(
 				"RTDB change event: action={}, path={}, subscription_id={}",
-				action,
+				event_obj["action"],
 				event.path(),
 				subscription_id
 			);
 
-			let mut event_obj = json!({
-				"action": action,
-				"path": event.path(),
-			});
-			if let Some(d) = &data {
-				event_obj["data"] = d.clone();
-			}
 			let msg = RtdbMessage::new(
 				"change",
 				json!({
@@ -428,6 +414,29 @@ fn parse_select(payload: &serde_json::Map<String, Value>) -> Option<Vec<String>>
 	(!fields.is_empty()).then_some(fields)
 }
 
+/// Convert a `ChangeEvent` to the event object of a `change` message, the shape
+/// the TS client expects. `sync` replays logged changes in the same shape.
+fn change_event_json(event: &ChangeEvent) -> Value {
+	let (action, data) = match event {
+		ChangeEvent::Create { data, .. } => ("create", Some(data)),
+		ChangeEvent::Update { data, .. } => ("update", Some(data)),
+		ChangeEvent::Delete { .. } => ("delete", None),
+		ChangeEvent::Lock { data, .. } => ("lock", Some(data)),
+		ChangeEvent::Unlock { data, .. } => ("unlock", Some(data)),
+		ChangeEvent::Ready { data, .. } => ("ready", data.as_ref()),
+		ChangeEvent::Replace { data, .. } => ("replace", data.as_ref()),
+	};
+
+	let mut event_obj = json!({
+		"action": action,
+		"path": event.path(),
+	});
+	if let Some(d) = data {
+		event_obj["data"] = d.clone();
+	}
+	event_obj
+}
+
 /// Check if the connection can write to the given path.
 ///
 /// - `Write`/`Admin`: always allowed (returns None)
@@ -515,6 +524,11 @@ async fn handle_rtdb_command(
 				let mut results = Vec::new();
 				let mut references: std::collections::HashMap<String, String> =
 					std::collections::HashMap::new();
+				// What the database's triggers get to see once this commits. Only
+				// collected when triggers are wired in: the old state of a replaced
+				// or deleted document costs a read.
+				let triggers = app.ext::<cloudillo_core::RtdbTriggerFn>().ok();
+				let mut changes: Vec<ChangeEvent> = Vec::new();
 
 				// Process all operations in the same transaction
 				for op in operations {
@@ -546,6 +560,37 @@ async fn handle_rtdb_command(
 						);
 					}
 
+					// A write replayed from an offline queue carries the version its
+					// client based it on. A write made since then is a conflict for
+					// the client to resolve, not one to silently overwrite. The
+					// refusal names the document and its current version.
+					if matches!(op_type, "update" | "replace" | "delete")
+						&& let Some(base) = op.get("baseSeq").and_then(Value::as_u64)
+					{
+						match txn.version(&path).await {
+							Ok(Some(version)) if version > base => {
+								drop(txn);
+								let mut err = RtdbMessage::error(
+									msg.id.clone(),
+									409,
+									format!("Document changed since {}: {}", base, path),
+								);
+								err.payload.insert("path".to_string(), Value::String(path));
+								err.payload.insert("seq".to_string(), Value::from(version));
+								return err;
+							}
+							Ok(_) => {}
+							Err(e) => {
+								drop(txn);
+								return RtdbMessage::error(
+									msg.id.clone(),
+									500,
+									format!("Transaction failed: {}", e),
+								);
+							}
+						}
+					}
+
 					let result = match op_type {
 						"create" => {
 							let mut data = op.get("data").cloned().unwrap_or(Value::Null);
@@ -564,8 +609,15 @@ async fn handle_rtdb_command(
 								warn!("Failed to process computed values: {}", e);
 								Err(e)
 							} else {
+								let logged = triggers.is_some().then(|| data.clone());
 								match txn.create(&path, data).await {
 									Ok(doc_id) => {
+										if let Some(data) = logged {
+											changes.push(ChangeEvent::Create {
+												path: format!("{}/{}", path, doc_id).into(),
+												data,
+											});
+										}
 										// Store reference if provided (e.g., { ref: "$post" })
 										if let Some(ref_value) = op.get("ref")
 											&& let Some(ref_name) = ref_value.as_str()
@@ -602,6 +654,7 @@ async fn handle_rtdb_command(
 								// Fetch existing document and merge with patch data
 								match txn.get(&path).await {
 									Ok(existing_opt) => {
+										let old_data = triggers.and(existing_opt.as_ref()).cloned();
 										let final_data = match existing_opt {
 											Some(mut existing) => {
 												match crate::merge::shallow_merge(
@@ -620,12 +673,25 @@ async fn handle_rtdb_command(
 											}
 										};
 										match final_data {
-											Ok(data) => match txn.update(&path, data).await {
-												Ok(()) => Ok(
-													json!({ "ref": Value::Null, "id": Value::Null }),
-												),
-												Err(e) => Err(e),
-											},
+											Ok(data) => {
+												let logged =
+													triggers.is_some().then(|| data.clone());
+												match txn.update(&path, data).await {
+													Ok(()) => {
+														if let Some(data) = logged {
+															changes.push(ChangeEvent::Update {
+																path: path.as_str().into(),
+																data,
+																old_data,
+															});
+														}
+														Ok(
+															json!({ "ref": Value::Null, "id": Value::Null }),
+														)
+													}
+													Err(e) => Err(e),
+												}
+											}
 											Err(e) => Err(e),
 										}
 									}
@@ -653,16 +719,49 @@ async fn handle_rtdb_command(
 								warn!("Failed to process computed values: {}", e);
 								Err(e)
 							} else {
-								match txn.update(&path, data).await {
-									Ok(()) => Ok(json!({ "ref": Value::Null, "id": Value::Null })),
+								let old_data = match triggers {
+									Some(_) => txn.get(&path).await.map(Some),
+									None => Ok(None),
+								};
+								match old_data {
+									Ok(old_data) => match txn.update(&path, data.clone()).await {
+										Ok(()) => {
+											if let Some(old_data) = old_data {
+												changes.push(ChangeEvent::Update {
+													path: path.as_str().into(),
+													data,
+													old_data,
+												});
+											}
+											Ok(json!({ "ref": Value::Null, "id": Value::Null }))
+										}
+										Err(e) => Err(e),
+									},
 									Err(e) => Err(e),
 								}
 							}
 						}
-						"delete" => match txn.delete(&path).await {
-							Ok(()) => Ok(json!({ "ref": Value::Null, "id": Value::Null })),
-							Err(e) => Err(e),
-						},
+						"delete" => {
+							let old_data = match triggers {
+								Some(_) => txn.get(&path).await.map(Some),
+								None => Ok(None),
+							};
+							match old_data {
+								Ok(old_data) => match txn.delete(&path).await {
+									Ok(()) => {
+										if let Some(old_data) = old_data {
+											changes.push(ChangeEvent::Delete {
+												path: path.as_str().into(),
+												old_data,
+											});
+										}
+										Ok(json!({ "ref": Value::Null, "id": Value::Null }))
+									}
+									Err(e) => Err(e),
+								},
+								Err(e) => Err(e),
+							}
+						}
 						_ => {
 							// Invalid operation type - abort transaction (will rollback on drop)
 							warn!("Unknown transaction operation type: {}", op_type);
@@ -697,6 +796,8 @@ async fn handle_rtdb_command(
 					"Transaction completed successfully with {} operations, committing",
 					results.len()
 				);
+				// The sequence the writes reached, which the client resumes `sync` from
+				let seq = txn.seq().await.ok().flatten();
 				if let Err(e) = txn.commit().await {
 					warn!("Transaction commit failed: {}", e);
 					return RtdbMessage::error(
@@ -717,8 +818,17 @@ async fn handle_rtdb_command(
 					index(app, conn.tn_id, &conn.file_id);
 				}
 
+				// Late-bound for the same reason: the triggers run on the action DSL.
+				// The hook only spawns them.
+				if let Some(fire) = triggers {
+					fire(app, conn.tn_id, &conn.file_id, changes);
+				}
+
 				let mut result_map = serde_json::Map::new();
 				result_map.insert("results".to_string(), Value::Array(results));
+				if let Some(seq) = seq {
+					result_map.insert("seq".to_string(), Value::from(seq));
+				}
 				RtdbMessage::response(msg.id.clone(), "transactionResult", result_map)
 			} else {
 				warn!("RTDB transaction: no operations found");
@@ -729,7 +839,7 @@ async fn handle_rtdb_command(
 		"query" => {
 			// Fetch documents with optional filtering/sorting/aggregation
 			use cloudillo_types::rtdb_adapter::{
-				AggregateOptions, QueryFilter, QueryOptions, SortField,
+				AggregateOptions, QueryFilter, QueryOptions, RtdbCursor, SortField,
 			};
 			let path = msg.payload.get("path").and_then(|v| v.as_str()).unwrap_or("");
 			debug!("RTDB query: path={}", path);
@@ -797,13 +907,92 @@ async fn handle_rtdb_command(
 				opts = opts.with_select(select);
 			}
 
+			// Parse cursors. Unlike the options above a cursor is opaque, so a bad
+			// one is rejected: ignoring it would restart paging from the top.
+			for (key, after) in [("startAfter", true), ("endBefore", false)] {
+				let Some(value) = msg.payload.get(key).and_then(Value::as_str) else { continue };
+				let Some(cursor) = RtdbCursor::decode(value) else {
+					return RtdbMessage::error(msg.id.clone(), 400, format!("Invalid {}", key));
+				};
+				opts = if after {
+					opts.with_start_after(cursor)
+				} else {
+					opts.with_end_before(cursor)
+				};
+			}
+			if let Err(e) = opts.validate_cursors() {
+				return RtdbMessage::error(msg.id.clone(), 400, e.to_string());
+			}
+
+			// Parse explain: answer with the query plan instead of the documents
+			let explain = msg.payload.get("explain").and_then(Value::as_bool).unwrap_or(false);
+			opts = opts.with_explain(explain);
+
+			// A cursor is taken from the sort fields, which a projection may drop,
+			// so they are selected too and the page is re-projected afterwards.
+			// One extra row tells whether another page follows.
+			let paging = !explain && opts.aggregate.is_none();
+			let select = opts.select.clone();
+			let sort = opts.sort_fields().to_vec();
+			let limit = opts.limit;
+			let backward = opts.end_before.is_some() && opts.start_after.is_none();
+			if paging {
+				if let Some(select) = &select {
+					let mut widened = select.clone();
+					widened.extend(
+						sort.iter().map(|f| f.field.clone()).filter(|f| !select.contains(f)),
+					);
+					opts = opts.with_select(widened);
+				}
+				if let Some(limit) = limit {
+					opts = opts.with_limit(limit.saturating_add(1));
+				}
+			}
+
 			match app.rtdb_adapter.query(conn.tn_id, &conn.file_id, path, opts).await {
-				Ok(documents) => {
+				Ok(documents) if explain => {
+					let mut result_map = serde_json::Map::new();
+					result_map.insert(
+						"plan".to_string(),
+						documents.into_iter().next().unwrap_or(Value::Null),
+					);
+					RtdbMessage::response(msg.id.clone(), "queryResult", result_map)
+				}
+				Ok(documents) if !paging => {
+					let mut result_map = serde_json::Map::new();
+					result_map.insert("data".to_string(), Value::Array(documents));
+					RtdbMessage::response(msg.id.clone(), "queryResult", result_map)
+				}
+				Ok(mut documents) => {
 					debug!("RTDB query result: {} documents", documents.len());
+					// The extra row sits past the far end of the page: the front
+					// when paging backwards from `endBefore`
+					let has_more = limit.is_some_and(|limit| documents.len() > limit as usize);
+					if has_more {
+						if backward {
+							documents.remove(0);
+						} else {
+							documents.pop();
+						}
+					}
+					let cursor = |doc: Option<&Value>| {
+						doc.and_then(|doc| RtdbCursor::for_doc(doc, &sort))
+							.map_or(Value::Null, |c| Value::String(c.encode()))
+					};
+					let start_cursor = cursor(documents.first());
+					let end_cursor = cursor(documents.last());
+					if let Some(select) = &select {
+						documents = documents.iter().map(|doc| project_doc(doc, select)).collect();
+					}
+
 					let mut result_map = serde_json::Map::new();
 					result_map.insert("data".to_string(), Value::Array(documents));
+					result_map.insert("startCursor".to_string(), start_cursor);
+					result_map.insert("endCursor".to_string(), end_cursor);
+					result_map.insert("hasMore".to_string(), Value::Bool(has_more));
 					RtdbMessage::response(msg.id.clone(), "queryResult", result_map)
 				}
+				Err(Error::ValidationError(e)) => RtdbMessage::error(msg.id.clone(), 400, e),
 				Err(e) => {
 					warn!("Query failed: {}", e);
 					RtdbMessage::error(msg.id.clone(), 500, "Query failed")
@@ -811,6 +1000,43 @@ async fn handle_rtdb_command(
 			}
 		}
 
+		"sync" => {
+			// Catch a client up on the writes after the last sequence it saw, in
+			// the shape of live `change` events, a page at a time. Without `since`
+			// the client has no state yet and just learns the current sequence.
+			let since = msg.payload.get("since").and_then(Value::as_u64).unwrap_or(u64::MAX);
+			let limit =
+				msg.payload.get("limit").and_then(Value::as_u64).map_or(SYNC_PAGE_SIZE, |l| {
+					u32::try_from(l).unwrap_or(u32::MAX).clamp(1, SYNC_PAGE_SIZE)
+				});
+			debug!("RTDB sync: since={}, limit={}", since, limit);
+
+			match app.rtdb_adapter.changes_since(conn.tn_id, &conn.file_id, since, limit).await {
+				Ok(change_set) => {
+					let changes = change_set
+						.changes
+						.iter()
+						.map(|record| {
+							let mut event_obj = change_event_json(&record.event);
+							event_obj["seq"] = Value::from(record.seq);
+							event_obj
+						})
+						.collect();
+
+					let mut result_map = serde_json::Map::new();
+					result_map.insert("changes".to_string(), Value::Array(changes));
+					result_map.insert("seq".to_string(), Value::from(change_set.seq));
+					result_map.insert("hasMore".to_string(), Value::Bool(change_set.has_more));
+					result_map.insert("resync".to_string(), Value::Bool(change_set.resync));
+					RtdbMessage::response(msg.id.clone(), "syncResult", result_map)
+				}
+				Err(e) => {
+					warn!("Sync failed: {}", e);
+					RtdbMessage::error(msg.id.clone(), 500, "Sync failed")
+				}
+			}
+		}
+
 		"get" => {
 			// Fetch single document
 			let path = msg.payload.get("path").and_then(|v| v.as_str()).unwrap_or("");
@@ -1119,28 +1345,46 @@ async fn handle_rtdb_command(
 		}
 
 		"createIndex" => {
-			// Create an index on a field for query optimization
+			// Create an index on a field, or a composite index on `fields`, for
+			// query optimization
+			use cloudillo_types::rtdb_adapter::IndexSpec;
+
 			let path = msg.payload.get("path").and_then(|v| v.as_str()).unwrap_or("");
-			let field = msg.payload.get("field").and_then(|v| v.as_str()).unwrap_or("");
+			let spec = match (msg.payload.get("fields"), msg.payload.get("field")) {
+				(Some(Value::Array(fields)), _) => fields
+					.iter()
+					.map(|f| f.as_str().filter(|f| !f.is_empty()).map(str::to_string))
+					.collect::<Option<Vec<_>>>()
+					.filter(|fields| !fields.is_empty())
+					.map(IndexSpec::composite),
+				(None, Some(Value::String(field))) if !field.is_empty() => {
+					Some(IndexSpec::field(field.as_str()))
+				}
+				_ => None,
+			};
 
-			if path.is_empty() || field.is_empty() {
+			let Some(spec) = spec.filter(|_| !path.is_empty()) else {
 				return RtdbMessage::error(
 					msg.id.clone(),
 					400,
 					"Missing path or field for index creation",
 				);
-			}
+			};
 
 			// Check write access for the collection being indexed
 			if let Some(err) = check_write_access(conn, &msg.id, path) {
 				return err;
 			}
 
-			debug!("RTDB createIndex: path={}, field={}", path, field);
+			debug!("RTDB createIndex: path={}, fields={:?}", path, spec.fields);
 
-			match app.rtdb_adapter.create_index(conn.tn_id, &conn.file_id, path, field).await {
+			match app
+				.rtdb_adapter
+				.create_composite_index(conn.tn_id, &conn.file_id, path, &spec)
+				.await
+			{
 				Ok(()) => {
-					debug!("Index created successfully: {} on {}", field, path);
+					debug!("Index created successfully: {:?} on {}", spec.fields, path);
 					RtdbMessage::response(
 						msg.id.clone(),
 						"createIndexResult",
@@ -110,6 +110,8 @@ pub struct PutDocFormat {
 	/// Deep-link query param name, e.g. `"nav"`.
 	pub nav_param: Option<String>,
 	pub search: Option<serde_json::Value>,
+	/// RTDB triggers, see `cloudillo_action::dsl::trigger`.
+	pub triggers: Option<serde_json::Value>,
 	pub x: Option<serde_json::Value>,
 }
 
@@ -169,6 +171,13 @@ pub async fn put_doc_format(
 	if let Some(search) = &body.search {
 		crate::rules::IndexRules::parse(search)?;
 	}
+	// Same for triggers, whose parser lives with the DSL they run on. Absent
+	// extension = triggers not wired in; they are stored and never run.
+	if let Some(triggers) = &body.triggers
+		&& let Ok(check) = app.ext::<cloudillo_core::RtdbTriggerCheckFn>()
+	{
+		check(triggers)?;
+	}
 
 	let existing = app.meta_adapter.read_doc_format(tn_id, &content_type).await?;
 
@@ -243,11 +252,20 @@ pub async fn put_doc_format(
 				store_tp: body.store_tp.as_deref(),
 				nav_param: body.nav_param.as_deref(),
 				search: body.search.as_ref(),
+				triggers: body.triggers.as_ref(),
 				x: body.x.as_ref(),
 			},
 		)
 		.await?;
 
+	// Keyed per trigger, so restating an unchanged block re-schedules nothing. A cron
+	// trigger the new block dropped keeps its task, which finds nothing to run.
+	if let Some(triggers) = &body.triggers
+		&& let Ok(schedule) = app.ext::<cloudillo_core::RtdbTriggerScheduleFn>()
+	{
+		schedule(&app, tn_id, &content_type, triggers).await?;
+	}
+
 	// Before anything downstream resolves this content type again — the sweep
 	// scheduled below indexes through `doc_format::resolve` — or the new rules and
 	// nav param would not take effect until restart.
@@ -395,8 +413,9 @@ fn claimed_by(existing: &DocFormat, publisher_tag: &str, app_name: &str) -> bool
 /// Whether a registration would write a row saying exactly what the bundle
 /// already says.
 ///
-/// Everything a `doc_formats` row carries except `x` — which the bundle has no
-/// way to express, so a body naming one is a genuine difference and must write.
+/// Everything a `doc_formats` row carries except `x` and `triggers` — which the
+/// bundle has no way to express, so a body naming either is a genuine difference
+/// and must write.
 /// `updated_at` is not compared: it is when the row (or the manifest file) was
 /// last touched, not part of what either declares.
 fn same_as_bundled(bundled: &DocFormat, body: &PutDocFormat) -> bool {
@@ -406,6 +425,7 @@ fn same_as_bundled(bundled: &DocFormat, body: &PutDocFormat) -> bool {
 		&& bundled.store_tp.as_deref() == body.store_tp.as_deref()
 		&& bundled.nav_param.as_deref() == body.nav_param.as_deref()
 		&& bundled.search.as_ref() == body.search.as_ref()
+		&& body.triggers.is_none()
 		&& body.x.is_none()
 }
 
@@ -423,6 +443,7 @@ fn same_content(existing: &DocFormat, body: &PutDocFormat) -> bool {
 		&& existing.store_tp.as_deref() == body.store_tp.as_deref()
 		&& existing.nav_param.as_deref() == body.nav_param.as_deref()
 		&& existing.search.as_ref() == body.search.as_ref()
+		&& existing.triggers.as_ref() == body.triggers.as_ref()
 		&& existing.x.as_ref() == body.x.as_ref()
 }
 
@@ -547,6 +568,7 @@ mod tests {
 			store_tp: Some("RTDB".into()),
 			nav_param: Some("nav".into()),
 			search,
+			triggers: None,
 			x: None,
 			updated_at: Timestamp(0),
 		}
@@ -562,6 +584,7 @@ mod tests {
 			store_tp: Some("RTDB".into()),
 			nav_param: Some("nav".into()),
 			search,
+			triggers: None,
 			x: None,
 		}
 	}
@@ -638,6 +661,12 @@ mod tests {
 		};
 		assert_eq!(gate(Some(&existing), &x), GateDecision::WriteSameVersion);
 
+		let triggers = PutDocFormat {
+			triggers: Some(serde_json::json!([{ "name": "t", "cron": "0 * * * *", "do": [] }])),
+			..put(v, Some(rules("ti")))
+		};
+		assert_eq!(gate(Some(&existing), &triggers), GateDecision::WriteSameVersion);
+
 		let app = PutDocFormat { app_name: "notillo2".into(), ..put(v, Some(rules("ti"))) };
 		assert_eq!(gate(Some(&existing), &app), GateDecision::WriteSameVersion);
 	}
@@ -669,6 +698,11 @@ mod tests {
 		with_x.x = Some(serde_json::json!({ "k": 1 }));
 		assert!(!same_as_bundled(&bundled, &with_x));
 
+		// Nor have triggers.
+		let mut with_triggers = put(Some(1_000_000), Some(rules("ti")));
+		with_triggers.triggers = Some(serde_json::json!([]));
+		assert!(!same_as_bundled(&bundled, &with_triggers));
+
 		// A field the bundle states and the body does not.
 		let mut no_nav = put(Some(1_000_000), Some(rules("ti")));
 		no_nav.nav_param = None;
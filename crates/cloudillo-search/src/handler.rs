@@ -54,6 +54,10 @@
 //!    never drops a row. Kept as a cross-check against future drift in either
 //!    half, and loud: a non-zero drop count logs at `warn!`.
 //!
+//! `collection` narrows the query to one of the caller's saved-item collections.
+//! It is a filter, not a grant: the layers above still decide what the caller
+//! sees, so an item saved while visible drops out of the hits once it is not.
+//!
 //! # Pagination
 //!
 //! Results are relevance-ordered, so this endpoint uses `limit`/`offset` rather
@@ -141,6 +145,8 @@ pub struct SearchQuery {
 	///
 	/// With `tags` present, `q` may be empty — that is a tag-only browse.
 	pub tags: Option<String>,
+	/// Restrict to the items of one of the caller's collections.
+	pub collection: Option<u64>,
 	pub limit: Option<u32>,
 	pub offset: Option<u32>,
 }
@@ -240,11 +246,23 @@ pub async fn get_search(
 	let content_type =
 		csv_filter(q.content_type.as_deref(), SEARCH_MAX_CONTENT_TYPES, "contentType")?;
 	let tags = csv_filter(q.tags.as_deref(), SEARCH_MAX_TAGS, "tags")?;
+	let collection_id = match q.collection {
+		// Collections are their creator's own: a guest or a scoped token (which
+		// identifies nobody) has none, and another user's reads as absent.
+		Some(_) if !authenticated || auth.scope.is_some() => {
+			return Err(Error::PermissionDenied);
+		}
+		Some(id) => {
+			Some(app.meta_adapter.read_collection(tn_id, &auth.id_tag, id).await?.collection_id)
+		}
+		None => None,
+	};
 
 	let mut opts = SearchOptions {
 		q: q.q,
 		obj_tp: obj_tp_filter(q.r#type.as_deref()),
 		file_id: q.file_id,
+		collection_id,
 		content_type,
 		tags,
 		limit,
@@ -281,18 +299,20 @@ pub async fn get_search(
 	}
 
 	if let Some(scope) = auth.scope.as_deref() {
-		// Only a file scope reaches here; anything else — including a scope
-		// string this build cannot parse — is denied rather than ignored.
-		let Some(TokenScope::File { file_id, .. }) = TokenScope::parse(scope) else {
+		// Only a file scope that reads (a view-only link does) reaches here;
+		// anything else — a file drop, or a scope string this build cannot
+		// parse — is denied rather than ignored.
+		let token_scope = TokenScope::parse(scope);
+		let Some((file_id, _)) = token_scope.as_ref().and_then(TokenScope::file_grant) else {
 			return Err(Error::PermissionDenied);
 		};
-		opts.scope_file_id = Some(file_id.clone());
+		opts.scope_file_id = Some(file_id.to_owned());
 		// The share itself is the grant: the shared file's own row and the deep
 		// parts of its document tree stay visible even at Direct visibility, or a
 		// share link to a private document would search to zero hits inside a
 		// document its holder can open. Child `'F'` rows in the tree keep the level
 		// filter — same split `GET /api/files` makes.
-		opts.scope_grant_file_id = Some(file_id.clone().into());
+		opts.scope_grant_file_id = Some(file_id.into());
 		opts.obj_tp = Some(scope_obj_tp(opts.obj_tp.take()));
 	}
 
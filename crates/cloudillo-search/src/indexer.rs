@@ -62,8 +62,8 @@ pub const OBJ_DOC: char = 'D';
 const MAX_CONTRIBUTIONS: usize = 100_000;
 
 /// `files.file_tp` values with a live document store behind them. Blobs are
-/// absent on purpose: extracting text from a PDF or a docx is a separate,
-/// heavier job than reading a structured document back.
+/// absent on purpose: a PDF's or a docx's pages are extracted from its bytes and
+/// ride the file's own `'F'` row — see `objects::document_text`.
 pub const STORE_RTDB: &str = "RTDB";
 pub const STORE_CRDT: &str = "CRDT";
 
@@ -30,8 +30,8 @@ use std::{
 use async_trait::async_trait;
 use cloudillo_core::scheduler::{Task, TaskId};
 use cloudillo_types::meta_adapter::{
-	ActionView, FileStatus, FileView, ListProfileOptions, MANAGED_PARENT_ID, Profile, SearchPart,
-	TRASH_PARENT_ID,
+	ActionView, FileId, FileStatus, FileView, ListProfileOptions, MANAGED_PARENT_ID, Profile,
+	SearchPart, TRASH_PARENT_ID, VERSIONS_PARENT_ID,
 };
 use cloudillo_types::site::{FRAGMENT_EXT, MANIFEST_ENTRY, entry_path, site_path};
 use cloudillo_types::worker::Priority;
@@ -152,7 +152,7 @@ pub async fn index_file_row(app: &App, tn_id: TnId, file: &FileView) -> ClResult
 	// silences every other managed file — see [`is_live_site_indexable`].
 	let live_site = is_live_site_container(app, tn_id, file).await?;
 	let indexable = if live_site { is_live_site_indexable(file) } else { is_indexable(file) };
-	let part = file_part(file, tags.as_deref(), indexable);
+	let mut part = file_part(file, tags.as_deref(), indexable);
 	// Only a file with a metadata row has content parts: the two go in and out of the
 	// index together, so a trashed container cannot leave its pages searchable.
 	let pages = if part.is_some() && live_site {
@@ -160,7 +160,24 @@ pub async fn index_file_row(app: &App, tn_id: TnId, file: &FileView) -> ClResult
 	} else {
 		Vec::new()
 	};
-	let mut parts: Vec<SearchPart<'_>> = Vec::with_capacity(part.iter().len() + pages.len());
+	let document =
+		if part.is_some() && !live_site { document_text(app, tn_id, file).await? } else { None };
+	// A document with no pages of its own is the file's body; a paged one is a part
+	// per page, keyed `{unit}/{number}` like a deep part is keyed `{kind}/{id}`.
+	let mut doc_pages: Vec<(String, &str, &str)> = Vec::new();
+	if let (Some(part), Some(document)) = (part.as_mut(), document.as_ref()) {
+		match document.unit {
+			None => part.body = document.pages.first().map(|p| p.text.text.as_str()),
+			Some(unit) => doc_pages.extend(
+				document
+					.pages
+					.iter()
+					.map(|page| (format!("{unit}/{}", page.number), unit, page.text.text.as_str())),
+			),
+		}
+	}
+	let mut parts: Vec<SearchPart<'_>> =
+		Vec::with_capacity(part.iter().len() + pages.len() + doc_pages.len());
 	parts.extend(part);
 	for page in &pages {
 		parts.push(SearchPart {
@@ -171,6 +188,16 @@ pub async fn index_file_row(app: &App, tn_id: TnId, file: &FileView) -> ClResult
 			..Default::default()
 		});
 	}
+	// Untitled: the file's name is on its own row, and repeating it on every page
+	// would make a search for the name return the whole document page by page.
+	for (part_id, unit, body) in &doc_pages {
+		parts.push(SearchPart {
+			part_id,
+			part_kind: Some(unit),
+			body: Some(body),
+			..Default::default()
+		});
+	}
 	let fts_cl = !crate::store_text(app, tn_id).await;
 	app.meta_adapter
 		.replace_search_row(tn_id, OBJ_FILE, &file.file_id, &parts, fts_cl)
@@ -405,6 +432,71 @@ async fn site_page_texts(app: &App, tn_id: TnId, file: &FileView) -> ClResult<Ve
 	Ok(pages)
 }
 
+/// Body text one uploaded document may contribute in total, in characters — the
+/// document counterpart of [`MAX_SITE_BODY_CHARS`], with [`MAX_BODY_CHARS`] per page.
+const MAX_DOCUMENT_BODY_CHARS: usize = 4_000_000;
+
+/// `files.file_tp` of an uploaded file: bytes in the blob store, no document store.
+const STORE_BLOB: &str = "BLOB";
+
+/// An uploaded document's extracted text: its pages, and what a page is.
+struct DocumentText {
+	/// `"page"`, `"slide"` or `"sheet"`; `None` for a document that is one page.
+	unit: Option<&'static str>,
+	pages: Vec<cloudillo_extract::Page>,
+}
+
+/// The page text of an uploaded PDF, office or text file, or `None` for a file in no
+/// format [`cloudillo_extract::document`] reads.
+///
+/// Re-extracted on every index run, like a site container's pages: a rename costs a
+/// `pdftotext` run, which the object debounce keeps to one per burst.
+///
+/// A blob read that fails propagates and leaves the existing rows alone. A file the
+/// extractor refuses — past its size cap, malformed, encrypted — is indexed by name
+/// and tags alone, as is every document on a node without `pdftotext`: none of those
+/// gets better by retrying, and the file must stay findable meanwhile.
+async fn document_text(app: &App, tn_id: TnId, file: &FileView) -> ClResult<Option<DocumentText>> {
+	if file.file_tp.as_deref().is_some_and(|tp| tp != STORE_BLOB) {
+		return Ok(None);
+	}
+	let Some(format) = file
+		.content_type
+		.as_deref()
+		.and_then(cloudillo_extract::DocumentFormat::from_content_type)
+	else {
+		return Ok(None);
+	};
+	let variants = app
+		.meta_adapter
+		.list_file_variants(tn_id, FileId::FileId(&file.file_id))
+		.await?;
+	let Some(orig) = variants.iter().find(|v| v.variant.as_ref() == "orig") else {
+		return Ok(None);
+	};
+	// Refused off the variant row, before the read.
+	if orig.size > format.max_input_bytes() as u64 {
+		warn!(tn_id = %tn_id, file_id = %file.file_id, size = orig.size,
+			"Document exceeds the extraction limit; indexing it by name alone");
+		return Ok(None);
+	}
+	let bytes = app.blob_adapter.read_blob_buf(tn_id, &orig.variant_id).await?;
+	let budget = cloudillo_extract::PageBudget::new(MAX_BODY_CHARS, MAX_DOCUMENT_BODY_CHARS);
+	let extracted = app
+		.worker
+		.run_slow(move || cloudillo_extract::document::extract_pages(format, &bytes, budget))
+		.await
+		.map_err(|e| Error::Internal(format!("Worker pool failed extracting document: {e}")))?;
+	match extracted {
+		Ok(pages) => Ok(Some(DocumentText { unit: format.unit(), pages })),
+		Err(err) => {
+			warn!(tn_id = %tn_id, file_id = %file.file_id, %err,
+				"Document text cannot be extracted; indexing it by name alone");
+			Ok(None)
+		}
+	}
+}
+
 /// `text` cut to `max` characters, on a char boundary.
 ///
 /// The `'D'` path clamps its title and tags through `extract_action`'s `TextSink`
@@ -435,7 +527,9 @@ fn page_body_budget(remaining: usize) -> Option<usize> {
 /// or the sweep would delete one and immediately rebuild the other.
 ///
 /// A file in the trash is excluded alongside a deleted one — it is out of every
-/// listing, so a hit on it would deep-link nowhere.
+/// listing, so a hit on it would deep-link nowhere. So is a retained revision: its
+/// file is found through the current one, and two hits for one document would be
+/// one too many.
 ///
 /// Managed files are excluded too, and that one is a disclosure rule rather than
 /// a dead-link rule. `crates/cloudillo-profile/src/media.rs` caches every peer's
@@ -457,6 +551,7 @@ fn page_body_budget(remaining: usize) -> Option<usize> {
 pub fn is_indexable(file: &FileView) -> bool {
 	file.parent_id.as_deref() != Some(TRASH_PARENT_ID)
 		&& file.parent_id.as_deref() != Some(MANAGED_PARENT_ID)
+		&& file.parent_id.as_deref() != Some(VERSIONS_PARENT_ID)
 		&& !file.hidden
 		&& !matches!(file.status, FileStatus::Deleted)
 }
@@ -538,7 +633,7 @@ pub async fn index_profile_row(
 /// Three conditions drop an action from the index before any manifest is
 /// consulted, because they are platform-wide tombstone conventions rather than
 /// per-type rules: the action is gone, its status is not Active, or its subtype
-/// is `DEL`. After that, a type with no manifest is simply not indexed — the
+/// is `DEL` or `EDIT`. After that, a type with no manifest is simply not indexed — the
 /// absence of a `search` block is the only allowlist there is.
 pub async fn index_action(app: &App, tn_id: TnId, action_id: &str) -> ClResult<()> {
 	if let Some(action) = app.meta_adapter.get_action(tn_id, action_id).await? {
@@ -589,9 +684,12 @@ fn action_text(app: &App, action: &ActionView) -> Option<ActionText> {
 /// Checked before any manifest, because both conditions are platform-wide
 /// conventions rather than anything a type declares: only an Active row is
 /// visible to clients, and a `DEL` subtype is a tombstone standing in for the
-/// action it retracts. A NULL status means Pending, which is not yet published.
+/// action it retracts. An `EDIT` is a marker too: its content is applied to the
+/// action it names, and that action is reindexed with it, so indexing the edit
+/// as well would find every edited post twice. A NULL status means Pending,
+/// which is not yet published.
 fn is_live(status: Option<&str>, sub_typ: Option<&str>) -> bool {
-	status == Some("A") && sub_typ != Some("DEL")
+	status == Some("A") && !matches!(sub_typ, Some("DEL" | "EDIT"))
 }
 
 /// Apply an action manifest to a wrapper document.
@@ -830,6 +928,7 @@ mod tests {
 		// live hook was forgotten.
 		assert!(!is_indexable(&file_view(Some(TRASH_PARENT_ID), "A")));
 		assert!(!is_indexable(&file_view(None, "D")));
+		assert!(!is_indexable(&file_view(Some(VERSIONS_PARENT_ID), "A")));
 		// A file in an ordinary folder is unaffected.
 		assert!(is_indexable(&file_view(Some("f1~folder"), "A")));
 		// A rejected gate produces no part, whichever rule computed it.
@@ -874,6 +973,7 @@ mod tests {
 		assert!(is_live(Some("A"), None));
 		assert!(is_live(Some("A"), Some("TEXT")));
 		assert!(!is_live(Some("A"), Some("DEL")), "a DEL tombstone must not be indexed");
+		assert!(!is_live(Some("A"), Some("EDIT")), "an edit is indexed as the action it edits");
 		assert!(!is_live(Some("P"), None), "a pending action is not published yet");
 		assert!(!is_live(Some("V"), None), "an inbound action mid-verification is not live");
 		assert!(!is_live(None, None));
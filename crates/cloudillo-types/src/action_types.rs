@@ -57,6 +57,10 @@ pub mod status {
 	/// Used for: rejected requests, deleted content
 	pub const DELETED: char = 'D';
 
+	/// Hidden - Kept, but withdrawn from listings by a community moderator
+	/// Used for: content hidden in response to a REPORT (MOD:HIDE, undone by MOD:RESTORE)
+	pub const HIDDEN: char = 'H';
+
 	/// Draft - Action is a draft, not yet published
 	/// Used for: drafts that can be edited before publishing
 	pub const DRAFT: char = 'R';
@@ -13,7 +13,7 @@ use std::collections::HashMap;
 use crate::{
 	action_types,
 	prelude::*,
-	types::{serialize_timestamp_iso, serialize_timestamp_iso_opt},
+	types::{TableDump, serialize_timestamp_iso, serialize_timestamp_iso_opt},
 };
 
 pub const ACCESS_TOKEN_EXPIRY: i64 = 3600;
@@ -343,6 +343,16 @@ pub trait AuthAdapter: Debug + Send + Sync {
 	/// Deletes a tenant
 	async fn delete_tenant(&self, id_tag: &str) -> ClResult<()>;
 
+	/// Dump the tenant's rows — login, profile signing keys, certificate,
+	/// passkeys, API keys — for a tenant backup.
+	async fn export_tenant(&self, tn_id: TnId) -> ClResult<Vec<TableDump>>;
+
+	/// Load a dump made by [`AuthAdapter::export_tenant`] as a new tenant, and
+	/// return the `TnId` it was given here.
+	///
+	/// All or nothing. `Error::Conflict` if the dump's ID tag is already taken.
+	async fn import_tenant(&self, tables: &[TableDump]) -> ClResult<TnId>;
+
 	/// Lists all tenants (for admin use)
 	async fn list_tenants(&self, opts: &ListTenantsOptions<'_>) -> ClResult<Vec<TenantListItem>>;
 
@@ -53,6 +53,7 @@ pub enum Error {
 	FileSourceForbidden,   // 403: Caller has no READ on the source
 	FileSourceUnreachable, // 503: Source server unreachable during synchronous resolve
 	FileCycleRejected,     // 400: sourceFileId is itself a cross-context row
+	QuotaExceeded,         // 507: the tenant's storage quota has no room for the upload
 
 	// externals
 	Io(std::io::Error),
@@ -219,6 +220,11 @@ impl IntoResponse for Error {
 				"E-FILE-CYCLEREJ".to_string(),
 				"cycle_rejected".to_string(),
 			),
+			Error::QuotaExceeded => (
+				StatusCode::INSUFFICIENT_STORAGE,
+				"E-FILE-QUOTA".to_string(),
+				"Storage quota exceeded".to_string(),
+			),
 		};
 
 		let error_response = ErrorResponse::new(code, message);
@@ -0,0 +1,144 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Hashtag parsing shared between the action pipeline (which indexes the
+//! hashtags of POST and CMNT content as it is stored) and the meta adapter
+//! (which backfills the `action_hashtags` index on upgrade).
+//!
+//! A hashtag is `#` followed by letters, digits and `_`, with at least one
+//! letter, at most [`MAX_HASHTAG_CHARS`] characters long. The `#` must start
+//! the text or follow a character that cannot be part of a word, so URL
+//! fragments (`/page#top`), HTML entities (`&#39;`) and `a#b` are not tags.
+//! Tags are stored and compared lowercased and without the `#`.
+
+/// Action types whose content is scanned for hashtags.
+pub const HASHTAG_ACTION_TYPES: [&str; 2] = ["POST", "CMNT"];
+
+/// Longest hashtag, in characters (without the `#`).
+pub const MAX_HASHTAG_CHARS: usize = 64;
+
+/// Most hashtags indexed per action. Anything past this is tag spam, not topic.
+pub const MAX_HASHTAGS: usize = 30;
+
+fn is_tag_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+/// Normalize a hashtag given with or without its `#`: lowercased, or `None` if it
+/// is not a valid tag.
+pub fn normalize_hashtag(tag: &str) -> Option<String> {
+	let tag = tag.strip_prefix('#').unwrap_or(tag);
+	let len = tag.chars().count();
+	if len == 0 || len > MAX_HASHTAG_CHARS || !tag.chars().all(is_tag_char) {
+		return None;
+	}
+	// `#1` and `#2024` are numbering, not topics.
+	if !tag.chars().any(char::is_alphabetic) {
+		return None;
+	}
+	Some(tag.to_lowercase())
+}
+
+/// Extract the distinct hashtags of a text, normalized, in order of first use.
+pub fn extract_hashtags(text: &str) -> Vec<String> {
+	let mut tags = Vec::new();
+	push_hashtags(text, &mut tags);
+	tags
+}
+
+/// Extract the distinct hashtags of every string in an action's JSON content,
+/// whether the content is a plain string or an object or array holding strings.
+pub fn content_hashtags(content: &serde_json::Value) -> Vec<String> {
+	fn walk(value: &serde_json::Value, tags: &mut Vec<String>) {
+		match value {
+			serde_json::Value::String(s) => push_hashtags(s, tags),
+			serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, tags)),
+			serde_json::Value::Object(map) => map.values().for_each(|v| walk(v, tags)),
+			_ => {}
+		}
+	}
+	let mut tags = Vec::new();
+	walk(content, &mut tags);
+	tags
+}
+
+/// Hashtags of an action's stored content (the JSON-encoded `actions.content`
+/// column). Content that is not JSON is scanned as plain text.
+pub fn stored_content_hashtags(content: &str) -> Vec<String> {
+	match serde_json::from_str::<serde_json::Value>(content) {
+		Ok(value) => content_hashtags(&value),
+		Err(_) => extract_hashtags(content),
+	}
+}
+
+fn push_hashtags(text: &str, tags: &mut Vec<String>) {
+	let mut prev: Option<char> = None;
+	let mut chars = text.char_indices().peekable();
+	while let Some((start, c)) = chars.next() {
+		let at_boundary = prev.is_none_or(|p| !is_tag_char(p) && !matches!(p, '#' | '&' | '/'));
+		prev = Some(c);
+		if c != '#' || !at_boundary {
+			continue;
+		}
+		let body_start = start + c.len_utf8();
+		let mut end = body_start;
+		while let Some(&(i, next)) = chars.peek() {
+			if !is_tag_char(next) {
+				break;
+			}
+			end = i + next.len_utf8();
+			prev = Some(next);
+			chars.next();
+		}
+		if tags.len() >= MAX_HASHTAGS {
+			return;
+		}
+		if let Some(tag) = normalize_hashtag(&text[body_start..end])
+			&& !tags.contains(&tag)
+		{
+			tags.push(tag);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normalize() {
+		assert_eq!(normalize_hashtag("#Rust").as_deref(), Some("rust"));
+		assert_eq!(normalize_hashtag("Ünnep_2024").as_deref(), Some("ünnep_2024"));
+		assert_eq!(normalize_hashtag("2024"), None);
+		assert_eq!(normalize_hashtag("#"), None);
+		assert_eq!(normalize_hashtag("two words"), None);
+		assert_eq!(normalize_hashtag(&"a".repeat(MAX_HASHTAG_CHARS + 1)), None);
+	}
+
+	#[test]
+	fn extract_from_text() {
+		let text =
+			"#Rust and #rust, (#WebDev) #2024 see https://x.example/page#top &#39; a#b ##nope";
+		assert_eq!(extract_hashtags(text), ["rust", "webdev"]);
+		assert_eq!(extract_hashtags("café #Ünnep."), ["ünnep"]);
+	}
+
+	#[test]
+	fn extract_from_content() {
+		let content = serde_json::json!({ "question": "Best #editor?", "options": ["#vim", "#Emacs", "#vim"] });
+		// Object key order depends on serde_json's `preserve_order` feature.
+		let mut tags = content_hashtags(&content);
+		tags.sort();
+		assert_eq!(tags, ["editor", "emacs", "vim"]);
+		assert_eq!(stored_content_hashtags("\"plain #post\""), ["post"]);
+		assert_eq!(stored_content_hashtags("not json #still"), ["still"]);
+	}
+
+	#[test]
+	fn extract_is_capped() {
+		let text = (0..MAX_HASHTAGS + 5).map(|i| format!("#tag{i}")).collect::<Vec<_>>().join(" ");
+		assert_eq!(extract_hashtags(&text).len(), MAX_HASHTAGS);
+	}
+}
+
+// vim: ts=4
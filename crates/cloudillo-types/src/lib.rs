@@ -17,7 +17,9 @@ pub mod crdt_adapter;
 pub mod error;
 pub mod extract;
 pub mod hasher;
+pub mod hashtags;
 pub mod identity_provider_adapter;
+pub mod mentions;
 pub mod meta_adapter;
 pub mod prelude;
 pub mod reactions;
@@ -0,0 +1,146 @@
+// SPDX-FileCopyrightText: Szilárd Hajba
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Mention parsing shared between the action pipeline (which indexes the
+//! mentions of POST and CMNT content as it is stored, delivers the action to the
+//! mentioned profiles and accepts a stranger's action that mentions the tenant)
+//! and the meta adapter (which backfills the `action_mentions` index on upgrade).
+//!
+//! A mention is `@` followed by an id_tag — a domain name such as
+//! `@alice.example.com`. The `@` must start the text or follow a character that
+//! cannot be part of a name, so e-mail addresses (`bob@example.com`) and
+//! `@@x.example` are not mentions. A trailing `.` or `-` ends the sentence, not
+//! the name. Mentions are stored canonical (see
+//! [`canonicalize_id_tag`](crate::validation::canonicalize_id_tag)) and
+//! without the `@`.
+
+use crate::validation::canonicalize_id_tag;
+
+/// Action types whose content is scanned for mentions.
+pub const MENTION_ACTION_TYPES: [&str; 2] = ["POST", "CMNT"];
+
+/// Most mentions honoured per action. Each one may cost a federated delivery, so
+/// anything past this is ignored rather than fanned out.
+pub const MAX_MENTIONS: usize = 20;
+
+fn is_name_char(c: char) -> bool {
+	c.is_alphanumeric() || matches!(c, '.' | '-')
+}
+
+/// Normalize a mention given with or without its `@`: the canonical id_tag, or
+/// `None` if it is not a domain-shaped id_tag.
+pub fn normalize_mention(mention: &str) -> Option<String> {
+	let id_tag = mention.strip_prefix('@').unwrap_or(mention);
+	if !id_tag.contains('.') || !id_tag.chars().all(is_name_char) {
+		return None;
+	}
+	canonicalize_id_tag(id_tag).ok().map(std::borrow::Cow::into_owned)
+}
+
+/// Extract the distinct mentions of a text, normalized, in order of first use.
+pub fn extract_mentions(text: &str) -> Vec<String> {
+	let mut mentions = Vec::new();
+	push_mentions(text, &mut mentions);
+	mentions
+}
+
+/// Extract the distinct mentions of an action's JSON content, searching every
+/// string it holds rather than only a top-level one.
+pub fn content_mentions(content: &serde_json::Value) -> Vec<String> {
+	fn walk(value: &serde_json::Value, mentions: &mut Vec<String>) {
+		match value {
+			serde_json::Value::String(s) => push_mentions(s, mentions),
+			serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, mentions)),
+			serde_json::Value::Object(map) => map.values().for_each(|v| walk(v, mentions)),
+			_ => {}
+		}
+	}
+	let mut mentions = Vec::new();
+	walk(content, &mut mentions);
+	mentions
+}
+
+/// Mentions of an action's stored content (the JSON-encoded `actions.content`
+/// column). Content that is not JSON is scanned as plain text.
+pub fn stored_content_mentions(content: &str) -> Vec<String> {
+	match serde_json::from_str::<serde_json::Value>(content) {
+		Ok(value) => content_mentions(&value),
+		Err(_) => extract_mentions(content),
+	}
+}
+
+fn push_mentions(text: &str, mentions: &mut Vec<String>) {
+	let mut prev: Option<char> = None;
+	let mut chars = text.char_indices().peekable();
+	while let Some((start, c)) = chars.next() {
+		let at_boundary = prev.is_none_or(|p| !is_name_char(p) && !matches!(p, '@' | '/' | '_'));
+		prev = Some(c);
+		if c != '@' || !at_boundary {
+			continue;
+		}
+		let body_start = start + c.len_utf8();
+		let mut end = body_start;
+		while let Some(&(i, next)) = chars.peek() {
+			if !is_name_char(next) {
+				break;
+			}
+			end = i + next.len_utf8();
+			prev = Some(next);
+			chars.next();
+		}
+		if mentions.len() >= MAX_MENTIONS {
+			return;
+		}
+		let body = text[body_start..end].trim_end_matches(['.', '-']);
+		if let Some(mention) = normalize_mention(body)
+			&& !mentions.contains(&mention)
+		{
+			mentions.push(mention);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normalize() {
+		assert_eq!(normalize_mention("@Alice.Example.com").as_deref(), Some("alice.example.com"));
+		assert_eq!(normalize_mention("bob.example").as_deref(), Some("bob.example"));
+		assert_eq!(normalize_mention("@alice"), None);
+		assert_eq!(normalize_mention("@"), None);
+		assert_eq!(normalize_mention("a_b.example"), None);
+	}
+
+	#[test]
+	fn extract_from_text() {
+		let text = "Hi @Alice.example.com and @alice.example.com, (@bob.example) \
+			mail me at carol@dave.example, @@eve.example @frank or @grace.example.";
+		assert_eq!(extract_mentions(text), ["alice.example.com", "bob.example", "grace.example"]);
+		assert_eq!(extract_mentions("see https://x.example/@heidi.example"), Vec::<String>::new());
+	}
+
+	#[test]
+	fn extract_from_content() {
+		let content =
+			serde_json::json!({ "question": "Ask @bob.example?", "options": ["@carol.example"] });
+		// Object key order depends on serde_json's `preserve_order` feature.
+		let mut mentions = content_mentions(&content);
+		mentions.sort();
+		assert_eq!(mentions, ["bob.example", "carol.example"]);
+		assert_eq!(stored_content_mentions("\"cc @dave.example\""), ["dave.example"]);
+		assert_eq!(stored_content_mentions("not json @eve.example"), ["eve.example"]);
+	}
+
+	#[test]
+	fn extract_is_capped() {
+		let text = (0..MAX_MENTIONS + 5)
+			.map(|i| format!("@u{i}.example"))
+			.collect::<Vec<_>>()
+			.join(" ");
+		assert_eq!(extract_mentions(&text).len(), MAX_MENTIONS);
+	}
+}
+
+// vim: ts=4
@@ -11,6 +11,13 @@ pub const TRASH_PARENT_ID: &str = "__trash__";
 /// reaped by the file GC when no canonical column still references them.
 pub const MANAGED_PARENT_ID: &str = "__managed__";
 
+/// Special parent_id value for the retained revisions of a versioned BLOB file.
+/// Every row of a version chain carries the chain's logical id in `version_of`;
+/// the current revision sits in a real folder, the earlier ones here. Nothing in
+/// this folder is listed or indexed, and the file GC leaves it alone: a revision
+/// leaves it only by being restored, or by being pruned into the managed folder.
+pub const VERSIONS_PARENT_ID: &str = "__versions__";
+
 /// Sentinel parent_id value representing the root (files with no parent folder).
 /// API input/filter only — never appears in DB rows; root rows have
 /// `parent_id = NULL` in the `files` table. Use this constant only on the API
@@ -28,7 +35,7 @@ use std::{
 
 use crate::{
 	prelude::*,
-	types::{serialize_timestamp_iso, serialize_timestamp_iso_opt},
+	types::{TableDump, serialize_timestamp_iso, serialize_timestamp_iso_opt},
 };
 
 // Tenants, profiles
@@ -144,6 +151,13 @@ pub struct RefData {
 	pub access_level: Option<char>,
 	/// Launch params as serialized query string (e.g., "mode=present")
 	pub params: Option<Box<str>>,
+	/// Share link mode: `'V'` = view only (previews, never the original), `'U'` = upload only
+	/// (file drop into a folder). `None` = the link works at `access_level`.
+	pub link_mode: Option<char>,
+	/// Redeeming the link needs a password — see [`MetaAdapter::check_ref_password`]. The
+	/// hash itself never leaves the adapter.
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub password_protected: bool,
 }
 
 pub struct ListRefsOptions {
@@ -166,13 +180,17 @@ pub struct CreateRefOptions {
 	pub access_level: Option<char>,
 	/// Launch params as serialized query string (e.g., "mode=present")
 	pub params: Option<String>,
+	/// Share link mode: `'V'` (view only) or `'U'` (upload only); see [`RefData::link_mode`]
+	pub link_mode: Option<char>,
+	/// Link password in plain text; the adapter stores only its hash
+	pub password: Option<String>,
 }
 
 /// Options for updating an existing reference via PATCH semantics.
 ///
 /// Each field uses `Patch<T>`: `Undefined` leaves the column unchanged,
-/// `Null` clears it, `Value(v)` sets it. `type`, `resource_id`, and
-/// `params` are intentionally immutable post-create.
+/// `Null` clears it, `Value(v)` sets it. `type`, `resource_id`, `params`
+/// and `link_mode` are intentionally immutable post-create.
 #[derive(Debug, Default)]
 pub struct UpdateRefOptions {
 	pub description: Patch<String>,
@@ -182,6 +200,8 @@ pub struct UpdateRefOptions {
 	pub count: Patch<u32>,
 	/// `Value('R'|'C'|'W')`.
 	pub access_level: Patch<char>,
+	/// Link password in plain text, hashed by the adapter. `Null` removes the password.
+	pub password: Patch<String>,
 }
 
 #[skip_serializing_none]
@@ -472,6 +492,12 @@ pub struct ActionData {
 	/// the counter-update exclusivity invariant in
 	/// `cloudillo_action::native_hooks::ownership`).
 	pub stat_at: Option<Timestamp>,
+	/// Poll tally (JSON) of a POLL action, federated as STAT `v`. `None` for
+	/// every other type.
+	pub votes: Option<Box<str>>,
+	/// RSVP tally (JSON) of an EVENT action, federated as STAT `rv`. `None` for
+	/// every other type.
+	pub rsvps: Option<Box<str>>,
 }
 
 /// Options for updating action metadata
@@ -484,6 +510,10 @@ pub struct UpdateActionDataOptions {
 	/// Last-comment timestamp (epoch seconds), federated as STAT `ct`.
 	pub comments_ts: Patch<Timestamp>,
 	pub reposts: Patch<u32>,
+	/// Poll tally (JSON), federated as STAT `v`.
+	pub votes: Patch<String>,
+	/// RSVP tally (JSON), federated as STAT `rv`.
+	pub rsvps: Patch<String>,
 	/// Watermark for inbound STAT mirror updates — see [`ActionData::stat_at`].
 	pub stat_at: Patch<Timestamp>,
 	pub status: Patch<char>,
@@ -553,6 +583,9 @@ pub enum AudienceType {
 #[derive(Debug, Clone, Copy)]
 pub enum ActionCountGroupBy {
 	SubType,
+	/// The raw `content` JSON — groups identical ballots (VOTE) so a tally is
+	/// one query per poll rather than a row scan.
+	Content,
 }
 
 /// Options for listing actions
@@ -574,6 +607,7 @@ pub struct ListActionOptions {
 	pub typ: Option<Vec<String>>,
 	#[serde(default, deserialize_with = "deserialize_split")]
 	pub status: Option<Vec<String>>,
+	/// A hashtag, with or without its `#`, matched against the hashtag index.
 	pub tag: Option<String>,
 	pub search: Option<String>,
 	#[serde(default, deserialize_with = "deserialize_split")]
@@ -626,9 +660,19 @@ pub struct ListActionOptions {
 	/// Exclude actions whose *effective audience* (coalesce(audience, issuer_tag))
 	/// is in this set. Server-set, not from query params. Used by the home feed
 	/// to drop posts addressed to communities the reader opted out of home
-	/// (`profiles.hidden_in_home = 1`).
+	/// (`profiles.hidden_in_home = 1`). A public post carrying a hashtag the
+	/// reader follows is kept even when its audience is excluded.
 	#[serde(skip)]
 	pub exclude_audiences: Option<Box<[String]>>,
+	/// When true, return only public actions carrying a hashtag the tenant
+	/// follows, issued by profiles the tenant follows: the followed-hashtags feed.
+	#[serde(rename = "followedHashtags")]
+	pub followed_hashtags: Option<bool>,
+	/// When true, return only actions issued by profiles the tenant follows or is
+	/// connected to. Server-set: the ranked feed takes these as a candidate set of
+	/// their own, so a burst of newer posts from others cannot crowd them out.
+	#[serde(skip)]
+	pub related_issuers: Option<bool>,
 	/// When true, exclude actions issued by the requesting tenant (issuer == viewer).
 	/// Requires an authenticated request (viewer_id_tag set by the handler).
 	#[serde(rename = "excludeOwnIssuer")]
@@ -715,6 +759,10 @@ pub struct ActionView {
 	/// the recursive type sized.
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub subject_action: Option<Box<ActionView>>,
+	/// The id_tags the content mentions (`@alice.example.com`), as indexed when
+	/// the action was stored or last edited. `None` when it mentions nobody.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub mentions: Option<Vec<Box<str>>>,
 	#[serde(serialize_with = "serialize_timestamp_iso")]
 	pub created_at: Timestamp,
 	/// LOCAL ingestion time (when this action was inserted on this node), emitted
@@ -727,6 +775,14 @@ pub struct ActionView {
 		skip_serializing_if = "Option::is_none"
 	)]
 	pub received_at: Option<Timestamp>,
+	/// When the displayed content was last replaced by an `EDIT` action, emitted as
+	/// `editedAt`. `None` for an action that was never edited. The earlier contents are
+	/// kept as [`ActionRevision`]s.
+	#[serde(
+		serialize_with = "serialize_timestamp_iso_opt",
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub edited_at: Option<Timestamp>,
 	#[serde(serialize_with = "serialize_timestamp_iso_opt")]
 	pub expires_at: Option<Timestamp>,
 	pub status: Option<Box<str>>,
@@ -737,12 +793,39 @@ pub struct ActionView {
 	#[serde(rename = "subLevel", skip_serializing_if = "Option::is_none")]
 	pub sub_level: Option<Box<str>>,
 	pub x: Option<serde_json::Value>, // Extensible metadata (x.role for SUBS, etc.)
+	/// The reader's `warn` content filter this action matched, set by the listing
+	/// path when the tenant owner reads their own feeds.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub filtered: Option<ContentFilterMatch>,
 	/// Raw signed JWS for this action, populated only when the list query sets
 	/// `includeTokens=true`. Lets clients verify action signatures locally.
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub token: Option<Box<str>>,
 }
 
+/// One content of an edited action: the original or the content an `EDIT` set.
+///
+/// `edit_id` names the action that produced the content — the `EDIT` action, or the
+/// edited action itself for the original. `edited_at` is that action's `created_at`, so
+/// revisions order by the issuer's clock rather than by arrival, and the newest one is
+/// the action's current content.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionRevision {
+	pub edit_id: Box<str>,
+	pub content: Option<serde_json::Value>,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub edited_at: Timestamp,
+}
+
+/// How often the reader reacted to, commented on or reposted one issuer's actions.
+#[derive(Debug, Clone)]
+pub struct InteractionCount {
+	pub id_tag: Box<str>,
+	pub interactions: u32,
+}
+
 // Files
 //*******
 #[derive(Debug)]
@@ -810,6 +893,10 @@ pub struct FileView {
 	/// `search_docs.owner_tag` — need the raw value.
 	#[serde(skip)]
 	pub owner_tag: Option<Box<str>>,
+	/// Logical id of the version chain this file belongs to — the `file_id` of the
+	/// chain's first revision. `None` for a file that never had a second revision.
+	#[serde(default)]
+	pub version_of: Option<Box<str>>,
 	#[serde(default)]
 	pub creator: Option<ProfileInfo>,
 	#[serde(default)]
@@ -1074,6 +1161,10 @@ pub struct CreateFile {
 	/// with `parent_id = MANAGED_PARENT_ID` so the file GC can reap them.
 	pub hidden: bool,
 	pub status: Option<FileStatus>, // None defaults to Pending, can set to Active for shared files
+	/// Logical id of the version chain a new revision joins. Set together with
+	/// `parent_id = VERSIONS_PARENT_ID`; the revision becomes current once its
+	/// content id is generated.
+	pub version_of: Option<Box<str>>,
 }
 
 /// Options for updating file metadata
@@ -1157,6 +1248,43 @@ pub struct DeleteFileResult {
 	pub share_entries_removed: u64,
 }
 
+/// One revision of a version chain, from [`MetaAdapter::list_file_versions`].
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileVersion {
+	pub file_id: Box<str>,
+	pub file_name: Box<str>,
+	pub content_type: Option<Box<str>>,
+	pub creator_tag: Option<Box<str>>,
+	/// Size of the largest stored rendition, in bytes.
+	pub size: Option<u64>,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+	/// Whether this is the chain's current revision.
+	pub current: bool,
+}
+
+/// Blob storage a tenant holds, in bytes, from [`MetaAdapter::read_storage_usage`].
+///
+/// Counts each blob in the tenant's own store once, however many files or variants
+/// reference it. Blobs in the shared `TnId(0)` store are not the tenant's, and a
+/// deleted file stops counting when it is tombstoned — not when the GC later
+/// reclaims its blobs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+	pub total: u64,
+	/// Of `total`: uploaded originals.
+	pub originals: u64,
+	/// Of `total`: derived variants (thumbnails, transcodes).
+	pub variants: u64,
+	/// Of `total`: blobs only files in the trash reference.
+	pub trash: u64,
+	/// Number of distinct blobs.
+	pub blobs: u64,
+}
+
 // Share Entries
 //**************
 
@@ -1243,6 +1371,247 @@ pub struct PushSubscription {
 	pub created_at: Timestamp,
 }
 
+// Moderation
+//************
+
+/// One decision in a community's moderation audit trail.
+///
+/// `decision` is what the moderator did — `dismiss`, `hide`, `restore`, `remove`,
+/// `warn`, `mute` or `ban`. `subject` names the moderated action, `target_id_tag` its
+/// author, and `reports` the REPORT actions the decision closed. Entries are only ever
+/// appended.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationEntry {
+	pub entry_id: u64,
+	pub decision: Box<str>,
+	pub subject: Option<Box<str>>,
+	pub target_id_tag: Box<str>,
+	pub moderator_id_tag: Box<str>,
+	pub reports: Vec<Box<str>>,
+	pub note: Option<Box<str>>,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+}
+
+/// A decision to append to the moderation audit trail; see [`ModerationEntry`].
+#[derive(Debug)]
+pub struct CreateModerationEntry<'a> {
+	pub decision: &'a str,
+	pub subject: Option<&'a str>,
+	pub target_id_tag: &'a str,
+	pub moderator_id_tag: &'a str,
+	pub reports: &'a [Box<str>],
+	pub note: Option<&'a str>,
+}
+
+/// Filters for listing the moderation audit trail, newest first.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListModerationLogOptions {
+	/// Only decisions on this action.
+	pub subject: Option<String>,
+	/// Only decisions about this author.
+	#[serde(rename = "idTag")]
+	pub target_id_tag: Option<String>,
+	/// Only entries older than this `entryId` — the cursor for the next page.
+	pub before: Option<u64>,
+	/// Maximum number of entries to return (default: 50, at most 200)
+	pub limit: Option<u32>,
+}
+
+// Content filters
+//*****************
+
+/// What a content filter matches against.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilterKind {
+	/// A word or phrase in the text, case-insensitive, on word boundaries.
+	Keyword,
+	/// A regular expression over the text, case-insensitive.
+	Regex,
+	/// A `#hashtag` in the text; the pattern is the tag without the `#`.
+	Hashtag,
+	/// Everything issued from a domain or any of its subdomains.
+	Instance,
+}
+
+/// What happens to an action a content filter matches.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilterMode {
+	/// Listed with a [`ContentFilterMatch`] so the client can put it behind a warning.
+	Warn,
+	/// Stored, but left out of the owner's feeds.
+	Hide,
+	/// Discarded on arrival and never stored.
+	Drop,
+}
+
+/// A filter the tenant set on what reaches its feeds. A filter past its
+/// `expires_at` no longer applies and is not listed.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentFilter {
+	pub filter_id: u64,
+	pub kind: ContentFilterKind,
+	pub pattern: Box<str>,
+	pub mode: ContentFilterMode,
+	#[serde(serialize_with = "serialize_timestamp_iso_opt")]
+	pub expires_at: Option<Timestamp>,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+}
+
+/// Body of `POST /api/filters`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateContentFilter {
+	pub kind: ContentFilterKind,
+	pub pattern: String,
+	pub mode: ContentFilterMode,
+	pub expires_at: Option<Timestamp>,
+}
+
+/// Options for updating a content filter via PATCH semantics. `kind` is
+/// immutable: a different kind is a different filter.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateContentFilterOptions {
+	#[serde(default)]
+	pub pattern: Patch<String>,
+	#[serde(default)]
+	pub mode: Patch<ContentFilterMode>,
+	/// `Null` makes the filter permanent.
+	#[serde(default)]
+	pub expires_at: Patch<Timestamp>,
+}
+
+/// The `warn` filter an action matched, attached to it in the owner's listings.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentFilterMatch {
+	pub filter_id: u64,
+	pub pattern: Box<str>,
+}
+
+// Hashtags
+//**********
+
+/// How often a hashtag was used over a time range: `uses` counts actions,
+/// `accounts` the distinct issuers among them.
+#[derive(Debug, Clone)]
+pub struct HashtagCount {
+	pub tag: Box<str>,
+	pub uses: u32,
+	pub accounts: u32,
+}
+
+/// A hashtag trending over a window, as last computed by the trending task.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendingHashtag {
+	pub tag: Box<str>,
+	pub uses: u32,
+	pub accounts: u32,
+	pub score: f64,
+}
+
+/// A hashtag the tenant follows.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FollowedHashtag {
+	pub tag: Box<str>,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+}
+
+// Collections
+//*************
+
+/// What a saved item refers to. Stored as the search index's object type
+/// (`'A'`, `'F'`, `'P'`), so a collection can narrow a search.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedItemType {
+	Action,
+	File,
+	Profile,
+}
+
+/// A private, named list of saved items, owned by one user of the tenant.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+	pub collection_id: u64,
+	pub name: Box<str>,
+	pub description: Option<Box<str>>,
+	pub item_count: u32,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub updated_at: Timestamp,
+}
+
+/// Body of `POST /api/collections`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateCollection {
+	pub name: String,
+	pub description: Option<String>,
+}
+
+/// Options for updating a collection via PATCH semantics.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateCollectionOptions {
+	#[serde(default)]
+	pub name: Patch<String>,
+	#[serde(default)]
+	pub description: Patch<String>,
+}
+
+/// An item in a collection. `position` orders the collection, ascending.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedItem {
+	pub item_type: SavedItemType,
+	pub item_id: Box<str>,
+	pub note: Option<Box<str>>,
+	pub position: i64,
+	#[serde(serialize_with = "serialize_timestamp_iso")]
+	pub created_at: Timestamp,
+}
+
+/// Body of `POST /api/collections/{collection_id}/items`. Saving an item the
+/// collection already holds updates its note (and position, if given).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SaveItem {
+	pub item_type: SavedItemType,
+	pub item_id: String,
+	pub note: Option<String>,
+	/// Where to insert the item; the items at and after it move down. Appended
+	/// when absent.
+	pub position: Option<i64>,
+}
+
+/// Options for updating a saved item via PATCH semantics. A new `position` moves
+/// the item there, and the items at and after it down.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateSavedItemOptions {
+	#[serde(default)]
+	pub note: Patch<String>,
+	#[serde(default)]
+	pub position: Patch<i64>,
+}
+
 // Tasks
 //*******
 pub struct Task {
@@ -1377,6 +1746,9 @@ pub struct SearchOptions {
 	pub obj_tp: Option<Vec<char>>,
 	/// Restrict to one container document (its own row plus its parts).
 	pub file_id: Option<String>,
+	/// Restrict to the items of one collection (a saved file with its parts).
+	/// The handler checks the collection is the caller's before setting it.
+	pub collection_id: Option<u64>,
 	pub content_type: Option<Vec<String>>,
 	/// AND-combined tag filter, applied inside the FTS match rather than after
 	/// it — filtering the top-`limit` rows afterwards would silently drop a
@@ -1494,6 +1866,10 @@ pub struct DocFormat {
 	/// The FTS index manifest.
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub search: Option<serde_json::Value>,
+	/// RTDB triggers run against every database of this content type. Parsed by
+	/// `cloudillo_action::dsl::trigger`; opaque here.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub triggers: Option<serde_json::Value>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub x: Option<serde_json::Value>,
 	#[serde(serialize_with = "serialize_timestamp_iso")]
@@ -1510,6 +1886,7 @@ pub struct UpsertDocFormat<'a> {
 	pub store_tp: Option<&'a str>,
 	pub nav_param: Option<&'a str>,
 	pub search: Option<&'a serde_json::Value>,
+	pub triggers: Option<&'a serde_json::Value>,
 	pub x: Option<&'a serde_json::Value>,
 }
 
@@ -1876,6 +2253,20 @@ pub trait MetaAdapter: Debug + Send + Sync {
 	/// Deletes a tenant
 	async fn delete_tenant(&self, tn_id: TnId) -> ClResult<()>;
 
+	/// Dump every row the tenant owns, table by table, for a tenant backup.
+	///
+	/// State the server rebuilds or re-fetches on its own is left out: the search
+	/// index, the key cache and scheduler tasks.
+	async fn export_tenant(&self, tn_id: TnId) -> ClResult<Vec<TableDump>>;
+
+	/// Load a dump made by [`MetaAdapter::export_tenant`] as tenant `tn_id`, which
+	/// may differ from the tenant it was taken from.
+	///
+	/// All or nothing. `Error::Conflict` if the tenant already exists here,
+	/// `Error::ValidationError` if the dump names a table or column this adapter
+	/// does not have.
+	async fn import_tenant(&self, tn_id: TnId, tables: &[TableDump]) -> ClResult<()>;
+
 	/// Lists all tenants (for admin use)
 	async fn list_tenants(&self, opts: &ListTenantsMetaOptions) -> ClResult<Vec<TenantListMeta>>;
 
@@ -2019,6 +2410,7 @@ pub trait MetaAdapter: Debug + Send + Sync {
 	///   - `"feed"`   → `profiles.feed_read_at` for `id_tag = key`
 	///   - `"msg"`    → `profiles.msg_read_at`  for `id_tag = key`
 	///   - `"thread"` → `actions.comments_read_at` for `action_id = key`
+	///   - `"seen"`   → `actions.seen_at` for `action_id = key` (the ranked feed)
 	/// Unknown scope → bad-request error.
 	async fn set_read_marker(
 		&self,
@@ -2028,6 +2420,16 @@ pub trait MetaAdapter: Debug + Send + Sync {
 		position: i64,
 	) -> ClResult<()>;
 
+	/// Count `reader_tag`'s active REACT / CMNT / REPOST actions created since
+	/// `since`, grouped by the issuer of the action each one answers (its `subject`,
+	/// else its `parent_id`). Answers to the reader's own actions are not counted.
+	async fn count_interactions(
+		&self,
+		tn_id: TnId,
+		reader_tag: &str,
+		since: Timestamp,
+	) -> ClResult<Vec<InteractionCount>>;
+
 	/// Auto-subscribe at Tracking: set `sub_level='T'` only when it is currently
 	/// NULL (never downgrade an existing Watching). No-op if the row is absent.
 	/// (Manual W/T/M changes go through `update_action_data`'s `sub_level` patch.)
@@ -2080,6 +2482,32 @@ pub trait MetaAdapter: Debug + Send + Sync {
 		opts: &UpdateActionDataOptions,
 	) -> ClResult<()>;
 
+	/// Replace `action_id`'s content with the content of the `EDIT` action `edit_id`,
+	/// created at `edited_at`. Identity, stats and children of the action are untouched.
+	///
+	/// Every content is kept as an [`ActionRevision`]; the first edit also records the
+	/// original. Edits federate independently and can arrive out of order, so the content
+	/// is only replaced when `edited_at` is newer than the current one — a late, older
+	/// edit still lands in the history. Replaying an edit already recorded is a no-op.
+	///
+	/// Returns whether the displayed content changed.
+	async fn apply_action_edit(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		edit_id: &str,
+		content: Option<&str>,
+		edited_at: Timestamp,
+	) -> ClResult<bool>;
+
+	/// Revisions of an edited action, newest (the current content) first. Empty for an
+	/// action that was never edited.
+	async fn list_action_revisions(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+	) -> ClResult<Vec<ActionRevision>>;
+
 	/// Get related action tokens by APRV action_id
 	/// Returns list of (action_id, token) pairs for actions that have ack = aprv_action_id
 	async fn get_related_action_tokens(
@@ -2104,6 +2532,8 @@ pub trait MetaAdapter: Debug + Send + Sync {
 	/// `global=1` variant rows across tenants; for other tenants returns only
 	/// the variants whose `global=0` (i.e., stored locally, not in shared).
 	async fn list_referenced_variant_ids(&self, tn_id: TnId) -> ClResult<Vec<Box<str>>>;
+	/// The tenant's blob storage usage, for quota accounting. See [`StorageUsage`].
+	async fn read_storage_usage(&self, tn_id: TnId) -> ClResult<StorageUsage>;
 	/// Targeted recheck for the blob GC: is there *currently* a `file_variants`
 	/// row that expects this blob to live in `tn_id`'s blob store? For
 	/// `TnId(0)` matches any `global=1` row; for other tenants matches a
@@ -2176,6 +2606,34 @@ pub trait MetaAdapter: Debug + Send + Sync {
 	/// row is gone. `None` for an unfinalized upload, which never had one.
 	async fn hard_delete_file(&self, tn_id: TnId, f_id: u64) -> ClResult<Option<Box<str>>>;
 
+	/// Make the revision `file_id` the current one of its version chain.
+	///
+	/// One transaction: the revision takes the current one's folder, visibility and
+	/// tags, the current one moves into [`VERSIONS_PARENT_ID`], and every share —
+	/// [`SHARE_FILE_REF_TYPE`] links and `share_entries` on both sides — is re-pointed
+	/// from the old content id to the new one. The first promotion of a chain also
+	/// stamps its first revision's `version_of`, which until then was NULL.
+	///
+	/// Returns the content id that stopped being current, or `None` if `file_id`
+	/// already was. A file outside any chain is a [`Error::ValidationError`].
+	async fn promote_file_version(&self, tn_id: TnId, file_id: &str) -> ClResult<Option<Box<str>>>;
+
+	/// The finalized revisions of the chain `version_of`, newest first, the current
+	/// one included. Pruned revisions are not listed. For a file that never had a
+	/// second revision, `version_of` is its own id and the list holds just it.
+	async fn list_file_versions(&self, tn_id: TnId, version_of: &str)
+	-> ClResult<Vec<FileVersion>>;
+
+	/// Prune all but the newest `keep` retained revisions of the chain `version_of`
+	/// by moving them into [`MANAGED_PARENT_ID`], where the file GC reaps them once
+	/// nothing references them. Returns the pruned content ids.
+	async fn prune_file_versions(
+		&self,
+		tn_id: TnId,
+		version_of: &str,
+		keep: u32,
+	) -> ClResult<Vec<Box<str>>>;
+
 	// Task scheduler
 	//****************
 	async fn list_tasks(&self, opts: ListTaskOptions) -> ClResult<Vec<Task>>;
@@ -2297,6 +2755,12 @@ pub trait MetaAdapter: Debug + Send + Sync {
 		expected_types: &[&str],
 	) -> ClResult<(TnId, Box<str>, RefData)>;
 
+	/// Check the password of a password-protected reference (global lookup, like `use_ref`).
+	///
+	/// `Ok(())` when the password matches, or the ref has none. `PermissionDenied` on a
+	/// mismatch and for an unknown ref alike, so a guess learns nothing about which refs exist.
+	async fn check_ref_password(&self, ref_id: &str, password: &str) -> ClResult<()>;
+
 	// Tag Management
 	//***************
 	/// List all tags for a tenant
@@ -2402,6 +2866,188 @@ pub trait MetaAdapter: Debug + Send + Sync {
 	/// (e.g., 410 Gone response from push service) or when user unsubscribes.
 	async fn delete_push_subscription(&self, tn_id: TnId, subscription_id: u64) -> ClResult<()>;
 
+	// Moderation audit trail
+	//***********************
+
+	/// Append a decision to the tenant's moderation audit trail. Returns its `entry_id`.
+	async fn create_moderation_entry(
+		&self,
+		tn_id: TnId,
+		entry: &CreateModerationEntry<'_>,
+	) -> ClResult<u64>;
+
+	/// List the tenant's moderation audit trail, newest first.
+	async fn list_moderation_log(
+		&self,
+		tn_id: TnId,
+		opts: &ListModerationLogOptions,
+	) -> ClResult<Vec<ModerationEntry>>;
+
+	// Content filters
+	//****************
+
+	/// List the tenant's content filters that have not expired, oldest first.
+	async fn list_content_filters(&self, tn_id: TnId) -> ClResult<Vec<ContentFilter>>;
+
+	/// Create a content filter.
+	async fn create_content_filter(
+		&self,
+		tn_id: TnId,
+		filter: &CreateContentFilter,
+	) -> ClResult<ContentFilter>;
+
+	/// Update a content filter. Returns `Error::NotFound` if it does not exist.
+	async fn update_content_filter(
+		&self,
+		tn_id: TnId,
+		filter_id: u64,
+		opts: &UpdateContentFilterOptions,
+	) -> ClResult<ContentFilter>;
+
+	/// Delete a content filter. Returns `Error::NotFound` if it does not exist.
+	async fn delete_content_filter(&self, tn_id: TnId, filter_id: u64) -> ClResult<()>;
+
+	// Hashtags
+	//*********
+
+	/// Replace the hashtags indexed for an action (normalized, see
+	/// [`crate::hashtags`]). An empty list removes the action from the index.
+	async fn update_action_hashtags(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		tags: &[String],
+	) -> ClResult<()>;
+
+	/// Count hashtag use by public, active actions created in `[since, until)`,
+	/// most accounts first.
+	async fn count_hashtags(
+		&self,
+		tn_id: TnId,
+		since: Timestamp,
+		until: Timestamp,
+		limit: u32,
+	) -> ClResult<Vec<HashtagCount>>;
+
+	/// Replace the stored trending list of a window.
+	async fn replace_trending_hashtags(
+		&self,
+		tn_id: TnId,
+		window: &str,
+		trending: &[TrendingHashtag],
+	) -> ClResult<()>;
+
+	/// List the stored trending list of a window, highest score first.
+	async fn list_trending_hashtags(
+		&self,
+		tn_id: TnId,
+		window: &str,
+	) -> ClResult<Vec<TrendingHashtag>>;
+
+	/// List the hashtags the tenant follows, alphabetically.
+	async fn list_followed_hashtags(&self, tn_id: TnId) -> ClResult<Vec<FollowedHashtag>>;
+
+	/// Follow a hashtag (idempotent).
+	async fn follow_hashtag(&self, tn_id: TnId, tag: &str) -> ClResult<()>;
+
+	/// Unfollow a hashtag. Returns `Error::NotFound` if it was not followed.
+	async fn unfollow_hashtag(&self, tn_id: TnId, tag: &str) -> ClResult<()>;
+
+	// Mentions
+	//*********
+
+	/// Replace the mentions indexed for an action (canonical id_tags, see
+	/// [`crate::mentions`]). An empty list removes the action from the index.
+	async fn update_action_mentions(
+		&self,
+		tn_id: TnId,
+		action_id: &str,
+		mentions: &[String],
+	) -> ClResult<()>;
+
+	// Collections
+	//************
+	//
+	// Every collection belongs to one `owner_tag`; a collection id of another
+	// owner is `Error::NotFound`, as is one that does not exist.
+
+	/// List the owner's collections, oldest first.
+	async fn list_collections(&self, tn_id: TnId, owner_tag: &str) -> ClResult<Vec<Collection>>;
+
+	/// Read one of the owner's collections.
+	async fn read_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<Collection>;
+
+	/// Create a collection.
+	async fn create_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection: &CreateCollection,
+	) -> ClResult<Collection>;
+
+	/// Update a collection's name or description.
+	async fn update_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		opts: &UpdateCollectionOptions,
+	) -> ClResult<Collection>;
+
+	/// Delete a collection with its items.
+	async fn delete_collection(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<()>;
+
+	/// List a collection's items in order.
+	async fn list_saved_items(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+	) -> ClResult<Vec<SavedItem>>;
+
+	/// Save an item to a collection, or update the note and position of one it
+	/// already holds.
+	async fn save_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item: &SaveItem,
+	) -> ClResult<SavedItem>;
+
+	/// Update a saved item's note or position. Returns `Error::NotFound` if the
+	/// collection does not hold the item.
+	async fn update_saved_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item_type: SavedItemType,
+		item_id: &str,
+		opts: &UpdateSavedItemOptions,
+	) -> ClResult<SavedItem>;
+
+	/// Remove an item from a collection. Returns `Error::NotFound` if the
+	/// collection does not hold the item.
+	async fn remove_saved_item(
+		&self,
+		tn_id: TnId,
+		owner_tag: &str,
+		collection_id: u64,
+		item_type: SavedItemType,
+		item_id: &str,
+	) -> ClResult<()>;
+
 	// Share Entry Management
 	//***********************
 
@@ -5,13 +5,46 @@
 //! (which writes to the `actions_data.reactions` column) and the
 //! STAT native hook (which normalizes inbound STAT `content.r`).
 //!
-//! Wire format: "<total>,<code><count>,<code><count>,..."
-//!   - `total` is the uncapped sum; the per-type list is capped to
-//!     the top 5 entries, sorted DESC by count then ASC by code.
-//!   - Empty list with `total == 0` encodes as an empty string.
+//! A REACT's subtype names its reaction in one of three forms, each with a
+//! count key (see [`reaction_type_key`]):
+//!   - one of the six built-in names (LIKE, LOVE, LAUGH, WOW, SAD, ANGRY),
+//!     keyed by a single uppercase letter;
+//!   - a Unicode emoji (one emoji, possibly a ZWJ, keycap or flag sequence),
+//!     keyed by itself;
+//!   - a custom emoji `:name:` from the emoji set its subject's owner
+//!     publishes (`profile.custom_emoji`, served at `/api/me/emoji`), keyed
+//!     by itself.
+//!
+//! Two wire formats carry the counts:
+//!   - v1: "<total>,<code><count>,<code><count>,..." — single-letter keys
+//!     only, the top 5 entries.
+//!   - v2: "v2;<total>;<key>=<count>;<key>=<count>;..." — any key, the top
+//!     [`MAX_V2_ENTRIES`] entries. No key contains `;`, `=` or `,`.
+//!
+//! The encoder writes v1 whenever every key is a single letter, so counts of
+//! built-in reactions stay readable by peers that predate v2; one emoji key
+//! switches the whole string to v2. In both, `total` is the uncapped sum, the
+//! entries are sorted DESC by count then ASC by key, and an empty list with
+//! `total == 0` encodes as an empty string.
+
+/// Version prefix of the v2 format.
+const V2_PREFIX: &str = "v2;";
+
+/// Most per-key entries a v1 string carries.
+const MAX_V1_ENTRIES: usize = 5;
+
+/// Most per-key entries a v2 string carries.
+pub const MAX_V2_ENTRIES: usize = 16;
+
+/// Longest Unicode emoji subtype, in bytes. Generous for ZWJ sequences (a family
+/// with skin tones is ~35 bytes) while keeping counts strings bounded.
+const MAX_EMOJI_BYTES: usize = 48;
+
+/// Longest custom emoji name, without the colons.
+pub const MAX_CUSTOM_EMOJI_NAME: usize = 32;
 
-/// Map a reaction sub_type (e.g. "LIKE") to its single-char wire key.
-pub fn reaction_type_key(sub_type: &str) -> Option<char> {
+/// Map a built-in reaction sub_type (e.g. "LIKE") to its single-char wire key.
+fn builtin_key(sub_type: &str) -> Option<char> {
 	match sub_type {
 		"LIKE" => Some('L'),
 		"LOVE" => Some('V'),
@@ -23,34 +56,160 @@ pub fn reaction_type_key(sub_type: &str) -> Option<char> {
 	}
 }
 
-/// Encodes reactions into the canonical wire format. Sorts and truncates
-/// `entries` in place so callers can't accidentally pass unsorted data.
-/// Returns "" when `total == 0`.
-pub fn encode_reaction_counts(mut entries: Vec<(char, u32)>, total: u32) -> String {
+/// Whether `name` may name a custom emoji: 2 to [`MAX_CUSTOM_EMOJI_NAME`]
+/// lowercase ASCII letters, digits or underscores.
+pub fn is_custom_emoji_name(name: &str) -> bool {
+	(2..=MAX_CUSTOM_EMOJI_NAME).contains(&name.len())
+		&& name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// The name of a custom emoji subtype `:name:`, or `None` for any other subtype.
+pub fn custom_emoji_name(sub_type: &str) -> Option<&str> {
+	let name = sub_type.strip_prefix(':')?.strip_suffix(':')?;
+	is_custom_emoji_name(name).then_some(name)
+}
+
+/// Whether `c` is a pictographic emoji base: the Extended_Pictographic ranges
+/// (which cover every Emoji_Presentation character), less the regional
+/// indicators and skin-tone modifiers, which only count as part of a flag or
+/// after a base.
+fn is_pictographic(c: char) -> bool {
+	matches!(u32::from(c),
+		0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139
+		| 0x2194..=0x2199 | 0x21A9..=0x21AA | 0x231A..=0x231B | 0x2328 | 0x2388 | 0x23CF
+		| 0x23E9..=0x23F3 | 0x23F8..=0x23FA | 0x24C2 | 0x25AA..=0x25AB | 0x25B6 | 0x25C0
+		| 0x25FB..=0x25FE | 0x2600..=0x2605 | 0x2607..=0x2612 | 0x2614..=0x2685
+		| 0x2690..=0x2705 | 0x2708..=0x2712 | 0x2714 | 0x2716 | 0x271D | 0x2721 | 0x2728
+		| 0x2733..=0x2734 | 0x2744 | 0x2747 | 0x274C | 0x274E | 0x2753..=0x2755 | 0x2757
+		| 0x2763..=0x2767 | 0x2795..=0x2797 | 0x27A1 | 0x27B0 | 0x27BF | 0x2934..=0x2935
+		| 0x2B05..=0x2B07 | 0x2B1B..=0x2B1C | 0x2B50 | 0x2B55 | 0x3030 | 0x303D | 0x3297
+		| 0x3299 | 0x1F000..=0x1F0FF | 0x1F10D..=0x1F10F | 0x1F12F | 0x1F16C..=0x1F171
+		| 0x1F17E..=0x1F17F | 0x1F18E | 0x1F191..=0x1F19A | 0x1F1AD..=0x1F1E5
+		| 0x1F201..=0x1F20F | 0x1F21A | 0x1F22F | 0x1F232..=0x1F23A | 0x1F23C..=0x1F23F
+		| 0x1F249..=0x1F3FA | 0x1F400..=0x1F53D | 0x1F546..=0x1F64F | 0x1F680..=0x1F6FF
+		| 0x1F774..=0x1F77F | 0x1F7D5..=0x1F7FF | 0x1F80C..=0x1F80F | 0x1F848..=0x1F84F
+		| 0x1F85A..=0x1F85F | 0x1F888..=0x1F88F | 0x1F8AE..=0x1F8FF | 0x1F90C..=0x1F93A
+		| 0x1F93C..=0x1F945 | 0x1F947..=0x1FAFF | 0x1FC00..=0x1FFFD)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+	('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+fn is_skin_tone(c: char) -> bool {
+	('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+/// Zero-width joiner, variation selector 16 and the combining keycap.
+const ZWJ: char = '\u{200D}';
+const VS16: char = '\u{FE0F}';
+const KEYCAP: char = '\u{20E3}';
+
+/// Whether `sub_type` is one Unicode emoji: a keycap (`1️⃣`), a flag (two
+/// regional indicators), or pictographic bases joined by ZWJ, each optionally
+/// followed by VS16 or a skin-tone modifier and closed by a tag sequence (the
+/// subdivision flags). Letters in any script are not emoji.
+fn is_unicode_emoji(sub_type: &str) -> bool {
+	if sub_type.is_empty() || sub_type.len() > MAX_EMOJI_BYTES {
+		return false;
+	}
+	let mut chars = sub_type.chars().peekable();
+	// Keycap: [0-9#*] VS16? U+20E3
+	if let Some(&c) = chars.peek()
+		&& (c.is_ascii_digit() || c == '#' || c == '*')
+	{
+		chars.next();
+		chars.next_if_eq(&VS16);
+		return chars.next() == Some(KEYCAP) && chars.next().is_none();
+	}
+	// Flag: a pair of regional indicators
+	if chars.peek().copied().is_some_and(is_regional_indicator) {
+		chars.next();
+		return chars.next().is_some_and(is_regional_indicator) && chars.next().is_none();
+	}
+	loop {
+		if !chars.next().is_some_and(is_pictographic) {
+			return false;
+		}
+		if chars.next_if(|&c| c == VS16 || is_skin_tone(c)).is_some() {
+			chars.next_if_eq(&VS16);
+		}
+		// Tag sequence: tag characters closed by CANCEL TAG
+		if chars.next_if(|c| ('\u{E0020}'..='\u{E007E}').contains(c)).is_some() {
+			while chars.next_if(|c| ('\u{E0020}'..='\u{E007E}').contains(c)).is_some() {}
+			if chars.next() != Some('\u{E007F}') {
+				return false;
+			}
+		}
+		match chars.next() {
+			None => return true,
+			Some(ZWJ) => {}
+			Some(_) => return false,
+		}
+	}
+}
+
+/// Map a reaction sub_type to its count key: a built-in name to its letter, an
+/// emoji or a custom `:name:` to itself. `None` for anything else.
+pub fn reaction_type_key(sub_type: &str) -> Option<String> {
+	if let Some(key) = builtin_key(sub_type) {
+		return Some(key.to_string());
+	}
+	(custom_emoji_name(sub_type).is_some() || is_unicode_emoji(sub_type))
+		.then(|| sub_type.to_string())
+}
+
+/// Encodes reactions into the canonical wire format, v1 when it can and v2
+/// otherwise. Sorts and truncates `entries` in place so callers can't
+/// accidentally pass unsorted data. Returns "" when `total == 0`.
+pub fn encode_reaction_counts(mut entries: Vec<(String, u32)>, total: u32) -> String {
 	if total == 0 {
 		return String::new();
 	}
 	entries.retain(|(_, c)| *c > 0);
 	entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
-	entries.truncate(5);
-	if entries.is_empty() {
-		return total.to_string();
+	let v1 = entries
+		.iter()
+		.all(|(k, _)| k.len() == 1 && k.as_bytes()[0].is_ascii_uppercase());
+	if v1 {
+		entries.truncate(MAX_V1_ENTRIES);
+		let mut out = total.to_string();
+		for (k, c) in &entries {
+			out.push(',');
+			out.push_str(k);
+			out.push_str(&c.to_string());
+		}
+		return out;
 	}
-	let mut out = total.to_string();
+	entries.truncate(MAX_V2_ENTRIES);
+	let mut out = format!("{}{}", V2_PREFIX, total);
 	for (k, c) in &entries {
-		out.push(',');
-		out.push(*k);
+		out.push(';');
+		out.push_str(k);
+		out.push('=');
 		out.push_str(&c.to_string());
 	}
 	out
 }
 
-/// Decodes the canonical wire format into `(entries, total)`.
+/// Decodes either wire format into `(entries, total)`.
 /// Lenient: skips malformed tokens silently.
-pub fn decode_reaction_counts(s: &str) -> (Vec<(char, u32)>, u32) {
+pub fn decode_reaction_counts(s: &str) -> (Vec<(String, u32)>, u32) {
 	if s.is_empty() {
 		return (Vec::new(), 0);
 	}
+	if let Some(v2) = s.strip_prefix(V2_PREFIX) {
+		let mut parts = v2.split(';');
+		let total: u32 = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+		let entries = parts
+			.filter_map(|part| {
+				let (key, n) = part.rsplit_once('=')?;
+				let n = n.parse::<u32>().ok().filter(|n| *n > 0)?;
+				(!key.is_empty()).then(|| (key.to_string(), n))
+			})
+			.collect();
+		return (entries, total);
+	}
 	let mut parts = s.split(',');
 	let total: u32 = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
 	let mut entries = Vec::new();
@@ -62,7 +221,7 @@ pub fn decode_reaction_counts(s: &str) -> (Vec<(char, u32)>, u32) {
 		if n == 0 {
 			continue;
 		}
-		entries.push((key, n));
+		entries.push((key.to_string(), n));
 	}
 	(entries, total)
 }
@@ -71,6 +230,10 @@ pub fn decode_reaction_counts(s: &str) -> (Vec<(char, u32)>, u32) {
 mod tests {
 	use super::*;
 
+	fn entries(list: &[(&str, u32)]) -> Vec<(String, u32)> {
+		list.iter().map(|(k, c)| ((*k).to_string(), *c)).collect()
+	}
+
 	#[test]
 	fn encode_empty() {
 		assert_eq!(encode_reaction_counts(Vec::new(), 0), "");
@@ -78,14 +241,17 @@ mod tests {
 
 	#[test]
 	fn encode_single() {
-		assert_eq!(encode_reaction_counts(vec![('L', 1)], 1), "1,L1");
+		assert_eq!(encode_reaction_counts(entries(&[("L", 1)]), 1), "1,L1");
 	}
 
 	#[test]
 	fn encode_with_overflow() {
 		// Already sorted DESC by count, ASC by code on tie.
 		assert_eq!(
-			encode_reaction_counts(vec![('L', 40), ('V', 30), ('H', 20), ('W', 7), ('S', 5)], 103,),
+			encode_reaction_counts(
+				entries(&[("L", 40), ("V", 30), ("H", 20), ("W", 7), ("S", 5)]),
+				103,
+			),
 			"103,L40,V30,H20,W7,S5"
 		);
 	}
@@ -99,7 +265,7 @@ mod tests {
 	#[test]
 	fn encode_normalises_unsorted_input() {
 		// Caller passed unsorted entries; encoder sorts them.
-		let s = encode_reaction_counts(vec![('A', 1), ('L', 5), ('V', 3)], 9);
+		let s = encode_reaction_counts(entries(&[("A", 1), ("L", 5), ("V", 3)]), 9);
 		assert_eq!(s, "9,L5,V3,A1");
 	}
 
@@ -107,7 +273,7 @@ mod tests {
 	fn encode_caps_to_top_five() {
 		// 6 entries; only top 5 by count appear (last in code-asc order).
 		let s = encode_reaction_counts(
-			vec![('A', 6), ('B', 5), ('C', 4), ('D', 3), ('E', 2), ('F', 1)],
+			entries(&[("A", 6), ("B", 5), ("C", 4), ("D", 3), ("E", 2), ("F", 1)]),
 			21,
 		);
 		assert_eq!(s, "21,A6,B5,C4,D3,E2");
@@ -127,7 +293,7 @@ mod tests {
 	fn decode_with_entries() {
 		assert_eq!(
 			decode_reaction_counts("103,L40,V30,H20,W7,S5"),
-			(vec![('L', 40), ('V', 30), ('H', 20), ('W', 7), ('S', 5)], 103)
+			(entries(&[("L", 40), ("V", 30), ("H", 20), ("W", 7), ("S", 5)]), 103)
 		);
 	}
 
@@ -141,9 +307,90 @@ mod tests {
 	#[test]
 	fn decode_skips_malformed() {
 		// "xy" parses key='x', tail="y" — not a number, skipped.
-		let (entries, total) = decode_reaction_counts("5,L3,xy,V2");
+		let (decoded, total) = decode_reaction_counts("5,L3,xy,V2");
 		assert_eq!(total, 5);
-		assert_eq!(entries, vec![('L', 3), ('V', 2)]);
+		assert_eq!(decoded, entries(&[("L", 3), ("V", 2)]));
+	}
+
+	#[test]
+	fn subtypes_map_to_keys() {
+		assert_eq!(reaction_type_key("LIKE").as_deref(), Some("L"));
+		assert_eq!(reaction_type_key("ANGRY").as_deref(), Some("A"));
+		assert_eq!(reaction_type_key("🎉").as_deref(), Some("🎉"));
+		assert_eq!(reaction_type_key("👩\u{200d}💻").as_deref(), Some("👩\u{200d}💻"));
+		assert_eq!(reaction_type_key(":party_parrot:").as_deref(), Some(":party_parrot:"));
+
+		assert_eq!(reaction_type_key("CLAP"), None);
+		assert_eq!(reaction_type_key(":Party:"), None);
+		assert_eq!(reaction_type_key(":x:"), None);
+		assert_eq!(reaction_type_key("🎉;L=9"), None);
+		assert_eq!(reaction_type_key("🎉 🎉"), None);
+		assert_eq!(reaction_type_key(""), None);
+	}
+
+	#[test]
+	fn unicode_emoji_are_recognized() {
+		for emoji in [
+			"👍",
+			"❤\u{fe0f}",
+			"👍\u{1f3fd}",
+			"1\u{fe0f}\u{20e3}",
+			"#\u{20e3}",
+			"🇭🇺",
+			"👨\u{200d}👩\u{200d}👧\u{200d}👦",
+			"🧑\u{1f3fb}\u{200d}💻",
+			"🏴\u{e0067}\u{e0062}\u{e0065}\u{e006e}\u{e0067}\u{e007f}",
+		] {
+			assert!(is_unicode_emoji(emoji), "{emoji:?} is an emoji");
+		}
+	}
+
+	#[test]
+	fn letters_are_not_emoji() {
+		for text in [
+			"café",
+			"Ölç",
+			"é",
+			"你好",
+			"привет",
+			"ß",
+			"🇭",
+			"1",
+			"👍a",
+			"a👍",
+			"👍\u{200d}",
+			"\u{1f3fb}",
+		] {
+			assert!(!is_unicode_emoji(text), "{text:?} is not an emoji");
+			assert_eq!(reaction_type_key(text), None);
+		}
+	}
+
+	#[test]
+	fn an_emoji_key_switches_to_v2() {
+		let s = encode_reaction_counts(entries(&[("L", 4), (":party:", 2), ("🎉", 2)]), 8);
+		assert_eq!(s, "v2;8;L=4;:party:=2;🎉=2");
+		let (decoded, total) = decode_reaction_counts(&s);
+		assert_eq!(total, 8);
+		assert_eq!(decoded, entries(&[("L", 4), (":party:", 2), ("🎉", 2)]));
+		assert_eq!(encode_reaction_counts(decoded, total), s);
+	}
+
+	#[test]
+	fn v2_caps_to_its_own_limit() {
+		let many: Vec<(String, u32)> =
+			(0..20u32).map(|i| (format!(":emoji_{:02}:", i), 20 - i)).collect();
+		let total = many.iter().map(|(_, c)| c).sum();
+		let (decoded, _) = decode_reaction_counts(&encode_reaction_counts(many, total));
+		assert_eq!(decoded.len(), MAX_V2_ENTRIES);
+		assert_eq!(decoded[0], (":emoji_00:".to_string(), 20));
+	}
+
+	#[test]
+	fn v2_decode_skips_malformed() {
+		let (decoded, total) = decode_reaction_counts("v2;6;🎉=3;junk;=2;🔥=x;:ok:=3");
+		assert_eq!(total, 6);
+		assert_eq!(decoded, entries(&[("🎉", 3), (":ok:", 3)]));
 	}
 }
 
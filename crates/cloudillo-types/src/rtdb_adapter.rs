@@ -66,6 +66,8 @@ pub struct AggregateOptions {
 ///
 /// Supports multiple filter operations on JSON document fields.
 /// A document matches if ALL specified conditions are satisfied (AND logic).
+/// `or` and `not` nest whole filters, so any boolean combination is expressible:
+/// `{"equals": {"a": 1}, "or": [{..}, {..}]}` is `a == 1 AND (.. OR ..)`.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QueryFilter {
 	/// Field equality constraints: field_name -> expected_value
@@ -111,6 +113,14 @@ pub struct QueryFilter {
 	/// Array-contains-all constraints: field_name -> array of values (all must be in the array field)
 	#[serde(default, skip_serializing_if = "HashMap::is_empty", rename = "arrayContainsAll")]
 	pub array_contains_all: HashMap<String, Vec<Value>>,
+
+	/// Alternatives: at least one of these filters must match (ignored when empty)
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub or: Vec<QueryFilter>,
+
+	/// Negation: this filter must NOT match
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub not: Option<Box<QueryFilter>>,
 }
 
 impl QueryFilter {
@@ -192,6 +202,18 @@ impl QueryFilter {
 		self
 	}
 
+	/// Add a set of alternatives, at least one of which must match (builder pattern).
+	pub fn with_or(mut self, alternatives: Vec<QueryFilter>) -> Self {
+		self.or = alternatives;
+		self
+	}
+
+	/// Add a negated filter, which must not match (builder pattern).
+	pub fn with_not(mut self, filter: QueryFilter) -> Self {
+		self.not = Some(Box::new(filter));
+		self
+	}
+
 	/// Check if a document matches this filter (all conditions must be satisfied).
 	pub fn matches(&self, doc: &Value) -> bool {
 		// Equality checks
@@ -295,9 +317,38 @@ impl QueryFilter {
 			}
 		}
 
+		// Alternatives (at least one must match)
+		if !self.or.is_empty() && !self.or.iter().any(|f| f.matches(doc)) {
+			return false;
+		}
+
+		// Negation
+		if let Some(ref not) = self.not
+			&& not.matches(doc)
+		{
+			return false;
+		}
+
 		true
 	}
 
+	/// How a change moves a document relative to this filter's result set.
+	///
+	/// `old` is the document before the change (`None` if it did not exist),
+	/// `new` after it (`None` once deleted). Live subscriptions use this to turn
+	/// an update that takes a document out of the set into a removal, instead of
+	/// dropping it and leaving the subscriber holding a stale match.
+	pub fn transition(&self, old: Option<&Value>, new: Option<&Value>) -> FilterTransition {
+		let was = old.is_some_and(|d| self.matches(d));
+		let is = new.is_some_and(|d| self.matches(d));
+		match (was, is) {
+			(false, true) => FilterTransition::Enter,
+			(true, true) => FilterTransition::Stay,
+			(true, false) => FilterTransition::Leave,
+			(false, false) => FilterTransition::Outside,
+		}
+	}
+
 	/// Check if this filter is empty (matches all documents).
 	pub fn is_empty(&self) -> bool {
 		self.equals.is_empty()
@@ -311,9 +362,25 @@ impl QueryFilter {
 			&& self.not_in_array.is_empty()
 			&& self.array_contains_any.is_empty()
 			&& self.array_contains_all.is_empty()
+			&& self.or.is_empty()
+			&& self.not.is_none()
 	}
 }
 
+/// Movement of one document relative to a filter's result set. See
+/// [`QueryFilter::transition`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterTransition {
+	/// Did not match before, matches now
+	Enter,
+	/// Matched before and still does
+	Stay,
+	/// Matched before, no longer does
+	Leave,
+	/// Matched neither before nor after
+	Outside,
+}
+
 /// Sort order for a field.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortField {
@@ -336,6 +403,123 @@ impl SortField {
 	}
 }
 
+/// Position in a sorted RTDB result set, for keyset pagination.
+///
+/// Opaque on the wire (base64url JSON, like [`crate::types::CursorData`]).
+/// Paging by cursor rather than offset stays cheap at any depth and neither
+/// skips nor repeats documents when the collection changes between pages.
+///
+/// The order a cursor refers to is the query's sort fields followed by the
+/// document id (see [`compare_documents`]), so every position is unique. The
+/// cursor records the sort it was taken under; replaying it against another
+/// sort is rejected rather than silently paging through the wrong order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RtdbCursor {
+	/// Sort fields the cursor was taken under, `-`-prefixed when descending
+	pub s: Vec<String>,
+	/// The document's value for each sort field (`null` where missing)
+	pub v: Vec<Value>,
+	/// Positions in `v` of fields the document did not have at all
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub m: Vec<usize>,
+	/// Document id, the final tie-breaker
+	pub id: String,
+}
+
+impl RtdbCursor {
+	/// The cursor positioned at `doc` under `sort`. `None` for a document
+	/// without an `id`.
+	pub fn for_doc(doc: &Value, sort: &[SortField]) -> Option<Self> {
+		let id = doc.get("id")?.as_str()?.to_string();
+		let mut v = Vec::with_capacity(sort.len());
+		let mut m = Vec::new();
+		for (i, field) in sort.iter().enumerate() {
+			if let Some(value) = doc.get(&field.field) {
+				v.push(value.clone());
+			} else {
+				v.push(Value::Null);
+				m.push(i);
+			}
+		}
+		Some(Self { s: Self::sort_signature(sort), v, m, id })
+	}
+
+	fn sort_signature(sort: &[SortField]) -> Vec<String> {
+		sort.iter()
+			.map(|f| if f.ascending { f.field.clone() } else { format!("-{}", f.field) })
+			.collect()
+	}
+
+	/// Whether this cursor was taken under `sort`.
+	pub fn matches_sort(&self, sort: &[SortField]) -> bool {
+		self.s == Self::sort_signature(sort) && self.v.len() == sort.len()
+	}
+
+	/// The cursor's value for the sort field at `index`, `None` where the
+	/// document had no such field.
+	pub fn value(&self, index: usize) -> Option<&Value> {
+		if self.m.contains(&index) { None } else { self.v.get(index) }
+	}
+
+	/// Where `doc` sits relative to this cursor under `sort`: `Greater` means
+	/// after it.
+	pub fn compare_doc(&self, doc: &Value, sort: &[SortField]) -> std::cmp::Ordering {
+		let doc_id = doc.get("id").and_then(Value::as_str).unwrap_or_default();
+		compare_sort_positions(
+			sort,
+			|_, field| doc.get(field),
+			doc_id,
+			|i, _| self.value(i),
+			&self.id,
+		)
+	}
+
+	/// Encode cursor to base64 string
+	pub fn encode(&self) -> String {
+		use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+		let json = serde_json::to_string(self).unwrap_or_default();
+		URL_SAFE_NO_PAD.encode(json.as_bytes())
+	}
+
+	/// Decode cursor from base64 string
+	pub fn decode(cursor: &str) -> Option<Self> {
+		use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+		let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+		let json = String::from_utf8(bytes).ok()?;
+		serde_json::from_str(&json).ok()
+	}
+}
+
+/// Compare two documents under `sort`, completing the order with the document
+/// id so that no two documents tie.
+///
+/// The id breaks ties in the direction of the last sort field (ascending for an
+/// unsorted query). This is the order cursors refer to, and the one every
+/// adapter code path — in-memory sort or ordered index walk — must produce.
+pub fn compare_documents(a: &Value, b: &Value, sort: &[SortField]) -> std::cmp::Ordering {
+	let id = |doc: &Value| doc.get("id").and_then(Value::as_str).unwrap_or_default().to_string();
+	compare_sort_positions(sort, |_, f| a.get(f), &id(a), |_, f| b.get(f), &id(b))
+}
+
+fn compare_sort_positions<'a, 'b>(
+	sort: &[SortField],
+	a_value: impl Fn(usize, &str) -> Option<&'a Value>,
+	a_id: &str,
+	b_value: impl Fn(usize, &str) -> Option<&'b Value>,
+	b_id: &str,
+) -> std::cmp::Ordering {
+	for (i, field) in sort.iter().enumerate() {
+		let ord = compare_json_values(a_value(i, &field.field), b_value(i, &field.field));
+		let ord = if field.ascending { ord } else { ord.reverse() };
+		if ord != std::cmp::Ordering::Equal {
+			return ord;
+		}
+	}
+
+	let ord = a_id.cmp(b_id);
+	if sort.last().is_some_and(|f| !f.ascending) { ord.reverse() } else { ord }
+}
+
 /// Options for querying documents (filter, sort, limit, offset).
 #[derive(Debug, Clone, Default)]
 pub struct QueryOptions {
@@ -357,6 +541,21 @@ pub struct QueryOptions {
 	/// Optional field projection. When set, only these top-level fields (plus
 	/// `id`) are returned. `None` returns whole documents.
 	pub select: Option<Vec<String>>,
+
+	/// Only documents after this position (see [`RtdbCursor`]). The cursor must
+	/// have been taken under this query's `sort`.
+	pub start_after: Option<RtdbCursor>,
+
+	/// Only documents before this position. Without `start_after`, `offset` and
+	/// `limit` then count backwards from the cursor, so the page returned is the
+	/// one ending right before it — what paging back needs.
+	pub end_before: Option<RtdbCursor>,
+
+	/// When set, returns a single [`QueryPlan`] object instead of documents.
+	///
+	/// The query still runs, so the plan reports what was actually examined
+	/// rather than an estimate.
+	pub explain: bool,
 }
 
 impl QueryOptions {
@@ -400,6 +599,143 @@ impl QueryOptions {
 		self.select = Some(select);
 		self
 	}
+
+	/// Page forward from a cursor.
+	pub fn with_start_after(mut self, cursor: RtdbCursor) -> Self {
+		self.start_after = Some(cursor);
+		self
+	}
+
+	/// Page backward from a cursor.
+	pub fn with_end_before(mut self, cursor: RtdbCursor) -> Self {
+		self.end_before = Some(cursor);
+		self
+	}
+
+	/// The sort this query's cursors refer to: the explicit sort, or none (id order).
+	pub fn sort_fields(&self) -> &[SortField] {
+		self.sort.as_deref().unwrap_or_default()
+	}
+
+	/// Check that the cursors were taken under this query's sort.
+	pub fn validate_cursors(&self) -> ClResult<()> {
+		let sort = self.sort_fields();
+		for cursor in [&self.start_after, &self.end_before].into_iter().flatten() {
+			if !cursor.matches_sort(sort) {
+				return Err(Error::ValidationError("cursor does not match the query sort".into()));
+			}
+		}
+		Ok(())
+	}
+
+	/// Whether `doc` lies between the cursors, if any.
+	pub fn within_cursors(&self, doc: &Value) -> bool {
+		let sort = self.sort_fields();
+		self.start_after
+			.as_ref()
+			.is_none_or(|c| c.compare_doc(doc, sort) == std::cmp::Ordering::Greater)
+			&& self
+				.end_before
+				.as_ref()
+				.is_none_or(|c| c.compare_doc(doc, sort) == std::cmp::Ordering::Less)
+	}
+
+	/// Request the query plan instead of the documents.
+	pub fn with_explain(mut self, explain: bool) -> Self {
+		self.explain = explain;
+		self
+	}
+}
+
+/// Definition of an index over one or more top-level fields of a collection.
+///
+/// A single-field spec is the classic equality index. With several fields the
+/// index is *composite*: entries are ordered by the fields in the given order, so
+/// it serves an equality filter on a prefix of the fields followed by a range
+/// filter or a sort on the next one. An array-valued field produces one entry per
+/// scalar element (a multi-entry index), which is what serves `arrayContains`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexSpec {
+	/// Indexed fields, most significant first. Never empty.
+	pub fields: Vec<String>,
+}
+
+impl IndexSpec {
+	/// An index on a single field.
+	pub fn field(field: impl Into<String>) -> Self {
+		Self { fields: vec![field.into()] }
+	}
+
+	/// A composite index over several fields, most significant first.
+	pub fn composite<I, S>(fields: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		Self { fields: fields.into_iter().map(Into::into).collect() }
+	}
+
+	/// True for an index over more than one field.
+	pub fn is_composite(&self) -> bool {
+		self.fields.len() > 1
+	}
+}
+
+/// How a query was executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryStrategy {
+	/// Candidates came from an index.
+	Index,
+	/// Every document of the collection was examined.
+	Scan,
+	/// Aggregation counted index entries without fetching documents.
+	AggregateIndex,
+	/// Aggregation examined every document of the collection.
+	AggregateScan,
+}
+
+/// Description of how a query was served, returned when [`QueryOptions::explain`]
+/// is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPlan {
+	pub strategy: QueryStrategy,
+
+	/// Fields of the chosen index, `None` for a scan.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub index: Option<Vec<String>>,
+
+	/// Number of leading index fields constrained by equality.
+	pub equality_fields: u32,
+
+	/// Whether the index also bounded a range constraint on the next field.
+	pub range: bool,
+
+	/// Whether results came out of the index already in the requested order, so no
+	/// in-memory sort was needed and the scan could stop at `offset + limit`.
+	pub sorted_by_index: bool,
+
+	/// Documents fetched and tested against the filter.
+	pub docs_examined: u64,
+
+	/// Documents (or groups) returned after filtering and pagination.
+	pub docs_returned: u64,
+}
+
+impl QueryPlan {
+	/// Plan of a full collection scan.
+	pub fn scan() -> Self {
+		Self {
+			strategy: QueryStrategy::Scan,
+			index: None,
+			equality_fields: 0,
+			range: false,
+			sorted_by_index: false,
+			docs_examined: 0,
+			docs_returned: 0,
+		}
+	}
 }
 
 /// Restrict a document to the selected top-level fields.
@@ -650,6 +986,40 @@ pub fn value_to_group_string(value: &Value) -> String {
 	}
 }
 
+/// One entry of a database's change log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRecord {
+	/// Position of the write in the database's sequence
+	pub seq: u64,
+	/// The change itself. `old_data` is not kept in the log.
+	#[serde(flatten)]
+	pub event: ChangeEvent,
+}
+
+/// Changes a client missed while it was away. See [`RtdbAdapter::changes_since`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSet {
+	/// The changes, oldest first
+	pub changes: Vec<ChangeRecord>,
+	/// Sequence to resume from: that of the last change returned, or the
+	/// database's current sequence when there is none
+	pub seq: u64,
+	/// More changes follow `seq`
+	pub has_more: bool,
+	/// The log no longer reaches back to the requested sequence. The client must
+	/// drop its local state, record `seq` and query afresh; changes after `seq`
+	/// that the fresh query already reflects replay harmlessly.
+	pub resync: bool,
+}
+
+impl ChangeSet {
+	/// A change set telling the client to start over from `seq`.
+	pub fn resync(seq: u64) -> Self {
+		Self { changes: Vec::new(), seq, has_more: false, resync: true }
+	}
+}
+
 /// Database statistics.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DbStats {
@@ -708,6 +1078,23 @@ pub trait Transaction: Send + Sync {
 	/// same file is open.
 	async fn check_lock(&self, path: &str) -> ClResult<Option<LockInfo>>;
 
+	/// The stored version of a document: the sequence number of the last write
+	/// to it, deletes included.
+	///
+	/// `None` for a document never written, or on a backend without a change
+	/// log. Replayed offline writes compare it to the version they were based on
+	/// to detect a conflicting write made in the meantime.
+	async fn version(&self, _path: &str) -> ClResult<Option<u64>> {
+		Ok(None)
+	}
+
+	/// Sequence number of the last write made through this transaction, `None`
+	/// before the first write or on a backend without a change log. Valid once
+	/// [`Transaction::commit`] succeeds.
+	async fn seq(&self) -> ClResult<Option<u64>> {
+		Ok(None)
+	}
+
 	/// Commit the transaction, applying all changes atomically.
 	async fn commit(&mut self) -> ClResult<()>;
 
@@ -760,6 +1147,40 @@ pub trait RtdbAdapter: Debug + Send + Sync {
 	async fn create_index(&self, tn_id: TnId, db_id: &str, path: &str, field: &str)
 	-> ClResult<()>;
 
+	/// Create a composite (multi-field) index. See [`IndexSpec`].
+	///
+	/// Defaults to [`RtdbAdapter::create_index`] for a single-field spec and to a
+	/// validation error otherwise, for a backend without composite indexes.
+	async fn create_composite_index(
+		&self,
+		tn_id: TnId,
+		db_id: &str,
+		path: &str,
+		spec: &IndexSpec,
+	) -> ClResult<()> {
+		match spec.fields.as_slice() {
+			[field] => self.create_index(tn_id, db_id, path, field).await,
+			[] => Err(Error::ValidationError("index spec has no fields".into())),
+			_ => Err(Error::ValidationError("composite indexes are not supported".into())),
+		}
+	}
+
+	/// Changes made to a database after sequence number `since`, oldest first and
+	/// at most `limit` of them.
+	///
+	/// The change log has bounded retention; once `since` falls behind it the
+	/// result says so with [`ChangeSet::resync`]. Defaults to always asking for a
+	/// resync, the honest answer for a backend without a change log.
+	async fn changes_since(
+		&self,
+		_tn_id: TnId,
+		_db_id: &str,
+		_since: u64,
+		_limit: u32,
+	) -> ClResult<ChangeSet> {
+		Ok(ChangeSet::resync(0))
+	}
+
 	/// Get database statistics (size, record count, table count).
 	async fn stats(&self, tn_id: TnId, db_id: &str) -> ClResult<DbStats>;
 
@@ -1053,6 +1474,51 @@ mod tests {
 		assert_eq!(compare_json_values(None, Some(&Value::Number(1.into()))), Ordering::Less);
 		assert_eq!(compare_json_values(Some(&Value::Number(1.into())), None), Ordering::Greater);
 	}
+
+	#[test]
+	fn test_or_and_not_deserialize_and_match() {
+		let filter: QueryFilter = serde_json::from_value(serde_json::json!({
+			"or": [{"equals": {"s": "open"}}, {"greaterThan": {"p": 5}}],
+			"not": {"equals": {"owner": "bob"}}
+		}))
+		.expect("filter should deserialize");
+
+		assert!(filter.matches(&serde_json::json!({"s": "open", "owner": "alice"})));
+		assert!(filter.matches(&serde_json::json!({"s": "closed", "p": 9})));
+		assert!(!filter.matches(&serde_json::json!({"s": "closed", "p": 1})));
+		assert!(!filter.matches(&serde_json::json!({"s": "open", "owner": "bob"})));
+	}
+
+	#[test]
+	fn test_filter_transition() {
+		let filter = QueryFilter::equals_one("s", Value::from("open"));
+		let open = serde_json::json!({"s": "open"});
+		let closed = serde_json::json!({"s": "closed"});
+
+		assert_eq!(filter.transition(Some(&closed), Some(&open)), FilterTransition::Enter);
+		assert_eq!(filter.transition(Some(&open), Some(&open)), FilterTransition::Stay);
+		assert_eq!(filter.transition(Some(&open), Some(&closed)), FilterTransition::Leave);
+		assert_eq!(filter.transition(None, Some(&closed)), FilterTransition::Outside);
+	}
+
+	#[test]
+	fn test_cursor_roundtrip_and_position() {
+		let sort = [SortField::desc("p")];
+		let cursor =
+			RtdbCursor::for_doc(&serde_json::json!({"id": "b", "p": 5}), &sort).expect("cursor");
+		let decoded = RtdbCursor::decode(&cursor.encode()).expect("cursor should decode");
+		assert_eq!(decoded, cursor);
+		assert!(decoded.matches_sort(&sort));
+		assert!(!decoded.matches_sort(&[SortField::asc("p")]));
+
+		// Descending, so lower values and, on a tie, lower ids come after
+		let after = |doc| decoded.compare_doc(&doc, &sort);
+		assert_eq!(after(serde_json::json!({"id": "z", "p": 4})), Ordering::Greater);
+		assert_eq!(after(serde_json::json!({"id": "a", "p": 5})), Ordering::Greater);
+		assert_eq!(after(serde_json::json!({"id": "c", "p": 5})), Ordering::Less);
+		assert_eq!(after(serde_json::json!({"id": "b", "p": 5})), Ordering::Equal);
+		assert!(RtdbCursor::decode("not a cursor").is_none());
+	}
 }
 
 // vim: ts=4
@@ -714,12 +714,19 @@ impl AccessLevel {
 
 /// Token scope for scoped access tokens (e.g., share links)
 ///
-/// Format in JWT: "file:{file_id}:{R|C|W}"
+/// Format in JWT: "file:{file_id}:{R|C|W|V}" or "file:{folder_id}:U:{link}"
 /// This enum provides type-safe parsing instead of manual string splitting.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenScope {
 	/// File-scoped access with specific access level
 	File { file_id: String, access: AccessLevel },
+	/// View-only file access: reads at `Read` level, but the original is never
+	/// served — only previews, and no archive download
+	FileView { file_id: String },
+	/// Upload-only folder access ("file drop"): creates files directly in the
+	/// folder and nothing else — no listing, no reads. `link` is the redacted
+	/// digest of the share link, which uploads carry as their attribution.
+	FileDrop { folder_id: String, link: String },
 	/// APKG publish scope — restricts to package upload and APKG action creation
 	ApkgPublish,
 }
@@ -731,11 +738,19 @@ impl TokenScope {
 	/// - "file:{file_id}:R" -> File scope with Read access
 	/// - "file:{file_id}:C" -> File scope with Comment access
 	/// - "file:{file_id}:W" -> File scope with Write access
+	/// - "file:{file_id}:V" -> View-only file scope
+	/// - "file:{folder_id}:U:{link}" -> Upload-only folder scope
 	pub fn parse(s: &str) -> Option<Self> {
 		if s == "apkg:publish" {
 			return Some(Self::ApkgPublish);
 		}
 		let parts: Vec<&str> = s.split(':').collect();
+		if parts.len() == 4 && parts[0] == "file" && parts[2] == "U" && !parts[3].is_empty() {
+			return Some(Self::FileDrop {
+				folder_id: parts[1].to_string(),
+				link: parts[3].to_string(),
+			});
+		}
 		if parts.len() == 3 && parts[0] == "file" {
 			// Exhaustive on purpose: an unrecognized level char (including `'A'`, which
 			// `to_scope_char` never emits) makes the whole scope unparseable, which callers treat
@@ -744,6 +759,7 @@ impl TokenScope {
 				"R" => AccessLevel::Read,
 				"C" => AccessLevel::Comment,
 				"W" => AccessLevel::Write,
+				"V" => return Some(Self::FileView { file_id: parts[1].to_string() }),
 				_ => return None,
 			};
 			return Some(Self::File { file_id: parts[1].to_string(), access });
@@ -751,19 +767,27 @@ impl TokenScope {
 		None
 	}
 
-	/// Get file ID if this is a file scope
+	/// Get file ID if this is a file scope (the folder for a file drop)
 	pub fn file_id(&self) -> Option<&str> {
 		match self {
-			Self::File { file_id, .. } => Some(file_id),
+			Self::File { file_id, .. } | Self::FileView { file_id } => Some(file_id),
+			Self::FileDrop { folder_id, .. } => Some(folder_id),
 			Self::ApkgPublish => None,
 		}
 	}
 
-	/// Get access level if this is a file scope
+	/// Get access level if this is a file scope that can read
 	pub fn file_access(&self) -> Option<AccessLevel> {
+		self.file_grant().map(|(_, access)| access)
+	}
+
+	/// The file this scope reads and the level it reads at — `None` for scopes that
+	/// grant no file reads at all (file drops, app publishing).
+	pub fn file_grant(&self) -> Option<(&str, AccessLevel)> {
 		match self {
-			Self::File { access, .. } => Some(*access),
-			Self::ApkgPublish => None,
+			Self::File { file_id, access } => Some((file_id, *access)),
+			Self::FileView { file_id } => Some((file_id, AccessLevel::Read)),
+			Self::FileDrop { .. } | Self::ApkgPublish => None,
 		}
 	}
 }
@@ -1011,6 +1035,33 @@ mod access_level_tests {
 			Some(TokenScope::File { file_id: "f1~abc".to_string(), access: AccessLevel::Read })
 		);
 	}
+
+	#[test]
+	fn view_and_drop_scopes_parse_to_their_own_variants() {
+		let view = TokenScope::parse("file:f1~abc:V");
+		assert_eq!(view, Some(TokenScope::FileView { file_id: "f1~abc".to_string() }));
+		assert_eq!(
+			view.as_ref().and_then(TokenScope::file_grant),
+			Some(("f1~abc", AccessLevel::Read))
+		);
+
+		let drop = TokenScope::parse("file:f1~dir:U:r1~link");
+		assert_eq!(
+			drop,
+			Some(TokenScope::FileDrop {
+				folder_id: "f1~dir".to_string(),
+				link: "r1~link".to_string()
+			})
+		);
+		// A drop reads nothing.
+		assert_eq!(drop.as_ref().and_then(TokenScope::file_grant), None);
+		assert_eq!(drop.as_ref().and_then(TokenScope::file_id), Some("f1~dir"));
+
+		// A drop scope without its link, or a link on any other level, does not parse.
+		assert_eq!(TokenScope::parse("file:f1~dir:U"), None);
+		assert_eq!(TokenScope::parse("file:f1~dir:U:"), None);
+		assert_eq!(TokenScope::parse("file:f1~dir:W:r1~link"), None);
+	}
 }
 
 /// What a storage-file compaction gave back.
@@ -1025,4 +1076,15 @@ pub struct CompactReport {
 	pub bytes_after: u64,
 }
 
+/// One table of a tenant's rows, as a database adapter dumps it for a tenant
+/// backup.
+///
+/// The rows are the adapter's own columns, keyed by column name, so a dump only
+/// restores into the same kind of adapter — at the same or a later schema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableDump {
+	pub table: Box<str>,
+	pub rows: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
 // vim: ts=4
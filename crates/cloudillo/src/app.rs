@@ -319,6 +319,8 @@ impl AppBuilder {
 		extensions.insert(proxy::new_proxy_cache());
 		extensions.insert(crate::auth::new_qr_login_store());
 		extensions.insert(cloudillo_file::new_container_cache());
+		extensions.insert(cloudillo_file::new_link_preview_cache());
+		extensions.insert(cloudillo_action::filter::new_content_filter_cache());
 		extensions.insert(cloudillo_core::dir_cache::new_dir_cache());
 		extensions.insert(cloudillo_site::cache::new_site_cache());
 
@@ -377,6 +379,27 @@ impl AppBuilder {
 			});
 		extensions.insert(search_object_fn);
 
+		// CRDT documents are read back as JSON by the search crate; file archives
+		// export them through the same reader.
+		let crdt_export_fn: cloudillo_core::CrdtExportFn = Box::new(|app, tn_id, doc_id| {
+			Box::pin(cloudillo_search::crdt::export_all(app, tn_id, doc_id))
+		});
+		extensions.insert(crdt_export_fn);
+
+		// RTDB triggers run on the action DSL, which neither cloudillo-rtdb (firing
+		// them) nor cloudillo-search (storing their manifests) may depend on.
+		let rtdb_trigger_fn: cloudillo_core::RtdbTriggerFn =
+			Box::new(cloudillo_action::dsl::trigger::dispatch);
+		extensions.insert(rtdb_trigger_fn);
+		let rtdb_trigger_check_fn: cloudillo_core::RtdbTriggerCheckFn =
+			Box::new(|block| cloudillo_action::dsl::trigger::parse_triggers(block).map(|_| ()));
+		extensions.insert(rtdb_trigger_check_fn);
+		let rtdb_trigger_schedule_fn: cloudillo_core::RtdbTriggerScheduleFn =
+			Box::new(|app, tn_id, content_type, block| {
+				Box::pin(cloudillo_action::dsl::trigger::schedule(app, tn_id, content_type, block))
+			});
+		extensions.insert(rtdb_trigger_schedule_fn);
+
 		// Which actions are indexed, and what text comes out of them, is declared
 		// per type in the DSL. This is how `cloudillo-search` reaches those
 		// declarations without depending on `cloudillo-action`.
@@ -507,6 +530,9 @@ impl AppBuilder {
 		// whose index predates this build's extraction rules.
 		cloudillo_search::schedule_recurring(&app).await?;
 
+		// Trending hashtags, recomputed every quarter hour.
+		cloudillo_action::schedule_recurring(&app).await?;
+
 		// Nightly FTS merge + WAL checkpoint + conditional VACUUM of meta.db.
 		cloudillo_core::maintenance::schedule(&app).await?;
 
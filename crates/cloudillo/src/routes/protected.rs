@@ -88,8 +88,11 @@ pub(super) fn init(app: App) -> Router<App> {
 				.merge(tables::pim::contacts())
 				.merge(tables::pim::calendars())
 				.merge(tables::misc::push_subscriptions())
+				.merge(tables::action::filters())
+				.merge(tables::action::followed_hashtags())
 				.merge(tables::search::reindex())
 				.merge(tables::site::config())
+				.merge(tables::file::storage())
 				.layer(middleware::from_fn(require_leader)),
 		)
 		// Auth only — handler self-enforces ownership
@@ -125,6 +128,10 @@ pub(super) fn init(app: App) -> Router<App> {
 		)
 		// Auth only — handler self-enforces ownership
 		.merge(tables::action::reader_state())
+		// Auth only — handler self-enforces the moderator role
+		.merge(tables::action::moderation())
+		// Auth only — handler self-enforces ownership
+		.merge(tables::action::collections())
 		.merge(tables::file::create().layer(middleware::from_fn_with_state(
 			app.clone(),
 			check_perm_create("file", "create"),
@@ -61,6 +61,7 @@ pub(super) fn init(app: App) -> Router<App> {
 		.merge(tables::misc::ref_idp_status())
 		.merge(tables::idp::public_discovery())
 		.merge(tables::action::list_public())
+		.merge(tables::action::hashtags_public())
 		.merge(
 			tables::action::read()
 				.layer(middleware::from_fn_with_state(app.clone(), check_perm_action("read"))),
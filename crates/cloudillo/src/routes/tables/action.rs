@@ -1,7 +1,8 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-//! `/api/actions/**`, `/api/inbox*`, `/api/outbox`, `/api/read-marker`.
+//! `/api/actions/**`, `/api/inbox*`, `/api/outbox`, `/api/read-marker`, `/api/feed/**`,
+//! `/api/moderation/**`, `/api/filters/**`, `/api/hashtags/**`, `/api/collections/**`.
 //!
 //! ## Method matrix
 //!
@@ -9,6 +10,8 @@
 //! |---|---|---|---|---|---|
 //! | `/api/actions`                        | `list_public()` ᴳ | `create()` ᶜ | | | |
 //! | `/api/actions/{action_id}`            | `read()` ᴬ | | | `write()` ᶜ | `write()` ᶜ |
+//! | `/api/actions/{action_id}/revisions`  | `read()` ᴬ | | | | |
+//! | `/api/actions/{action_id}/ics`        | `read()` ᴬ | | | | |
 //! | `/api/actions/{action_id}/publish`    | | `write()` ᶜ | | | |
 //! | `/api/actions/{action_id}/cancel`     | | `write()` ᶜ | | | |
 //! | `/api/actions/{action_id}/accept`     | | `write()` ᶜ | | | |
@@ -17,12 +20,26 @@
 //! | `/api/actions/{action_id}/subscribe`  | | | `reader_state()` ᴱ | | |
 //! | `/api/read-marker`                    | | | `reader_state()` ᴱ | | |
 //! | `/api/outbox`                         | `reader_state()` ᴱ | | | | |
+//! | `/api/feed/ranked`                    | `reader_state()` ᴱ | | | | |
 //! | `/api/inbox`                          | | `inbox()` ᶠ | | | |
 //! | `/api/inbox/sync`                     | | `inbox()` ᶠ | | | |
+//! | `/api/moderation/reports`             | `moderation()` ᴱ | | | | |
+//! | `/api/moderation/decisions`           | | `moderation()` ᴱ | | | |
+//! | `/api/moderation/log`                 | `moderation()` ᴱ | | | | |
+//! | `/api/filters`                        | `filters()` ᴸ | `filters()` ᴸ | | | |
+//! | `/api/filters/{filter_id}`            | | | | `filters()` ᴸ | `filters()` ᴸ |
+//! | `/api/hashtags/trending`              | `hashtags_public()` ᴳ | | | | |
+//! | `/api/hashtags/{tag}/actions`         | `hashtags_public()` ᴳ | | | | |
+//! | `/api/hashtags/followed`              | `followed_hashtags()` ᴸ | | | | |
+//! | `/api/hashtags/followed/{tag}`        | | | `followed_hashtags()` ᴸ | | `followed_hashtags()` ᴸ |
+//! | `/api/collections`                    | `collections()` ᴱ | `collections()` ᴱ | | | |
+//! | `/api/collections/{collection_id}`    | | | | `collections()` ᴱ | `collections()` ᴱ |
+//! | `/api/collections/{collection_id}/items` | `collections()` ᴱ | `collections()` ᴱ | | | |
+//! | `/api/collections/{collection_id}/items/{item_type}/{item_id}` | | | | `collections()` ᴱ | `collections()` ᴱ |
 //!
 //! ᴬ public surface (`optional_auth`) but ABAC-guarded, ᴳ public + rate-limited
 //! only, ᶠ public under the `"federation"` bucket + a raised body limit,
-//! ᶜ auth + ABAC, ᴱ auth only — handler self-enforces. The guard on each fn is
+//! ᶜ auth + ABAC, ᴸ `require_leader`, ᴱ auth only — handler self-enforces. The guard on each fn is
 //! in `routes/protected.rs` / `routes/public.rs`.
 //!
 //! `/api/actions/{action_id}` spans two guards: `GET` is a public ABAC read,
@@ -30,11 +47,11 @@
 
 use axum::{
 	Router,
-	routing::{delete, get, post, put},
+	routing::{delete, get, patch, post, put},
 };
 
 use crate::prelude::*;
-use cloudillo_action::handler;
+use cloudillo_action::{collections, feed, handler, hashtags, moderation};
 
 /// Action creation, gated by `check_perm_create("action", "create")` for
 /// quota/tier checking. Collection-level — `check_perm_create` takes no `Path`.
@@ -65,19 +82,82 @@ pub(crate) fn write() -> Router<App> {
 /// - thread subscription level: the reader's own cached row;
 /// - `/api/outbox`: federation history sync (peer-initiated pull). Auth is
 ///   enforced by the `Auth` extractor; non-related peers are rejected with an
-///   empty list inside the handler before any action query runs.
+///   empty list inside the handler before any action query runs;
+/// - `/api/feed/ranked`: the owner's home feed, ranked from the owner's own
+///   relationships, interactions and settings; anyone else is rejected.
 pub(crate) fn reader_state() -> Router<App> {
 	Router::new()
 		.route("/api/read-marker", put(handler::put_read_marker))
+		.route("/api/feed/ranked", get(feed::get_ranked_feed))
 		.route("/api/actions/{action_id}/subscribe", put(handler::put_action_subscribe))
 		.route("/api/outbox", get(handler::get_outbox))
 }
 
+/// Community moderation — authentication only, no ABAC guard. The handlers
+/// require a community tenant and a moderator or higher role.
+pub(crate) fn moderation() -> Router<App> {
+	Router::new()
+		.route("/api/moderation/reports", get(moderation::list_moderation_queue))
+		.route("/api/moderation/decisions", post(moderation::post_moderation_decision))
+		.route("/api/moderation/log", get(moderation::list_moderation_log))
+}
+
+/// The tenant's content filters — gated by `require_leader`, like every other
+/// tenant-owned resource: federated visitors and share-link tokens are rejected.
+pub(crate) fn filters() -> Router<App> {
+	Router::new()
+		.route(
+			"/api/filters",
+			get(handler::list_content_filters).post(handler::post_content_filter),
+		)
+		.route(
+			"/api/filters/{filter_id}",
+			patch(handler::patch_content_filter).delete(handler::delete_content_filter),
+		)
+}
+
+/// The hashtags the tenant follows — gated by `require_leader`, like the content
+/// filters.
+pub(crate) fn followed_hashtags() -> Router<App> {
+	Router::new()
+		.route("/api/hashtags/followed", get(hashtags::list_followed_hashtags))
+		.route(
+			"/api/hashtags/followed/{tag}",
+			put(hashtags::put_followed_hashtag).delete(hashtags::delete_followed_hashtag),
+		)
+}
+
+/// Saved-item collections — authentication only, no ABAC guard. Collections are
+/// private to the user who made them: the handlers scope every query to the
+/// caller and reject scoped tokens, and list only items the caller may still open.
+pub(crate) fn collections() -> Router<App> {
+	Router::new()
+		.route(
+			"/api/collections",
+			get(collections::list_collections).post(collections::post_collection),
+		)
+		.route(
+			"/api/collections/{collection_id}",
+			patch(collections::patch_collection).delete(collections::delete_collection),
+		)
+		.route(
+			"/api/collections/{collection_id}/items",
+			get(collections::list_saved_items).post(collections::post_saved_item),
+		)
+		.route(
+			"/api/collections/{collection_id}/items/{item_type}/{item_id}",
+			patch(collections::patch_saved_item).delete(collections::delete_saved_item),
+		)
+}
+
 /// Action reads, gated by `check_perm_action("read")` with a guest
 /// (OptionalAuth) context. Every route here must capture the action id as
 /// `{action_id}`.
 pub(crate) fn read() -> Router<App> {
-	Router::new().route("/api/actions/{action_id}", get(handler::get_action_by_id))
+	Router::new()
+		.route("/api/actions/{action_id}", get(handler::get_action_by_id))
+		.route("/api/actions/{action_id}/revisions", get(handler::list_action_revisions))
+		.route("/api/actions/{action_id}/ics", get(handler::get_action_ics))
 }
 
 /// Federation inbox — unauthenticated peers POST signed action tokens.
@@ -98,4 +178,13 @@ pub(crate) fn list_public() -> Router<App> {
 	Router::new().route("/api/actions", get(handler::list_actions))
 }
 
+/// Hashtag listings and trending topics. Public like `list_public()`: the tag
+/// listing is the action listing with the tag set, and trending counts only
+/// public actions.
+pub(crate) fn hashtags_public() -> Router<App> {
+	Router::new()
+		.route("/api/hashtags/trending", get(hashtags::list_trending_hashtags))
+		.route("/api/hashtags/{tag}/actions", get(hashtags::list_hashtag_actions))
+}
+
 // vim: ts=4
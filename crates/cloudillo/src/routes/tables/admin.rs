@@ -13,6 +13,7 @@
 
 use axum::{
 	Router,
+	extract::DefaultBodyLimit,
 	routing::{get, post},
 };
 
@@ -30,6 +31,12 @@ pub(crate) fn tenant() -> Router<App> {
 			post(admin::tenant::send_password_reset),
 		)
 		.route("/api/admin/tenants/{id_tag}/purge", post(admin::tenant::purge_tenant_handler))
+		.route("/api/admin/tenants/{id_tag}/backup", get(admin::backup::get_tenant_backup))
+		// A backup carries every blob of the tenant: no body limit.
+		.route(
+			"/api/admin/tenants/restore",
+			post(admin::backup::post_tenant_restore).layer(DefaultBodyLimit::disable()),
+		)
 		.route("/api/admin/email/test", post(admin::email::send_test_email))
 		.route("/api/admin/cert-status", get(admin::cert::get_cert_status))
 		.route("/api/admin/db-maintenance", post(admin::maintenance::post_db_maintenance))
@@ -9,6 +9,7 @@
 //! |---|---|---|---|---|
 //! | `/api/auth/login`                    | | `public_login()` ᴿ | | |
 //! | `/api/auth/login-token`              | `public_login()` ᴿ | | | |
+//! | `/api/auth/share-link`               | | `public_login()` ᴿ | | |
 //! | `/api/auth/login-init`               | | `recovery()` ᴾ | | |
 //! | `/api/auth/set-password`             | | `recovery()` ᴾ | | |
 //! | `/api/auth/forgot-password`          | | `recovery()` ᴾ | | |
@@ -102,6 +103,8 @@ pub(crate) fn public_login() -> Router<App> {
 	Router::new()
 		.route("/api/auth/login", post(handler::post_login))
 		.route("/api/auth/login-token", get(handler::get_login_token))
+		// Password-protected share links: a guessable secret, so the same bucket as login.
+		.route("/api/auth/share-link", post(handler::post_share_link))
 		.route("/api/auth/wa/login/challenge", get(webauthn::get_login_challenge))
 		.route("/api/auth/wa/login", post(webauthn::post_login))
 		.route("/api/auth/qr-login/init", post(qr_login::post_init))
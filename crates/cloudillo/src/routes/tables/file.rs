@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: Szilárd Hajba
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-//! `/api/files/**`, `/api/trash`, `/api/shares`, `/api/tags`, `/api/apps/**`.
+//! `/api/files/**`, `/api/trash`, `/api/storage`, `/api/shares`, `/api/tags`, `/api/apps/**`.
 //!
 //! ## Method matrix
 //!
@@ -9,13 +9,20 @@
 //! |---|---|---|---|---|---|
 //! | `/api/files`                          | `list_public()` ᴳ | `create()` ᶜ | | | |
 //! | `/api/files/{preset}/{file_name}`     | | `create()` ᶜ ᴮ | | | |
+//! | `/api/files/archive`                  | `list_public()` ᴳ | | | | |
+//! | `/api/files/link-preview`             | | `create()` ᶜ | | | |
 //! | `/api/files/{file_id}`                | `read()` ᴬ | | | `write()` ᶜ | `write()` ᶜ |
 //! | `/api/files/{file_id}/descriptor`     | `read()` ᴬ | | | | |
 //! | `/api/files/{file_id}/metadata`       | `read()` ᴬ | | | | |
 //! | `/api/files/variant/{variant_id}`     | `read()` ᴬ | | | | |
 //! | `/api/files/{file_id}/content/{*path}`| `list_public()` ᴳ | | | | |
+//! | `/api/files/{file_id}/stream/{*path}` | `read()` ᴬ | | | | |
 //! | `/api/files/{file_id}/duplicate`      | | `create()` ᶜ | | | |
+//! | `/api/files/{file_id}/edit`           | | `create()` ᶜ | | | |
 //! | `/api/files/{file_id}/restore`        | | `write()` ᶜ | | | |
+//! | `/api/files/{file_id}/versions`       | `read()` ᴬ | | | | |
+//! | `/api/files/{file_id}/versions/{version_id}` | | | | | `write()` ᶜ |
+//! | `/api/files/{file_id}/versions/{version_id}/restore` | | `write()` ᶜ | | | |
 //! | `/api/files/{file_id}/tag/{tag}`      | | | `write()` ᶜ | | `write()` ᶜ |
 //! | `/api/files/{file_id}/user`           | | | | `user_data()` ᴱ | |
 //! | `/api/files/{file_id}/refresh`        | | `user_data()` ᴱ | | | |
@@ -24,15 +31,16 @@
 //! | `/api/shares`                         | `shares()` ᴱ | | | | |
 //! | `/api/tags`                           | `tags()` ᴱ | | | | |
 //! | `/api/trash`                          | | | | | `trash()` ᶜ |
+//! | `/api/storage`                        | `storage()` ᴸ | | | | |
 //! | `/api/apps`                           | `list_public()` ᴳ | | | | |
 //! | `/api/apps/install`                   | | `app_management()` ᶜ | | | |
 //! | `/api/apps/installed`                 | `app_management()` ᶜ | | | | |
 //! | `/api/apps/@{publisher}/{name}`       | | | | | `app_management()` ᶜ |
 //!
 //! ᴬ public surface (`optional_auth`) but ABAC-guarded, ᴳ public + rate-limited
-//! only, ᶜ auth + ABAC, ᴱ auth only — handler self-enforces. ᴮ carries its own
-//! body-limit layer. The guard on each fn is in `routes/protected.rs` /
-//! `routes/public.rs`.
+//! only, ᶜ auth + ABAC, ᴱ auth only — handler self-enforces, ᴸ auth +
+//! `require_leader`. ᴮ carries its own body-limit layer. The guard on each fn
+//! is in `routes/protected.rs` / `routes/public.rs`.
 //!
 //! Note `/api/files/{file_id}` spans two guards: `GET` is a public ABAC read,
 //! `PATCH`/`DELETE` are protected ABAC writes. They cannot be chained.
@@ -43,7 +51,9 @@ use axum::{
 	routing::{delete, get, patch, post, put},
 };
 
-use crate::file::{apkg, handler, management, share, tag};
+use crate::file::{
+	apkg, archive, edit, handler, link_preview, management, quota, share, stream, tag, version,
+};
 use crate::prelude::*;
 
 /// File and app-package creation, gated by `check_perm_create("file", "create")`.
@@ -60,6 +70,8 @@ pub(crate) fn create() -> Router<App> {
 			post(handler::post_file_blob).layer(DefaultBodyLimit::disable()),
 		)
 		.route("/api/files/{file_id}/duplicate", post(management::duplicate_file))
+		.route("/api/files/{file_id}/edit", post(edit::edit_image))
+		.route("/api/files/link-preview", post(link_preview::post_link_preview))
 }
 
 /// File mutation, gated by `check_perm_file("write")`.
@@ -74,6 +86,8 @@ pub(crate) fn write() -> Router<App> {
 			patch(management::patch_file).delete(management::delete_file),
 		)
 		.route("/api/files/{file_id}/restore", post(management::restore_file))
+		.route("/api/files/{file_id}/versions/{version_id}/restore", post(version::restore_version))
+		.route("/api/files/{file_id}/versions/{version_id}", delete(version::delete_version))
 		.route(
 			"/api/files/{file_id}/tag/{tag}",
 			put(tag::put_file_tag).delete(tag::delete_file_tag),
@@ -86,6 +100,12 @@ pub(crate) fn trash() -> Router<App> {
 	Router::new().route("/api/trash", delete(management::empty_trash))
 }
 
+/// Tenant storage usage against its quota, behind `require_leader` — it is the
+/// tenant's figure, not the caller's.
+pub(crate) fn storage() -> Router<App> {
+	Router::new().route("/api/storage", get(quota::get_storage_usage))
+}
+
 /// App install / uninstall, gated by `check_perm_create("app", "create")`
 /// (a leader-level check).
 pub(crate) fn app_management() -> Router<App> {
@@ -136,14 +156,18 @@ pub(crate) fn read() -> Router<App> {
 		.route("/api/files/variant/{variant_id}", get(handler::get_file_variant))
 		.route("/api/files/{file_id}/descriptor", get(handler::get_file_descriptor))
 		.route("/api/files/{file_id}/metadata", get(handler::get_file_metadata))
+		.route("/api/files/{file_id}/versions", get(version::list_versions))
+		.route("/api/files/{file_id}/stream/{*path}", get(stream::get_stream_content))
 		.route("/api/files/{file_id}", get(handler::get_file_variant_file_id))
 }
 
-/// Unauthenticated listing / app discovery / container content. Visibility is
-/// checked inside the handlers; mounted under the `"general"` rate-limit bucket.
+/// Unauthenticated listing / app discovery / container content / archive
+/// downloads. Visibility is checked inside the handlers; mounted under the
+/// `"general"` rate-limit bucket.
 pub(crate) fn list_public() -> Router<App> {
 	Router::new()
 		.route("/api/files", get(handler::get_file_list))
+		.route("/api/files/archive", get(archive::get_archive))
 		.route("/api/apps", get(apkg::list_apps))
 		.route("/api/files/{file_id}/content/{*path}", get(apkg::get_container_content))
 }
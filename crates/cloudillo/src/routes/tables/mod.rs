@@ -82,9 +82,14 @@ fn all_api_tables() -> axum::Router<crate::prelude::App> {
 		.merge(action::create())
 		.merge(action::write())
 		.merge(action::reader_state())
+		.merge(action::moderation())
+		.merge(action::filters())
+		.merge(action::followed_hashtags())
+		.merge(action::collections())
 		.merge(action::read())
 		.merge(action::inbox())
 		.merge(action::list_public())
+		.merge(action::hashtags_public())
 		.merge(admin::tenant())
 		.merge(auth::session())
 		.merge(auth::owner_credentials())
@@ -97,6 +102,7 @@ fn all_api_tables() -> axum::Router<crate::prelude::App> {
 		.merge(file::create())
 		.merge(file::write())
 		.merge(file::trash())
+		.merge(file::storage())
 		.merge(file::app_management())
 		.merge(file::user_data())
 		.merge(file::shares())
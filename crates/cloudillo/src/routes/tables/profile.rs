@@ -14,6 +14,7 @@
 //! | `/api/me`                              | `public_discovery()` ᴳ | | | `own()` ᴱ |
 //! | `/api/me/full`                         | `recovery_public()` ᴳ | | | |
 //! | `/api/me/app-domain`                   | `public_discovery()` ᴳ | | | |
+//! | `/api/me/emoji`                        | `public_discovery()` ᴳ | | | |
 //! | `/api/me/image`                        | | | `own()` ᴱ ᴮ | |
 //! | `/api/me/cover`                        | | | `own()` ᴱ ᴮ | |
 //! | `/api/profiles`                        | `own()` ᴱ | | | |
@@ -129,6 +130,7 @@ pub(crate) fn public_discovery() -> Router<App> {
 	Router::new()
 		.route("/api/me", get(handler::get_tenant_profile_base))
 		.route("/api/me/app-domain", get(handler::get_tenant_app_domain))
+		.route("/api/me/emoji", get(handler::get_tenant_emoji))
 }
 
 /// Full tenant profile for the recovery/reset page. Mounted under the
@@ -114,6 +114,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 		idle_timeout_secs: 3600, // evict after 1 hour of inactivity
 		broadcast_capacity: 128, // channel buffer for change broadcasts
 		auto_evict: true,
+		change_log_retention: 10_000, // writes an offline client can catch up on
 	};
 	let rtdb_adapter = Arc::new(
 		RtdbAdapterRedb::new(config.db_dir.join("rtdb"), true, rtdb_config)